@@ -0,0 +1,69 @@
+#!/usr/bin/env node
+/**
+ * 编辑器集成桥示例客户端：读取 FlowHub 写入的 local-api.json，完成握手后发送一段选中代码，
+ * 并长轮询一次 FlowHub 推送的补丁。编辑器插件可按同样流程实现。
+ *
+ * 用法：node scripts/editor-bridge-client.mjs <local-api.json> <文件路径> [起始行] [结束行]
+ */
+
+import { readFile } from 'fs/promises';
+import path from 'path';
+
+const PROTOCOL_VERSION = 1;
+
+async function call(info, action, body) {
+  const response = await fetch(`http://127.0.0.1:${info.port}/v1/editor/${action}`, {
+    method: 'POST',
+    headers: {
+      Authorization: `Bearer ${info.token}`,
+      'Content-Type': 'application/json',
+    },
+    body: JSON.stringify(body ?? {}),
+  });
+  const payload = await response.json();
+  if (!response.ok) {
+    throw new Error(`${action} failed (${response.status}): ${payload.error}`);
+  }
+  return payload;
+}
+
+async function main() {
+  const [infoPath, filePath, startArg, endArg] = process.argv.slice(2);
+  if (!infoPath || !filePath) {
+    console.error('用法: node scripts/editor-bridge-client.mjs <local-api.json> <文件路径> [起始行] [结束行]');
+    process.exit(1);
+  }
+  const info = JSON.parse(await readFile(infoPath, 'utf8'));
+
+  const hello = await call(info, 'hello', { client: 'example', protocol: PROTOCOL_VERSION });
+  if (hello.protocol !== PROTOCOL_VERSION) {
+    throw new Error(`Unsupported protocol version: ${hello.protocol}`);
+  }
+  console.log(`已连接 FlowHub ${hello.version}，支持: ${hello.capabilities.join(', ')}`);
+
+  const absolutePath = path.resolve(filePath);
+  const lines = (await readFile(absolutePath, 'utf8')).split('\n');
+  const startLine = Number(startArg || 1);
+  const endLine = Number(endArg || Math.min(lines.length, startLine + 20));
+  const sent = await call(info, 'send-selection', {
+    filePath: absolutePath,
+    selection: lines.slice(startLine - 1, endLine).join('\n'),
+    languageId: path.extname(absolutePath).slice(1),
+    startLine,
+    endLine,
+    instruction: '解释这段代码',
+  });
+  console.log(`已发送给 Agent ${sent.agentId}`);
+
+  const { messages } = await call(info, 'poll', { after: 0, waitSecs: 10 });
+  for (const message of messages) {
+    if (message.type === 'applyPatch') {
+      console.log(`收到 ${message.filePath} 的补丁:\n${message.patch}`);
+    }
+  }
+}
+
+main().catch((error) => {
+  console.error(String(error));
+  process.exit(1);
+});
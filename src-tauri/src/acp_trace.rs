@@ -0,0 +1,74 @@
+//! ACP 报文追踪（默认关闭）：开启后在内存中保留最近收发的 ACP 报文，供问题报告打包使用，不落盘。
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+const MAX_TRACE_ENTRIES: usize = 500;
+const MAX_TRACE_MESSAGE_CHARS: usize = 4000;
+
+static TRACE_ENABLED: AtomicBool = AtomicBool::new(false);
+static TRACE: Lazy<Mutex<VecDeque<AcpTraceEntry>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TraceDirection {
+    Send,
+    Receive,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AcpTraceEntry {
+    pub at: String,
+    pub endpoint: String,
+    pub direction: TraceDirection,
+    pub message: String,
+}
+
+fn lock_trace() -> std::sync::MutexGuard<'static, VecDeque<AcpTraceEntry>> {
+    TRACE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// 设置变更时调用；关闭时清空已记录的报文
+pub(crate) fn set_acp_trace_enabled(enabled: bool) {
+    TRACE_ENABLED.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        lock_trace().clear();
+    }
+}
+
+pub(crate) fn record_acp_message(endpoint: &str, direction: TraceDirection, message: &str) {
+    if !TRACE_ENABLED.load(Ordering::Relaxed) || message.trim().is_empty() {
+        return;
+    }
+    let message = if message.chars().count() > MAX_TRACE_MESSAGE_CHARS {
+        format!(
+            "{}…",
+            message
+                .chars()
+                .take(MAX_TRACE_MESSAGE_CHARS)
+                .collect::<String>()
+        )
+    } else {
+        message.to_string()
+    };
+    let mut trace = lock_trace();
+    if trace.len() >= MAX_TRACE_ENTRIES {
+        trace.pop_front();
+    }
+    trace.push_back(AcpTraceEntry {
+        at: chrono::Utc::now().to_rfc3339(),
+        endpoint: endpoint.to_string(),
+        direction,
+        message,
+    });
+}
+
+pub(crate) fn acp_trace_snapshot() -> Vec<AcpTraceEntry> {
+    lock_trace().iter().cloned().collect()
+}
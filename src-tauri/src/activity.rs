@@ -0,0 +1,218 @@
+//! 工作区活跃度热力图：按「星期 × 小时」（本地时间）汇总提问与工具调用次数，
+//! 数据来自本地会话存储与 iFlow 历史记录，前端直接渲染即可。
+use std::collections::HashSet;
+
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Timelike, Utc};
+use serde::Serialize;
+use tauri::State;
+
+use crate::history::load_iflow_history_activity;
+use crate::state::AppState;
+use crate::storage::{read_snapshot_from_path, storage_path};
+
+const DEFAULT_RANGE_DAYS: u32 = 365;
+const MAX_RANGE_DAYS: u32 = 3 * 365;
+const DAYS_PER_WEEK: usize = 7;
+const HOURS_PER_DAY: usize = 24;
+
+#[derive(Debug, Default, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityCell {
+    pub prompts: u32,
+    pub tool_calls: u32,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityHeatmap {
+    pub from: String,
+    pub to: String,
+    /// `cells[星期][小时]`，星期从周一（0）到周日（6）
+    pub cells: Vec<Vec<ActivityCell>>,
+    pub total_prompts: u32,
+    pub total_tool_calls: u32,
+    /// 单格最大的「提问 + 工具调用」数，供前端计算色阶
+    pub max_cell: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ActivityEvent {
+    at: DateTime<Utc>,
+    prompts: u32,
+    tool_calls: u32,
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .ok()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+/// 把 [from, to] 内的事件按 `tz` 的星期与小时分桶
+fn build_heatmap<Tz: TimeZone>(
+    events: &[ActivityEvent],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    tz: &Tz,
+) -> ActivityHeatmap {
+    let mut cells = vec![vec![ActivityCell::default(); HOURS_PER_DAY]; DAYS_PER_WEEK];
+    let mut total_prompts = 0_u32;
+    let mut total_tool_calls = 0_u32;
+    for event in events
+        .iter()
+        .filter(|event| event.at >= from && event.at <= to)
+    {
+        let local = event.at.with_timezone(tz);
+        let cell =
+            &mut cells[local.weekday().num_days_from_monday() as usize][local.hour() as usize];
+        cell.prompts = cell.prompts.saturating_add(event.prompts);
+        cell.tool_calls = cell.tool_calls.saturating_add(event.tool_calls);
+        total_prompts = total_prompts.saturating_add(event.prompts);
+        total_tool_calls = total_tool_calls.saturating_add(event.tool_calls);
+    }
+    let max_cell = cells
+        .iter()
+        .flatten()
+        .map(|cell| cell.prompts.saturating_add(cell.tool_calls))
+        .max()
+        .unwrap_or(0);
+
+    ActivityHeatmap {
+        from: from.to_rfc3339(),
+        to: to.to_rfc3339(),
+        cells,
+        total_prompts,
+        total_tool_calls,
+        max_cell,
+    }
+}
+
+/// 工作区活跃度热力图。本地会话只记录提问，工具调用来自 iFlow 历史；
+/// 已绑定 iFlow 历史的本地会话以历史为准，避免重复计数。
+#[tauri::command]
+pub async fn get_activity_heatmap(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: String,
+    agent_ids: Vec<String>,
+    range_days: Option<u32>,
+) -> Result<ActivityHeatmap, String> {
+    let workspace_path = workspace_path.trim().to_string();
+    if workspace_path.is_empty() {
+        return Err("Workspace path cannot be empty".to_string());
+    }
+    let range_days = range_days
+        .unwrap_or(DEFAULT_RANGE_DAYS)
+        .clamp(1, MAX_RANGE_DAYS);
+    let to = Utc::now();
+    let from = to - Duration::days(i64::from(range_days));
+
+    let history = load_iflow_history_activity(&workspace_path).await?;
+    let history_sessions: HashSet<&str> = history
+        .iter()
+        .map(|item| item.session_id.as_str())
+        .collect();
+    let mut events: Vec<ActivityEvent> = history
+        .iter()
+        .filter_map(|item| {
+            Some(ActivityEvent {
+                at: parse_timestamp(&item.timestamp)?,
+                prompts: item.prompts,
+                tool_calls: item.tool_calls,
+            })
+        })
+        .collect();
+
+    let snapshot = {
+        let _guard = state.storage_lock.lock().await;
+        read_snapshot_from_path(&storage_path(&app_handle)?).await?
+    };
+    for agent_id in &agent_ids {
+        let Some(sessions) = snapshot.sessions_by_agent.get(agent_id) else {
+            continue;
+        };
+        for session in sessions {
+            let in_history = session
+                .acp_session_id
+                .as_deref()
+                .map(|id| history_sessions.contains(id))
+                .unwrap_or(false);
+            if in_history {
+                continue;
+            }
+            let messages = snapshot
+                .messages_by_session
+                .get(&session.id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            events.extend(
+                messages
+                    .iter()
+                    .filter(|message| message.role == "user")
+                    .filter_map(|message| parse_timestamp(&message.timestamp))
+                    .map(|at| ActivityEvent {
+                        at,
+                        prompts: 1,
+                        tool_calls: 0,
+                    }),
+            );
+        }
+    }
+
+    Ok(build_heatmap(&events, from, to, &Local))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        parse_timestamp(value).expect("timestamp")
+    }
+
+    #[test]
+    fn events_are_bucketed_by_local_weekday_and_hour() {
+        let event = |value: &str, prompts: u32, tool_calls: u32| ActivityEvent {
+            at: at(value),
+            prompts,
+            tool_calls,
+        };
+        let events = vec![
+            // 2026-03-01 是周日，UTC+8 下为周一 07 点
+            event("2026-03-01T23:30:00Z", 1, 0),
+            event("2026-03-01T23:45:00Z", 0, 3),
+            event("2026-03-02T06:00:00Z", 1, 1),
+            // 超出范围
+            event("2026-01-01T00:00:00Z", 5, 5),
+        ];
+        let tz = FixedOffset::east_opt(8 * 3600).unwrap();
+        let heatmap = build_heatmap(
+            &events,
+            at("2026-02-01T00:00:00Z"),
+            at("2026-03-03T00:00:00Z"),
+            &tz,
+        );
+
+        assert_eq!(heatmap.cells.len(), 7);
+        assert!(heatmap.cells.iter().all(|row| row.len() == 24));
+        assert_eq!(
+            heatmap.cells[0][7],
+            ActivityCell {
+                prompts: 1,
+                tool_calls: 3
+            }
+        );
+        assert_eq!(
+            heatmap.cells[0][14],
+            ActivityCell {
+                prompts: 1,
+                tool_calls: 1
+            }
+        );
+        assert_eq!(heatmap.cells[6][23], ActivityCell::default());
+        assert_eq!(heatmap.total_prompts, 2);
+        assert_eq!(heatmap.total_tool_calls, 4);
+        assert_eq!(heatmap.max_cell, 4);
+    }
+}
@@ -0,0 +1,403 @@
+//! ACP Agent 适配器：各 CLI 的差异（启动参数、传输方式、initialize / session 参数、报文转换）
+//! 收敛到 `AcpAgentAdapter`，监听任务与启动流程共用。接入新的 ACP CLI 只需实现该 trait
+//! 并在 `adapter_for_type` 中登记。
+use std::process::Stdio;
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use tauri::Manager;
+use tokio::net::TcpListener;
+use tokio::process::{Child, ChildStderr, Command};
+use tokio::time::Duration;
+
+use super::claude_adapter::ClaudeAdapter;
+use super::gemini_adapter::GeminiAdapter;
+use super::iflow_adapter::{find_available_port, message_listener_task, IflowAdapter};
+use super::registry::{record_agent, RegisteredAgent};
+use super::remote::RemoteIflowAdapter;
+use super::session_params::{
+    build_initialize_params, build_prompt_params, build_session_load_params,
+    build_session_new_params, build_session_new_params_with_id,
+};
+use super::spawn_env::resolve_agent_env;
+use super::stdio_bridge::{log_stderr_lines, run_stdio_bridge};
+use crate::app_lock::EmitUnlessLocked;
+use crate::database::workspace_mcp_servers;
+use crate::generation::GenerationOptions;
+use crate::models::{
+    AgentInfo, AgentStatus, ConnectResponse, ListenerCommand, PermissionMode, PromptResource,
+};
+use crate::runtime_env::{resolve_executable_path, runtime_path_env};
+use crate::scratch::{ensure_scratch_dir, SCRATCH_DIR_ENV};
+use crate::state::{AgentInstance, AppState};
+use crate::wsl_path::host_spawn_dir;
+
+/// Agent 与 FlowHub 之间的传输方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AcpTransport {
+    /// Agent 自己在 `spawn_args` 指定的端口上提供 WebSocket（iFlow）
+    WebSocket,
+    /// stdio 上的换行分隔 JSON-RPC，由 FlowHub 桥接为本机 WebSocket
+    Stdio,
+}
+
+pub(crate) trait AcpAgentAdapter: Send + Sync {
+    /// 写入 `AgentInfo.agent_type`
+    fn agent_type(&self) -> &'static str;
+    /// 界面显示名
+    fn display_name(&self) -> &'static str;
+    fn transport(&self) -> AcpTransport;
+    /// 启动参数；`port` 仅在 WebSocket 传输时有值
+    fn spawn_args(&self, port: Option<u16>, model: Option<&str>) -> Vec<String>;
+
+    fn spawn_envs(&self, _model: Option<&str>) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// 连接时先 `session/load` 该工作区上次使用的会话（Agent 需能跨进程载入会话）
+    fn resumes_last_session(&self) -> bool {
+        false
+    }
+
+    /// 进程启动后到监听任务首次连接前的等待时间
+    fn startup_delay(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// 处理进程 stderr，默认只记录日志
+    fn watch_stderr(&self, _app_handle: &tauri::AppHandle, agent_id: &str, stderr: ChildStderr) {
+        log_stderr_lines(format!("{}:{}", self.agent_type(), agent_id), stderr);
+    }
+
+    fn initialize_params(&self) -> Value {
+        build_initialize_params(true)
+    }
+
+    /// `session_id` 为空时由 Agent 分配会话 ID
+    fn session_new_params(
+        &self,
+        workspace_path: &str,
+        session_id: Option<&str>,
+        mcp_servers: &[Value],
+        permission_mode: PermissionMode,
+    ) -> Value {
+        match session_id {
+            Some(session_id) => build_session_new_params_with_id(
+                workspace_path,
+                session_id,
+                mcp_servers,
+                permission_mode,
+            ),
+            None => build_session_new_params(workspace_path, mcp_servers, permission_mode),
+        }
+    }
+
+    fn session_load_params(
+        &self,
+        workspace_path: &str,
+        session_id: &str,
+        mcp_servers: &[Value],
+        permission_mode: PermissionMode,
+    ) -> Value {
+        build_session_load_params(workspace_path, session_id, mcp_servers, permission_mode)
+    }
+
+    fn prompt_params(
+        &self,
+        session_id: &str,
+        prompt: &str,
+        resources: &[PromptResource],
+        generation: Option<&GenerationOptions>,
+    ) -> Value {
+        build_prompt_params(session_id, prompt, resources, generation)
+    }
+
+    /// 收到的报文在路由前的转换钩子，可把私有字段映射为标准 ACP 结构
+    fn translate_incoming(&self, message: Value) -> Value {
+        message
+    }
+}
+
+/// 按 `agent_type` 取适配器；未知类型按 iFlow 处理
+pub(crate) fn adapter_for_type(agent_type: &str) -> Arc<dyn AcpAgentAdapter> {
+    match agent_type {
+        super::claude_adapter::CLAUDE_AGENT_TYPE => Arc::new(ClaudeAdapter),
+        super::gemini_adapter::GEMINI_AGENT_TYPE => Arc::new(GeminiAdapter),
+        super::remote::REMOTE_AGENT_TYPE => Arc::new(RemoteIflowAdapter),
+        _ => Arc::new(IflowAdapter),
+    }
+}
+
+/// 去掉首尾空白后为空的模型名视为未指定
+pub(super) fn normalized_model(model: Option<String>) -> Option<String> {
+    model
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// 启动 Agent 进程、建立连接并注册，随后由监听任务完成 initialize / session 生命周期
+pub(crate) async fn connect_acp_agent(
+    app_handle: tauri::AppHandle,
+    state: &AppState,
+    adapter: Arc<dyn AcpAgentAdapter>,
+    agent_id: String,
+    executable: String,
+    workspace_path: String,
+    model: Option<String>,
+    permission_mode: PermissionMode,
+) -> Result<ConnectResponse, String> {
+    let name = adapter.display_name();
+    let adapter_type = adapter.agent_type().to_string();
+    println!("Connecting to {}...", name);
+    println!("Agent ID: {}", agent_id);
+    println!("Workspace: {}", workspace_path);
+    let model = normalized_model(model);
+    if let Some(model_name) = model.as_ref() {
+        println!("Model override: {}", model_name);
+    }
+
+    // WebSocket 型把端口交给 Agent；stdio 型先占住桥接端口，避免与其他 Agent 抢同一端口
+    let (port, bridge_listener) = match adapter.transport() {
+        AcpTransport::WebSocket => (find_available_port().await?, None),
+        AcpTransport::Stdio => {
+            let listener = TcpListener::bind("127.0.0.1:0")
+                .await
+                .map_err(|e| format!("Failed to bind: {}", e))?;
+            let port = listener
+                .local_addr()
+                .map_err(|e| format!("Failed to get local address: {}", e))?
+                .port();
+            (port, Some(listener))
+        }
+    };
+    println!("Using port: {}", port);
+
+    let resolved_path = resolve_executable_path(&executable)?;
+    let runtime_path = runtime_path_env()?;
+    let agent_env = resolve_agent_env(&app_handle, &workspace_path).await?;
+    let scratch_dir = ensure_scratch_dir(&app_handle, &agent_id).await?;
+    println!("Resolved {} executable: {}", name, resolved_path.display());
+
+    let spawn_port = bridge_listener.is_none().then_some(port);
+    let mut cmd = Command::new(&resolved_path);
+    cmd.current_dir(host_spawn_dir(&workspace_path))
+        .args(adapter.spawn_args(spawn_port, model.as_deref()))
+        .envs(agent_env)
+        .envs(adapter.spawn_envs(model.as_deref()))
+        .env(SCRATCH_DIR_ENV, &scratch_dir)
+        .env("PATH", runtime_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if bridge_listener.is_some() {
+        cmd.stdin(Stdio::piped());
+    }
+
+    println!("Spawning {} process...", name);
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", name, e))?;
+    println!("{} process started, PID: {:?}", name, child.id());
+    if let Some(stderr) = child.stderr.take() {
+        adapter.watch_stderr(&app_handle, &agent_id, stderr);
+    }
+    if let Some(listener) = bridge_listener {
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            let _ = child.kill().await;
+            return Err(format!("Failed to capture {} stdio", name));
+        };
+        let label = format!("{}:{}", adapter.agent_type(), agent_id);
+        tokio::spawn(run_stdio_bridge(label, listener, stdin, stdout));
+    }
+
+    let startup_delay = adapter.startup_delay();
+    if !startup_delay.is_zero() {
+        println!("Waiting for {} to initialize...", name);
+        tokio::time::sleep(startup_delay).await;
+    }
+
+    let mcp_servers = workspace_mcp_servers(&app_handle, &workspace_path).await;
+    Ok(register_agent_listener(
+        app_handle,
+        state,
+        adapter,
+        AgentLaunch {
+            agent_id,
+            name: name.to_string(),
+            agent_type: adapter_type,
+            process: Some(child),
+            adopted_pid: None,
+            port,
+            ws_url: format!("ws://127.0.0.1:{}/acp", port),
+            executable,
+            workspace_path,
+            model,
+            permission_mode,
+            mcp_servers,
+        },
+    )
+    .await)
+}
+
+/// 更新 Agent 状态，状态变化时发出 `agent-status-changed`
+pub(crate) async fn set_agent_status(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    status: AgentStatus,
+) {
+    let state = app_handle.state::<AppState>();
+    let Some(previous) = state.agent_manager.set_status(agent_id, status).await else {
+        return;
+    };
+    emit_agent_status(app_handle, agent_id, status, Some(previous));
+}
+
+/// Agent 已从管理器移除时直接发出状态事件
+pub(crate) fn emit_agent_status(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    status: AgentStatus,
+    previous: Option<AgentStatus>,
+) {
+    let _ = app_handle.emit_unless_locked(
+        "agent-status-changed",
+        json!({
+            "agentId": agent_id,
+            "status": status,
+            "previous": previous,
+        }),
+    );
+}
+
+/// 已启动或可直接连接的 Agent
+pub(crate) struct AgentLaunch {
+    pub agent_id: String,
+    pub name: String,
+    pub agent_type: String,
+    /// 使用已有端口连接时没有本地进程
+    pub process: Option<Child>,
+    /// 应用重启后接管的进程，不再持有其句柄
+    pub adopted_pid: Option<u32>,
+    pub port: u16,
+    /// 监听任务连接的 ACP endpoint（ws:// 或 wss://）
+    pub ws_url: String,
+    pub executable: String,
+    pub workspace_path: String,
+    pub model: Option<String>,
+    pub permission_mode: PermissionMode,
+    pub mcp_servers: Vec<Value>,
+}
+
+/// 注册 Agent 实例并启动后台监听任务
+pub(crate) async fn register_agent_listener(
+    app_handle: tauri::AppHandle,
+    state: &AppState,
+    adapter: Arc<dyn AcpAgentAdapter>,
+    launch: AgentLaunch,
+) -> ConnectResponse {
+    let AgentLaunch {
+        agent_id,
+        name,
+        agent_type,
+        process,
+        adopted_pid,
+        port,
+        ws_url,
+        executable,
+        workspace_path,
+        model,
+        permission_mode,
+        mcp_servers,
+    } = launch;
+
+    // stdio 桥接随应用退出失效，只登记 WebSocket 型 Agent
+    if adapter.transport() == AcpTransport::WebSocket {
+        record_agent(
+            &app_handle,
+            RegisteredAgent {
+                agent_id: agent_id.clone(),
+                name: name.clone(),
+                agent_type: agent_type.clone(),
+                pid: process.as_ref().and_then(Child::id).or(adopted_pid),
+                port,
+                ws_url: ws_url.clone(),
+                executable: executable.clone(),
+                workspace_path: workspace_path.clone(),
+                model: model.clone(),
+                permission_mode,
+                registered_at: chrono::Utc::now().to_rfc3339(),
+            },
+        )
+        .await;
+    }
+
+    // 创建消息发送通道
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<ListenerCommand>();
+
+    let instance = AgentInstance {
+        info: AgentInfo {
+            id: agent_id.clone(),
+            name,
+            agent_type,
+            status: AgentStatus::Connecting,
+            workspace_path: workspace_path.clone(),
+            port: Some(port),
+        },
+        process,
+        adopted_pid,
+        port,
+        iflow_path: executable,
+        model,
+        permission_mode,
+        message_sender: Some(tx),
+        acp_session_id: None,
+    };
+    state.agent_manager.upsert(agent_id.clone(), instance).await;
+    emit_agent_status(&app_handle, &agent_id, AgentStatus::Connecting, None);
+    let (agent_count, agent_ids) = state.agent_manager.stats().await;
+    println!("[connect] Agent saved, total agents: {}", agent_count);
+    println!("[connect] Agent IDs: {:?}", agent_ids);
+
+    // 启动后台消息监听任务
+    let listener_agent_id = agent_id.clone();
+    tokio::spawn(async move {
+        message_listener_task(
+            app_handle,
+            listener_agent_id,
+            adapter,
+            ws_url,
+            workspace_path,
+            mcp_servers,
+            permission_mode,
+            rx,
+        )
+        .await;
+    });
+
+    println!("Agent {} connected successfully", agent_id);
+
+    ConnectResponse {
+        success: true,
+        port,
+        error: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adapters_are_resolved_by_agent_type() {
+        assert_eq!(adapter_for_type("claude").agent_type(), "claude");
+        assert_eq!(adapter_for_type("gemini").transport(), AcpTransport::Stdio);
+        let fallback = adapter_for_type("unknown");
+        assert_eq!(fallback.agent_type(), "iflow");
+        assert_eq!(fallback.transport(), AcpTransport::WebSocket);
+        assert_eq!(
+            fallback.spawn_args(Some(8090), Some("glm-4.7")),
+            vec!["--experimental-acp", "--port", "8090", "--model", "glm-4.7"]
+        );
+        assert_eq!(normalized_model(Some("  ".to_string())), None);
+    }
+}
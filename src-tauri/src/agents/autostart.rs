@@ -0,0 +1,267 @@
+//! 固定工作区的 Agent 自动启动：设置中登记的工作区在应用启动时由后端依次连接其默认 Agent，
+//! 每启动一个进程后间隔 `AUTO_START_STAGGER` 再启动下一个，避免同时拉起多个进程。
+//! 每一步通过 `agent-autostart` 事件通知前端；前端加载晚于启动时用 `get_auto_start_status` 补齐进度。
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, State};
+use tokio::time::Duration;
+
+use super::adapter::{adapter_for_type, connect_acp_agent};
+use super::claude_adapter::{CLAUDE_AGENT_TYPE, DEFAULT_CLAUDE_ACP_COMMAND};
+use super::gemini_adapter::{DEFAULT_GEMINI_COMMAND, GEMINI_AGENT_TYPE};
+use super::registry::adopt_registered_agent;
+use crate::app_lock::EmitUnlessLocked;
+use crate::capabilities::CommandCapability;
+use crate::history::normalize_workspace_path;
+use crate::models::PermissionMode;
+use crate::state::AppState;
+
+const AUTO_START_STAGGER: Duration = Duration::from_secs(3);
+const IFLOW_AGENT_TYPE: &str = "iflow";
+const DEFAULT_IFLOW_COMMAND: &str = "iflow";
+
+fn default_agent_type() -> String {
+    IFLOW_AGENT_TYPE.to_string()
+}
+
+/// 固定工作区的默认 Agent 及其连接参数
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoStartAgent {
+    /// 与前端保存的 Agent 对应
+    pub agent_id: String,
+    pub workspace_path: String,
+    /// iflow / claude / gemini
+    #[serde(default = "default_agent_type")]
+    pub agent_type: String,
+    /// 可执行文件；为空时使用该类型的默认命令
+    #[serde(default)]
+    pub executable: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub permission_mode: PermissionMode,
+}
+
+impl AutoStartAgent {
+    fn executable(&self) -> String {
+        match self
+            .executable
+            .as_deref()
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+        {
+            Some(path) => path.to_string(),
+            None => match self.agent_type.as_str() {
+                CLAUDE_AGENT_TYPE => DEFAULT_CLAUDE_ACP_COMMAND.to_string(),
+                GEMINI_AGENT_TYPE => DEFAULT_GEMINI_COMMAND.to_string(),
+                _ => DEFAULT_IFLOW_COMMAND.to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoStartPhase {
+    Pending,
+    Starting,
+    Started,
+    /// 已在运行（接管或先行连接）或未启用进程能力
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoStartProgress {
+    pub agent_id: String,
+    pub workspace_path: String,
+    pub phase: AutoStartPhase,
+    pub error: Option<String>,
+}
+
+/// 每个工作区只能固定一个默认 Agent，同一 Agent 也不能固定到多个工作区
+pub(crate) fn validate_auto_start_agents(agents: &[AutoStartAgent]) -> Result<(), String> {
+    let mut workspaces = HashSet::new();
+    let mut agent_ids = HashSet::new();
+    for agent in agents {
+        if agent.agent_id.trim().is_empty() {
+            return Err("Auto-start agent id cannot be empty".to_string());
+        }
+        if agent.workspace_path.trim().is_empty() {
+            return Err("Auto-start workspace path cannot be empty".to_string());
+        }
+        if ![IFLOW_AGENT_TYPE, CLAUDE_AGENT_TYPE, GEMINI_AGENT_TYPE]
+            .contains(&agent.agent_type.as_str())
+        {
+            return Err(format!(
+                "Unsupported auto-start agent type: {}",
+                agent.agent_type
+            ));
+        }
+        if !workspaces.insert(normalize_workspace_path(&agent.workspace_path)) {
+            return Err(format!(
+                "Workspace {} already has an auto-start agent",
+                agent.workspace_path
+            ));
+        }
+        if !agent_ids.insert(agent.agent_id.as_str()) {
+            return Err(format!(
+                "Agent {} is pinned to more than one workspace",
+                agent.agent_id
+            ));
+        }
+    }
+    Ok(())
+}
+
+async fn report(
+    app_handle: &tauri::AppHandle,
+    index: usize,
+    phase: AutoStartPhase,
+    error: Option<String>,
+) {
+    let state = app_handle.state::<AppState>();
+    let mut progress = state.auto_start_progress.write().await;
+    let Some(item) = progress.get_mut(index) else {
+        return;
+    };
+    item.phase = phase;
+    item.error = error;
+    let _ = app_handle.emit_unless_locked("agent-autostart", item.clone());
+}
+
+/// 启动一个固定的 Agent；已在运行或可接管上次的进程时不再启动新进程
+async fn start_agent(
+    app_handle: &tauri::AppHandle,
+    entry: &AutoStartAgent,
+) -> Result<AutoStartPhase, String> {
+    let state = app_handle.state::<AppState>();
+    let _startup = state.agent_startup_lock.lock().await;
+    if state.agent_manager.port_of(&entry.agent_id).await.is_some()
+        || adopt_registered_agent(app_handle, &state, &entry.agent_id).await
+    {
+        return Ok(AutoStartPhase::Skipped);
+    }
+
+    let response = connect_acp_agent(
+        app_handle.clone(),
+        &state,
+        adapter_for_type(&entry.agent_type),
+        entry.agent_id.clone(),
+        entry.executable(),
+        entry.workspace_path.clone(),
+        entry.model.clone(),
+        entry.permission_mode,
+    )
+    .await?;
+    if response.success {
+        Ok(AutoStartPhase::Started)
+    } else {
+        Err(response
+            .error
+            .unwrap_or_else(|| "Agent failed to start".to_string()))
+    }
+}
+
+/// 应用启动时在后台依次连接固定工作区的默认 Agent
+pub(crate) fn spawn_auto_start(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let state = app_handle.state::<AppState>();
+        let entries = state.settings.read().await.auto_start_agents.clone();
+        if entries.is_empty() {
+            return;
+        }
+        *state.auto_start_progress.write().await = entries
+            .iter()
+            .map(|entry| AutoStartProgress {
+                agent_id: entry.agent_id.clone(),
+                workspace_path: entry.workspace_path.clone(),
+                phase: AutoStartPhase::Pending,
+                error: None,
+            })
+            .collect();
+
+        let mut spawned = false;
+        for (index, entry) in entries.iter().enumerate() {
+            if !state
+                .capability_policy
+                .is_enabled(CommandCapability::Process)
+            {
+                report(
+                    &app_handle,
+                    index,
+                    AutoStartPhase::Skipped,
+                    Some("Process capability is disabled".to_string()),
+                )
+                .await;
+                continue;
+            }
+            if spawned {
+                tokio::time::sleep(AUTO_START_STAGGER).await;
+            }
+            report(&app_handle, index, AutoStartPhase::Starting, None).await;
+            println!(
+                "[autostart] Starting {} for {}",
+                entry.agent_id, entry.workspace_path
+            );
+            match start_agent(&app_handle, entry).await {
+                Ok(phase) => {
+                    spawned = phase == AutoStartPhase::Started;
+                    report(&app_handle, index, phase, None).await;
+                }
+                Err(e) => {
+                    println!("[autostart] Failed to start {}: {}", entry.agent_id, e);
+                    spawned = true;
+                    report(&app_handle, index, AutoStartPhase::Failed, Some(e)).await;
+                }
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_auto_start_status(
+    state: State<'_, AppState>,
+) -> Result<Vec<AutoStartProgress>, String> {
+    Ok(state.auto_start_progress.read().await.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pinned(agent_id: &str, workspace_path: &str) -> AutoStartAgent {
+        AutoStartAgent {
+            agent_id: agent_id.to_string(),
+            workspace_path: workspace_path.to_string(),
+            agent_type: default_agent_type(),
+            executable: None,
+            model: None,
+            permission_mode: PermissionMode::default(),
+        }
+    }
+
+    #[test]
+    fn each_workspace_pins_one_agent() {
+        let mut agents = vec![pinned("agent-a", "/ws/app"), pinned("agent-b", "/ws/api")];
+        assert!(validate_auto_start_agents(&agents).is_ok());
+        assert_eq!(agents[0].executable(), "iflow");
+
+        agents[1].agent_type = "claude".to_string();
+        agents[1].executable = Some("  ".to_string());
+        assert_eq!(agents[1].executable(), DEFAULT_CLAUDE_ACP_COMMAND);
+
+        agents.push(pinned("agent-c", "/ws/app/"));
+        assert!(validate_auto_start_agents(&agents).is_err());
+
+        agents[2] = pinned("agent-a", "/ws/docs");
+        assert!(validate_auto_start_agents(&agents).is_err());
+
+        agents[2] = pinned("agent-c", "/ws/docs");
+        agents[2].agent_type = "remote".to_string();
+        assert!(validate_auto_start_agents(&agents).is_err());
+    }
+}
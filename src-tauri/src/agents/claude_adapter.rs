@@ -0,0 +1,33 @@
+//! Claude Code 适配器：以 ACP 模式启动 `claude-code-acp`（stdio 上的换行分隔 JSON-RPC），
+//! 经 stdio 桥接复用 iFlow 的监听任务完成会话生命周期与消息路由。
+use super::adapter::{AcpAgentAdapter, AcpTransport};
+
+pub(crate) const CLAUDE_AGENT_TYPE: &str = "claude";
+pub(crate) const DEFAULT_CLAUDE_ACP_COMMAND: &str = "claude-code-acp";
+
+pub(crate) struct ClaudeAdapter;
+
+impl AcpAgentAdapter for ClaudeAdapter {
+    fn agent_type(&self) -> &'static str {
+        CLAUDE_AGENT_TYPE
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Claude Code"
+    }
+
+    fn transport(&self) -> AcpTransport {
+        AcpTransport::Stdio
+    }
+
+    fn spawn_args(&self, _port: Option<u16>, _model: Option<&str>) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// 模型通过 `ANTHROPIC_MODEL` 传入
+    fn spawn_envs(&self, model: Option<&str>) -> Vec<(String, String)> {
+        model
+            .map(|model_name| vec![("ANTHROPIC_MODEL".to_string(), model_name.to_string())])
+            .unwrap_or_default()
+    }
+}
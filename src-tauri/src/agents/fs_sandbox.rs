@@ -0,0 +1,145 @@
+//! ACP `fs/read_text_file` / `fs/write_text_file` 的路径沙箱：请求路径按 Agent 的工作区解析，
+//! 解析符号链接后必须落在工作区或设置中显式放行的目录（`fs_allowed_dirs`）之下。
+//! 写入的目标文件可以尚不存在，此时以最近的已存在祖先目录解析真实路径。
+use std::path::{Component, Path, PathBuf};
+
+use crate::wsl_path::to_workspace_host_path;
+
+/// 放行目录只接受绝对路径
+pub(crate) fn validate_fs_allowed_dirs(dirs: &[String]) -> Result<(), String> {
+    for dir in dirs {
+        if dir.trim().is_empty() {
+            return Err("Allowed directory cannot be empty".to_string());
+        }
+        if !Path::new(dir.trim()).is_absolute() {
+            return Err(format!("Allowed directory must be absolute: {}", dir));
+        }
+    }
+    Ok(())
+}
+
+/// 解析符号链接后的真实路径；路径不存在时解析最近的已存在祖先，再拼回其余部分
+async fn resolve_real_path(path: &Path) -> Result<PathBuf, String> {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        match tokio::fs::canonicalize(existing).await {
+            Ok(real) => {
+                return Ok(rest.iter().rev().fold(real, |acc, part| acc.join(part)));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+                    return Err(format!("Failed to resolve path {}: {}", path.display(), e));
+                };
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            Err(e) => {
+                return Err(format!("Failed to resolve path {}: {}", path.display(), e));
+            }
+        }
+    }
+}
+
+/// 把 Agent 请求的路径限制在工作区与放行目录内，返回解析后的真实路径
+pub(crate) async fn resolve_sandboxed_path(
+    workspace_path: &str,
+    allowed_dirs: &[String],
+    requested: &str,
+) -> Result<PathBuf, String> {
+    // WSL 内的 Agent 报告 Linux 路径，换算为工作区一侧可访问的路径
+    let host_path = to_workspace_host_path(workspace_path, requested);
+    let requested_path = Path::new(host_path.as_deref().unwrap_or(requested.trim()));
+    if requested_path.as_os_str().is_empty() {
+        return Err("Path cannot be empty".to_string());
+    }
+    // 不存在的部分无法解析符号链接，直接拒绝其中的 `..`
+    if requested_path
+        .components()
+        .any(|component| component == Component::ParentDir)
+    {
+        return Err(format!("Path {} must not contain '..'", requested));
+    }
+
+    let workspace_root = tokio::fs::canonicalize(workspace_path)
+        .await
+        .map_err(|e| format!("Failed to resolve workspace path {}: {}", workspace_path, e))?;
+    let target = resolve_real_path(&workspace_root.join(requested_path)).await?;
+
+    let mut roots = vec![workspace_root];
+    for dir in allowed_dirs {
+        if let Ok(root) = tokio::fs::canonicalize(dir.trim()).await {
+            roots.push(root);
+        }
+    }
+    if roots.iter().any(|root| target.starts_with(root)) {
+        Ok(target)
+    } else {
+        Err(format!("Path {} is outside the workspace", requested))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("iflow-fs-sandbox-{}", uuid::Uuid::new_v4()))
+            .join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::canonicalize(dir).unwrap()
+    }
+
+    #[tokio::test]
+    async fn paths_stay_inside_workspace() {
+        let workspace = temp_dir("ws");
+        std::fs::write(workspace.join("notes.md"), "hi").unwrap();
+        let ws = workspace.to_string_lossy().to_string();
+
+        let read = resolve_sandboxed_path(&ws, &[], "notes.md").await.unwrap();
+        assert_eq!(read, workspace.join("notes.md"));
+        let absolute = workspace.join("src/new.rs").to_string_lossy().to_string();
+        let write = resolve_sandboxed_path(&ws, &[], &absolute).await.unwrap();
+        assert_eq!(write, workspace.join("src/new.rs"));
+
+        assert!(resolve_sandboxed_path(&ws, &[], "../escape.txt")
+            .await
+            .is_err());
+        assert!(resolve_sandboxed_path(&ws, &[], "/etc/hosts")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn allowlist_opens_extra_directories() {
+        let workspace = temp_dir("ws");
+        let shared = temp_dir("shared");
+        let ws = workspace.to_string_lossy().to_string();
+        let target = shared.join("out.txt").to_string_lossy().to_string();
+
+        assert!(resolve_sandboxed_path(&ws, &[], &target).await.is_err());
+        let allowed = vec![shared.to_string_lossy().to_string()];
+        assert!(validate_fs_allowed_dirs(&allowed).is_ok());
+        assert_eq!(
+            resolve_sandboxed_path(&ws, &allowed, &target)
+                .await
+                .unwrap(),
+            shared.join("out.txt")
+        );
+        assert!(validate_fs_allowed_dirs(&["relative/dir".to_string()]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn symlinks_cannot_escape_workspace() {
+        let workspace = temp_dir("ws");
+        let outside = temp_dir("outside");
+        std::os::unix::fs::symlink(&outside, workspace.join("link")).unwrap();
+        let ws = workspace.to_string_lossy().to_string();
+
+        assert!(resolve_sandboxed_path(&ws, &[], "link/secret.txt")
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,48 @@
+//! Gemini CLI 适配器：以 `gemini --experimental-acp` 启动（stdio 上的换行分隔 JSON-RPC），
+//! 经 stdio 桥接复用 iFlow 的监听任务，会话更新同样经 `router::handle_session_update` 转发给前端。
+use super::adapter::{AcpAgentAdapter, AcpTransport};
+
+pub(crate) const GEMINI_AGENT_TYPE: &str = "gemini";
+pub(crate) const DEFAULT_GEMINI_COMMAND: &str = "gemini";
+
+pub(crate) struct GeminiAdapter;
+
+impl AcpAgentAdapter for GeminiAdapter {
+    fn agent_type(&self) -> &'static str {
+        GEMINI_AGENT_TYPE
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Gemini CLI"
+    }
+
+    fn transport(&self) -> AcpTransport {
+        AcpTransport::Stdio
+    }
+
+    fn spawn_args(&self, _port: Option<u16>, model: Option<&str>) -> Vec<String> {
+        let mut args = vec!["--experimental-acp".to_string()];
+        if let Some(model_name) = model {
+            args.push("--model".to_string());
+            args.push(model_name.to_string());
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_is_passed_as_cli_flag() {
+        assert_eq!(
+            GeminiAdapter.spawn_args(None, None),
+            vec!["--experimental-acp"]
+        );
+        assert_eq!(
+            GeminiAdapter.spawn_args(None, Some("gemini-2.5-pro")),
+            vec!["--experimental-acp", "--model", "gemini-2.5-pro"]
+        );
+    }
+}
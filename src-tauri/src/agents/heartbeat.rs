@@ -0,0 +1,103 @@
+//! WebSocket 心跳：按保活间隔发送 ping，根据距上次收到数据（含 pong）的时长判断连接健康状况。
+//! 连续错过两次 pong 视为连接停滞并发出 `connection-health` 事件，错过四次视为已断开，
+//! 由监听任务主动关闭连接并重连。
+use serde_json::json;
+use tokio::time::Duration;
+
+use crate::app_lock::EmitUnlessLocked;
+
+pub(crate) const DEFAULT_KEEPALIVE_INTERVAL_SECS: u64 = 15;
+const STALE_AFTER_MISSED: u32 = 2;
+const DEAD_AFTER_MISSED: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ConnectionHealth {
+    Healthy,
+    Stale,
+    /// 需要断开重连
+    Dead,
+}
+
+impl ConnectionHealth {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Healthy => "healthy",
+            Self::Stale => "stale",
+            Self::Dead => "reconnecting",
+        }
+    }
+}
+
+pub(super) struct Heartbeat {
+    interval: Duration,
+    reported: ConnectionHealth,
+}
+
+impl Heartbeat {
+    pub(super) fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            reported: ConnectionHealth::Healthy,
+        }
+    }
+
+    fn health_for(&self, silence: Duration) -> ConnectionHealth {
+        if silence >= self.interval * DEAD_AFTER_MISSED {
+            ConnectionHealth::Dead
+        } else if silence >= self.interval * STALE_AFTER_MISSED {
+            ConnectionHealth::Stale
+        } else {
+            ConnectionHealth::Healthy
+        }
+    }
+
+    /// 按静默时长更新状态，状态变化时返回新状态
+    pub(super) fn observe(&mut self, silence: Duration) -> Option<ConnectionHealth> {
+        let health = self.health_for(silence);
+        if health == self.reported {
+            return None;
+        }
+        self.reported = health;
+        Some(health)
+    }
+}
+
+pub(super) fn emit_connection_health(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    health: ConnectionHealth,
+    silence: Duration,
+) {
+    let _ = app_handle.emit_unless_locked(
+        "connection-health",
+        json!({
+            "agentId": agent_id,
+            "status": health.as_str(),
+            "silentSecs": silence.as_secs(),
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missed_pongs_escalate_from_stale_to_dead() {
+        let mut heartbeat = Heartbeat::new(Duration::from_secs(10));
+        assert_eq!(heartbeat.observe(Duration::from_secs(5)), None);
+        assert_eq!(
+            heartbeat.observe(Duration::from_secs(20)),
+            Some(ConnectionHealth::Stale)
+        );
+        assert_eq!(heartbeat.observe(Duration::from_secs(30)), None);
+        assert_eq!(
+            heartbeat.observe(Duration::from_secs(1)),
+            Some(ConnectionHealth::Healthy)
+        );
+        assert_eq!(
+            heartbeat.observe(Duration::from_secs(40)),
+            Some(ConnectionHealth::Dead)
+        );
+    }
+}
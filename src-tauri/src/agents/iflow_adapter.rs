@@ -1,61 +1,153 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
 
 use futures::{SinkExt, StreamExt};
 use serde_json::{json, Value};
-use tauri::Emitter;
-use tokio::time::{timeout, Duration};
+use tauri::Manager;
+use tokio::time::{Duration, Instant, MissedTickBehavior};
 use tokio_tungstenite::tungstenite::Message as WsMessage;
 
-use crate::models::ListenerCommand;
-use crate::router::{emit_task_finish, handle_session_update};
-use super::session_params::{
-    build_initialize_params, build_session_new_params,
-    build_session_new_params_with_id, build_session_load_params, build_prompt_params,
+use super::adapter::{set_agent_status, AcpAgentAdapter, AcpTransport};
+use super::fs_sandbox::resolve_sandboxed_path;
+use super::heartbeat::{emit_connection_health, ConnectionHealth, Heartbeat};
+use super::remote::is_remote_agent_type;
+use super::tls::connector_for;
+use super::write_preview::{
+    emit_file_write_preview, requires_write_approval, resolve_file_write, write_file,
+    PendingFileWrite,
 };
+use crate::acp_trace::{record_acp_message, TraceDirection};
+use crate::annotations::spawn_annotation_extraction;
+use crate::app_lock::EmitUnlessLocked;
+use crate::diagram::spawn_diagram_rendering;
+use crate::generation::GenerationOptions;
+use crate::iflow_auth::{
+    auth_methods_from_initialize, clear_auth_required, emit_auth_required, is_auth_error,
+    watch_iflow_stderr, AuthMethod,
+};
+use crate::legal_hold::record_session_audit;
+use crate::memory_guard::{
+    clear_spilled_outputs, memory_limits, queue_has_room, MemoryGuard, MemoryUsage,
+};
+use crate::models::{
+    AgentSessions, AgentStatus, ListenerCommand, PausedStreamMode, PermissionMode, PromptResource,
+};
+use crate::notify_channels::notify_task_finish;
+use crate::path_display::relativize_workspace_paths_in_value;
+use crate::permissions::{
+    apply_permission_rules, deny_rule_for_tool_call, outcome_for_decision, outcome_for_option,
+    PermissionDecision,
+};
+use crate::plugins::{dispatch_plugin_hook, PluginHook};
+use crate::postprocess::spawn_postprocess;
+use crate::progress::{emit_task_progress, TaskProgress, PROGRESS_INTERVAL};
+use crate::prompt_split::prompt_split_metadata;
+use crate::reply_language::spawn_language_check;
+use crate::retry::{
+    emit_prompt_retry, is_transient_prompt_error, reconnect_backoff, reconnect_jitter,
+    retry_backoff,
+};
+use crate::router::{emit_task_finish, handle_session_update, message_chunk_text};
+use crate::session_title::spawn_session_title;
+use crate::settings::{
+    fs_allowed_dirs, keepalive_interval, message_style, paused_stream_mode, permission_timeout,
+    prompt_max_retries, SystemMarker,
+};
+use crate::state::AppState;
+use crate::storage::{load_last_acp_session, remember_last_acp_session};
+use crate::stream_wal::StreamWal;
+use crate::suggestions::{
+    snapshot_git_changes, spawn_suggested_actions, GitBaseline, TurnActivity,
+};
+use crate::tasks::{record_task_finish, record_task_start, TaskRecord};
+use crate::verification::spawn_verification;
+
+/// 暂停期间最多缓存的流式更新条数
+const MAX_PAUSED_UPDATES: usize = 5000;
 
 // ACP 连接
-struct AcpConnection {
+pub(super) struct AcpConnection {
     ws_stream: tokio_tungstenite::WebSocketStream<
         tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
     >,
+    endpoint: String,
+    /// 最近一次收到任意帧（含 pong）的时间，心跳据此判断连接是否停滞
+    last_received: Instant,
 }
 
 impl AcpConnection {
-    async fn connect(url: &str) -> Result<Self, String> {
+    pub(super) async fn connect(url: &str) -> Result<Self, String> {
+        let endpoint = url.to_string();
         let url = url::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
 
-        let (ws_stream, _) = tokio_tungstenite::connect_async(url)
-            .await
-            .map_err(|e| format!("WebSocket connection failed: {}", e))?;
-
-        Ok(Self { ws_stream })
+        // wss 使用为该 endpoint 登记的 TLS 配置（自定义 CA、客户端证书），未登记时信任系统根证书
+        let connector = match url.scheme() {
+            "wss" => connector_for(&endpoint),
+            _ => None,
+        };
+        let (ws_stream, _) =
+            tokio_tungstenite::connect_async_tls_with_config(url, None, false, connector)
+                .await
+                .map_err(|e| format!("WebSocket connection failed: {}", e))?;
+
+        Ok(Self {
+            ws_stream,
+            endpoint,
+            last_received: Instant::now(),
+        })
     }
 
-    async fn send_message(&mut self, message: String) -> Result<(), String> {
+    pub(super) async fn send_message(&mut self, message: String) -> Result<(), String> {
+        record_acp_message(&self.endpoint, TraceDirection::Send, &message);
         self.ws_stream
             .send(WsMessage::Text(message.into()))
             .await
             .map_err(|e| format!("Failed to send message: {}", e))
     }
 
-    async fn receive_message(&mut self) -> Result<Option<String>, String> {
-        match timeout(Duration::from_secs(30), self.ws_stream.next()).await {
-            Ok(Some(Ok(WsMessage::Text(text)))) => Ok(Some(text.to_string())),
-            Ok(Some(Ok(WsMessage::Binary(bin)))) => String::from_utf8(bin.to_vec())
+    pub(super) async fn receive_message(&mut self) -> Result<Option<String>, String> {
+        let received = self.receive_raw().await;
+        if let Ok(Some(message)) = &received {
+            record_acp_message(&self.endpoint, TraceDirection::Receive, message);
+        }
+        received
+    }
+
+    /// 发送 ping；对端的 pong 由 `receive_message` 读取并刷新 `last_received`
+    pub(super) async fn send_ping(&mut self) -> Result<(), String> {
+        self.ws_stream
+            .send(WsMessage::Ping(Vec::new()))
+            .await
+            .map_err(|e| format!("Failed to send ping: {}", e))
+    }
+
+    /// 距最近一次收到数据的时长
+    pub(super) fn silence(&self) -> Duration {
+        self.last_received.elapsed()
+    }
+
+    // 不设读超时：空闲连接的存活由心跳判断，一次性会话由调用方的整体超时兜底
+    async fn receive_raw(&mut self) -> Result<Option<String>, String> {
+        let frame = self.ws_stream.next().await;
+        if matches!(frame, Some(Ok(_))) {
+            self.last_received = Instant::now();
+        }
+        match frame {
+            Some(Ok(WsMessage::Text(text))) => Ok(Some(text.to_string())),
+            Some(Ok(WsMessage::Binary(bin))) => String::from_utf8(bin.to_vec())
                 .map(Some)
                 .map_err(|e| format!("Invalid UTF-8: {}", e)),
-            Ok(Some(Ok(WsMessage::Ping(_)))) => Ok(Some(String::new())),
-            Ok(Some(Ok(WsMessage::Pong(_)))) => Ok(Some(String::new())),
-            Ok(Some(Ok(WsMessage::Close(_)))) => Ok(None),
-            Ok(Some(Err(e))) => Err(format!("WebSocket error: {}", e)),
-            Ok(None) => Ok(None),
-            Err(_) => Ok(Some(String::new())),
+            Some(Ok(WsMessage::Ping(_))) => Ok(Some(String::new())),
+            Some(Ok(WsMessage::Pong(_))) => Ok(Some(String::new())),
+            Some(Ok(WsMessage::Close(_))) => Ok(None),
+            Some(Err(e)) => Err(format!("WebSocket error: {}", e)),
+            None => Ok(None),
             _ => Ok(None),
         }
     }
 }
 
-fn build_rpc_request(id: i64, method: &str, params: Value) -> String {
+pub(super) fn build_rpc_request(id: i64, method: &str, params: Value) -> String {
     json!({
         "jsonrpc": "2.0",
         "id": id,
@@ -65,7 +157,11 @@ fn build_rpc_request(id: i64, method: &str, params: Value) -> String {
     .to_string()
 }
 
-async fn send_rpc_result(conn: &mut AcpConnection, id: i64, result: Value) -> Result<(), String> {
+pub(super) async fn send_rpc_result(
+    conn: &mut AcpConnection,
+    id: i64,
+    result: Value,
+) -> Result<(), String> {
     conn.send_message(
         json!({
             "jsonrpc": "2.0",
@@ -77,7 +173,7 @@ async fn send_rpc_result(conn: &mut AcpConnection, id: i64, result: Value) -> Re
     .await
 }
 
-async fn send_rpc_error(
+pub(super) async fn send_rpc_error(
     conn: &mut AcpConnection,
     id: i64,
     code: i64,
@@ -97,7 +193,7 @@ async fn send_rpc_error(
     .await
 }
 
-fn parse_rpc_id(message: &Value) -> Option<i64> {
+pub(super) fn parse_rpc_id(message: &Value) -> Option<i64> {
     let id = message.get("id")?;
     if let Some(v) = id.as_i64() {
         return Some(v);
@@ -111,12 +207,52 @@ fn parse_rpc_id(message: &Value) -> Option<i64> {
     None
 }
 
+/// 转交前端、等待用户选择的 session/request_permission
+struct PendingPermission {
+    params: Value,
+    deadline: Instant,
+    timeout_decision: PermissionDecision,
+}
+
+/// 需要等待前端应答的 Agent 请求
+enum PendingServerRequest {
+    Permission(PendingPermission),
+    FileWrite(PendingFileWrite),
+}
+
+/// 应答权限请求，并通知前端关闭对应的确认框
+async fn answer_permission(
+    conn: &mut AcpConnection,
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    request_id: i64,
+    outcome: Value,
+    timed_out: bool,
+) -> Result<(), String> {
+    let _ = app_handle.emit_unless_locked(
+        "permission-resolved",
+        json!({
+            "agentId": agent_id,
+            "requestId": request_id,
+            "outcome": &outcome,
+            "timedOut": timed_out,
+        }),
+    );
+    send_rpc_result(conn, request_id, json!({ "outcome": outcome })).await
+}
+
+/// 处理 Agent 发来的请求；未命中规则的权限请求与计划模式下的文件写入返回待应答项，
+/// 由监听任务等待用户选择
 async fn handle_server_request(
     conn: &mut AcpConnection,
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    workspace_path: &str,
+    permission_mode: PermissionMode,
     request_id: i64,
     method: &str,
     params: Option<&Value>,
-) {
+) -> Option<PendingServerRequest> {
     let params = params.cloned().unwrap_or(Value::Null);
     println!(
         "[listener] Server request received: method={}, id={}",
@@ -125,29 +261,80 @@ async fn handle_server_request(
 
     let result = match method {
         "session/request_permission" => {
-            send_rpc_result(
-                conn,
-                request_id,
-                json!({
-                    "outcome": {
-                        "outcome": "selected",
-                        "optionId": "allow_once",
-                    }
-                }),
-            )
-            .await
+            // 命中工作区已保存的允许/拒绝规则时自动应答，否则转交前端由用户选择
+            let outcome = match apply_permission_rules(app_handle, workspace_path, &params).await {
+                Some((outcome, rule)) => {
+                    let _ = app_handle.emit_unless_locked(
+                        "permission-decision",
+                        json!({
+                            "agentId": agent_id,
+                            "toolCallId": params
+                                .get("toolCall")
+                                .and_then(|tool_call| tool_call.get("toolCallId"))
+                                .cloned()
+                                .unwrap_or(Value::Null),
+                            "decision": rule.decision,
+                            "ruleId": rule.id,
+                        }),
+                    );
+                    outcome
+                }
+                None => {
+                    let (wait, timeout_decision) = permission_timeout(app_handle).await;
+                    let _ = app_handle.emit_unless_locked(
+                        "permission-request",
+                        json!({
+                            "agentId": agent_id,
+                            "requestId": request_id,
+                            "sessionId": params.get("sessionId").cloned().unwrap_or(Value::Null),
+                            "toolCall": relativize_workspace_paths_in_value(
+                                params.get("toolCall").unwrap_or(&Value::Null),
+                                workspace_path,
+                            ),
+                            "options": params.get("options").cloned().unwrap_or_else(|| json!([])),
+                            "timeoutSecs": wait.as_secs(),
+                            "timeoutDecision": timeout_decision,
+                        }),
+                    );
+                    return Some(PendingServerRequest::Permission(PendingPermission {
+                        params,
+                        deadline: Instant::now() + wait,
+                        timeout_decision,
+                    }));
+                }
+            };
+            send_rpc_result(conn, request_id, json!({ "outcome": outcome })).await
         }
         "fs/read_text_file" => {
             let Some(path) = params.get("path").and_then(Value::as_str) else {
                 let _ = send_rpc_error(conn, request_id, -32602, "Missing path").await;
-                return;
+                return None;
             };
             let session_id = params
                 .get("sessionId")
                 .and_then(Value::as_str)
                 .unwrap_or_default();
+            let allowed_dirs = fs_allowed_dirs(app_handle).await;
+            let target = match resolve_sandboxed_path(workspace_path, &allowed_dirs, path).await {
+                Ok(target) => target,
+                Err(e) => {
+                    println!("[listener] Rejected fs/read_text_file: {}", e);
+                    record_session_audit(app_handle, session_id, method, true, Some(e.clone()))
+                        .await;
+                    let _ = send_rpc_error(conn, request_id, -32602, &e).await;
+                    return None;
+                }
+            };
+            record_session_audit(
+                app_handle,
+                session_id,
+                method,
+                false,
+                Some(target.display().to_string()),
+            )
+            .await;
 
-            match tokio::fs::read_to_string(path).await {
+            match tokio::fs::read_to_string(&target).await {
                 Ok(content) => {
                     send_rpc_result(
                         conn,
@@ -174,36 +361,341 @@ async fn handle_server_request(
         "fs/write_text_file" => {
             let Some(path) = params.get("path").and_then(Value::as_str) else {
                 let _ = send_rpc_error(conn, request_id, -32602, "Missing path").await;
-                return;
+                return None;
             };
             let Some(content) = params.get("content").and_then(Value::as_str) else {
                 let _ = send_rpc_error(conn, request_id, -32602, "Missing content").await;
-                return;
+                return None;
             };
-
-            match tokio::fs::write(path, content).await {
-                Ok(_) => send_rpc_result(conn, request_id, Value::Null).await,
+            let session_id = params
+                .get("sessionId")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let allowed_dirs = fs_allowed_dirs(app_handle).await;
+            let target = match resolve_sandboxed_path(workspace_path, &allowed_dirs, path).await {
+                Ok(target) => target,
                 Err(e) => {
-                    send_rpc_error(
-                        conn,
-                        request_id,
-                        -32603,
-                        &format!("Failed to write file: {}", e),
-                    )
-                    .await
+                    println!("[listener] Rejected fs/write_text_file: {}", e);
+                    record_session_audit(app_handle, session_id, method, true, Some(e.clone()))
+                        .await;
+                    let _ = send_rpc_error(conn, request_id, -32602, &e).await;
+                    return None;
                 }
+            };
+
+            let approval = if requires_write_approval(permission_mode) {
+                Some(permission_timeout(app_handle).await)
+            } else {
+                None
+            };
+            emit_file_write_preview(
+                app_handle,
+                agent_id,
+                workspace_path,
+                request_id,
+                session_id,
+                path,
+                &target,
+                content,
+                approval,
+            )
+            .await;
+            if let Some((wait, timeout_decision)) = approval {
+                return Some(PendingServerRequest::FileWrite(PendingFileWrite::new(
+                    session_id,
+                    target,
+                    content.to_string(),
+                    wait,
+                    timeout_decision,
+                )));
             }
+            record_session_audit(
+                app_handle,
+                session_id,
+                method,
+                false,
+                Some(target.display().to_string()),
+            )
+            .await;
+            write_file(conn, request_id, &target, content).await
         }
         "_iflow/user/questions" => {
             send_rpc_result(conn, request_id, json!({ "answers": {} })).await
         }
-        "_iflow/plan/exit" => send_rpc_result(conn, request_id, json!({ "approved": true })).await,
+        "_iflow/plan/exit" => {
+            let approved = plan_exit_approved(permission_mode);
+            if !approved {
+                println!("[listener] Denied _iflow/plan/exit: agent is in plan mode");
+                let _ = app_handle.emit_unless_locked(
+                    "stream-message",
+                    json!({
+                        "agentId": agent_id,
+                        "content": "Agent 请求退出计划模式，已拒绝；如需执行请先切换权限模式",
+                        "type": "system",
+                    }),
+                );
+            }
+            send_rpc_result(conn, request_id, json!({ "approved": approved })).await
+        }
         _ => send_rpc_error(conn, request_id, -32601, "Method not found").await,
     };
 
     if let Err(e) = result {
         println!("[listener] Failed to respond to {}: {}", method, e);
     }
+    None
+}
+
+/// 用户选择的计划模式下不允许 Agent 自行退出计划并开始修改，由用户切换权限模式
+fn plan_exit_approved(mode: PermissionMode) -> bool {
+    mode != PermissionMode::Plan
+}
+
+/// 缓存暂停期间的流式更新，超出上限时丢弃最早的
+fn buffer_paused_update(
+    buffer: &mut VecDeque<(Value, Value)>,
+    display_update: Value,
+    update: Value,
+) {
+    if buffer.len() >= MAX_PAUSED_UPDATES {
+        buffer.pop_front();
+    }
+    buffer.push_back((display_update, update));
+}
+
+/// 记录使用过的会话，最近使用的排在最后
+fn remember_session(sessions: &mut Vec<String>, session_id: &str) {
+    sessions.retain(|item| item != session_id);
+    sessions.push(session_id.to_string());
+}
+
+/// 通知前端并登记当前会话，删除历史时据此避开正在使用的会话文件；
+/// 同时按工作区持久化，供应用重启后恢复
+async fn publish_acp_session(app_handle: &tauri::AppHandle, agent_id: &str, session_id: &str) {
+    let manager = &app_handle.state::<AppState>().agent_manager;
+    manager
+        .set_acp_session(agent_id, Some(session_id.to_string()))
+        .await;
+    if let (Some(agent_type), Some(workspace_path)) = (
+        manager.agent_type_of(agent_id).await,
+        manager.workspace_path_of(agent_id).await,
+    ) {
+        if let Err(e) =
+            remember_last_acp_session(app_handle, &agent_type, &workspace_path, session_id).await
+        {
+            println!("[listener] Failed to persist last session: {}", e);
+        }
+    }
+    let _ = app_handle.emit_unless_locked(
+        "acp-session",
+        json!({
+            "agentId": agent_id,
+            "sessionId": session_id,
+        }),
+    );
+}
+
+/// 由监听任务的状态推导 Agent 状态
+fn listener_status(
+    session_ready: bool,
+    reconnecting: bool,
+    busy: bool,
+    cancelling: bool,
+) -> AgentStatus {
+    match (session_ready, busy) {
+        (false, _) if reconnecting => AgentStatus::Reconnecting,
+        (false, _) => AgentStatus::Connecting,
+        (true, true) if cancelling => AgentStatus::Stopping,
+        (true, true) => AgentStatus::Busy,
+        (true, false) => AgentStatus::Connected,
+    }
+}
+
+/// 排队等待发送的 prompt：内容、目标 sessionId、生成参数、附带的资源
+type QueuedPrompt = (
+    String,
+    Option<String>,
+    Option<GenerationOptions>,
+    Vec<PromptResource>,
+);
+
+/// 已发送、等待响应的 session/prompt
+struct PendingPrompt {
+    task_id: String,
+    session_id: String,
+    prompt: String,
+    generation: Option<GenerationOptions>,
+    resources: Vec<PromptResource>,
+    started_at: Instant,
+    /// 已自动重试的次数
+    attempt: u32,
+    /// 轮次开始时工作区未提交改动的快照，用于只按本轮改动生成后续建议
+    git_baseline: Option<GitBaseline>,
+}
+
+/// 等待退避结束后重新发送的 prompt
+struct ScheduledRetry {
+    due: Instant,
+    pending: PendingPrompt,
+}
+
+/// 为即将等待响应的 prompt 写入任务记录；记录失败不影响发送。
+/// 提供本地工作区时在后台记录 Git 改动快照
+async fn begin_prompt_task(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    session_id: &str,
+    prompt: String,
+    generation: Option<GenerationOptions>,
+    resources: Vec<PromptResource>,
+    local_workspace_path: Option<&str>,
+) -> PendingPrompt {
+    let mut record = TaskRecord::new(agent_id, Some(session_id), &prompt);
+    // 记录生成参数，便于复现
+    if let Some(generation) = generation.as_ref() {
+        record
+            .metadata
+            .insert("generation".to_string(), json!(generation));
+    }
+    // 记录长 prompt 的拆分方式
+    if let Some(split) = prompt_split_metadata(&prompt) {
+        record.metadata.insert("promptSplit".to_string(), split);
+    }
+    // 记录所用模型，供模型评分榜聚合
+    if let Some(model) = app_handle
+        .state::<AppState>()
+        .agent_manager
+        .model_of(agent_id)
+        .await
+    {
+        record.metadata.insert("model".to_string(), json!(model));
+    }
+    let task_id = record.id.clone();
+    if let Err(e) = record_task_start(app_handle, record).await {
+        println!("[listener] Failed to record task start: {}", e);
+    }
+    PendingPrompt {
+        task_id,
+        session_id: session_id.to_string(),
+        prompt,
+        generation,
+        resources,
+        started_at: Instant::now(),
+        attempt: 0,
+        git_baseline: local_workspace_path
+            .map(|workspace_path| tokio::spawn(snapshot_git_changes(workspace_path.to_string()))),
+    }
+}
+
+async fn fail_prompt_task(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    pending: &PendingPrompt,
+    error: String,
+) {
+    if let Err(e) =
+        record_task_finish(app_handle, &pending.task_id, None, Some(error.clone())).await
+    {
+        println!("[listener] Failed to record task finish: {}", e);
+    }
+    let _ = app_handle.emit_unless_locked(
+        "agent-error",
+        json!({
+            "agentId": agent_id,
+            "error": error,
+        }),
+    );
+}
+
+/// 未超过重试上限时安排退避重发，否则按失败结束任务
+async fn retry_or_fail_prompt(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    mut pending: PendingPrompt,
+    reason: String,
+    scheduled_retries: &mut Vec<ScheduledRetry>,
+) {
+    let max_retries = prompt_max_retries(app_handle).await;
+    if pending.attempt >= max_retries {
+        fail_prompt_task(
+            app_handle,
+            agent_id,
+            &pending,
+            format!("session/prompt failed: {}", reason),
+        )
+        .await;
+        return;
+    }
+
+    pending.attempt += 1;
+    let delay = retry_backoff(pending.attempt);
+    println!(
+        "[listener] Retrying prompt {} ({}/{}) in {:?}: {}",
+        pending.task_id, pending.attempt, max_retries, delay, reason
+    );
+    emit_prompt_retry(
+        app_handle,
+        agent_id,
+        &pending.task_id,
+        pending.attempt,
+        max_retries,
+        delay,
+        &reason,
+    );
+    scheduled_retries.push(ScheduledRetry {
+        due: Instant::now() + delay,
+        pending,
+    });
+}
+
+fn queued_prompt_bytes(queued_prompts: &VecDeque<QueuedPrompt>) -> u64 {
+    queued_prompts
+        .iter()
+        .map(|(prompt, _, _, _)| prompt.len() as u64)
+        .sum()
+}
+
+/// 监听任务当前缓冲内容的字节数
+fn buffered_usage(
+    queued_prompts: &VecDeque<QueuedPrompt>,
+    pending_prompts: &HashMap<i64, PendingPrompt>,
+    scheduled_retries: &[ScheduledRetry],
+    streamed_message: &str,
+) -> MemoryUsage {
+    let pending = pending_prompts
+        .values()
+        .chain(scheduled_retries.iter().map(|retry| &retry.pending))
+        .map(|pending| pending.prompt.len() as u64)
+        .sum();
+    MemoryUsage {
+        queued_prompts: queued_prompt_bytes(queued_prompts),
+        pending_prompts: pending,
+        streamed_reply: streamed_message.len() as u64,
+    }
+}
+
+/// 排队 prompt 超过上限时拒收并通知前端
+async fn reject_if_queue_full(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    queued_prompts: &VecDeque<QueuedPrompt>,
+    prompt: &str,
+) -> bool {
+    let limits = memory_limits(app_handle).await;
+    if queue_has_room(queued_prompt_bytes(queued_prompts), prompt.len(), &limits) {
+        return false;
+    }
+    let _ = app_handle.emit_unless_locked(
+        "agent-error",
+        json!({
+            "agentId": agent_id,
+            "error": format!(
+                "Prompt queue is full (>{} bytes), prompt dropped",
+                limits.max_queued_bytes
+            ),
+        }),
+    );
+    true
 }
 
 fn next_rpc_id(counter: &mut i64) -> i64 {
@@ -322,7 +814,7 @@ fn emit_command_registry_payload(app_handle: &tauri::AppHandle, agent_id: &str,
         return;
     }
 
-    let _ = app_handle.emit(
+    let _ = app_handle.emit_unless_locked(
         "command-registry",
         json!({
             "agentId": agent_id,
@@ -399,7 +891,7 @@ fn emit_model_registry_payload(app_handle: &tauri::AppHandle, agent_id: &str, pa
         return;
     };
 
-    let _ = app_handle.emit(
+    let _ = app_handle.emit_unless_locked(
         "model-registry",
         json!({
             "agentId": agent_id,
@@ -409,6 +901,56 @@ fn emit_model_registry_payload(app_handle: &tauri::AppHandle, agent_id: &str, pa
     );
 }
 
+pub(crate) struct IflowAdapter;
+
+impl AcpAgentAdapter for IflowAdapter {
+    fn agent_type(&self) -> &'static str {
+        "iflow"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "iFlow"
+    }
+
+    fn transport(&self) -> AcpTransport {
+        AcpTransport::WebSocket
+    }
+
+    fn spawn_args(&self, port: Option<u16>, model: Option<&str>) -> Vec<String> {
+        let mut args = vec!["--experimental-acp".to_string()];
+        if let Some(port) = port {
+            args.push("--port".to_string());
+            args.push(port.to_string());
+        }
+        if let Some(model_name) = model {
+            args.push("--model".to_string());
+            args.push(model_name.to_string());
+        }
+        args
+    }
+
+    /// iFlow 会话保存在本地历史中，重启后仍可载入
+    fn resumes_last_session(&self) -> bool {
+        true
+    }
+
+    /// iFlow 启动 WebSocket 服务需要一段时间
+    fn startup_delay(&self) -> Duration {
+        Duration::from_secs(3)
+    }
+
+    /// stderr 中的登录提示转为 auth-required 事件
+    fn watch_stderr(
+        &self,
+        app_handle: &tauri::AppHandle,
+        agent_id: &str,
+        stderr: tokio::process::ChildStderr,
+    ) {
+        clear_auth_required(agent_id);
+        watch_iflow_stderr(app_handle.clone(), agent_id.to_string(), stderr);
+    }
+}
+
 // 查找可用端口
 pub async fn find_available_port() -> Result<u16, String> {
     let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
@@ -426,20 +968,74 @@ pub async fn find_available_port() -> Result<u16, String> {
 pub async fn message_listener_task(
     app_handle: tauri::AppHandle,
     agent_id: String,
+    adapter: Arc<dyn AcpAgentAdapter>,
     ws_url: String,
     workspace_path: String,
+    mcp_servers: Vec<Value>,
+    mut permission_mode: PermissionMode,
     mut message_rx: tokio::sync::mpsc::UnboundedReceiver<ListenerCommand>,
 ) {
     println!("[listener] Starting for agent: {}", agent_id);
 
     let mut retry_count = 0;
     let max_retries = 5;
-    let mut cached_session_id: Option<String> = None;
+    let agent_type = app_handle
+        .state::<AppState>()
+        .agent_manager
+        .agent_type_of(&agent_id)
+        .await
+        .unwrap_or_else(|| adapter.agent_type().to_string());
+    // 远程 Agent 的工作区路径在远程主机上：不代为读写文件，也不在本机解析产物、图表与 git 状态
+    let local_workspace = !is_remote_agent_type(&agent_type);
+    let local_workspace_path = local_workspace.then_some(workspace_path.as_str());
+    // 应用重启后先尝试载入该工作区上次使用的会话，载入失败时回退新建
+    let mut cached_session_id: Option<String> = if adapter.resumes_last_session() {
+        load_last_acp_session(&app_handle, &agent_type, &workspace_path).await
+    } else {
+        None
+    };
+    // 本次进程生命周期内创建或载入过的会话，跨重连保留
+    let mut live_sessions: Vec<String> = Vec::new();
+    // 暂停时的流式更新处理方式，为空表示未暂停；暂停状态跨重连保留
+    let mut paused: Option<PausedStreamMode> = None;
+    // 暂停期间缓存的流式更新（推送给前端的更新, 原始更新）
+    let mut paused_updates: VecDeque<(Value, Value)> = VecDeque::new();
 
     // 未 ready 前收到的 prompt 先入队。每条可绑定一个目标 sessionId（用于恢复指定会话后再发送）。
-    let mut queued_prompts: VecDeque<(String, Option<String>)> = VecDeque::new();
+    let mut queued_prompts: VecDeque<QueuedPrompt> = VecDeque::new();
+    // 当前轮次已收到的助手正文，任务结束时用于识别图表等产物
+    let mut streamed_message = String::new();
+    // 当前轮次正文的预写日志，崩溃后可恢复部分回复
+    let mut stream_wal = StreamWal::new(&agent_id);
+    // 缓冲内容超过软上限时发出 memory-pressure 警告
+    let mut memory_guard = MemoryGuard::new(&agent_id);
+    // 当前轮次的工具调用摘要，任务结束时用于生成后续操作建议
+    let mut turn_activity = TurnActivity::default();
+    // 当前轮次的进度计数，长任务心跳使用
+    let mut turn_progress = TaskProgress::default();
+    // 因瞬时错误或断线等待重发的 prompt，跨重连保留
+    let mut scheduled_retries: Vec<ScheduledRetry> = Vec::new();
+    // 断线时被中断的任务；重连并恢复会话后随 agent-reconnected 事件下发，为 None 表示不是断线重连
+    let mut interrupted_tasks: Option<Vec<String>> = None;
+    let mut last_error = String::new();
+    // 最近一次上报的状态，状态变化时才更新管理器并发出事件
+    let mut reported_status = AgentStatus::Connecting;
 
     while retry_count < max_retries {
+        let connecting_status = if interrupted_tasks.is_some() {
+            AgentStatus::Reconnecting
+        } else {
+            AgentStatus::Connecting
+        };
+        if reported_status != connecting_status {
+            reported_status = connecting_status;
+            set_agent_status(&app_handle, &agent_id, reported_status).await;
+        }
+        if retry_count > 0 {
+            let delay = reconnect_backoff(retry_count, reconnect_jitter());
+            println!("[listener] Reconnecting in {:?}", delay);
+            tokio::time::sleep(delay).await;
+        }
         println!(
             "[listener] Connection attempt {}/{}",
             retry_count + 1,
@@ -449,7 +1045,6 @@ pub async fn message_listener_task(
         match AcpConnection::connect(&ws_url).await {
             Ok(mut conn) => {
                 println!("[listener] WebSocket connected!");
-                retry_count = 0;
 
                 let mut rpc_id_counter: i64 = 1;
                 let mut initialize_request_id: Option<i64>;
@@ -458,12 +1053,23 @@ pub async fn message_listener_task(
                 let mut session_load_request_id: Option<i64> = None;
                 let mut session_load_target_id: Option<String> = None;
                 let mut session_load_for_initialize = false;
+                // switch_agent_session 发起的 session/load，载入完成后应答
+                let mut pending_session_switch: Option<
+                    tokio::sync::oneshot::Sender<Result<(), String>>,
+                > = None;
                 let mut session_id: Option<String> = cached_session_id.clone();
-                let mut pending_prompt_request_ids: HashSet<i64> = HashSet::new();
+                let mut pending_prompts: HashMap<i64, PendingPrompt> = HashMap::new();
                 let mut pending_set_model_requests: HashMap<
                     i64,
                     (tokio::sync::oneshot::Sender<Result<String, String>>, String),
                 > = HashMap::new();
+                let mut pending_set_mode_requests: HashMap<
+                    i64,
+                    (
+                        tokio::sync::oneshot::Sender<Result<(), String>>,
+                        PermissionMode,
+                    ),
+                > = HashMap::new();
                 let mut pending_set_think_requests: HashMap<
                     i64,
                     (
@@ -472,29 +1078,257 @@ pub async fn message_listener_task(
                         String,
                     ),
                 > = HashMap::new();
+                // initialize 声明的登录方式；需要登录时随 auth-required 事件下发
+                let mut auth_methods: Vec<AuthMethod> = Vec::new();
+                let mut pending_authenticate: Option<(
+                    i64,
+                    tokio::sync::oneshot::Sender<Result<(), String>>,
+                )> = None;
+                // session/new 因未登录失败时记录目标会话，登录成功后重新创建
+                let mut session_new_after_auth: Option<Option<String>> = None;
+                let mut pending_permissions: HashMap<i64, PendingPermission> = HashMap::new();
+                let mut pending_file_writes: HashMap<i64, PendingFileWrite> = HashMap::new();
 
                 let init_id = next_rpc_id(&mut rpc_id_counter);
                 let init_request =
-                    build_rpc_request(init_id, "initialize", build_initialize_params());
+                    build_rpc_request(init_id, "initialize", adapter.initialize_params());
                 if let Err(e) = conn.send_message(init_request).await {
                     println!("[listener] Failed to send initialize: {}", e);
                     break;
                 }
                 initialize_request_id = Some(init_id);
 
+                let mut progress_ticker = tokio::time::interval(PROGRESS_INTERVAL);
+                progress_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                // 保活间隔为 0 时不发心跳，定时器仍需一个有效周期
+                let keepalive = keepalive_interval(&app_handle).await;
+                let keepalive_period = keepalive.unwrap_or(Duration::from_secs(3600));
+                let mut keepalive_ticker =
+                    tokio::time::interval_at(Instant::now() + keepalive_period, keepalive_period);
+                keepalive_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                let mut heartbeat = Heartbeat::new(keepalive_period);
+                // 已发送 session/cancel、等待进行中的 prompt 结束
+                let mut cancelling = false;
+
                 loop {
+                    let session_ready = session_id.is_some()
+                        && initialize_request_id.is_none()
+                        && session_new_request_id.is_none()
+                        && !(session_load_for_initialize && session_load_request_id.is_some());
+                    let busy = !pending_prompts.is_empty()
+                        || !scheduled_retries.is_empty()
+                        || !queued_prompts.is_empty();
+                    cancelling &= busy;
+                    let status = listener_status(
+                        session_ready,
+                        interrupted_tasks.is_some(),
+                        busy,
+                        cancelling,
+                    );
+                    if status != reported_status {
+                        reported_status = status;
+                        set_agent_status(&app_handle, &agent_id, status).await;
+                    }
+
                     tokio::select! {
+                        _ = tokio::time::sleep_until(
+                            scheduled_retries
+                                .iter()
+                                .map(|retry| retry.due)
+                                .min()
+                                .unwrap_or_else(Instant::now),
+                        ), if !scheduled_retries.is_empty()
+                            && paused.is_none()
+                            && session_id.is_some()
+                            && initialize_request_id.is_none()
+                            && session_new_request_id.is_none()
+                            && session_load_request_id.is_none() => {
+                            let now = Instant::now();
+                            let (due, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut scheduled_retries)
+                                .into_iter()
+                                .partition(|retry| retry.due <= now);
+                            scheduled_retries = waiting;
+
+                            let mut send_failed = false;
+                            for retry in due {
+                                let pending = retry.pending;
+                                if send_failed {
+                                    scheduled_retries.push(ScheduledRetry { due: now, pending });
+                                    continue;
+                                }
+                                if session_id.as_deref() != Some(pending.session_id.as_str()) {
+                                    fail_prompt_task(
+                                        &app_handle,
+                                        &agent_id,
+                                        &pending,
+                                        "session/prompt retry skipped: session changed".to_string(),
+                                    )
+                                    .await;
+                                    continue;
+                                }
+
+                                let prompt_id = next_rpc_id(&mut rpc_id_counter);
+                                let prompt_request = build_rpc_request(
+                                    prompt_id,
+                                    "session/prompt",
+                                    adapter.prompt_params(
+                                        &pending.session_id,
+                                        &pending.prompt,
+                                        &pending.resources,
+                                        pending.generation.as_ref(),
+                                    ),
+                                );
+                                println!(
+                                    "[listener] Resending session/prompt: id={}, attempt={}",
+                                    prompt_id, pending.attempt
+                                );
+                                if let Err(e) = conn.send_message(prompt_request).await {
+                                    println!("[listener] Failed to resend prompt: {}", e);
+                                    scheduled_retries.push(ScheduledRetry { due: now, pending });
+                                    send_failed = true;
+                                    continue;
+                                }
+                                pending_prompts.insert(prompt_id, pending);
+                            }
+                            if send_failed {
+                                break;
+                            }
+                        }
+
+                        _ = tokio::time::sleep_until(
+                            pending_permissions
+                                .values()
+                                .map(|pending| pending.deadline)
+                                .min()
+                                .unwrap_or_else(Instant::now),
+                        ), if !pending_permissions.is_empty() => {
+                            let now = Instant::now();
+                            let expired: Vec<i64> = pending_permissions
+                                .iter()
+                                .filter(|(_, pending)| pending.deadline <= now)
+                                .map(|(request_id, _)| *request_id)
+                                .collect();
+                            for request_id in expired {
+                                let Some(pending) = pending_permissions.remove(&request_id) else {
+                                    continue;
+                                };
+                                println!(
+                                    "[listener] Permission request {} timed out, answering {:?}",
+                                    request_id, pending.timeout_decision
+                                );
+                                let outcome = outcome_for_decision(&pending.params, pending.timeout_decision);
+                                if let Err(e) =
+                                    answer_permission(&mut conn, &app_handle, &agent_id, request_id, outcome, true).await
+                                {
+                                    println!("[listener] Failed to respond to permission request: {}", e);
+                                }
+                            }
+                        }
+
+                        _ = tokio::time::sleep_until(
+                            pending_file_writes
+                                .values()
+                                .map(|pending| pending.deadline)
+                                .min()
+                                .unwrap_or_else(Instant::now),
+                        ), if !pending_file_writes.is_empty() => {
+                            let now = Instant::now();
+                            let expired: Vec<i64> = pending_file_writes
+                                .iter()
+                                .filter(|(_, pending)| pending.deadline <= now)
+                                .map(|(request_id, _)| *request_id)
+                                .collect();
+                            for request_id in expired {
+                                let Some(pending) = pending_file_writes.remove(&request_id) else {
+                                    continue;
+                                };
+                                println!(
+                                    "[listener] File write {} timed out, answering {:?}",
+                                    request_id, pending.timeout_decision
+                                );
+                                let accepted = pending.timeout_decision == PermissionDecision::Allow;
+                                if let Err(e) = resolve_file_write(
+                                    &mut conn,
+                                    &app_handle,
+                                    &agent_id,
+                                    request_id,
+                                    pending,
+                                    accepted,
+                                    true,
+                                )
+                                .await
+                                {
+                                    println!("[listener] Failed to respond to file write: {}", e);
+                                }
+                            }
+                        }
+
+                        _ = keepalive_ticker.tick(), if keepalive.is_some() => {
+                            let silence = conn.silence();
+                            if let Some(health) = heartbeat.observe(silence) {
+                                println!(
+                                    "[listener] Connection health: {:?}, silent for {}s",
+                                    health,
+                                    silence.as_secs()
+                                );
+                                emit_connection_health(&app_handle, &agent_id, health, silence);
+                                if health == ConnectionHealth::Dead {
+                                    break;
+                                }
+                            }
+                            if let Err(e) = conn.send_ping().await {
+                                println!("[listener] {}", e);
+                                break;
+                            }
+                        }
+
+                        _ = progress_ticker.tick() => {
+                            stream_wal.flush().await;
+                            memory_guard
+                                .check(
+                                    &app_handle,
+                                    buffered_usage(&queued_prompts, &pending_prompts, &scheduled_retries, &streamed_message),
+                                )
+                                .await;
+                            // 并发的多个 prompt 按最早发出的那个计时
+                            if let Some(pending) = pending_prompts
+                                .values()
+                                .min_by_key(|pending| pending.started_at)
+                                .filter(|_| paused.is_none())
+                            {
+                                emit_task_progress(
+                                    &app_handle,
+                                    &agent_id,
+                                    &pending.task_id,
+                                    pending.started_at.elapsed(),
+                                    &turn_progress,
+                                );
+                            }
+                        }
+
                         msg = message_rx.recv() => {
                             match msg {
-                                Some(ListenerCommand::UserPrompt { content: prompt, session_id: requested_session_id }) => {
+                                Some(ListenerCommand::UserPrompt { content: prompt, session_id: requested_session_id, generation, resources }) => {
                                     let target_session_id = requested_session_id
                                         .map(|item| item.trim().to_string())
                                         .filter(|item| !item.is_empty());
 
+                                    if paused.is_some() {
+                                        if reject_if_queue_full(&app_handle, &agent_id, &queued_prompts, &prompt).await {
+                                            continue;
+                                        }
+                                        println!("[listener] Agent paused, prompt queued");
+                                        queued_prompts.push_back((prompt, target_session_id, generation, resources));
+                                        continue;
+                                    }
+
                                     if let Some(target) = target_session_id.as_ref() {
                                         if session_id.as_deref() != Some(target.as_str()) {
                                             println!("[listener] Session switch requested: {} -> {}", session_id.as_deref().unwrap_or("<none>"), target);
-                                            queued_prompts.push_back((prompt, target_session_id.clone()));
+                                            if reject_if_queue_full(&app_handle, &agent_id, &queued_prompts, &prompt).await {
+                                                continue;
+                                            }
+                                            queued_prompts.push_back((prompt, target_session_id.clone(), generation, resources));
 
                                             if session_load_request_id.is_none() {
                                                 let load_id = next_rpc_id(&mut rpc_id_counter);
@@ -504,7 +1338,7 @@ pub async fn message_listener_task(
                                                 let load_request = build_rpc_request(
                                                     load_id,
                                                     "session/load",
-                                                    build_session_load_params(&workspace_path, target),
+                                                    adapter.session_load_params(&workspace_path, target, &mcp_servers, permission_mode),
                                                 );
                                                 if let Err(e) = conn.send_message(load_request).await {
                                                     println!("[listener] Failed to send session/load: {}", e);
@@ -520,22 +1354,57 @@ pub async fn message_listener_task(
                                         let prompt_request = build_rpc_request(
                                             prompt_id,
                                             "session/prompt",
-                                            build_prompt_params(current_session_id, &prompt),
+                                            adapter.prompt_params(current_session_id, &prompt, &resources, generation.as_ref()),
                                         );
 
                                         println!("[listener] Sending session/prompt request: id={}", prompt_id);
                                         if let Err(e) = conn.send_message(prompt_request).await {
                                             println!("[listener] Failed to send prompt: {}", e);
-                                            queued_prompts.push_front((prompt, target_session_id));
+                                            queued_prompts.push_front((prompt, target_session_id, generation, resources));
                                             break;
                                         }
-                                        pending_prompt_request_ids.insert(prompt_id);
+                                        let pending =
+                                            begin_prompt_task(&app_handle, &agent_id, current_session_id, prompt, generation, resources, local_workspace_path).await;
+                                        pending_prompts.insert(prompt_id, pending);
                                     } else {
+                                        if reject_if_queue_full(&app_handle, &agent_id, &queued_prompts, &prompt).await {
+                                            continue;
+                                        }
                                         println!("[listener] Session not ready, prompt queued");
-                                        queued_prompts.push_back((prompt, target_session_id));
+                                        queued_prompts.push_back((prompt, target_session_id, generation, resources));
                                     }
                                 }
                                 Some(ListenerCommand::CancelPrompt) => {
+                                    // 取消时仍在等待用户选择的权限请求一并按取消应答
+                                    for (request_id, _) in pending_permissions.drain() {
+                                        if let Err(e) = answer_permission(
+                                            &mut conn,
+                                            &app_handle,
+                                            &agent_id,
+                                            request_id,
+                                            json!({ "outcome": "cancelled" }),
+                                            false,
+                                        )
+                                        .await
+                                        {
+                                            println!("[listener] Failed to respond to permission request: {}", e);
+                                        }
+                                    }
+                                    for (request_id, pending) in pending_file_writes.drain() {
+                                        if let Err(e) = resolve_file_write(
+                                            &mut conn,
+                                            &app_handle,
+                                            &agent_id,
+                                            request_id,
+                                            pending,
+                                            false,
+                                            false,
+                                        )
+                                        .await
+                                        {
+                                            println!("[listener] Failed to respond to file write: {}", e);
+                                        }
+                                    }
                                     if let Some(current_session_id) = &session_id {
                                         let cancel_id = next_rpc_id(&mut rpc_id_counter);
                                         let cancel_request = build_rpc_request(
@@ -547,6 +1416,8 @@ pub async fn message_listener_task(
                                         );
                                         if let Err(e) = conn.send_message(cancel_request).await {
                                             println!("[listener] Failed to send session/cancel: {}", e);
+                                        } else {
+                                            cancelling = true;
                                         }
                                     } else {
                                         println!("[listener] Session not ready, cancel ignored");
@@ -575,6 +1446,149 @@ pub async fn message_listener_task(
                                         let _ = response.send(Err("Session not ready".to_string()));
                                     }
                                 }
+                                Some(ListenerCommand::SetPaused { paused: pause, response }) => {
+                                    let mut send_failed = false;
+                                    if pause {
+                                        if paused.is_none() {
+                                            paused = Some(paused_stream_mode(&app_handle).await);
+                                            println!("[listener] Agent paused");
+                                        }
+                                    } else if paused.take().is_some() {
+                                        println!(
+                                            "[listener] Agent resumed, replaying {} buffered updates",
+                                            paused_updates.len()
+                                        );
+                                        for (display_update, update) in paused_updates.drain(..) {
+                                            handle_session_update(&app_handle, &agent_id, &display_update).await;
+                                            emit_command_registry_from_update(&app_handle, &agent_id, &update);
+                                        }
+
+                                        // 会话就绪时立即发送暂停期间排队的 prompt，否则等会话就绪后照常发送
+                                        let session_ready = initialize_request_id.is_none()
+                                            && session_new_request_id.is_none()
+                                            && session_load_request_id.is_none();
+                                        while let Some(current_session_id) = session_id.clone().filter(|_| session_ready) {
+                                            let Some((prompt, target_session_id, generation, resources)) =
+                                                queued_prompts.pop_front()
+                                            else {
+                                                break;
+                                            };
+                                            if let Some(target) = target_session_id.clone().filter(|target| *target != current_session_id) {
+                                                queued_prompts.push_front((prompt, target_session_id, generation, resources));
+                                                let load_id = next_rpc_id(&mut rpc_id_counter);
+                                                session_load_request_id = Some(load_id);
+                                                session_load_target_id = Some(target.clone());
+                                                session_load_for_initialize = false;
+                                                let load_request = build_rpc_request(
+                                                    load_id,
+                                                    "session/load",
+                                                    adapter.session_load_params(&workspace_path, &target, &mcp_servers, permission_mode),
+                                                );
+                                                if let Err(e) = conn.send_message(load_request).await {
+                                                    println!("[listener] Failed to send queued session/load: {}", e);
+                                                    send_failed = true;
+                                                }
+                                                break;
+                                            }
+
+                                            let prompt_id = next_rpc_id(&mut rpc_id_counter);
+                                            let prompt_request = build_rpc_request(
+                                                prompt_id,
+                                                "session/prompt",
+                                                adapter.prompt_params(&current_session_id, &prompt, &resources, generation.as_ref()),
+                                            );
+                                            if let Err(e) = conn.send_message(prompt_request).await {
+                                                println!("[listener] Failed to send queued prompt: {}", e);
+                                                queued_prompts.push_front((prompt, target_session_id, generation, resources));
+                                                send_failed = true;
+                                                break;
+                                            }
+                                            let pending =
+                                                begin_prompt_task(&app_handle, &agent_id, &current_session_id, prompt, generation, resources, local_workspace_path).await;
+                                            pending_prompts.insert(prompt_id, pending);
+                                        }
+                                    }
+                                    let _ = app_handle.emit_unless_locked(
+                                        "agent-paused",
+                                        json!({
+                                            "agentId": &agent_id,
+                                            "paused": paused.is_some(),
+                                        }),
+                                    );
+                                    let _ = response.send(Ok(()));
+                                    if send_failed {
+                                        break;
+                                    }
+                                }
+                                Some(ListenerCommand::ListSessions { response }) => {
+                                    let _ = response.send(AgentSessions {
+                                        current: session_id.clone(),
+                                        sessions: live_sessions.clone(),
+                                    });
+                                }
+                                Some(ListenerCommand::SwitchSession { session_id: target, response }) => {
+                                    let target = target.trim().to_string();
+                                    if target.is_empty() {
+                                        let _ = response.send(Err("Session id cannot be empty".to_string()));
+                                        continue;
+                                    }
+                                    if session_id.as_deref() == Some(target.as_str()) {
+                                        let _ = response.send(Ok(()));
+                                        continue;
+                                    }
+                                    if !pending_prompts.is_empty() {
+                                        let _ = response.send(Err(
+                                            "Agent is busy, wait for the current task to finish".to_string(),
+                                        ));
+                                        continue;
+                                    }
+                                    if session_load_request_id.is_some() || session_new_request_id.is_some() {
+                                        let _ = response.send(Err("Another session change is in progress".to_string()));
+                                        continue;
+                                    }
+                                    println!(
+                                        "[listener] Switching session: {} -> {}",
+                                        session_id.as_deref().unwrap_or("<none>"),
+                                        target
+                                    );
+                                    let load_id = next_rpc_id(&mut rpc_id_counter);
+                                    let load_request = build_rpc_request(
+                                        load_id,
+                                        "session/load",
+                                        adapter.session_load_params(&workspace_path, &target, &mcp_servers, permission_mode),
+                                    );
+                                    if let Err(e) = conn.send_message(load_request).await {
+                                        let _ = response.send(Err(format!("Failed to send session/load: {}", e)));
+                                        break;
+                                    }
+                                    session_load_request_id = Some(load_id);
+                                    session_load_target_id = Some(target);
+                                    session_load_for_initialize = false;
+                                    pending_session_switch = Some(response);
+                                }
+                                Some(ListenerCommand::SetPermissionMode { mode, response }) => {
+                                    if let Some(current_session_id) = &session_id {
+                                        let switch_id = next_rpc_id(&mut rpc_id_counter);
+                                        let switch_request = build_rpc_request(
+                                            switch_id,
+                                            "session/set_mode",
+                                            json!({
+                                                "sessionId": current_session_id,
+                                                "modeId": mode.as_str(),
+                                            }),
+                                        );
+                                        if let Err(e) = conn.send_message(switch_request).await {
+                                            let _ = response.send(Err(format!(
+                                                "Failed to send session/set_mode: {}",
+                                                e
+                                            )));
+                                            break;
+                                        }
+                                        pending_set_mode_requests.insert(switch_id, (response, mode));
+                                    } else {
+                                        let _ = response.send(Err("Session not ready".to_string()));
+                                    }
+                                }
                                 Some(ListenerCommand::SetThink {
                                     enable,
                                     config,
@@ -605,6 +1619,82 @@ pub async fn message_listener_task(
                                         let _ = response.send(Err("Session not ready".to_string()));
                                     }
                                 }
+                                Some(ListenerCommand::Authenticate { method_id, response }) => {
+                                    if pending_authenticate.is_some() {
+                                        let _ = response.send(Err("Authentication already in progress".to_string()));
+                                        continue;
+                                    }
+                                    let auth_id = next_rpc_id(&mut rpc_id_counter);
+                                    let auth_request = build_rpc_request(
+                                        auth_id,
+                                        "authenticate",
+                                        json!({
+                                            "methodId": method_id,
+                                        }),
+                                    );
+                                    if let Err(e) = conn.send_message(auth_request).await {
+                                        let _ = response.send(Err(format!(
+                                            "Failed to send authenticate: {}",
+                                            e
+                                        )));
+                                        break;
+                                    }
+                                    pending_authenticate = Some((auth_id, response));
+                                }
+                                Some(ListenerCommand::RespondPermission { request_id, option_id, response }) => {
+                                    let Some(pending) = pending_permissions.get(&request_id) else {
+                                        let _ = response.send(Err(format!(
+                                            "Permission request {} is no longer pending",
+                                            request_id
+                                        )));
+                                        continue;
+                                    };
+                                    let outcome = match outcome_for_option(&pending.params, option_id.as_deref()) {
+                                        Ok(outcome) => outcome,
+                                        Err(e) => {
+                                            let _ = response.send(Err(e));
+                                            continue;
+                                        }
+                                    };
+                                    pending_permissions.remove(&request_id);
+                                    if let Err(e) =
+                                        answer_permission(&mut conn, &app_handle, &agent_id, request_id, outcome, false).await
+                                    {
+                                        let _ = response.send(Err(format!(
+                                            "Failed to respond to permission request: {}",
+                                            e
+                                        )));
+                                        break;
+                                    }
+                                    let _ = response.send(Ok(()));
+                                }
+                                Some(ListenerCommand::RespondFileWrite { request_id, accept, response }) => {
+                                    let Some(pending) = pending_file_writes.remove(&request_id) else {
+                                        let _ = response.send(Err(format!(
+                                            "File write {} is no longer pending",
+                                            request_id
+                                        )));
+                                        continue;
+                                    };
+                                    if let Err(e) = resolve_file_write(
+                                        &mut conn,
+                                        &app_handle,
+                                        &agent_id,
+                                        request_id,
+                                        pending,
+                                        accept,
+                                        false,
+                                    )
+                                    .await
+                                    {
+                                        let _ = response.send(Err(format!(
+                                            "Failed to respond to file write: {}",
+                                            e
+                                        )));
+                                        break;
+                                    }
+                                    let _ = response.send(Ok(()));
+                                }
                                 None => {
                                     println!("[listener] Channel closed, exiting");
                                     return;
@@ -634,6 +1724,7 @@ pub async fn message_listener_task(
                                             println!("[listener] JSON parse failed: {}", raw);
                                             continue;
                                         };
+                                        let message_json = adapter.translate_incoming(message_json);
 
                                         if let Some(method) = message_json.get("method").and_then(Value::as_str) {
                                             let request_id = parse_rpc_id(&message_json);
@@ -641,14 +1732,97 @@ pub async fn message_listener_task(
 
                                             if method == "session/update" {
                                                 if let Some(update) = params.and_then(|p| p.get("update")) {
-                                                    handle_session_update(&app_handle, &agent_id, update).await;
-                                                    emit_command_registry_from_update(&app_handle, &agent_id, update);
+                                                    if let Some(chunk) = message_chunk_text(update) {
+                                                        streamed_message.push_str(&chunk);
+                                                        stream_wal.record_chunk(&app_handle, session_id.as_deref(), &chunk).await;
+                                                    }
+                                                    turn_activity.record_update(update);
+                                                    // 推送给前端的事件使用工作区相对路径
+                                                    let display_update =
+                                                        relativize_workspace_paths_in_value(update, &workspace_path);
+                                                    turn_progress.record_update(&display_update);
+                                                    if update.get("sessionUpdate").and_then(Value::as_str) == Some("tool_call") {
+                                                        dispatch_plugin_hook(
+                                                            &app_handle,
+                                                            PluginHook::ToolCall,
+                                                            Some(&workspace_path),
+                                                            json!({
+                                                                "agentId": &agent_id,
+                                                                "toolCall": &display_update,
+                                                            }),
+                                                        );
+                                                        // 未经权限请求直接执行的工具调用命中拒绝规则时取消本轮
+                                                        if let Some(rule) =
+                                                            deny_rule_for_tool_call(&app_handle, &workspace_path, update).await
+                                                        {
+                                                            let _ = app_handle.emit_unless_locked(
+                                                                "policy-violation",
+                                                                json!({
+                                                                    "agentId": &agent_id,
+                                                                    "toolCall": &display_update,
+                                                                    "ruleId": &rule.id,
+                                                                    "tool": &rule.tool,
+                                                                    "pattern": &rule.pattern,
+                                                                }),
+                                                            );
+                                                            if let Some(current_session_id) = &session_id {
+                                                                let cancel_id = next_rpc_id(&mut rpc_id_counter);
+                                                                let cancel_request = build_rpc_request(
+                                                                    cancel_id,
+                                                                    "session/cancel",
+                                                                    json!({
+                                                                        "sessionId": current_session_id,
+                                                                    }),
+                                                                );
+                                                                if let Err(e) = conn.send_message(cancel_request).await {
+                                                                    println!("[listener] Failed to send session/cancel: {}", e);
+                                                                } else {
+                                                                    println!("[listener] Tool call denied by rule {}, turn cancelled", rule.id);
+                                                                    cancelling = true;
+                                                                }
+                                                            }
+                                                        }
+                                                    }
+                                                    match paused {
+                                                        None => {
+                                                            handle_session_update(&app_handle, &agent_id, &display_update).await;
+                                                            emit_command_registry_from_update(&app_handle, &agent_id, update);
+                                                        }
+                                                        Some(PausedStreamMode::Buffer) => {
+                                                            buffer_paused_update(&mut paused_updates, display_update, update.clone());
+                                                        }
+                                                        Some(PausedStreamMode::Discard) => {}
+                                                    }
                                                 }
                                                 continue;
                                             }
 
                                             if let Some(request_id) = request_id {
-                                                handle_server_request(&mut conn, request_id, method, params).await;
+                                                if !local_workspace && method.starts_with("fs/") {
+                                                    let _ = send_rpc_error(&mut conn, request_id, -32601, "File access is not available for remote agents").await;
+                                                    continue;
+                                                }
+                                                if let Some(pending) = handle_server_request(
+                                                    &mut conn,
+                                                    &app_handle,
+                                                    &agent_id,
+                                                    &workspace_path,
+                                                    permission_mode,
+                                                    request_id,
+                                                    method,
+                                                    params,
+                                                )
+                                                .await
+                                                {
+                                                    match pending {
+                                                        PendingServerRequest::Permission(pending) => {
+                                                            pending_permissions.insert(request_id, pending);
+                                                        }
+                                                        PendingServerRequest::FileWrite(pending) => {
+                                                            pending_file_writes.insert(request_id, pending);
+                                                        }
+                                                    }
+                                                }
                                             } else {
                                                 println!("[listener] Notification method ignored: {}", method);
                                             }
@@ -665,7 +1839,10 @@ pub async fn message_listener_task(
                                             initialize_request_id = None;
 
                                             if let Some(error) = message_json.get("error") {
-                                                let _ = app_handle.emit(
+                                                if is_auth_error(error) {
+                                                    emit_auth_required(&app_handle, &agent_id, "acp", &error.to_string(), &auth_methods);
+                                                }
+                                                let _ = app_handle.emit_unless_locked(
                                                     "agent-error",
                                                     json!({
                                                         "agentId": &agent_id,
@@ -674,6 +1851,7 @@ pub async fn message_listener_task(
                                                 );
                                                 break;
                                             }
+                                            auth_methods = auth_methods_from_initialize(message_json.get("result"));
 
                                             if let Some(existing_session_id) = &session_id {
                                                 let session_load_id = next_rpc_id(&mut rpc_id_counter);
@@ -683,7 +1861,7 @@ pub async fn message_listener_task(
                                                 let session_load_request = build_rpc_request(
                                                     session_load_id,
                                                     "session/load",
-                                                    build_session_load_params(&workspace_path, existing_session_id),
+                                                    adapter.session_load_params(&workspace_path, existing_session_id, &mcp_servers, permission_mode),
                                                 );
 
                                                 if let Err(e) = conn.send_message(session_load_request).await {
@@ -697,7 +1875,7 @@ pub async fn message_listener_task(
                                                 let session_new_request = build_rpc_request(
                                                     session_new_id,
                                                     "session/new",
-                                                    build_session_new_params(&workspace_path),
+                                                    adapter.session_new_params(&workspace_path, None, &mcp_servers, permission_mode),
                                                 );
 
                                                 if let Err(e) = conn.send_message(session_new_request).await {
@@ -714,15 +1892,24 @@ pub async fn message_listener_task(
                                             let load_target = session_load_target_id.take();
                                             let load_was_initialize = session_load_for_initialize;
                                             session_load_for_initialize = false;
+                                            let switch_response = pending_session_switch.take();
 
                                             if let Some(error) = message_json.get("error") {
                                                 println!("[listener] session/load failed: {}", error);
+                                                // 主动切换失败时保留当前会话，不回退创建
+                                                if let Some(response) = switch_response {
+                                                    let _ = response.send(Err(format!("session/load failed: {}", error)));
+                                                    continue;
+                                                }
                                                 if load_was_initialize {
-                                                    let _ = app_handle.emit(
+                                                    let _ = app_handle.emit_unless_locked(
                                                         "stream-message",
                                                         json!({
                                                             "agentId": &agent_id,
-                                                            "content": format!("⚠️ 会话恢复失败，已回退创建新会话：{}", error),
+                                                            "content": message_style(&app_handle).await.decorate(
+                                                                SystemMarker::Warning,
+                                                                &format!("会话恢复失败，已回退创建新会话：{}", error),
+                                                            ),
                                                             "type": "system",
                                                         }),
                                                     );
@@ -733,7 +1920,7 @@ pub async fn message_listener_task(
                                                     let session_new_request = build_rpc_request(
                                                         session_new_id,
                                                         "session/new",
-                                                        build_session_new_params(&workspace_path),
+                                                        adapter.session_new_params(&workspace_path, None, &mcp_servers, permission_mode),
                                                     );
 
                                                     if let Err(e) = conn.send_message(session_new_request).await {
@@ -741,14 +1928,17 @@ pub async fn message_listener_task(
                                                         break;
                                                     }
                                                 } else if let Some(target) = load_target.as_ref() {
-                                                    let _ = app_handle.emit(
+                                                    let _ = app_handle.emit_unless_locked(
                                                         "stream-message",
                                                         json!({
                                                             "agentId": &agent_id,
-                                                            "content": format!(
-                                                                "⚠️ 目标会话恢复失败（{}），将回退创建会话：{}",
-                                                                target,
-                                                                error
+                                                            "content": message_style(&app_handle).await.decorate(
+                                                                SystemMarker::Warning,
+                                                                &format!(
+                                                                    "目标会话恢复失败（{}），将回退创建会话：{}",
+                                                                    target,
+                                                                    error
+                                                                ),
                                                             ),
                                                             "type": "system",
                                                         }),
@@ -760,9 +1950,11 @@ pub async fn message_listener_task(
                                                     let session_new_request = build_rpc_request(
                                                         session_new_id,
                                                         "session/new",
-                                                        build_session_new_params_with_id(
+                                                        adapter.session_new_params(
                                                             &workspace_path,
-                                                            target,
+                                                            Some(target),
+                                                            &mcp_servers,
+                                                            permission_mode,
                                                         ),
                                                     );
                                                     if let Err(e) = conn.send_message(session_new_request).await {
@@ -770,7 +1962,7 @@ pub async fn message_listener_task(
                                                             "[listener] Failed to send targeted session/new: {}",
                                                             e
                                                         );
-                                                        let _ = app_handle.emit(
+                                                        let _ = app_handle.emit_unless_locked(
                                                             "agent-error",
                                                             json!({
                                                                 "agentId": &agent_id,
@@ -780,7 +1972,7 @@ pub async fn message_listener_task(
                                                         break;
                                                     }
                                                 } else {
-                                                    let _ = app_handle.emit(
+                                                    let _ = app_handle.emit_unless_locked(
                                                         "agent-error",
                                                         json!({
                                                             "agentId": &agent_id,
@@ -794,26 +1986,45 @@ pub async fn message_listener_task(
                                             if let Some(target_session_id) = load_target {
                                                 session_id = Some(target_session_id.clone());
                                                 cached_session_id = Some(target_session_id.clone());
-                                                let _ = app_handle.emit(
-                                                    "acp-session",
-                                                    json!({
-                                                        "agentId": &agent_id,
-                                                        "sessionId": target_session_id,
-                                                    }),
-                                                );
+                                                remember_session(&mut live_sessions, &target_session_id);
+                                                publish_acp_session(&app_handle, &agent_id, &target_session_id).await;
                                             }
+                                            if let Some(response) = switch_response {
+                                                let _ = response.send(Ok(()));
+                                            }
+                                            retry_count = 0;
 
                                             if let Some(result) = message_json.get("result") {
                                                 emit_command_registry_payload(&app_handle, &agent_id, result);
                                                 emit_model_registry_payload(&app_handle, &agent_id, result);
                                             }
 
-                                            let message_text = if load_was_initialize {
-                                                "✅ iFlow ACP 会话已恢复"
-                                            } else {
-                                                "✅ 已切换到目标会话"
+                                            let reconnected = load_was_initialize
+                                                .then(|| interrupted_tasks.take())
+                                                .flatten();
+                                            let notice = match reconnected.as_ref() {
+                                                Some(tasks) if !tasks.is_empty() => format!(
+                                                    "连接已恢复，会话已重新载入，{} 个中断的请求将自动继续",
+                                                    tasks.len()
+                                                ),
+                                                Some(_) => "连接已恢复，会话已重新载入".to_string(),
+                                                None if load_was_initialize => "iFlow ACP 会话已恢复".to_string(),
+                                                None => "已切换到目标会话".to_string(),
                                             };
-                                            let _ = app_handle.emit(
+                                            if let Some(tasks) = reconnected {
+                                                let _ = app_handle.emit_unless_locked(
+                                                    "agent-reconnected",
+                                                    json!({
+                                                        "agentId": &agent_id,
+                                                        "sessionId": &session_id,
+                                                        "resumedTasks": tasks,
+                                                    }),
+                                                );
+                                            }
+                                            let message_text = message_style(&app_handle)
+                                                .await
+                                                .decorate(SystemMarker::Success, &notice);
+                                            let _ = app_handle.emit_unless_locked(
                                                 "stream-message",
                                                 json!({
                                                     "agentId": &agent_id,
@@ -822,14 +2033,16 @@ pub async fn message_listener_task(
                                                 }),
                                             );
 
-                                            while let Some((prompt, target_session_id)) =
-                                                queued_prompts.pop_front()
+                                            while let Some((prompt, target_session_id, generation, resources)) =
+                                                if paused.is_some() { None } else { queued_prompts.pop_front() }
                                             {
                                                 if let Some(target) = target_session_id.as_ref() {
                                                     if session_id.as_deref() != Some(target.as_str()) {
                                                         queued_prompts.push_front((
                                                             prompt,
                                                             target_session_id.clone(),
+                                                            generation,
+                                                            resources,
                                                         ));
                                                         if session_load_request_id.is_none() {
                                                             let load_id = next_rpc_id(&mut rpc_id_counter);
@@ -839,9 +2052,11 @@ pub async fn message_listener_task(
                                                             let load_request = build_rpc_request(
                                                                 load_id,
                                                                 "session/load",
-                                                                build_session_load_params(
+                                                                adapter.session_load_params(
                                                                     &workspace_path,
                                                                     target,
+                                                                    &mcp_servers,
+                                                                    permission_mode,
                                                                 ),
                                                             );
                                                             if let Err(e) = conn.send_message(load_request).await {
@@ -861,19 +2076,31 @@ pub async fn message_listener_task(
                                                     let prompt_request = build_rpc_request(
                                                         prompt_id,
                                                         "session/prompt",
-                                                        build_prompt_params(current_session_id, &prompt),
+                                                        adapter.prompt_params(current_session_id, &prompt, &resources, generation.as_ref()),
                                                     );
                                                     if let Err(e) = conn.send_message(prompt_request).await {
                                                         println!("[listener] Failed to flush prompt queue: {}", e);
                                                         queued_prompts.push_front((
                                                             prompt,
                                                             target_session_id,
+                                                            generation,
+                                                            resources,
                                                         ));
                                                         break;
                                                     }
-                                                    pending_prompt_request_ids.insert(prompt_id);
+                                                    let pending = begin_prompt_task(
+                                                        &app_handle,
+                                                        &agent_id,
+                                                        current_session_id,
+                                                        prompt,
+                                                        generation,
+                                                        resources,
+                                                        local_workspace_path,
+                                                    )
+                                                    .await;
+                                                    pending_prompts.insert(prompt_id, pending);
                                                 } else {
-                                                    queued_prompts.push_front((prompt, target_session_id));
+                                                    queued_prompts.push_front((prompt, target_session_id, generation, resources));
                                                     break;
                                                 }
                                             }
@@ -886,7 +2113,13 @@ pub async fn message_listener_task(
                                             let requested_session_id = session_new_target_id.take();
 
                                             if let Some(error) = message_json.get("error") {
-                                                let _ = app_handle.emit(
+                                                if is_auth_error(error) {
+                                                    // 保持连接，等待前端通过 authenticate_iflow 完成登录后重新创建会话
+                                                    emit_auth_required(&app_handle, &agent_id, "acp", &error.to_string(), &auth_methods);
+                                                    session_new_after_auth = Some(requested_session_id);
+                                                    continue;
+                                                }
+                                                let _ = app_handle.emit_unless_locked(
                                                     "agent-error",
                                                     json!({
                                                         "agentId": &agent_id,
@@ -910,9 +2143,12 @@ pub async fn message_listener_task(
                                                 .map(|s| s.to_string())
                                                 .or(requested_session_id);
                                             cached_session_id = session_id.clone();
+                                            retry_count = 0;
+                                            // 会话未能恢复时中断的请求会因会话变化而失败，不再视为断线重连
+                                            interrupted_tasks = None;
 
                                             if session_id.is_none() {
-                                                let _ = app_handle.emit(
+                                                let _ = app_handle.emit_unless_locked(
                                                     "agent-error",
                                                     json!({
                                                         "agentId": &agent_id,
@@ -928,24 +2164,21 @@ pub async fn message_listener_task(
                                             }
 
                                             if let Some(current_session_id) = &session_id {
-                                                let _ = app_handle.emit(
-                                                    "acp-session",
-                                                    json!({
-                                                        "agentId": &agent_id,
-                                                        "sessionId": current_session_id,
-                                                    }),
-                                                );
+                                                remember_session(&mut live_sessions, current_session_id);
+                                                publish_acp_session(&app_handle, &agent_id, current_session_id).await;
                                             }
 
                                             if let Some(current_session_id) = &session_id {
-                                                while let Some((prompt, target_session_id)) =
-                                                    queued_prompts.pop_front()
+                                                while let Some((prompt, target_session_id, generation, resources)) =
+                                                    if paused.is_some() { None } else { queued_prompts.pop_front() }
                                                 {
                                                     if let Some(target) = target_session_id.as_ref() {
                                                         if session_id.as_deref() != Some(target.as_str()) {
                                                             queued_prompts.push_front((
                                                                 prompt,
                                                                 target_session_id.clone(),
+                                                                generation,
+                                                                resources,
                                                             ));
                                                             if session_load_request_id.is_none() {
                                                                 let load_id = next_rpc_id(&mut rpc_id_counter);
@@ -955,9 +2188,11 @@ pub async fn message_listener_task(
                                                                 let load_request = build_rpc_request(
                                                                     load_id,
                                                                     "session/load",
-                                                                    build_session_load_params(
+                                                                    adapter.session_load_params(
                                                                         &workspace_path,
                                                                         target,
+                                                                        &mcp_servers,
+                                                                        permission_mode,
                                                                     ),
                                                                 );
                                                                 if let Err(e) = conn.send_message(load_request).await {
@@ -975,32 +2210,68 @@ pub async fn message_listener_task(
                                                     let prompt_request = build_rpc_request(
                                                         prompt_id,
                                                         "session/prompt",
-                                                        build_prompt_params(current_session_id, &prompt),
+                                                        adapter.prompt_params(current_session_id, &prompt, &resources, generation.as_ref()),
                                                     );
                                                     if let Err(e) = conn.send_message(prompt_request).await {
                                                         println!("[listener] Failed to flush prompt queue: {}", e);
                                                         queued_prompts.push_front((
                                                             prompt,
                                                             target_session_id,
+                                                            generation,
+                                                            resources,
                                                         ));
                                                         break;
                                                     }
-                                                    pending_prompt_request_ids.insert(prompt_id);
+                                                    let pending = begin_prompt_task(
+                                                        &app_handle,
+                                                        &agent_id,
+                                                        current_session_id,
+                                                        prompt,
+                                                        generation,
+                                                        resources,
+                                                        local_workspace_path,
+                                                    )
+                                                    .await;
+                                                    pending_prompts.insert(prompt_id, pending);
                                                 }
                                             }
 
                                             continue;
                                         }
 
-                                        if pending_prompt_request_ids.remove(&response_id) {
+                                        if let Some(pending) = pending_prompts.remove(&response_id) {
+                                            let completed_message = std::mem::take(&mut streamed_message);
+                                            stream_wal.finish().await;
+                                            let completed_activity = std::mem::take(&mut turn_activity);
+                                            turn_progress = TaskProgress::default();
                                             if let Some(error) = message_json.get("error") {
-                                                let _ = app_handle.emit(
-                                                    "agent-error",
-                                                    json!({
-                                                        "agentId": &agent_id,
-                                                        "error": format!("session/prompt failed: {}", error),
-                                                    }),
-                                                );
+                                                if is_auth_error(error) {
+                                                    emit_auth_required(&app_handle, &agent_id, "acp", &error.to_string(), &auth_methods);
+                                                    fail_prompt_task(
+                                                        &app_handle,
+                                                        &agent_id,
+                                                        &pending,
+                                                        format!("session/prompt failed: {}", error),
+                                                    )
+                                                    .await;
+                                                } else if is_transient_prompt_error(error) {
+                                                    retry_or_fail_prompt(
+                                                        &app_handle,
+                                                        &agent_id,
+                                                        pending,
+                                                        error.to_string(),
+                                                        &mut scheduled_retries,
+                                                    )
+                                                    .await;
+                                                } else {
+                                                    fail_prompt_task(
+                                                        &app_handle,
+                                                        &agent_id,
+                                                        &pending,
+                                                        format!("session/prompt failed: {}", error),
+                                                    )
+                                                    .await;
+                                                }
                                                 continue;
                                             }
 
@@ -1009,7 +2280,143 @@ pub async fn message_listener_task(
                                                 .and_then(|r| r.get("stopReason"))
                                                 .and_then(Value::as_str)
                                                 .unwrap_or("completed");
-                                            emit_task_finish(&app_handle, &agent_id, reason).await;
+                                            if let Err(e) =
+                                                record_task_finish(&app_handle, &pending.task_id, Some(reason), None).await
+                                            {
+                                                println!("[listener] Failed to record task finish: {}", e);
+                                            }
+                                            emit_task_finish(
+                                                &app_handle,
+                                                &agent_id,
+                                                &pending.task_id,
+                                                reason,
+                                                &completed_message,
+                                            )
+                                            .await;
+                                            if reason != "cancelled" {
+                                                notify_task_finish(
+                                                    &app_handle,
+                                                    &workspace_path,
+                                                    &agent_id,
+                                                    reason,
+                                                    &completed_message,
+                                                );
+                                            }
+                                            dispatch_plugin_hook(
+                                                &app_handle,
+                                                PluginHook::TaskFinish,
+                                                Some(&workspace_path),
+                                                json!({
+                                                    "agentId": &agent_id,
+                                                    "taskId": &pending.task_id,
+                                                    "stopReason": reason,
+                                                    "message": &completed_message,
+                                                }),
+                                            );
+                                            if local_workspace {
+                                                spawn_diagram_rendering(
+                                                    &app_handle,
+                                                    &agent_id,
+                                                    &workspace_path,
+                                                    &completed_message,
+                                                );
+                                            }
+                                            if local_workspace
+                                                && reason != "cancelled"
+                                                && !completed_message.trim().is_empty()
+                                            {
+                                                spawn_postprocess(
+                                                    &app_handle,
+                                                    &agent_id,
+                                                    &workspace_path,
+                                                    &pending.task_id,
+                                                    &completed_message,
+                                                );
+                                            }
+                                            if reason == "end_turn" && !completed_message.trim().is_empty() {
+                                                spawn_language_check(
+                                                    &app_handle,
+                                                    &agent_id,
+                                                    &workspace_path,
+                                                    &pending.task_id,
+                                                    &completed_message,
+                                                );
+                                                if local_workspace {
+                                                    spawn_annotation_extraction(
+                                                        &app_handle,
+                                                        &agent_id,
+                                                        &workspace_path,
+                                                        &pending.task_id,
+                                                        &completed_message,
+                                                    );
+                                                }
+                                            }
+                                            if local_workspace && reason != "cancelled" {
+                                                spawn_suggested_actions(
+                                                    &app_handle,
+                                                    &agent_id,
+                                                    &workspace_path,
+                                                    completed_activity,
+                                                    pending.git_baseline,
+                                                );
+                                            }
+                                            if reason == "end_turn" && !completed_message.trim().is_empty() {
+                                                spawn_session_title(
+                                                    &app_handle,
+                                                    &agent_id,
+                                                    &ws_url,
+                                                    &workspace_path,
+                                                    &pending.session_id,
+                                                    &pending.prompt,
+                                                    &completed_message,
+                                                );
+                                            }
+                                            let verify_answers = app_handle
+                                                .state::<AppState>()
+                                                .settings
+                                                .read()
+                                                .await
+                                                .verify_answers;
+                                            if verify_answers
+                                                && reason == "end_turn"
+                                                && !completed_message.trim().is_empty()
+                                            {
+                                                spawn_verification(
+                                                    &app_handle,
+                                                    &agent_id,
+                                                    &ws_url,
+                                                    &workspace_path,
+                                                    &pending.task_id,
+                                                    &pending.prompt,
+                                                    &completed_message,
+                                                );
+                                            }
+                                            continue;
+                                        }
+
+                                        if pending_authenticate.as_ref().map(|(id, _)| *id) == Some(response_id) {
+                                            let Some((_, response)) = pending_authenticate.take() else {
+                                                continue;
+                                            };
+                                            if let Some(error) = message_json.get("error") {
+                                                let _ = response.send(Err(format!("authenticate failed: {}", error)));
+                                                continue;
+                                            }
+                                            let _ = response.send(Ok(()));
+                                            if let Some(target) = session_new_after_auth.take() {
+                                                let session_new_id = next_rpc_id(&mut rpc_id_counter);
+                                                session_new_request_id = Some(session_new_id);
+                                                let params = match target.as_deref() {
+                                                    Some(target) => adapter.session_new_params(&workspace_path, Some(target), &mcp_servers, permission_mode),
+                                                    None => adapter.session_new_params(&workspace_path, None, &mcp_servers, permission_mode),
+                                                };
+                                                session_new_target_id = target;
+                                                let session_new_request = build_rpc_request(session_new_id, "session/new", params);
+                                                if let Err(e) = conn.send_message(session_new_request).await {
+                                                    println!("[listener] Failed to send session/new: {}", e);
+                                                    break;
+                                                }
+                                            }
                                             continue;
                                         }
 
@@ -1032,7 +2439,7 @@ pub async fn message_listener_task(
                                                 .filter(|value| !value.is_empty())
                                                 .unwrap_or(requested_model);
 
-                                            let _ = app_handle.emit(
+                                            let _ = app_handle.emit_unless_locked(
                                                 "model-registry",
                                                 json!({
                                                     "agentId": &agent_id,
@@ -1044,6 +2451,22 @@ pub async fn message_listener_task(
                                             continue;
                                         }
 
+                                        if let Some((response, requested_mode)) =
+                                            pending_set_mode_requests.remove(&response_id)
+                                        {
+                                            if let Some(error) = message_json.get("error") {
+                                                let _ = response.send(Err(format!(
+                                                    "session/set_mode failed: {}",
+                                                    error
+                                                )));
+                                                continue;
+                                            }
+                                            // 重连后的 session/new、session/load 沿用新模式
+                                            permission_mode = requested_mode;
+                                            let _ = response.send(Ok(()));
+                                            continue;
+                                        }
+
                                         if let Some((response, requested_enable, requested_config)) =
                                             pending_set_think_requests.remove(&response_id)
                                         {
@@ -1068,7 +2491,7 @@ pub async fn message_listener_task(
                                                 .filter(|value| !value.is_empty())
                                                 .unwrap_or(requested_config);
 
-                                            let _ = app_handle.emit(
+                                            let _ = app_handle.emit_unless_locked(
                                                 "think-status-changed",
                                                 json!({
                                                     "agentId": &agent_id,
@@ -1093,25 +2516,87 @@ pub async fn message_listener_task(
                         }
                     }
                 }
-            }
-            Err(e) => {
-                retry_count += 1;
-                println!("[listener] Connection failed: {}", e);
-                if retry_count >= max_retries {
-                    let _ = app_handle.emit(
-                        "agent-error",
+
+                // 连接已断开，未应答的权限请求随之失效
+                for (request_id, _) in pending_permissions.drain() {
+                    let _ = app_handle.emit_unless_locked(
+                        "permission-resolved",
                         json!({
                             "agentId": &agent_id,
-                            "error": format!("Failed after {} attempts: {}", max_retries, e),
+                            "requestId": request_id,
+                            "outcome": { "outcome": "cancelled" },
+                            "timedOut": false,
                         }),
                     );
-                    break;
                 }
-                tokio::time::sleep(Duration::from_secs(2)).await;
+                for (request_id, _) in pending_file_writes.drain() {
+                    let _ = app_handle.emit_unless_locked(
+                        "file-write-resolved",
+                        json!({
+                            "agentId": &agent_id,
+                            "requestId": request_id,
+                            "accepted": false,
+                            "timedOut": false,
+                        }),
+                    );
+                }
+                if let Some(response) = pending_session_switch.take() {
+                    let _ =
+                        response.send(Err("Connection lost while switching session".to_string()));
+                }
+
+                // 连接中断时仍在等待响应的 prompt 在重连并恢复会话后原样重发，不计入重试次数
+                if !pending_prompts.is_empty() {
+                    streamed_message.clear();
+                    stream_wal.finish().await;
+                    turn_activity = TurnActivity::default();
+                    turn_progress = TaskProgress::default();
+                }
+                let tasks = interrupted_tasks.get_or_insert_with(Vec::new);
+                for (_, pending) in pending_prompts.drain() {
+                    println!(
+                        "[listener] Prompt {} interrupted by disconnect",
+                        pending.task_id
+                    );
+                    tasks.push(pending.task_id.clone());
+                    scheduled_retries.push(ScheduledRetry {
+                        due: Instant::now(),
+                        pending,
+                    });
+                }
+                retry_count += 1;
+                last_error = "connection lost".to_string();
+            }
+            Err(e) => {
+                retry_count += 1;
+                println!("[listener] Connection failed: {}", e);
+                last_error = e;
             }
         }
     }
 
+    if retry_count >= max_retries {
+        set_agent_status(&app_handle, &agent_id, AgentStatus::Error).await;
+        let _ = app_handle.emit_unless_locked(
+            "agent-error",
+            json!({
+                "agentId": &agent_id,
+                "error": format!("Failed after {} attempts: {}", max_retries, last_error),
+            }),
+        );
+        for retry in scheduled_retries.drain(..) {
+            fail_prompt_task(
+                &app_handle,
+                &agent_id,
+                &retry.pending,
+                format!("session/prompt failed: {}", last_error),
+            )
+            .await;
+        }
+    }
+
+    stream_wal.finish().await;
+    clear_spilled_outputs(&agent_id).await;
     println!("[listener] Stopped for agent: {}", agent_id);
 }
 
@@ -1119,7 +2604,61 @@ pub async fn message_listener_task(
 mod tests {
     use serde_json::json;
 
-    use super::{normalized_command_entries, normalized_mcp_entries, text_from_json_value};
+    use super::{
+        buffer_paused_update, listener_status, normalized_command_entries, normalized_mcp_entries,
+        plan_exit_approved, remember_session, text_from_json_value, MAX_PAUSED_UPDATES,
+    };
+    use crate::models::{AgentStatus, PermissionMode};
+
+    #[test]
+    fn plan_exit_is_denied_only_in_plan_mode() {
+        assert!(!plan_exit_approved(PermissionMode::Plan));
+        assert!(plan_exit_approved(PermissionMode::AcceptEdits));
+        assert!(plan_exit_approved(PermissionMode::Yolo));
+    }
+
+    #[test]
+    fn listener_state_maps_to_agent_status() {
+        assert_eq!(
+            listener_status(false, false, true, false),
+            AgentStatus::Connecting
+        );
+        assert_eq!(
+            listener_status(false, true, false, false),
+            AgentStatus::Reconnecting
+        );
+        assert_eq!(listener_status(true, false, true, false), AgentStatus::Busy);
+        assert_eq!(
+            listener_status(true, false, true, true),
+            AgentStatus::Stopping
+        );
+        assert_eq!(
+            listener_status(true, true, false, true),
+            AgentStatus::Connected
+        );
+    }
+
+    #[test]
+    fn paused_updates_keep_the_latest() {
+        let mut buffer = std::collections::VecDeque::new();
+        for index in 0..MAX_PAUSED_UPDATES + 2 {
+            buffer_paused_update(&mut buffer, json!(index), json!(index));
+        }
+        assert_eq!(buffer.len(), MAX_PAUSED_UPDATES);
+        assert_eq!(
+            buffer.front().map(|(display, _)| display.clone()),
+            Some(json!(2))
+        );
+    }
+
+    #[test]
+    fn remembered_sessions_move_to_the_end() {
+        let mut sessions = Vec::new();
+        remember_session(&mut sessions, "s1");
+        remember_session(&mut sessions, "s2");
+        remember_session(&mut sessions, "s1");
+        assert_eq!(sessions, vec!["s2".to_string(), "s1".to_string()]);
+    }
 
     #[test]
     fn parse_text_from_json_value_array() {
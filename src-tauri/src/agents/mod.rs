@@ -1,2 +1,15 @@
+pub mod adapter;
+pub mod autostart;
+pub mod claude_adapter;
+pub mod fs_sandbox;
+pub mod gemini_adapter;
+pub mod heartbeat;
 pub mod iflow_adapter;
+pub mod oneshot;
+pub mod registry;
+pub mod remote;
 pub mod session_params;
+pub mod spawn_env;
+pub mod stdio_bridge;
+pub mod tls;
+pub mod write_preview;
@@ -0,0 +1,250 @@
+//! 一次性 ACP 请求：独立连接同一 iFlow 进程，新建临时会话发送单条 prompt 并收集完整回复，
+//! 不经过主监听任务，也不向前端推送流式事件。
+use serde_json::{json, Value};
+use tokio::time::{timeout, Duration};
+
+use super::iflow_adapter::{
+    build_rpc_request, parse_rpc_id, send_rpc_error, send_rpc_result, AcpConnection,
+};
+use super::session_params::{
+    build_initialize_params, build_prompt_params, build_session_new_params,
+};
+use crate::models::PermissionMode;
+use crate::router::message_chunk_text;
+
+/// 一次性会话只规划不执行：yolo 下 iFlow 不发 session/request_permission，工具会直接运行
+const ONESHOT_PERMISSION_MODE: PermissionMode = PermissionMode::Plan;
+
+const CANCEL_REQUEST_ID: i64 = 5;
+
+/// 一次性会话中出现工具调用时的处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OneshotToolPolicy {
+    /// 计划模式下 Agent 自行决定的只读工具照常运行
+    ReadOnly,
+    /// 出现任何 tool_call 即取消会话并返回错误；prompt 中含未经清洗的对话内容时使用
+    Reject,
+}
+
+fn oneshot_session_new_params(workspace_path: &str) -> Value {
+    build_session_new_params(workspace_path, &[], ONESHOT_PERMISSION_MODE)
+}
+
+fn is_tool_call(update: &Value) -> bool {
+    update.get("sessionUpdate").and_then(Value::as_str) == Some("tool_call")
+}
+
+/// 等待指定请求的响应；期间收集目标会话的助手正文，并拒绝 Agent 发起的权限/文件请求
+async fn await_response(
+    conn: &mut AcpConnection,
+    request_id: i64,
+    session_id: Option<&str>,
+    tools: OneshotToolPolicy,
+    collected: &mut String,
+) -> Result<Value, String> {
+    loop {
+        let Some(raw) = conn.receive_message().await? else {
+            return Err("ACP connection closed".to_string());
+        };
+        if raw.trim().is_empty() {
+            continue;
+        }
+        let Ok(message) = serde_json::from_str::<Value>(&raw) else {
+            continue;
+        };
+
+        if let Some(method) = message.get("method").and_then(Value::as_str) {
+            let params = message.get("params");
+            if method == "session/update" {
+                let same_session = session_id.is_some()
+                    && params
+                        .and_then(|p| p.get("sessionId"))
+                        .and_then(Value::as_str)
+                        == session_id;
+                let update = params
+                    .filter(|_| same_session)
+                    .and_then(|p| p.get("update"));
+                if tools == OneshotToolPolicy::Reject && update.is_some_and(is_tool_call) {
+                    if let Some(session_id) = session_id {
+                        let _ = conn
+                            .send_message(build_rpc_request(
+                                CANCEL_REQUEST_ID,
+                                "session/cancel",
+                                json!({ "sessionId": session_id }),
+                            ))
+                            .await;
+                    }
+                    return Err("Tool call rejected in one-shot session".to_string());
+                }
+                if let Some(chunk) = update.and_then(message_chunk_text) {
+                    collected.push_str(&chunk);
+                }
+                continue;
+            }
+
+            // 一次性会话只读，不授予任何工具权限
+            if let Some(server_request_id) = parse_rpc_id(&message) {
+                let result = if method == "session/request_permission" {
+                    send_rpc_result(
+                        conn,
+                        server_request_id,
+                        json!({ "outcome": { "outcome": "cancelled" } }),
+                    )
+                    .await
+                } else {
+                    send_rpc_error(
+                        conn,
+                        server_request_id,
+                        -32601,
+                        "Not supported in one-shot session",
+                    )
+                    .await
+                };
+                result?;
+            }
+            continue;
+        }
+
+        if parse_rpc_id(&message) != Some(request_id) {
+            continue;
+        }
+        if let Some(error) = message.get("error") {
+            return Err(error.to_string());
+        }
+        return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+    }
+}
+
+/// 完成 initialize 并新建临时会话，返回会话 ID
+async fn open_session(
+    conn: &mut AcpConnection,
+    workspace_path: &str,
+    tools: OneshotToolPolicy,
+    collected: &mut String,
+) -> Result<String, String> {
+    conn.send_message(build_rpc_request(
+        1,
+        "initialize",
+        build_initialize_params(false),
+    ))
+    .await?;
+    await_response(conn, 1, None, tools, collected)
+        .await
+        .map_err(|e| format!("initialize failed: {}", e))?;
+
+    conn.send_message(build_rpc_request(
+        2,
+        "session/new",
+        oneshot_session_new_params(workspace_path),
+    ))
+    .await?;
+    let session = await_response(conn, 2, None, tools, collected)
+        .await
+        .map_err(|e| format!("session/new failed: {}", e))?;
+    session
+        .get("sessionId")
+        .and_then(Value::as_str)
+        .map(|session_id| session_id.to_string())
+        .ok_or_else(|| "session/new returned no sessionId".to_string())
+}
+
+async fn run_oneshot(
+    ws_url: &str,
+    workspace_path: &str,
+    model: Option<&str>,
+    prompt: &str,
+    tools: OneshotToolPolicy,
+) -> Result<String, String> {
+    let mut conn = AcpConnection::connect(ws_url).await?;
+    let mut collected = String::new();
+    let session_id = open_session(&mut conn, workspace_path, tools, &mut collected).await?;
+
+    if let Some(model) = model {
+        conn.send_message(build_rpc_request(
+            3,
+            "session/set_model",
+            json!({
+                "sessionId": session_id,
+                "modelId": model,
+            }),
+        ))
+        .await?;
+        await_response(&mut conn, 3, Some(&session_id), tools, &mut collected)
+            .await
+            .map_err(|e| format!("session/set_model failed: {}", e))?;
+    }
+
+    conn.send_message(build_rpc_request(
+        4,
+        "session/prompt",
+        build_prompt_params(&session_id, prompt, &[], None),
+    ))
+    .await?;
+    await_response(&mut conn, 4, Some(&session_id), tools, &mut collected)
+        .await
+        .map_err(|e| format!("session/prompt failed: {}", e))?;
+
+    Ok(collected)
+}
+
+/// 在临时 ACP 会话中发送单条 prompt，返回完整回复文本
+pub(crate) async fn run_oneshot_prompt(
+    ws_url: &str,
+    workspace_path: &str,
+    model: Option<&str>,
+    prompt: &str,
+    tools: OneshotToolPolicy,
+    timeout_secs: u64,
+) -> Result<String, String> {
+    timeout(
+        Duration::from_secs(timeout_secs),
+        run_oneshot(ws_url, workspace_path, model, prompt, tools),
+    )
+    .await
+    .map_err(|_| format!("One-shot prompt timeout after {} seconds", timeout_secs))?
+}
+
+/// 只做 ACP 握手并新建临时会话（不发送 prompt），返回会话 ID；用于自检
+pub(crate) async fn probe_acp_session(
+    ws_url: &str,
+    workspace_path: &str,
+    timeout_secs: u64,
+) -> Result<String, String> {
+    timeout(Duration::from_secs(timeout_secs), async {
+        let mut conn = AcpConnection::connect(ws_url).await?;
+        open_session(
+            &mut conn,
+            workspace_path,
+            OneshotToolPolicy::Reject,
+            &mut String::new(),
+        )
+        .await
+    })
+    .await
+    .map_err(|_| format!("ACP handshake timeout after {} seconds", timeout_secs))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oneshot_sessions_open_in_plan_mode() {
+        let params = oneshot_session_new_params("/ws");
+        assert_eq!(params["cwd"], "/ws");
+        assert_eq!(params["settings"]["permission_mode"], "plan");
+    }
+
+    #[test]
+    fn only_tool_call_updates_are_rejected() {
+        assert!(is_tool_call(
+            &json!({ "sessionUpdate": "tool_call", "kind": "execute" })
+        ));
+        assert!(!is_tool_call(
+            &json!({ "sessionUpdate": "tool_call_update" })
+        ));
+        assert!(!is_tool_call(
+            &json!({ "sessionUpdate": "agent_message_chunk" })
+        ));
+    }
+}
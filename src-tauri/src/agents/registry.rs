@@ -0,0 +1,327 @@
+//! Agent 登记表：连接后的 WebSocket 型 Agent（进程 PID、端口、工作区等）写入应用数据目录，
+//! 应用重启后 `restore_agents` 据此重新接管进程仍存活、端口可连接的 Agent，不必逐个重新连接。
+//! stdio 型 Agent 的桥接运行在应用进程内，随应用退出失效，不写入登记表。
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, State};
+use tokio::fs;
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+
+use super::adapter::{adapter_for_type, register_agent_listener, AgentLaunch};
+use super::remote::REMOTE_AGENT_TYPE;
+use crate::database::workspace_mcp_servers;
+use crate::models::PermissionMode;
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag};
+
+const PORT_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RegisteredAgent {
+    pub agent_id: String,
+    pub name: String,
+    pub agent_type: String,
+    /// 本机进程（iFlow 或 ssh）；直接连接已有端口时为空
+    #[serde(default)]
+    pub pid: Option<u32>,
+    pub port: u16,
+    pub ws_url: String,
+    pub executable: String,
+    pub workspace_path: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub permission_mode: PermissionMode,
+    pub registered_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoredAgent {
+    pub agent_id: String,
+    pub agent_type: String,
+    pub workspace_path: String,
+    pub model: Option<String>,
+    pub restored: bool,
+    /// 未能接管的原因
+    pub error: Option<String>,
+}
+
+fn registry_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-agent-registry-{}.json", storage_env_tag())))
+}
+
+async fn read_registry_from_path(path: &Path) -> Result<HashMap<String, RegisteredAgent>, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(HashMap::new());
+            }
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse agent registry: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(err) => Err(format!("Failed to read agent registry: {}", err)),
+    }
+}
+
+async fn write_registry_to_path(
+    path: &Path,
+    registry: &HashMap<String, RegisteredAgent>,
+) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create agent registry dir: {}", e))?;
+    }
+    let payload = serde_json::to_vec(registry)
+        .map_err(|e| format!("Failed to encode agent registry: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write agent registry: {}", e))
+}
+
+async fn update_registry(
+    app_handle: &tauri::AppHandle,
+    apply: impl FnOnce(&mut HashMap<String, RegisteredAgent>),
+) -> Result<(), String> {
+    let state = app_handle.state::<AppState>();
+    let _guard = state.agent_registry_lock.lock().await;
+    let path = registry_path(app_handle)?;
+    let mut registry = read_registry_from_path(&path).await?;
+    apply(&mut registry);
+    write_registry_to_path(&path, &registry).await
+}
+
+/// 登记表中的 Agent ID（读取失败时为空）
+pub(crate) async fn registered_agent_ids(app_handle: &tauri::AppHandle) -> Vec<String> {
+    let Ok(path) = registry_path(app_handle) else {
+        return Vec::new();
+    };
+    match read_registry_from_path(&path).await {
+        Ok(registry) => registry.into_keys().collect(),
+        Err(e) => {
+            println!("[agent-registry] {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// 登记表中该 Agent 的工作区（未登记或读取失败时为 None）
+pub(crate) async fn registered_workspace_path(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+) -> Option<String> {
+    let path = registry_path(app_handle).ok()?;
+    read_registry_from_path(&path)
+        .await
+        .ok()?
+        .remove(agent_id)
+        .map(|entry| entry.workspace_path)
+}
+
+pub(crate) async fn record_agent(app_handle: &tauri::AppHandle, entry: RegisteredAgent) {
+    if let Err(e) = update_registry(app_handle, |registry| {
+        registry.insert(entry.agent_id.clone(), entry);
+    })
+    .await
+    {
+        println!("[agent-registry] {}", e);
+    }
+}
+
+pub(crate) async fn forget_agent(app_handle: &tauri::AppHandle, agent_id: &str) {
+    if let Err(e) = update_registry(app_handle, |registry| {
+        registry.remove(agent_id);
+    })
+    .await
+    {
+        println!("[agent-registry] {}", e);
+    }
+}
+
+#[cfg(unix)]
+async fn process_alive(pid: u32) -> bool {
+    tokio::process::Command::new("kill")
+        .arg("-0")
+        .arg(pid.to_string())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .await
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+// 其他平台只检查端口
+#[cfg(not(unix))]
+async fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+fn probe_address(ws_url: &str) -> Result<String, String> {
+    let url = url::Url::parse(ws_url).map_err(|e| format!("Invalid URL: {}", e))?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| format!("URL has no host: {}", ws_url))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| format!("URL has no port: {}", ws_url))?;
+    Ok(format!("{}:{}", host, port))
+}
+
+/// 进程存活且端口可连接时可以接管
+async fn check_adoptable(entry: &RegisteredAgent) -> Result<(), String> {
+    if let Some(pid) = entry.pid {
+        if !process_alive(pid).await {
+            return Err(format!("Process {} is no longer running", pid));
+        }
+    }
+    let address = probe_address(&entry.ws_url)?;
+    match timeout(PORT_PROBE_TIMEOUT, TcpStream::connect(&address)).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(format!("{} is not responding: {}", address, e)),
+        Err(_) => Err(format!("{} is not responding", address)),
+    }
+}
+
+/// 接管一条登记的 Agent；无法接管时从登记表移除
+async fn adopt_entry(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+    entry: RegisteredAgent,
+) -> RestoredAgent {
+    let outcome = check_adoptable(&entry).await;
+    let mut result = RestoredAgent {
+        agent_id: entry.agent_id.clone(),
+        agent_type: entry.agent_type.clone(),
+        workspace_path: entry.workspace_path.clone(),
+        model: entry.model.clone(),
+        restored: outcome.is_ok(),
+        error: outcome.err(),
+    };
+    if let Some(error) = result.error.as_ref() {
+        println!("[agent-registry] Dropping {}: {}", entry.agent_id, error);
+        forget_agent(app_handle, &entry.agent_id).await;
+        return result;
+    }
+
+    println!(
+        "[agent-registry] Re-adopting {} on port {}",
+        entry.agent_id, entry.port
+    );
+    // 与连接时一致：远程 Agent 不使用本机 MCP 配置
+    let mcp_servers = if entry.agent_type == REMOTE_AGENT_TYPE {
+        Vec::new()
+    } else {
+        workspace_mcp_servers(app_handle, &entry.workspace_path).await
+    };
+    let response = register_agent_listener(
+        app_handle.clone(),
+        state,
+        adapter_for_type(&entry.agent_type),
+        AgentLaunch {
+            agent_id: entry.agent_id,
+            name: entry.name,
+            agent_type: entry.agent_type,
+            process: None,
+            adopted_pid: entry.pid,
+            port: entry.port,
+            ws_url: entry.ws_url,
+            executable: entry.executable,
+            workspace_path: entry.workspace_path,
+            model: entry.model,
+            permission_mode: entry.permission_mode,
+            mcp_servers,
+        },
+    )
+    .await;
+    result.restored = response.success;
+    result.error = response.error;
+    result
+}
+
+/// 接管指定 Agent 在登记表中的进程；调用方需持有 `agent_startup_lock`
+pub(crate) async fn adopt_registered_agent(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+    agent_id: &str,
+) -> bool {
+    let entry = {
+        let _guard = state.agent_registry_lock.lock().await;
+        match registry_path(app_handle) {
+            Ok(path) => read_registry_from_path(&path).await,
+            Err(e) => Err(e),
+        }
+    };
+    match entry.map(|mut registry| registry.remove(agent_id)) {
+        Ok(Some(entry)) => adopt_entry(app_handle, state, entry).await.restored,
+        Ok(None) => false,
+        Err(e) => {
+            println!("[agent-registry] {}", e);
+            false
+        }
+    }
+}
+
+/// 接管登记表中仍在运行的 Agent；无法接管的条目从登记表移除。已在运行的 Agent 跳过。
+#[tauri::command]
+pub async fn restore_agents(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<RestoredAgent>, String> {
+    // 与启动时的自动连接互斥，避免同一 Agent 被接管两次
+    let _startup = state.agent_startup_lock.lock().await;
+    let entries = {
+        let _guard = state.agent_registry_lock.lock().await;
+        read_registry_from_path(&registry_path(&app_handle)?).await?
+    };
+
+    let mut results = Vec::new();
+    for entry in entries.into_values() {
+        if state.agent_manager.port_of(&entry.agent_id).await.is_some() {
+            continue;
+        }
+        results.push(adopt_entry(&app_handle, &state, entry).await);
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn unreachable_agents_are_not_adoptable() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut entry = RegisteredAgent {
+            agent_id: "agent-a".to_string(),
+            name: "iFlow".to_string(),
+            agent_type: "iflow".to_string(),
+            pid: None,
+            port,
+            ws_url: format!("ws://127.0.0.1:{}/acp", port),
+            executable: "iflow".to_string(),
+            workspace_path: "/tmp".to_string(),
+            model: None,
+            permission_mode: PermissionMode::Plan,
+            registered_at: "2026-03-02T08:00:00Z".to_string(),
+        };
+        assert!(check_adoptable(&entry).await.is_ok());
+
+        drop(listener);
+        assert!(check_adoptable(&entry).await.is_err());
+
+        entry.ws_url = "wss://agents.example.com/acp".to_string();
+        assert_eq!(
+            probe_address(&entry.ws_url).unwrap(),
+            "agents.example.com:443"
+        );
+    }
+}
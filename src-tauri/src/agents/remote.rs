@@ -0,0 +1,318 @@
+//! 远程 Agent：通过 SSH 在远程主机上启动 iFlow，并把远程 ACP 端口转发到本机，监听任务照常连接本机端口；
+//! 也可直接连接已手动转发好的端口，或反向代理之后的 ws:// / wss:// endpoint。工作区路径指远程主机上的路径。
+use std::process::Stdio;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tokio::process::Command;
+use tokio::time::Duration;
+
+use serde_json::Value;
+
+use super::adapter::{
+    normalized_model, register_agent_listener, AcpAgentAdapter, AcpTransport, AgentLaunch,
+};
+use super::iflow_adapter::{find_available_port, IflowAdapter};
+use super::session_params::build_initialize_params;
+use super::stdio_bridge::log_stderr_lines;
+use super::tls::{register_endpoint_tls, AcpTlsOptions};
+use crate::models::{ConnectResponse, PermissionMode};
+use crate::runtime_env::{resolve_executable_path, runtime_path_env};
+use crate::state::AppState;
+
+pub(crate) const REMOTE_AGENT_TYPE: &str = "iflow-remote";
+const DEFAULT_REMOTE_IFLOW_COMMAND: &str = "iflow";
+/// SSH 握手加远程 iFlow 启动，比本机多留一些时间
+const REMOTE_STARTUP_DELAY: Duration = Duration::from_secs(5);
+
+/// 远程主机上的 iFlow：报文与本机 iFlow 相同，但工作区不在本机磁盘上，
+/// 因此不声明 fs 读写能力，Agent 自行在远程读写文件
+pub(crate) struct RemoteIflowAdapter;
+
+impl AcpAgentAdapter for RemoteIflowAdapter {
+    fn agent_type(&self) -> &'static str {
+        REMOTE_AGENT_TYPE
+    }
+
+    fn display_name(&self) -> &'static str {
+        "iFlow (remote)"
+    }
+
+    fn transport(&self) -> AcpTransport {
+        AcpTransport::WebSocket
+    }
+
+    fn spawn_args(&self, port: Option<u16>, model: Option<&str>) -> Vec<String> {
+        IflowAdapter.spawn_args(port, model)
+    }
+
+    fn resumes_last_session(&self) -> bool {
+        true
+    }
+
+    fn initialize_params(&self) -> Value {
+        build_initialize_params(false)
+    }
+}
+
+/// 工作区路径是否只在 Agent 所在主机上有意义（不能按本机路径读取历史、git 与产物）
+pub(crate) fn is_remote_agent_type(agent_type: &str) -> bool {
+    agent_type == REMOTE_AGENT_TYPE
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteConnection {
+    /// `user@host` 或 ssh config 中的主机别名
+    pub host: String,
+    #[serde(default)]
+    pub ssh_port: Option<u16>,
+    #[serde(default)]
+    pub identity_file: Option<String>,
+    /// 远程主机上的 iflow 命令，默认 `iflow`
+    #[serde(default)]
+    pub iflow_path: Option<String>,
+    /// 远程 iFlow 监听的端口；为空时与本机转发端口相同
+    #[serde(default)]
+    pub remote_port: Option<u16>,
+    /// 已转发到本机的端口：直接连接，不再建立隧道或启动远程进程
+    #[serde(default)]
+    pub forwarded_port: Option<u16>,
+    /// 完整的 ACP endpoint（如反向代理后的 `wss://agents.example.com/acp`），优先于端口转发
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// wss endpoint 的证书配置
+    #[serde(default)]
+    pub tls: Option<AcpTlsOptions>,
+}
+
+fn validate_host(host: &str) -> Result<String, String> {
+    let host = host.trim();
+    if host.is_empty() {
+        return Err("Remote host cannot be empty".to_string());
+    }
+    // 以 - 开头会被 ssh 当作选项
+    if host.starts_with('-') || host.chars().any(char::is_whitespace) {
+        return Err(format!("Invalid remote host: {}", host));
+    }
+    Ok(host.to_string())
+}
+
+/// 校验 endpoint 并取其端口
+fn endpoint_port(endpoint: &str) -> Result<u16, String> {
+    let url = url::Url::parse(endpoint).map_err(|e| format!("Invalid endpoint: {}", e))?;
+    if !matches!(url.scheme(), "ws" | "wss") {
+        return Err(format!("Unsupported endpoint scheme: {}", url.scheme()));
+    }
+    url.port_or_known_default()
+        .ok_or_else(|| format!("Endpoint has no port: {}", endpoint))
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// 在远程 shell 中执行的命令：进入工作区后以 ACP 模式启动 iFlow
+fn remote_iflow_command(
+    iflow_path: &str,
+    workspace_path: &str,
+    port: u16,
+    model: Option<&str>,
+) -> String {
+    let mut command = format!(
+        "cd {} && exec {} --experimental-acp --port {}",
+        shell_quote(workspace_path),
+        shell_quote(iflow_path),
+        port
+    );
+    if let Some(model_name) = model {
+        command.push_str(&format!(" --model {}", shell_quote(model_name)));
+    }
+    command
+}
+
+/// 同一个 ssh 进程负责端口转发与远程启动；`-tt` 分配终端，ssh 退出时远程 iFlow 随之收到 SIGHUP
+fn ssh_args(
+    remote: &RemoteConnection,
+    host: &str,
+    local_port: u16,
+    remote_port: u16,
+    remote_command: String,
+) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "-tt",
+        "-o",
+        "BatchMode=yes",
+        "-o",
+        "ExitOnForwardFailure=yes",
+        "-o",
+        "ServerAliveInterval=30",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    args.push("-L".to_string());
+    args.push(format!("{}:127.0.0.1:{}", local_port, remote_port));
+    if let Some(port) = remote.ssh_port {
+        args.push("-p".to_string());
+        args.push(port.to_string());
+    }
+    if let Some(identity) = remote
+        .identity_file
+        .as_deref()
+        .map(str::trim)
+        .filter(|identity| !identity.is_empty())
+    {
+        args.push("-i".to_string());
+        args.push(identity.to_string());
+    }
+    args.push(host.to_string());
+    args.push(remote_command);
+    args
+}
+
+/// 建立到远程 iFlow 的连接并注册为普通 Agent
+pub(crate) async fn connect_remote_iflow(
+    app_handle: tauri::AppHandle,
+    state: &AppState,
+    agent_id: String,
+    workspace_path: String,
+    remote: RemoteConnection,
+    model: Option<String>,
+    permission_mode: PermissionMode,
+) -> Result<ConnectResponse, String> {
+    let host = validate_host(&remote.host)?;
+    let model = normalized_model(model);
+    println!("Connecting to remote iFlow on {}...", host);
+    println!("Agent ID: {}", agent_id);
+    println!("Remote workspace: {}", workspace_path);
+
+    let endpoint = remote
+        .endpoint
+        .as_deref()
+        .map(str::trim)
+        .filter(|endpoint| !endpoint.is_empty())
+        .map(str::to_string);
+    if let (Some(endpoint), Some(tls)) = (endpoint.as_ref(), remote.tls.as_ref()) {
+        register_endpoint_tls(endpoint, tls)?;
+    }
+
+    let (port, process) = match (endpoint.as_deref(), remote.forwarded_port) {
+        (Some(endpoint), _) => {
+            println!("Using endpoint: {}", endpoint);
+            (endpoint_port(endpoint)?, None)
+        }
+        (None, Some(port)) => {
+            println!("Using forwarded port: {}", port);
+            (port, None)
+        }
+        (None, None) => {
+            let port = find_available_port().await?;
+            let remote_port = remote.remote_port.unwrap_or(port);
+            let iflow_path = remote
+                .iflow_path
+                .as_deref()
+                .map(str::trim)
+                .filter(|path| !path.is_empty())
+                .unwrap_or(DEFAULT_REMOTE_IFLOW_COMMAND);
+            let remote_command =
+                remote_iflow_command(iflow_path, &workspace_path, remote_port, model.as_deref());
+            println!("Forwarding local port {} to {}:{}", port, host, remote_port);
+
+            let ssh_path = resolve_executable_path("ssh")?;
+            let mut child = Command::new(&ssh_path)
+                .args(ssh_args(&remote, &host, port, remote_port, remote_command))
+                .env("PATH", runtime_path_env()?)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|e| format!("Failed to start ssh: {}", e))?;
+            println!("ssh process started, PID: {:?}", child.id());
+            if let Some(stderr) = child.stderr.take() {
+                log_stderr_lines(format!("ssh:{}", agent_id), stderr);
+            }
+
+            println!("Waiting for remote iFlow to initialize...");
+            tokio::time::sleep(REMOTE_STARTUP_DELAY).await;
+            if let Ok(Some(status)) = child.try_wait() {
+                return Err(format!("ssh exited early: {}", status));
+            }
+            (port, Some(child))
+        }
+    };
+
+    // 本机的 MCP 配置无法在远程主机上启动，远程 iFlow 使用其自身配置
+    Ok(register_agent_listener(
+        app_handle,
+        state,
+        Arc::new(RemoteIflowAdapter),
+        AgentLaunch {
+            agent_id,
+            name: format!("iFlow @ {}", host),
+            agent_type: REMOTE_AGENT_TYPE.to_string(),
+            process,
+            adopted_pid: None,
+            port,
+            ws_url: endpoint.unwrap_or_else(|| format!("ws://127.0.0.1:{}/acp", port)),
+            executable: host,
+            workspace_path,
+            model,
+            permission_mode,
+            mcp_servers: Vec::new(),
+        },
+    )
+    .await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ssh_invocation_forwards_port_and_quotes_remote_command() {
+        let remote = RemoteConnection {
+            host: "dev@box".to_string(),
+            ssh_port: Some(2222),
+            identity_file: Some(" ".to_string()),
+            iflow_path: None,
+            remote_port: None,
+            forwarded_port: None,
+            endpoint: None,
+            tls: None,
+        };
+        let command = remote_iflow_command("iflow", "/home/dev/it's", 9000, Some("glm-4.7"));
+        assert_eq!(
+            command,
+            "cd '/home/dev/it'\\''s' && exec 'iflow' --experimental-acp --port 9000 --model 'glm-4.7'"
+        );
+        let args = ssh_args(&remote, "dev@box", 8100, 9000, command.clone());
+        assert!(args
+            .windows(2)
+            .any(|pair| pair == ["-L", "8100:127.0.0.1:9000"]));
+        assert!(args.windows(2).any(|pair| pair == ["-p", "2222"]));
+        assert!(!args.contains(&"-i".to_string()));
+        assert_eq!(&args[args.len() - 2..], ["dev@box".to_string(), command]);
+
+        assert_eq!(endpoint_port("wss://agents.example.com/acp"), Ok(443));
+        assert_eq!(endpoint_port("ws://10.0.0.5:8090/acp"), Ok(8090));
+        assert!(endpoint_port("https://agents.example.com").is_err());
+
+        assert!(validate_host("-oProxyCommand=x").is_err());
+        assert!(validate_host("  ").is_err());
+        assert_eq!(validate_host(" dev@box ").unwrap(), "dev@box");
+    }
+
+    #[test]
+    fn remote_agents_do_not_advertise_local_fs() {
+        let params = RemoteIflowAdapter.initialize_params();
+        let fs = &params["clientCapabilities"]["fs"];
+        assert_eq!(fs["readTextFile"], false);
+        assert_eq!(fs["writeTextFile"], false);
+        assert_eq!(
+            IflowAdapter.initialize_params()["clientCapabilities"]["fs"]["readTextFile"],
+            true
+        );
+    }
+}
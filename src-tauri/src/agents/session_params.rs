@@ -1,57 +1,119 @@
 //! ACP JSON-RPC session 请求参数构建
 use serde_json::{json, Value};
 
-pub(super) fn build_initialize_params() -> Value {
+use crate::generation::GenerationOptions;
+use crate::models::{PermissionMode, PromptResource};
+use crate::prompt_split::prompt_text_parts;
+
+/// `local_fs` 为 false 时不声明文件读写能力：工作区不在本机（远程 Agent）或调用方不处理 fs 请求
+pub(super) fn build_initialize_params(local_fs: bool) -> Value {
     json!({
         "protocolVersion": 1,
         "clientCapabilities": {
             "fs": {
-                "readTextFile": true,
-                "writeTextFile": true,
+                "readTextFile": local_fs,
+                "writeTextFile": local_fs,
             }
         },
         "mcpServers": [],
     })
 }
 
-pub(super) fn build_session_new_params(workspace_path: &str) -> Value {
+pub(super) fn build_session_new_params(
+    workspace_path: &str,
+    mcp_servers: &[Value],
+    permission_mode: PermissionMode,
+) -> Value {
     json!({
         "cwd": workspace_path,
-        "mcpServers": [],
+        "mcpServers": mcp_servers,
         "settings": {
-            "permission_mode": "yolo",
+            "permission_mode": permission_mode.as_str(),
         }
     })
 }
 
-pub(super) fn build_session_new_params_with_id(workspace_path: &str, session_id: &str) -> Value {
+pub(super) fn build_session_new_params_with_id(
+    workspace_path: &str,
+    session_id: &str,
+    mcp_servers: &[Value],
+    permission_mode: PermissionMode,
+) -> Value {
     json!({
         "cwd": workspace_path,
         "sessionId": session_id,
-        "mcpServers": [],
+        "mcpServers": mcp_servers,
         "settings": {
-            "permission_mode": "yolo",
+            "permission_mode": permission_mode.as_str(),
         }
     })
 }
 
-pub(super) fn build_session_load_params(workspace_path: &str, session_id: &str) -> Value {
+pub(super) fn build_session_load_params(
+    workspace_path: &str,
+    session_id: &str,
+    mcp_servers: &[Value],
+    permission_mode: PermissionMode,
+) -> Value {
     json!({
         "cwd": workspace_path,
         "sessionId": session_id,
-        "mcpServers": [],
+        "mcpServers": mcp_servers,
         "settings": {
-            "permission_mode": "yolo",
+            "permission_mode": permission_mode.as_str(),
         }
     })
 }
 
-pub(super) fn build_prompt_params(session_id: &str, prompt: &str) -> Value {
-    json!({
+pub(super) fn build_prompt_params(
+    session_id: &str,
+    prompt: &str,
+    resources: &[PromptResource],
+    generation: Option<&GenerationOptions>,
+) -> Value {
+    let mut parts: Vec<Value> = prompt_text_parts(prompt)
+        .into_iter()
+        .map(|text| {
+            json!({
+                "type": "text",
+                "text": text,
+            })
+        })
+        .collect();
+    for resource in resources {
+        let mut part = json!({
+            "type": "resource_link",
+            "uri": resource.uri,
+            "name": resource.name,
+        });
+        if let Some(mime_type) = resource.mime_type.as_ref() {
+            part["mimeType"] = json!(mime_type);
+        }
+        if let Some(size) = resource.size {
+            part["size"] = json!(size);
+        }
+        parts.push(part);
+    }
+    let mut params = json!({
         "sessionId": session_id,
-        "prompt": [{
-            "type": "text",
-            "text": prompt,
-        }],
-    })
+        "prompt": parts,
+    });
+    if let Some(generation) = generation {
+        params["settings"] = generation.prompt_settings();
+    }
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_params_carry_permission_mode() {
+        let params =
+            build_session_load_params("/ws", "session-1", &[], PermissionMode::AcceptEdits);
+        assert_eq!(params["settings"]["permission_mode"], "acceptEdits");
+        let params = build_session_new_params("/ws", &[], PermissionMode::default());
+        assert_eq!(params["settings"]["permission_mode"], "yolo");
+    }
 }
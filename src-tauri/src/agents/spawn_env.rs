@@ -0,0 +1,261 @@
+//! 启动 Agent 时注入的环境变量：设置中的 `agent_env`（随档案隔离）与按工作区配置的
+//! `workspace_agent_env`（同名时覆盖前者）。取值支持 `${env:VAR}` 引用 FlowHub 进程的环境变量、
+//! `${secret:NAME}` 引用密钥存储（工作区作用域优先），`$${` 表示字面量 `${`。
+//! 启动前一次性解析全部取值，有缺失时列出所有缺失项并拒绝启动。
+use std::collections::{BTreeMap, HashMap};
+
+use tauri::Manager;
+
+use crate::history::normalize_workspace_path;
+use crate::secrets::{load_secret_store, SecretStore};
+use crate::state::AppState;
+
+/// 模板中的一个引用
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplateRef {
+    Env(String),
+    Secret(String),
+}
+
+impl TemplateRef {
+    fn label(&self) -> String {
+        match self {
+            TemplateRef::Env(name) => format!("env:{}", name),
+            TemplateRef::Secret(name) => format!("secret:{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Ref(TemplateRef),
+}
+
+fn parse_template(value: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut rest = value;
+    while let Some(offset) = rest.find('$') {
+        text.push_str(&rest[..offset]);
+        rest = &rest[offset..];
+        if rest.starts_with("$${") {
+            text.push_str("${");
+            rest = &rest[3..];
+        } else if rest.starts_with("${") {
+            let end = rest
+                .find('}')
+                .ok_or_else(|| format!("Unterminated placeholder in `{}`", value))?;
+            let inner = &rest[2..end];
+            let (scheme, name) = inner.split_once(':').ok_or_else(|| {
+                format!(
+                    "Placeholder `${{{}}}` must be env:NAME or secret:NAME",
+                    inner
+                )
+            })?;
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(format!("Placeholder `${{{}}}` is missing a name", inner));
+            }
+            let reference = match scheme.trim() {
+                "env" => TemplateRef::Env(name.to_string()),
+                "secret" => TemplateRef::Secret(name.to_string()),
+                other => {
+                    return Err(format!(
+                        "Unknown placeholder source `{}` in `${{{}}}`",
+                        other, inner
+                    ))
+                }
+            };
+            if !text.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+            }
+            segments.push(Segment::Ref(reference));
+            rest = &rest[end + 1..];
+        } else {
+            text.push('$');
+            rest = &rest[1..];
+        }
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
+
+fn validate_env_map(env: &HashMap<String, String>) -> Result<(), String> {
+    for (key, value) in env {
+        if key.trim().is_empty() || key.contains('=') || key.contains('\0') {
+            return Err(format!("Invalid environment variable name `{}`", key));
+        }
+        parse_template(value).map_err(|e| format!("{}: {}", key, e))?;
+    }
+    Ok(())
+}
+
+/// 设置保存前校验变量名与模板语法（不检查引用是否存在）
+pub(crate) fn validate_agent_env(
+    agent_env: &HashMap<String, String>,
+    workspace_agent_env: &HashMap<String, HashMap<String, String>>,
+) -> Result<(), String> {
+    validate_env_map(agent_env)?;
+    for (workspace, env) in workspace_agent_env {
+        if workspace.trim().is_empty() {
+            return Err("Workspace path cannot be empty".to_string());
+        }
+        validate_env_map(env).map_err(|e| format!("{}: {}", workspace, e))?;
+    }
+    Ok(())
+}
+
+/// 合并全局与工作区配置，工作区同名变量覆盖全局
+fn merged_env(
+    agent_env: &HashMap<String, String>,
+    workspace_agent_env: &HashMap<String, HashMap<String, String>>,
+    workspace_path: &str,
+) -> BTreeMap<String, String> {
+    let mut merged: BTreeMap<String, String> = agent_env
+        .iter()
+        .map(|(key, value)| (key.trim().to_string(), value.clone()))
+        .collect();
+    let normalized = normalize_workspace_path(workspace_path);
+    if let Some((_, env)) = workspace_agent_env
+        .iter()
+        .find(|(path, _)| normalize_workspace_path(path) == normalized)
+    {
+        merged.extend(
+            env.iter()
+                .map(|(key, value)| (key.trim().to_string(), value.clone())),
+        );
+    }
+    merged
+}
+
+/// 解析全部取值；有缺失引用时返回按出现顺序去重的缺失列表
+fn expand_env(
+    env: &BTreeMap<String, String>,
+    lookup: impl Fn(&TemplateRef) -> Option<String>,
+) -> Result<Vec<(String, String)>, String> {
+    let mut resolved = Vec::with_capacity(env.len());
+    let mut missing: Vec<String> = Vec::new();
+    for (key, template) in env {
+        let segments = parse_template(template).map_err(|e| format!("{}: {}", key, e))?;
+        let mut value = String::new();
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => value.push_str(&text),
+                Segment::Ref(reference) => match lookup(&reference) {
+                    Some(found) => value.push_str(&found),
+                    None => {
+                        let label = reference.label();
+                        if !missing.contains(&label) {
+                            missing.push(label);
+                        }
+                    }
+                },
+            }
+        }
+        resolved.push((key.clone(), value));
+    }
+    if missing.is_empty() {
+        Ok(resolved)
+    } else {
+        Err(format!(
+            "Missing variables for agent environment: {}",
+            missing.join(", ")
+        ))
+    }
+}
+
+fn lookup_reference(
+    secrets: &SecretStore,
+    workspace_path: &str,
+    reference: &TemplateRef,
+) -> Option<String> {
+    match reference {
+        TemplateRef::Env(name) => std::env::var(name).ok(),
+        TemplateRef::Secret(name) => secrets.resolve(Some(workspace_path), name),
+    }
+}
+
+/// 启动前解析某个工作区的 Agent 环境变量
+pub(crate) async fn resolve_agent_env(
+    app_handle: &tauri::AppHandle,
+    workspace_path: &str,
+) -> Result<Vec<(String, String)>, String> {
+    let env = {
+        let settings = app_handle.state::<AppState>().settings.read().await;
+        merged_env(
+            &settings.agent_env,
+            &settings.workspace_agent_env,
+            workspace_path,
+        )
+    };
+    if env.is_empty() {
+        return Ok(Vec::new());
+    }
+    let secrets = load_secret_store(app_handle).await?;
+    expand_env(&env, |reference| {
+        lookup_reference(&secrets, workspace_path, reference)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_parse_escapes_and_reject_bad_placeholders() {
+        assert_eq!(
+            parse_template("Bearer ${secret:TOKEN} $${literal} $5").unwrap(),
+            vec![
+                Segment::Text("Bearer ".to_string()),
+                Segment::Ref(TemplateRef::Secret("TOKEN".to_string())),
+                Segment::Text(" ${literal} $5".to_string()),
+            ]
+        );
+        assert!(parse_template("${env:HOME").is_err());
+        assert!(parse_template("${HOME}").is_err());
+        assert!(parse_template("${vault:KEY}").is_err());
+        assert!(parse_template("${env:}").is_err());
+
+        let mut env = HashMap::new();
+        env.insert("BAD=KEY".to_string(), "x".to_string());
+        assert!(validate_agent_env(&env, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn expansion_merges_workspace_and_lists_every_missing_variable() {
+        let mut global = HashMap::new();
+        global.insert("API_BASE".to_string(), "https://${env:HOST}/v1".to_string());
+        global.insert("TOKEN".to_string(), "${secret:GLOBAL_TOKEN}".to_string());
+        let mut workspace = HashMap::new();
+        workspace.insert(
+            "/repo/".to_string(),
+            HashMap::from([("TOKEN".to_string(), "${secret:REPO_TOKEN}".to_string())]),
+        );
+        let merged = merged_env(&global, &workspace, "/repo");
+        assert_eq!(merged["TOKEN"], "${secret:REPO_TOKEN}");
+
+        let resolved = expand_env(&merged, |reference| match reference {
+            TemplateRef::Env(name) if name == "HOST" => Some("example.com".to_string()),
+            TemplateRef::Secret(name) if name == "REPO_TOKEN" => Some("abc".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(
+            resolved,
+            vec![
+                ("API_BASE".to_string(), "https://example.com/v1".to_string()),
+                ("TOKEN".to_string(), "abc".to_string()),
+            ]
+        );
+
+        let error = expand_env(&merged, |_| None).unwrap_err();
+        assert_eq!(
+            error,
+            "Missing variables for agent environment: env:HOST, secret:REPO_TOKEN"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! stdio 桥接：Claude Code、Gemini CLI 等以 stdio 上的换行分隔 JSON-RPC 通信，
+//! 这里在本机端口上把 stdio 桥接为 WebSocket，使其与 iFlow 共用同一个监听任务。
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::process::ChildStderr;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+async fn write_frame<W: AsyncWrite + Unpin>(stdin: &mut W, message: &str) -> std::io::Result<()> {
+    stdin.write_all(message.trim_end().as_bytes()).await?;
+    stdin.write_all(b"\n").await?;
+    stdin.flush().await
+}
+
+/// 逐个接受 WebSocket 连接并与进程 stdio 互相转发；监听任务断线重连时复用同一进程。
+/// 进程 stdout 关闭（退出）后桥接结束，监听任务随之重连失败并退出。
+pub(super) async fn run_stdio_bridge<W, R>(
+    label: String,
+    listener: TcpListener,
+    mut stdin: W,
+    stdout: R,
+) where
+    W: AsyncWrite + Unpin,
+    R: AsyncRead + Unpin,
+{
+    let mut lines = BufReader::new(stdout).lines();
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                println!("[{}] Failed to accept bridge connection: {}", label, e);
+                continue;
+            }
+        };
+        let mut ws = match tokio_tungstenite::accept_async(stream).await {
+            Ok(ws) => ws,
+            Err(e) => {
+                println!("[{}] Bridge handshake failed: {}", label, e);
+                continue;
+            }
+        };
+
+        loop {
+            tokio::select! {
+                incoming = ws.next() => match incoming {
+                    Some(Ok(WsMessage::Text(text))) => {
+                        if let Err(e) = write_frame(&mut stdin, text.as_str()).await {
+                            println!("[{}] Failed to write to stdin: {}", label, e);
+                            let _ = ws.close(None).await;
+                            return;
+                        }
+                    }
+                    Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+                line = lines.next_line() => match line {
+                    Ok(Some(line)) => {
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        if ws.send(WsMessage::Text(line)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) | Err(_) => {
+                        println!("[{}] ACP process closed stdout", label);
+                        let _ = ws.close(None).await;
+                        return;
+                    }
+                },
+            }
+        }
+    }
+}
+
+pub(super) fn log_stderr_lines(label: String, stderr: ChildStderr) {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let line = line.trim();
+            if !line.is_empty() {
+                println!("[{}] {}", label, line);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::iflow_adapter::AcpConnection;
+
+    #[tokio::test]
+    async fn bridge_relays_frames_between_websocket_and_stdio() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (stdin_writer, stdin_reader) = tokio::io::duplex(4096);
+        let (mut stdout_writer, stdout_reader) = tokio::io::duplex(4096);
+        tokio::spawn(run_stdio_bridge(
+            "test".to_string(),
+            listener,
+            stdin_writer,
+            stdout_reader,
+        ));
+
+        let mut conn = AcpConnection::connect(&format!("ws://127.0.0.1:{}/acp", port))
+            .await
+            .unwrap();
+        conn.send_message("{\"id\":1}".to_string()).await.unwrap();
+        let mut stdin_lines = BufReader::new(stdin_reader).lines();
+        assert_eq!(
+            stdin_lines.next_line().await.unwrap().as_deref(),
+            Some("{\"id\":1}")
+        );
+
+        stdout_writer.write_all(b"\n{\"id\":2}\n").await.unwrap();
+        assert_eq!(
+            conn.receive_message().await.unwrap().as_deref(),
+            Some("{\"id\":2}")
+        );
+    }
+}
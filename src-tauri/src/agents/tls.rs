@@ -0,0 +1,152 @@
+//! wss 连接的 TLS 配置：在系统根证书之外信任自定义 CA，并可出示客户端证书，
+//! 用于连接位于反向代理之后的 Agent。配置按 endpoint 登记，监听任务与临时会话（标题、复核）连接同一 endpoint 时共用。
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use once_cell::sync::Lazy;
+use rustls::pki_types::CertificateDer;
+use rustls::{ClientConfig, RootCertStore};
+use serde::Deserialize;
+use tokio_tungstenite::Connector;
+
+static ENDPOINT_TLS: Lazy<Mutex<HashMap<String, Arc<ClientConfig>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Default, Clone, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AcpTlsOptions {
+    /// PEM 格式的 CA 证书（可含多张）
+    #[serde(default)]
+    pub ca_cert_path: Option<String>,
+    /// PEM 格式的客户端证书链，与 `client_key_path` 同时提供
+    #[serde(default)]
+    pub client_cert_path: Option<String>,
+    #[serde(default)]
+    pub client_key_path: Option<String>,
+    /// 只信任 `ca_cert_path` 中的证书
+    #[serde(default)]
+    pub skip_system_roots: bool,
+}
+
+fn lock_endpoints() -> MutexGuard<'static, HashMap<String, Arc<ClientConfig>>> {
+    ENDPOINT_TLS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn open_pem(path: &str) -> Result<BufReader<File>, String> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| format!("Failed to open {}: {}", path, e))
+}
+
+fn read_certificates(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = rustls_pemfile::certs(&mut open_pem(path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to parse certificates in {}: {}", path, e))?;
+    if certs.is_empty() {
+        return Err(format!("No certificates found in {}", path));
+    }
+    Ok(certs)
+}
+
+pub(crate) fn build_client_config(options: &AcpTlsOptions) -> Result<Arc<ClientConfig>, String> {
+    let mut roots = RootCertStore::empty();
+    if !options.skip_system_roots {
+        let native = rustls_native_certs::load_native_certs()
+            .map_err(|e| format!("Failed to load system certificates: {}", e))?;
+        roots.add_parsable_certificates(native);
+    }
+    if let Some(ca_path) = non_empty(&options.ca_cert_path) {
+        for cert in read_certificates(ca_path)? {
+            roots
+                .add(cert)
+                .map_err(|e| format!("Invalid CA certificate in {}: {}", ca_path, e))?;
+        }
+    }
+    if roots.is_empty() {
+        return Err("No trusted certificates configured".to_string());
+    }
+
+    let builder = ClientConfig::builder().with_root_certificates(roots);
+    let config = match (
+        non_empty(&options.client_cert_path),
+        non_empty(&options.client_key_path),
+    ) {
+        (Some(cert_path), Some(key_path)) => {
+            let certs = read_certificates(cert_path)?;
+            let key = rustls_pemfile::private_key(&mut open_pem(key_path)?)
+                .map_err(|e| format!("Failed to parse private key in {}: {}", key_path, e))?
+                .ok_or_else(|| format!("No private key found in {}", key_path))?;
+            builder
+                .with_client_auth_cert(certs, key)
+                .map_err(|e| format!("Invalid client certificate: {}", e))?
+        }
+        (None, None) => builder.with_no_client_auth(),
+        _ => return Err("Client certificate and key must be provided together".to_string()),
+    };
+    Ok(Arc::new(config))
+}
+
+/// 为 endpoint 登记 TLS 配置；配置有误时立即报错，而不是等到连接时
+pub(crate) fn register_endpoint_tls(endpoint: &str, options: &AcpTlsOptions) -> Result<(), String> {
+    let config = build_client_config(options)?;
+    lock_endpoints().insert(endpoint.to_string(), config);
+    Ok(())
+}
+
+/// 未登记的 endpoint 使用默认配置（系统根证书）
+pub(super) fn connector_for(endpoint: &str) -> Option<Connector> {
+    lock_endpoints()
+        .get(endpoint)
+        .cloned()
+        .map(Connector::Rustls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_tls_options_are_rejected() {
+        let dir = std::env::temp_dir().join(format!("flowhub-tls-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let empty = dir.join("empty.pem");
+        std::fs::write(&empty, "").unwrap();
+        let empty = empty.to_string_lossy().to_string();
+
+        let no_roots = AcpTlsOptions {
+            skip_system_roots: true,
+            ..Default::default()
+        };
+        assert!(build_client_config(&no_roots).is_err());
+
+        let empty_ca = AcpTlsOptions {
+            ca_cert_path: Some(empty),
+            skip_system_roots: true,
+            ..Default::default()
+        };
+        assert!(build_client_config(&empty_ca)
+            .unwrap_err()
+            .starts_with("No certificates found"));
+
+        let missing_ca = AcpTlsOptions {
+            ca_cert_path: Some("/nonexistent/ca.pem".to_string()),
+            skip_system_roots: true,
+            ..Default::default()
+        };
+        assert!(build_client_config(&missing_ca)
+            .unwrap_err()
+            .starts_with("Failed to open"));
+        assert!(connector_for("wss://unregistered/acp").is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
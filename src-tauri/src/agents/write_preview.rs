@@ -0,0 +1,171 @@
+//! `fs/write_text_file` 写入预览：写盘前计算当前内容与新内容的 unified diff，
+//! 通过 `file-write-preview` 事件交给前端展示。计划模式（plan）下的写入需用户确认，
+//! 等待期间由监听任务登记，超时按权限请求的默认决策处理；其余模式预览后立即写入。
+use std::path::{Path, PathBuf};
+
+use serde_json::json;
+use similar::TextDiff;
+use tokio::time::{Duration, Instant};
+
+use super::iflow_adapter::{send_rpc_error, send_rpc_result, AcpConnection};
+use crate::app_lock::EmitUnlessLocked;
+use crate::legal_hold::record_session_audit;
+use crate::models::PermissionMode;
+use crate::path_display::relativize_workspace_paths;
+use crate::permissions::PermissionDecision;
+
+const DIFF_CONTEXT_LINES: usize = 3;
+
+/// 等待用户确认的写入
+pub(super) struct PendingFileWrite {
+    pub(super) session_id: String,
+    pub(super) target: PathBuf,
+    pub(super) content: String,
+    pub(super) deadline: Instant,
+    pub(super) timeout_decision: PermissionDecision,
+}
+
+impl PendingFileWrite {
+    pub(super) fn new(
+        session_id: &str,
+        target: PathBuf,
+        content: String,
+        wait: Duration,
+        timeout_decision: PermissionDecision,
+    ) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            target,
+            content,
+            deadline: Instant::now() + wait,
+            timeout_decision,
+        }
+    }
+}
+
+/// 只有计划模式下的写入需要用户确认
+pub(super) fn requires_write_approval(mode: PermissionMode) -> bool {
+    mode == PermissionMode::Plan
+}
+
+/// 以 `path` 为文件头生成 unified diff；新文件的原内容为空
+pub(super) fn unified_diff(path: &str, original: &str, incoming: &str) -> String {
+    TextDiff::from_lines(original, incoming)
+        .unified_diff()
+        .context_radius(DIFF_CONTEXT_LINES)
+        .header(&format!("a/{}", path), &format!("b/{}", path))
+        .to_string()
+}
+
+/// 计算并发送写入预览；`approval` 为空表示预览后直接写入
+pub(super) async fn emit_file_write_preview(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    workspace_path: &str,
+    request_id: i64,
+    session_id: &str,
+    path: &str,
+    target: &Path,
+    content: &str,
+    approval: Option<(Duration, PermissionDecision)>,
+) {
+    let original = tokio::fs::read_to_string(target).await.ok();
+    let display_path = relativize_workspace_paths(path, workspace_path);
+    let diff = unified_diff(
+        display_path.trim_start_matches("./"),
+        original.as_deref().unwrap_or_default(),
+        content,
+    );
+    let _ = app_handle.emit_unless_locked(
+        "file-write-preview",
+        json!({
+            "agentId": agent_id,
+            "requestId": request_id,
+            "sessionId": session_id,
+            "path": display_path,
+            "newFile": original.is_none(),
+            "diff": diff,
+            "awaitingApproval": approval.is_some(),
+            "timeoutSecs": approval.map(|(wait, _)| wait.as_secs()),
+            "timeoutDecision": approval.map(|(_, decision)| decision),
+        }),
+    );
+}
+
+/// 写入文件并应答 Agent
+pub(super) async fn write_file(
+    conn: &mut AcpConnection,
+    request_id: i64,
+    target: &Path,
+    content: &str,
+) -> Result<(), String> {
+    match tokio::fs::write(target, content).await {
+        Ok(_) => send_rpc_result(conn, request_id, serde_json::Value::Null).await,
+        Err(e) => {
+            send_rpc_error(
+                conn,
+                request_id,
+                -32603,
+                &format!("Failed to write file: {}", e),
+            )
+            .await
+        }
+    }
+}
+
+/// 按用户选择（或超时默认决策）完成待确认的写入，并通知前端关闭预览
+pub(super) async fn resolve_file_write(
+    conn: &mut AcpConnection,
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    request_id: i64,
+    pending: PendingFileWrite,
+    accepted: bool,
+    timed_out: bool,
+) -> Result<(), String> {
+    let _ = app_handle.emit_unless_locked(
+        "file-write-resolved",
+        json!({
+            "agentId": agent_id,
+            "requestId": request_id,
+            "accepted": accepted,
+            "timedOut": timed_out,
+        }),
+    );
+    record_session_audit(
+        app_handle,
+        &pending.session_id,
+        "fs/write_text_file",
+        !accepted,
+        Some(pending.target.display().to_string()),
+    )
+    .await;
+    if accepted {
+        write_file(conn, request_id, &pending.target, &pending.content).await
+    } else {
+        send_rpc_error(conn, request_id, -32603, "File write rejected by user").await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unified_diff_marks_changed_lines() {
+        let diff = unified_diff(
+            "src/lib.rs",
+            "fn a() {}\nfn b() {}\n",
+            "fn a() {}\nfn c() {}\n",
+        );
+        assert!(diff.starts_with("--- a/src/lib.rs\n+++ b/src/lib.rs\n"));
+        assert!(diff.contains("-fn b() {}\n"));
+        assert!(diff.contains("+fn c() {}\n"));
+        assert!(diff.contains(" fn a() {}\n"));
+
+        let created = unified_diff("new.txt", "", "hello\n");
+        assert!(created.contains("+hello\n"));
+        assert!(requires_write_approval(PermissionMode::Plan));
+        assert!(!requires_write_approval(PermissionMode::Yolo));
+    }
+}
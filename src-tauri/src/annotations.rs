@@ -0,0 +1,341 @@
+//! 文件批注：任务结束后从助手回复中提取 `path:line` 引用及同一行的评审意见，
+//! 按工作区持久化为批注，供编辑器或界面以行内注释（gutter）形式展示。
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, State};
+use tokio::fs;
+
+use crate::history::normalize_workspace_path;
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag};
+
+/// 每个工作区保留的批注上限，超出时丢弃最早的
+const MAX_ANNOTATIONS_PER_WORKSPACE: usize = 500;
+const MIN_COMMENT_CHARS: usize = 4;
+const MAX_COMMENT_CHARS: usize = 500;
+
+/// `path/to/file.ext:12`、`file.ext:12-20`、`file.ext:12:5`，可被反引号包裹
+static REFERENCE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"`?((?:[A-Za-z]:)?[\w./\\-]*[\w-]\.[A-Za-z0-9]{1,10}):(\d{1,6})(?:[-–](\d{1,6})|:\d{1,4})?`?")
+        .expect("valid reference pattern")
+});
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileAnnotation {
+    pub id: String,
+    /// 相对工作区的路径（统一使用 `/`）
+    pub path: String,
+    pub line: u32,
+    #[serde(default)]
+    pub end_line: Option<u32>,
+    pub comment: String,
+    pub severity: AnnotationSeverity,
+    pub agent_id: String,
+    pub task_id: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationStore {
+    #[serde(default)]
+    pub by_workspace: HashMap<String, Vec<FileAnnotation>>,
+}
+
+/// 从回复中解析出的候选批注，尚未确认文件存在
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ParsedAnnotation {
+    pub path: String,
+    pub line: u32,
+    pub end_line: Option<u32>,
+    pub comment: String,
+    pub severity: AnnotationSeverity,
+}
+
+fn annotations_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-annotations-{}.json", storage_env_tag())))
+}
+
+async fn read_annotations_from_path(path: &Path) -> Result<AnnotationStore, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(AnnotationStore::default());
+            }
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse annotations: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(AnnotationStore::default()),
+        Err(err) => Err(format!("Failed to read annotations: {}", err)),
+    }
+}
+
+async fn write_annotations_to_path(path: &Path, store: &AnnotationStore) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create annotations dir: {}", e))?;
+    }
+    let payload =
+        serde_json::to_vec(store).map_err(|e| format!("Failed to encode annotations: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write annotations: {}", e))
+}
+
+fn severity_of(comment: &str) -> AnnotationSeverity {
+    let lower = comment.to_lowercase();
+    let has_any = |keywords: &[&str]| keywords.iter().any(|keyword| lower.contains(keyword));
+    if has_any(&[
+        "bug", "error", "crash", "panic", "vulnerab", "错误", "缺陷", "崩溃", "漏洞", "严重",
+    ]) {
+        AnnotationSeverity::Error
+    } else if has_any(&["warn", "should", "consider", "建议", "注意", "可能", "应该"]) {
+        AnnotationSeverity::Warning
+    } else {
+        AnnotationSeverity::Info
+    }
+}
+
+/// 去掉列表符号、引用与分隔符后的评论文本
+fn clean_comment(text: &str) -> String {
+    let trimmed = text
+        .trim()
+        .trim_start_matches([':', '：', '-', '—', '–', '|', ')', '）', ','])
+        .trim()
+        .trim_end_matches('|')
+        .trim();
+    trimmed.chars().take(MAX_COMMENT_CHARS).collect()
+}
+
+/// 工作区内的相对路径；工作区外的绝对路径与 URL 返回 None
+fn workspace_relative(path: &str, workspace_path: &str) -> Option<String> {
+    let path = path.replace('\\', "/");
+    let workspace = normalize_workspace_path(workspace_path);
+    let relative = if Path::new(&path).is_absolute() || path.contains(':') {
+        path.strip_prefix(&format!("{}/", workspace))?.to_string()
+    } else {
+        path.trim_start_matches("./").to_string()
+    };
+    if relative.is_empty() || relative.split('/').any(|segment| segment == "..") {
+        return None;
+    }
+    Some(relative)
+}
+
+/// 逐行解析引用：每行取第一个引用，其余文本作为评论；同一位置只保留第一条
+pub(crate) fn parse_annotations(message: &str, workspace_path: &str) -> Vec<ParsedAnnotation> {
+    let mut parsed: Vec<ParsedAnnotation> = Vec::new();
+    let mut in_code_block = false;
+    for line in message.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block || line.contains("://") {
+            continue;
+        }
+        let Some(captures) = REFERENCE_PATTERN.captures(line) else {
+            continue;
+        };
+        let (Some(full), Some(path), Some(start)) =
+            (captures.get(0), captures.get(1), captures.get(2))
+        else {
+            continue;
+        };
+        let Some(path) = workspace_relative(path.as_str(), workspace_path) else {
+            continue;
+        };
+        let Ok(line_number) = start.as_str().parse::<u32>() else {
+            continue;
+        };
+        if line_number == 0 {
+            continue;
+        }
+        let end_line = captures
+            .get(3)
+            .and_then(|end| end.as_str().parse::<u32>().ok())
+            .filter(|end| *end > line_number);
+        let before = line[..full.start()]
+            .trim()
+            .trim_start_matches(['-', '*', '>', '|'])
+            .trim();
+        let after = clean_comment(&line[full.end()..]);
+        let comment = if after.chars().count() >= MIN_COMMENT_CHARS {
+            after
+        } else {
+            clean_comment(before)
+        };
+        if comment.chars().count() < MIN_COMMENT_CHARS {
+            continue;
+        }
+        if parsed
+            .iter()
+            .any(|item| item.path == path && item.line == line_number && item.comment == comment)
+        {
+            continue;
+        }
+        parsed.push(ParsedAnnotation {
+            severity: severity_of(&comment),
+            path,
+            line: line_number,
+            end_line,
+            comment,
+        });
+    }
+    parsed
+}
+
+/// 任务结束后在后台提取批注，只保留工作区中确实存在的文件
+pub(crate) fn spawn_annotation_extraction(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    workspace_path: &str,
+    task_id: &str,
+    message: &str,
+) {
+    let parsed = parse_annotations(message, workspace_path);
+    if parsed.is_empty() {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    let agent_id = agent_id.to_string();
+    let workspace_path = workspace_path.to_string();
+    let task_id = task_id.to_string();
+    tokio::spawn(async move {
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut annotations = Vec::new();
+        for item in parsed {
+            if !fs::try_exists(Path::new(&workspace_path).join(&item.path))
+                .await
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            annotations.push(FileAnnotation {
+                id: uuid::Uuid::new_v4().to_string(),
+                path: item.path,
+                line: item.line,
+                end_line: item.end_line,
+                comment: item.comment,
+                severity: item.severity,
+                agent_id: agent_id.clone(),
+                task_id: task_id.clone(),
+                created_at: now.clone(),
+            });
+        }
+        if annotations.is_empty() {
+            return;
+        }
+
+        let state = app_handle.state::<AppState>();
+        let _guard = state.annotations_lock.lock().await;
+        let result = async {
+            let path = annotations_path(&app_handle)?;
+            let mut store = read_annotations_from_path(&path).await?;
+            let entries = store
+                .by_workspace
+                .entry(normalize_workspace_path(&workspace_path))
+                .or_default();
+            entries.extend(annotations);
+            let overflow = entries.len().saturating_sub(MAX_ANNOTATIONS_PER_WORKSPACE);
+            entries.drain(..overflow);
+            write_annotations_to_path(&path, &store).await
+        }
+        .await;
+        if let Err(e) = result {
+            println!("[annotations] Failed to save annotations: {}", e);
+        }
+    });
+}
+
+/// 某文件的批注（按行号排序）；`path` 为绝对路径，或配合 `workspace_path` 使用相对路径
+#[tauri::command]
+pub async fn get_file_annotations(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    workspace_path: Option<String>,
+) -> Result<Vec<FileAnnotation>, String> {
+    let _guard = state.annotations_lock.lock().await;
+    let store = read_annotations_from_path(&annotations_path(&app_handle)?).await?;
+    let mut matched: Vec<FileAnnotation> = store
+        .by_workspace
+        .iter()
+        .filter(|(workspace, _)| {
+            workspace_path
+                .as_deref()
+                .map(|expected| normalize_workspace_path(expected) == **workspace)
+                .unwrap_or(true)
+        })
+        .flat_map(|(workspace, annotations)| {
+            let relative = workspace_relative(&path, workspace);
+            annotations
+                .iter()
+                .filter(move |annotation| relative.as_deref() == Some(annotation.path.as_str()))
+                .cloned()
+        })
+        .collect();
+    matched.sort_by(|a, b| a.line.cmp(&b.line).then(a.created_at.cmp(&b.created_at)));
+    Ok(matched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn review_comments_are_parsed_from_references() {
+        let message = "审查结果：\n\
+            - `src/main.rs:42` — 这里未处理错误，可能导致崩溃\n\
+            - src/lib.rs:10-14: consider extracting this into a helper\n\
+            - 缓存未失效的问题见 /repo/src/cache.ts:7\n\
+            - 参考 https://example.com/a.rs:3 不应被解析\n\
+            ```\n\
+            src/ignored.rs:1 in code block\n\
+            ```\n\
+            - ../outside.rs:3 escapes workspace\n\
+            - src/short.rs:5 ok";
+        let parsed = parse_annotations(message, "/repo");
+        assert_eq!(parsed.len(), 3);
+
+        assert_eq!(parsed[0].path, "src/main.rs");
+        assert_eq!(parsed[0].line, 42);
+        assert_eq!(parsed[0].comment, "这里未处理错误，可能导致崩溃");
+        assert_eq!(parsed[0].severity, AnnotationSeverity::Error);
+
+        assert_eq!(parsed[1].end_line, Some(14));
+        assert_eq!(parsed[1].severity, AnnotationSeverity::Warning);
+
+        assert_eq!(parsed[2].path, "src/cache.ts");
+        assert_eq!(parsed[2].comment, "缓存未失效的问题见");
+    }
+
+    #[test]
+    fn relative_paths_stay_inside_workspace() {
+        assert_eq!(
+            workspace_relative("/repo/src/a.rs", "/repo/"),
+            Some("src/a.rs".to_string())
+        );
+        assert_eq!(
+            workspace_relative("./src/a.rs", "/repo"),
+            Some("src/a.rs".to_string())
+        );
+        assert_eq!(workspace_relative("/other/a.rs", "/repo"), None);
+        assert_eq!(workspace_relative("src/../../a.rs", "/repo"), None);
+    }
+}
@@ -0,0 +1,595 @@
+//! 应用锁：设置密码后，除解锁与状态查询外的命令在 unlock_app 成功前返回 Locked 错误，
+//! 推送给前端的事件暂存到解锁后按顺序补发；连续输错密码按次数退避。
+//! 密码校验值（PBKDF2-HMAC-SHA256，带格式版本）按档案保存在系统钥匙串，空闲超过设置的时长后自动上锁。
+//! 生物识别需要平台原生提示，当前构建未提供，只支持密码解锁。
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tauri::{Emitter, Manager, State};
+
+use crate::profiles::{active_profile_id, DEFAULT_PROFILE_ID};
+use crate::state::AppState;
+use crate::storage::storage_env_tag;
+
+/// 上锁时受保护命令返回的错误
+pub(crate) const APP_LOCKED: &str = "Locked";
+
+const KEYRING_SERVICE: &str = "iflow-workspace";
+/// 当前校验值格式：`pbkdf2-sha256$<轮数>$<salt hex>$<hash hex>`
+const VERIFIER_SCHEME: &str = "pbkdf2-sha256";
+const PBKDF2_ROUNDS: u32 = 600_000;
+/// 旧格式 `<salt hex>$<hash hex>` 使用的迭代 SHA-256 轮数，仅用于校验后升级
+const LEGACY_HASH_ITERATIONS: u32 = 100_000;
+const SALT_BYTES: usize = 16;
+const HASH_BYTES: usize = 32;
+const MIN_PASSWORD_CHARS: usize = 8;
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// 上锁期间仍可调用的命令；其余命令一律返回 Locked
+const UNLOCKED_COMMANDS: &[&str] = &["get_app_lock_status", "unlock_app", "lock_app"];
+/// 前端定时或被事件驱动调用的命令，不代表用户操作，不刷新空闲计时
+const BACKGROUND_COMMANDS: &[&str] = &[
+    "save_storage_snapshot",
+    "get_app_lock_status",
+    "get_job",
+    "list_jobs",
+    "list_task_records",
+    "get_unread_counts",
+    "get_quiet_hours_status",
+    "list_iflow_history_sessions",
+    "list_agent_sessions",
+];
+/// 连续输错超过此次数后开始退避
+const FREE_UNLOCK_ATTEMPTS: u32 = 3;
+const MAX_UNLOCK_BACKOFF: Duration = Duration::from_secs(300);
+/// 上锁期间暂存的事件上限，超出后丢弃新事件
+const MAX_PENDING_EVENTS: usize = 5000;
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AppLockStatus {
+    pub enabled: bool,
+    pub locked: bool,
+    pub timeout_secs: Option<u64>,
+}
+
+struct LockInner {
+    enabled: bool,
+    locked: bool,
+    timeout: Option<Duration>,
+    last_activity: Instant,
+    failed_attempts: u32,
+    retry_after: Option<Instant>,
+    pending_events: VecDeque<(String, Value)>,
+}
+
+pub(crate) struct AppLockState {
+    inner: Mutex<LockInner>,
+}
+
+impl Default for AppLockState {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(LockInner {
+                enabled: false,
+                locked: false,
+                timeout: None,
+                last_activity: Instant::now(),
+                failed_attempts: 0,
+                retry_after: None,
+                pending_events: VecDeque::new(),
+            }),
+        }
+    }
+}
+
+impl AppLockState {
+    /// 启动或修改密码时调用；启用时立即上锁
+    fn set_enabled(&self, enabled: bool, lock_now: bool) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.enabled = enabled;
+            inner.locked = enabled && lock_now;
+            inner.last_activity = Instant::now();
+        }
+    }
+
+    pub(crate) fn set_timeout(&self, timeout_secs: Option<u64>) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.timeout = timeout_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs);
+        }
+    }
+
+    fn status(&self) -> AppLockStatus {
+        self.inner
+            .lock()
+            .map(|inner| AppLockStatus {
+                enabled: inner.enabled,
+                locked: inner.locked,
+                timeout_secs: inner.timeout.map(|timeout| timeout.as_secs()),
+            })
+            .unwrap_or(AppLockStatus {
+                enabled: true,
+                locked: true,
+                timeout_secs: None,
+            })
+    }
+
+    fn set_locked(&self, locked: bool) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.locked = inner.enabled && locked;
+            inner.last_activity = Instant::now();
+        }
+    }
+
+    /// 解锁并按顺序补发上锁期间暂存的事件；持锁补发，保证先于解锁后的新事件到达
+    fn unlock_and_flush(&self, app_handle: &tauri::AppHandle) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        inner.locked = false;
+        inner.last_activity = Instant::now();
+        inner.failed_attempts = 0;
+        inner.retry_after = None;
+        for (event, payload) in inner.pending_events.drain(..) {
+            let _ = app_handle.emit(&event, payload);
+        }
+    }
+
+    /// 上锁时暂存事件并返回 None，否则原样返回 payload 交给调用方推送
+    fn hold_event(&self, event: &str, payload: Value) -> Option<Value> {
+        let Ok(mut inner) = self.inner.lock() else {
+            return Some(payload);
+        };
+        if !inner.locked {
+            return Some(payload);
+        }
+        if inner.pending_events.len() < MAX_PENDING_EVENTS {
+            inner.pending_events.push_back((event.to_string(), payload));
+        } else if inner.pending_events.len() == MAX_PENDING_EVENTS {
+            println!("[app-lock] Pending event queue full, dropping events while locked");
+        }
+        None
+    }
+
+    /// 退避期内返回还需等待的时长
+    fn unlock_wait(&self, now: Instant) -> Option<Duration> {
+        let inner = self.inner.lock().ok()?;
+        inner
+            .retry_after
+            .filter(|retry_after| *retry_after > now)
+            .map(|retry_after| retry_after - now)
+    }
+
+    fn record_failed_unlock(&self, now: Instant) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.failed_attempts = inner.failed_attempts.saturating_add(1);
+            inner.retry_after = Some(now + unlock_backoff(inner.failed_attempts));
+        }
+    }
+
+    /// 空闲超时则上锁，返回本次是否新上锁
+    fn expire_if_idle(&self, now: Instant) -> bool {
+        let Ok(mut inner) = self.inner.lock() else {
+            return false;
+        };
+        let idle = inner
+            .timeout
+            .map(|timeout| now.duration_since(inner.last_activity) >= timeout)
+            .unwrap_or(false);
+        if inner.enabled && !inner.locked && idle {
+            inner.locked = true;
+            return true;
+        }
+        false
+    }
+
+    /// 是否处于上锁状态（先按空闲时长判断是否该上锁），不刷新活跃时间
+    pub(crate) fn is_locked(&self, now: Instant) -> bool {
+        self.expire_if_idle(now);
+        self.inner.lock().map(|inner| inner.locked).unwrap_or(true)
+    }
+
+    /// 命令分发前调用：受保护命令在上锁时被拒绝，其余命令只刷新活跃时间（后台命令除外）
+    pub(crate) fn check_command(&self, command: &str, now: Instant) -> Result<(), String> {
+        self.expire_if_idle(now);
+        let Ok(mut inner) = self.inner.lock() else {
+            return Err(APP_LOCKED.to_string());
+        };
+        if inner.locked && !UNLOCKED_COMMANDS.contains(&command) {
+            return Err(APP_LOCKED.to_string());
+        }
+        if !inner.locked && !BACKGROUND_COMMANDS.contains(&command) {
+            inner.last_activity = now;
+        }
+        Ok(())
+    }
+}
+
+/// 前几次输错不等待，之后每次翻倍（1s、2s、4s ...），最长 5 分钟
+fn unlock_backoff(failed_attempts: u32) -> Duration {
+    if failed_attempts <= FREE_UNLOCK_ATTEMPTS {
+        return Duration::ZERO;
+    }
+    let exponent = (failed_attempts - FREE_UNLOCK_ATTEMPTS - 1).min(16);
+    Duration::from_secs(1 << exponent).min(MAX_UNLOCK_BACKOFF)
+}
+
+/// 推送事件给前端；应用上锁期间暂存，解锁后补发，避免会话内容出现在锁定的界面中
+pub(crate) trait EmitUnlessLocked {
+    fn emit_unless_locked<S: Serialize>(&self, event: &str, payload: S) -> tauri::Result<()>;
+}
+
+impl EmitUnlessLocked for tauri::AppHandle {
+    fn emit_unless_locked<S: Serialize>(&self, event: &str, payload: S) -> tauri::Result<()> {
+        let payload = serde_json::to_value(payload)?;
+        match self.state::<AppState>().app_lock.hold_event(event, payload) {
+            Some(payload) => self.emit(event, payload),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 钥匙串账户名；默认档案沿用旧名称，其余档案各自独立
+fn keyring_account(profile_id: &str) -> String {
+    if profile_id == DEFAULT_PROFILE_ID {
+        format!("app-lock-{}", storage_env_tag())
+    } else {
+        format!("app-lock-{}-{}", storage_env_tag(), profile_id)
+    }
+}
+
+fn keyring_entry(state: &AppState) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, &keyring_account(&active_profile_id(state)))
+        .map_err(|e| format!("Failed to open OS keychain: {}", e))
+}
+
+fn read_verifier(state: &AppState) -> Result<Option<String>, String> {
+    match keyring_entry(state)?.get_password() {
+        Ok(verifier) => Ok(Some(verifier)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read app lock credential: {}", e)),
+    }
+}
+
+fn derive_password_hash(salt: &[u8], password: &str, rounds: u32) -> Vec<u8> {
+    let mut hash = vec![0_u8; HASH_BYTES];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, rounds, &mut hash);
+    hash
+}
+
+fn legacy_hash_password(salt: &[u8], password: &str) -> Vec<u8> {
+    let mut digest = Sha256::new()
+        .chain_update(salt)
+        .chain_update(password.as_bytes())
+        .finalize();
+    for _ in 1..LEGACY_HASH_ITERATIONS {
+        digest = Sha256::new()
+            .chain_update(salt)
+            .chain_update(digest)
+            .finalize();
+    }
+    digest.to_vec()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+fn build_verifier(password: &str) -> String {
+    let mut salt = [0_u8; SALT_BYTES];
+    OsRng.fill_bytes(&mut salt);
+    format!(
+        "{}${}${}${}",
+        VERIFIER_SCHEME,
+        PBKDF2_ROUNDS,
+        to_hex(&salt),
+        to_hex(&derive_password_hash(&salt, password, PBKDF2_ROUNDS))
+    )
+}
+
+/// 旧格式校验值在解锁成功后改写为当前格式
+fn is_legacy_verifier(verifier: &str) -> bool {
+    !verifier.starts_with(&format!("{}$", VERIFIER_SCHEME))
+}
+
+fn verify_password(verifier: &str, password: &str) -> bool {
+    let parts: Vec<&str> = verifier.split('$').collect();
+    let (rounds, salt, expected) = match parts.as_slice() {
+        [VERIFIER_SCHEME, rounds, salt, expected] => match rounds.parse::<u32>() {
+            Ok(rounds) if rounds > 0 => (Some(rounds), *salt, *expected),
+            _ => return false,
+        },
+        [salt, expected] => (None, *salt, *expected),
+        _ => return false,
+    };
+    let (Some(salt), Some(expected)) = (from_hex(salt), from_hex(expected)) else {
+        return false;
+    };
+    let actual = match rounds {
+        Some(rounds) => derive_password_hash(&salt, password, rounds),
+        None => legacy_hash_password(&salt, password),
+    };
+    constant_time_eq(&actual, &expected)
+}
+
+/// 逐字节累积比较，避免按前缀提前返回泄露匹配长度
+pub(crate) fn constant_time_eq(actual: &[u8], expected: &[u8]) -> bool {
+    actual.len() == expected.len()
+        && actual
+            .iter()
+            .zip(expected)
+            .fold(0_u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn emit_lock_status(app_handle: &tauri::AppHandle, status: &AppLockStatus) {
+    let _ = app_handle.emit("app-lock", status);
+}
+
+/// 启动时根据钥匙串中是否存在凭据决定是否上锁（读取失败时上锁），并启动空闲检测
+pub(crate) async fn init_app_lock(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<AppState>();
+    let timeout_secs = state.settings.read().await.app_lock_timeout_secs;
+    state.app_lock.set_timeout(timeout_secs);
+    match read_verifier(&state) {
+        Ok(verifier) => state.app_lock.set_enabled(verifier.is_some(), true),
+        Err(e) => {
+            // 钥匙串暂不可用时按已设密码处理，解锁时再读取并返回具体错误
+            println!("[app-lock] {}", e);
+            state.app_lock.set_enabled(true, true);
+        }
+    }
+
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(IDLE_CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let state = app_handle.state::<AppState>();
+            if state.app_lock.expire_if_idle(Instant::now()) {
+                emit_lock_status(&app_handle, &state.app_lock.status());
+            }
+        }
+    });
+}
+
+/// 切换档案后改用新档案的密码：新档案设有密码时立即上锁
+pub(crate) async fn reload_app_lock(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<AppState>();
+    let timeout_secs = state.settings.read().await.app_lock_timeout_secs;
+    state.app_lock.set_timeout(timeout_secs);
+    match read_verifier(&state) {
+        Ok(verifier) => state.app_lock.set_enabled(verifier.is_some(), true),
+        Err(e) => {
+            // 钥匙串暂不可用时按已设密码处理，解锁时再读取并返回具体错误
+            println!("[app-lock] {}", e);
+            state.app_lock.set_enabled(true, true);
+        }
+    }
+    emit_lock_status(app_handle, &state.app_lock.status());
+}
+
+#[tauri::command]
+pub async fn get_app_lock_status(state: State<'_, AppState>) -> Result<AppLockStatus, String> {
+    Ok(state.app_lock.status())
+}
+
+/// 设置、修改或移除（new_password 为空）应用锁密码；已启用时需提供当前密码
+#[tauri::command]
+pub async fn set_app_lock_password(
+    state: State<'_, AppState>,
+    current_password: Option<String>,
+    new_password: Option<String>,
+) -> Result<AppLockStatus, String> {
+    if let Some(verifier) = read_verifier(&state)? {
+        let current = current_password.unwrap_or_default();
+        if !verify_password(&verifier, &current) {
+            return Err("Current password is incorrect".to_string());
+        }
+    }
+
+    let entry = keyring_entry(&state)?;
+    match new_password.filter(|password| !password.is_empty()) {
+        Some(password) => {
+            if password.chars().count() < MIN_PASSWORD_CHARS {
+                return Err(format!(
+                    "Password must be at least {} characters",
+                    MIN_PASSWORD_CHARS
+                ));
+            }
+            entry
+                .set_password(&build_verifier(&password))
+                .map_err(|e| format!("Failed to store app lock credential: {}", e))?;
+            state.app_lock.set_enabled(true, false);
+        }
+        None => {
+            match entry.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => {}
+                Err(e) => return Err(format!("Failed to remove app lock credential: {}", e)),
+            }
+            state.app_lock.set_enabled(false, false);
+        }
+    }
+    Ok(state.app_lock.status())
+}
+
+#[tauri::command]
+pub async fn unlock_app(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    password: String,
+) -> Result<AppLockStatus, String> {
+    if let Some(wait) = state.app_lock.unlock_wait(Instant::now()) {
+        return Err(format!(
+            "Too many failed attempts, retry in {} seconds",
+            wait.as_secs().max(1)
+        ));
+    }
+    let Some(verifier) = read_verifier(&state)? else {
+        state.app_lock.set_enabled(false, false);
+        return Ok(state.app_lock.status());
+    };
+    // 校验较慢，放到阻塞线程池；旧格式校验值顺带改写为当前格式
+    let legacy = is_legacy_verifier(&verifier);
+    let (valid, upgraded) = tokio::task::spawn_blocking(move || {
+        let valid = verify_password(&verifier, &password);
+        (valid, (valid && legacy).then(|| build_verifier(&password)))
+    })
+    .await
+    .map_err(|e| format!("Unlock task failed: {}", e))?;
+    if !valid {
+        state.app_lock.record_failed_unlock(Instant::now());
+        return Err("Incorrect password".to_string());
+    }
+    if let Some(verifier) = upgraded {
+        if let Err(e) = keyring_entry(&state).and_then(|entry| {
+            entry
+                .set_password(&verifier)
+                .map_err(|e| format!("Failed to store app lock credential: {}", e))
+        }) {
+            println!("[app-lock] Failed to upgrade credential: {}", e);
+        }
+    }
+    let status = AppLockStatus {
+        locked: false,
+        ..state.app_lock.status()
+    };
+    emit_lock_status(&app_handle, &status);
+    state.app_lock.unlock_and_flush(&app_handle);
+    Ok(state.app_lock.status())
+}
+
+#[tauri::command]
+pub async fn lock_app(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<AppLockStatus, String> {
+    state.app_lock.set_locked(true);
+    let status = state.app_lock.status();
+    emit_lock_status(&app_handle, &status);
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifier_accepts_only_matching_password() {
+        let verifier = build_verifier("correct horse");
+        assert!(verify_password(&verifier, "correct horse"));
+        assert!(!verify_password(&verifier, "wrong"));
+        assert!(!verify_password("not-a-verifier", "correct horse"));
+        assert_ne!(build_verifier("correct horse"), verifier);
+
+        let parts: Vec<&str> = verifier.split('$').collect();
+        assert_eq!(parts[0], VERIFIER_SCHEME);
+        assert_eq!(parts[1], PBKDF2_ROUNDS.to_string());
+        assert_eq!(parts[2].len(), SALT_BYTES * 2);
+        assert!(!is_legacy_verifier(&verifier));
+    }
+
+    #[test]
+    fn legacy_verifiers_still_unlock() {
+        let salt = [7_u8; SALT_BYTES];
+        let legacy = format!(
+            "{}${}",
+            to_hex(&salt),
+            to_hex(&legacy_hash_password(&salt, "correct horse"))
+        );
+        assert!(is_legacy_verifier(&legacy));
+        assert!(verify_password(&legacy, "correct horse"));
+        assert!(!verify_password(&legacy, "wrong"));
+        assert!(!verify_password(
+            &format!("{}$0${}$00", VERIFIER_SCHEME, to_hex(&salt)),
+            ""
+        ));
+    }
+
+    #[test]
+    fn each_profile_has_its_own_keyring_account() {
+        let default_account = keyring_account(DEFAULT_PROFILE_ID);
+        assert_eq!(default_account, format!("app-lock-{}", storage_env_tag()));
+        assert_ne!(keyring_account("alice-1234abcd"), default_account);
+        assert_ne!(
+            keyring_account("alice-1234abcd"),
+            keyring_account("bob-5678abcd")
+        );
+    }
+
+    #[test]
+    fn locked_state_blocks_protected_commands_and_auto_locks() {
+        let lock = AppLockState::default();
+        let start = Instant::now();
+        assert!(lock.check_command("load_storage_snapshot", start).is_ok());
+
+        lock.set_enabled(true, true);
+        assert_eq!(
+            lock.check_command("load_storage_snapshot", start),
+            Err(APP_LOCKED.to_string())
+        );
+        assert!(lock.check_command("get_app_lock_status", start).is_ok());
+        for command in ["connect_iflow", "send_message", "read_html_artifact"] {
+            assert_eq!(
+                lock.check_command(command, start),
+                Err(APP_LOCKED.to_string())
+            );
+        }
+        assert!(lock
+            .hold_event("stream-message", Value::from("chunk"))
+            .is_none());
+
+        lock.set_timeout(Some(60));
+        lock.set_locked(false);
+        let now = Instant::now();
+        assert!(lock.check_command("get_app_settings", now).is_ok());
+        assert!(lock
+            .check_command("save_storage_snapshot", now + Duration::from_secs(30))
+            .is_ok());
+        assert!(!lock.expire_if_idle(now + Duration::from_secs(30)));
+        assert!(lock.expire_if_idle(now + Duration::from_secs(61)));
+        assert!(lock.status().locked);
+        assert_eq!(lock.inner.lock().unwrap().pending_events.len(), 1);
+    }
+
+    #[test]
+    fn failed_unlocks_back_off() {
+        assert_eq!(unlock_backoff(FREE_UNLOCK_ATTEMPTS), Duration::ZERO);
+        assert_eq!(
+            unlock_backoff(FREE_UNLOCK_ATTEMPTS + 1),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            unlock_backoff(FREE_UNLOCK_ATTEMPTS + 3),
+            Duration::from_secs(4)
+        );
+        assert_eq!(unlock_backoff(u32::MAX), MAX_UNLOCK_BACKOFF);
+
+        let lock = AppLockState::default();
+        let now = Instant::now();
+        for _ in 0..=FREE_UNLOCK_ATTEMPTS {
+            assert!(lock.unlock_wait(now).is_none());
+            lock.record_failed_unlock(now);
+        }
+        assert_eq!(lock.unlock_wait(now), Some(Duration::from_secs(1)));
+        assert!(lock.unlock_wait(now + Duration::from_secs(2)).is_none());
+    }
+}
@@ -1,16 +1,103 @@
-//! HTML Artifact 路径解析与安全读取
+//! HTML / Notebook Artifact 路径解析与安全读取
 use std::path::{Path, PathBuf};
 use std::time::Instant;
 
+use serde::Serialize;
+use serde_json::Value;
 use tauri::State;
 
+use crate::agents::remote::is_remote_agent_type;
+use crate::artifact_csp::{
+    apply_artifact_csp, artifact_csp_policy, effective_artifact_csp, ArtifactCspReport,
+};
+use crate::history::workspace_path_variants;
+use crate::html_sanitize::{sanitize_html, HtmlSanitizeReport};
 use crate::state::AppState;
+use crate::wsl_path::to_workspace_host_path;
 
 const MAX_HTML_ARTIFACT_SIZE: u64 = 2 * 1024 * 1024;
+const MAX_NOTEBOOK_ARTIFACT_SIZE: u64 = 20 * 1024 * 1024;
+const MAX_NOTEBOOK_CELLS: usize = 500;
+const MAX_NOTEBOOK_TEXT_CHARS: usize = 64 * 1024;
+const MAX_NOTEBOOK_IMAGE_BYTES: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HtmlArtifact {
+    pub content: String,
+    /// 未开启净化时为空
+    pub sanitized: Option<HtmlSanitizeReport>,
+    /// 关闭 CSP 时为空
+    pub csp: Option<ArtifactCspReport>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookArtifact {
+    pub path: String,
+    pub language: Option<String>,
+    pub cells: Vec<NotebookCell>,
+    pub total_cells: usize,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NotebookCell {
+    pub cell_type: String,
+    pub source: String,
+    pub execution_count: Option<i64>,
+    pub outputs: Vec<NotebookOutput>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum NotebookOutput {
+    Stream {
+        name: String,
+        text: String,
+    },
+    Text {
+        text: String,
+    },
+    Html {
+        html: String,
+    },
+    Markdown {
+        text: String,
+    },
+    Image {
+        mime: String,
+        data: String,
+    },
+    Error {
+        ename: String,
+        evalue: String,
+        traceback: String,
+    },
+    Omitted {
+        reason: String,
+    },
+}
 
 async fn resolve_html_artifact_path_in_workspace(
     workspace_path: &str,
     file_path: &str,
+) -> Result<PathBuf, String> {
+    resolve_artifact_path_in_workspace(
+        workspace_path,
+        file_path,
+        &["html", "htm"],
+        "Only .html/.htm artifacts are supported",
+    )
+    .await
+}
+
+pub(crate) async fn resolve_artifact_path_in_workspace(
+    workspace_path: &str,
+    file_path: &str,
+    allowed_extensions: &[&str],
+    unsupported_message: &str,
 ) -> Result<PathBuf, String> {
     let workspace_root = tokio::fs::canonicalize(workspace_path).await.map_err(|e| {
         format!(
@@ -23,6 +110,8 @@ async fn resolve_html_artifact_path_in_workspace(
     if requested_path.is_empty() {
         return Err("Artifact file path cannot be empty".to_string());
     }
+    let requested_path =
+        to_workspace_host_path(workspace_path, &requested_path).unwrap_or(requested_path);
 
     let requested = PathBuf::from(&requested_path);
     let is_absolute_request = requested.is_absolute();
@@ -40,8 +129,11 @@ async fn resolve_html_artifact_path_in_workspace(
         )
     })?;
 
-    if !is_absolute_request && !canonical_target.starts_with(&workspace_root) {
-        return Err("Artifact path is outside workspace".to_string());
+    if !is_absolute_request {
+        let workspace_roots = workspace_path_variants(workspace_path).await;
+        if !is_within_workspace(&canonical_target, &workspace_root, &workspace_roots) {
+            return Err("Artifact path is outside workspace".to_string());
+        }
     }
 
     let extension = canonical_target
@@ -49,13 +141,25 @@ async fn resolve_html_artifact_path_in_workspace(
         .and_then(|ext| ext.to_str())
         .unwrap_or_default()
         .to_lowercase();
-    if extension != "html" && extension != "htm" {
-        return Err("Only .html/.htm artifacts are supported".to_string());
+    if !allowed_extensions.contains(&extension.as_str()) {
+        return Err(unsupported_message.to_string());
     }
 
     Ok(canonical_target)
 }
 
+/// 目标（已解析符号链接）位于工作区真实路径或词法路径之下即视为在工作区内
+fn is_within_workspace(
+    canonical_target: &Path,
+    workspace_root: &Path,
+    workspace_roots: &[String],
+) -> bool {
+    canonical_target.starts_with(workspace_root)
+        || workspace_roots
+            .iter()
+            .any(|root| canonical_target.starts_with(root))
+}
+
 fn is_windows_absolute_like(path: &str) -> bool {
     let bytes = path.as_bytes();
     if bytes.len() < 3 {
@@ -141,6 +245,13 @@ fn normalize_artifact_request_path(file_path: &str) -> String {
 }
 
 async fn validate_html_artifact_file(canonical_target: &Path) -> Result<(), String> {
+    validate_artifact_file(canonical_target, MAX_HTML_ARTIFACT_SIZE).await
+}
+
+pub(crate) async fn validate_artifact_file(
+    canonical_target: &Path,
+    max_size: u64,
+) -> Result<(), String> {
     let metadata = tokio::fs::metadata(canonical_target).await.map_err(|e| {
         format!(
             "Failed to stat artifact {}: {}",
@@ -151,15 +262,33 @@ async fn validate_html_artifact_file(canonical_target: &Path) -> Result<(), Stri
     if !metadata.is_file() {
         return Err("Artifact path is not a file".to_string());
     }
-    if metadata.len() > MAX_HTML_ARTIFACT_SIZE {
-        return Err(format!(
-            "Artifact is too large (>{} bytes)",
-            MAX_HTML_ARTIFACT_SIZE
-        ));
+    if metadata.len() > max_size {
+        return Err(format!("Artifact is too large (>{} bytes)", max_size));
     }
     Ok(())
 }
 
+/// 产物命令所用的 Agent 工作区；远程 Agent 的工作区在远程主机上，同名本机路径不是它写出的文件
+pub(crate) async fn local_artifact_workspace(
+    state: &AppState,
+    agent_id: &str,
+) -> Result<String, String> {
+    let workspace_path = state
+        .agent_manager
+        .workspace_path_of(agent_id)
+        .await
+        .ok_or_else(|| format!("Agent {} not found", agent_id))?;
+    if state
+        .agent_manager
+        .agent_type_of(agent_id)
+        .await
+        .is_some_and(|agent_type| is_remote_agent_type(&agent_type))
+    {
+        return Err("Artifacts of remote agents are not available locally".to_string());
+    }
+    Ok(workspace_path)
+}
+
 /// 解析 HTML Artifact 的绝对路径（限制在当前 Agent 工作目录内）
 #[tauri::command]
 pub async fn resolve_html_artifact_path(
@@ -167,35 +296,44 @@ pub async fn resolve_html_artifact_path(
     agent_id: String,
     file_path: String,
 ) -> Result<String, String> {
-    let workspace_path = state
-        .agent_manager
-        .workspace_path_of(&agent_id)
-        .await
-        .ok_or_else(|| format!("Agent {} not found", agent_id))?;
+    let workspace_path = local_artifact_workspace(&state, &agent_id).await?;
+    // 原始文件地址会绕过净化与 CSP 注入
+    {
+        let settings = state.settings.read().await;
+        if settings.sanitize_html_artifacts {
+            return Err(
+                "HTML artifact sanitization is enabled; raw artifact URLs are disabled".to_string(),
+            );
+        }
+        let csp_settings = effective_artifact_csp(
+            &settings.artifact_csp,
+            &settings.workspace_artifact_csp,
+            &workspace_path,
+        );
+        if artifact_csp_policy(&csp_settings).is_some() {
+            return Err("Artifact CSP is enabled; raw artifact URLs are disabled".to_string());
+        }
+    }
     let canonical_target =
         resolve_html_artifact_path_in_workspace(&workspace_path, &file_path).await?;
     validate_html_artifact_file(&canonical_target).await?;
     Ok(canonical_target.to_string_lossy().to_string())
 }
 
-/// 读取 HTML Artifact（限制在当前 Agent 工作目录内）
+/// 读取 HTML Artifact（限制在当前 Agent 工作目录内），按设置净化并注入 CSP
 #[tauri::command]
 pub async fn read_html_artifact(
     state: State<'_, AppState>,
     agent_id: String,
     file_path: String,
-) -> Result<String, String> {
+) -> Result<HtmlArtifact, String> {
     let started_at = Instant::now();
     println!(
         "[read_html_artifact] start agent={} path={}",
         agent_id, file_path
     );
 
-    let workspace_path = state
-        .agent_manager
-        .workspace_path_of(&agent_id)
-        .await
-        .ok_or_else(|| format!("Agent {} not found", agent_id))?;
+    let workspace_path = local_artifact_workspace(&state, &agent_id).await?;
     let canonical_target =
         resolve_html_artifact_path_in_workspace(&workspace_path, &file_path).await?;
     validate_html_artifact_file(&canonical_target).await?;
@@ -210,6 +348,40 @@ pub async fn read_html_artifact(
         )
     })?;
 
+    let settings = state.settings.read().await.clone();
+    let (content, sanitized) = if settings.sanitize_html_artifacts {
+        let (content, report) = sanitize_html(&content);
+        if report.removed_anything() {
+            println!(
+                "[read_html_artifact] sanitized path={} report={:?}",
+                canonical_target.display(),
+                report
+            );
+        }
+        (content, Some(report))
+    } else {
+        (content, None)
+    };
+    // 净化会去掉 <meta>，CSP 在净化之后注入
+    let csp_settings = effective_artifact_csp(
+        &settings.artifact_csp,
+        &settings.workspace_artifact_csp,
+        &workspace_path,
+    );
+    let (content, csp) = match apply_artifact_csp(&content, &csp_settings) {
+        Some((content, report)) => {
+            if !report.blocked_urls.is_empty() {
+                println!(
+                    "[read_html_artifact] blocked external urls path={} count={}",
+                    canonical_target.display(),
+                    report.blocked_urls.len()
+                );
+            }
+            (content, Some(report))
+        }
+        None => (content, None),
+    };
+
     println!(
         "[read_html_artifact] done agent={} path={} bytes={} elapsed={}ms",
         agent_id,
@@ -218,5 +390,350 @@ pub async fn read_html_artifact(
         started_at.elapsed().as_millis()
     );
 
-    Ok(content)
+    Ok(HtmlArtifact {
+        content,
+        sanitized,
+        csp,
+    })
+}
+
+fn notebook_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .concat(),
+        _ => String::new(),
+    }
+}
+
+fn truncate_notebook_text(text: String) -> String {
+    if text.chars().count() <= MAX_NOTEBOOK_TEXT_CHARS {
+        return text;
+    }
+    let mut truncated: String = text.chars().take(MAX_NOTEBOOK_TEXT_CHARS).collect();
+    truncated.push_str("\n…(truncated)");
+    truncated
+}
+
+fn strip_ansi_codes(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\u{1b}' && chars.peek() == Some(&'[') {
+            chars.next();
+            for next in chars.by_ref() {
+                if next.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        output.push(ch);
+    }
+    output
+}
+
+/// `sanitize` 为真时 HTML / SVG 输出与 HTML Artifact 一样先净化再交给 WebView
+fn convert_rich_output(data: &Value, sanitize: bool) -> NotebookOutput {
+    for mime in ["image/png", "image/jpeg", "image/gif"] {
+        if let Some(image) = data.get(mime) {
+            let encoded: String = notebook_text(Some(image))
+                .chars()
+                .filter(|ch| !ch.is_whitespace())
+                .collect();
+            if encoded.len() > MAX_NOTEBOOK_IMAGE_BYTES {
+                return NotebookOutput::Omitted {
+                    reason: format!("{} output exceeds {} bytes", mime, MAX_NOTEBOOK_IMAGE_BYTES),
+                };
+            }
+            return NotebookOutput::Image {
+                mime: mime.to_string(),
+                data: encoded,
+            };
+        }
+    }
+    if let Some(html) = data.get("image/svg+xml").or_else(|| data.get("text/html")) {
+        let html = notebook_text(Some(html));
+        let html = if sanitize {
+            sanitize_html(&html).0
+        } else {
+            html
+        };
+        return NotebookOutput::Html {
+            html: truncate_notebook_text(html),
+        };
+    }
+    if let Some(markdown) = data.get("text/markdown") {
+        return NotebookOutput::Markdown {
+            text: truncate_notebook_text(notebook_text(Some(markdown))),
+        };
+    }
+    if let Some(text) = data.get("text/plain") {
+        return NotebookOutput::Text {
+            text: truncate_notebook_text(notebook_text(Some(text))),
+        };
+    }
+    NotebookOutput::Omitted {
+        reason: "Unsupported output mime type".to_string(),
+    }
+}
+
+fn convert_notebook_output(output: &Value, sanitize: bool) -> NotebookOutput {
+    match output
+        .get("output_type")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+    {
+        "stream" => NotebookOutput::Stream {
+            name: output
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or("stdout")
+                .to_string(),
+            text: truncate_notebook_text(strip_ansi_codes(&notebook_text(output.get("text")))),
+        },
+        "execute_result" | "display_data" => match output.get("data") {
+            Some(data) => convert_rich_output(data, sanitize),
+            None => NotebookOutput::Omitted {
+                reason: "Output has no data".to_string(),
+            },
+        },
+        "error" => NotebookOutput::Error {
+            ename: output
+                .get("ename")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            evalue: output
+                .get("evalue")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            traceback: truncate_notebook_text(strip_ansi_codes(
+                &output
+                    .get("traceback")
+                    .and_then(Value::as_array)
+                    .map(|lines| {
+                        lines
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    })
+                    .unwrap_or_default(),
+            )),
+        },
+        other => NotebookOutput::Omitted {
+            reason: format!("Unsupported output type: {}", other),
+        },
+    }
+}
+
+fn parse_notebook(path: &str, raw: &str, sanitize: bool) -> Result<NotebookArtifact, String> {
+    let notebook: Value =
+        serde_json::from_str(raw).map_err(|e| format!("Invalid notebook JSON: {}", e))?;
+    let raw_cells = notebook
+        .get("cells")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "Notebook has no cells array (nbformat 4 required)".to_string())?;
+
+    let language = notebook
+        .get("metadata")
+        .and_then(|meta| {
+            meta.get("language_info")
+                .and_then(|info| info.get("name"))
+                .or_else(|| meta.get("kernelspec").and_then(|spec| spec.get("language")))
+        })
+        .and_then(Value::as_str)
+        .map(|value| value.to_string());
+
+    let cells = raw_cells
+        .iter()
+        .take(MAX_NOTEBOOK_CELLS)
+        .map(|cell| NotebookCell {
+            cell_type: cell
+                .get("cell_type")
+                .and_then(Value::as_str)
+                .unwrap_or("raw")
+                .to_string(),
+            source: truncate_notebook_text(notebook_text(cell.get("source"))),
+            execution_count: cell.get("execution_count").and_then(Value::as_i64),
+            outputs: cell
+                .get("outputs")
+                .and_then(Value::as_array)
+                .map(|outputs| {
+                    outputs
+                        .iter()
+                        .map(|output| convert_notebook_output(output, sanitize))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+        .collect::<Vec<_>>();
+
+    Ok(NotebookArtifact {
+        path: path.to_string(),
+        language,
+        total_cells: raw_cells.len(),
+        truncated: raw_cells.len() > cells.len(),
+        cells,
+    })
+}
+
+/// 读取 .ipynb Artifact 并转换为可渲染结构（限制在当前 Agent 工作目录内）
+#[tauri::command]
+pub async fn read_notebook_artifact(
+    state: State<'_, AppState>,
+    agent_id: String,
+    file_path: String,
+) -> Result<NotebookArtifact, String> {
+    let workspace_path = local_artifact_workspace(&state, &agent_id).await?;
+    let canonical_target = resolve_artifact_path_in_workspace(
+        &workspace_path,
+        &file_path,
+        &["ipynb"],
+        "Only .ipynb artifacts are supported",
+    )
+    .await?;
+    validate_artifact_file(&canonical_target, MAX_NOTEBOOK_ARTIFACT_SIZE).await?;
+
+    let raw = tokio::fs::read_to_string(&canonical_target)
+        .await
+        .map_err(|e| {
+            format!(
+                "Failed to read notebook {}: {}",
+                canonical_target.display(),
+                e
+            )
+        })?;
+    // 片段无法注入 CSP，启用 CSP 时同样净化
+    let sanitize = {
+        let settings = state.settings.read().await;
+        settings.sanitize_html_artifacts
+            || artifact_csp_policy(&effective_artifact_csp(
+                &settings.artifact_csp,
+                &settings.workspace_artifact_csp,
+                &workspace_path,
+            ))
+            .is_some()
+    };
+    parse_notebook(&canonical_target.to_string_lossy(), &raw, sanitize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn resolve_artifact_path_accepts_symlinked_workspace() {
+        let base = std::env::temp_dir().join(format!("iflow-artifact-{}", uuid::Uuid::new_v4()));
+        let real = base.join("real");
+        let link = base.join("link");
+        std::fs::create_dir_all(&real).expect("create real dir");
+        std::os::unix::fs::symlink(&real, &link).expect("create symlink");
+        std::fs::write(real.join("report.html"), "<html></html>").expect("write html");
+
+        let resolved =
+            resolve_html_artifact_path_in_workspace(&link.to_string_lossy(), "report.html")
+                .await
+                .expect("resolve through symlink");
+        assert!(resolved.ends_with("report.html"));
+        assert!(resolve_html_artifact_path_in_workspace(
+            &link.to_string_lossy(),
+            "../outside.html"
+        )
+        .await
+        .is_err());
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[cfg(target_os = "macos")]
+    #[tokio::test]
+    async fn resolve_artifact_path_handles_macos_tmp_alias() {
+        let workspace = format!("/tmp/iflow-artifact-{}", uuid::Uuid::new_v4());
+        std::fs::create_dir_all(&workspace).expect("create workspace");
+        std::fs::write(format!("{}/index.html", workspace), "<html></html>").expect("write html");
+
+        let resolved = resolve_html_artifact_path_in_workspace(&workspace, "index.html")
+            .await
+            .expect("resolve under /tmp");
+        assert!(resolved.starts_with("/private/tmp"));
+
+        let _ = std::fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn parse_notebook_converts_cells_and_outputs() {
+        let raw = r##"{
+            "metadata": { "language_info": { "name": "python" } },
+            "nbformat": 4,
+            "cells": [
+                { "cell_type": "markdown", "source": ["# Title\n", "text"] },
+                {
+                    "cell_type": "code",
+                    "execution_count": 3,
+                    "source": "print(1)",
+                    "outputs": [
+                        { "output_type": "stream", "name": "stdout", "text": ["1\n"] },
+                        { "output_type": "display_data", "data": { "image/png": "aGVs\nbG8=" } },
+                        { "output_type": "error", "ename": "ValueError", "evalue": "bad", "traceback": ["\u001b[31mline\u001b[0m"] }
+                    ]
+                }
+            ]
+        }"##;
+
+        let notebook = parse_notebook("/tmp/demo.ipynb", raw, false).expect("parse notebook");
+        assert_eq!(notebook.language.as_deref(), Some("python"));
+        assert_eq!(notebook.cells.len(), 2);
+        assert_eq!(notebook.cells[0].source, "# Title\ntext");
+        assert_eq!(notebook.cells[1].execution_count, Some(3));
+        assert_eq!(
+            notebook.cells[1].outputs[1],
+            NotebookOutput::Image {
+                mime: "image/png".to_string(),
+                data: "aGVsbG8=".to_string(),
+            }
+        );
+        assert_eq!(
+            notebook.cells[1].outputs[2],
+            NotebookOutput::Error {
+                ename: "ValueError".to_string(),
+                evalue: "bad".to_string(),
+                traceback: "line".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_notebook_rejects_missing_cells() {
+        assert!(parse_notebook("/tmp/bad.ipynb", "{}", false).is_err());
+    }
+
+    #[test]
+    fn parse_notebook_sanitizes_html_outputs_when_enabled() {
+        let raw = r##"{
+            "cells": [{
+                "cell_type": "code",
+                "source": "display(HTML(...))",
+                "outputs": [
+                    { "output_type": "display_data", "data": { "text/html": "<b>ok</b><script>alert(1)</script>" } }
+                ]
+            }]
+        }"##;
+
+        let html_of = |notebook: NotebookArtifact| match &notebook.cells[0].outputs[0] {
+            NotebookOutput::Html { html } => html.clone(),
+            other => panic!("unexpected output {:?}", other),
+        };
+        let sanitized = html_of(parse_notebook("/tmp/html.ipynb", raw, true).expect("parse"));
+        assert!(sanitized.contains("<b>ok</b>"));
+        assert!(!sanitized.contains("<script"));
+        let raw_html = html_of(parse_notebook("/tmp/html.ipynb", raw, false).expect("parse"));
+        assert!(raw_html.contains("<script>"));
+    }
 }
@@ -0,0 +1,339 @@
+//! HTML Artifact 预览的内容安全策略（CSP）：默认只允许内联与本地资源，拒绝一切外部来源；
+//! 可选地把指向外部地址的资源链接改写为占位地址，并支持按工作区放行指定来源（如 CDN）。
+//! 目前预览通过 `read_html_artifact` 读取内容，策略以 `<meta http-equiv>` 注入文档头部；
+//! `artifact_csp_policy` 生成的策略字符串同样可直接作为预览服务的响应头使用。
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+use crate::history::normalize_workspace_path;
+
+/// 被拦截的外部资源改写后的地址
+const BLOCKED_URL_PLACEHOLDER: &str = "about:blank#blocked";
+
+static HEAD_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<head\b[^>]*>").unwrap());
+static HTML_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<html\b[^>]*>").unwrap());
+static DOCTYPE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^\s*<!doctype[^>]*>").unwrap());
+static EXTERNAL_ATTR_URL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)(\s(?:src|href|action|formaction|poster|data)\s*=\s*["']\s*)((?:https?:)?//[^"'\s]+)"#,
+    )
+    .unwrap()
+});
+static EXTERNAL_CSS_URL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)(url\(\s*["']?\s*|@import\s+["'])((?:https?:)?//[^)"'\s]+)"#).unwrap()
+});
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactCspSettings {
+    /// 关闭后预览内容原样返回
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 额外放行的来源，如 `https://cdn.jsdelivr.net`、`https://*.example.com`
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// 把未放行的外部资源地址改写为占位地址，而不只依赖浏览器拦截
+    #[serde(default)]
+    pub rewrite_external_urls: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for ArtifactCspSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            allowed_origins: Vec::new(),
+            rewrite_external_urls: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactCspReport {
+    pub policy: String,
+    /// 被改写为占位地址的外部资源（按出现顺序去重）
+    pub blocked_urls: Vec<String>,
+}
+
+fn validate_origin(origin: &str) -> Result<(), String> {
+    let host = origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://"))
+        .unwrap_or("");
+    let host_valid = !host.is_empty()
+        && !host.contains('/')
+        && !host
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, ';' | ',' | '\'' | '"' | '<' | '>'));
+    if !host_valid {
+        return Err(format!(
+            "Invalid artifact CSP origin {} (expected https://host[:port])",
+            origin
+        ));
+    }
+    Ok(())
+}
+
+/// 设置保存前校验全局与各工作区放行的来源
+pub(crate) fn validate_artifact_csp(
+    artifact_csp: &ArtifactCspSettings,
+    workspace_artifact_csp: &HashMap<String, ArtifactCspSettings>,
+) -> Result<(), String> {
+    artifact_csp
+        .allowed_origins
+        .iter()
+        .try_for_each(|origin| validate_origin(origin.trim()))?;
+    for (workspace, settings) in workspace_artifact_csp {
+        if workspace.trim().is_empty() {
+            return Err("Workspace path cannot be empty".to_string());
+        }
+        settings
+            .allowed_origins
+            .iter()
+            .try_for_each(|origin| validate_origin(origin.trim()))
+            .map_err(|e| format!("{}: {}", workspace, e))?;
+    }
+    Ok(())
+}
+
+/// 工作区有单独配置时整体替换全局配置
+pub(crate) fn effective_artifact_csp(
+    artifact_csp: &ArtifactCspSettings,
+    workspace_artifact_csp: &HashMap<String, ArtifactCspSettings>,
+    workspace_path: &str,
+) -> ArtifactCspSettings {
+    let normalized = normalize_workspace_path(workspace_path);
+    workspace_artifact_csp
+        .iter()
+        .find(|(path, _)| normalize_workspace_path(path) == normalized)
+        .map_or_else(|| artifact_csp.clone(), |(_, settings)| settings.clone())
+}
+
+/// 生成策略字符串；关闭时返回 None
+pub(crate) fn artifact_csp_policy(settings: &ArtifactCspSettings) -> Option<String> {
+    if !settings.enabled {
+        return None;
+    }
+    let origins: Vec<&str> = settings
+        .allowed_origins
+        .iter()
+        .map(|origin| origin.trim())
+        .filter(|origin| !origin.is_empty())
+        .collect();
+    let directives = [
+        ("default-src", "'self' data: blob:", true),
+        ("script-src", "'self' 'unsafe-inline' 'unsafe-eval'", true),
+        ("style-src", "'self' 'unsafe-inline'", true),
+        ("img-src", "'self' data: blob:", true),
+        ("font-src", "'self' data:", true),
+        ("media-src", "'self' data: blob:", true),
+        ("connect-src", "'self'", true),
+        ("frame-src", "'none'", false),
+        ("object-src", "'none'", false),
+        ("base-uri", "'none'", false),
+        ("form-action", "'none'", false),
+    ];
+    let policy = directives
+        .iter()
+        .map(|(name, sources, with_origins)| {
+            let mut directive = format!("{} {}", name, sources);
+            if *with_origins {
+                for origin in &origins {
+                    directive.push(' ');
+                    directive.push_str(origin);
+                }
+            }
+            directive
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+    Some(policy)
+}
+
+/// `scheme://host[:port]`，协议相对地址按 https 处理
+fn url_origin(url: &str) -> Option<(String, String)> {
+    let (scheme, rest) = match url.find("//") {
+        Some(0) => ("https".to_string(), &url[2..]),
+        Some(index) => (
+            url[..index].trim_end_matches(':').to_ascii_lowercase(),
+            &url[index + 2..],
+        ),
+        None => return None,
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority
+        .rsplit('@')
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    (!host.is_empty()).then_some((scheme, host))
+}
+
+fn origin_allowed(url: &str, allowed_origins: &[String]) -> bool {
+    let Some((scheme, host)) = url_origin(url) else {
+        return false;
+    };
+    allowed_origins.iter().any(|allowed| {
+        let Some((allowed_scheme, allowed_host)) = url_origin(allowed.trim()) else {
+            return false;
+        };
+        if allowed_scheme != scheme {
+            return false;
+        }
+        match allowed_host.strip_prefix("*.") {
+            Some(suffix) => host.ends_with(&format!(".{}", suffix)),
+            None => allowed_host == host,
+        }
+    })
+}
+
+fn rewrite_external_urls(html: &str, allowed_origins: &[String]) -> (String, Vec<String>) {
+    let mut blocked: Vec<String> = Vec::new();
+    let mut replace = |caps: &Captures| {
+        let url = &caps[2];
+        if origin_allowed(url, allowed_origins) {
+            return caps[0].to_string();
+        }
+        if !blocked.iter().any(|existing| existing == url) {
+            blocked.push(url.to_string());
+        }
+        format!("{}{}", &caps[1], BLOCKED_URL_PLACEHOLDER)
+    };
+    let rewritten = EXTERNAL_ATTR_URL
+        .replace_all(html, &mut replace)
+        .into_owned();
+    let rewritten = EXTERNAL_CSS_URL
+        .replace_all(&rewritten, &mut replace)
+        .into_owned();
+    (rewritten, blocked)
+}
+
+/// 注入 CSP `<meta>`（放在所有资源之前），按设置改写外部资源地址
+pub(crate) fn apply_artifact_csp(
+    html: &str,
+    settings: &ArtifactCspSettings,
+) -> Option<(String, ArtifactCspReport)> {
+    let policy = artifact_csp_policy(settings)?;
+    let (html, blocked_urls) = if settings.rewrite_external_urls {
+        rewrite_external_urls(html, &settings.allowed_origins)
+    } else {
+        (html.to_string(), Vec::new())
+    };
+    let meta = format!(
+        r#"<meta http-equiv="Content-Security-Policy" content="{}">"#,
+        policy
+    );
+    let (at, insert) = if let Some(head) = HEAD_TAG.find(&html) {
+        (head.end(), meta)
+    } else if let Some(root) = HTML_TAG.find(&html) {
+        (root.end(), format!("<head>{}</head>", meta))
+    } else {
+        // 保留 doctype 在最前，避免进入怪异模式
+        (DOCTYPE.find(&html).map_or(0, |doctype| doctype.end()), meta)
+    };
+    let mut content = String::with_capacity(html.len() + insert.len());
+    content.push_str(&html[..at]);
+    content.push_str(&insert);
+    content.push_str(&html[at..]);
+    Some((
+        content,
+        ArtifactCspReport {
+            policy,
+            blocked_urls,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn injects_policy_and_rewrites_unlisted_external_urls() {
+        let html = r#"<!doctype html><html><head><title>Chart</title>
+<link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/a.css">
+<script src="https://tracker.example/t.js"></script>
+<style>@import "https://fonts.example/f.css"; body { background: url(//img.example/bg.png); }</style>
+</head><body><img src="chart.png"><img src='https://img.example/x.png'></body></html>"#;
+        let settings = ArtifactCspSettings {
+            enabled: true,
+            allowed_origins: vec!["https://*.jsdelivr.net".to_string()],
+            rewrite_external_urls: true,
+        };
+        let (content, report) = apply_artifact_csp(html, &settings).unwrap();
+
+        assert!(content.starts_with(
+            "<!doctype html><html><head><meta http-equiv=\"Content-Security-Policy\""
+        ));
+        assert!(report
+            .policy
+            .contains("script-src 'self' 'unsafe-inline' 'unsafe-eval' https://*.jsdelivr.net;"));
+        assert!(report.policy.contains("frame-src 'none';"));
+        assert_eq!(
+            report.blocked_urls,
+            vec![
+                "https://tracker.example/t.js",
+                "https://img.example/x.png",
+                "https://fonts.example/f.css",
+                "//img.example/bg.png",
+            ]
+        );
+        assert!(content.contains(r#"href="https://cdn.jsdelivr.net/npm/a.css""#));
+        assert!(content.contains(r#"<script src="about:blank#blocked">"#));
+        assert!(content.contains("url(about:blank#blocked)"));
+        assert!(content.contains(r#"<img src="chart.png">"#));
+
+        let (fragment, report) =
+            apply_artifact_csp("<p>hi</p>", &ArtifactCspSettings::default()).unwrap();
+        assert!(fragment.starts_with("<meta http-equiv"));
+        assert!(report.blocked_urls.is_empty());
+        assert!(apply_artifact_csp(
+            "<p>hi</p>",
+            &ArtifactCspSettings {
+                enabled: false,
+                ..Default::default()
+            }
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn workspace_overrides_replace_global_settings_and_origins_are_validated() {
+        let global = ArtifactCspSettings::default();
+        let mut workspaces = HashMap::new();
+        workspaces.insert(
+            "/work/dashboard/".to_string(),
+            ArtifactCspSettings {
+                allowed_origins: vec!["https://cdn.example.com".to_string()],
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            effective_artifact_csp(&global, &workspaces, "/work/dashboard").allowed_origins,
+            vec!["https://cdn.example.com"]
+        );
+        assert_eq!(
+            effective_artifact_csp(&global, &workspaces, "/work/other"),
+            global
+        );
+        assert!(validate_artifact_csp(&global, &workspaces).is_ok());
+
+        workspaces.insert(
+            "/work/bad".to_string(),
+            ArtifactCspSettings {
+                allowed_origins: vec!["https://cdn.example.com/path; script-src *".to_string()],
+                ..Default::default()
+            },
+        );
+        assert!(validate_artifact_csp(&global, &workspaces).is_err());
+        assert!(validate_origin("ftp://cdn.example.com").is_err());
+        assert!(validate_origin("http://localhost:8080").is_ok());
+    }
+}
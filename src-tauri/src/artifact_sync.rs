@@ -0,0 +1,196 @@
+//! 产物发布：把 Agent 生成的报告、HTML 页面等上传到设置中的远端存储（S3 兼容 / WebDAV），
+//! 对象键为 `<工作区前缀>/<相对工作区的路径>`，返回公开地址或预签名链接。
+use std::collections::HashMap;
+use std::path::{Component, Path};
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::artifact::{resolve_artifact_path_in_workspace, validate_artifact_file};
+use crate::history::normalize_workspace_path;
+use crate::remote_storage::{RemoteStorage, MAX_PRESIGN_SECS};
+use crate::state::AppState;
+
+const MAX_PUBLISH_FILE_SIZE: u64 = 50 * 1024 * 1024;
+const DEFAULT_PREFIX_ROOT: &str = "artifacts";
+pub(crate) const PUBLISHABLE_EXTENSIONS: &[&str] = &[
+    "html", "htm", "pdf", "md", "txt", "csv", "json", "ipynb", "png", "jpg", "jpeg", "gif", "svg",
+    "webp",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishedArtifact {
+    /// 相对工作区的路径
+    pub path: String,
+    pub key: String,
+    pub url: String,
+}
+
+fn content_type_for(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "pdf" => "application/pdf",
+        "md" => "text/markdown; charset=utf-8",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "json" | "ipynb" => "application/json",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+fn sanitize_segment(segment: &str) -> String {
+    let cleaned: String = segment
+        .trim()
+        .chars()
+        .map(|ch| {
+            if ch.is_alphanumeric() || matches!(ch, '.' | '_' | '-') {
+                ch
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let cleaned = cleaned.trim_matches(|ch| ch == '-' || ch == '.');
+    if cleaned.is_empty() {
+        "workspace".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// 工作区的对象键前缀：优先使用设置中的映射，否则为 `artifacts/<工作区目录名>`
+fn workspace_prefix(prefixes: &HashMap<String, String>, workspace_path: &str) -> String {
+    let normalized = normalize_workspace_path(workspace_path);
+    let configured = prefixes
+        .iter()
+        .find(|(path, _)| normalize_workspace_path(path) == normalized)
+        .map(|(_, prefix)| prefix.as_str());
+    match configured {
+        Some(prefix) => prefix
+            .split('/')
+            .filter(|segment| !segment.trim().is_empty())
+            .map(sanitize_segment)
+            .collect::<Vec<_>>()
+            .join("/"),
+        None => {
+            let name = normalized.rsplit('/').next().unwrap_or_default();
+            format!("{}/{}", DEFAULT_PREFIX_ROOT, sanitize_segment(name))
+        }
+    }
+}
+
+/// 相对路径转为对象键后缀；只接受普通路径段
+fn relative_key(relative: &Path) -> Result<String, String> {
+    let mut segments = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(segment) => segments.push(segment.to_string_lossy().to_string()),
+            _ => return Err("Artifact path is outside workspace".to_string()),
+        }
+    }
+    if segments.is_empty() {
+        return Err("Artifact file path cannot be empty".to_string());
+    }
+    Ok(segments.join("/"))
+}
+
+/// 上传选中的产物（限制在当前 Agent 工作目录内），返回每个文件的访问链接
+#[tauri::command]
+pub async fn publish_artifacts(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    agent_id: String,
+    file_paths: Vec<String>,
+    expires_secs: Option<u64>,
+) -> Result<Vec<PublishedArtifact>, String> {
+    if file_paths.is_empty() {
+        return Ok(Vec::new());
+    }
+    let workspace_path = state
+        .agent_manager
+        .workspace_path_of(&agent_id)
+        .await
+        .ok_or_else(|| format!("Agent {} not found", agent_id))?;
+    let workspace_root = tokio::fs::canonicalize(&workspace_path)
+        .await
+        .map_err(|e| format!("Failed to resolve workspace path {}: {}", workspace_path, e))?;
+    let prefix = workspace_prefix(
+        &state.settings.read().await.artifact_prefixes,
+        &workspace_path,
+    );
+    let storage = RemoteStorage::from_settings(&app_handle).await?;
+    let expires_secs = expires_secs.unwrap_or(MAX_PRESIGN_SECS);
+
+    let mut published = Vec::with_capacity(file_paths.len());
+    for file_path in &file_paths {
+        let target = resolve_artifact_path_in_workspace(
+            &workspace_path,
+            file_path,
+            PUBLISHABLE_EXTENSIONS,
+            "Unsupported artifact type for publishing",
+        )
+        .await?;
+        let relative = target
+            .strip_prefix(&workspace_root)
+            .map_err(|_| "Artifact path is outside workspace".to_string())?;
+        validate_artifact_file(&target, MAX_PUBLISH_FILE_SIZE).await?;
+
+        let relative_path = relative_key(relative)?;
+        let key = format!("{}/{}", prefix, relative_path);
+        let body = tokio::fs::read(&target)
+            .await
+            .map_err(|e| format!("Failed to read artifact {}: {}", target.display(), e))?;
+        let url = storage.share_url(&key, expires_secs)?;
+        storage.put(&key, body, content_type_for(&target)).await?;
+        published.push(PublishedArtifact {
+            path: relative_path,
+            key,
+            url,
+        });
+    }
+    Ok(published)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixes_are_per_workspace_and_sanitized() {
+        let mut prefixes = HashMap::new();
+        assert_eq!(
+            workspace_prefix(&prefixes, "/work/Quarterly Report/"),
+            "artifacts/Quarterly-Report"
+        );
+
+        prefixes.insert(
+            "/work/site".to_string(),
+            "/public/../site docs/".to_string(),
+        );
+        assert_eq!(
+            workspace_prefix(&prefixes, "/work/site/"),
+            "public/workspace/site-docs"
+        );
+
+        assert_eq!(
+            relative_key(Path::new("reports/summary.html")).unwrap(),
+            "reports/summary.html"
+        );
+        assert!(relative_key(Path::new("../outside.html")).is_err());
+        assert_eq!(
+            content_type_for(Path::new("index.HTML")),
+            "text/html; charset=utf-8"
+        );
+    }
+}
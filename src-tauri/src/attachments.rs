@@ -0,0 +1,434 @@
+//! 会话附件：剪贴板图片与本地文件按内容哈希保存到应用数据目录下的内容寻址存储，
+//! 同一内容无论在多少条消息中附加都只存一份。消息通过 `attachments` 记录引用的哈希，
+//! 保留期清理时删除不再被任何消息引用的文件。
+use std::collections::HashSet;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{Manager, State};
+
+use crate::state::AppState;
+use crate::storage::{app_data_dir, read_snapshot_from_path, storage_path, StorageSnapshot};
+
+const MAX_CLIPBOARD_IMAGE_PIXELS: usize = 64 * 1024 * 1024;
+const MAX_ATTACHMENT_BYTES: u64 = 50 * 1024 * 1024;
+/// 新存入、尚未随消息保存的附件在此期间内不会被清理
+const UNREFERENCED_GRACE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 消息正文中以路径形式出现的附件同样视为引用
+static BLOB_HASH_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b[0-9a-f]{64}\b").expect("valid blob hash pattern"));
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentDescriptor {
+    pub id: String,
+    pub session_id: String,
+    pub file_name: String,
+    pub path: String,
+    pub uri: String,
+    pub mime_type: String,
+    pub size: u64,
+    /// 仅图片附件有尺寸
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// 存储中已存在相同内容时为 true
+    pub deduplicated: bool,
+}
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentRetentionReport {
+    pub removed: Vec<String>,
+    pub freed_bytes: u64,
+}
+
+struct ClipboardImage {
+    width: u32,
+    height: u32,
+    png: Vec<u8>,
+}
+
+/// 内容寻址存储目录，文件以完整 SHA-256 命名
+pub(crate) fn attachment_blobs_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join("attachments").join("blobs"))
+}
+
+fn validate_session_id(session_id: &str) -> Result<String, String> {
+    let trimmed = session_id.trim();
+    if trimmed.is_empty() {
+        return Err("Session id cannot be empty".to_string());
+    }
+    Ok(trimmed.to_string())
+}
+
+fn mime_type_for_extension(extension: &str) -> &'static str {
+    match extension {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "txt" | "md" | "log" => "text/plain",
+        "json" => "application/json",
+        "csv" => "text/csv",
+        _ => "application/octet-stream",
+    }
+}
+
+fn encode_rgba_png(width: u32, height: u32, rgba: Vec<u8>) -> Result<Vec<u8>, String> {
+    let image = image::RgbaImage::from_raw(width, height, rgba)
+        .ok_or_else(|| "Clipboard image has invalid dimensions".to_string())?;
+    let mut png = Vec::new();
+    image::DynamicImage::ImageRgba8(image)
+        .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode clipboard image: {}", e))?;
+    Ok(png)
+}
+
+fn read_clipboard_image() -> Result<ClipboardImage, String> {
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| format!("Failed to access clipboard: {}", e))?;
+    let data = clipboard.get_image().map_err(|e| match e {
+        arboard::Error::ContentNotAvailable => "Clipboard does not contain an image".to_string(),
+        other => format!("Failed to read clipboard image: {}", other),
+    })?;
+
+    if data.width == 0 || data.height == 0 {
+        return Err("Clipboard image is empty".to_string());
+    }
+    if data.width.saturating_mul(data.height) > MAX_CLIPBOARD_IMAGE_PIXELS {
+        return Err("Clipboard image is too large".to_string());
+    }
+
+    let width =
+        u32::try_from(data.width).map_err(|_| "Clipboard image is too large".to_string())?;
+    let height =
+        u32::try_from(data.height).map_err(|_| "Clipboard image is too large".to_string())?;
+    let png = encode_rgba_png(width, height, data.bytes.into_owned())?;
+    Ok(ClipboardImage { width, height, png })
+}
+
+fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+struct StoredBlob {
+    hash: String,
+    file_name: String,
+    path: PathBuf,
+    deduplicated: bool,
+}
+
+/// 以内容哈希命名写入存储；相同内容已存在时直接复用并刷新修改时间，使其重新计入保留宽限期
+async fn store_blob(dir: &Path, bytes: &[u8], extension: &str) -> Result<StoredBlob, String> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|e| format!("Failed to create attachments dir: {}", e))?;
+
+    let hash = content_hash(bytes);
+    let file_name = if extension.is_empty() {
+        hash.clone()
+    } else {
+        format!("{}.{}", hash, extension)
+    };
+    let path = dir.join(&file_name);
+    let deduplicated = tokio::fs::metadata(&path).await.is_ok();
+    if deduplicated {
+        if let Ok(file) = std::fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+    } else {
+        // 先写临时文件再改名，避免并发写入时读到半写入的文件
+        let temp_path = dir.join(format!(".{}.tmp", uuid::Uuid::new_v4()));
+        tokio::fs::write(&temp_path, bytes)
+            .await
+            .map_err(|e| format!("Failed to write attachment: {}", e))?;
+        tokio::fs::rename(&temp_path, &path)
+            .await
+            .map_err(|e| format!("Failed to save attachment: {}", e))?;
+    }
+    Ok(StoredBlob {
+        hash,
+        file_name,
+        path,
+        deduplicated,
+    })
+}
+
+fn describe_blob(
+    blob: StoredBlob,
+    session_id: &str,
+    mime_type: &str,
+    size: u64,
+    dimensions: Option<(u32, u32)>,
+) -> AttachmentDescriptor {
+    let uri = url::Url::from_file_path(&blob.path)
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| blob.path.to_string_lossy().to_string());
+    AttachmentDescriptor {
+        id: blob.hash,
+        session_id: session_id.to_string(),
+        file_name: blob.file_name,
+        path: blob.path.to_string_lossy().to_string(),
+        uri,
+        mime_type: mime_type.to_string(),
+        size,
+        width: dimensions.map(|(width, _)| width),
+        height: dimensions.map(|(_, height)| height),
+        deduplicated: blob.deduplicated,
+    }
+}
+
+async fn store_png_attachment(
+    dir: &Path,
+    session_id: &str,
+    image: ClipboardImage,
+) -> Result<AttachmentDescriptor, String> {
+    let blob = store_blob(dir, &image.png, "png").await?;
+    Ok(describe_blob(
+        blob,
+        session_id,
+        "image/png",
+        image.png.len() as u64,
+        Some((image.width, image.height)),
+    ))
+}
+
+async fn import_file_attachment(
+    dir: &Path,
+    session_id: &str,
+    source: &Path,
+) -> Result<AttachmentDescriptor, String> {
+    let metadata = tokio::fs::metadata(source)
+        .await
+        .map_err(|e| format!("Failed to read attachment: {}", e))?;
+    if !metadata.is_file() {
+        return Err("Attachment is not a file".to_string());
+    }
+    if metadata.len() > MAX_ATTACHMENT_BYTES {
+        return Err("Attachment is too large".to_string());
+    }
+    let bytes = tokio::fs::read(source)
+        .await
+        .map_err(|e| format!("Failed to read attachment: {}", e))?;
+    let extension = source
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .filter(|ext| ext.chars().all(|ch| ch.is_ascii_alphanumeric()))
+        .unwrap_or_default();
+    let mime_type = mime_type_for_extension(&extension);
+    let dimensions = if mime_type.starts_with("image/") {
+        image::image_dimensions(source).ok()
+    } else {
+        None
+    };
+    let blob = store_blob(dir, &bytes, &extension).await?;
+    Ok(describe_blob(
+        blob,
+        session_id,
+        mime_type,
+        bytes.len() as u64,
+        dimensions,
+    ))
+}
+
+/// 会话存储中所有消息引用的附件哈希
+fn referenced_blob_hashes(snapshot: &StorageSnapshot) -> HashSet<String> {
+    let mut referenced = HashSet::new();
+    for message in snapshot.messages_by_session.values().flatten() {
+        referenced.extend(message.attachments.iter().cloned());
+        referenced.extend(
+            BLOB_HASH_PATTERN
+                .find_iter(&message.content)
+                .map(|found| found.as_str().to_string()),
+        );
+    }
+    referenced
+}
+
+/// 删除未被引用且超过宽限期的文件
+async fn collect_unreferenced_blobs(
+    dir: &Path,
+    referenced: &HashSet<String>,
+    now: SystemTime,
+) -> Result<AttachmentRetentionReport, String> {
+    let mut report = AttachmentRetentionReport::default();
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+        Err(err) => return Err(format!("Failed to read attachments dir: {}", err)),
+    };
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read attachments dir: {}", e))?
+    {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let hash = file_name.split('.').next().unwrap_or_default();
+        if file_name.starts_with('.') || hash.len() != 64 || referenced.contains(hash) {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+        let modified = metadata.modified().unwrap_or(now);
+        if now.duration_since(modified).unwrap_or_default() < UNREFERENCED_GRACE {
+            continue;
+        }
+        if tokio::fs::remove_file(entry.path()).await.is_ok() {
+            report.removed.push(hash.to_string());
+            report.freed_bytes += metadata.len();
+        }
+    }
+    Ok(report)
+}
+
+/// 保留期清理：按当前会话存储计算引用，删除无人引用的附件
+pub(crate) async fn run_attachment_retention_pass(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+) -> Result<AttachmentRetentionReport, String> {
+    let _guard = state.storage_lock.lock().await;
+    let snapshot = read_snapshot_from_path(&storage_path(app_handle)?).await?;
+    collect_unreferenced_blobs(
+        &attachment_blobs_dir(app_handle)?,
+        &referenced_blob_hashes(&snapshot),
+        SystemTime::now(),
+    )
+    .await
+}
+
+/// 启动时在后台执行一次保留期清理
+pub(crate) fn spawn_attachment_retention(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let state = app_handle.state::<AppState>();
+        if state.legal_hold.is_active() {
+            return;
+        }
+        match run_attachment_retention_pass(&app_handle, &state).await {
+            Ok(report) if !report.removed.is_empty() => println!(
+                "[attachments] Removed {} unreferenced attachments ({} bytes)",
+                report.removed.len(),
+                report.freed_bytes
+            ),
+            Ok(_) => {}
+            Err(e) => println!("[attachments] Retention pass failed: {}", e),
+        }
+    });
+}
+
+/// 读取剪贴板图片并保存为会话附件，返回可随下一条消息发送的附件描述
+#[tauri::command]
+pub async fn paste_clipboard_image(
+    app_handle: tauri::AppHandle,
+    session_id: String,
+) -> Result<AttachmentDescriptor, String> {
+    let session_id = validate_session_id(&session_id)?;
+    let dir = attachment_blobs_dir(&app_handle)?;
+    let image = tokio::task::spawn_blocking(read_clipboard_image)
+        .await
+        .map_err(|e| format!("Clipboard task failed: {}", e))??;
+    store_png_attachment(&dir, &session_id, image).await
+}
+
+/// 把本地文件存入附件存储；重复附加同一内容只保存一份
+#[tauri::command]
+pub async fn import_attachment(
+    app_handle: tauri::AppHandle,
+    session_id: String,
+    path: String,
+) -> Result<AttachmentDescriptor, String> {
+    let session_id = validate_session_id(&session_id)?;
+    let dir = attachment_blobs_dir(&app_handle)?;
+    import_file_attachment(&dir, &session_id, Path::new(&path)).await
+}
+
+/// 手动触发附件保留期清理
+#[tauri::command]
+pub async fn run_attachment_retention(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<AttachmentRetentionReport, String> {
+    run_attachment_retention_pass(&app_handle, &state).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn store_png_attachment_deduplicates_by_hash() {
+        let dir = std::env::temp_dir().join(format!("iflow-attachments-{}", uuid::Uuid::new_v4()));
+        let png = encode_rgba_png(2, 1, vec![255, 0, 0, 255, 0, 0, 255, 255]).expect("encode");
+        let image = || ClipboardImage {
+            width: 2,
+            height: 1,
+            png: png.clone(),
+        };
+
+        let first = store_png_attachment(&dir, "session-1", image())
+            .await
+            .expect("first store");
+        let second = store_png_attachment(&dir, "session-1", image())
+            .await
+            .expect("second store");
+
+        assert!(!first.deduplicated);
+        assert!(second.deduplicated);
+        assert_eq!(first.path, second.path);
+        assert_eq!(first.mime_type, "image/png");
+        assert_eq!(std::fs::read_dir(&dir).expect("read dir").count(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn unreferenced_blobs_are_collected_after_grace_period() {
+        let dir = std::env::temp_dir().join(format!("iflow-blobs-{}", uuid::Uuid::new_v4()));
+        let kept = store_blob(&dir, b"kept", "txt").await.expect("store kept");
+        let mentioned = store_blob(&dir, b"mentioned", "")
+            .await
+            .expect("store mentioned");
+        let orphan = store_blob(&dir, b"orphan", "txt")
+            .await
+            .expect("store orphan");
+
+        let mut snapshot = StorageSnapshot::default();
+        snapshot.messages_by_session.insert(
+            "s1".to_string(),
+            vec![
+                crate::storage::StoredMessage {
+                    attachments: vec![kept.hash.clone()],
+                    ..Default::default()
+                },
+                crate::storage::StoredMessage {
+                    content: format!("see {}", mentioned.path.display()),
+                    ..Default::default()
+                },
+            ],
+        );
+        let referenced = referenced_blob_hashes(&snapshot);
+
+        let report = collect_unreferenced_blobs(&dir, &referenced, SystemTime::now())
+            .await
+            .expect("fresh pass");
+        assert!(report.removed.is_empty());
+
+        let later = SystemTime::now() + UNREFERENCED_GRACE + Duration::from_secs(1);
+        let report = collect_unreferenced_blobs(&dir, &referenced, later)
+            .await
+            .expect("late pass");
+        assert_eq!(report.removed, vec![orphan.hash]);
+        assert_eq!(report.freed_bytes, 6);
+        assert!(kept.path.exists() && mentioned.path.exists() && !orphan.path.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,196 @@
+//! 命令能力分组：把暴露给 WebView 的命令按 fs / process / network / history 分组，
+//! 由设置中的 disabledCapabilities 在后端统一拦截（如 kiosk 部署禁用进程启动）。
+//! 未归组的命令（设置、会话存储、消息收发等）始终可用。
+use std::collections::HashSet;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::state::AppState;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandCapability {
+    /// 读写工作区或应用目录之外的文件、导出、插件安装、档案的创建切换与删除
+    Fs,
+    /// 启动外部进程（iFlow、git、团队同步、图表渲染、后处理钩子、自检），
+    /// 以及外部程序经本地回调接口向 Agent 发送提问
+    Process,
+    /// 直接访问外部网络服务（数据库、远端存储、邮件）
+    Network,
+    /// 读取和修改 iFlow 历史会话
+    History,
+}
+
+impl CommandCapability {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Fs => "fs",
+            Self::Process => "process",
+            Self::Network => "network",
+            Self::History => "history",
+        }
+    }
+}
+
+/// 命令所属的能力分组；None 表示不受策略限制
+pub(crate) fn command_capability(command: &str) -> Option<CommandCapability> {
+    use CommandCapability::*;
+    let capability = match command {
+        "resolve_html_artifact_path"
+        | "read_html_artifact"
+        | "read_notebook_artifact"
+        | "preview_table_file"
+        | "pick_folder"
+        | "discover_skills"
+        | "export_conversation_pdf"
+        | "paste_clipboard_image"
+        | "install_plugin"
+        | "remove_plugin"
+        | "read_iflow_config"
+        | "update_iflow_config"
+        | "install_git_hook"
+        | "uninstall_git_hook"
+        | "ensure_gitignore_entries"
+        | "estimate_prompt"
+        | "import_attachment"
+        | "run_attachment_retention"
+        | "import_session_jsonl"
+        | "submit_prompt_batch"
+        | "get_scratch_dir"
+        | "create_profile"
+        | "switch_profile"
+        | "delete_profile" => Fs,
+        "connect_iflow"
+        | "connect_claude"
+        | "connect_gemini"
+        | "connect_remote_agent"
+        | "restore_agents"
+        | "list_available_models"
+        | "list_git_changes"
+        | "load_git_file_diff"
+        | "suggest_prompts"
+        | "render_diagram"
+        | "sync_now"
+        | "local_api_git_review"
+        | "local_api_editor"
+        | "run_diagnostics"
+        | "create_support_bundle" => Process,
+        "query_database"
+        | "share_session_encrypted"
+        | "open_shared_session"
+        | "publish_artifacts"
+        | "send_report_email"
+        | "notify_budget_alert"
+        | "check_for_updates"
+        | "fetch_release_notes"
+        | "download_update"
+        | "check_provider_credentials"
+        | "upload_telemetry" => Network,
+        "list_iflow_history_sessions"
+        | "load_iflow_history_messages"
+        | "delete_iflow_history_session"
+        | "clear_iflow_history_sessions"
+        | "undo_last_clear"
+        | "get_activity_heatmap"
+        | "fork_session"
+        | "search_iflow_history"
+        | "load_iflow_history_entries"
+        | "watch_iflow_history"
+        | "unwatch_iflow_history" => History,
+        _ => return None,
+    };
+    Some(capability)
+}
+
+/// 当前生效的禁用分组；IPC 分发在同步上下文中进行，因此用 std 锁缓存一份
+#[derive(Default)]
+pub(crate) struct CapabilityPolicy {
+    disabled: RwLock<HashSet<CommandCapability>>,
+}
+
+impl CapabilityPolicy {
+    pub(crate) fn apply(&self, disabled: &[CommandCapability]) {
+        if let Ok(mut current) = self.disabled.write() {
+            *current = disabled.iter().copied().collect();
+        }
+    }
+
+    pub(crate) fn is_enabled(&self, capability: CommandCapability) -> bool {
+        self.disabled
+            .read()
+            .map(|disabled| !disabled.contains(&capability))
+            .unwrap_or(false)
+    }
+
+    pub(crate) fn check_command(&self, command: &str) -> Result<(), String> {
+        match command_capability(command) {
+            Some(capability) if !self.is_enabled(capability) => Err(format!(
+                "Command {} is disabled by capability policy ({})",
+                command,
+                capability.as_str()
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 供后台流程（自动渲染、后处理钩子等）在执行前查询
+pub(crate) fn capability_enabled(
+    app_handle: &tauri::AppHandle,
+    capability: CommandCapability,
+) -> bool {
+    app_handle
+        .state::<AppState>()
+        .capability_policy
+        .is_enabled(capability)
+}
+
+/// 前端只能追加禁用分组，重新启用需修改设置文件，避免 WebView 自行解除限制
+pub(crate) fn merge_disabled_capabilities(
+    current: &[CommandCapability],
+    requested: &[CommandCapability],
+) -> Vec<CommandCapability> {
+    let mut merged = current.to_vec();
+    for capability in requested {
+        if !merged.contains(capability) {
+            merged.push(*capability);
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_blocks_only_disabled_groups() {
+        let policy = CapabilityPolicy::default();
+        assert!(policy.check_command("connect_iflow").is_ok());
+
+        policy.apply(&[CommandCapability::Process]);
+        let error = policy
+            .check_command("connect_iflow")
+            .expect_err("process disabled");
+        assert!(error.contains("process"));
+        assert!(policy.check_command("run_diagnostics").is_err());
+        assert!(policy.check_command("create_support_bundle").is_err());
+        assert!(policy.check_command("list_iflow_history_sessions").is_ok());
+        assert!(policy.check_command("get_app_settings").is_ok());
+        assert!(!policy.is_enabled(CommandCapability::Process));
+    }
+
+    #[test]
+    fn webview_cannot_reenable_capabilities() {
+        let merged = merge_disabled_capabilities(
+            &[CommandCapability::Process],
+            &[CommandCapability::Network],
+        );
+        assert_eq!(
+            merged,
+            vec![CommandCapability::Process, CommandCapability::Network]
+        );
+    }
+}
@@ -1,15 +1,35 @@
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::Arc;
 
+use serde_json::json;
 use tauri::State;
 use tokio::process::{Child, Command};
 use tokio::time::{timeout, Duration};
 
-use crate::agents::iflow_adapter::{find_available_port, message_listener_task};
-use crate::models::{AgentInfo, AgentStatus, ConnectResponse, ListenerCommand, SkillRuntimeItem};
-use crate::runtime_env::{resolve_executable_path, runtime_path_env};
+use crate::agents::adapter::{
+    adapter_for_type, connect_acp_agent, emit_agent_status, set_agent_status,
+};
+use crate::agents::claude_adapter::{ClaudeAdapter, DEFAULT_CLAUDE_ACP_COMMAND};
+use crate::agents::gemini_adapter::{GeminiAdapter, DEFAULT_GEMINI_COMMAND};
+use crate::agents::iflow_adapter::IflowAdapter;
+use crate::agents::registry::forget_agent;
+use crate::agents::remote::{connect_remote_iflow, RemoteConnection, REMOTE_AGENT_TYPE};
+use crate::app_lock::EmitUnlessLocked;
+use crate::generation::GenerationOptions;
+use crate::memory_guard::{memory_limits, spill_large_prompt};
+use crate::model_switch::record_model_switch;
+use crate::models::{
+    AgentSessions, AgentStatus, ConnectResponse, ListenerCommand, PermissionMode, SkillRuntimeItem,
+};
+use crate::plugins::{dispatch_plugin_hook, PluginHook};
+use crate::prompt_rules::preprocess_prompt;
+use crate::reply_language::{append_language_instruction, reply_language_for};
+use crate::runtime_env::home_dir;
+use crate::scratch::remove_scratch_dir;
 use crate::state::{AgentInstance, AppState};
+use crate::storage::ModelSwitch;
 
 async fn terminate_agent_process(process: &mut Child) {
     let pid = process.id();
@@ -44,9 +64,36 @@ async fn terminate_agent_process(process: &mut Child) {
     }
 }
 
+/// 接管的进程没有句柄，按 PID 结束进程及其子进程
+#[cfg(unix)]
+async fn terminate_adopted_process(pid: u32) {
+    let pid = pid.to_string();
+    for (program, args) in [("pkill", ["-TERM", "-P"]), ("kill", ["-TERM", "--"])] {
+        let _ = Command::new(program)
+            .args(args)
+            .arg(&pid)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await;
+    }
+}
+
+#[cfg(not(unix))]
+async fn terminate_adopted_process(pid: u32) {
+    let _ = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+}
+
 async fn terminate_agent_instance(instance: &mut AgentInstance) {
     if let Some(mut process) = instance.process.take() {
         terminate_agent_process(&mut process).await;
+    } else if let Some(pid) = instance.adopted_pid.take() {
+        terminate_adopted_process(pid).await;
     }
 }
 
@@ -57,129 +104,105 @@ pub async fn shutdown_all_agents(state: &AppState) {
     }
 }
 
-async fn spawn_iflow_agent(
+/// 连接 iFlow
+#[tauri::command]
+pub async fn connect_iflow(
     app_handle: tauri::AppHandle,
-    state: &AppState,
+    state: State<'_, AppState>,
     agent_id: String,
     iflow_path: String,
     workspace_path: String,
     model: Option<String>,
+    permission_mode: Option<PermissionMode>,
 ) -> Result<ConnectResponse, String> {
-    println!("Connecting to iFlow...");
-    println!("Agent ID: {}", agent_id);
-    println!("Workspace: {}", workspace_path);
-    if let Some(model_name) = model.as_ref() {
-        println!("Model override: {}", model_name);
-    }
-
-    // 查找可用端口
-    let port = find_available_port().await?;
-    println!("Using port: {}", port);
-
-    let resolved_iflow_path = resolve_executable_path(&iflow_path)?;
-    let runtime_path = runtime_path_env()?;
-    println!("Resolved iFlow executable: {}", resolved_iflow_path.display());
-
-    // 启动 iFlow 进程
-    let mut cmd = Command::new(&resolved_iflow_path);
-    cmd.current_dir(&workspace_path)
-        .arg("--experimental-acp")
-        .arg("--port")
-        .arg(port.to_string())
-        .env("PATH", runtime_path)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .kill_on_drop(true);
-
-    if let Some(model_name) = model.as_ref() {
-        let trimmed = model_name.trim();
-        if !trimmed.is_empty() {
-            cmd.arg("--model").arg(trimmed);
-        }
-    }
-
-    println!("Spawning iFlow process...");
-    let child = cmd
-        .spawn()
-        .map_err(|e| format!("Failed to start iFlow: {}", e))?;
-    println!("iFlow process started, PID: {:?}", child.id());
-
-    // 等待 iFlow 启动
-    println!("Waiting for iFlow to initialize...");
-    tokio::time::sleep(Duration::from_secs(3)).await;
-
-    let ws_url = format!("ws://127.0.0.1:{}/acp", port);
-
-    // 创建消息发送通道
-    let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<ListenerCommand>();
-
-    // 保存 Agent 实例
-    let agent_info = AgentInfo {
-        id: agent_id.clone(),
-        name: "iFlow".to_string(),
-        agent_type: "iflow".to_string(),
-        status: AgentStatus::Connected,
-        workspace_path: workspace_path.clone(),
-        port: Some(port),
-    };
-
-    let instance = AgentInstance {
-        info: agent_info,
-        process: Some(child),
-        port,
-        iflow_path: iflow_path.clone(),
-        model: model.clone(),
-        message_sender: Some(tx),
-    };
-
-    state.agent_manager.upsert(agent_id.clone(), instance).await;
-    let (agent_count, agent_ids) = state.agent_manager.stats().await;
-    println!("[connect] Agent saved, total agents: {}", agent_count);
-    println!("[connect] Agent IDs: {:?}", agent_ids);
-
-    // 启动后台消息监听任务
-    let app_handle_clone = app_handle.clone();
-    let agent_id_clone = agent_id.clone();
-    let ws_url_clone = ws_url.clone();
-    let workspace_path_clone = workspace_path.clone();
-
-    tokio::spawn(async move {
-        message_listener_task(
-            app_handle_clone,
-            agent_id_clone,
-            ws_url_clone,
-            workspace_path_clone,
-            rx,
-        )
-        .await;
-    });
+    connect_acp_agent(
+        app_handle,
+        &state,
+        Arc::new(IflowAdapter),
+        agent_id,
+        iflow_path,
+        workspace_path,
+        model,
+        permission_mode.unwrap_or_default(),
+    )
+    .await
+}
 
-    println!("Agent {} connected successfully", agent_id);
+/// 连接 Claude Code（ACP 模式），可与 iFlow Agent 在同一工作区并行
+#[tauri::command]
+pub async fn connect_claude(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    agent_id: String,
+    claude_path: Option<String>,
+    workspace_path: String,
+    model: Option<String>,
+    permission_mode: Option<PermissionMode>,
+) -> Result<ConnectResponse, String> {
+    let claude_path = claude_path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .unwrap_or_else(|| DEFAULT_CLAUDE_ACP_COMMAND.to_string());
+    connect_acp_agent(
+        app_handle,
+        &state,
+        Arc::new(ClaudeAdapter),
+        agent_id,
+        claude_path,
+        workspace_path,
+        model,
+        permission_mode.unwrap_or_default(),
+    )
+    .await
+}
 
-    Ok(ConnectResponse {
-        success: true,
-        port,
-        error: None,
-    })
+/// 连接 Gemini CLI（`--experimental-acp`）
+#[tauri::command]
+pub async fn connect_gemini(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    agent_id: String,
+    gemini_path: Option<String>,
+    workspace_path: String,
+    model: Option<String>,
+    permission_mode: Option<PermissionMode>,
+) -> Result<ConnectResponse, String> {
+    let gemini_path = gemini_path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .unwrap_or_else(|| DEFAULT_GEMINI_COMMAND.to_string());
+    connect_acp_agent(
+        app_handle,
+        &state,
+        Arc::new(GeminiAdapter),
+        agent_id,
+        gemini_path,
+        workspace_path,
+        model,
+        permission_mode.unwrap_or_default(),
+    )
+    .await
 }
 
-/// 连接 iFlow
+/// 通过 SSH 连接远程主机上的 iFlow；`workspace_path` 为远程主机上的路径
 #[tauri::command]
-pub async fn connect_iflow(
+pub async fn connect_remote_agent(
     app_handle: tauri::AppHandle,
     state: State<'_, AppState>,
     agent_id: String,
-    iflow_path: String,
     workspace_path: String,
+    remote: RemoteConnection,
     model: Option<String>,
+    permission_mode: Option<PermissionMode>,
 ) -> Result<ConnectResponse, String> {
-    spawn_iflow_agent(
+    connect_remote_iflow(
         app_handle,
         &state,
         agent_id,
-        iflow_path,
         workspace_path,
+        remote,
         model,
+        permission_mode.unwrap_or_default(),
     )
     .await
 }
@@ -199,6 +222,8 @@ pub async fn switch_agent_model(
         return Err("Model name cannot be empty".to_string());
     }
 
+    let previous_model = state.agent_manager.model_of(&agent_id).await;
+    let acp_session_id = state.agent_manager.acp_session_of(&agent_id).await;
     let (agent_exists, sender) = state.agent_manager.sender_of(&agent_id).await;
     if agent_exists {
         if let Some(sender) = sender {
@@ -210,7 +235,22 @@ pub async fn switch_agent_model(
 
             if send_result.is_ok() {
                 match timeout(Duration::from_secs(20), rx).await {
-                    Ok(Ok(Ok(_current_model))) => {
+                    Ok(Ok(Ok(current_model))) => {
+                        state
+                            .agent_manager
+                            .set_model(&agent_id, Some(current_model.clone()))
+                            .await;
+                        record_model_switch(
+                            &app_handle,
+                            &agent_id,
+                            acp_session_id.as_deref(),
+                            ModelSwitch {
+                                from: previous_model,
+                                to: current_model,
+                                restarted: false,
+                            },
+                        )
+                        .await;
                         let port = state
                             .agent_manager
                             .port_of(&agent_id)
@@ -245,19 +285,57 @@ pub async fn switch_agent_model(
         }
     }
 
+    // 远程 Agent 的启动参数不在本机，无法就地重启
+    if state
+        .agent_manager
+        .agent_type_of(&agent_id)
+        .await
+        .as_deref()
+        == Some(REMOTE_AGENT_TYPE)
+    {
+        return Err("Remote agent model switch failed; reconnect with the new model".to_string());
+    }
+
+    let permission_mode = state
+        .agent_manager
+        .permission_mode_of(&agent_id)
+        .await
+        .unwrap_or_default();
+    let mut agent_type = None;
+    let mut executable = iflow_path;
     if let Some(mut instance) = state.agent_manager.remove(&agent_id).await {
+        // 前端只传 iFlow 路径；其他类型的 Agent 沿用连接时的可执行文件
+        if instance.info.agent_type != "iflow" {
+            executable = instance.iflow_path.clone();
+        }
+        agent_type = Some(instance.info.agent_type.clone());
         terminate_agent_instance(&mut instance).await;
     }
 
-    spawn_iflow_agent(
-        app_handle,
+    let adapter = adapter_for_type(agent_type.as_deref().unwrap_or("iflow"));
+    let response = connect_acp_agent(
+        app_handle.clone(),
         &state,
-        agent_id,
-        iflow_path,
+        adapter,
+        agent_id.clone(),
+        executable,
         workspace_path,
         Some(target_model.to_string()),
+        permission_mode,
     )
-    .await
+    .await?;
+    record_model_switch(
+        &app_handle,
+        &agent_id,
+        acp_session_id.as_deref(),
+        ModelSwitch {
+            from: previous_model,
+            to: target_model.to_string(),
+            restarted: true,
+        },
+    )
+    .await;
+    Ok(response)
 }
 
 #[tauri::command]
@@ -298,15 +376,150 @@ pub async fn toggle_agent_think(
     }
 }
 
+/// 会话中途切换权限模式（ACP `session/set_mode`），之后的重连沿用新模式
+#[tauri::command]
+pub async fn set_permission_mode(
+    state: State<'_, AppState>,
+    agent_id: String,
+    mode: PermissionMode,
+) -> Result<(), String> {
+    let (agent_exists, sender) = state.agent_manager.sender_of(&agent_id).await;
+    if !agent_exists {
+        return Err(format!("Agent {} not found", agent_id));
+    }
+
+    let Some(sender) = sender else {
+        return Err("Message sender not available".to_string());
+    };
+
+    let (tx, rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
+    sender
+        .send(ListenerCommand::SetPermissionMode { mode, response: tx })
+        .map_err(|e| format!("Failed to queue permission mode switch: {}", e))?;
+
+    match timeout(Duration::from_secs(20), rx).await {
+        Ok(Ok(Ok(()))) => {
+            state
+                .agent_manager
+                .set_permission_mode(&agent_id, mode)
+                .await;
+            Ok(())
+        }
+        Ok(Ok(Err(err))) => Err(err),
+        Ok(Err(_)) => Err("Permission mode switch response channel closed".to_string()),
+        Err(_) => Err("Permission mode switch timeout after 20 seconds".to_string()),
+    }
+}
+
+async fn set_agent_paused(state: &AppState, agent_id: &str, paused: bool) -> Result<(), String> {
+    let (agent_exists, sender) = state.agent_manager.sender_of(agent_id).await;
+    if !agent_exists {
+        return Err(format!("Agent {} not found", agent_id));
+    }
+
+    let Some(sender) = sender else {
+        return Err("Message sender not available".to_string());
+    };
+
+    let (tx, rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
+    sender
+        .send(ListenerCommand::SetPaused {
+            paused,
+            response: tx,
+        })
+        .map_err(|e| format!("Failed to queue pause switch: {}", e))?;
+
+    match timeout(Duration::from_secs(20), rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err("Pause switch response channel closed".to_string()),
+        Err(_) => Err("Pause switch timeout after 20 seconds".to_string()),
+    }
+}
+
+/// 暂停 Agent：保留进程与会话，暂不发送新 prompt、不推送流式更新
+#[tauri::command]
+pub async fn pause_agent(state: State<'_, AppState>, agent_id: String) -> Result<(), String> {
+    set_agent_paused(&state, &agent_id, true).await
+}
+
+/// 恢复 Agent：推送暂停期间缓存的更新，并发送排队的 prompt
+#[tauri::command]
+pub async fn resume_agent(state: State<'_, AppState>, agent_id: String) -> Result<(), String> {
+    set_agent_paused(&state, &agent_id, false).await
+}
+
+/// 列出 Agent 当前进程中创建或载入过的会话
+#[tauri::command]
+pub async fn list_agent_sessions(
+    state: State<'_, AppState>,
+    agent_id: String,
+) -> Result<AgentSessions, String> {
+    let (agent_exists, sender) = state.agent_manager.sender_of(&agent_id).await;
+    if !agent_exists {
+        return Err(format!("Agent {} not found", agent_id));
+    }
+
+    let Some(sender) = sender else {
+        return Err("Message sender not available".to_string());
+    };
+
+    let (tx, rx) = tokio::sync::oneshot::channel::<AgentSessions>();
+    sender
+        .send(ListenerCommand::ListSessions { response: tx })
+        .map_err(|e| format!("Failed to queue session list: {}", e))?;
+
+    match timeout(Duration::from_secs(20), rx).await {
+        Ok(Ok(sessions)) => Ok(sessions),
+        Ok(Err(_)) => Err("Session list response channel closed".to_string()),
+        Err(_) => Err("Session list timeout after 20 seconds".to_string()),
+    }
+}
+
+/// 不重启进程，把 Agent 切换到另一个已有会话
+#[tauri::command]
+pub async fn switch_agent_session(
+    state: State<'_, AppState>,
+    agent_id: String,
+    session_id: String,
+) -> Result<(), String> {
+    let (agent_exists, sender) = state.agent_manager.sender_of(&agent_id).await;
+    if !agent_exists {
+        return Err(format!("Agent {} not found", agent_id));
+    }
+
+    let Some(sender) = sender else {
+        return Err("Message sender not available".to_string());
+    };
+
+    let (tx, rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
+    sender
+        .send(ListenerCommand::SwitchSession {
+            session_id,
+            response: tx,
+        })
+        .map_err(|e| format!("Failed to queue session switch: {}", e))?;
+
+    match timeout(Duration::from_secs(20), rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err("Session switch response channel closed".to_string()),
+        Err(_) => Err("Session switch timeout after 20 seconds".to_string()),
+    }
+}
 
 /// 发送消息
 #[tauri::command]
 pub async fn send_message(
+    app_handle: tauri::AppHandle,
     state: State<'_, AppState>,
     agent_id: String,
     content: String,
     session_id: Option<String>,
+    generation: Option<GenerationOptions>,
 ) -> Result<(), String> {
+    let generation = match generation {
+        Some(options) => options.normalized()?,
+        None => None,
+    };
     println!(
         "[send_message] Starting for agent {}: {}",
         agent_id, content
@@ -330,6 +543,64 @@ pub async fn send_message(
         sender.is_some()
     );
 
+    let workspace_path = state.agent_manager.workspace_path_of(&agent_id).await;
+    let content = match workspace_path.as_deref() {
+        Some(workspace_path) => {
+            let outcome = preprocess_prompt(&app_handle, workspace_path, &content).await;
+            if !outcome.applied.is_empty() {
+                println!("[send_message] Prompt rules applied: {:?}", outcome.applied);
+                let _ = app_handle.emit_unless_locked(
+                    "prompt-preprocessed",
+                    json!({
+                        "agentId": &agent_id,
+                        "content": &outcome.content,
+                        "applied": &outcome.applied,
+                    }),
+                );
+            }
+            outcome.content
+        }
+        None => content,
+    };
+    // 超长内容（如整段粘贴）改为附件发送，正文只保留开头与说明；远程 Agent 读不到本机的临时文件
+    let is_remote = state
+        .agent_manager
+        .agent_type_of(&agent_id)
+        .await
+        .as_deref()
+        == Some(REMOTE_AGENT_TYPE);
+    let (content, resources) = if is_remote {
+        (content, Vec::new())
+    } else {
+        spill_large_prompt(&agent_id, content, &memory_limits(&app_handle).await).await
+    };
+    if !resources.is_empty() {
+        println!(
+            "[send_message] Prompt spilled to attachment: {}",
+            resources[0].uri
+        );
+    }
+    let reply_language = match workspace_path.as_deref() {
+        Some(workspace_path) => {
+            reply_language_for(&state.settings.read().await.reply_languages, workspace_path)
+        }
+        None => None,
+    };
+    let content = match reply_language {
+        Some(language) => append_language_instruction(&content, language),
+        None => content,
+    };
+    dispatch_plugin_hook(
+        &app_handle,
+        PluginHook::Prompt,
+        workspace_path.as_deref(),
+        json!({
+            "agentId": &agent_id,
+            "sessionId": &session_id,
+            "content": &content,
+        }),
+    );
+
     if let Some(sender) = sender {
         println!(
             "[send_message] Queueing user prompt to listener: {}",
@@ -338,6 +609,8 @@ pub async fn send_message(
         match sender.send(ListenerCommand::UserPrompt {
             content,
             session_id,
+            generation,
+            resources,
         }) {
             Ok(_) => {
                 println!("[send_message] Prompt queued successfully");
@@ -374,11 +647,24 @@ pub async fn stop_message(state: State<'_, AppState>, agent_id: String) -> Resul
 
 /// 断开连接
 #[tauri::command]
-pub async fn disconnect_agent(state: State<'_, AppState>, agent_id: String) -> Result<(), String> {
+pub async fn disconnect_agent(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    agent_id: String,
+) -> Result<(), String> {
     println!("Disconnecting agent: {}", agent_id);
 
+    set_agent_status(&app_handle, &agent_id, AgentStatus::Stopping).await;
+    forget_agent(&app_handle, &agent_id).await;
     if let Some(mut instance) = state.agent_manager.remove(&agent_id).await {
         terminate_agent_instance(&mut instance).await;
+        remove_scratch_dir(&app_handle, &agent_id).await;
+        emit_agent_status(
+            &app_handle,
+            &agent_id,
+            AgentStatus::Disconnected,
+            Some(AgentStatus::Stopping),
+        );
         println!("Agent {} disconnected", agent_id);
     }
 
@@ -441,10 +727,7 @@ fn parse_skill_frontmatter(content: &str) -> (Option<String>, Option<String>) {
 }
 
 fn resolve_iflow_skill_root() -> Result<PathBuf, String> {
-    let home = std::env::var_os("HOME")
-        .or_else(|| std::env::var_os("USERPROFILE"))
-        .map(PathBuf::from)
-        .ok_or_else(|| "Cannot resolve home directory".to_string())?;
+    let home = home_dir().ok_or_else(|| "Cannot resolve home directory".to_string())?;
     Ok(home.join(".iflow").join("skills"))
 }
 
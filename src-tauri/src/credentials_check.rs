@@ -0,0 +1,288 @@
+//! 模型服务凭据体检：读取 iFlow 配置中的认证方式与 API Key，向模型服务发起轻量请求
+//! （列出模型）确认凭据有效、所用模型可用；OAuth 登录只检查本地凭据是否过期，不发起网络请求。
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::State;
+
+use crate::iflow_config::{iflow_config_dir, load_iflow_config};
+use crate::state::AppState;
+
+const DEFAULT_BASE_URL: &str = "https://apis.iflow.cn/v1";
+const OAUTH_CREDS_FILE: &str = "oauth_creds.json";
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialStatus {
+    Valid,
+    Invalid,
+    Expired,
+    Missing,
+    /// 已配置但无法确认（网络错误、服务端异常等）
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderCredentialCheck {
+    pub provider: String,
+    pub auth_type: Option<String>,
+    pub status: CredentialStatus,
+    pub detail: String,
+    /// 服务端模型列表中找不到的模型
+    pub missing_models: Vec<String>,
+}
+
+impl ProviderCredentialCheck {
+    fn new(
+        provider: &str,
+        auth_type: Option<&str>,
+        status: CredentialStatus,
+        detail: String,
+    ) -> Self {
+        Self {
+            provider: provider.to_string(),
+            auth_type: auth_type.map(str::to_string),
+            status,
+            detail,
+            missing_models: Vec::new(),
+        }
+    }
+}
+
+fn config_str<'a>(config: &'a Map<String, Value>, key: &str) -> Option<&'a str> {
+    config
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn status_from_http(status: u16) -> CredentialStatus {
+    match status {
+        200..=299 => CredentialStatus::Valid,
+        401 | 403 => CredentialStatus::Invalid,
+        _ => CredentialStatus::Unknown,
+    }
+}
+
+/// OpenAI 兼容 `/models` 响应中不存在的模型
+fn missing_models(listing: &Value, wanted: &[String]) -> Vec<String> {
+    let available: Vec<&str> = listing
+        .get("data")
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.get("id").and_then(Value::as_str))
+                .collect()
+        })
+        .unwrap_or_default();
+    if available.is_empty() {
+        return Vec::new();
+    }
+    wanted
+        .iter()
+        .filter(|model| {
+            !available
+                .iter()
+                .any(|id| id.eq_ignore_ascii_case(model.as_str()))
+        })
+        .cloned()
+        .collect()
+}
+
+/// OAuth 凭据：有刷新令牌时视为有效，否则按过期时间（毫秒时间戳）判断
+fn oauth_status(creds: &Value, now_ms: i64) -> (CredentialStatus, String) {
+    let has_access = creds
+        .get("access_token")
+        .and_then(Value::as_str)
+        .is_some_and(|token| !token.is_empty());
+    if !has_access {
+        return (
+            CredentialStatus::Missing,
+            "OAuth 凭据中没有访问令牌".to_string(),
+        );
+    }
+    let refreshable = creds
+        .get("refresh_token")
+        .and_then(Value::as_str)
+        .is_some_and(|token| !token.is_empty());
+    match creds.get("expiry_date").and_then(Value::as_i64) {
+        Some(expiry) if expiry <= now_ms && !refreshable => (
+            CredentialStatus::Expired,
+            "OAuth 登录已过期，请重新登录 iFlow".to_string(),
+        ),
+        Some(expiry) if expiry <= now_ms => (
+            CredentialStatus::Valid,
+            "访问令牌已过期，将使用刷新令牌自动续期".to_string(),
+        ),
+        _ => (CredentialStatus::Valid, "OAuth 凭据有效".to_string()),
+    }
+}
+
+async fn check_oauth(auth_type: &str) -> ProviderCredentialCheck {
+    let path = match iflow_config_dir() {
+        Ok(dir) => dir.join(OAUTH_CREDS_FILE),
+        Err(e) => {
+            return ProviderCredentialCheck::new(
+                "iflow",
+                Some(auth_type),
+                CredentialStatus::Unknown,
+                e,
+            )
+        }
+    };
+    let (status, detail) = match tokio::fs::read_to_string(&path).await {
+        Ok(raw) => match serde_json::from_str::<Value>(&raw) {
+            Ok(creds) => oauth_status(&creds, chrono::Utc::now().timestamp_millis()),
+            Err(e) => (
+                CredentialStatus::Invalid,
+                format!("OAuth 凭据无法解析: {}", e),
+            ),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            (CredentialStatus::Missing, "尚未登录 iFlow 账号".to_string())
+        }
+        Err(e) => (
+            CredentialStatus::Unknown,
+            format!("Failed to read OAuth credentials: {}", e),
+        ),
+    };
+    ProviderCredentialCheck::new("iflow", Some(auth_type), status, detail)
+}
+
+async fn check_api_key(
+    auth_type: Option<&str>,
+    base_url: &str,
+    api_key: &str,
+    models: &[String],
+) -> ProviderCredentialCheck {
+    let client = match reqwest::Client::builder().timeout(CHECK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            return ProviderCredentialCheck::new(
+                "iflow",
+                auth_type,
+                CredentialStatus::Unknown,
+                format!("Failed to create HTTP client: {}", e),
+            )
+        }
+    };
+    let url = format!("{}/models", base_url.trim_end_matches('/'));
+    let response = match client.get(&url).bearer_auth(api_key).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            return ProviderCredentialCheck::new(
+                "iflow",
+                auth_type,
+                CredentialStatus::Unknown,
+                format!("Failed to reach {}: {}", base_url, e),
+            )
+        }
+    };
+    let http_status = response.status();
+    let status = status_from_http(http_status.as_u16());
+    let mut check = ProviderCredentialCheck::new(
+        "iflow",
+        auth_type,
+        status,
+        match status {
+            CredentialStatus::Valid => "API Key 有效".to_string(),
+            CredentialStatus::Invalid => format!("API Key 无效或已过期（HTTP {}）", http_status),
+            _ => format!("服务端返回 HTTP {}，无法确认凭据", http_status),
+        },
+    );
+    if status == CredentialStatus::Valid {
+        if let Ok(listing) = response.json::<Value>().await {
+            check.missing_models = missing_models(&listing, models);
+        }
+    }
+    check
+}
+
+/// 检查 iFlow 配置引用的模型服务凭据；`models` 为空时检查配置中的默认模型与复核模型
+#[tauri::command]
+pub async fn check_provider_credentials(
+    state: State<'_, AppState>,
+    models: Option<Vec<String>>,
+) -> Result<Vec<ProviderCredentialCheck>, String> {
+    let config = load_iflow_config().await?.unwrap_or_default();
+    let auth_type = config_str(&config, "selectedAuthType");
+
+    let mut wanted: Vec<String> = match models {
+        Some(models) => models,
+        None => {
+            let verification_model = state.settings.read().await.verification_model.clone();
+            config_str(&config, "modelName")
+                .map(str::to_string)
+                .into_iter()
+                .chain(verification_model)
+                .collect()
+        }
+    };
+    wanted.retain(|model| !model.trim().is_empty());
+    wanted.dedup();
+
+    let mut checks = Vec::new();
+    let primary = match (auth_type, config_str(&config, "apiKey")) {
+        (Some(auth_type @ "oauth-iflow"), _) => check_oauth(auth_type).await,
+        (_, Some(api_key)) => {
+            let base_url = config_str(&config, "baseUrl").unwrap_or(DEFAULT_BASE_URL);
+            check_api_key(auth_type, base_url, api_key, &wanted).await
+        }
+        (_, None) => ProviderCredentialCheck::new(
+            "iflow",
+            auth_type,
+            CredentialStatus::Missing,
+            "iFlow 配置中没有 API Key".to_string(),
+        ),
+    };
+    checks.push(primary);
+
+    if config_str(&config, "searchApiKey").is_some() {
+        checks.push(ProviderCredentialCheck::new(
+            "search",
+            None,
+            CredentialStatus::Unknown,
+            "已配置搜索 API Key（不发起验证请求）".to_string(),
+        ));
+    }
+    Ok(checks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn http_status_and_model_listing_are_classified() {
+        assert_eq!(status_from_http(200), CredentialStatus::Valid);
+        assert_eq!(status_from_http(401), CredentialStatus::Invalid);
+        assert_eq!(status_from_http(503), CredentialStatus::Unknown);
+
+        let listing = json!({ "data": [{ "id": "qwen3-coder" }, { "id": "GLM-4.6" }] });
+        let wanted = vec!["glm-4.6".to_string(), "kimi-k2".to_string()];
+        assert_eq!(
+            missing_models(&listing, &wanted),
+            vec!["kimi-k2".to_string()]
+        );
+        assert!(missing_models(&json!({}), &wanted).is_empty());
+    }
+
+    #[test]
+    fn oauth_expiry_respects_refresh_token() {
+        let now = 1_000_000;
+        let expired = json!({ "access_token": "a", "expiry_date": now - 1 });
+        assert_eq!(oauth_status(&expired, now).0, CredentialStatus::Expired);
+
+        let refreshable =
+            json!({ "access_token": "a", "refresh_token": "r", "expiry_date": now - 1 });
+        assert_eq!(oauth_status(&refreshable, now).0, CredentialStatus::Valid);
+
+        assert_eq!(oauth_status(&json!({}), now).0, CredentialStatus::Missing);
+    }
+}
@@ -0,0 +1,655 @@
+//! 只读 SQL 查询（SQLite / Postgres）：语句解析校验 + 数据库侧只读事务双重保护
+use std::path::Path;
+
+use futures::TryStreamExt;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::time::{timeout, Duration};
+
+use crate::agents::tls::{build_client_config, AcpTlsOptions};
+use crate::secrets::resolve_secret;
+use crate::wsl_path::host_spawn_dir;
+
+/// 工作区数据库连接串在密钥库中的名称
+pub(crate) const DATABASE_URL_SECRET: &str = "database_url";
+pub(crate) const DATABASE_URL_ENV: &str = "FLOWHUB_DATABASE_URL";
+const DEFAULT_MAX_ROWS: usize = 200;
+const MAX_ROWS_LIMIT: usize = 5000;
+const QUERY_TIMEOUT_SECS: u64 = 30;
+
+const READ_ONLY_LEADING_KEYWORDS: &[&str] =
+    &["select", "with", "explain", "values", "show", "table"];
+
+// 单条语句中出现即拒绝的写入关键字/副作用函数（字符串与注释已在分词阶段剔除）
+const FORBIDDEN_KEYWORDS: &[&str] = &[
+    "insert",
+    "update",
+    "delete",
+    "merge",
+    "upsert",
+    "create",
+    "alter",
+    "drop",
+    "truncate",
+    "grant",
+    "revoke",
+    "attach",
+    "detach",
+    "copy",
+    "call",
+    "into",
+    "load_extension",
+    "writefile",
+    "lo_import",
+    "lo_export",
+    "lo_unlink",
+    "set_config",
+    "pg_terminate_backend",
+    "pg_cancel_backend",
+];
+
+/// 以此开头的函数一律拒绝：dblink 系列会另开一条不受只读事务约束的连接执行任意语句
+const FORBIDDEN_FUNCTION_PREFIXES: &[&str] = &["dblink"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    pub row_count: usize,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SqlToken {
+    Word(String),
+    /// 双引号标识符（区分大小写），只用于检查被禁止的函数名
+    QuotedIdentifier(String),
+    Semicolon,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DatabaseTarget {
+    Sqlite(String),
+    Postgres(String),
+}
+
+/// 跳到终止符之后；未终止时返回 None（交给数据库报错会让后续内容逃过校验）
+fn skip_until(chars: &[char], mut index: usize, terminator: &[char]) -> Option<usize> {
+    while index < chars.len() {
+        if chars[index..].starts_with(terminator) {
+            return Some(index + terminator.len());
+        }
+        index += 1;
+    }
+    None
+}
+
+fn skip_quoted(
+    chars: &[char],
+    mut index: usize,
+    quote: char,
+    backslash_escapes: bool,
+) -> Option<usize> {
+    // index 指向起始引号之后；'' / "" 为转义，E'...' 中反斜杠也转义下一个字符
+    while index < chars.len() {
+        if backslash_escapes && chars[index] == '\\' {
+            index += 2;
+            continue;
+        }
+        if chars[index] == quote {
+            if index + 1 < chars.len() && chars[index + 1] == quote {
+                index += 2;
+                continue;
+            }
+            return Some(index + 1);
+        }
+        index += 1;
+    }
+    None
+}
+
+fn skip_dollar_quoted(chars: &[char], index: usize) -> Option<Result<usize, String>> {
+    // Postgres $tag$ ... $tag$；tag 不能以数字开头（$1 是参数占位符）
+    if chars.get(index + 1).is_some_and(char::is_ascii_digit) {
+        return None;
+    }
+    let mut end = index + 1;
+    while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_') {
+        end += 1;
+    }
+    if end >= chars.len() || chars[end] != '$' {
+        return None;
+    }
+    let tag: Vec<char> = chars[index..=end].to_vec();
+    Some(
+        skip_until(chars, end + 1, &tag)
+            .ok_or_else(|| "Unterminated dollar-quoted string".to_string()),
+    )
+}
+
+/// 前一个字符是否属于标识符（`E'` 只有作为独立前缀时才是转义字符串）
+fn follows_identifier(chars: &[char], index: usize) -> bool {
+    index > 0 && (chars[index - 1].is_alphanumeric() || chars[index - 1] == '_')
+}
+
+fn tokenize_sql(sql: &str) -> Result<Vec<SqlToken>, String> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let ch = chars[index];
+        if ch == '-' && chars.get(index + 1) == Some(&'-') {
+            index = skip_until(&chars, index + 2, &['\n']).unwrap_or(chars.len());
+        } else if ch == '/' && chars.get(index + 1) == Some(&'*') {
+            index = skip_until(&chars, index + 2, &['*', '/'])
+                .ok_or_else(|| "Unterminated block comment".to_string())?;
+        } else if (ch == 'e' || ch == 'E')
+            && chars.get(index + 1) == Some(&'\'')
+            && !follows_identifier(&chars, index)
+        {
+            index = skip_quoted(&chars, index + 2, '\'', true)
+                .ok_or_else(|| "Unterminated string literal".to_string())?;
+        } else if ch == '"' {
+            let end = skip_quoted(&chars, index + 1, ch, false)
+                .ok_or_else(|| "Unterminated quoted string or identifier".to_string())?;
+            let identifier: String = chars[index + 1..end - 1].iter().collect();
+            tokens.push(SqlToken::QuotedIdentifier(identifier.replace("\"\"", "\"")));
+            index = end;
+        } else if ch == '\'' || ch == '`' {
+            index = skip_quoted(&chars, index + 1, ch, false)
+                .ok_or_else(|| "Unterminated quoted string or identifier".to_string())?;
+        } else if ch == '[' {
+            index = skip_until(&chars, index + 1, &[']'])
+                .ok_or_else(|| "Unterminated bracketed identifier".to_string())?;
+        } else if ch == '$' {
+            index = match skip_dollar_quoted(&chars, index) {
+                Some(end) => end?,
+                None => index + 1,
+            };
+        } else if ch == ';' {
+            tokens.push(SqlToken::Semicolon);
+            index += 1;
+        } else if ch.is_alphanumeric() || ch == '_' {
+            let start = index;
+            while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_') {
+                index += 1;
+            }
+            let word: String = chars[start..index].iter().collect();
+            tokens.push(SqlToken::Word(word.to_lowercase()));
+        } else {
+            index += 1;
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// 校验 SQL 为单条只读语句
+fn ensure_read_only_sql(sql: &str) -> Result<(), String> {
+    let tokens = tokenize_sql(sql)?;
+    let mut statements: Vec<Vec<String>> = vec![Vec::new()];
+    let mut quoted_identifiers = Vec::new();
+    for token in tokens {
+        match token {
+            SqlToken::Semicolon => statements.push(Vec::new()),
+            SqlToken::Word(word) => {
+                if let Some(current) = statements.last_mut() {
+                    current.push(word);
+                }
+            }
+            SqlToken::QuotedIdentifier(identifier) => quoted_identifiers.push(identifier),
+        }
+    }
+    statements.retain(|words| !words.is_empty());
+
+    if statements.is_empty() {
+        return Err("SQL statement cannot be empty".to_string());
+    }
+    if statements.len() > 1 {
+        return Err("Only a single SQL statement is allowed".to_string());
+    }
+
+    let words = &statements[0];
+    let leading = words[0].as_str();
+    if !READ_ONLY_LEADING_KEYWORDS.contains(&leading) {
+        return Err(format!(
+            "Only read-only statements are allowed (got {})",
+            leading.to_uppercase()
+        ));
+    }
+    if let Some(keyword) = words
+        .iter()
+        .find(|word| FORBIDDEN_KEYWORDS.contains(&word.as_str()))
+    {
+        return Err(format!(
+            "Statement rejected: {} is not allowed in read-only queries",
+            keyword.to_uppercase()
+        ));
+    }
+    if let Some(function) = words.iter().chain(&quoted_identifiers).find(|name| {
+        FORBIDDEN_FUNCTION_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+    }) {
+        return Err(format!(
+            "Statement rejected: {} is not allowed in read-only queries",
+            function.to_uppercase()
+        ));
+    }
+
+    Ok(())
+}
+
+fn parse_database_target(connection: &str) -> Result<DatabaseTarget, String> {
+    let trimmed = connection.trim();
+    if trimmed.is_empty() {
+        return Err("Database connection string is empty".to_string());
+    }
+
+    let lowered = trimmed.to_lowercase();
+    if lowered.starts_with("postgres://") || lowered.starts_with("postgresql://") {
+        return Ok(DatabaseTarget::Postgres(trimmed.to_string()));
+    }
+    if let Some(rest) = trimmed
+        .strip_prefix("sqlite://")
+        .or_else(|| trimmed.strip_prefix("sqlite:"))
+    {
+        if rest.is_empty() {
+            return Err("SQLite database path is empty".to_string());
+        }
+        return Ok(DatabaseTarget::Sqlite(rest.to_string()));
+    }
+    if lowered.ends_with(".db") || lowered.ends_with(".sqlite") || lowered.ends_with(".sqlite3") {
+        return Ok(DatabaseTarget::Sqlite(trimmed.to_string()));
+    }
+
+    Err("Unsupported database connection string (expected postgres:// or sqlite:)".to_string())
+}
+
+/// 相对路径的 SQLite 连接串改为以工作区为基准的绝对路径，否则会按应用进程的工作目录打开；
+/// `file:` URI 与 `:memory:` 原样保留
+pub(crate) fn resolve_workspace_database(connection: &str, workspace_path: &str) -> String {
+    let Ok(DatabaseTarget::Sqlite(path)) = parse_database_target(connection) else {
+        return connection.to_string();
+    };
+    if path.starts_with("file:") || path == ":memory:" || Path::new(&path).is_absolute() {
+        return connection.to_string();
+    }
+    let workspace = host_spawn_dir(workspace_path);
+    format!(
+        "sqlite:{}",
+        Path::new(&workspace).join(path).to_string_lossy()
+    )
+}
+
+fn sqlite_value_to_json(value: rusqlite::types::ValueRef<'_>) -> Value {
+    use rusqlite::types::ValueRef;
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(item) => json!(item),
+        ValueRef::Real(item) => json!(item),
+        ValueRef::Text(bytes) => Value::String(String::from_utf8_lossy(bytes).to_string()),
+        ValueRef::Blob(bytes) => Value::String(format!("<blob {} bytes>", bytes.len())),
+    }
+}
+
+fn run_sqlite_query(path: &str, sql: &str, max_rows: usize) -> Result<QueryResult, String> {
+    use rusqlite::OpenFlags;
+
+    let conn = rusqlite::Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY
+            | OpenFlags::SQLITE_OPEN_URI
+            | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("Failed to open SQLite database {}: {}", path, e))?;
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+    if !stmt.readonly() {
+        return Err("Statement rejected: SQLite reports it is not read-only".to_string());
+    }
+
+    let columns: Vec<String> = stmt
+        .column_names()
+        .into_iter()
+        .map(|name| name.to_string())
+        .collect();
+    let column_count = columns.len();
+    let mut rows = Vec::new();
+    let mut truncated = false;
+    let mut cursor = stmt
+        .query([])
+        .map_err(|e| format!("Failed to execute query: {}", e))?;
+    while let Some(row) = cursor
+        .next()
+        .map_err(|e| format!("Failed to read row: {}", e))?
+    {
+        if rows.len() >= max_rows {
+            truncated = true;
+            break;
+        }
+        let mut values = Vec::with_capacity(column_count);
+        for index in 0..column_count {
+            let value = row
+                .get_ref(index)
+                .map_err(|e| format!("Failed to read column {}: {}", index, e))?;
+            values.push(sqlite_value_to_json(value));
+        }
+        rows.push(values);
+    }
+
+    Ok(QueryResult {
+        columns,
+        row_count: rows.len(),
+        rows,
+        truncated,
+    })
+}
+
+fn postgres_value_to_json(row: &tokio_postgres::Row, index: usize) -> Value {
+    fn get<'a, T>(row: &'a tokio_postgres::Row, index: usize) -> Option<T>
+    where
+        T: tokio_postgres::types::FromSql<'a>,
+    {
+        row.try_get::<_, Option<T>>(index).ok().flatten()
+    }
+
+    let type_name = row.columns()[index].type_().name();
+    let value = match type_name {
+        "bool" => get::<bool>(row, index).map(Value::from),
+        "int2" => get::<i16>(row, index).map(Value::from),
+        "int4" => get::<i32>(row, index).map(Value::from),
+        "int8" => get::<i64>(row, index).map(Value::from),
+        "oid" => get::<u32>(row, index).map(Value::from),
+        "float4" => get::<f32>(row, index).map(Value::from),
+        "float8" => get::<f64>(row, index).map(Value::from),
+        "json" | "jsonb" => get::<Value>(row, index),
+        "timestamptz" => get::<chrono::DateTime<chrono::Utc>>(row, index)
+            .map(|item| Value::String(item.to_rfc3339())),
+        "timestamp" => {
+            get::<chrono::NaiveDateTime>(row, index).map(|item| Value::String(item.to_string()))
+        }
+        "date" => get::<chrono::NaiveDate>(row, index).map(|item| Value::String(item.to_string())),
+        "bytea" => get::<Vec<u8>>(row, index)
+            .map(|bytes| Value::String(format!("<blob {} bytes>", bytes.len()))),
+        _ => match row.try_get::<_, Option<String>>(index) {
+            Ok(item) => item.map(Value::String),
+            // 无对应 Rust 类型（numeric 等）时只给出类型名
+            Err(_) => Some(Value::String(format!("<{}>", type_name))),
+        },
+    };
+    value.unwrap_or(Value::Null)
+}
+
+async fn run_postgres_query(url: &str, sql: &str, max_rows: usize) -> Result<QueryResult, String> {
+    // 使用系统根证书；托管 Postgres 多要求 sslmode=require，默认 prefer 时服务端不支持 TLS 会回退明文
+    let tls_config = build_client_config(&AcpTlsOptions::default())?;
+    let tls = tokio_postgres_rustls::MakeRustlsConnect::new((*tls_config).clone());
+    let (client, connection) = tokio_postgres::connect(url, tls)
+        .await
+        .map_err(|e| format!("Failed to connect Postgres: {}", e))?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("[database] Postgres connection error: {}", e);
+        }
+    });
+
+    client
+        .batch_execute(&format!(
+            "BEGIN TRANSACTION READ ONLY; SET LOCAL statement_timeout = '{}s'",
+            QUERY_TIMEOUT_SECS
+        ))
+        .await
+        .map_err(|e| format!("Failed to start read-only transaction: {}", e))?;
+
+    // 扩展协议的预备语句只能包含一条语句，`...; COMMIT; DROP ...` 在服务端即被拒绝
+    let result = read_postgres_rows(&client, sql, max_rows).await;
+    let _ = client.batch_execute("ROLLBACK").await;
+    let (columns, rows, truncated) =
+        result.map_err(|e| format!("Failed to execute query: {}", e))?;
+
+    Ok(QueryResult {
+        columns,
+        row_count: rows.len(),
+        rows,
+        truncated,
+    })
+}
+
+/// 逐行读取结果，读到第 max_rows + 1 行即停止，大表不会整体载入内存；
+/// 列名取自预备语句，结果为空时也能返回
+async fn read_postgres_rows(
+    client: &tokio_postgres::Client,
+    sql: &str,
+    max_rows: usize,
+) -> Result<(Vec<String>, Vec<Vec<Value>>, bool), tokio_postgres::Error> {
+    let statement = client.prepare(sql).await?;
+    let columns = statement
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect();
+    let stream = client
+        .query_raw(
+            &statement,
+            std::iter::empty::<&(dyn tokio_postgres::types::ToSql + Sync)>(),
+        )
+        .await?;
+    futures::pin_mut!(stream);
+
+    let mut rows = Vec::new();
+    let mut truncated = false;
+    while let Some(row) = stream.try_next().await? {
+        if rows.len() == max_rows {
+            truncated = true;
+            break;
+        }
+        rows.push(
+            (0..row.len())
+                .map(|index| postgres_value_to_json(&row, index))
+                .collect(),
+        );
+    }
+    Ok((columns, rows, truncated))
+}
+
+/// 校验并执行只读查询；Tauri 命令与 MCP 桥接共用
+pub(crate) async fn execute_read_only_query(
+    connection: &str,
+    sql: &str,
+    max_rows: Option<usize>,
+) -> Result<QueryResult, String> {
+    ensure_read_only_sql(sql)?;
+    let target = parse_database_target(connection)?;
+    let max_rows = max_rows
+        .unwrap_or(DEFAULT_MAX_ROWS)
+        .clamp(1, MAX_ROWS_LIMIT);
+
+    let run = async {
+        match target {
+            DatabaseTarget::Sqlite(path) => {
+                let sql = sql.to_string();
+                tokio::task::spawn_blocking(move || run_sqlite_query(&path, &sql, max_rows))
+                    .await
+                    .map_err(|e| format!("SQLite query task failed: {}", e))?
+            }
+            DatabaseTarget::Postgres(url) => run_postgres_query(&url, sql, max_rows).await,
+        }
+    };
+
+    timeout(Duration::from_secs(QUERY_TIMEOUT_SECS), run)
+        .await
+        .map_err(|_| format!("Query timeout after {} seconds", QUERY_TIMEOUT_SECS))?
+}
+
+/// 构建 session/new 的 MCP 服务器条目：以本程序的 MCP 模式作为 stdio 服务端
+pub(crate) fn database_mcp_server_entry(database_url: &str) -> Result<Value, String> {
+    let current_exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to resolve current executable: {}", e))?;
+    Ok(json!({
+        "name": "flowhub-database",
+        "command": current_exe.to_string_lossy(),
+        "args": [crate::mcp_bridge::MCP_QUERY_DATABASE_FLAG],
+        "env": [{
+            "name": DATABASE_URL_ENV,
+            "value": database_url,
+        }],
+    }))
+}
+
+/// 工作区配置了数据库连接串时，返回需注入 ACP 会话的 MCP 服务器列表
+pub(crate) async fn workspace_mcp_servers(
+    app_handle: &tauri::AppHandle,
+    workspace_path: &str,
+) -> Vec<Value> {
+    match resolve_secret(app_handle, Some(workspace_path), DATABASE_URL_SECRET).await {
+        Ok(Some(database_url)) => match database_mcp_server_entry(&resolve_workspace_database(
+            &database_url,
+            workspace_path,
+        )) {
+            Ok(entry) => vec![entry],
+            Err(e) => {
+                println!("[database] Skip MCP bridge: {}", e);
+                Vec::new()
+            }
+        },
+        Ok(None) => Vec::new(),
+        Err(e) => {
+            println!("[database] Failed to read database secret: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// 以只读方式查询工作区配置的数据库
+#[tauri::command]
+pub async fn query_database(
+    app_handle: tauri::AppHandle,
+    workspace_path: String,
+    sql: String,
+    max_rows: Option<usize>,
+) -> Result<QueryResult, String> {
+    let connection = resolve_secret(&app_handle, Some(&workspace_path), DATABASE_URL_SECRET)
+        .await?
+        .ok_or_else(|| {
+            format!(
+                "No database configured for workspace {} (secret `{}`)",
+                workspace_path, DATABASE_URL_SECRET
+            )
+        })?;
+    let connection = resolve_workspace_database(&connection, &workspace_path);
+    execute_read_only_query(&connection, &sql, max_rows).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_only_check_accepts_select_and_cte() {
+        assert!(ensure_read_only_sql("SELECT * FROM users WHERE name = 'drop table'").is_ok());
+        assert!(ensure_read_only_sql("with t as (select 1) select * from t;").is_ok());
+        assert!(ensure_read_only_sql("-- delete\nSELECT 1 /* update */").is_ok());
+        assert!(ensure_read_only_sql("select $$insert$$ as text").is_ok());
+    }
+
+    #[test]
+    fn read_only_check_rejects_writes_and_multiple_statements() {
+        assert!(ensure_read_only_sql("DELETE FROM users").is_err());
+        assert!(ensure_read_only_sql("SELECT 1; DROP TABLE users").is_err());
+        assert!(
+            ensure_read_only_sql("WITH x AS (DELETE FROM t RETURNING *) SELECT * FROM x").is_err()
+        );
+        assert!(ensure_read_only_sql("SELECT * INTO backup FROM users").is_err());
+        assert!(ensure_read_only_sql("select load_extension('evil')").is_err());
+        assert!(ensure_read_only_sql(
+            "SELECT * FROM dblink('dbname=x', 'DELETE FROM t RETURNING 1') AS t(x int)"
+        )
+        .is_err());
+        assert!(ensure_read_only_sql("select dblink_send_query('c', 'select 1')").is_err());
+        assert!(
+            ensure_read_only_sql("select * from \"dblink\"('c', 'select 1') as t(x int)").is_err()
+        );
+        assert!(ensure_read_only_sql("select \"update\" from t").is_ok());
+        assert!(ensure_read_only_sql("   ").is_err());
+    }
+
+    #[test]
+    fn read_only_check_understands_escape_and_dollar_strings() {
+        // E'\'' 中的反斜杠转义引号，后面的 COMMIT / DROP 不在字符串内
+        assert!(ensure_read_only_sql(r"SELECT E'\''; COMMIT; DROP TABLE x; --'").is_err());
+        assert!(ensure_read_only_sql(r"SELECT e'it\'s', E'a\\' FROM t").is_ok());
+        assert!(ensure_read_only_sql("SELECT $tag$ ; drop $tag$").is_ok());
+        assert!(ensure_read_only_sql("SELECT $tag$; DROP TABLE x").is_err());
+        assert!(ensure_read_only_sql("SELECT $1; DROP TABLE x").is_err());
+        assert!(ensure_read_only_sql("SELECT 'unterminated; DROP TABLE x").is_err());
+    }
+
+    #[test]
+    fn parse_database_target_detects_driver() {
+        assert_eq!(
+            parse_database_target("postgres://u:p@localhost/db"),
+            Ok(DatabaseTarget::Postgres(
+                "postgres://u:p@localhost/db".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_database_target("sqlite:///tmp/app.db"),
+            Ok(DatabaseTarget::Sqlite("/tmp/app.db".to_string()))
+        );
+        assert_eq!(
+            parse_database_target("data/app.sqlite"),
+            Ok(DatabaseTarget::Sqlite("data/app.sqlite".to_string()))
+        );
+        assert!(parse_database_target("mysql://localhost").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn relative_sqlite_paths_resolve_against_the_workspace() {
+        assert_eq!(
+            resolve_workspace_database("sqlite:data/app.db", "/home/me/project"),
+            "sqlite:/home/me/project/data/app.db"
+        );
+        assert_eq!(
+            resolve_workspace_database("app.sqlite", "/home/me/project"),
+            "sqlite:/home/me/project/app.sqlite"
+        );
+        for unchanged in [
+            "sqlite:///tmp/app.db",
+            "sqlite::memory:",
+            "sqlite:file:app.db?mode=ro",
+            "postgres://u:p@localhost/db",
+        ] {
+            assert_eq!(
+                resolve_workspace_database(unchanged, "/home/me/project"),
+                unchanged
+            );
+        }
+    }
+
+    #[test]
+    fn sqlite_query_returns_rows_and_truncates() {
+        let path = std::env::temp_dir().join(format!("iflow-db-{}.sqlite", uuid::Uuid::new_v4()));
+        {
+            let conn = rusqlite::Connection::open(&path).expect("create sqlite");
+            conn.execute_batch(
+                "CREATE TABLE items (id INTEGER, name TEXT); INSERT INTO items VALUES (1, 'a'), (2, 'b'), (3, NULL);",
+            )
+            .expect("seed sqlite");
+        }
+
+        let path_text = path.to_string_lossy().to_string();
+        let result = run_sqlite_query(&path_text, "SELECT id, name FROM items ORDER BY id", 2)
+            .expect("query sqlite");
+        assert_eq!(result.columns, vec!["id".to_string(), "name".to_string()]);
+        assert_eq!(result.row_count, 2);
+        assert!(result.truncated);
+        assert_eq!(result.rows[0], vec![json!(1), json!("a")]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
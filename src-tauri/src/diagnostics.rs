@@ -0,0 +1,457 @@
+//! 启动自检：逐项检查 iFlow 可执行文件、ACP 握手（临时进程 + 临时会话）、历史目录、会话存储、
+//! 本地端口与系统时钟，返回结构化报告，便于用户反馈问题时直接附上。
+use std::process::Stdio;
+use std::time::Instant;
+
+use chrono::{DateTime, Datelike, Utc};
+use serde::Serialize;
+use tauri::State;
+use tokio::process::Command;
+use tokio::time::{sleep, timeout, Duration};
+
+use crate::agents::iflow_adapter::find_available_port;
+use crate::agents::oneshot::probe_acp_session;
+use crate::capabilities::{capability_enabled, CommandCapability};
+use crate::history::iflow_projects_root;
+use crate::runtime_env::{resolve_executable_path, runtime_path_env};
+use crate::state::AppState;
+use crate::storage::{read_snapshot_from_path, storage_path, StorageSnapshot};
+
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+/// 等待临时 iFlow 进程开始监听的最长时间
+const ACP_STARTUP_TIMEOUT: Duration = Duration::from_secs(20);
+const ACP_RETRY_INTERVAL: Duration = Duration::from_millis(500);
+const ACP_PROBE_TIMEOUT_SECS: u64 = 15;
+const CLOCK_SAMPLE: Duration = Duration::from_millis(500);
+/// 墙上时钟与单调时钟在采样期间允许的偏差
+const MAX_CLOCK_DRIFT_MS: i64 = 2000;
+/// 存储中的时间戳超前当前时间多少视为时钟回拨
+const MAX_FUTURE_SKEW_MINUTES: i64 = 5;
+const MIN_SANE_YEAR: i32 = 2024;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticCheck {
+    pub id: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    pub generated_at: String,
+    /// 没有失败项
+    pub ok: bool,
+    pub checks: Vec<DiagnosticCheck>,
+}
+
+fn check(id: &str, status: CheckStatus, detail: impl Into<String>) -> DiagnosticCheck {
+    DiagnosticCheck {
+        id: id.to_string(),
+        status,
+        detail: detail.into(),
+        duration_ms: 0,
+    }
+}
+
+fn timed(mut result: DiagnosticCheck, started: Instant) -> DiagnosticCheck {
+    result.duration_ms = started.elapsed().as_millis() as u64;
+    result
+}
+
+async fn check_iflow_executable(iflow_path: &str) -> DiagnosticCheck {
+    let executable = match resolve_executable_path(iflow_path) {
+        Ok(path) => path,
+        Err(e) => return check("iflow", CheckStatus::Fail, e),
+    };
+    let mut cmd = Command::new(&executable);
+    cmd.arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Ok(path) = runtime_path_env() {
+        cmd.env("PATH", path);
+    }
+    match timeout(VERSION_TIMEOUT, cmd.output()).await {
+        Ok(Ok(output)) if output.status.success() => {
+            let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            check(
+                "iflow",
+                CheckStatus::Pass,
+                format!("{} ({})", executable.display(), version),
+            )
+        }
+        Ok(Ok(output)) => check(
+            "iflow",
+            CheckStatus::Fail,
+            format!(
+                "{} --version exited with {}: {}",
+                executable.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ),
+        Ok(Err(e)) => check(
+            "iflow",
+            CheckStatus::Fail,
+            format!("Failed to run {}: {}", executable.display(), e),
+        ),
+        Err(_) => check(
+            "iflow",
+            CheckStatus::Fail,
+            format!("{} --version timed out", executable.display()),
+        ),
+    }
+}
+
+/// 在临时目录启动独立的 iFlow ACP 进程，完成握手并新建临时会话后结束进程
+async fn check_acp_handshake(iflow_path: &str) -> DiagnosticCheck {
+    let executable = match resolve_executable_path(iflow_path) {
+        Ok(path) => path,
+        Err(e) => return check("acp", CheckStatus::Skipped, e),
+    };
+    let port = match find_available_port().await {
+        Ok(port) => port,
+        Err(e) => return check("acp", CheckStatus::Fail, e),
+    };
+    let workspace =
+        std::env::temp_dir().join(format!("flowhub-diagnostics-{}", uuid::Uuid::new_v4()));
+    if let Err(e) = tokio::fs::create_dir_all(&workspace).await {
+        return check(
+            "acp",
+            CheckStatus::Fail,
+            format!("Failed to create temp workspace: {}", e),
+        );
+    }
+
+    let mut cmd = Command::new(&executable);
+    cmd.current_dir(&workspace)
+        .arg("--experimental-acp")
+        .arg("--port")
+        .arg(port.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+    if let Ok(path) = runtime_path_env() {
+        cmd.env("PATH", path);
+    }
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            let _ = tokio::fs::remove_dir_all(&workspace).await;
+            return check(
+                "acp",
+                CheckStatus::Fail,
+                format!("Failed to start iFlow: {}", e),
+            );
+        }
+    };
+
+    let ws_url = format!("ws://127.0.0.1:{}/acp", port);
+    let workspace_path = workspace.to_string_lossy().to_string();
+    let deadline = Instant::now() + ACP_STARTUP_TIMEOUT;
+    let result = loop {
+        match probe_acp_session(&ws_url, &workspace_path, ACP_PROBE_TIMEOUT_SECS).await {
+            Ok(session_id) => {
+                break check(
+                    "acp",
+                    CheckStatus::Pass,
+                    format!("Handshake OK, temporary session {}", session_id),
+                )
+            }
+            Err(e) if Instant::now() >= deadline => {
+                break check(
+                    "acp",
+                    CheckStatus::Fail,
+                    format!("ACP handshake failed: {}", e),
+                )
+            }
+            Err(_) => {
+                if let Ok(Some(status)) = child.try_wait() {
+                    break check(
+                        "acp",
+                        CheckStatus::Fail,
+                        format!("iFlow exited before accepting connections: {}", status),
+                    );
+                }
+                sleep(ACP_RETRY_INTERVAL).await;
+            }
+        }
+    };
+
+    let _ = child.kill().await;
+    let _ = tokio::fs::remove_dir_all(&workspace).await;
+    result
+}
+
+async fn check_history_dir() -> DiagnosticCheck {
+    let root = match iflow_projects_root() {
+        Ok(root) => root,
+        Err(e) => return check("history", CheckStatus::Fail, e),
+    };
+    match tokio::fs::read_dir(&root).await {
+        Ok(mut reader) => {
+            let mut projects = 0_usize;
+            while let Ok(Some(_)) = reader.next_entry().await {
+                projects += 1;
+            }
+            check(
+                "history",
+                CheckStatus::Pass,
+                format!("{} ({} project dirs)", root.display(), projects),
+            )
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => check(
+            "history",
+            CheckStatus::Warn,
+            format!("{} does not exist yet (no iFlow history)", root.display()),
+        ),
+        Err(e) => check(
+            "history",
+            CheckStatus::Fail,
+            format!("Cannot read {}: {}", root.display(), e),
+        ),
+    }
+}
+
+/// 存储中最新的会话/消息时间戳，用于检测时钟回拨
+fn latest_recorded_at(snapshot: &StorageSnapshot) -> Option<DateTime<Utc>> {
+    let session_times = snapshot
+        .sessions_by_agent
+        .values()
+        .flatten()
+        .map(|session| session.updated_at.as_str());
+    let message_times = snapshot
+        .messages_by_session
+        .values()
+        .flatten()
+        .map(|message| message.timestamp.as_str());
+    session_times
+        .chain(message_times)
+        .filter_map(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|value| value.with_timezone(&Utc))
+        .max()
+}
+
+async fn check_storage(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+) -> (DiagnosticCheck, Option<DateTime<Utc>>) {
+    let path = match storage_path(app_handle) {
+        Ok(path) => path,
+        Err(e) => return (check("storage", CheckStatus::Fail, e), None),
+    };
+    let snapshot = {
+        let _guard = state.storage_lock.lock().await;
+        read_snapshot_from_path(&path).await
+    };
+    match snapshot {
+        Ok(snapshot) => {
+            let sessions: usize = snapshot.sessions_by_agent.values().map(Vec::len).sum();
+            (
+                check(
+                    "storage",
+                    CheckStatus::Pass,
+                    format!("{} ({} sessions)", path.display(), sessions),
+                ),
+                latest_recorded_at(&snapshot),
+            )
+        }
+        Err(e) => (check("storage", CheckStatus::Fail, e), None),
+    }
+}
+
+async fn check_port_binding() -> DiagnosticCheck {
+    match find_available_port().await {
+        Ok(port) => check(
+            "port",
+            CheckStatus::Pass,
+            format!("Bound 127.0.0.1:{}", port),
+        ),
+        Err(e) => check("port", CheckStatus::Fail, e),
+    }
+}
+
+fn check_clock(
+    now: DateTime<Utc>,
+    drift_ms: i64,
+    latest_recorded: Option<DateTime<Utc>>,
+) -> DiagnosticCheck {
+    if now.year() < MIN_SANE_YEAR {
+        return check(
+            "clock",
+            CheckStatus::Fail,
+            format!("System clock reads {}", now.to_rfc3339()),
+        );
+    }
+    if drift_ms.abs() > MAX_CLOCK_DRIFT_MS {
+        return check(
+            "clock",
+            CheckStatus::Warn,
+            format!("Wall clock drifted {} ms against monotonic clock", drift_ms),
+        );
+    }
+    if let Some(latest) = latest_recorded {
+        if latest > now + chrono::Duration::minutes(MAX_FUTURE_SKEW_MINUTES) {
+            return check(
+                "clock",
+                CheckStatus::Warn,
+                format!(
+                    "Stored data is dated {} which is ahead of the system clock {}",
+                    latest.to_rfc3339(),
+                    now.to_rfc3339()
+                ),
+            );
+        }
+    }
+    check("clock", CheckStatus::Pass, now.to_rfc3339())
+}
+
+/// 采样期间墙上时钟与单调时钟的差值（毫秒）
+async fn sample_clock_drift() -> i64 {
+    let wall_start = Utc::now();
+    let mono_start = Instant::now();
+    sleep(CLOCK_SAMPLE).await;
+    let wall_elapsed = (Utc::now() - wall_start).num_milliseconds();
+    wall_elapsed - mono_start.elapsed().as_millis() as i64
+}
+
+/// 依次运行各项检查；禁用外部进程能力时跳过 iFlow 相关检查
+pub(crate) async fn collect_diagnostics(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+    iflow_path: Option<String>,
+) -> DiagnosticsReport {
+    let iflow_path = iflow_path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .unwrap_or_else(|| "iflow".to_string());
+    let mut checks = Vec::new();
+
+    if capability_enabled(app_handle, CommandCapability::Process) {
+        let started = Instant::now();
+        let iflow = check_iflow_executable(&iflow_path).await;
+        let iflow_ok = iflow.status == CheckStatus::Pass;
+        checks.push(timed(iflow, started));
+
+        let started = Instant::now();
+        let acp = if iflow_ok {
+            check_acp_handshake(&iflow_path).await
+        } else {
+            check(
+                "acp",
+                CheckStatus::Skipped,
+                "iFlow executable is unavailable",
+            )
+        };
+        checks.push(timed(acp, started));
+    } else {
+        for id in ["iflow", "acp"] {
+            checks.push(check(
+                id,
+                CheckStatus::Skipped,
+                "Process capability is disabled",
+            ));
+        }
+    }
+
+    let started = Instant::now();
+    checks.push(timed(check_history_dir().await, started));
+
+    let started = Instant::now();
+    let (storage, latest_recorded) = check_storage(app_handle, state).await;
+    checks.push(timed(storage, started));
+
+    let started = Instant::now();
+    checks.push(timed(check_port_binding().await, started));
+
+    let started = Instant::now();
+    let drift_ms = sample_clock_drift().await;
+    checks.push(timed(
+        check_clock(Utc::now(), drift_ms, latest_recorded),
+        started,
+    ));
+
+    DiagnosticsReport {
+        generated_at: Utc::now().to_rfc3339(),
+        ok: checks.iter().all(|item| item.status != CheckStatus::Fail),
+        checks,
+    }
+}
+
+/// 运行自检，返回各项结果
+#[tauri::command]
+pub async fn run_diagnostics(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    iflow_path: Option<String>,
+) -> Result<DiagnosticsReport, String> {
+    Ok(collect_diagnostics(&app_handle, &state, iflow_path).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StoredMessage;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn clock_check_flags_bad_time_drift_and_backwards_jumps() {
+        let now = at("2026-03-02T08:00:00Z");
+        assert_eq!(check_clock(now, 10, None).status, CheckStatus::Pass);
+        assert_eq!(
+            check_clock(at("2001-01-01T00:00:00Z"), 0, None).status,
+            CheckStatus::Fail
+        );
+        assert_eq!(check_clock(now, 5000, None).status, CheckStatus::Warn);
+        assert_eq!(
+            check_clock(now, 0, Some(at("2026-03-02T07:59:00Z"))).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(
+            check_clock(now, 0, Some(at("2026-03-03T08:00:00Z"))).status,
+            CheckStatus::Warn
+        );
+    }
+
+    #[test]
+    fn latest_recorded_time_covers_sessions_and_messages() {
+        let mut snapshot = StorageSnapshot::default();
+        assert_eq!(latest_recorded_at(&snapshot), None);
+        snapshot.messages_by_session.insert(
+            "s1".to_string(),
+            vec![
+                StoredMessage {
+                    timestamp: "2026-03-01T10:00:00.000Z".to_string(),
+                    ..Default::default()
+                },
+                StoredMessage {
+                    timestamp: "not a time".to_string(),
+                    ..Default::default()
+                },
+            ],
+        );
+        assert_eq!(
+            latest_recorded_at(&snapshot),
+            Some(at("2026-03-01T10:00:00Z"))
+        );
+    }
+}
@@ -0,0 +1,481 @@
+//! Mermaid / PlantUML 图表识别与 SVG 渲染。优先使用随应用打包的 mmdc / plantuml
+//! （`npm run tauri:build:diagrams` 把 `src-tauri/binaries/{mmdc,plantuml}-<目标三元组>`
+//! 作为 sidecar 放在主程序旁），其次使用 PATH 中的同名程序。
+//! 两处都没有时不报错，返回 RendererMissing 并附安装提示，由前端提示用户。
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tauri::State;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::capabilities::{capability_enabled, CommandCapability};
+use crate::path_display::relativize_workspace_paths;
+use crate::runtime_env::{resolve_executable_path, runtime_path_env};
+use crate::state::AppState;
+
+const RENDER_TIMEOUT_SECS: u64 = 30;
+const MAX_DIAGRAM_SOURCE_SIZE: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagramKind {
+    Mermaid,
+    Plantuml,
+}
+
+impl DiagramKind {
+    fn from_fence_language(language: &str) -> Option<Self> {
+        match language.trim().to_lowercase().as_str() {
+            "mermaid" | "mmd" => Some(Self::Mermaid),
+            "plantuml" | "puml" | "uml" => Some(Self::Plantuml),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Mermaid => "mermaid",
+            Self::Plantuml => "plantuml",
+        }
+    }
+
+    /// 渲染所需的本机可执行文件
+    fn renderer(&self) -> &'static str {
+        match self {
+            Self::Mermaid => "mmdc",
+            Self::Plantuml => "plantuml",
+        }
+    }
+
+    fn install_hint(&self) -> &'static str {
+        match self {
+            Self::Mermaid => "npm install -g @mermaid-js/mermaid-cli",
+            Self::Plantuml => "brew install plantuml 或 apt install plantuml",
+        }
+    }
+}
+
+/// 渲染结果；本机没有对应渲染器时为 RendererMissing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum DiagramRender {
+    Rendered { path: PathBuf },
+    RendererMissing { renderer: String, hint: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DiagramBlock {
+    pub(crate) kind: DiagramKind,
+    pub(crate) source: String,
+}
+
+/// 从完整的助手消息中提取 ```mermaid / ```plantuml 代码块
+pub(crate) fn extract_diagram_blocks(text: &str) -> Vec<DiagramBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(DiagramKind, Vec<&str>)> = None;
+    let mut in_other_fence = false;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let is_fence = trimmed.starts_with("```");
+
+        if current.is_some() {
+            if is_fence && trimmed.trim_end() == "```" {
+                if let Some((kind, lines)) = current.take() {
+                    let source = lines.join("\n").trim().to_string();
+                    if !source.is_empty() {
+                        blocks.push(DiagramBlock { kind, source });
+                    }
+                }
+            } else if let Some((_, lines)) = current.as_mut() {
+                lines.push(line);
+            }
+            continue;
+        }
+
+        if in_other_fence {
+            if is_fence && trimmed.trim_end() == "```" {
+                in_other_fence = false;
+            }
+            continue;
+        }
+        if !is_fence {
+            continue;
+        }
+
+        let language = trimmed.trim_start_matches('`').trim();
+        match DiagramKind::from_fence_language(language) {
+            Some(kind) => current = Some((kind, Vec::new())),
+            None => in_other_fence = true,
+        }
+    }
+
+    blocks
+}
+
+fn diagram_output_dir(workspace_path: &str) -> PathBuf {
+    Path::new(workspace_path).join(".flowhub").join("diagrams")
+}
+
+fn diagram_file_stem(kind: DiagramKind, source: &str) -> String {
+    let digest = Sha256::digest(source.as_bytes());
+    let hash = format!("{:x}", digest);
+    format!("{}-{}", kind.as_str(), &hash[..16])
+}
+
+async fn run_renderer(mut cmd: Command, stdin_payload: Option<&str>) -> Result<Vec<u8>, String> {
+    cmd.env("PATH", runtime_path_env()?)
+        .stdin(if stdin_payload.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to start diagram renderer: {}", e))?;
+    if let (Some(payload), Some(mut stdin)) = (stdin_payload, child.stdin.take()) {
+        stdin
+            .write_all(payload.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write diagram source: {}", e))?;
+    }
+
+    let output = timeout(
+        Duration::from_secs(RENDER_TIMEOUT_SECS),
+        child.wait_with_output(),
+    )
+    .await
+    .map_err(|_| {
+        format!(
+            "Diagram render timeout after {} seconds",
+            RENDER_TIMEOUT_SECS
+        )
+    })?
+    .map_err(|e| format!("Diagram renderer failed: {}", e))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(if error.is_empty() {
+            "Diagram renderer exited with error".to_string()
+        } else {
+            format!("Diagram renderer exited with error: {}", error)
+        });
+    }
+
+    Ok(output.stdout)
+}
+
+async fn render_svg(
+    kind: DiagramKind,
+    executable: PathBuf,
+    source: &str,
+    output_path: &Path,
+) -> Result<(), String> {
+    match kind {
+        DiagramKind::Mermaid => {
+            let input_path = output_path.with_extension("mmd");
+            tokio::fs::write(&input_path, source)
+                .await
+                .map_err(|e| format!("Failed to write mermaid source: {}", e))?;
+
+            let mut cmd = Command::new(executable);
+            cmd.arg("-i").arg(&input_path).arg("-o").arg(output_path);
+            let result = run_renderer(cmd, None).await;
+            let _ = tokio::fs::remove_file(&input_path).await;
+            result.map(|_| ())
+        }
+        DiagramKind::Plantuml => {
+            // 沙箱配置禁止 !include / !includeurl 读取本机文件或访问网络；
+            // 包装脚本不一定转发 JVM 参数，环境变量与命令行都设置
+            let mut cmd = Command::new(executable);
+            cmd.env("PLANTUML_SECURITY_PROFILE", "SANDBOX")
+                .arg("-DPLANTUML_SECURITY_PROFILE=SANDBOX")
+                .arg("-tsvg")
+                .arg("-pipe");
+            let svg = run_renderer(cmd, Some(source)).await?;
+            tokio::fs::write(output_path, svg)
+                .await
+                .map_err(|e| format!("Failed to write diagram svg: {}", e))
+        }
+    }
+}
+
+/// 先渲染到同目录的临时文件，成功后再改名为缓存文件；
+/// 渲染被中断或超时留下的半截 SVG 不会被当作缓存命中
+async fn render_svg_atomically(
+    kind: DiagramKind,
+    executable: PathBuf,
+    source: &str,
+    output_path: &Path,
+) -> Result<(), String> {
+    // mmdc 按扩展名决定输出格式，临时文件保留 .svg 后缀
+    let temp_path = output_path.with_extension(format!("{}.tmp.svg", uuid::Uuid::new_v4()));
+    let result = match render_svg(kind, executable, source, &temp_path).await {
+        Ok(()) => tokio::fs::rename(&temp_path, output_path)
+            .await
+            .map_err(|e| format!("Failed to save diagram svg: {}", e)),
+        Err(e) => Err(e),
+    };
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+    }
+    result
+}
+
+/// 渲染图表并写入工作区 `.flowhub/diagrams`，相同源码命中缓存
+pub(crate) async fn render_diagram_to_artifact(
+    workspace_path: &str,
+    kind: DiagramKind,
+    source: &str,
+) -> Result<DiagramRender, String> {
+    render_diagram_with(workspace_path, kind, source, resolve_diagram_renderer).await
+}
+
+/// sidecar 打包后与主程序位于同一目录，文件名去掉目标三元组后缀
+fn bundled_renderer_path(exe_dir: &Path, renderer: &str) -> PathBuf {
+    exe_dir.join(format!("{}{}", renderer, std::env::consts::EXE_SUFFIX))
+}
+
+fn resolve_diagram_renderer(renderer: &str) -> Result<PathBuf, String> {
+    let bundled = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| bundled_renderer_path(dir, renderer)))
+        .filter(|path| path.is_file());
+    match bundled {
+        Some(path) => Ok(path),
+        None => resolve_executable_path(renderer),
+    }
+}
+
+async fn render_diagram_with(
+    workspace_path: &str,
+    kind: DiagramKind,
+    source: &str,
+    resolve_renderer: fn(&str) -> Result<PathBuf, String>,
+) -> Result<DiagramRender, String> {
+    if source.trim().is_empty() {
+        return Err("Diagram source cannot be empty".to_string());
+    }
+    if source.len() > MAX_DIAGRAM_SOURCE_SIZE {
+        return Err(format!(
+            "Diagram source is too large (>{} bytes)",
+            MAX_DIAGRAM_SOURCE_SIZE
+        ));
+    }
+
+    let output_dir = diagram_output_dir(workspace_path);
+    tokio::fs::create_dir_all(&output_dir)
+        .await
+        .map_err(|e| format!("Failed to create diagram dir: {}", e))?;
+    let output_path = output_dir.join(format!("{}.svg", diagram_file_stem(kind, source)));
+
+    if tokio::fs::metadata(&output_path).await.is_err() {
+        let Ok(executable) = resolve_renderer(kind.renderer()) else {
+            return Ok(DiagramRender::RendererMissing {
+                renderer: kind.renderer().to_string(),
+                hint: kind.install_hint().to_string(),
+            });
+        };
+        render_svg_atomically(kind, executable, source, &output_path).await?;
+    }
+
+    Ok(DiagramRender::Rendered { path: output_path })
+}
+
+fn emit_renderer_missing(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    kind: DiagramKind,
+    renderer: &str,
+    hint: &str,
+) {
+    let _ = app_handle.emit_unless_locked(
+        "diagram-renderer-missing",
+        json!({
+            "agentId": agent_id,
+            "language": kind.as_str(),
+            "renderer": renderer,
+            "hint": hint,
+        }),
+    );
+}
+
+pub(crate) fn emit_diagram_artifact(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    workspace_path: &str,
+    kind: DiagramKind,
+    path: &Path,
+) {
+    let _ = app_handle.emit_unless_locked(
+        "artifact-detected",
+        json!({
+            "agentId": agent_id,
+            "kind": "diagram",
+            "language": kind.as_str(),
+            "path": relativize_workspace_paths(&path.to_string_lossy(), workspace_path),
+        }),
+    );
+}
+
+/// 任务完成后后台渲染消息中的图表，成功后经 artifact-detected 推送给前端
+pub(crate) fn spawn_diagram_rendering(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    workspace_path: &str,
+    message: &str,
+) {
+    if !capability_enabled(app_handle, CommandCapability::Process) {
+        return;
+    }
+    let blocks = extract_diagram_blocks(message);
+    if blocks.is_empty() {
+        return;
+    }
+
+    let app_handle = app_handle.clone();
+    let agent_id = agent_id.to_string();
+    let workspace_path = workspace_path.to_string();
+    tokio::spawn(async move {
+        // 缺失的渲染器只提示一次，其余同类图表跳过，不影响另一类图表
+        let mut missing = HashSet::new();
+        for block in blocks {
+            if missing.contains(&block.kind) {
+                continue;
+            }
+            match render_diagram_to_artifact(&workspace_path, block.kind, &block.source).await {
+                Ok(DiagramRender::Rendered { path }) => emit_diagram_artifact(
+                    &app_handle,
+                    &agent_id,
+                    &workspace_path,
+                    block.kind,
+                    &path,
+                ),
+                Ok(DiagramRender::RendererMissing { renderer, hint }) => {
+                    emit_renderer_missing(&app_handle, &agent_id, block.kind, &renderer, &hint);
+                    missing.insert(block.kind);
+                }
+                Err(e) => println!("[diagram] Render {} failed: {}", block.kind.as_str(), e),
+            }
+        }
+    });
+}
+
+/// 渲染 Mermaid / PlantUML 源码为 SVG Artifact；成功时返回文件绝对路径，未安装渲染器时返回 rendererMissing
+#[tauri::command]
+pub async fn render_diagram(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    agent_id: String,
+    language: String,
+    source: String,
+) -> Result<DiagramRender, String> {
+    let kind = DiagramKind::from_fence_language(&language)
+        .ok_or_else(|| format!("Unsupported diagram language: {}", language))?;
+    let workspace_path = state
+        .agent_manager
+        .workspace_path_of(&agent_id)
+        .await
+        .ok_or_else(|| format!("Agent {} not found", agent_id))?;
+
+    let result = render_diagram_to_artifact(&workspace_path, kind, &source).await?;
+    if let DiagramRender::Rendered { path } = &result {
+        emit_diagram_artifact(&app_handle, &agent_id, &workspace_path, kind, path);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_diagram_blocks_finds_mermaid_and_plantuml() {
+        let text = "intro\n```mermaid\ngraph TD\n  A-->B\n```\n```rust\nfn main() {}\n```\n```plantuml\n@startuml\nA -> B\n@enduml\n```\n";
+        let blocks = extract_diagram_blocks(text);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].kind, DiagramKind::Mermaid);
+        assert_eq!(blocks[0].source, "graph TD\n  A-->B");
+        assert_eq!(blocks[1].kind, DiagramKind::Plantuml);
+        assert!(blocks[1].source.starts_with("@startuml"));
+    }
+
+    #[test]
+    fn extract_diagram_blocks_ignores_unclosed_and_nested_fences() {
+        let text = "```markdown\n```mermaid\ngraph TD\n```\n```mermaid\nA-->B";
+        assert!(extract_diagram_blocks(text).is_empty());
+    }
+
+    #[test]
+    fn diagram_file_stem_is_stable() {
+        let first = diagram_file_stem(DiagramKind::Mermaid, "graph TD");
+        let second = diagram_file_stem(DiagramKind::Mermaid, "graph TD");
+        assert_eq!(first, second);
+        assert!(first.starts_with("mermaid-"));
+    }
+
+    #[test]
+    fn bundled_renderer_sits_next_to_the_app() {
+        let path = bundled_renderer_path(Path::new("/opt/flowhub"), "mmdc");
+        assert_eq!(path.parent(), Some(Path::new("/opt/flowhub")));
+        assert!(path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("mmdc")));
+    }
+
+    #[tokio::test]
+    async fn missing_renderer_is_reported_unless_svg_is_cached() {
+        let workspace = std::env::temp_dir().join(format!("diagram-{}", uuid::Uuid::new_v4()));
+        let workspace_path = workspace.to_string_lossy().to_string();
+        let not_installed = |name: &str| Err(format!("Executable not found: {}", name));
+
+        let result = render_diagram_with(
+            &workspace_path,
+            DiagramKind::Mermaid,
+            "graph TD",
+            not_installed,
+        )
+        .await
+        .expect("render");
+        assert_eq!(
+            result,
+            DiagramRender::RendererMissing {
+                renderer: "mmdc".to_string(),
+                hint: DiagramKind::Mermaid.install_hint().to_string(),
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(&result).unwrap()["status"],
+            "rendererMissing"
+        );
+
+        let cached = diagram_output_dir(&workspace_path).join(format!(
+            "{}.svg",
+            diagram_file_stem(DiagramKind::Plantuml, "@startuml\n@enduml")
+        ));
+        std::fs::write(&cached, "<svg/>").expect("write cached svg");
+        let result = render_diagram_with(
+            &workspace_path,
+            DiagramKind::Plantuml,
+            "@startuml\n@enduml",
+            not_installed,
+        )
+        .await
+        .expect("render");
+        assert_eq!(result, DiagramRender::Rendered { path: cached });
+
+        let _ = std::fs::remove_dir_all(&workspace);
+    }
+}
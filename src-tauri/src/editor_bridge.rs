@@ -0,0 +1,365 @@
+//! 编辑器集成桥：复用本地回调接口（`/v1/editor/*`），为 VS Code 等编辑器插件提供握手、
+//! 发送选中代码作为 prompt、定位文件所属会话，以及由 FlowHub 向编辑器推送补丁（编辑器长轮询拉取）。
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{Manager, State};
+use tokio::sync::Notify;
+use tokio::time::{timeout, Duration};
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::local_api::LocalApiResponse;
+use crate::models::ListenerCommand;
+use crate::prompt_rules::preprocess_prompt;
+use crate::state::AppState;
+
+const EDITOR_PROTOCOL_VERSION: u32 = 1;
+const EDITOR_CAPABILITIES: &[&str] = &["sendSelection", "showSession", "applyPatch"];
+/// 推送给编辑器的消息只保留最近若干条
+const MAX_OUTBOX_MESSAGES: usize = 100;
+const MAX_POLL_WAIT: Duration = Duration::from_secs(25);
+const MAX_SELECTION_CHARS: usize = 50_000;
+
+static OUTBOX: Lazy<Mutex<EditorOutbox>> = Lazy::new(|| Mutex::new(EditorOutbox::default()));
+static OUTBOX_NOTIFY: Lazy<Notify> = Lazy::new(Notify::new);
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum EditorMessageBody {
+    /// 把统一 diff 应用到编辑器中打开的文件
+    #[serde(rename_all = "camelCase")]
+    ApplyPatch { file_path: String, patch: String },
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EditorMessage {
+    pub seq: u64,
+    pub workspace_path: String,
+    #[serde(flatten)]
+    pub body: EditorMessageBody,
+    pub created_at: String,
+}
+
+#[derive(Default)]
+struct EditorOutbox {
+    next_seq: u64,
+    messages: VecDeque<EditorMessage>,
+}
+
+impl EditorOutbox {
+    fn push(&mut self, workspace_path: &str, body: EditorMessageBody) -> u64 {
+        self.next_seq += 1;
+        self.messages.push_back(EditorMessage {
+            seq: self.next_seq,
+            workspace_path: workspace_path.to_string(),
+            body,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        });
+        while self.messages.len() > MAX_OUTBOX_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.next_seq
+    }
+
+    fn after(&self, seq: u64) -> Vec<EditorMessage> {
+        self.messages
+            .iter()
+            .filter(|message| message.seq > seq)
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendSelectionRequest {
+    file_path: String,
+    selection: String,
+    #[serde(default)]
+    language_id: Option<String>,
+    #[serde(default)]
+    start_line: Option<u32>,
+    #[serde(default)]
+    end_line: Option<u32>,
+    #[serde(default)]
+    instruction: Option<String>,
+    #[serde(default)]
+    agent_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ShowSessionRequest {
+    file_path: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PollRequest {
+    #[serde(default)]
+    after: u64,
+    #[serde(default)]
+    wait_secs: Option<u64>,
+}
+
+fn lock_outbox() -> std::sync::MutexGuard<'static, EditorOutbox> {
+    OUTBOX
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn parse_body<T: for<'de> Deserialize<'de>>(body: Value) -> Result<T, LocalApiResponse> {
+    serde_json::from_value(body)
+        .map_err(|e| LocalApiResponse::error(400, &format!("Invalid editor request: {}", e)))
+}
+
+fn relative_display(file_path: &str, workspace_path: &str) -> String {
+    Path::new(file_path)
+        .strip_prefix(workspace_path)
+        .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_else(|_| file_path.to_string())
+}
+
+fn selection_prompt(request: &SendSelectionRequest, workspace_path: &str) -> String {
+    let location = match (request.start_line, request.end_line) {
+        (Some(start), Some(end)) if end > start => format!("第 {}-{} 行", start, end),
+        (Some(start), _) => format!("第 {} 行", start),
+        _ => "选中内容".to_string(),
+    };
+    let instruction = request
+        .instruction
+        .as_deref()
+        .map(str::trim)
+        .filter(|instruction| !instruction.is_empty())
+        .unwrap_or("请查看以下代码：");
+    let selection: String = request
+        .selection
+        .chars()
+        .take(MAX_SELECTION_CHARS)
+        .collect();
+    format!(
+        "{}\n\n`{}` {}：\n```{}\n{}\n```",
+        instruction,
+        relative_display(&request.file_path, workspace_path),
+        location,
+        request.language_id.as_deref().unwrap_or_default(),
+        selection.trim_end()
+    )
+}
+
+/// 把 FlowHub 窗口切到前台并通知前端切换到对应 Agent；`prompt` 为已代为发送的提问，由前端记为用户消息
+fn reveal_agent(
+    app_handle: &tauri::AppHandle,
+    action: &str,
+    agent_id: &str,
+    file_path: &str,
+    prompt: Option<&str>,
+) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+    let _ = app_handle.emit_unless_locked(
+        "editor-request",
+        json!({
+            "action": action,
+            "agentId": agent_id,
+            "filePath": file_path,
+            "prompt": prompt,
+        }),
+    );
+}
+
+async fn send_selection(app_handle: &tauri::AppHandle, body: Value) -> LocalApiResponse {
+    let request: SendSelectionRequest = match parse_body(body) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    if request.selection.trim().is_empty() {
+        return LocalApiResponse::error(400, "Selection is empty");
+    }
+    let state = app_handle.state::<AppState>();
+    let target = match request.agent_id.as_deref() {
+        Some(agent_id) => state
+            .agent_manager
+            .workspace_path_of(agent_id)
+            .await
+            .map(|workspace_path| (agent_id.to_string(), workspace_path)),
+        None => state.agent_manager.agent_for_path(&request.file_path).await,
+    };
+    let Some((agent_id, workspace_path)) = target else {
+        return LocalApiResponse::error(404, "No online agent for this file");
+    };
+    let (_, Some(sender)) = state.agent_manager.sender_of(&agent_id).await else {
+        return LocalApiResponse::error(404, "Message sender not available");
+    };
+
+    let prompt = selection_prompt(&request, &workspace_path);
+    let content = preprocess_prompt(app_handle, &workspace_path, &prompt)
+        .await
+        .content;
+    if let Err(e) = sender.send(ListenerCommand::UserPrompt {
+        content,
+        session_id: None,
+        generation: None,
+        resources: Vec::new(),
+    }) {
+        return LocalApiResponse::error(500, &format!("Failed to queue prompt: {}", e));
+    }
+    reveal_agent(
+        app_handle,
+        "sendSelection",
+        &agent_id,
+        &request.file_path,
+        Some(&prompt),
+    );
+    LocalApiResponse::ok(json!({ "agentId": agent_id }))
+}
+
+async fn show_session(app_handle: &tauri::AppHandle, body: Value) -> LocalApiResponse {
+    let request: ShowSessionRequest = match parse_body(body) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    let target = app_handle
+        .state::<AppState>()
+        .agent_manager
+        .agent_for_path(&request.file_path)
+        .await;
+    let Some((agent_id, workspace_path)) = target else {
+        return LocalApiResponse::error(404, "No online agent for this file");
+    };
+    reveal_agent(
+        app_handle,
+        "showSession",
+        &agent_id,
+        &request.file_path,
+        None,
+    );
+    LocalApiResponse::ok(json!({ "agentId": agent_id, "workspacePath": workspace_path }))
+}
+
+/// 长轮询：返回序号大于 `after` 的消息，没有新消息时最多等待 `waitSecs`
+async fn poll(body: Value) -> LocalApiResponse {
+    let request: PollRequest = if body.is_null() {
+        PollRequest::default()
+    } else {
+        match parse_body(body) {
+            Ok(request) => request,
+            Err(response) => return response,
+        }
+    };
+    let wait = request
+        .wait_secs
+        .map(Duration::from_secs)
+        .unwrap_or(MAX_POLL_WAIT)
+        .min(MAX_POLL_WAIT);
+    let notified = OUTBOX_NOTIFY.notified();
+    let mut messages = lock_outbox().after(request.after);
+    if messages.is_empty() && !wait.is_zero() {
+        let _ = timeout(wait, notified).await;
+        messages = lock_outbox().after(request.after);
+    }
+    LocalApiResponse::ok(json!({ "messages": messages }))
+}
+
+/// 本地回调接口 `POST /v1/editor/<action>`
+pub(crate) async fn handle_editor_request(
+    app_handle: &tauri::AppHandle,
+    action: &str,
+    body: Value,
+) -> LocalApiResponse {
+    match action {
+        "hello" => LocalApiResponse::ok(json!({
+            "name": "flowhub",
+            "version": env!("CARGO_PKG_VERSION"),
+            "protocol": EDITOR_PROTOCOL_VERSION,
+            "capabilities": EDITOR_CAPABILITIES,
+        })),
+        "send-selection" => send_selection(app_handle, body).await,
+        "show-session" => show_session(app_handle, body).await,
+        "poll" => poll(body).await,
+        _ => LocalApiResponse::error(404, "Unknown editor action"),
+    }
+}
+
+/// 把补丁推送给打开了该工作区的编辑器，返回消息序号
+#[tauri::command]
+pub async fn push_editor_patch(
+    state: State<'_, AppState>,
+    agent_id: String,
+    file_path: String,
+    patch: String,
+) -> Result<u64, String> {
+    if patch.trim().is_empty() {
+        return Err("Patch cannot be empty".to_string());
+    }
+    let workspace_path = state
+        .agent_manager
+        .workspace_path_of(&agent_id)
+        .await
+        .ok_or_else(|| format!("Agent {} not found", agent_id))?;
+    let file_path = if Path::new(&file_path).is_absolute() {
+        file_path
+    } else {
+        Path::new(&workspace_path)
+            .join(&file_path)
+            .to_string_lossy()
+            .to_string()
+    };
+    let seq = lock_outbox().push(
+        &workspace_path,
+        EditorMessageBody::ApplyPatch { file_path, patch },
+    );
+    OUTBOX_NOTIFY.notify_waiters();
+    Ok(seq)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_prompt_uses_relative_path_and_lines() {
+        let request = SendSelectionRequest {
+            file_path: "/repo/src/main.rs".to_string(),
+            selection: "fn main() {}\n".to_string(),
+            language_id: Some("rust".to_string()),
+            start_line: Some(3),
+            end_line: Some(5),
+            instruction: Some("解释这段代码".to_string()),
+            agent_id: None,
+        };
+        assert_eq!(
+            selection_prompt(&request, "/repo"),
+            "解释这段代码\n\n`src/main.rs` 第 3-5 行：\n```rust\nfn main() {}\n```"
+        );
+    }
+
+    #[test]
+    fn outbox_keeps_recent_messages_in_order() {
+        let mut outbox = EditorOutbox::default();
+        for index in 0..(MAX_OUTBOX_MESSAGES + 5) {
+            outbox.push(
+                "/repo",
+                EditorMessageBody::ApplyPatch {
+                    file_path: format!("/repo/{}.rs", index),
+                    patch: "@@".to_string(),
+                },
+            );
+        }
+        let all = outbox.after(0);
+        assert_eq!(all.len(), MAX_OUTBOX_MESSAGES);
+        assert_eq!(all[0].seq, 6);
+        assert_eq!(outbox.after(all[MAX_OUTBOX_MESSAGES - 2].seq).len(), 1);
+
+        let value = serde_json::to_value(&all[0]).unwrap();
+        assert_eq!(value["type"], "applyPatch");
+        assert_eq!(value["filePath"], "/repo/5.rs");
+    }
+}
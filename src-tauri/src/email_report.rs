@@ -0,0 +1,331 @@
+//! 邮件报告：通过设置中的 SMTP 服务器把会话导出（PDF 附件）或每日摘要发送给指定收件人；
+//! SMTP 密码保存在全局密钥存储，附件大小受设置限制。
+use std::path::PathBuf;
+use std::time::Duration;
+
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::export::render_session_pdf_bytes;
+use crate::secrets::resolve_secret;
+use crate::state::AppState;
+use crate::tasks::{load_task_records, TaskRecord};
+
+/// SMTP 密码在密钥存储中的名称
+pub(crate) const SMTP_PASSWORD_SECRET: &str = "SMTP_PASSWORD";
+const DEFAULT_MAX_ATTACHMENT_BYTES: u64 = 10 * 1024 * 1024;
+const MAX_RECIPIENTS: usize = 20;
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+const DIGEST_WINDOW_HOURS: i64 = 24;
+const MAX_DIGEST_PROMPT_CHARS: usize = 120;
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// 明文连接后升级 TLS（通常为 587 端口）
+    #[default]
+    StartTls,
+    /// 直接 TLS（通常为 465 端口）
+    Tls,
+    /// 不加密，仅用于本机或内网中继
+    None,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SmtpConfig {
+    pub host: String,
+    /// 为空时按 security 使用默认端口
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default)]
+    pub username: Option<String>,
+    /// 发件人，例如 `FlowHub <bot@example.com>`
+    pub from: String,
+    #[serde(default)]
+    pub max_attachment_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ReportContent {
+    /// 会话导出为 PDF 附件
+    #[serde(rename_all = "camelCase")]
+    SessionExport {
+        session_id: String,
+        #[serde(default)]
+        font_path: Option<String>,
+    },
+    /// 最近 24 小时的任务摘要
+    DailyDigest,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailReceipt {
+    pub recipients: usize,
+    pub subject: String,
+    pub attachment_bytes: usize,
+}
+
+fn parse_recipients(recipients: &[String]) -> Result<Vec<Mailbox>, String> {
+    let recipients: Vec<&str> = recipients
+        .iter()
+        .map(|recipient| recipient.trim())
+        .filter(|recipient| !recipient.is_empty())
+        .collect();
+    if recipients.is_empty() {
+        return Err("At least one recipient is required".to_string());
+    }
+    if recipients.len() > MAX_RECIPIENTS {
+        return Err(format!("Too many recipients (>{})", MAX_RECIPIENTS));
+    }
+    recipients
+        .into_iter()
+        .map(|recipient| {
+            recipient
+                .parse::<Mailbox>()
+                .map_err(|e| format!("Invalid recipient {}: {}", recipient, e))
+        })
+        .collect()
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    let single_line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if single_line.chars().count() > max_chars {
+        format!(
+            "{}…",
+            single_line.chars().take(max_chars).collect::<String>()
+        )
+    } else {
+        single_line
+    }
+}
+
+/// 每日摘要正文：窗口内开始的任务数、失败数及逐条概要
+fn build_daily_digest(records: &[TaskRecord], now: chrono::DateTime<chrono::Utc>) -> String {
+    let since = now - chrono::Duration::hours(DIGEST_WINDOW_HOURS);
+    let recent: Vec<&TaskRecord> = records
+        .iter()
+        .filter(|record| {
+            chrono::DateTime::parse_from_rfc3339(&record.started_at)
+                .map(|started| started.with_timezone(&chrono::Utc) >= since)
+                .unwrap_or(false)
+        })
+        .collect();
+    let failed = recent
+        .iter()
+        .filter(|record| record.error.is_some())
+        .count();
+    let running = recent
+        .iter()
+        .filter(|record| record.finished_at.is_none())
+        .count();
+
+    let mut body = format!(
+        "FlowHub 每日摘要（{} 至 {}）\n\n任务 {} 个，失败 {} 个，进行中 {} 个\n",
+        since.format("%Y-%m-%d %H:%M UTC"),
+        now.format("%Y-%m-%d %H:%M UTC"),
+        recent.len(),
+        failed,
+        running
+    );
+    for record in recent.iter().rev() {
+        let status = match (&record.error, &record.finished_at) {
+            (Some(error), _) => format!("失败：{}", truncate_chars(error, MAX_DIGEST_PROMPT_CHARS)),
+            (None, Some(_)) => record
+                .stop_reason
+                .clone()
+                .unwrap_or_else(|| "完成".to_string()),
+            (None, None) => "进行中".to_string(),
+        };
+        body.push_str(&format!(
+            "\n- [{}] {}\n  {}",
+            record.started_at,
+            truncate_chars(&record.prompt, MAX_DIGEST_PROMPT_CHARS),
+            status
+        ));
+    }
+    body
+}
+
+async fn build_transport(
+    app_handle: &tauri::AppHandle,
+    config: &SmtpConfig,
+) -> Result<AsyncSmtpTransport<Tokio1Executor>, String> {
+    let host = config.host.trim();
+    if host.is_empty() {
+        return Err("SMTP host cannot be empty".to_string());
+    }
+    let builder = match config.security {
+        SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+            .map_err(|e| format!("Failed to configure SMTP: {}", e))?,
+        SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+            .map_err(|e| format!("Failed to configure SMTP: {}", e))?,
+        SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+    };
+    let mut builder = builder.timeout(Some(SMTP_TIMEOUT));
+    if let Some(port) = config.port {
+        builder = builder.port(port);
+    }
+    if let Some(username) = config
+        .username
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        let password = resolve_secret(app_handle, None, SMTP_PASSWORD_SECRET)
+            .await?
+            .ok_or_else(|| format!("Secret {} is not set", SMTP_PASSWORD_SECRET))?;
+        builder = builder.credentials(Credentials::new(username.to_string(), password));
+    }
+    Ok(builder.build())
+}
+
+/// 发送会话导出或每日摘要邮件
+#[tauri::command]
+pub async fn send_report_email(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    content: ReportContent,
+    recipients: Vec<String>,
+    subject: Option<String>,
+) -> Result<EmailReceipt, String> {
+    let config = state
+        .settings
+        .read()
+        .await
+        .smtp
+        .clone()
+        .ok_or_else(|| "SMTP is not configured".to_string())?;
+    let from = config
+        .from
+        .trim()
+        .parse::<Mailbox>()
+        .map_err(|e| format!("Invalid sender {}: {}", config.from, e))?;
+    let recipients = parse_recipients(&recipients)?;
+    let max_attachment_bytes = config
+        .max_attachment_bytes
+        .unwrap_or(DEFAULT_MAX_ATTACHMENT_BYTES);
+
+    let (default_subject, text, attachment) = match content {
+        ReportContent::SessionExport {
+            session_id,
+            font_path,
+        } => {
+            let font = font_path
+                .map(|path| path.trim().to_string())
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from);
+            let pdf = render_session_pdf_bytes(&app_handle, &state, &session_id, font).await?;
+            if pdf.len() as u64 > max_attachment_bytes {
+                return Err(format!(
+                    "Attachment is too large (>{} bytes)",
+                    max_attachment_bytes
+                ));
+            }
+            (
+                "FlowHub 会话导出".to_string(),
+                "会话导出见附件 PDF。".to_string(),
+                Some(pdf),
+            )
+        }
+        ReportContent::DailyDigest => {
+            let records = load_task_records(&app_handle).await?;
+            (
+                format!(
+                    "FlowHub 每日摘要 {}",
+                    chrono::Local::now().format("%Y-%m-%d")
+                ),
+                build_daily_digest(&records, chrono::Utc::now()),
+                None,
+            )
+        }
+    };
+    let subject = subject
+        .map(|subject| subject.trim().to_string())
+        .filter(|subject| !subject.is_empty())
+        .unwrap_or(default_subject);
+
+    let mut builder = Message::builder().from(from).subject(subject.clone());
+    for recipient in &recipients {
+        builder = builder.to(recipient.clone());
+    }
+    let attachment_bytes = attachment.as_ref().map(Vec::len).unwrap_or(0);
+    let body = match attachment {
+        Some(pdf) => {
+            let pdf_type = ContentType::parse("application/pdf")
+                .map_err(|e| format!("Invalid attachment type: {}", e))?;
+            MultiPart::mixed()
+                .singlepart(SinglePart::plain(text))
+                .singlepart(Attachment::new("session.pdf".to_string()).body(pdf, pdf_type))
+        }
+        None => MultiPart::mixed().singlepart(SinglePart::plain(text)),
+    };
+    let message = builder
+        .multipart(body)
+        .map_err(|e| format!("Failed to build email: {}", e))?;
+
+    build_transport(&app_handle, &config)
+        .await?
+        .send(message)
+        .await
+        .map_err(|e| format!("Failed to send email: {}", e))?;
+
+    Ok(EmailReceipt {
+        recipients: recipients.len(),
+        subject,
+        attachment_bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recipients_are_validated() {
+        let parsed = parse_recipients(&[
+            " alice@example.com ".to_string(),
+            "Bob <bob@example.com>".to_string(),
+            String::new(),
+        ])
+        .expect("valid recipients");
+        assert_eq!(parsed.len(), 2);
+        assert!(parse_recipients(&["not-an-address".to_string()]).is_err());
+        assert!(parse_recipients(&[]).is_err());
+        assert!(parse_recipients(&vec!["a@example.com".to_string(); MAX_RECIPIENTS + 1]).is_err());
+    }
+
+    #[test]
+    fn digest_covers_only_recent_tasks() {
+        let now = chrono::DateTime::parse_from_rfc3339("2026-03-02T08:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let task = |prompt: &str, started_at: &str, error: Option<&str>| TaskRecord {
+            prompt: prompt.to_string(),
+            started_at: started_at.to_string(),
+            finished_at: Some(started_at.to_string()),
+            stop_reason: Some("end_turn".to_string()),
+            error: error.map(str::to_string),
+            ..Default::default()
+        };
+        let records = vec![
+            task("old task", "2026-02-28T08:00:00Z", None),
+            task("write report", "2026-03-01T20:00:00Z", None),
+            task("deploy", "2026-03-02T07:00:00Z", Some("connection refused")),
+        ];
+
+        let digest = build_daily_digest(&records, now);
+        assert!(digest.contains("任务 2 个，失败 1 个，进行中 0 个"));
+        assert!(digest.contains("write report"));
+        assert!(digest.contains("失败：connection refused"));
+        assert!(!digest.contains("old task"));
+    }
+}
@@ -0,0 +1,803 @@
+//! 会话导出：从本地会话存储读取对话并生成 PDF 等归档格式
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use printpdf::{
+    BuiltinFont, Color, Image, ImageTransform, IndirectFontRef, Mm, PdfDocument,
+    PdfDocumentReference, PdfLayerReference, Rgb,
+};
+use tauri::{Manager, State};
+
+use crate::agents::registry::registered_workspace_path;
+use crate::highlight::{highlight_to_spans, HighlightSpan, DEFAULT_HIGHLIGHT_THEME};
+use crate::jobs::{spawn_job, JobContext, JobHandle};
+use crate::path_display::relativize_workspace_paths;
+use crate::state::AppState;
+use crate::storage::{read_snapshot_from_path, storage_path, StoredMessage, StoredSession};
+
+const PAGE_WIDTH_MM: f32 = 210.0;
+const PAGE_HEIGHT_MM: f32 = 297.0;
+const MARGIN_MM: f32 = 18.0;
+const BODY_FONT_SIZE: f32 = 10.5;
+const CODE_FONT_SIZE: f32 = 9.0;
+const HEADING_FONT_SIZE: f32 = 16.0;
+const ROLE_FONT_SIZE: f32 = 11.5;
+const LINE_SPACING: f32 = 1.45;
+const PT_TO_MM: f32 = 0.3528;
+const IMAGE_DPI: f32 = 150.0;
+const MAX_IMAGE_HEIGHT_MM: f32 = 120.0;
+
+/// 内置的 Helvetica/Courier 不含中日韩字形，未指定字体时按顺序查找系统字体。
+/// printpdf 以 TrueType 方式嵌入字体，TrueType 轮廓的字体排在前面
+const SYSTEM_CJK_FONTS: &[&str] = &[
+    r"C:\Windows\Fonts\msyh.ttc",
+    r"C:\Windows\Fonts\msyh.ttf",
+    r"C:\Windows\Fonts\simhei.ttf",
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Light.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+];
+
+pub(crate) struct SessionTranscript {
+    pub(crate) session: StoredSession,
+    pub(crate) messages: Vec<StoredMessage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TranscriptBlock {
+    Text(String),
+    Code {
+        language: String,
+        lines: Vec<String>,
+    },
+    Image {
+        alt: String,
+        path: String,
+    },
+}
+
+/// 从会话存储读取指定会话及其消息
+pub(crate) async fn load_session_transcript(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+    session_id: &str,
+) -> Result<SessionTranscript, String> {
+    let snapshot = {
+        let _guard = state.storage_lock.lock().await;
+        read_snapshot_from_path(&storage_path(app_handle)?).await?
+    };
+
+    let session = snapshot
+        .sessions_by_agent
+        .values()
+        .flatten()
+        .find(|session| session.id == session_id)
+        .cloned()
+        .ok_or_else(|| format!("Session {} not found", session_id))?;
+    let messages = snapshot
+        .messages_by_session
+        .get(session_id)
+        .cloned()
+        .unwrap_or_default();
+
+    Ok(SessionTranscript { session, messages })
+}
+
+fn parse_markdown_image(line: &str) -> Option<(String, String)> {
+    let trimmed = line.trim();
+    let rest = trimmed.strip_prefix("![")?;
+    let (alt, rest) = rest.split_once("](")?;
+    let path = rest.strip_suffix(')')?;
+    let path = path.split_whitespace().next().unwrap_or_default();
+    if path.is_empty() {
+        return None;
+    }
+    Some((alt.to_string(), path.to_string()))
+}
+
+fn parse_transcript_blocks(content: &str) -> Vec<TranscriptBlock> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut code: Option<(String, Vec<String>)> = None;
+
+    fn flush_paragraph(paragraph: &mut Vec<&str>, blocks: &mut Vec<TranscriptBlock>) {
+        if !paragraph.is_empty() {
+            blocks.push(TranscriptBlock::Text(paragraph.join("\n")));
+            paragraph.clear();
+        }
+    }
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            if let Some((language, lines)) = code.take() {
+                blocks.push(TranscriptBlock::Code { language, lines });
+            } else {
+                flush_paragraph(&mut paragraph, &mut blocks);
+                code = Some((
+                    trimmed.trim_start_matches('`').trim().to_string(),
+                    Vec::new(),
+                ));
+            }
+            continue;
+        }
+
+        if let Some((_, lines)) = code.as_mut() {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        if let Some((alt, path)) = parse_markdown_image(line) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            blocks.push(TranscriptBlock::Image { alt, path });
+            continue;
+        }
+
+        if line.trim().is_empty() {
+            flush_paragraph(&mut paragraph, &mut blocks);
+        } else {
+            paragraph.push(line);
+        }
+    }
+
+    if let Some((language, lines)) = code.take() {
+        blocks.push(TranscriptBlock::Code { language, lines });
+    }
+    flush_paragraph(&mut paragraph, &mut blocks);
+    blocks
+}
+
+fn is_cjk_char(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{3000}'..='\u{30FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{AC00}'..='\u{D7AF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FF00}'..='\u{FFEF}'
+    )
+}
+
+fn transcript_has_cjk(transcript: &SessionTranscript) -> bool {
+    std::iter::once(&transcript.session.title)
+        .chain(transcript.messages.iter().map(|message| &message.content))
+        .any(|text| text.chars().any(is_cjk_char))
+}
+
+fn system_cjk_font() -> Option<PathBuf> {
+    SYSTEM_CJK_FONTS
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.is_file())
+}
+
+fn estimated_char_width_mm(ch: char, font_size: f32, monospace: bool) -> f32 {
+    let ratio = if monospace {
+        if ch.is_ascii() {
+            0.6
+        } else {
+            1.0
+        }
+    } else if ch.is_ascii() {
+        0.55
+    } else {
+        1.0
+    };
+    ratio * font_size * PT_TO_MM
+}
+
+/// 按估算字宽折行（CJK 字符按全角计）
+fn wrap_text(text: &str, font_size: f32, max_width_mm: f32, monospace: bool) -> Vec<String> {
+    let mut lines = Vec::new();
+    for raw_line in text.lines() {
+        let mut current = String::new();
+        let mut width = 0.0_f32;
+        for ch in raw_line.chars() {
+            let ch = if ch == '\t' { ' ' } else { ch };
+            let char_width = estimated_char_width_mm(ch, font_size, monospace);
+            if width + char_width > max_width_mm && !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+                width = 0.0;
+            }
+            current.push(ch);
+            width += char_width;
+        }
+        lines.push(current);
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
+
+fn role_label(role: &str) -> &str {
+    match role {
+        "user" => "User",
+        "assistant" => "Assistant",
+        "system" => "System",
+        other => other,
+    }
+}
+
+fn rgb(r: f32, g: f32, b: f32) -> Color {
+    Color::Rgb(Rgb::new(r, g, b, None))
+}
+
+fn hex_to_color(hex: &str) -> Color {
+    let channel = |range: std::ops::Range<usize>| {
+        hex.trim_start_matches('#')
+            .get(range)
+            .and_then(|value| u8::from_str_radix(value, 16).ok())
+            .map(|value| value as f32 / 255.0)
+            .unwrap_or(0.1)
+    };
+    rgb(channel(0..2), channel(2..4), channel(4..6))
+}
+
+struct PdfFonts {
+    body: IndirectFontRef,
+    bold: IndirectFontRef,
+    code: IndirectFontRef,
+}
+
+struct PdfLayout {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    fonts: PdfFonts,
+    cursor_y: f32,
+    page_count: usize,
+}
+
+impl PdfLayout {
+    fn new(title: &str, font_path: Option<&Path>) -> Result<Self, String> {
+        let (doc, page, layer) =
+            PdfDocument::new(title, Mm(PAGE_WIDTH_MM), Mm(PAGE_HEIGHT_MM), "Layer 1");
+        let fonts = match font_path {
+            Some(path) => {
+                // CJK 字体动辄十几 MB，只嵌入一次，三种样式共用
+                let file = File::open(path)
+                    .map_err(|e| format!("Failed to open font {}: {}", path.display(), e))?;
+                let font = doc
+                    .add_external_font(file)
+                    .map_err(|e| format!("Failed to load font {}: {:?}", path.display(), e))?;
+                PdfFonts {
+                    body: font.clone(),
+                    bold: font.clone(),
+                    code: font,
+                }
+            }
+            None => {
+                let builtin = |font: BuiltinFont| {
+                    doc.add_builtin_font(font)
+                        .map_err(|e| format!("Failed to load builtin font: {:?}", e))
+                };
+                PdfFonts {
+                    body: builtin(BuiltinFont::Helvetica)?,
+                    bold: builtin(BuiltinFont::HelveticaBold)?,
+                    code: builtin(BuiltinFont::Courier)?,
+                }
+            }
+        };
+        let layer = doc.get_page(page).get_layer(layer);
+
+        Ok(Self {
+            doc,
+            layer,
+            fonts,
+            cursor_y: PAGE_HEIGHT_MM - MARGIN_MM,
+            page_count: 1,
+        })
+    }
+
+    fn content_width(&self) -> f32 {
+        PAGE_WIDTH_MM - MARGIN_MM * 2.0
+    }
+
+    fn ensure_space(&mut self, height_mm: f32) {
+        if self.cursor_y - height_mm >= MARGIN_MM {
+            return;
+        }
+        self.page_count += 1;
+        let (page, layer) = self.doc.add_page(
+            Mm(PAGE_WIDTH_MM),
+            Mm(PAGE_HEIGHT_MM),
+            format!("Layer {}", self.page_count),
+        );
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.cursor_y = PAGE_HEIGHT_MM - MARGIN_MM;
+    }
+
+    fn write_lines(&mut self, text: &str, font_size: f32, style: TextStyle, indent_mm: f32) {
+        let monospace = matches!(style, TextStyle::Code | TextStyle::CodeComment);
+        let line_height = font_size * PT_TO_MM * LINE_SPACING;
+        let max_width = self.content_width() - indent_mm;
+        for line in wrap_text(text, font_size, max_width, monospace) {
+            self.ensure_space(line_height);
+            self.cursor_y -= line_height;
+            let (font, color) = match style {
+                TextStyle::Body => (&self.fonts.body, rgb(0.1, 0.1, 0.1)),
+                TextStyle::Heading => (&self.fonts.bold, rgb(0.0, 0.0, 0.0)),
+                TextStyle::Meta => (&self.fonts.body, rgb(0.45, 0.45, 0.45)),
+                TextStyle::Code => (&self.fonts.code, rgb(0.12, 0.25, 0.45)),
+                TextStyle::CodeComment => (&self.fonts.code, rgb(0.4, 0.5, 0.4)),
+            };
+            self.layer.set_fill_color(color);
+            self.layer.use_text(
+                line,
+                font_size,
+                Mm(MARGIN_MM + indent_mm),
+                Mm(self.cursor_y),
+                font,
+            );
+        }
+    }
+
+    /// 单行高亮代码，按片段逐段着色；调用方保证不需要折行
+    fn write_code_spans(&mut self, spans: &[HighlightSpan], font_size: f32, indent_mm: f32) {
+        let line_height = font_size * PT_TO_MM * LINE_SPACING;
+        self.ensure_space(line_height);
+        self.cursor_y -= line_height;
+        let mut x = MARGIN_MM + indent_mm;
+        for span in spans {
+            let text = span.text.replace('\t', " ");
+            self.layer.set_fill_color(hex_to_color(&span.color));
+            self.layer.use_text(
+                text.as_str(),
+                font_size,
+                Mm(x),
+                Mm(self.cursor_y),
+                &self.fonts.code,
+            );
+            x += text
+                .chars()
+                .map(|ch| estimated_char_width_mm(ch, font_size, true))
+                .sum::<f32>();
+        }
+    }
+
+    fn write_code_block(&mut self, language: &str, lines: &[String]) {
+        if !language.is_empty() {
+            self.write_lines(language, CODE_FONT_SIZE - 1.0, TextStyle::Meta, 4.0);
+        }
+        // 与前端 highlight_code 使用同一套语法定义，导出配色保持一致
+        let highlighted = highlight_to_spans(
+            &lines.join("\n"),
+            Some(language),
+            Some(DEFAULT_HIGHLIGHT_THEME),
+        )
+        .ok();
+        let max_width = self.content_width() - 4.0;
+        for (index, line) in lines.iter().enumerate() {
+            let fits = wrap_text(line, CODE_FONT_SIZE, max_width, true).len() == 1;
+            if let Some(spans) = highlighted
+                .as_ref()
+                .and_then(|code| code.lines.get(index))
+                .filter(|_| fits)
+            {
+                self.write_code_spans(spans, CODE_FONT_SIZE, 4.0);
+                continue;
+            }
+
+            let trimmed = line.trim_start();
+            let style = if trimmed.starts_with("//") || trimmed.starts_with('#') {
+                TextStyle::CodeComment
+            } else {
+                TextStyle::Code
+            };
+            self.write_lines(line, CODE_FONT_SIZE, style, 4.0);
+        }
+        self.gap(2.0);
+    }
+
+    fn write_image(&mut self, alt: &str, path: &Path) {
+        let image = match printpdf::image_crate::open(path) {
+            Ok(image) => image,
+            Err(e) => {
+                println!("[export] Skip image {}: {}", path.display(), e);
+                self.write_lines(
+                    &format!("[image: {}]", alt),
+                    BODY_FONT_SIZE,
+                    TextStyle::Meta,
+                    0.0,
+                );
+                return;
+            }
+        };
+
+        let width_mm = image.width() as f32 / IMAGE_DPI * 25.4;
+        let height_mm = image.height() as f32 / IMAGE_DPI * 25.4;
+        let scale = (self.content_width() / width_mm)
+            .min(MAX_IMAGE_HEIGHT_MM / height_mm)
+            .min(1.0);
+        let rendered_height = height_mm * scale;
+
+        self.ensure_space(rendered_height + 2.0);
+        self.cursor_y -= rendered_height;
+        Image::from_dynamic_image(&image).add_to_layer(
+            self.layer.clone(),
+            ImageTransform {
+                translate_x: Some(Mm(MARGIN_MM)),
+                translate_y: Some(Mm(self.cursor_y)),
+                scale_x: Some(scale),
+                scale_y: Some(scale),
+                dpi: Some(IMAGE_DPI),
+                ..Default::default()
+            },
+        );
+        self.gap(2.0);
+    }
+
+    fn gap(&mut self, height_mm: f32) {
+        self.cursor_y -= height_mm;
+    }
+
+    fn save(self, output_path: &Path) -> Result<(), String> {
+        let file = File::create(output_path)
+            .map_err(|e| format!("Failed to create {}: {}", output_path.display(), e))?;
+        self.doc
+            .save(&mut BufWriter::new(file))
+            .map_err(|e| format!("Failed to write PDF: {:?}", e))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum TextStyle {
+    Body,
+    Heading,
+    Meta,
+    Code,
+    CodeComment,
+}
+
+/// 只嵌入会话工作区内的本地图片；绝对路径或 `..` 指向工作区外时返回 None，按占位文本输出，
+/// 避免消息里点名的任意本机文件被写进（可能被邮件发出的）PDF
+fn resolve_image_path(raw: &str, base_dir: Option<&Path>) -> Option<PathBuf> {
+    let trimmed = raw.trim().trim_start_matches("file://");
+    if trimmed.starts_with("http://")
+        || trimmed.starts_with("https://")
+        || trimmed.starts_with("data:")
+    {
+        return None;
+    }
+    let base_dir = std::fs::canonicalize(base_dir?).ok()?;
+    let resolved = std::fs::canonicalize(base_dir.join(trimmed)).ok()?;
+    resolved.starts_with(&base_dir).then_some(resolved)
+}
+
+/// 每渲染一条消息前调用 before_message(已完成数, 总数)，返回 Err 时中止且不写出文件
+fn render_transcript_pdf(
+    transcript: &SessionTranscript,
+    output_path: &Path,
+    font_path: Option<&Path>,
+    image_base_dir: Option<&Path>,
+    before_message: &dyn Fn(usize, usize) -> Result<(), String>,
+) -> Result<(), String> {
+    let fallback_font = match font_path {
+        Some(_) => None,
+        None if transcript_has_cjk(transcript) => Some(system_cjk_font().ok_or_else(|| {
+            "No CJK font found on this system; choose a TTF font that covers Chinese".to_string()
+        })?),
+        None => None,
+    };
+    let font_path = font_path.or(fallback_font.as_deref());
+    let mut layout = PdfLayout::new(&transcript.session.title, font_path)?;
+
+    layout.write_lines(
+        &transcript.session.title,
+        HEADING_FONT_SIZE,
+        TextStyle::Heading,
+        0.0,
+    );
+    layout.write_lines(
+        &format!(
+            "{} · {} messages",
+            transcript.session.created_at,
+            transcript.messages.len()
+        ),
+        BODY_FONT_SIZE - 1.0,
+        TextStyle::Meta,
+        0.0,
+    );
+    layout.gap(6.0);
+
+    let total = transcript.messages.len();
+    // 出现模型切换标记后，助手消息标题注明所用模型
+    let mut current_model: Option<&str> = None;
+    for (index, message) in transcript.messages.iter().enumerate() {
+        before_message(index, total)?;
+        if let Some(switch) = message.model_switch.as_ref() {
+            current_model = Some(switch.to.as_str());
+        }
+        let label = match (message.role.as_str(), current_model) {
+            ("assistant", Some(model)) => format!("{} · {}", role_label(&message.role), model),
+            _ => role_label(&message.role).to_string(),
+        };
+        layout.write_lines(
+            &format!("{}  {}", label, message.timestamp),
+            ROLE_FONT_SIZE,
+            TextStyle::Heading,
+            0.0,
+        );
+        layout.gap(1.0);
+
+        for block in parse_transcript_blocks(&message.content) {
+            match block {
+                TranscriptBlock::Text(text) => {
+                    layout.write_lines(&text, BODY_FONT_SIZE, TextStyle::Body, 0.0);
+                    layout.gap(1.5);
+                }
+                TranscriptBlock::Code { language, lines } => {
+                    layout.write_code_block(&language, &lines);
+                }
+                TranscriptBlock::Image { alt, path } => {
+                    match resolve_image_path(&path, image_base_dir) {
+                        Some(resolved) => layout.write_image(&alt, &resolved),
+                        None => layout.write_lines(
+                            &format!("[image: {}]({})", alt, path),
+                            BODY_FONT_SIZE,
+                            TextStyle::Meta,
+                            0.0,
+                        ),
+                    }
+                }
+            }
+        }
+        layout.gap(4.0);
+    }
+
+    layout.save(output_path)
+}
+
+/// 将本地会话导出为 PDF（可指定 TTF 字体；未指定且含中日韩文字时使用系统 CJK 字体）；以后台任务执行，完成结果为输出路径
+#[tauri::command]
+pub async fn export_conversation_pdf(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    output_path: String,
+    font_path: Option<String>,
+    workspace_path: Option<String>,
+) -> Result<JobHandle, String> {
+    let output = PathBuf::from(output_path.trim());
+    if output.as_os_str().is_empty() {
+        return Err("Output path cannot be empty".to_string());
+    }
+    Ok(spawn_job(
+        &app_handle,
+        &state,
+        "export_pdf",
+        move |job| async move {
+            export_pdf_job(job, session_id, output, font_path, workspace_path).await
+        },
+    ))
+}
+
+async fn export_pdf_job(
+    job: JobContext,
+    session_id: String,
+    output: PathBuf,
+    font_path: Option<String>,
+    workspace_path: Option<String>,
+) -> Result<String, String> {
+    let mut transcript = {
+        let app_handle = job.app_handle();
+        load_session_transcript(app_handle, &app_handle.state::<AppState>(), &session_id).await?
+    };
+    job.check_cancelled()?;
+    let font = font_path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from);
+    let image_base = workspace_path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from);
+
+    // 导出内容可能被分享，工作区内的绝对路径改写为相对路径（图片仍相对工作区解析）
+    if let Some(workspace) = image_base.as_deref() {
+        let workspace = workspace.to_string_lossy();
+        for message in &mut transcript.messages {
+            message.content = relativize_workspace_paths(&message.content, &workspace);
+        }
+    }
+
+    let output_clone = output.clone();
+    tokio::task::spawn_blocking(move || {
+        let before_message = |done: usize, total: usize| -> Result<(), String> {
+            job.check_cancelled()?;
+            job.report(done, total, "rendering messages");
+            Ok(())
+        };
+        render_transcript_pdf(
+            &transcript,
+            &output_clone,
+            font.as_deref(),
+            image_base.as_deref(),
+            &before_message,
+        )
+    })
+    .await
+    .map_err(|e| format!("PDF export task failed: {}", e))??;
+
+    Ok(output.to_string_lossy().to_string())
+}
+
+/// 渲染会话 PDF 并返回内容（用于邮件附件等不落盘到用户目录的场景）
+pub(crate) async fn render_session_pdf_bytes(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+    session_id: &str,
+    font_path: Option<PathBuf>,
+) -> Result<Vec<u8>, String> {
+    let mut transcript = load_session_transcript(app_handle, state, session_id).await?;
+    // 与 export_conversation_pdf 一致，按会话所属 Agent 的工作区改写绝对路径
+    let workspace_path = match state
+        .agent_manager
+        .workspace_path_of(&transcript.session.agent_id)
+        .await
+    {
+        Some(path) => Some(path),
+        None => registered_workspace_path(app_handle, &transcript.session.agent_id).await,
+    };
+    if let Some(workspace) = workspace_path.as_deref() {
+        for message in &mut transcript.messages {
+            message.content = relativize_workspace_paths(&message.content, workspace);
+        }
+    }
+    let image_base = workspace_path.map(PathBuf::from);
+    let output = std::env::temp_dir().join(format!("iflow-report-{}.pdf", uuid::Uuid::new_v4()));
+    let output_clone = output.clone();
+    tokio::task::spawn_blocking(move || {
+        render_transcript_pdf(
+            &transcript,
+            &output_clone,
+            font_path.as_deref(),
+            image_base.as_deref(),
+            &|_, _| Ok(()),
+        )
+    })
+    .await
+    .map_err(|e| format!("PDF export task failed: {}", e))??;
+    let bytes = tokio::fs::read(&output)
+        .await
+        .map_err(|e| format!("Failed to read exported PDF: {}", e));
+    let _ = tokio::fs::remove_file(&output).await;
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_transcript_blocks_splits_code_and_images() {
+        let content =
+            "Intro line\nsecond\n\n```rust\nfn main() {}\n```\n![chart](out/chart.png)\nTail";
+        let blocks = parse_transcript_blocks(content);
+        assert_eq!(
+            blocks,
+            vec![
+                TranscriptBlock::Text("Intro line\nsecond".to_string()),
+                TranscriptBlock::Code {
+                    language: "rust".to_string(),
+                    lines: vec!["fn main() {}".to_string()],
+                },
+                TranscriptBlock::Image {
+                    alt: "chart".to_string(),
+                    path: "out/chart.png".to_string(),
+                },
+                TranscriptBlock::Text("Tail".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn images_outside_the_workspace_are_not_embedded() {
+        let root = std::env::temp_dir().join(format!("export-images-{}", uuid::Uuid::new_v4()));
+        let workspace = root.join("workspace");
+        std::fs::create_dir_all(workspace.join("out")).unwrap();
+        std::fs::write(workspace.join("out/chart.png"), b"png").unwrap();
+        std::fs::write(root.join("secret.png"), b"png").unwrap();
+
+        let inside = resolve_image_path("out/chart.png", Some(&workspace)).expect("inside");
+        assert!(inside.ends_with("out/chart.png"));
+        let outside = root.join("secret.png");
+        assert!(resolve_image_path(&outside.to_string_lossy(), Some(&workspace)).is_none());
+        assert!(resolve_image_path("../secret.png", Some(&workspace)).is_none());
+        assert!(resolve_image_path("out/../../secret.png", Some(&workspace)).is_none());
+        assert!(resolve_image_path("out/chart.png", None).is_none());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn wrap_text_respects_width_and_cjk() {
+        let ascii = wrap_text(&"a".repeat(100), 10.0, 50.0, true);
+        assert!(ascii.len() > 1);
+        assert!(ascii.iter().all(|line| line.chars().count() <= 24));
+
+        let cjk = wrap_text(&"中".repeat(20), 10.0, 35.3, false);
+        assert_eq!(cjk[0].chars().count(), 10);
+    }
+
+    fn transcript(content: &str) -> SessionTranscript {
+        SessionTranscript {
+            session: StoredSession {
+                id: "s1".to_string(),
+                agent_id: "a1".to_string(),
+                title: "Demo".to_string(),
+                created_at: "2024-01-01T00:00:00Z".to_string(),
+                updated_at: "2024-01-01T00:00:00Z".to_string(),
+                ..Default::default()
+            },
+            messages: vec![StoredMessage {
+                id: "m1".to_string(),
+                role: "assistant".to_string(),
+                content: content.to_string(),
+                timestamp: "2024-01-01T00:00:01Z".to_string(),
+                agent_id: None,
+                interrupted: false,
+                attachments: Vec::new(),
+                model_switch: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn render_transcript_pdf_writes_file() {
+        let transcript = transcript("Hello\n```js\n// note\nconsole.log(1)\n```");
+        let output =
+            std::env::temp_dir().join(format!("iflow-export-{}.pdf", uuid::Uuid::new_v4()));
+        render_transcript_pdf(&transcript, &output, None, None, &|_, _| Ok(()))
+            .expect("render pdf");
+        let bytes = std::fs::read(&output).expect("read pdf");
+        assert!(bytes.starts_with(b"%PDF"));
+        let _ = std::fs::remove_file(&output);
+
+        let cancelled = render_transcript_pdf(&transcript, &output, None, None, &|_, _| {
+            Err("Job cancelled".to_string())
+        });
+        assert!(cancelled.is_err());
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn cjk_text_is_rendered_with_cjk_glyphs() {
+        let text = "中文导出测试";
+        let output =
+            std::env::temp_dir().join(format!("iflow-export-{}.pdf", uuid::Uuid::new_v4()));
+        let result = render_transcript_pdf(&transcript(text), &output, None, None, &|_, _| Ok(()));
+        if system_cjk_font().is_none() {
+            // 没有可用的 CJK 字体时明确报错，不输出缺字的 PDF
+            assert!(result.unwrap_err().contains("CJK font"));
+            assert!(!output.exists());
+            return;
+        }
+        result.expect("render pdf");
+
+        let doc = printpdf::lopdf::Document::load(&output).expect("load pdf");
+        let page_id = *doc.get_pages().values().next().expect("first page");
+        let content = doc
+            .get_and_decode_page_content(page_id)
+            .expect("decode page");
+        // 外部字体按 2 字节字形 ID 写入，缺字的字符会被丢弃
+        let glyph_count = text.chars().count();
+        assert!(content
+            .operations
+            .iter()
+            .filter(|operation| operation.operator == "Tj")
+            .filter_map(|operation| operation.operands.first()?.as_str().ok())
+            .any(|bytes| bytes.len() == glyph_count * 2
+                && bytes.chunks(2).all(|glyph| glyph != [0, 0])));
+        let _ = std::fs::remove_file(&output);
+    }
+}
@@ -0,0 +1,128 @@
+//! 单条 prompt 的生成参数（temperature、top_p、最大输出 token、停止序列）：
+//! 发送前校验，写入 session/prompt 请求的 `settings`，并随任务记录保存以便复现。
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+const MAX_STOP_SEQUENCES: usize = 4;
+const MAX_STOP_SEQUENCE_CHARS: usize = 64;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequences: Vec<String>,
+}
+
+impl GenerationOptions {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.temperature.is_none()
+            && self.top_p.is_none()
+            && self.max_output_tokens.is_none()
+            && self.stop_sequences.is_empty()
+    }
+
+    /// 校验取值范围，去掉空的停止序列；全部为空时返回 None
+    pub(crate) fn normalized(mut self) -> Result<Option<Self>, String> {
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                return Err("temperature must be between 0 and 2".to_string());
+            }
+        }
+        if let Some(top_p) = self.top_p {
+            if top_p <= 0.0 || top_p > 1.0 {
+                return Err("top_p must be greater than 0 and at most 1".to_string());
+            }
+        }
+        if self.max_output_tokens == Some(0) {
+            return Err("max output tokens must be greater than zero".to_string());
+        }
+        self.stop_sequences.retain(|item| !item.is_empty());
+        if self.stop_sequences.len() > MAX_STOP_SEQUENCES {
+            return Err(format!(
+                "At most {} stop sequences are allowed",
+                MAX_STOP_SEQUENCES
+            ));
+        }
+        if self
+            .stop_sequences
+            .iter()
+            .any(|item| item.chars().count() > MAX_STOP_SEQUENCE_CHARS)
+        {
+            return Err(format!(
+                "Stop sequences must be at most {} characters",
+                MAX_STOP_SEQUENCE_CHARS
+            ));
+        }
+        Ok((!self.is_empty()).then_some(self))
+    }
+
+    /// session/prompt 请求中的 `settings` 字段，键名与 session/new 的 settings 风格一致
+    pub(crate) fn prompt_settings(&self) -> Value {
+        let mut settings = Map::new();
+        if let Some(temperature) = self.temperature {
+            settings.insert("temperature".to_string(), Value::from(temperature));
+        }
+        if let Some(top_p) = self.top_p {
+            settings.insert("top_p".to_string(), Value::from(top_p));
+        }
+        if let Some(max_output_tokens) = self.max_output_tokens {
+            settings.insert("max_tokens".to_string(), Value::from(max_output_tokens));
+        }
+        if !self.stop_sequences.is_empty() {
+            settings.insert("stop".to_string(), Value::from(self.stop_sequences.clone()));
+        }
+        Value::Object(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn options_are_validated_and_mapped_to_prompt_settings() {
+        let options = GenerationOptions {
+            temperature: Some(0.2),
+            top_p: None,
+            max_output_tokens: Some(2048),
+            stop_sequences: vec!["".to_string(), "###".to_string()],
+        }
+        .normalized()
+        .unwrap()
+        .expect("non-empty options");
+        assert_eq!(options.stop_sequences, vec!["###".to_string()]);
+        assert_eq!(
+            options.prompt_settings(),
+            json!({ "temperature": 0.2, "max_tokens": 2048, "stop": ["###"] })
+        );
+
+        assert_eq!(GenerationOptions::default().normalized().unwrap(), None);
+        for invalid in [
+            GenerationOptions {
+                temperature: Some(2.5),
+                ..Default::default()
+            },
+            GenerationOptions {
+                top_p: Some(0.0),
+                ..Default::default()
+            },
+            GenerationOptions {
+                max_output_tokens: Some(0),
+                ..Default::default()
+            },
+            GenerationOptions {
+                stop_sequences: vec!["x".to_string(); 5],
+                ..Default::default()
+            },
+        ] {
+            assert!(invalid.normalized().is_err());
+        }
+    }
+}
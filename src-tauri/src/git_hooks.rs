@@ -0,0 +1,366 @@
+//! Git 钩子集成：安装 pre-commit / pre-push 钩子，提交或推送前通过本地回调接口通知 FlowHub，
+//! 由工作区内的在线 Agent 审查待提交的改动，钩子阻塞到用户在 FlowHub 中批准或拒绝为止。
+//! FlowHub 未运行时钩子直接放行；不会覆盖已有的非 FlowHub 钩子，除非用户确认（原钩子会备份）。
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::Manager;
+use tokio::fs;
+use tokio::process::Command;
+use tokio::sync::oneshot;
+use tokio::time::{timeout, Duration};
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::local_api::{local_api_info_path, LocalApiResponse};
+use crate::models::ListenerCommand;
+use crate::state::AppState;
+
+/// 钩子脚本中的标记行，用于识别由 FlowHub 管理的钩子
+const HOOK_MARKER: &str = "flowhub-agent-review";
+const BACKUP_SUFFIX: &str = "flowhub-backup";
+const REVIEW_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+const GIT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REVIEW_DIFF_CHARS: usize = 60_000;
+
+static PENDING_REVIEWS: Lazy<Mutex<HashMap<String, oneshot::Sender<bool>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum GitHookKind {
+    PreCommit,
+    PrePush,
+}
+
+impl GitHookKind {
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::PreCommit => "pre-commit",
+            Self::PrePush => "pre-push",
+        }
+    }
+
+    fn action_label(self) -> &'static str {
+        match self {
+            Self::PreCommit => "提交",
+            Self::PrePush => "推送",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitHookInstallResult {
+    pub path: String,
+    /// 被替换的原钩子备份路径
+    pub backup_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GitReviewRequest {
+    workspace_path: String,
+    hook: GitHookKind,
+}
+
+fn lock_pending() -> std::sync::MutexGuard<'static, HashMap<String, oneshot::Sender<bool>>> {
+    PENDING_REVIEWS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub(crate) async fn run_git(workspace_path: &str, args: &[&str]) -> Result<String, String> {
+    let output = timeout(
+        GIT_TIMEOUT,
+        Command::new("git")
+            .arg("-C")
+            .arg(workspace_path)
+            .args(args)
+            .output(),
+    )
+    .await
+    .map_err(|_| "Git command timeout".to_string())?
+    .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// 钩子目录（遵循 core.hooksPath）
+async fn hooks_dir(workspace_path: &str) -> Result<PathBuf, String> {
+    let raw = run_git(workspace_path, &["rev-parse", "--git-path", "hooks"]).await?;
+    let path = PathBuf::from(raw.trim());
+    Ok(if path.is_absolute() {
+        path
+    } else {
+        Path::new(workspace_path).join(path)
+    })
+}
+
+/// 单引号包裹的 shell 字符串
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn render_hook_script(hook: GitHookKind, workspace_path: &str, info_path: &Path) -> String {
+    let body = json!({
+        "workspacePath": workspace_path,
+        "hook": hook,
+    })
+    .to_string();
+    format!(
+        r#"#!/bin/sh
+# {marker}: managed by FlowHub, remove it from FlowHub to restore the previous hook
+# {action}前由 FlowHub 中的 Agent 审查改动，需在 FlowHub 中批准后才能继续
+if [ "${{FLOWHUB_SKIP_REVIEW:-}}" = "1" ]; then
+  exit 0
+fi
+INFO_FILE={info}
+if ! command -v curl >/dev/null 2>&1 || [ ! -f "$INFO_FILE" ]; then
+  echo "[FlowHub] 未检测到 FlowHub，跳过 Agent 审查" >&2
+  exit 0
+fi
+PORT=$(sed -n 's/.*"port":\([0-9]*\).*/\1/p' "$INFO_FILE")
+TOKEN=$(sed -n 's/.*"token":"\([^"]*\)".*/\1/p' "$INFO_FILE")
+echo "[FlowHub] 等待在 FlowHub 中批准本次{action}..." >&2
+RESULT=$(curl -s --max-time {timeout} -X POST \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "Content-Type: application/json" \
+  --data {body} \
+  "http://127.0.0.1:$PORT/v1/git-review")
+if [ $? -ne 0 ]; then
+  echo "[FlowHub] 无法连接 FlowHub，跳过 Agent 审查" >&2
+  exit 0
+fi
+case "$RESULT" in
+  *'"approved":true'*) exit 0 ;;
+esac
+echo "[FlowHub] 改动未获批准，已阻止{action}（设置 FLOWHUB_SKIP_REVIEW=1 可跳过）" >&2
+exit 1
+"#,
+        marker = HOOK_MARKER,
+        action = hook.action_label(),
+        info = shell_quote(&info_path.to_string_lossy()),
+        timeout = REVIEW_TIMEOUT.as_secs() + 30,
+        body = shell_quote(&body),
+    )
+}
+
+async fn is_managed_hook(path: &Path) -> bool {
+    fs::read_to_string(path)
+        .await
+        .map(|content| content.contains(HOOK_MARKER))
+        .unwrap_or(false)
+}
+
+/// 写入审查钩子；已有非 FlowHub 钩子时需 `overwrite` 确认，原钩子改名备份
+#[tauri::command]
+pub async fn install_git_hook(
+    app_handle: tauri::AppHandle,
+    workspace_path: String,
+    hook: GitHookKind,
+    overwrite: Option<bool>,
+) -> Result<GitHookInstallResult, String> {
+    let dir = hooks_dir(&workspace_path).await?;
+    fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create hooks dir: {}", e))?;
+    let path = dir.join(hook.file_name());
+
+    let mut backup_path = None;
+    if fs::try_exists(&path).await.unwrap_or(false) && !is_managed_hook(&path).await {
+        if !overwrite.unwrap_or(false) {
+            return Err(format!(
+                "A {} hook already exists; confirm to replace it (the original will be backed up)",
+                hook.file_name()
+            ));
+        }
+        let backup = dir.join(format!("{}.{}", hook.file_name(), BACKUP_SUFFIX));
+        fs::rename(&path, &backup)
+            .await
+            .map_err(|e| format!("Failed to back up existing hook: {}", e))?;
+        backup_path = Some(backup.to_string_lossy().to_string());
+    }
+
+    let script = render_hook_script(hook, &workspace_path, &local_api_info_path(&app_handle)?);
+    fs::write(&path, script)
+        .await
+        .map_err(|e| format!("Failed to write git hook: {}", e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .await
+            .map_err(|e| format!("Failed to make git hook executable: {}", e))?;
+    }
+    Ok(GitHookInstallResult {
+        path: path.to_string_lossy().to_string(),
+        backup_path,
+    })
+}
+
+/// 移除 FlowHub 钩子并恢复安装时备份的原钩子；不会删除非 FlowHub 钩子
+#[tauri::command]
+pub async fn uninstall_git_hook(workspace_path: String, hook: GitHookKind) -> Result<bool, String> {
+    let dir = hooks_dir(&workspace_path).await?;
+    let path = dir.join(hook.file_name());
+    if !fs::try_exists(&path).await.unwrap_or(false) {
+        return Ok(false);
+    }
+    if !is_managed_hook(&path).await {
+        return Err(format!(
+            "The {} hook is not managed by FlowHub",
+            hook.file_name()
+        ));
+    }
+    fs::remove_file(&path)
+        .await
+        .map_err(|e| format!("Failed to remove git hook: {}", e))?;
+    let backup = dir.join(format!("{}.{}", hook.file_name(), BACKUP_SUFFIX));
+    if fs::try_exists(&backup).await.unwrap_or(false) {
+        fs::rename(&backup, &path)
+            .await
+            .map_err(|e| format!("Failed to restore previous hook: {}", e))?;
+    }
+    Ok(true)
+}
+
+/// 批准或拒绝正在等待的钩子审查
+#[tauri::command]
+pub async fn resolve_git_review(review_id: String, approved: bool) -> Result<(), String> {
+    let sender = lock_pending()
+        .remove(&review_id)
+        .ok_or_else(|| format!("Review {} not found", review_id))?;
+    let _ = sender.send(approved);
+    Ok(())
+}
+
+/// 待审查的改动：pre-commit 为暂存区，pre-push 为尚未推送到上游的提交
+async fn review_diff(workspace_path: &str, hook: GitHookKind) -> Result<String, String> {
+    match hook {
+        GitHookKind::PreCommit => run_git(workspace_path, &["diff", "--cached"]).await,
+        GitHookKind::PrePush => {
+            match run_git(workspace_path, &["diff", "@{upstream}...HEAD"]).await {
+                Ok(diff) => Ok(diff),
+                Err(_) => run_git(workspace_path, &["show", "--format=", "HEAD"]).await,
+            }
+        }
+    }
+}
+
+fn review_prompt(hook: GitHookKind, diff: &str) -> String {
+    let truncated = diff.chars().count() > MAX_REVIEW_DIFF_CHARS;
+    let mut diff: String = diff.chars().take(MAX_REVIEW_DIFF_CHARS).collect();
+    if truncated {
+        diff.push_str("\n...（改动过长，已截断）");
+    }
+    format!(
+        "请审查以下即将{}的改动，指出潜在缺陷、安全问题和需要修改的地方，最后给出「建议通过」或「建议修改」的结论。\n\n```diff\n{}\n```",
+        hook.action_label(),
+        diff.trim_end()
+    )
+}
+
+/// 本地回调接口 `POST /v1/git-review`：等待用户批准，超时视为拒绝
+pub(crate) async fn handle_git_review_request(
+    app_handle: &tauri::AppHandle,
+    body: Value,
+) -> LocalApiResponse {
+    let request: GitReviewRequest = match serde_json::from_value(body) {
+        Ok(request) => request,
+        Err(e) => return LocalApiResponse::error(400, &format!("Invalid review request: {}", e)),
+    };
+    let diff = match review_diff(&request.workspace_path, request.hook).await {
+        Ok(diff) => diff,
+        Err(e) => return LocalApiResponse::error(400, &e),
+    };
+    if diff.trim().is_empty() {
+        return LocalApiResponse::ok(json!({ "approved": true, "reason": "empty diff" }));
+    }
+
+    let review_id = uuid::Uuid::new_v4().to_string();
+    let (tx, rx) = oneshot::channel();
+    lock_pending().insert(review_id.clone(), tx);
+
+    let state = app_handle.state::<AppState>();
+    let agent_id = state
+        .agent_manager
+        .agent_for_workspace(&request.workspace_path)
+        .await;
+    // 已代为发送的审查提问，由前端记为用户消息
+    let mut prompt = None;
+    if let Some(agent_id) = agent_id.as_deref() {
+        if let (_, Some(sender)) = state.agent_manager.sender_of(agent_id).await {
+            let content = review_prompt(request.hook, &diff);
+            if sender
+                .send(ListenerCommand::UserPrompt {
+                    content: content.clone(),
+                    session_id: None,
+                    generation: None,
+                    resources: Vec::new(),
+                })
+                .is_ok()
+            {
+                prompt = Some(content);
+            }
+        }
+    }
+    let _ = app_handle.emit_unless_locked(
+        "git-review-requested",
+        json!({
+            "reviewId": &review_id,
+            "workspacePath": &request.workspace_path,
+            "hook": request.hook,
+            "agentId": agent_id,
+            "diffLines": diff.lines().count(),
+            "prompt": prompt,
+        }),
+    );
+
+    let approved = match timeout(REVIEW_TIMEOUT, rx).await {
+        Ok(Ok(approved)) => approved,
+        _ => {
+            lock_pending().remove(&review_id);
+            false
+        }
+    };
+    LocalApiResponse::ok(json!({ "approved": approved, "reviewId": review_id }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hook_script_embeds_quoted_request() {
+        let script = render_hook_script(
+            GitHookKind::PreCommit,
+            "/tmp/it's repo",
+            Path::new("/home/u/.local/share/flowhub/local-api.json"),
+        );
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains(HOOK_MARKER));
+        assert!(script.contains(r#""workspacePath":"/tmp/it'\''s repo""#));
+        assert!(script.contains(r#""hook":"pre-commit""#));
+        assert!(script.contains("INFO_FILE='/home/u/.local/share/flowhub/local-api.json'"));
+        assert_eq!(shell_quote("a'b"), r#"'a'\''b'"#);
+    }
+
+    #[test]
+    fn review_prompt_truncates_long_diffs() {
+        let long = "+x\n".repeat(MAX_REVIEW_DIFF_CHARS);
+        let prompt = review_prompt(GitHookKind::PrePush, &long);
+        assert!(prompt.contains("即将推送"));
+        assert!(prompt.contains("已截断"));
+        assert!(!review_prompt(GitHookKind::PreCommit, "+x\n").contains("已截断"));
+    }
+}
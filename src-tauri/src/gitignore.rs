@@ -0,0 +1,118 @@
+//! 工作区 `.gitignore` 维护：FlowHub 在工作区内创建的目录（如 `.flowhub/diagrams`）追加到
+//! 工作区根的 `.gitignore`，避免应用数据被误提交。只处理已存在的目录；已忽略的条目不重复追加。
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::git_hooks::run_git;
+
+/// FlowHub 在工作区内管理的目录
+const APP_MANAGED_ENTRIES: &[&str] = &[".flowhub/"];
+const GITIGNORE_HEADER: &str = "# FlowHub";
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GitignoreUpdate {
+    pub path: String,
+    /// 需要（或已经）追加的条目
+    pub added: Vec<String>,
+    /// 预览模式下为 false
+    pub applied: bool,
+}
+
+/// `.flowhub/`、`.flowhub`、`/.flowhub/` 等写法都视为已忽略
+fn is_ignored(content: &str, entry: &str) -> bool {
+    let name = entry.trim_matches('/');
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .any(|line| line.trim_matches('/') == name)
+}
+
+fn missing_entries(content: &str, entries: &[&str]) -> Vec<String> {
+    entries
+        .iter()
+        .filter(|entry| !is_ignored(content, entry))
+        .map(|entry| entry.to_string())
+        .collect()
+}
+
+fn append_entries(content: &str, entries: &[String]) -> String {
+    let mut updated = content.to_string();
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    if !updated.lines().any(|line| line.trim() == GITIGNORE_HEADER) {
+        if !updated.is_empty() {
+            updated.push('\n');
+        }
+        updated.push_str(GITIGNORE_HEADER);
+        updated.push('\n');
+    }
+    for entry in entries {
+        updated.push_str(entry);
+        updated.push('\n');
+    }
+    updated
+}
+
+/// 把工作区内已存在的 FlowHub 目录追加到 `.gitignore`；`dry_run` 时只返回待追加的条目
+#[tauri::command]
+pub async fn ensure_gitignore_entries(
+    workspace_path: String,
+    dry_run: Option<bool>,
+) -> Result<GitignoreUpdate, String> {
+    let inside = run_git(&workspace_path, &["rev-parse", "--is-inside-work-tree"]).await?;
+    if inside.trim() != "true" {
+        return Err(format!("{} is not a git work tree", workspace_path));
+    }
+
+    let workspace = Path::new(&workspace_path);
+    let present: Vec<&str> = APP_MANAGED_ENTRIES
+        .iter()
+        .copied()
+        .filter(|entry| workspace.join(entry.trim_matches('/')).is_dir())
+        .collect();
+    let path = workspace.join(".gitignore");
+    let content = match tokio::fs::read_to_string(&path).await {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(format!("Failed to read {}: {}", path.display(), err)),
+    };
+    let added = missing_entries(&content, &present);
+    let applied = !dry_run.unwrap_or(false) && !added.is_empty();
+    if applied {
+        tokio::fs::write(&path, append_entries(&content, &added))
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(GitignoreUpdate {
+        path: path.to_string_lossy().to_string(),
+        added,
+        applied,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_missing_entries_once() {
+        let content = "target/\nnode_modules";
+        let missing = missing_entries(content, APP_MANAGED_ENTRIES);
+        assert_eq!(missing, vec![".flowhub/".to_string()]);
+
+        let updated = append_entries(content, &missing);
+        assert_eq!(updated, "target/\nnode_modules\n\n# FlowHub\n.flowhub/\n");
+        assert!(missing_entries(&updated, APP_MANAGED_ENTRIES).is_empty());
+
+        assert!(missing_entries("/.flowhub\n", APP_MANAGED_ENTRIES).is_empty());
+        assert_eq!(
+            missing_entries("# .flowhub/\n", APP_MANAGED_ENTRIES).len(),
+            1
+        );
+        assert_eq!(append_entries("", &missing), "# FlowHub\n.flowhub/\n");
+    }
+}
@@ -0,0 +1,375 @@
+//! 会话交接：把当前会话的摘要与固定文件注入另一个 Agent / 模型的新会话，
+//! 并在会话存储中互相关联（便于“先用便宜模型，再升级到更强模型”的工作流）。
+//! 写入存储后推送 `sessions-stored`，由前端合并进内存状态。
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::State;
+use tokio::time::{timeout, Duration};
+
+use crate::export::{load_session_transcript, SessionTranscript};
+use crate::models::ListenerCommand;
+use crate::session_graph::{append_session_link, SessionLink};
+use crate::state::AppState;
+use crate::storage::{
+    emit_sessions_stored, read_snapshot_from_path, storage_path, write_snapshot_to_path,
+    StoredMessage, StoredSession, StoredSessionsUpdate,
+};
+
+const MAX_SUMMARY_USER_TURNS: usize = 10;
+const MAX_USER_TURN_CHARS: usize = 300;
+const MAX_FINAL_REPLY_CHARS: usize = 2000;
+const MAX_PINNED_FILE_BYTES: u64 = 64 * 1024;
+const MAX_PINNED_TOTAL_BYTES: u64 = 256 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandoffResult {
+    pub source_session: StoredSession,
+    pub target_session: StoredSession,
+    pub acp_session_id: String,
+    pub prompt: String,
+}
+
+struct PinnedFile {
+    path: String,
+    content: String,
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    let trimmed = text.trim();
+    if trimmed.chars().count() <= max_chars {
+        return trimmed.to_string();
+    }
+    format!("{}…", trimmed.chars().take(max_chars).collect::<String>())
+}
+
+/// 抽取式摘要：用户诉求列表 + 最后一条助手回复
+fn summarize_transcript(transcript: &SessionTranscript) -> String {
+    let user_turns: Vec<&StoredMessage> = transcript
+        .messages
+        .iter()
+        .filter(|message| message.role == "user" && !message.content.trim().is_empty())
+        .collect();
+    let skipped = user_turns.len().saturating_sub(MAX_SUMMARY_USER_TURNS);
+
+    let mut sections = vec![format!("会话标题：{}", transcript.session.title)];
+    if !user_turns.is_empty() {
+        let mut lines = vec!["用户诉求：".to_string()];
+        if skipped > 0 {
+            lines.push(format!("（更早的 {} 条已省略）", skipped));
+        }
+        for (index, message) in user_turns.iter().skip(skipped).enumerate() {
+            lines.push(format!(
+                "{}. {}",
+                index + 1,
+                truncate_chars(&message.content, MAX_USER_TURN_CHARS).replace('\n', " ")
+            ));
+        }
+        sections.push(lines.join("\n"));
+    }
+
+    if let Some(reply) = transcript
+        .messages
+        .iter()
+        .rev()
+        .find(|message| message.role == "assistant" && !message.content.trim().is_empty())
+    {
+        sections.push(format!(
+            "最近一次助手回复：\n{}",
+            truncate_chars(&reply.content, MAX_FINAL_REPLY_CHARS)
+        ));
+    }
+
+    sections.join("\n\n")
+}
+
+fn build_handoff_prompt(summary: &str, pinned_files: &[PinnedFile]) -> String {
+    let mut prompt = format!(
+        "以下是从另一个会话交接过来的上下文，请先理解再继续完成任务。\n\n{}",
+        summary
+    );
+    for file in pinned_files {
+        prompt.push_str(&format!(
+            "\n\n固定文件 `{}`：\n```\n{}\n```",
+            file.path, file.content
+        ));
+    }
+    prompt.push_str("\n\n请基于以上上下文继续，先简要确认你对当前进展的理解。");
+    prompt
+}
+
+async fn read_pinned_files(
+    workspace_path: &str,
+    files: &[String],
+) -> Result<Vec<PinnedFile>, String> {
+    let workspace_root = tokio::fs::canonicalize(workspace_path)
+        .await
+        .map_err(|e| format!("Failed to resolve workspace path {}: {}", workspace_path, e))?;
+
+    let mut pinned = Vec::new();
+    let mut total_bytes = 0_u64;
+    for file in files {
+        let trimmed = file.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let requested = PathBuf::from(trimmed);
+        let target = if requested.is_absolute() {
+            requested
+        } else {
+            workspace_root.join(requested)
+        };
+        let canonical = tokio::fs::canonicalize(&target)
+            .await
+            .map_err(|e| format!("Failed to resolve pinned file {}: {}", trimmed, e))?;
+        if !canonical.starts_with(&workspace_root) {
+            return Err(format!("Pinned file {} is outside workspace", trimmed));
+        }
+
+        let size = tokio::fs::metadata(&canonical)
+            .await
+            .map_err(|e| format!("Failed to stat pinned file {}: {}", trimmed, e))?
+            .len();
+        if size > MAX_PINNED_FILE_BYTES {
+            return Err(format!(
+                "Pinned file {} is too large (>{} bytes)",
+                trimmed, MAX_PINNED_FILE_BYTES
+            ));
+        }
+        total_bytes += size;
+        if total_bytes > MAX_PINNED_TOTAL_BYTES {
+            return Err(format!(
+                "Pinned files exceed {} bytes in total",
+                MAX_PINNED_TOTAL_BYTES
+            ));
+        }
+
+        let content = tokio::fs::read_to_string(&canonical)
+            .await
+            .map_err(|e| format!("Failed to read pinned file {}: {}", trimmed, e))?;
+        pinned.push(PinnedFile {
+            path: display_relative(&canonical, &workspace_root),
+            content,
+        });
+    }
+    Ok(pinned)
+}
+
+fn display_relative(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+async fn switch_target_model(state: &AppState, agent_id: &str, model: &str) -> Result<(), String> {
+    let (_, sender) = state.agent_manager.sender_of(agent_id).await;
+    let sender = sender.ok_or_else(|| "Message sender not available".to_string())?;
+    let (tx, rx) = tokio::sync::oneshot::channel::<Result<String, String>>();
+    sender
+        .send(ListenerCommand::SetModel {
+            model: model.to_string(),
+            response: tx,
+        })
+        .map_err(|e| format!("Failed to queue model switch: {}", e))?;
+
+    match timeout(Duration::from_secs(20), rx).await {
+        Ok(Ok(Ok(_))) => Ok(()),
+        Ok(Ok(Err(err))) => Err(err),
+        Ok(Err(_)) => Err("Model switch response channel closed".to_string()),
+        Err(_) => Err("Model switch timeout after 20 seconds".to_string()),
+    }
+}
+
+/// 将会话交接给另一个已连接的 Agent（可选切换模型），在其上新建 ACP 会话并发送交接上下文
+#[tauri::command]
+pub async fn handoff_session(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    source_session_id: String,
+    target_agent_id: String,
+    pinned_files: Option<Vec<String>>,
+    model: Option<String>,
+) -> Result<HandoffResult, String> {
+    let transcript = load_session_transcript(&app_handle, &state, &source_session_id).await?;
+    let workspace_path = state
+        .agent_manager
+        .workspace_path_of(&target_agent_id)
+        .await
+        .ok_or_else(|| format!("Agent {} not found", target_agent_id))?;
+    let (_, sender) = state.agent_manager.sender_of(&target_agent_id).await;
+    let sender = sender.ok_or_else(|| "Message sender not available".to_string())?;
+
+    let pinned = read_pinned_files(&workspace_path, &pinned_files.unwrap_or_default()).await?;
+    if let Some(model) = model
+        .as_deref()
+        .map(str::trim)
+        .filter(|model| !model.is_empty())
+    {
+        switch_target_model(&state, &target_agent_id, model).await?;
+    }
+
+    let prompt = build_handoff_prompt(&summarize_transcript(&transcript), &pinned);
+    let now = chrono::Utc::now().to_rfc3339();
+    let acp_session_id = format!("session-{}", uuid::Uuid::new_v4());
+    let target_session = StoredSession {
+        id: uuid::Uuid::new_v4().to_string(),
+        agent_id: target_agent_id.clone(),
+        title: format!("交接：{}", transcript.session.title),
+        created_at: now.clone(),
+        updated_at: now.clone(),
+        acp_session_id: Some(acp_session_id.clone()),
+        source: Some("handoff".to_string()),
+        message_count_hint: Some(1),
+        handoff_from: Some(transcript.session.id.clone()),
+        handoff_to: Vec::new(),
+        title_generated: true,
+    };
+
+    let prompt_message = StoredMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        role: "user".to_string(),
+        content: prompt.clone(),
+        timestamp: now,
+        agent_id: Some(target_agent_id.clone()),
+        interrupted: false,
+        attachments: Vec::new(),
+        model_switch: None,
+    };
+
+    let source_session = {
+        let _guard = state.storage_lock.lock().await;
+        let path = storage_path(&app_handle)?;
+        let mut snapshot = read_snapshot_from_path(&path).await?;
+
+        let source = snapshot
+            .sessions_by_agent
+            .values_mut()
+            .flatten()
+            .find(|session| session.id == source_session_id)
+            .ok_or_else(|| format!("Session {} not found", source_session_id))?;
+        source.handoff_to.push(target_session.id.clone());
+        let source_session = source.clone();
+
+        snapshot
+            .sessions_by_agent
+            .entry(target_agent_id.clone())
+            .or_default()
+            .insert(0, target_session.clone());
+        snapshot
+            .messages_by_session
+            .insert(target_session.id.clone(), vec![prompt_message.clone()]);
+        write_snapshot_to_path(&path, &snapshot).await?;
+        append_session_link(
+            &app_handle,
+            SessionLink {
+                parent_session_id: source_session.id.clone(),
+                child_session_id: target_session.id.clone(),
+                fork_point: transcript.messages.len(),
+                workspace_path: workspace_path.clone(),
+                created_at: target_session.created_at.clone(),
+            },
+        )
+        .await?;
+        source_session
+    };
+    emit_sessions_stored(
+        &app_handle,
+        &StoredSessionsUpdate {
+            sessions: vec![source_session.clone(), target_session.clone()],
+            messages_by_session: [(target_session.id.clone(), vec![prompt_message])].into(),
+        },
+    );
+
+    // 指定一个不存在的 sessionId 时，监听任务会回退为以该 ID 新建 ACP 会话
+    sender
+        .send(ListenerCommand::UserPrompt {
+            content: prompt.clone(),
+            session_id: Some(acp_session_id.clone()),
+            generation: None,
+            resources: Vec::new(),
+        })
+        .map_err(|e| format!("Failed to queue handoff prompt: {}", e))?;
+
+    Ok(HandoffResult {
+        source_session,
+        target_session,
+        acp_session_id,
+        prompt,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> StoredMessage {
+        StoredMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: String::new(),
+            agent_id: None,
+            interrupted: false,
+            attachments: Vec::new(),
+            model_switch: None,
+        }
+    }
+
+    #[test]
+    fn handoff_prompt_contains_summary_and_pinned_files() {
+        let transcript = SessionTranscript {
+            session: StoredSession {
+                id: "s1".to_string(),
+                title: "Refactor parser".to_string(),
+                ..Default::default()
+            },
+            messages: vec![
+                message("user", "Split the parser module"),
+                message("assistant", "First draft"),
+                message("user", "Now add tests"),
+                message("assistant", "Added tests for the tokenizer"),
+            ],
+        };
+
+        let summary = summarize_transcript(&transcript);
+        assert!(summary.contains("Refactor parser"));
+        assert!(summary.contains("1. Split the parser module"));
+        assert!(summary.contains("2. Now add tests"));
+        assert!(summary.contains("Added tests for the tokenizer"));
+        assert!(!summary.contains("First draft"));
+
+        let prompt = build_handoff_prompt(
+            &summary,
+            &[PinnedFile {
+                path: "src/parser.rs".to_string(),
+                content: "fn parse() {}".to_string(),
+            }],
+        );
+        assert!(prompt.contains("`src/parser.rs`"));
+        assert!(prompt.contains("fn parse() {}"));
+    }
+
+    #[tokio::test]
+    async fn pinned_files_must_stay_in_workspace() {
+        let base = std::env::temp_dir().join(format!("iflow-handoff-{}", uuid::Uuid::new_v4()));
+        let workspace = base.join("ws");
+        std::fs::create_dir_all(&workspace).expect("create workspace");
+        std::fs::write(workspace.join("notes.md"), "pinned").expect("write pinned");
+        std::fs::write(base.join("secret.txt"), "secret").expect("write outside");
+
+        let workspace_path = workspace.to_string_lossy().to_string();
+        let pinned = read_pinned_files(&workspace_path, &["notes.md".to_string()])
+            .await
+            .expect("read pinned");
+        assert_eq!(pinned[0].path, "notes.md");
+        assert!(
+            read_pinned_files(&workspace_path, &["../secret.txt".to_string()])
+                .await
+                .is_err()
+        );
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
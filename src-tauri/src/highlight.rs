@@ -0,0 +1,181 @@
+//! 后端语法高亮（syntect）：返回与主题无关的样式片段（颜色/粗斜体），
+//! 供前端渲染超大代码块，也供导出复用，保证各处高亮一致。
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, FontStyle, Style, Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+pub(crate) const DEFAULT_HIGHLIGHT_THEME: &str = "InspiredGitHub";
+const MAX_HIGHLIGHT_BYTES: usize = 2 * 1024 * 1024;
+
+static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+static THEME_SET: Lazy<ThemeSet> = Lazy::new(ThemeSet::load_defaults);
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HighlightSpan {
+    pub text: String,
+    /// `#rrggbb`
+    pub color: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub bold: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub italic: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub underline: bool,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HighlightedCode {
+    /// 实际匹配到的语法名称，未识别时为 Plain Text
+    pub language: String,
+    pub theme: String,
+    pub foreground: Option<String>,
+    pub background: Option<String>,
+    /// 每行的样式片段（不含换行符）
+    pub lines: Vec<Vec<HighlightSpan>>,
+}
+
+fn hex_color(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+fn find_syntax(lang: Option<&str>) -> &'static SyntaxReference {
+    lang.map(str::trim)
+        .filter(|lang| !lang.is_empty())
+        .and_then(|lang| {
+            SYNTAX_SET
+                .find_syntax_by_token(lang)
+                .or_else(|| SYNTAX_SET.find_syntax_by_name(lang))
+                .or_else(|| SYNTAX_SET.find_syntax_by_token(&lang.to_lowercase()))
+        })
+        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text())
+}
+
+fn find_theme(theme: Option<&str>) -> Result<(&'static str, &'static Theme), String> {
+    let name = theme
+        .map(str::trim)
+        .filter(|theme| !theme.is_empty())
+        .unwrap_or(DEFAULT_HIGHLIGHT_THEME);
+    THEME_SET
+        .themes
+        .get_key_value(name)
+        .map(|(name, theme)| (name.as_str(), theme))
+        .ok_or_else(|| {
+            format!(
+                "Unknown highlight theme {}, available: {}",
+                name,
+                highlight_theme_names().join(", ")
+            )
+        })
+}
+
+fn to_span(style: Style, text: &str) -> HighlightSpan {
+    HighlightSpan {
+        text: text.to_string(),
+        color: hex_color(style.foreground),
+        bold: style.font_style.contains(FontStyle::BOLD),
+        italic: style.font_style.contains(FontStyle::ITALIC),
+        underline: style.font_style.contains(FontStyle::UNDERLINE),
+    }
+}
+
+pub(crate) fn highlight_theme_names() -> Vec<String> {
+    let mut names: Vec<String> = THEME_SET.themes.keys().cloned().collect();
+    names.sort();
+    names
+}
+
+/// 同步高亮；调用方负责放到阻塞线程池
+pub(crate) fn highlight_to_spans(
+    code: &str,
+    lang: Option<&str>,
+    theme: Option<&str>,
+) -> Result<HighlightedCode, String> {
+    if code.len() > MAX_HIGHLIGHT_BYTES {
+        return Err(format!(
+            "Code is too large to highlight (>{} bytes)",
+            MAX_HIGHLIGHT_BYTES
+        ));
+    }
+    let syntax = find_syntax(lang);
+    let (theme_name, theme) = find_theme(theme)?;
+    let mut highlighter = HighlightLines::new(syntax, theme);
+
+    let mut lines = Vec::new();
+    for line in LinesWithEndings::from(code) {
+        let ranges = highlighter
+            .highlight_line(line, &SYNTAX_SET)
+            .map_err(|e| format!("Failed to highlight code: {}", e))?;
+        let spans = ranges
+            .into_iter()
+            .filter_map(|(style, text)| {
+                let text = text.trim_end_matches(['\n', '\r']);
+                (!text.is_empty()).then(|| to_span(style, text))
+            })
+            .collect();
+        lines.push(spans);
+    }
+
+    Ok(HighlightedCode {
+        language: syntax.name.clone(),
+        theme: theme_name.to_string(),
+        foreground: theme.settings.foreground.map(hex_color),
+        background: theme.settings.background.map(hex_color),
+        lines,
+    })
+}
+
+#[tauri::command]
+pub async fn highlight_code(
+    code: String,
+    lang: Option<String>,
+    theme: Option<String>,
+) -> Result<HighlightedCode, String> {
+    tokio::task::spawn_blocking(move || {
+        highlight_to_spans(&code, lang.as_deref(), theme.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Highlight task failed: {}", e))?
+}
+
+#[tauri::command]
+pub async fn list_highlight_themes() -> Result<Vec<String>, String> {
+    Ok(highlight_theme_names())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_rust_with_distinct_colors() {
+        let result =
+            highlight_to_spans("fn main() {\n    // hi\n}\n", Some("rs"), None).expect("highlight");
+        assert_eq!(result.language, "Rust");
+        assert_eq!(result.theme, DEFAULT_HIGHLIGHT_THEME);
+        assert_eq!(result.lines.len(), 3);
+
+        let first_line: String = result.lines[0]
+            .iter()
+            .map(|span| span.text.as_str())
+            .collect();
+        assert_eq!(first_line, "fn main() {");
+        let keyword = &result.lines[0][0];
+        let comment = result.lines[1]
+            .iter()
+            .find(|span| span.text.contains("hi"))
+            .expect("comment span");
+        assert_ne!(keyword.color, comment.color);
+    }
+
+    #[test]
+    fn unknown_language_falls_back_and_unknown_theme_errors() {
+        let result = highlight_to_spans("plain", Some("no-such-lang"), None).expect("highlight");
+        assert_eq!(result.language, "Plain Text");
+        assert!(highlight_to_spans("x", None, Some("no-such-theme")).is_err());
+    }
+}
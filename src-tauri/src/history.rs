@@ -1,13 +1,59 @@
 //! iFlow 历史会话文件读取与解析
 
-use std::collections::HashSet;
-use std::env;
+use std::collections::{HashMap, HashSet};
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
 
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use futures::stream::{self, StreamExt};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use tauri::{Manager, State};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::history_trash::{move_sessions_to_trash, HistoryClearManifest};
+use crate::jobs::{spawn_job, JobContext, JobHandle};
+use crate::runtime_env::home_dir;
+use crate::state::AppState;
+use crate::wsl_path::wsl_path_aliases;
+
+// 读取项目目录首条 cwd 时最多扫描的行数
+const CWD_SCAN_MAX_LINES: usize = 64;
+
+// 最近写入过的会话文件视为仍在被 Agent 使用，清空时跳过
+const ACTIVE_WRITE_GRACE: Duration = Duration::from_secs(10);
+
+// 全文搜索最多返回的会话数，以及每个会话保留的片段数与片段上下文长度
+const MAX_SEARCH_RESULTS: usize = 50;
+const MAX_SEARCH_SNIPPETS: usize = 5;
+const SEARCH_SNIPPET_CONTEXT_CHARS: usize = 60;
+
+// 时间线中单条工具输出保留的最大字符数
+const MAX_TOOL_OUTPUT_CHARS: usize = 8000;
+
+// 项目目录 -> 首条记录 cwd 的缓存（同一目录的 cwd 不会变化）
+static PROJECT_DIR_CWD_CACHE: Lazy<Mutex<HashMap<PathBuf, String>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// 列出会话时同时解析的摘要文件数，避免数百个会话文件同时打开
+const HISTORY_SUMMARY_PARSE_CONCURRENCY: usize = 16;
+
+// 会话文件 -> 摘要的缓存，修改时间或大小变化即失效；超过上限时整体清空
+const MAX_HISTORY_SUMMARY_CACHE_ENTRIES: usize = 4096;
+static HISTORY_SUMMARY_CACHE: Lazy<Mutex<HashMap<PathBuf, CachedHistorySummary>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone)]
+struct CachedHistorySummary {
+    modified: Option<SystemTime>,
+    len: u64,
+    session: IflowHistorySession,
+    cwds: Vec<String>,
+    has_assistant_messages: bool,
+}
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -19,6 +65,107 @@ pub struct IflowHistorySession {
     pub message_count: usize,
 }
 
+/// `list_iflow_history_sessions` 的可选过滤条件；时间为 RFC 3339，与会话最后一条消息的时间比较
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IflowHistoryFilter {
+    #[serde(default)]
+    pub updated_after: Option<String>,
+    #[serde(default)]
+    pub updated_before: Option<String>,
+    /// true 只保留有 Agent 回复的会话，false 只保留没有回复的会话
+    #[serde(default)]
+    pub has_assistant_messages: Option<bool>,
+    #[serde(default)]
+    pub min_message_count: Option<usize>,
+}
+
+/// 校验后的过滤条件，解析会话摘要时逐个判断
+#[derive(Debug, Default, Clone)]
+struct HistorySessionFilter {
+    updated_after: Option<DateTime<Utc>>,
+    updated_before: Option<DateTime<Utc>>,
+    has_assistant_messages: Option<bool>,
+    min_message_count: usize,
+}
+
+fn parse_filter_time(value: Option<&str>, field: &str) -> Result<Option<DateTime<Utc>>, String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|time| time.with_timezone(&Utc))
+                .map_err(|e| format!("Invalid {} {}: {}", field, value, e))
+        })
+        .transpose()
+}
+
+impl IflowHistoryFilter {
+    fn validated(&self) -> Result<HistorySessionFilter, String> {
+        let updated_after = parse_filter_time(self.updated_after.as_deref(), "updatedAfter")?;
+        let updated_before = parse_filter_time(self.updated_before.as_deref(), "updatedBefore")?;
+        if let (Some(after), Some(before)) = (updated_after, updated_before) {
+            if after >= before {
+                return Err("updatedAfter must be earlier than updatedBefore".to_string());
+            }
+        }
+        Ok(HistorySessionFilter {
+            updated_after,
+            updated_before,
+            has_assistant_messages: self.has_assistant_messages,
+            min_message_count: self.min_message_count.unwrap_or(0),
+        })
+    }
+}
+
+impl HistorySessionFilter {
+    /// 时间区间为 [updatedAfter, updatedBefore)；设置了时间条件时，时间无法解析的会话不保留
+    fn matches(&self, summary: &CachedHistorySummary) -> bool {
+        if summary.session.message_count < self.min_message_count {
+            return false;
+        }
+        if self
+            .has_assistant_messages
+            .is_some_and(|expected| expected != summary.has_assistant_messages)
+        {
+            return false;
+        }
+        if self.updated_after.is_none() && self.updated_before.is_none() {
+            return true;
+        }
+        let Ok(updated_at) = DateTime::parse_from_rfc3339(&summary.session.updated_at) else {
+            return false;
+        };
+        let updated_at = updated_at.with_timezone(&Utc);
+        if self.updated_after.is_some_and(|after| updated_at < after) {
+            return false;
+        }
+        !self
+            .updated_before
+            .is_some_and(|before| updated_at >= before)
+    }
+}
+
+/// 清空预览中将被移除的会话文件
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingHistoryFile {
+    pub session_id: String,
+    pub path: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryClearPreview {
+    pub dry_run: bool,
+    pub workspace_path: String,
+    pub files: Vec<PendingHistoryFile>,
+    pub total_bytes: u64,
+    pub skipped: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IflowHistoryMessage {
@@ -28,7 +175,42 @@ pub struct IflowHistoryMessage {
     pub timestamp: String,
 }
 
-fn normalize_workspace_path(workspace_path: &str) -> String {
+/// 执行计划中的一项（来自 todo 类工具的 `todos` 参数）
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct IflowHistoryPlanItem {
+    pub content: String,
+    pub status: String,
+}
+
+/// 历史会话时间线中的一项：文本消息、工具调用（附带结果）或执行计划
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum IflowHistoryEntry {
+    Text {
+        id: String,
+        role: String,
+        content: String,
+        timestamp: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    ToolCall {
+        id: String,
+        name: String,
+        input: Value,
+        /// 对应 tool_result 的文本；会话中断时可能没有结果
+        output: Option<String>,
+        is_error: bool,
+        timestamp: String,
+    },
+    Plan {
+        id: String,
+        items: Vec<IflowHistoryPlanItem>,
+        timestamp: String,
+    },
+}
+
+pub(crate) fn normalize_workspace_path(workspace_path: &str) -> String {
     let mut normalized = workspace_path.trim().replace('\\', "/");
     while normalized.len() > 1 && normalized.ends_with('/') {
         normalized.pop();
@@ -47,43 +229,76 @@ fn is_same_or_ancestor(base: &str, path: &str) -> bool {
     path.starts_with(&prefix)
 }
 
-fn workspace_path_matches(expected_workspace_path: &str, record_cwd: &str) -> bool {
-    let expected = normalize_workspace_path(expected_workspace_path);
-    let actual = normalize_workspace_path(record_cwd);
-    is_same_or_ancestor(&expected, &actual) || is_same_or_ancestor(&actual, &expected)
-}
-
-fn workspace_to_iflow_project_key(workspace_path: &str) -> String {
-    let normalized = normalize_workspace_path(workspace_path);
-    let mut key = normalized.replace('/', "-").replace(':', "-");
-    if !key.starts_with('-') {
-        key = format!("-{}", key);
+/// 工作区路径的比较形式：词法归一化路径 + 解析符号链接后的真实路径。
+/// 两者可能不同（如 macOS 的 /tmp -> /private/tmp），比较时任一形式命中即视为同一路径。
+/// 解析符号链接需访问文件系统，调用方应按命令只计算一次并向下传递
+pub(crate) async fn workspace_path_variants(path: &str) -> Vec<String> {
+    let lexical = normalize_workspace_path(path);
+    let mut variants = vec![lexical.clone()];
+    if let Ok(canonical) = tokio::fs::canonicalize(&lexical).await {
+        let raw = canonical.to_string_lossy();
+        // Windows canonicalize 返回带 \\?\ 前缀的扩展路径
+        let canonical = normalize_workspace_path(raw.strip_prefix(r"\\?\").unwrap_or(&*raw));
+        if !variants.contains(&canonical) {
+            variants.push(canonical);
+        }
+    }
+    // WSL 工作区：`\\wsl$\<发行版>\...` 与 Agent 记录的 Linux cwd、`/mnt/c/...` 与 `C:\...` 互为别名
+    for alias in wsl_path_aliases(&lexical) {
+        let alias = normalize_workspace_path(&alias);
+        if !variants.contains(&alias) {
+            variants.push(alias);
+        }
     }
-    key
+    variants
 }
 
-fn iflow_projects_root() -> Result<PathBuf, String> {
-    let home_dir = env::var("HOME")
-        .or_else(|_| env::var("USERPROFILE"))
-        .map_err(|e| format!("HOME/USERPROFILE is not set: {}", e))?;
-    Ok(PathBuf::from(home_dir).join(".iflow").join("projects"))
+/// `workspace_variants` 为 `workspace_path_variants` 预先算好的工作区路径形式
+async fn workspace_path_matches(workspace_variants: &[String], record_cwd: &str) -> bool {
+    let actual_variants = workspace_path_variants(record_cwd).await;
+    workspace_variants.iter().any(|expected| {
+        actual_variants.iter().any(|actual| {
+            is_same_or_ancestor(expected, actual) || is_same_or_ancestor(actual, expected)
+        })
+    })
 }
 
-fn iflow_project_dirs_for_workspace(
-    workspace_path: &str,
-    normalized_workspace_path: &str,
-) -> Result<Vec<PathBuf>, String> {
-    let mut candidates = Vec::new();
-    let mut seen = HashSet::new();
+/// 工作区对应的 iFlow 项目目录名候选：分隔符与冒号替换为 `-`，Unix 路径以 `-` 开头。
+/// 盘符路径（`C:\repo` -> `C--repo`）不加前缀，同时兼容带前缀的旧格式与小写盘符
+pub(crate) fn workspace_to_iflow_project_keys(workspace_path: &str) -> Vec<String> {
+    let normalized = normalize_workspace_path(workspace_path);
+    let to_key = |path: &str| path.replace(['/', ':'], "-");
+    let bytes = normalized.as_bytes();
+    let has_drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    if !has_drive {
+        let key = to_key(&normalized);
+        return vec![if key.starts_with('-') {
+            key
+        } else {
+            format!("-{}", key)
+        }];
+    }
 
-    for path in [workspace_path, normalized_workspace_path] {
-        let key = workspace_to_iflow_project_key(path);
-        if seen.insert(key.clone()) {
-            candidates.push(iflow_projects_root()?.join(key));
+    let mut keys = Vec::new();
+    let upper = format!(
+        "{}{}",
+        normalized[..1].to_ascii_uppercase(),
+        &normalized[1..]
+    );
+    for path in [upper, normalized] {
+        let key = to_key(&path);
+        for candidate in [key.clone(), format!("-{}", key)] {
+            if !keys.contains(&candidate) {
+                keys.push(candidate);
+            }
         }
     }
+    keys
+}
 
-    Ok(candidates)
+pub(crate) fn iflow_projects_root() -> Result<PathBuf, String> {
+    let home_dir = home_dir().ok_or_else(|| "Cannot resolve home directory".to_string())?;
+    Ok(home_dir.join(".iflow").join("projects"))
 }
 
 async fn list_all_iflow_project_dirs() -> Result<Vec<PathBuf>, String> {
@@ -117,6 +332,114 @@ async fn list_all_iflow_project_dirs() -> Result<Vec<PathBuf>, String> {
     Ok(dirs)
 }
 
+async fn read_first_record_cwd(file_path: &Path) -> Option<String> {
+    let file = tokio::fs::File::open(file_path).await.ok()?;
+    let mut lines = BufReader::new(file).lines();
+    let mut scanned = 0_usize;
+    while let Ok(Some(line)) = lines.next_line().await {
+        scanned += 1;
+        if scanned > CWD_SCAN_MAX_LINES {
+            break;
+        }
+        let Ok(record) = serde_json::from_str::<Value>(line.trim()) else {
+            continue;
+        };
+        if let Some(cwd) = extract_history_record_cwd(&record) {
+            return Some(cwd);
+        }
+    }
+    None
+}
+
+/// 读取项目目录内任一会话文件的首条 cwd，作为该目录对应的工作区
+async fn read_project_dir_cwd(project_dir: &Path) -> Option<String> {
+    if let Ok(cache) = PROJECT_DIR_CWD_CACHE.lock() {
+        if let Some(cwd) = cache.get(project_dir) {
+            return Some(cwd.clone());
+        }
+    }
+
+    let mut reader = tokio::fs::read_dir(project_dir).await.ok()?;
+    let mut session_files = Vec::new();
+    while let Ok(Some(entry)) = reader.next_entry().await {
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if file_name.starts_with("session-") && file_name.ends_with(".jsonl") {
+            session_files.push(entry.path());
+        }
+    }
+    session_files.sort();
+
+    for file_path in session_files {
+        if let Some(cwd) = read_first_record_cwd(&file_path).await {
+            if let Ok(mut cache) = PROJECT_DIR_CWD_CACHE.lock() {
+                cache.insert(project_dir.to_path_buf(), cwd.clone());
+            }
+            return Some(cwd);
+        }
+    }
+    None
+}
+
+/// 扫描全部项目目录，建立 cwd -> 项目目录 的反向索引；未能读取 cwd 的目录单独返回
+async fn build_project_cwd_index() -> Result<(HashMap<String, Vec<PathBuf>>, Vec<PathBuf>), String>
+{
+    let mut index: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    let mut unresolved = Vec::new();
+    for project_dir in list_all_iflow_project_dirs().await? {
+        match read_project_dir_cwd(&project_dir).await {
+            Some(cwd) => {
+                for variant in workspace_path_variants(&cwd).await {
+                    index.entry(variant).or_default().push(project_dir.clone());
+                }
+            }
+            None => unresolved.push(project_dir),
+        }
+    }
+    Ok((index, unresolved))
+}
+
+/// 发现工作区对应的 iFlow 项目目录：优先按记录中的 cwd 精确匹配，
+/// 仅对读不到 cwd 的目录（如尚无会话文件）回退到目录名推导
+pub(crate) async fn iflow_project_dirs_for_workspace(
+    workspace_path: &str,
+    normalized_workspace_path: &str,
+) -> Result<Vec<PathBuf>, String> {
+    let mut workspace_variants = workspace_path_variants(workspace_path).await;
+    for variant in workspace_path_variants(normalized_workspace_path).await {
+        if !workspace_variants.contains(&variant) {
+            workspace_variants.push(variant);
+        }
+    }
+    let (index, unresolved) = build_project_cwd_index().await?;
+
+    let mut candidates = Vec::new();
+    let mut seen = HashSet::new();
+    for variant in &workspace_variants {
+        for project_dir in index.get(variant).into_iter().flatten() {
+            if seen.insert(project_dir.clone()) {
+                candidates.push(project_dir.clone());
+            }
+        }
+    }
+
+    let derived_keys: HashSet<String> = workspace_variants
+        .iter()
+        .flat_map(|path| workspace_to_iflow_project_keys(path))
+        .collect();
+    for project_dir in unresolved {
+        let matches_key = project_dir
+            .file_name()
+            .map(|name| derived_keys.contains(name.to_string_lossy().as_ref()))
+            .unwrap_or(false);
+        if matches_key && seen.insert(project_dir.clone()) {
+            candidates.push(project_dir);
+        }
+    }
+
+    Ok(candidates)
+}
+
 fn to_rfc3339_or_now(system_time: Option<std::time::SystemTime>) -> String {
     system_time
         .map(DateTime::<Utc>::from)
@@ -124,7 +447,7 @@ fn to_rfc3339_or_now(system_time: Option<std::time::SystemTime>) -> String {
         .unwrap_or_else(|| Utc::now().to_rfc3339())
 }
 
-fn compact_title(raw: &str) -> String {
+pub(crate) fn compact_title(raw: &str) -> String {
     let normalized = raw.replace('\n', " ").replace('\r', " ").trim().to_string();
     if normalized.is_empty() {
         return "iFlow 会话".to_string();
@@ -228,7 +551,7 @@ fn has_structured_tool_entries(value: &Value) -> bool {
     })
 }
 
-fn extract_history_message_content(record: &Value, record_type: &str) -> Option<String> {
+pub(crate) fn extract_history_message_content(record: &Value, record_type: &str) -> Option<String> {
     let content = record.get("message").and_then(|message| message.get("content"))?;
 
     if has_structured_tool_entries(content) {
@@ -250,7 +573,7 @@ fn extract_history_message_content(record: &Value, record_type: &str) -> Option<
     Some(text_only)
 }
 
-fn extract_history_timestamp(record: &Value) -> Option<String> {
+pub(crate) fn extract_history_timestamp(record: &Value) -> Option<String> {
     record
         .get("timestamp")
         .and_then(Value::as_str)
@@ -265,23 +588,18 @@ fn extract_history_record_cwd(record: &Value) -> Option<String> {
         .map(normalize_workspace_path)
 }
 
-async fn parse_iflow_history_summary(
-    file_path: &Path,
+/// 解析会话文件的摘要、出现过的 cwd 以及是否有 Agent 回复（与工作区无关，便于缓存）
+fn summarize_history_records(
+    raw: &str,
     session_id: &str,
-    expected_workspace_path: &str,
-) -> Result<Option<IflowHistorySession>, String> {
-    let raw = tokio::fs::read_to_string(file_path)
-        .await
-        .map_err(|e| format!("Failed to read {}: {}", file_path.display(), e))?;
-    let metadata = tokio::fs::metadata(file_path).await.ok();
-    let fallback_ts = to_rfc3339_or_now(metadata.and_then(|item| item.modified().ok()));
-
+    fallback_ts: String,
+) -> (IflowHistorySession, Vec<String>, bool) {
     let mut created_at: Option<String> = None;
     let mut updated_at: Option<String> = None;
     let mut title: Option<String> = None;
     let mut message_count = 0_usize;
-    let mut has_cwd = false;
-    let mut workspace_matches = false;
+    let mut has_assistant_messages = false;
+    let mut cwds: Vec<String> = Vec::new();
 
     for line in raw.lines() {
         let trimmed = line.trim();
@@ -303,9 +621,8 @@ async fn parse_iflow_history_summary(
         }
 
         if let Some(cwd) = extract_history_record_cwd(&record) {
-            has_cwd = true;
-            if workspace_path_matches(expected_workspace_path, &cwd) {
-                workspace_matches = true;
+            if !cwds.contains(&cwd) {
+                cwds.push(cwd);
             }
         }
 
@@ -314,6 +631,7 @@ async fn parse_iflow_history_summary(
         };
 
         message_count += 1;
+        has_assistant_messages |= record_type == "assistant";
 
         if let Some(ts) = extract_history_timestamp(&record) {
             if created_at.is_none() {
@@ -327,23 +645,74 @@ async fn parse_iflow_history_summary(
         }
     }
 
-    if has_cwd && !workspace_matches {
-        return Ok(None);
-    }
-
-    Ok(Some(IflowHistorySession {
+    let session = IflowHistorySession {
         session_id: session_id.to_string(),
         title: compact_title(title.as_deref().unwrap_or(session_id)),
         created_at: created_at.unwrap_or_else(|| fallback_ts.clone()),
         updated_at: updated_at.unwrap_or(fallback_ts),
         message_count,
-    }))
+    };
+    (session, cwds, has_assistant_messages)
+}
+
+/// 读取会话摘要；文件修改时间与大小未变时直接使用缓存。不属于工作区时返回 None
+async fn parse_iflow_history_summary(
+    file_path: &Path,
+    session_id: &str,
+    workspace_variants: &[String],
+) -> Result<Option<CachedHistorySummary>, String> {
+    let metadata = tokio::fs::metadata(file_path)
+        .await
+        .map_err(|e| format!("Failed to inspect {}: {}", file_path.display(), e))?;
+    let modified = metadata.modified().ok();
+    let len = metadata.len();
+    let cached = HISTORY_SUMMARY_CACHE.lock().ok().and_then(|cache| {
+        cache
+            .get(file_path)
+            .filter(|entry| entry.modified == modified && entry.len == len)
+            .cloned()
+    });
+
+    let entry = match cached {
+        Some(entry) => entry,
+        None => {
+            let raw = tokio::fs::read_to_string(file_path)
+                .await
+                .map_err(|e| format!("Failed to read {}: {}", file_path.display(), e))?;
+            let (session, cwds, has_assistant_messages) =
+                summarize_history_records(&raw, session_id, to_rfc3339_or_now(modified));
+            let entry = CachedHistorySummary {
+                modified,
+                len,
+                session,
+                cwds,
+                has_assistant_messages,
+            };
+            if let Ok(mut cache) = HISTORY_SUMMARY_CACHE.lock() {
+                if cache.len() >= MAX_HISTORY_SUMMARY_CACHE_ENTRIES {
+                    cache.clear();
+                }
+                cache.insert(file_path.to_path_buf(), entry.clone());
+            }
+            entry
+        }
+    };
+
+    if entry.cwds.is_empty() {
+        return Ok(Some(entry));
+    }
+    for cwd in &entry.cwds {
+        if workspace_path_matches(workspace_variants, cwd).await {
+            return Ok(Some(entry));
+        }
+    }
+    Ok(None)
 }
 
 async fn parse_iflow_history_messages(
     file_path: &Path,
     session_id: &str,
-    expected_workspace_path: &str,
+    workspace_variants: &[String],
 ) -> Result<Vec<IflowHistoryMessage>, String> {
     let raw = tokio::fs::read_to_string(file_path)
         .await
@@ -352,6 +721,7 @@ async fn parse_iflow_history_messages(
     let mut messages = Vec::new();
     let mut has_cwd = false;
     let mut workspace_matches = false;
+    let mut checked_cwds = HashSet::new();
     for (index, line) in raw.lines().enumerate() {
         let trimmed = line.trim();
         if trimmed.is_empty() {
@@ -377,7 +747,11 @@ async fn parse_iflow_history_messages(
 
         if let Some(cwd) = extract_history_record_cwd(&record) {
             has_cwd = true;
-            if workspace_path_matches(expected_workspace_path, &cwd) {
+            // 同一会话文件的 cwd 基本不变，避免逐行重复解析符号链接
+            if !workspace_matches
+                && checked_cwds.insert(cwd.clone())
+                && workspace_path_matches(workspace_variants, &cwd).await
+            {
                 workspace_matches = true;
             }
         }
@@ -405,36 +779,284 @@ async fn parse_iflow_history_messages(
     if has_cwd && !workspace_matches {
         return Err(format!(
             "Session {} does not belong to workspace {}",
-            session_id, expected_workspace_path
+            session_id,
+            workspace_variants
+                .first()
+                .map(String::as_str)
+                .unwrap_or_default()
         ));
     }
 
     Ok(messages)
 }
 
-fn normalize_iflow_session_id(session_id: &str) -> Result<String, String> {
-    let normalized_session_id = session_id.trim().trim_end_matches(".jsonl").to_string();
-    if normalized_session_id.is_empty() {
-        return Err("session_id cannot be empty".to_string());
+/// 没有 cwd 的旧会话文件视为匹配
+async fn session_matches_workspace(raw: &str, workspace_variants: &[String]) -> bool {
+    let mut has_cwd = false;
+    let mut checked_cwds = HashSet::new();
+    for line in raw.lines() {
+        let Ok(record) = serde_json::from_str::<Value>(line.trim()) else {
+            continue;
+        };
+        let Some(cwd) = extract_history_record_cwd(&record) else {
+            continue;
+        };
+        has_cwd = true;
+        if checked_cwds.insert(cwd.clone())
+            && workspace_path_matches(workspace_variants, &cwd).await
+        {
+            return true;
+        }
     }
-    if !normalized_session_id.starts_with("session-") {
-        return Err("Invalid session_id format".to_string());
+    !has_cwd
+}
+
+fn plan_items(input: &Value) -> Option<Vec<IflowHistoryPlanItem>> {
+    let todos = input.get("todos")?.as_array()?;
+    Some(
+        todos
+            .iter()
+            .filter_map(|todo| {
+                let content = todo.get("content").and_then(Value::as_str)?.trim();
+                (!content.is_empty()).then(|| IflowHistoryPlanItem {
+                    content: content.to_string(),
+                    status: todo
+                        .get("status")
+                        .and_then(Value::as_str)
+                        .unwrap_or("pending")
+                        .to_string(),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn truncate_tool_output(text: String) -> String {
+    if text.chars().count() <= MAX_TOOL_OUTPUT_CHARS {
+        return text;
     }
-    Ok(normalized_session_id)
+    format!(
+        "{}...",
+        text.chars().take(MAX_TOOL_OUTPUT_CHARS).collect::<String>()
+    )
 }
 
-#[tauri::command]
-pub async fn list_iflow_history_sessions(
-    workspace_path: String,
-) -> Result<Vec<IflowHistorySession>, String> {
-    let normalized_workspace = match tokio::fs::canonicalize(&workspace_path).await {
+/// 按记录顺序构建时间线：文本、tool_use（todo 类工具视为计划）及其 tool_result
+fn extract_history_entries(raw: &str, session_id: &str) -> Vec<IflowHistoryEntry> {
+    let mut entries = Vec::new();
+    let mut tool_call_index: HashMap<String, usize> = HashMap::new();
+    for (index, line) in raw.lines().enumerate() {
+        let Ok(record) = serde_json::from_str::<Value>(line.trim()) else {
+            continue;
+        };
+        let role = match record.get("type").and_then(Value::as_str).map(str::trim) {
+            Some("user") => "user",
+            Some("assistant") => "assistant",
+            _ => continue,
+        };
+        let Some(content) = record
+            .get("message")
+            .and_then(|message| message.get("content"))
+        else {
+            continue;
+        };
+        let timestamp =
+            extract_history_timestamp(&record).unwrap_or_else(|| Utc::now().to_rfc3339());
+        let record_id = record
+            .get("uuid")
+            .and_then(Value::as_str)
+            .map(|item| item.to_string())
+            .unwrap_or_else(|| format!("{}-{}", session_id, index));
+
+        if let Some(text) =
+            extract_text_entries_only(content).filter(|text| !text.trim().is_empty())
+        {
+            entries.push(IflowHistoryEntry::Text {
+                id: record_id.clone(),
+                role: role.to_string(),
+                content: text,
+                timestamp: timestamp.clone(),
+            });
+        }
+
+        let Value::Array(items) = content else {
+            continue;
+        };
+        for (position, item) in items.iter().enumerate() {
+            let kind = item.get("type").and_then(Value::as_str).unwrap_or_default();
+            let item_id = item
+                .get("id")
+                .and_then(Value::as_str)
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| format!("{}-{}", record_id, position));
+            if kind == "tool_use" {
+                let input = item.get("input").cloned().unwrap_or(Value::Null);
+                if let Some(items) = plan_items(&input) {
+                    entries.push(IflowHistoryEntry::Plan {
+                        id: item_id,
+                        items,
+                        timestamp: timestamp.clone(),
+                    });
+                    continue;
+                }
+                tool_call_index.insert(item_id.clone(), entries.len());
+                entries.push(IflowHistoryEntry::ToolCall {
+                    id: item_id,
+                    name: item
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or("tool")
+                        .to_string(),
+                    input,
+                    output: None,
+                    is_error: false,
+                    timestamp: timestamp.clone(),
+                });
+            } else if kind == "tool_result" {
+                let Some(&entry_index) = item
+                    .get("tool_use_id")
+                    .and_then(Value::as_str)
+                    .and_then(|id| tool_call_index.get(id))
+                else {
+                    continue;
+                };
+                if let IflowHistoryEntry::ToolCall {
+                    output, is_error, ..
+                } = &mut entries[entry_index]
+                {
+                    *output = item
+                        .get("content")
+                        .and_then(extract_text_value)
+                        .map(truncate_tool_output);
+                    *is_error = item
+                        .get("is_error")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false);
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// 历史记录中的一条活动（供活跃度统计使用）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HistoryActivity {
+    pub session_id: String,
+    pub timestamp: String,
+    pub prompts: u32,
+    pub tool_calls: u32,
+}
+
+fn count_tool_use_entries(value: &Value) -> u32 {
+    let Value::Array(items) = value else {
+        return 0;
+    };
+
+    items
+        .iter()
+        .filter(|item| {
+            item.as_object()
+                .and_then(|map| map.get("type"))
+                .and_then(Value::as_str)
+                .map(|kind| kind == "tool_use")
+                .unwrap_or(false)
+        })
+        .count() as u32
+}
+
+async fn parse_iflow_history_activity(
+    file_path: &Path,
+    session_id: &str,
+    workspace_variants: &[String],
+) -> Result<Vec<HistoryActivity>, String> {
+    let raw = tokio::fs::read_to_string(file_path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", file_path.display(), e))?;
+
+    let mut activities = Vec::new();
+    let mut has_cwd = false;
+    let mut workspace_matches = false;
+    let mut checked_cwds = HashSet::new();
+    for line in raw.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let Ok(record) = serde_json::from_str::<Value>(trimmed) else {
+            continue;
+        };
+
+        let record_type = record
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .trim();
+        if record_type != "user" && record_type != "assistant" {
+            continue;
+        }
+
+        if let Some(cwd) = extract_history_record_cwd(&record) {
+            has_cwd = true;
+            if !workspace_matches
+                && checked_cwds.insert(cwd.clone())
+                && workspace_path_matches(workspace_variants, &cwd).await
+            {
+                workspace_matches = true;
+            }
+        }
+
+        // 没有时间戳的记录无法落到具体时段，直接跳过
+        let Some(timestamp) = extract_history_timestamp(&record) else {
+            continue;
+        };
+        let content = record
+            .get("message")
+            .and_then(|message| message.get("content"));
+        let (prompts, tool_calls) = if record_type == "user" {
+            // tool_result 也以 user 记录写入，只有文本输入才算一次提问
+            let is_prompt = content
+                .filter(|content| !has_structured_tool_entries(content))
+                .and_then(extract_text_entries_only)
+                .is_some();
+            (u32::from(is_prompt), 0)
+        } else {
+            (0, content.map(count_tool_use_entries).unwrap_or(0))
+        };
+        if prompts == 0 && tool_calls == 0 {
+            continue;
+        }
+
+        activities.push(HistoryActivity {
+            session_id: session_id.to_string(),
+            timestamp,
+            prompts,
+            tool_calls,
+        });
+    }
+
+    if has_cwd && !workspace_matches {
+        return Ok(Vec::new());
+    }
+
+    Ok(activities)
+}
+
+/// 读取工作区下所有 iFlow 历史会话的提问与工具调用记录
+pub(crate) async fn load_iflow_history_activity(
+    workspace_path: &str,
+) -> Result<Vec<HistoryActivity>, String> {
+    let workspace_variants = workspace_path_variants(workspace_path).await;
+    let normalized_workspace = match tokio::fs::canonicalize(workspace_path).await {
         Ok(path) => normalize_workspace_path(&path.to_string_lossy()),
-        Err(_) => normalize_workspace_path(&workspace_path),
+        Err(_) => normalize_workspace_path(workspace_path),
     };
-    let candidate_dirs = iflow_project_dirs_for_workspace(&workspace_path, &normalized_workspace)?;
+    let candidate_dirs =
+        iflow_project_dirs_for_workspace(workspace_path, &normalized_workspace).await?;
 
     let mut seen_sessions = HashSet::new();
-    let mut sessions = Vec::new();
+    let mut activities = Vec::new();
     for project_dir in candidate_dirs {
         let mut reader = match tokio::fs::read_dir(&project_dir).await {
             Ok(reader) => reader,
@@ -464,52 +1086,119 @@ pub async fn list_iflow_history_sessions(
             if !seen_sessions.insert(session_id.clone()) {
                 continue;
             }
-            if let Ok(Some(summary)) =
-                parse_iflow_history_summary(&path, &session_id, &normalized_workspace).await
+            if let Ok(items) =
+                parse_iflow_history_activity(&path, &session_id, &workspace_variants).await
             {
-                sessions.push(summary);
+                activities.extend(items);
             }
         }
     }
 
-    if sessions.is_empty() {
-        let fallback_dirs = list_all_iflow_project_dirs().await?;
-        for project_dir in fallback_dirs {
-            let mut reader = match tokio::fs::read_dir(&project_dir).await {
-                Ok(reader) => reader,
-                Err(error) if error.kind() == ErrorKind::NotFound => continue,
-                Err(error) => {
-                    return Err(format!(
-                        "Failed to open iFlow project dir {}: {}",
-                        project_dir.display(),
-                        error
-                    ))
-                }
-            };
-
-            while let Some(entry) = reader
-                .next_entry()
-                .await
-                .map_err(|e| format!("Failed to read iFlow project entry: {}", e))?
-            {
-                let path = entry.path();
-                let file_name = entry.file_name();
-                let file_name = file_name.to_string_lossy();
-                if !file_name.starts_with("session-") || !file_name.ends_with(".jsonl") {
-                    continue;
-                }
+    Ok(activities)
+}
 
-                let session_id = file_name.trim_end_matches(".jsonl").to_string();
-                if !seen_sessions.insert(session_id.clone()) {
-                    continue;
-                }
-                if let Ok(Some(summary)) =
-                    parse_iflow_history_summary(&path, &session_id, &normalized_workspace).await
-                {
-                    sessions.push(summary);
-                }
-            }
-        }
+fn normalize_iflow_session_id(session_id: &str) -> Result<String, String> {
+    let normalized_session_id = session_id.trim().trim_end_matches(".jsonl").to_string();
+    if normalized_session_id.is_empty() {
+        return Err("session_id cannot be empty".to_string());
+    }
+    if !normalized_session_id.starts_with("session-") {
+        return Err("Invalid session_id format".to_string());
+    }
+    Ok(normalized_session_id)
+}
+
+/// 列出项目目录下的会话文件（按会话 ID 去重，先出现的目录优先）
+async fn collect_iflow_session_files(
+    project_dirs: &[PathBuf],
+    seen_sessions: &mut HashSet<String>,
+) -> Result<Vec<(PathBuf, String)>, String> {
+    let mut files = Vec::new();
+    for project_dir in project_dirs {
+        let mut reader = match tokio::fs::read_dir(project_dir).await {
+            Ok(reader) => reader,
+            Err(error) if error.kind() == ErrorKind::NotFound => continue,
+            Err(error) => {
+                return Err(format!(
+                    "Failed to open iFlow project dir {}: {}",
+                    project_dir.display(),
+                    error
+                ))
+            }
+        };
+
+        while let Some(entry) = reader
+            .next_entry()
+            .await
+            .map_err(|e| format!("Failed to read iFlow project entry: {}", e))?
+        {
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            if !file_name.starts_with("session-") || !file_name.ends_with(".jsonl") {
+                continue;
+            }
+
+            let session_id = file_name.trim_end_matches(".jsonl").to_string();
+            if seen_sessions.insert(session_id.clone()) {
+                files.push((entry.path(), session_id));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// 并发解析会话摘要，并发数受 `HISTORY_SUMMARY_PARSE_CONCURRENCY` 限制；解析失败的文件跳过。
+/// 返回满足 `filter` 的会话，以及过滤前属于工作区的会话数
+async fn parse_iflow_history_summaries(
+    files: Vec<(PathBuf, String)>,
+    workspace_variants: &[String],
+    filter: &HistorySessionFilter,
+) -> (Vec<IflowHistorySession>, usize) {
+    let summaries: Vec<CachedHistorySummary> = stream::iter(files)
+        .map(|(path, session_id)| async move {
+            parse_iflow_history_summary(&path, &session_id, workspace_variants)
+                .await
+                .ok()
+                .flatten()
+        })
+        .buffer_unordered(HISTORY_SUMMARY_PARSE_CONCURRENCY)
+        .filter_map(|summary| async move { summary })
+        .collect()
+        .await;
+    let workspace_matches = summaries.len();
+    let sessions = summaries
+        .into_iter()
+        .filter(|summary| filter.matches(summary))
+        .map(|summary| summary.session)
+        .collect();
+    (sessions, workspace_matches)
+}
+
+/// 列出工作区的 iFlow 历史会话；`filter` 在解析摘要时逐个判断，不满足的会话不返回
+#[tauri::command]
+pub async fn list_iflow_history_sessions(
+    workspace_path: String,
+    filter: Option<IflowHistoryFilter>,
+) -> Result<Vec<IflowHistorySession>, String> {
+    let filter = filter.unwrap_or_default().validated()?;
+    let workspace_variants = workspace_path_variants(&workspace_path).await;
+    let normalized_workspace = match tokio::fs::canonicalize(&workspace_path).await {
+        Ok(path) => normalize_workspace_path(&path.to_string_lossy()),
+        Err(_) => normalize_workspace_path(&workspace_path),
+    };
+    let candidate_dirs =
+        iflow_project_dirs_for_workspace(&workspace_path, &normalized_workspace).await?;
+
+    let mut seen_sessions = HashSet::new();
+    let files = collect_iflow_session_files(&candidate_dirs, &mut seen_sessions).await?;
+    let (mut sessions, workspace_matches) =
+        parse_iflow_history_summaries(files, &workspace_variants, &filter).await;
+
+    // 只有候选目录里没有该工作区的会话时才扫描全部项目目录；会话全被过滤掉时不回退
+    if workspace_matches == 0 {
+        let fallback_dirs = list_all_iflow_project_dirs().await?;
+        let files = collect_iflow_session_files(&fallback_dirs, &mut seen_sessions).await?;
+        (sessions, _) = parse_iflow_history_summaries(files, &workspace_variants, &filter).await;
     }
 
     sessions.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
@@ -522,12 +1211,14 @@ pub async fn load_iflow_history_messages(
     session_id: String,
 ) -> Result<Vec<IflowHistoryMessage>, String> {
     let normalized_session_id = normalize_iflow_session_id(&session_id)?;
+    let workspace_variants = workspace_path_variants(&workspace_path).await;
 
     let normalized_workspace = match tokio::fs::canonicalize(&workspace_path).await {
         Ok(path) => normalize_workspace_path(&path.to_string_lossy()),
         Err(_) => normalize_workspace_path(&workspace_path),
     };
-    let candidate_dirs = iflow_project_dirs_for_workspace(&workspace_path, &normalized_workspace)?;
+    let candidate_dirs =
+        iflow_project_dirs_for_workspace(&workspace_path, &normalized_workspace).await?;
 
     for project_dir in candidate_dirs {
         let file_path = project_dir.join(format!("{}.jsonl", normalized_session_id));
@@ -536,7 +1227,7 @@ pub async fn load_iflow_history_messages(
                 return parse_iflow_history_messages(
                     &file_path,
                     &normalized_session_id,
-                    &normalized_workspace,
+                    &workspace_variants,
                 )
                 .await;
             }
@@ -556,7 +1247,7 @@ pub async fn load_iflow_history_messages(
                 return parse_iflow_history_messages(
                     &file_path,
                     &normalized_session_id,
-                    &normalized_workspace,
+                    &workspace_variants,
                 )
                 .await;
             }
@@ -574,21 +1265,240 @@ pub async fn load_iflow_history_messages(
     ))
 }
 
+/// 读取会话的完整时间线（文本、工具调用与计划），会话须属于该工作区
+#[tauri::command]
+pub async fn load_iflow_history_entries(
+    workspace_path: String,
+    session_id: String,
+) -> Result<Vec<IflowHistoryEntry>, String> {
+    let normalized_session_id = normalize_iflow_session_id(&session_id)?;
+    let file_path = find_iflow_session_file(Some(&workspace_path), &normalized_session_id)
+        .await?
+        .ok_or_else(|| format!("Session file not found for {}", normalized_session_id))?;
+    let raw = tokio::fs::read_to_string(&file_path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", file_path.display(), e))?;
+    let workspace_variants = workspace_path_variants(&workspace_path).await;
+    if !session_matches_workspace(&raw, &workspace_variants).await {
+        return Err(format!(
+            "Session {} does not belong to workspace {}",
+            normalized_session_id, workspace_path
+        ));
+    }
+    Ok(extract_history_entries(&raw, &normalized_session_id))
+}
+
+/// 全文搜索的一处命中
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HistorySearchSnippet {
+    pub message_id: String,
+    pub role: String,
+    pub timestamp: String,
+    pub snippet: String,
+    /// 命中位置在消息内容中的字符偏移
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HistorySearchHit {
+    pub session_id: String,
+    pub title: String,
+    pub updated_at: String,
+    /// 命中的消息数（片段最多保留 MAX_SEARCH_SNIPPETS 条）
+    pub match_count: usize,
+    pub snippets: Vec<HistorySearchSnippet>,
+}
+
+fn fold_chars(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+fn find_folded(haystack: &[char], needle: &[char]) -> Option<usize> {
+    if needle.is_empty() || needle.len() > haystack.len() {
+        return None;
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn search_snippet(content: &[char], offset: usize, len: usize) -> String {
+    let start = offset.saturating_sub(SEARCH_SNIPPET_CONTEXT_CHARS);
+    let end = (offset + len + SEARCH_SNIPPET_CONTEXT_CHARS).min(content.len());
+    let body: String = content[start..end]
+        .iter()
+        .map(|&c| if c == '\n' || c == '\r' { ' ' } else { c })
+        .collect();
+    format!(
+        "{}{}{}",
+        if start > 0 { "…" } else { "" },
+        body.trim(),
+        if end < content.len() { "…" } else { "" }
+    )
+}
+
+/// 在会话消息中查找同时包含全部关键词（不区分大小写）的消息，片段以第一个关键词为中心
+fn search_history_messages(
+    session_id: &str,
+    messages: &[IflowHistoryMessage],
+    terms: &[Vec<char>],
+) -> Option<HistorySearchHit> {
+    let mut match_count = 0;
+    let mut snippets = Vec::new();
+    for message in messages {
+        let folded = fold_chars(&message.content);
+        let mut offsets = terms.iter().map(|term| find_folded(&folded, term));
+        let Some(Some(offset)) = offsets.next() else {
+            continue;
+        };
+        if offsets.any(|found| found.is_none()) {
+            continue;
+        }
+        match_count += 1;
+        if snippets.len() < MAX_SEARCH_SNIPPETS {
+            let content: Vec<char> = message.content.chars().collect();
+            snippets.push(HistorySearchSnippet {
+                message_id: message.id.clone(),
+                role: message.role.clone(),
+                timestamp: message.timestamp.clone(),
+                snippet: search_snippet(&content, offset, terms[0].len()),
+                offset,
+            });
+        }
+    }
+    if match_count == 0 {
+        return None;
+    }
+    let title = messages
+        .iter()
+        .find(|message| message.role == "user")
+        .map(|message| message.content.as_str())
+        .unwrap_or(session_id);
+    Some(HistorySearchHit {
+        session_id: session_id.to_string(),
+        title: compact_title(title),
+        updated_at: messages
+            .last()
+            .map(|message| message.timestamp.clone())
+            .unwrap_or_default(),
+        match_count,
+        snippets,
+    })
+}
+
+async fn collect_session_files(
+    project_dirs: Vec<PathBuf>,
+    seen_sessions: &mut HashSet<String>,
+) -> Result<Vec<(String, PathBuf)>, String> {
+    let mut files = Vec::new();
+    for project_dir in project_dirs {
+        let mut reader = match tokio::fs::read_dir(&project_dir).await {
+            Ok(reader) => reader,
+            Err(error) if error.kind() == ErrorKind::NotFound => continue,
+            Err(error) => {
+                return Err(format!(
+                    "Failed to open iFlow project dir {}: {}",
+                    project_dir.display(),
+                    error
+                ))
+            }
+        };
+        while let Some(entry) = reader
+            .next_entry()
+            .await
+            .map_err(|e| format!("Failed to read iFlow project entry: {}", e))?
+        {
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            if !file_name.starts_with("session-") || !file_name.ends_with(".jsonl") {
+                continue;
+            }
+            let session_id = file_name.trim_end_matches(".jsonl").to_string();
+            if seen_sessions.insert(session_id.clone()) {
+                files.push((session_id, entry.path()));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// 在工作区的 iFlow 历史会话中全文搜索，按会话更新时间倒序返回命中的会话与消息片段
+#[tauri::command]
+pub async fn search_iflow_history(
+    workspace_path: String,
+    query: String,
+) -> Result<Vec<HistorySearchHit>, String> {
+    let terms: Vec<Vec<char>> = query.split_whitespace().map(fold_chars).collect();
+    if terms.is_empty() {
+        return Err("Search query cannot be empty".to_string());
+    }
+    let workspace_variants = workspace_path_variants(&workspace_path).await;
+
+    let normalized_workspace = match tokio::fs::canonicalize(&workspace_path).await {
+        Ok(path) => normalize_workspace_path(&path.to_string_lossy()),
+        Err(_) => normalize_workspace_path(&workspace_path),
+    };
+    let candidate_dirs =
+        iflow_project_dirs_for_workspace(&workspace_path, &normalized_workspace).await?;
+    let mut seen_sessions = HashSet::new();
+    let mut files = collect_session_files(candidate_dirs, &mut seen_sessions).await?;
+    if files.is_empty() {
+        let fallback_dirs = list_all_iflow_project_dirs().await?;
+        files = collect_session_files(fallback_dirs, &mut seen_sessions).await?;
+    }
+
+    let mut hits = Vec::new();
+    for (session_id, path) in files {
+        // 不属于该工作区或无法读取的会话文件直接跳过
+        let Ok(messages) =
+            parse_iflow_history_messages(&path, &session_id, &workspace_variants).await
+        else {
+            continue;
+        };
+        if let Some(hit) = search_history_messages(&session_id, &messages, &terms) {
+            hits.push(hit);
+        }
+    }
+    hits.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    hits.truncate(MAX_SEARCH_RESULTS);
+    Ok(hits)
+}
+
 #[tauri::command]
 pub async fn delete_iflow_history_session(
+    state: State<'_, AppState>,
     workspace_path: String,
     session_id: String,
+    dry_run: Option<bool>,
 ) -> Result<bool, String> {
+    let dry_run = dry_run.unwrap_or(false);
     let normalized_session_id = normalize_iflow_session_id(&session_id)?;
+    let _guard = state.history_lock.lock().await;
+    if state
+        .agent_manager
+        .active_acp_sessions()
+        .await
+        .contains(&normalized_session_id)
+    {
+        return Err(format!(
+            "Session {} is in use by a connected agent",
+            normalized_session_id
+        ));
+    }
     let normalized_workspace = match tokio::fs::canonicalize(&workspace_path).await {
         Ok(path) => normalize_workspace_path(&path.to_string_lossy()),
         Err(_) => normalize_workspace_path(&workspace_path),
     };
-    let candidate_dirs = iflow_project_dirs_for_workspace(&workspace_path, &normalized_workspace)?;
+    let candidate_dirs =
+        iflow_project_dirs_for_workspace(&workspace_path, &normalized_workspace).await?;
 
     for project_dir in candidate_dirs {
         let file_path = project_dir.join(format!("{}.jsonl", normalized_session_id));
-        match tokio::fs::remove_file(&file_path).await {
+        match remove_session_file(&file_path, dry_run).await {
             Ok(_) => return Ok(true),
             Err(error) if error.kind() == ErrorKind::NotFound => continue,
             Err(error) => {
@@ -600,7 +1510,7 @@ pub async fn delete_iflow_history_session(
     let fallback_dirs = list_all_iflow_project_dirs().await?;
     for project_dir in fallback_dirs {
         let file_path = project_dir.join(format!("{}.jsonl", normalized_session_id));
-        match tokio::fs::remove_file(&file_path).await {
+        match remove_session_file(&file_path, dry_run).await {
             Ok(_) => return Ok(true),
             Err(error) if error.kind() == ErrorKind::NotFound => continue,
             Err(error) => {
@@ -612,40 +1522,651 @@ pub async fn delete_iflow_history_session(
     Ok(false)
 }
 
+/// 在工作区对应的项目目录（找不到或未指定工作区时回退全部项目目录）中定位会话文件
+async fn find_iflow_session_file(
+    workspace_path: Option<&str>,
+    session_id: &str,
+) -> Result<Option<PathBuf>, String> {
+    let mut candidate_dirs = Vec::new();
+    if let Some(workspace_path) = workspace_path {
+        let normalized_workspace = match tokio::fs::canonicalize(workspace_path).await {
+            Ok(path) => normalize_workspace_path(&path.to_string_lossy()),
+            Err(_) => normalize_workspace_path(workspace_path),
+        };
+        candidate_dirs =
+            iflow_project_dirs_for_workspace(workspace_path, &normalized_workspace).await?;
+    }
+    candidate_dirs.extend(list_all_iflow_project_dirs().await?);
+
+    for project_dir in candidate_dirs {
+        let file_path = project_dir.join(format!("{}.jsonl", session_id));
+        match tokio::fs::metadata(&file_path).await {
+            Ok(metadata) if metadata.is_file() => return Ok(Some(file_path)),
+            Ok(_) => continue,
+            Err(error) if error.kind() == ErrorKind::NotFound => continue,
+            Err(error) => {
+                return Err(format!(
+                    "Failed to inspect {}: {}",
+                    file_path.display(),
+                    error
+                ));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// 把记录中的 sessionId 改为新会话；无法解析的行原样保留
+fn rewrite_session_records(raw: &str, source_session_id: &str, target_session_id: &str) -> String {
+    let mut output = String::with_capacity(raw.len());
+    for line in raw.lines() {
+        let rewritten = serde_json::from_str::<Value>(line.trim())
+            .ok()
+            .filter(|record| {
+                record.get("sessionId").and_then(Value::as_str) == Some(source_session_id)
+            })
+            .and_then(|mut record| {
+                record["sessionId"] = Value::String(target_session_id.to_string());
+                serde_json::to_string(&record).ok()
+            });
+        output.push_str(rewritten.as_deref().unwrap_or(line));
+        output.push('\n');
+    }
+    output
+}
+
+/// 复制会话文件为新的会话（同一项目目录），返回新会话 ID 与文件路径；原会话不受影响
+pub(crate) async fn copy_iflow_session(
+    workspace_path: &str,
+    session_id: &str,
+) -> Result<(String, PathBuf), String> {
+    let normalized_session_id = normalize_iflow_session_id(session_id)?;
+    let source = find_iflow_session_file(Some(workspace_path), &normalized_session_id)
+        .await?
+        .ok_or_else(|| format!("Session file not found for {}", normalized_session_id))?;
+    let raw = tokio::fs::read_to_string(&source)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+
+    let forked_session_id = format!("session-{}", uuid::Uuid::new_v4());
+    let target = source.with_file_name(format!("{}.jsonl", forked_session_id));
+    let content = rewrite_session_records(&raw, &normalized_session_id, &forked_session_id);
+    tokio::fs::write(&target, content)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+    Ok((forked_session_id, target))
+}
+
+/// 提取记录中的 tool_use / tool_result 条目（附带所在记录的类型与时间）
+fn extract_tool_entries(raw: &str) -> Vec<Value> {
+    let mut entries = Vec::new();
+    for line in raw.lines() {
+        let Ok(record) = serde_json::from_str::<Value>(line.trim()) else {
+            continue;
+        };
+        let Some(Value::Array(items)) = record
+            .get("message")
+            .and_then(|message| message.get("content"))
+        else {
+            continue;
+        };
+        for item in items {
+            let kind = item.get("type").and_then(Value::as_str).unwrap_or_default();
+            if kind == "tool_use" || kind == "tool_result" {
+                entries.push(serde_json::json!({
+                    "recordType": record.get("type").cloned().unwrap_or(Value::Null),
+                    "timestamp": record.get("timestamp").cloned().unwrap_or(Value::Null),
+                    "entry": item,
+                }));
+            }
+        }
+    }
+    entries
+}
+
+/// 读取会话的工具调用记录；会话文件不存在时返回空列表
+pub(crate) async fn load_iflow_tool_entries(
+    workspace_path: Option<&str>,
+    session_id: &str,
+) -> Result<Vec<Value>, String> {
+    let normalized_session_id = normalize_iflow_session_id(session_id)?;
+    let Some(file_path) = find_iflow_session_file(workspace_path, &normalized_session_id).await?
+    else {
+        return Ok(Vec::new());
+    };
+    let raw = tokio::fs::read_to_string(&file_path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", file_path.display(), e))?;
+    Ok(extract_tool_entries(&raw))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::workspace_path_matches;
+    use super::{
+        extract_history_entries, extract_tool_entries, fold_chars, is_session_file_busy,
+        parse_iflow_history_summary, read_project_dir_cwd, remove_session_file,
+        rewrite_session_records, search_history_messages, workspace_path_matches,
+        workspace_path_variants, workspace_to_iflow_project_keys, IflowHistoryEntry,
+        IflowHistoryMessage, IflowHistoryPlanItem, HISTORY_SUMMARY_CACHE,
+    };
+    use std::collections::HashSet;
+    use std::time::{Duration, SystemTime};
 
     #[test]
-    fn workspace_match_supports_exact_and_parent_child() {
-        assert!(workspace_path_matches(
-            "/Users/chenweilong/playground/iflow/iflow-workspace",
-            "/Users/chenweilong/playground/iflow/iflow-workspace"
-        ));
-        assert!(workspace_path_matches(
-            "/Users/chenweilong/playground",
-            "/Users/chenweilong/playground/iflow/iflow-workspace"
-        ));
-        assert!(workspace_path_matches(
-            "/Users/chenweilong/playground/iflow/iflow-workspace",
-            "/Users/chenweilong/playground"
+    fn project_keys_handle_unix_and_windows_paths() {
+        assert_eq!(
+            workspace_to_iflow_project_keys("/Users/me/repo/"),
+            vec!["-Users-me-repo".to_string()]
+        );
+        assert_eq!(
+            workspace_to_iflow_project_keys(r"C:\Users\me\repo"),
+            vec![
+                "C--Users-me-repo".to_string(),
+                "-C--Users-me-repo".to_string()
+            ]
+        );
+        assert_eq!(
+            workspace_to_iflow_project_keys("d:/work/repo"),
+            vec![
+                "D--work-repo".to_string(),
+                "-D--work-repo".to_string(),
+                "d--work-repo".to_string(),
+                "-d--work-repo".to_string(),
+            ]
+        );
+        assert_eq!(
+            workspace_to_iflow_project_keys(r"\\server\share\repo"),
+            vec!["--server-share-repo".to_string()]
+        );
+    }
+
+    #[test]
+    fn history_search_requires_every_term_and_reports_offsets() {
+        let message = |id: &str, role: &str, content: &str| IflowHistoryMessage {
+            id: id.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: format!("2026-01-01T00:00:0{}Z", id),
+        };
+        let messages = vec![
+            message("1", "user", "How does login work?"),
+            message(
+                "2",
+                "assistant",
+                "The Auth flow starts in\nsrc/auth.rs and issues a token.",
+            ),
+            message("3", "assistant", "Unrelated flow chart."),
+        ];
+        let terms: Vec<Vec<char>> = "auth FLOW".split_whitespace().map(fold_chars).collect();
+        let hit = search_history_messages("session-1", &messages, &terms).unwrap();
+        assert_eq!(hit.title, "How does login work?");
+        assert_eq!(hit.updated_at, "2026-01-01T00:00:03Z");
+        assert_eq!(hit.match_count, 1);
+        assert_eq!(hit.snippets[0].message_id, "2");
+        assert_eq!(hit.snippets[0].offset, 4);
+        assert_eq!(
+            hit.snippets[0].snippet,
+            "The Auth flow starts in src/auth.rs and issues a token."
+        );
+
+        let missing: Vec<Vec<char>> = vec![fold_chars("oauth")];
+        assert!(search_history_messages("session-1", &messages, &missing).is_none());
+    }
+
+    #[test]
+    fn busy_session_files_are_skipped() {
+        let now = SystemTime::now();
+        let old = Some(now - Duration::from_secs(3600));
+        let active: HashSet<String> = ["session-live".to_string()].into_iter().collect();
+
+        assert!(is_session_file_busy("session-live", &active, old, now));
+        assert!(is_session_file_busy(
+            "session-fresh",
+            &active,
+            Some(now),
+            now
         ));
-        assert!(!workspace_path_matches(
-            "/Users/chenweilong/playground",
-            "/Users/chenweilong/Downloads"
+        assert!(!is_session_file_busy("session-old", &active, old, now));
+        assert!(!is_session_file_busy("session-unknown", &active, None, now));
+    }
+
+    #[test]
+    fn forked_records_point_to_new_session() {
+        let raw = concat!(
+            r#"{"type":"user","sessionId":"session-a","message":{"content":"session-a"}}"#,
+            "\n",
+            "not json\n",
+            r#"{"type":"assistant","message":{"content":"ok"}}"#,
+        );
+        let forked = rewrite_session_records(raw, "session-a", "session-b");
+        let lines: Vec<&str> = forked.lines().collect();
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["sessionId"], "session-b");
+        assert_eq!(first["message"]["content"], "session-a");
+        assert_eq!(lines[1], "not json");
+        assert_eq!(
+            lines[2],
+            r#"{"type":"assistant","message":{"content":"ok"}}"#
+        );
+    }
+
+    #[test]
+    fn tool_entries_keep_use_and_result() {
+        let tools = extract_tool_entries(concat!(
+            r#"{"type":"assistant","timestamp":"t1","message":{"content":[{"type":"text","text":"hi"},{"type":"tool_use","id":"a"}]}}"#,
+            "\n",
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"a"}]}}"#,
         ));
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[0]["entry"]["id"], "a");
+        assert_eq!(tools[0]["timestamp"], "t1");
+        assert_eq!(tools[1]["recordType"], "user");
+    }
+
+    #[test]
+    fn history_entries_pair_tool_results_and_detect_plans() {
+        let entries = extract_history_entries(
+            concat!(
+                r#"{"type":"user","uuid":"u1","timestamp":"t0","message":{"content":"fix the build"}}"#,
+                "\n",
+                r#"{"type":"assistant","uuid":"a1","timestamp":"t1","message":{"content":[{"type":"text","text":"Running cargo"},{"type":"tool_use","id":"call-1","name":"run_shell_command","input":{"command":"cargo build"}}]}}"#,
+                "\n",
+                r#"{"type":"user","uuid":"u2","timestamp":"t2","message":{"content":[{"type":"tool_result","tool_use_id":"call-1","is_error":true,"content":[{"type":"text","text":"error[E0425]"}]}]}}"#,
+                "\n",
+                r#"{"type":"assistant","timestamp":"t3","message":{"content":[{"type":"tool_use","id":"call-2","name":"todo_write","input":{"todos":[{"content":"Fix import","status":"in_progress"},{"content":"  "}]}}]}}"#,
+            ),
+            "session-1",
+        );
+        assert_eq!(entries.len(), 4);
+        assert_eq!(
+            entries[1],
+            IflowHistoryEntry::Text {
+                id: "a1".to_string(),
+                role: "assistant".to_string(),
+                content: "Running cargo".to_string(),
+                timestamp: "t1".to_string(),
+            }
+        );
+        let IflowHistoryEntry::ToolCall {
+            id,
+            name,
+            input,
+            output,
+            is_error,
+            ..
+        } = &entries[2]
+        else {
+            panic!("expected tool call, got {:?}", entries[2]);
+        };
+        assert_eq!(id, "call-1");
+        assert_eq!(name, "run_shell_command");
+        assert_eq!(input["command"], "cargo build");
+        assert_eq!(output.as_deref(), Some("error[E0425]"));
+        assert!(*is_error);
+        assert_eq!(
+            entries[3],
+            IflowHistoryEntry::Plan {
+                id: "call-2".to_string(),
+                items: vec![IflowHistoryPlanItem {
+                    content: "Fix import".to_string(),
+                    status: "in_progress".to_string(),
+                }],
+                timestamp: "t3".to_string(),
+            }
+        );
+        let serialized = serde_json::to_value(&entries[2]).unwrap();
+        assert_eq!(serialized["kind"], "toolCall");
+        assert_eq!(serialized["isError"], true);
+    }
+
+    #[tokio::test]
+    async fn summary_cache_is_reused_until_file_changes() {
+        let dir = std::env::temp_dir().join(format!("iflow-summary-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let path = dir.join("session-1.jsonl");
+        let line =
+            r#"{"type":"user","cwd":"/work/repo","timestamp":"t1","message":{"content":"first"}}"#;
+        std::fs::write(&path, format!("{}\n", line)).expect("write session");
+
+        let summary = parse_iflow_history_summary(&path, "session-1", &["/work/repo".to_string()])
+            .await
+            .expect("parse")
+            .expect("matching workspace");
+        assert_eq!(summary.session.message_count, 1);
+        assert!(HISTORY_SUMMARY_CACHE.lock().unwrap().contains_key(&path));
+        // 缓存的 cwd 仍按调用方的工作区判断
+        assert!(
+            parse_iflow_history_summary(&path, "session-1", &["/work/other".to_string()])
+                .await
+                .expect("parse")
+                .is_none()
+        );
+
+        std::fs::write(
+            &path,
+            format!("{}\n{}\n", line, line.replace("first", "second")),
+        )
+        .expect("append session");
+        let summary = parse_iflow_history_summary(&path, "session-1", &["/work/repo".to_string()])
+            .await
+            .expect("parse")
+            .expect("matching workspace");
+        assert_eq!(summary.session.message_count, 2);
+        assert_eq!(summary.session.title, "first");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn session_files_are_deduplicated_and_parsed_concurrently() {
+        let root = std::env::temp_dir().join(format!("iflow-parallel-{}", uuid::Uuid::new_v4()));
+        let (first, second) = (root.join("-work-repo"), root.join("-work-repo-old"));
+        std::fs::create_dir_all(&first).expect("create first dir");
+        std::fs::create_dir_all(&second).expect("create second dir");
+        let line =
+            r#"{"type":"user","cwd":"/work/repo","timestamp":"t1","message":{"content":"hi"}}"#;
+        for index in 0..40 {
+            let name = format!("session-{}.jsonl", index);
+            std::fs::write(first.join(&name), format!("{}\n", line)).expect("write session");
+        }
+        std::fs::write(second.join("session-0.jsonl"), "{}\n").expect("write duplicate");
+        std::fs::write(second.join("notes.txt"), "x").expect("write other file");
+
+        let mut seen = HashSet::new();
+        let files = collect_iflow_session_files(&[first, second], &mut seen)
+            .await
+            .expect("collect");
+        assert_eq!(files.len(), 40);
+        let filter = HistorySessionFilter {
+            min_message_count: 2,
+            ..HistorySessionFilter::default()
+        };
+        let (sessions, workspace_matches) =
+            parse_iflow_history_summaries(files.clone(), &["/work/repo".to_string()], &filter)
+                .await;
+        // 全部被过滤掉时仍按过滤前的匹配数判断，不会回退扫描其他项目目录
+        assert!(sessions.is_empty());
+        assert_eq!(workspace_matches, 40);
+        let (sessions, _) = parse_iflow_history_summaries(
+            files,
+            &["/work/repo".to_string()],
+            &HistorySessionFilter::default(),
+        )
+        .await;
+        assert_eq!(sessions.len(), 40);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn history_filter_checks_time_range_role_and_message_count() {
+        let raw = [
+            r#"{"type":"user","timestamp":"2026-01-01T08:00:00Z","message":{"content":"hi"}}"#,
+            r#"{"type":"assistant","timestamp":"2026-01-02T08:00:00Z","message":{"content":"hello"}}"#,
+        ]
+        .join("\n");
+        let (session, cwds, has_assistant_messages) =
+            summarize_history_records(&raw, "session-1", "t0".to_string());
+        assert!(has_assistant_messages);
+        let summary = CachedHistorySummary {
+            modified: None,
+            len: 0,
+            session,
+            cwds,
+            has_assistant_messages,
+        };
+        let filter = |value: serde_json::Value| {
+            serde_json::from_value::<IflowHistoryFilter>(value)
+                .expect("deserialize filter")
+                .validated()
+        };
+
+        let last_week = filter(serde_json::json!({
+            "updatedAfter": "2026-01-02T00:00:00Z",
+            "hasAssistantMessages": true,
+            "minMessageCount": 2,
+        }))
+        .expect("valid filter");
+        assert!(last_week.matches(&summary));
+        assert!(
+            !filter(serde_json::json!({ "updatedBefore": "2026-01-02T08:00:00Z" }))
+                .expect("valid filter")
+                .matches(&summary)
+        );
+        assert!(
+            !filter(serde_json::json!({ "hasAssistantMessages": false }))
+                .expect("valid filter")
+                .matches(&summary)
+        );
+        assert!(!filter(serde_json::json!({ "minMessageCount": 3 }))
+            .expect("valid filter")
+            .matches(&summary));
+        assert!(filter(serde_json::json!({ "updatedAfter": "last week" })).is_err());
+        assert!(filter(serde_json::json!({
+            "updatedAfter": "2026-01-02T00:00:00Z",
+            "updatedBefore": "2026-01-01T00:00:00Z",
+        }))
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn dry_run_delete_keeps_session_file() {
+        let path = std::env::temp_dir().join(format!("session-{}.jsonl", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "{}\n").expect("write session");
+
+        remove_session_file(&path, true).await.expect("dry run");
+        assert!(path.exists());
+        remove_session_file(&path, false).await.expect("delete");
+        assert!(!path.exists());
+        assert!(remove_session_file(&path, true).await.is_err());
+    }
+
+    async fn workspace_matches(workspace_path: &str, record_cwd: &str) -> bool {
+        workspace_path_matches(&workspace_path_variants(workspace_path).await, record_cwd).await
+    }
+
+    #[tokio::test]
+    async fn workspace_match_supports_exact_and_parent_child() {
+        assert!(
+            workspace_matches(
+                "/Users/chenweilong/playground/iflow/iflow-workspace",
+                "/Users/chenweilong/playground/iflow/iflow-workspace"
+            )
+            .await
+        );
+        assert!(
+            workspace_matches(
+                "/Users/chenweilong/playground",
+                "/Users/chenweilong/playground/iflow/iflow-workspace"
+            )
+            .await
+        );
+        assert!(
+            workspace_matches(
+                "/Users/chenweilong/playground/iflow/iflow-workspace",
+                "/Users/chenweilong/playground"
+            )
+            .await
+        );
+        assert!(
+            !workspace_matches(
+                "/Users/chenweilong/playground",
+                "/Users/chenweilong/Downloads"
+            )
+            .await
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn workspace_match_compares_symlink_and_real_path() {
+        let base = std::env::temp_dir().join(format!("iflow-symlink-{}", uuid::Uuid::new_v4()));
+        let real = base.join("real");
+        let link = base.join("link");
+        std::fs::create_dir_all(&real).expect("create real dir");
+        std::os::unix::fs::symlink(&real, &link).expect("create symlink");
+
+        let real_canonical = std::fs::canonicalize(&real).expect("canonicalize");
+        assert!(
+            workspace_matches(&link.to_string_lossy(), &real_canonical.to_string_lossy()).await
+        );
+        assert!(
+            workspace_matches(&real_canonical.to_string_lossy(), &link.to_string_lossy()).await
+        );
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[cfg(target_os = "macos")]
+    #[tokio::test]
+    async fn workspace_match_handles_macos_private_tmp() {
+        let name = format!("iflow-history-{}", uuid::Uuid::new_v4());
+        let workspace = format!("/tmp/{}", name);
+        std::fs::create_dir_all(&workspace).expect("create workspace");
+
+        assert!(workspace_matches(&workspace, &format!("/private/tmp/{}", name)).await);
+        assert!(workspace_matches(&format!("/private/tmp/{}", name), &workspace).await);
+        assert!(workspace_path_variants(&workspace)
+            .await
+            .iter()
+            .any(|variant| variant.starts_with("/private/tmp/")));
+
+        let _ = std::fs::remove_dir_all(&workspace);
+    }
+
+    #[tokio::test]
+    async fn project_dir_cwd_is_read_from_first_record() {
+        let dir = std::env::temp_dir().join(format!("iflow-history-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        std::fs::write(
+            dir.join("session-a.jsonl"),
+            "{\"type\":\"system\"}\n{\"type\":\"user\",\"cwd\":\"/Users/张三/my project/\"}\n",
+        )
+        .expect("write session");
+
+        assert_eq!(
+            read_project_dir_cwd(&dir).await.as_deref(),
+            Some("/Users/张三/my project")
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn history_activity_counts_prompts_and_tool_calls() {
+        let path = std::env::temp_dir().join(format!("session-{}.jsonl", uuid::Uuid::new_v4()));
+        let lines = [
+            r#"{"type":"user","cwd":"/work/app","timestamp":"2026-03-02T09:00:00Z","message":{"content":"fix tests"}}"#,
+            r#"{"type":"assistant","timestamp":"2026-03-02T09:00:05Z","message":{"content":[{"type":"tool_use","id":"a"},{"type":"tool_use","id":"b"}]}}"#,
+            r#"{"type":"user","timestamp":"2026-03-02T09:00:06Z","message":{"content":[{"type":"tool_result","tool_use_id":"a"}]}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"c"}]}}"#,
+        ];
+        std::fs::write(&path, lines.join("\n")).expect("write session");
+
+        let activities =
+            parse_iflow_history_activity(&path, "session-a", &["/work/app".to_string()])
+                .await
+                .expect("parse activity");
+        assert_eq!(
+            activities
+                .iter()
+                .map(|item| (item.prompts, item.tool_calls))
+                .collect::<Vec<_>>(),
+            vec![(1, 0), (0, 2)]
+        );
+        assert!(
+            parse_iflow_history_activity(&path, "session-a", &["/work/other".to_string()])
+                .await
+                .expect("parse activity")
+                .is_empty()
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
+
+/// dry_run 时只确认文件存在，不删除
+async fn remove_session_file(path: &Path, dry_run: bool) -> std::io::Result<()> {
+    if dry_run {
+        tokio::fs::metadata(path).await.map(|_| ())
+    } else {
+        tokio::fs::remove_file(path).await
     }
 }
 
+/// 以后台任务清空工作区历史，立即返回任务句柄；完成结果为回收清单，
+/// dry_run 时结果为 HistoryClearPreview，不移动任何文件
 #[tauri::command]
-pub async fn clear_iflow_history_sessions(workspace_path: String) -> Result<usize, String> {
-    let normalized_workspace = match tokio::fs::canonicalize(&workspace_path).await {
+pub async fn clear_iflow_history_sessions(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: String,
+    dry_run: Option<bool>,
+) -> Result<JobHandle, String> {
+    if dry_run.unwrap_or(false) {
+        return Ok(spawn_job(
+            &app_handle,
+            &state,
+            "clear_history",
+            move |job| async move { preview_history_clear(&job, workspace_path).await },
+        ));
+    }
+    Ok(spawn_job(
+        &app_handle,
+        &state,
+        "clear_history",
+        move |job| async move { clear_history_sessions_job(&job, workspace_path).await },
+    ))
+}
+
+async fn preview_history_clear(
+    job: &JobContext,
+    workspace_path: String,
+) -> Result<HistoryClearPreview, String> {
+    let state = job.app_handle().state::<AppState>();
+    let _guard = state.history_lock.lock().await;
+    let (files, skipped) = collect_clearable_sessions(job, &state, &workspace_path).await?;
+    Ok(HistoryClearPreview {
+        dry_run: true,
+        total_bytes: files.iter().map(|file| file.bytes).sum(),
+        workspace_path,
+        files,
+        skipped,
+    })
+}
+
+async fn clear_history_sessions_job(
+    job: &JobContext,
+    workspace_path: String,
+) -> Result<HistoryClearManifest, String> {
+    let state = job.app_handle().state::<AppState>();
+    let _guard = state.history_lock.lock().await;
+    let (files, skipped) = collect_clearable_sessions(job, &state, &workspace_path).await?;
+    let sessions = files
+        .into_iter()
+        .map(|file| (file.session_id, PathBuf::from(file.path)))
+        .collect();
+    move_sessions_to_trash(job, &workspace_path, sessions, skipped).await
+}
+
+/// 列出清空时会移除的会话文件与因占用而保留的会话；调用方需持有 history_lock
+async fn collect_clearable_sessions(
+    job: &JobContext,
+    state: &AppState,
+    workspace_path: &str,
+) -> Result<(Vec<PendingHistoryFile>, Vec<String>), String> {
+    job.check_cancelled()?;
+    let active_sessions = state.agent_manager.active_acp_sessions().await;
+    let now = SystemTime::now();
+    let normalized_workspace = match tokio::fs::canonicalize(workspace_path).await {
         Ok(path) => normalize_workspace_path(&path.to_string_lossy()),
-        Err(_) => normalize_workspace_path(&workspace_path),
+        Err(_) => normalize_workspace_path(workspace_path),
     };
-    let candidate_dirs = iflow_project_dirs_for_workspace(&workspace_path, &normalized_workspace)?;
+    let candidate_dirs =
+        iflow_project_dirs_for_workspace(workspace_path, &normalized_workspace).await?;
 
-    let mut deleted_files = 0_usize;
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
 
     for project_dir in candidate_dirs {
         let mut reader = match tokio::fs::read_dir(&project_dir).await {
@@ -665,6 +2186,7 @@ pub async fn clear_iflow_history_sessions(workspace_path: String) -> Result<usiz
             .await
             .map_err(|e| format!("Failed to read iFlow project entry: {}", e))?
         {
+            job.check_cancelled()?;
             let file_name = entry.file_name();
             let file_name = file_name.to_string_lossy();
             if !file_name.starts_with("session-") || !file_name.ends_with(".jsonl") {
@@ -672,12 +2194,37 @@ pub async fn clear_iflow_history_sessions(workspace_path: String) -> Result<usiz
             }
 
             let path = entry.path();
-            tokio::fs::remove_file(&path)
-                .await
-                .map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
-            deleted_files += 1;
+            let session_id = file_name.trim_end_matches(".jsonl");
+            let metadata = entry.metadata().await.ok();
+            let modified = metadata.as_ref().and_then(|meta| meta.modified().ok());
+            if is_session_file_busy(session_id, &active_sessions, modified, now) {
+                println!("[history] Skip busy session file: {}", path.display());
+                skipped.push(session_id.to_string());
+                continue;
+            }
+            files.push(PendingHistoryFile {
+                session_id: session_id.to_string(),
+                path: path.to_string_lossy().to_string(),
+                bytes: metadata.map(|meta| meta.len()).unwrap_or(0),
+            });
         }
     }
 
-    Ok(deleted_files)
+    Ok((files, skipped))
+}
+
+/// 会话被在线 Agent 绑定，或刚刚仍有写入
+fn is_session_file_busy(
+    session_id: &str,
+    active_sessions: &HashSet<String>,
+    modified: Option<SystemTime>,
+    now: SystemTime,
+) -> bool {
+    if active_sessions.contains(session_id) {
+        return true;
+    }
+    modified
+        .and_then(|modified| now.duration_since(modified).ok())
+        .map(|age| age < ACTIVE_WRITE_GRACE)
+        .unwrap_or(false)
 }
@@ -0,0 +1,504 @@
+//! 历史会话回收站：清空会话时先把 JSONL 移入应用数据目录下的回收目录并记录清单，
+//! 在撤销窗口内可通过 undo_last_clear 恢复该工作区最近一次清空，过期批次由后台定期彻底删除。
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, State};
+
+use crate::history::normalize_workspace_path;
+use crate::jobs::JobContext;
+use crate::legal_hold::LegalHold;
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag};
+
+/// 清空后可撤销的时间窗口
+const UNDO_CLEAR_WINDOW_MINUTES: i64 = 10;
+/// 后台清理过期批次的间隔
+const PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearedHistoryFile {
+    pub session_id: String,
+    pub original_path: String,
+    pub trash_path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryClearManifest {
+    pub clear_id: String,
+    pub workspace_path: String,
+    pub cleared_at: String,
+    pub undo_expires_at: String,
+    pub files: Vec<ClearedHistoryFile>,
+    /// 因在线 Agent 占用或仍在写入而保留的会话
+    #[serde(default)]
+    pub skipped: Vec<String>,
+}
+
+impl HistoryClearManifest {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        DateTime::parse_from_rfc3339(&self.undo_expires_at)
+            .map(|expires_at| now >= expires_at.with_timezone(&Utc))
+            .unwrap_or(true)
+    }
+}
+
+fn trash_root(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-history-trash-{}", storage_env_tag())))
+}
+
+/// 优先重命名；跨文件系统时退化为复制后删除
+async fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if tokio::fs::rename(from, to).await.is_ok() {
+        return Ok(());
+    }
+    tokio::fs::copy(from, to).await.map_err(|e| {
+        format!(
+            "Failed to move {} to {}: {}",
+            from.display(),
+            to.display(),
+            e
+        )
+    })?;
+    tokio::fs::remove_file(from)
+        .await
+        .map_err(|e| format!("Failed to delete {}: {}", from.display(), e))
+}
+
+async fn write_manifest(batch_dir: &Path, manifest: &HistoryClearManifest) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize clear manifest: {}", e))?;
+    tokio::fs::write(batch_dir.join(MANIFEST_FILE_NAME), content)
+        .await
+        .map_err(|e| format!("Failed to write clear manifest: {}", e))
+}
+
+async fn read_manifests(root: &Path) -> Result<Vec<(PathBuf, HistoryClearManifest)>, String> {
+    let mut reader = match tokio::fs::read_dir(root).await {
+        Ok(reader) => reader,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(format!("Failed to open history trash dir: {}", error)),
+    };
+
+    let mut manifests = Vec::new();
+    while let Some(entry) = reader
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read history trash entry: {}", e))?
+    {
+        let batch_dir = entry.path();
+        let Ok(content) = tokio::fs::read_to_string(batch_dir.join(MANIFEST_FILE_NAME)).await
+        else {
+            continue;
+        };
+        match serde_json::from_str::<HistoryClearManifest>(&content) {
+            Ok(manifest) => manifests.push((batch_dir, manifest)),
+            Err(e) => println!(
+                "[history] Invalid clear manifest in {}: {}",
+                batch_dir.display(),
+                e
+            ),
+        }
+    }
+    manifests.sort_by(|a, b| a.1.cleared_at.cmp(&b.1.cleared_at));
+    Ok(manifests)
+}
+
+/// 彻底删除已过撤销窗口的批次
+async fn purge_expired_batches(root: &Path, now: DateTime<Utc>) -> Result<usize, String> {
+    let mut purged = 0_usize;
+    for (batch_dir, manifest) in read_manifests(root).await? {
+        if !manifest.is_expired(now) {
+            continue;
+        }
+        tokio::fs::remove_dir_all(&batch_dir)
+            .await
+            .map_err(|e| format!("Failed to purge {}: {}", batch_dir.display(), e))?;
+        purged += 1;
+    }
+    Ok(purged)
+}
+
+/// 后台清理一轮；保全期间回收批次一律保留，与删除类命令、附件保留期清理一致
+async fn run_purge_pass(
+    root: &Path,
+    legal_hold: &LegalHold,
+    now: DateTime<Utc>,
+) -> Result<usize, String> {
+    if legal_hold.is_active() {
+        return Ok(0);
+    }
+    purge_expired_batches(root, now).await
+}
+
+/// 把会话文件移入新的回收批次，返回清单；调用方需持有 history_lock。
+/// 每移动一个文件前调用 before_move(已完成数, 总数)，返回 Err 时中止，已移动部分仍可撤销
+async fn move_sessions_to_trash_in(
+    root: &Path,
+    workspace_path: &str,
+    sessions: Vec<(String, PathBuf)>,
+    skipped: Vec<String>,
+    now: DateTime<Utc>,
+    mut before_move: impl FnMut(usize, usize) -> Result<(), String>,
+) -> Result<HistoryClearManifest, String> {
+    if let Err(e) = purge_expired_batches(root, now).await {
+        println!("[history] Failed to purge expired trash: {}", e);
+    }
+
+    let clear_id = uuid::Uuid::new_v4().to_string();
+    let batch_dir = root.join(&clear_id);
+    tokio::fs::create_dir_all(&batch_dir)
+        .await
+        .map_err(|e| format!("Failed to create history trash dir: {}", e))?;
+
+    let mut manifest = HistoryClearManifest {
+        clear_id,
+        workspace_path: workspace_path.to_string(),
+        cleared_at: now.to_rfc3339(),
+        undo_expires_at: (now + Duration::minutes(UNDO_CLEAR_WINDOW_MINUTES)).to_rfc3339(),
+        files: Vec::new(),
+        skipped,
+    };
+
+    let total = sessions.len();
+    for (index, (session_id, original_path)) in sessions.into_iter().enumerate() {
+        // 不同项目目录可能存在同名会话文件，加序号避免覆盖
+        let trash_path = batch_dir.join(format!("{}-{}.jsonl", index, session_id));
+        let moved = match before_move(index, total) {
+            Ok(()) => move_file(&original_path, &trash_path).await,
+            Err(error) => Err(error),
+        };
+        if let Err(error) = moved {
+            // 已移动的文件先落清单，保证仍可撤销
+            write_manifest(&batch_dir, &manifest).await?;
+            return Err(error);
+        }
+        manifest.files.push(ClearedHistoryFile {
+            session_id,
+            original_path: original_path.to_string_lossy().to_string(),
+            trash_path: trash_path.to_string_lossy().to_string(),
+        });
+    }
+
+    write_manifest(&batch_dir, &manifest).await?;
+    Ok(manifest)
+}
+
+pub(crate) async fn move_sessions_to_trash(
+    job: &JobContext,
+    workspace_path: &str,
+    sessions: Vec<(String, PathBuf)>,
+    skipped: Vec<String>,
+) -> Result<HistoryClearManifest, String> {
+    let root = trash_root(job.app_handle())?;
+    let progress = |done: usize, total: usize| -> Result<(), String> {
+        job.check_cancelled()?;
+        job.report(done, total, "moving session files");
+        Ok(())
+    };
+    move_sessions_to_trash_in(
+        &root,
+        workspace_path,
+        sessions,
+        skipped,
+        Utc::now(),
+        progress,
+    )
+    .await
+}
+
+/// 原位置已被新文件占用时的恢复位置：`<id>.restored-<n>.jsonl`
+async fn conflict_free_path(original_path: &Path) -> PathBuf {
+    let stem = original_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut attempt = 1_usize;
+    loop {
+        let candidate =
+            original_path.with_file_name(format!("{}.restored-{}.jsonl", stem, attempt));
+        if !tokio::fs::try_exists(&candidate).await.unwrap_or(true) {
+            return candidate;
+        }
+        attempt += 1;
+    }
+}
+
+/// 恢复该工作区最近一次未过期的清空；原位置已有同名文件时改名恢复，保留现有文件。
+/// 返回的清单中 `original_path` 为实际恢复到的位置
+async fn restore_last_clear_in(
+    root: &Path,
+    workspace_path: &str,
+    now: DateTime<Utc>,
+) -> Result<HistoryClearManifest, String> {
+    let workspace = normalize_workspace_path(workspace_path);
+    let (batch_dir, manifest) = read_manifests(root)
+        .await?
+        .into_iter()
+        .rev()
+        .find(|(_, manifest)| normalize_workspace_path(&manifest.workspace_path) == workspace)
+        .ok_or_else(|| "No cleared history to undo".to_string())?;
+    if manifest.is_expired(now) {
+        return Err(format!(
+            "Undo window expired at {}",
+            manifest.undo_expires_at
+        ));
+    }
+
+    let mut restored = Vec::new();
+    for file in &manifest.files {
+        let trash_path = Path::new(&file.trash_path);
+        // 中断过的恢复可能已移走部分文件
+        if !tokio::fs::try_exists(trash_path).await.unwrap_or(false) {
+            continue;
+        }
+        let mut target = PathBuf::from(&file.original_path);
+        if tokio::fs::try_exists(&target).await.unwrap_or(true) {
+            target = conflict_free_path(&target).await;
+        }
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        move_file(trash_path, &target).await?;
+        restored.push(ClearedHistoryFile {
+            original_path: target.to_string_lossy().to_string(),
+            ..file.clone()
+        });
+    }
+
+    // 所有文件都已移出后才删除批次
+    tokio::fs::remove_dir_all(&batch_dir)
+        .await
+        .map_err(|e| format!("Failed to remove {}: {}", batch_dir.display(), e))?;
+
+    Ok(HistoryClearManifest {
+        files: restored,
+        ..manifest
+    })
+}
+
+/// 启动后定期彻底删除已过撤销窗口的批次
+pub(crate) fn spawn_history_trash_purge(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let Ok(root) = trash_root(&app_handle) else {
+                continue;
+            };
+            let state = app_handle.state::<AppState>();
+            let _guard = state.history_lock.lock().await;
+            match run_purge_pass(&root, &state.legal_hold, Utc::now()).await {
+                Ok(purged) if purged > 0 => {
+                    println!("[history] Purged {} expired trash batches", purged)
+                }
+                Ok(_) => {}
+                Err(e) => println!("[history] Failed to purge expired trash: {}", e),
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn undo_last_clear(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: String,
+) -> Result<HistoryClearManifest, String> {
+    let _guard = state.history_lock.lock().await;
+    restore_last_clear_in(&trash_root(&app_handle)?, &workspace_path, Utc::now()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("iflow-{}-{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        dir
+    }
+
+    #[tokio::test]
+    async fn cleared_sessions_can_be_restored_within_window() {
+        let project = temp_dir("project");
+        let root = temp_dir("trash");
+        let session_path = project.join("session-a.jsonl");
+        std::fs::write(&session_path, "{}\n").expect("write session");
+        let now = Utc::now();
+
+        let manifest = move_sessions_to_trash_in(
+            &root,
+            "/workspace",
+            vec![("session-a".to_string(), session_path.clone())],
+            vec!["session-live".to_string()],
+            now,
+            |_, _| Ok(()),
+        )
+        .await
+        .expect("move to trash");
+        assert_eq!(manifest.files.len(), 1);
+        assert_eq!(manifest.skipped, vec!["session-live"]);
+        assert!(!session_path.exists());
+
+        let restored = restore_last_clear_in(&root, "/workspace", now + Duration::minutes(1))
+            .await
+            .expect("undo");
+        assert_eq!(restored.files.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(&session_path).expect("restored"),
+            "{}\n"
+        );
+        assert!(restore_last_clear_in(&root, "/workspace", now)
+            .await
+            .is_err());
+
+        let _ = std::fs::remove_dir_all(&project);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn aborted_clear_keeps_moved_files_undoable() {
+        let project = temp_dir("project");
+        let root = temp_dir("trash");
+        let sessions: Vec<(String, PathBuf)> = ["session-1", "session-2"]
+            .iter()
+            .map(|id| {
+                let path = project.join(format!("{}.jsonl", id));
+                std::fs::write(&path, "{}\n").expect("write session");
+                (id.to_string(), path)
+            })
+            .collect();
+        let now = Utc::now();
+
+        let stop_at_second = |done: usize, _total: usize| {
+            if done == 1 {
+                Err("stop".to_string())
+            } else {
+                Ok(())
+            }
+        };
+        let result = move_sessions_to_trash_in(
+            &root,
+            "/workspace",
+            sessions,
+            Vec::new(),
+            now,
+            stop_at_second,
+        )
+        .await;
+        assert_eq!(result, Err("stop".to_string()));
+        assert!(project.join("session-2.jsonl").exists());
+
+        let restored = restore_last_clear_in(&root, "/workspace", now)
+            .await
+            .expect("undo");
+        assert_eq!(restored.files.len(), 1);
+        assert!(project.join("session-1.jsonl").exists());
+
+        let _ = std::fs::remove_dir_all(&project);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn undo_is_scoped_per_workspace_and_keeps_conflicting_files() {
+        let project = temp_dir("project");
+        let root = temp_dir("trash");
+        let session_path = project.join("session-c.jsonl");
+        std::fs::write(&session_path, "old\n").expect("write session");
+        let other_path = project.join("session-d.jsonl");
+        std::fs::write(&other_path, "other\n").expect("write session");
+        let now = Utc::now();
+
+        move_sessions_to_trash_in(
+            &root,
+            "/workspace-a",
+            vec![("session-c".to_string(), session_path.clone())],
+            Vec::new(),
+            now,
+            |_, _| Ok(()),
+        )
+        .await
+        .expect("move to trash");
+        move_sessions_to_trash_in(
+            &root,
+            "/workspace-b",
+            vec![("session-d".to_string(), other_path.clone())],
+            Vec::new(),
+            now + Duration::seconds(1),
+            |_, _| Ok(()),
+        )
+        .await
+        .expect("move to trash");
+        // iFlow 在清空后又写入了同名会话
+        std::fs::write(&session_path, "new\n").expect("write session");
+
+        let restored = restore_last_clear_in(&root, "/workspace-a/", now)
+            .await
+            .expect("undo");
+        let restored_path = project.join("session-c.restored-1.jsonl");
+        assert_eq!(
+            restored.files[0].original_path,
+            restored_path.to_string_lossy()
+        );
+        assert_eq!(std::fs::read_to_string(&session_path).unwrap(), "new\n");
+        assert_eq!(std::fs::read_to_string(&restored_path).unwrap(), "old\n");
+        assert!(!other_path.exists());
+        assert_eq!(read_manifests(&root).await.expect("read").len(), 1);
+
+        let _ = std::fs::remove_dir_all(&project);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn expired_batches_cannot_be_undone_and_are_purged_unless_held() {
+        let project = temp_dir("project");
+        let root = temp_dir("trash");
+        let session_path = project.join("session-b.jsonl");
+        std::fs::write(&session_path, "{}\n").expect("write session");
+        let now = Utc::now();
+
+        move_sessions_to_trash_in(
+            &root,
+            "/workspace",
+            vec![("session-b".to_string(), session_path)],
+            Vec::new(),
+            now,
+            |_, _| Ok(()),
+        )
+        .await
+        .expect("move to trash");
+
+        let later = now + Duration::minutes(UNDO_CLEAR_WINDOW_MINUTES + 1);
+        assert!(restore_last_clear_in(&root, "/workspace", later)
+            .await
+            .is_err());
+        let legal_hold = LegalHold::default();
+        legal_hold.apply(true);
+        assert_eq!(
+            run_purge_pass(&root, &legal_hold, later)
+                .await
+                .expect("held"),
+            0
+        );
+        assert_eq!(read_manifests(&root).await.expect("read").len(), 1);
+        legal_hold.apply(false);
+        assert_eq!(
+            run_purge_pass(&root, &legal_hold, later)
+                .await
+                .expect("purge"),
+            1
+        );
+        assert!(read_manifests(&root).await.expect("read").is_empty());
+
+        let _ = std::fs::remove_dir_all(&project);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
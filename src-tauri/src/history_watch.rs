@@ -0,0 +1,291 @@
+//! 监听 `~/.iflow/projects` 下与工作区对应的项目目录：会话文件新增、写入或删除时
+//! 发出 `history-changed` 事件（按工作区合并、短暂防抖），前端据此刷新会话侧栏而无需轮询。
+//! 监听整个项目根目录，以便工作区首次产生历史时新建的项目目录也能被发现。
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use notify::event::{EventKind, ModifyKind};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use tauri::State;
+use tokio::sync::mpsc;
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::history::{
+    iflow_project_dirs_for_workspace, iflow_projects_root, normalize_workspace_path,
+    workspace_path_variants, workspace_to_iflow_project_keys,
+};
+use crate::state::AppState;
+
+/// 连续写入（Agent 流式输出时）合并为一次事件
+const HISTORY_EVENT_DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum HistoryChangeKind {
+    Created,
+    Updated,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct HistoryChange {
+    session_id: String,
+    kind: HistoryChangeKind,
+}
+
+/// 按工作区保存的文件监听器，移除即停止监听
+#[derive(Default)]
+pub(crate) struct HistoryWatchers {
+    watchers: Mutex<HashMap<String, RecommendedWatcher>>,
+}
+
+/// 工作区对应的项目目录：已知目录加上按路径推导的目录名
+struct WatchScope {
+    project_dirs: HashSet<PathBuf>,
+    project_keys: HashSet<String>,
+}
+
+impl WatchScope {
+    fn contains(&self, project_dir: &Path) -> bool {
+        self.project_dirs.contains(project_dir)
+            || project_dir
+                .file_name()
+                .map(|name| self.project_keys.contains(name.to_string_lossy().as_ref()))
+                .unwrap_or(false)
+    }
+}
+
+/// 把文件系统事件换算为会话变化；与工作区无关或非会话文件时返回 None
+fn history_change(
+    kind: &EventKind,
+    path: &Path,
+    scope: &WatchScope,
+) -> Option<(String, HistoryChangeKind)> {
+    let change = match kind {
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)) => {
+            if path.exists() {
+                HistoryChangeKind::Created
+            } else {
+                HistoryChangeKind::Removed
+            }
+        }
+        EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any) => HistoryChangeKind::Updated,
+        EventKind::Remove(_) => HistoryChangeKind::Removed,
+        _ => return None,
+    };
+    if path.extension().and_then(|ext| ext.to_str()) != Some("jsonl") {
+        return None;
+    }
+    let session_id = path.file_stem()?.to_string_lossy().to_string();
+    if !session_id.starts_with("session-") || !scope.contains(path.parent()?) {
+        return None;
+    }
+    Some((session_id, change))
+}
+
+/// 防抖窗口内同一会话的多次变化合并：删除优先，其次新建
+fn merge_change(
+    pending: &mut BTreeMap<String, HistoryChangeKind>,
+    session_id: String,
+    kind: HistoryChangeKind,
+) {
+    let merged = match (pending.get(&session_id), kind) {
+        (_, HistoryChangeKind::Removed) => HistoryChangeKind::Removed,
+        (Some(HistoryChangeKind::Created), _) => HistoryChangeKind::Created,
+        _ => kind,
+    };
+    pending.insert(session_id, merged);
+}
+
+async fn forward_history_changes(
+    app_handle: tauri::AppHandle,
+    workspace_path: String,
+    mut receiver: mpsc::UnboundedReceiver<(String, HistoryChangeKind)>,
+) {
+    // 监听器被移除后发送端随之释放，recv 返回 None 结束任务
+    while let Some((session_id, kind)) = receiver.recv().await {
+        let mut pending = BTreeMap::new();
+        merge_change(&mut pending, session_id, kind);
+        while let Ok(Some((session_id, kind))) =
+            tokio::time::timeout(HISTORY_EVENT_DEBOUNCE, receiver.recv()).await
+        {
+            merge_change(&mut pending, session_id, kind);
+        }
+        let changes: Vec<HistoryChange> = pending
+            .into_iter()
+            .map(|(session_id, kind)| HistoryChange { session_id, kind })
+            .collect();
+        let _ = app_handle.emit_unless_locked(
+            "history-changed",
+            serde_json::json!({
+                "workspacePath": workspace_path,
+                "changes": changes,
+            }),
+        );
+    }
+}
+
+/// 开始监听工作区的 iFlow 历史目录；重复调用不会重复监听
+#[tauri::command]
+pub async fn watch_iflow_history(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: String,
+) -> Result<(), String> {
+    let normalized = normalize_workspace_path(&workspace_path);
+    let already_watching = state
+        .history_watchers
+        .watchers
+        .lock()
+        .map_err(|_| "History watcher lock poisoned".to_string())?
+        .contains_key(&normalized);
+    if already_watching {
+        return Ok(());
+    }
+
+    let root = iflow_projects_root()?;
+    if !tokio::fs::metadata(&root)
+        .await
+        .map(|metadata| metadata.is_dir())
+        .unwrap_or(false)
+    {
+        return Err(format!(
+            "iFlow history directory {} not found",
+            root.display()
+        ));
+    }
+    let scope = WatchScope {
+        project_dirs: iflow_project_dirs_for_workspace(&workspace_path, &normalized)
+            .await?
+            .into_iter()
+            .collect(),
+        project_keys: workspace_path_variants(&workspace_path)
+            .await
+            .iter()
+            .flat_map(|path| workspace_to_iflow_project_keys(path))
+            .collect(),
+    };
+
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        let Ok(event) = result else {
+            return;
+        };
+        for path in &event.paths {
+            if let Some(change) = history_change(&event.kind, path, &scope) {
+                let _ = sender.send(change);
+            }
+        }
+    })
+    .map_err(|e| format!("Failed to create history watcher: {}", e))?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {}: {}", root.display(), e))?;
+
+    let mut watchers = state
+        .history_watchers
+        .watchers
+        .lock()
+        .map_err(|_| "History watcher lock poisoned".to_string())?;
+    if watchers.contains_key(&normalized) {
+        return Ok(());
+    }
+    watchers.insert(normalized, watcher);
+    tokio::spawn(forward_history_changes(
+        app_handle,
+        workspace_path,
+        receiver,
+    ));
+    Ok(())
+}
+
+/// 停止监听工作区的 iFlow 历史目录
+#[tauri::command]
+pub async fn unwatch_iflow_history(
+    state: State<'_, AppState>,
+    workspace_path: String,
+) -> Result<(), String> {
+    state
+        .history_watchers
+        .watchers
+        .lock()
+        .map_err(|_| "History watcher lock poisoned".to_string())?
+        .remove(&normalize_workspace_path(&workspace_path));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, RemoveKind};
+
+    #[test]
+    fn history_changes_are_scoped_and_merged() {
+        let root = std::env::temp_dir().join(format!("iflow-watch-{}", uuid::Uuid::new_v4()));
+        let project_dir = root.join("-work-repo");
+        std::fs::create_dir_all(&project_dir).expect("create project dir");
+        let session = project_dir.join("session-1.jsonl");
+        std::fs::write(&session, "{}\n").expect("write session");
+        let scope = WatchScope {
+            project_dirs: HashSet::new(),
+            project_keys: workspace_to_iflow_project_keys("/work/repo")
+                .into_iter()
+                .collect(),
+        };
+
+        let created = EventKind::Create(CreateKind::File);
+        let appended = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        assert_eq!(
+            history_change(&created, &session, &scope),
+            Some(("session-1".to_string(), HistoryChangeKind::Created))
+        );
+        assert_eq!(
+            history_change(&appended, &session, &scope),
+            Some(("session-1".to_string(), HistoryChangeKind::Updated))
+        );
+        assert_eq!(
+            history_change(
+                &created,
+                &root.join("-work-other").join("session-2.jsonl"),
+                &scope
+            ),
+            None
+        );
+        assert_eq!(
+            history_change(&created, &project_dir.join("notes.txt"), &scope),
+            None
+        );
+
+        let mut pending = BTreeMap::new();
+        merge_change(
+            &mut pending,
+            "session-1".to_string(),
+            HistoryChangeKind::Created,
+        );
+        merge_change(
+            &mut pending,
+            "session-1".to_string(),
+            HistoryChangeKind::Updated,
+        );
+        merge_change(
+            &mut pending,
+            "session-2".to_string(),
+            HistoryChangeKind::Updated,
+        );
+        merge_change(
+            &mut pending,
+            "session-2".to_string(),
+            history_change(&EventKind::Remove(RemoveKind::File), &session, &scope)
+                .expect("removal")
+                .1,
+        );
+        assert_eq!(pending["session-1"], HistoryChangeKind::Created);
+        assert_eq!(pending["session-2"], HistoryChangeKind::Removed);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,113 @@
+//! HTML Artifact 预览前的净化（设置 `sanitize_html_artifacts` 开启时）：用 ammonia 去掉脚本、
+//! 事件属性、内嵌框架与指向外部地址的链接/资源，`<style>` 保留但去掉其中的 `@import` 与外部 `url()`。
+//! 报告按类别统计原文中被移除的内容，供预览界面提示。
+use std::collections::HashSet;
+
+use ammonia::{Builder, UrlRelative};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+static SCRIPT_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<script\b").unwrap());
+static EVENT_HANDLER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)<[^>]*\son[a-z]+\s*="#).unwrap());
+static EMBED_TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)<(iframe|frame|object|embed|applet)\b").unwrap());
+static EXTERNAL_ATTR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)\s(src|href|action|formaction|poster|srcset|data)\s*=\s*["']?\s*(https?:)?//"#,
+    )
+    .unwrap()
+});
+static CSS_IMPORT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)@import[^;]*;?").unwrap());
+static CSS_EXTERNAL_URL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)url\(\s*(&quot;|["'])?\s*(https?:)?//[^)]*\)"#).unwrap());
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HtmlSanitizeReport {
+    pub scripts: usize,
+    pub event_handlers: usize,
+    pub embedded_frames: usize,
+    /// 指向外部地址的 src / href 等属性
+    pub external_resources: usize,
+    /// `<style>` 与 style 属性中的 `@import` 及外部 `url()`
+    pub css_network_calls: usize,
+}
+
+impl HtmlSanitizeReport {
+    pub(crate) fn removed_anything(&self) -> bool {
+        self.scripts
+            + self.event_handlers
+            + self.embedded_frames
+            + self.external_resources
+            + self.css_network_calls
+            > 0
+    }
+}
+
+fn sanitizer() -> Builder<'static> {
+    let mut builder = Builder::default();
+    builder
+        .rm_clean_content_tags(["style"])
+        .add_clean_content_tags(["title"])
+        .add_tags(["style"])
+        .add_generic_attributes(["class", "id", "style"])
+        // 只保留 data: 与相对地址（相对地址由预览按工作区内文件解析）
+        .url_schemes(HashSet::from(["data"]))
+        .url_relative(UrlRelative::PassThrough);
+    builder
+}
+
+/// 净化 HTML 并报告移除的内容
+pub(crate) fn sanitize_html(html: &str) -> (String, HtmlSanitizeReport) {
+    let mut report = HtmlSanitizeReport {
+        scripts: SCRIPT_TAG.find_iter(html).count(),
+        event_handlers: EVENT_HANDLER.find_iter(html).count(),
+        embedded_frames: EMBED_TAG.find_iter(html).count(),
+        external_resources: EXTERNAL_ATTR.find_iter(html).count(),
+        css_network_calls: 0,
+    };
+    let cleaned = sanitizer().clean(html).to_string();
+    report.css_network_calls = CSS_IMPORT.find_iter(&cleaned).count();
+    let cleaned = CSS_IMPORT.replace_all(&cleaned, "");
+    report.css_network_calls += CSS_EXTERNAL_URL.find_iter(&cleaned).count();
+    let cleaned = CSS_EXTERNAL_URL.replace_all(&cleaned, "none");
+    (cleaned.into_owned(), report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_strips_scripts_and_network_calls_but_keeps_layout() {
+        let html = r#"<html><head><title>Report</title>
+<style>@import url("https://cdn.example.com/a.css"); body { background: url(//evil.example/x.png); color: red; }</style>
+<script src="https://cdn.example.com/app.js"></script><script>alert(1)</script></head>
+<body onload="track()"><h1 class="title">Result</h1>
+<img src="https://tracker.example/pixel.gif"><img src="chart.png">
+<iframe src="https://example.com"></iframe><a href="http://example.com">link</a></body></html>"#;
+        let (cleaned, report) = sanitize_html(html);
+
+        assert_eq!(report.scripts, 2);
+        assert_eq!(report.event_handlers, 1);
+        assert_eq!(report.embedded_frames, 1);
+        assert_eq!(report.external_resources, 4);
+        assert_eq!(report.css_network_calls, 2);
+        assert!(report.removed_anything());
+
+        assert!(!cleaned.contains("<script"));
+        assert!(!cleaned.contains("alert"));
+        assert!(!cleaned.contains("onload"));
+        assert!(!cleaned.contains("<iframe"));
+        assert!(!cleaned.contains("example"));
+        assert!(!cleaned.contains("Report"));
+        assert!(cleaned.contains("color: red;"));
+        assert!(cleaned.contains(r#"<h1 class="title">Result</h1>"#));
+        assert!(cleaned.contains(r#"<img src="chart.png">"#));
+
+        let (_, clean_report) = sanitize_html("<p>plain</p>");
+        assert!(!clean_report.removed_anything());
+    }
+}
@@ -0,0 +1,251 @@
+//! iFlow 登录状态桥接：从 iFlow 进程 stderr 与 ACP 错误中识别需要登录的状态并发出 `auth-required` 事件；
+//! 登录通过 ACP `authenticate` 请求完成（由 iFlow 打开浏览器或输出设备码），期间的 stderr 输出以
+//! `auth-progress` 事件转发给前端展示。
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::State;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::ChildStderr;
+use tokio::time::{timeout, Duration};
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::models::ListenerCommand;
+use crate::state::AppState;
+
+/// ACP 规范中表示需要认证的错误码
+const ACP_AUTH_REQUIRED_CODE: i64 = -32000;
+/// 浏览器登录需要用户操作，等待时间较长
+const AUTH_TIMEOUT: Duration = Duration::from_secs(300);
+const AUTH_REQUIRED_PATTERNS: &[&str] = &[
+    "auth required",
+    "authentication required",
+    "not authenticated",
+    "not logged in",
+    "please login",
+    "please log in",
+    "login required",
+    "invalid api key",
+    "api key is invalid",
+    "token expired",
+    "unauthorized",
+    "请先登录",
+    "未登录",
+    "登录已过期",
+    "认证失败",
+];
+
+// 已发出 auth-required、尚未完成登录的 Agent；登录期间转发其 stderr
+static AWAITING_AUTH: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AuthMethod {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+fn lock_awaiting() -> std::sync::MutexGuard<'static, HashSet<String>> {
+    AWAITING_AUTH
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub(crate) fn is_auth_required_text(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    AUTH_REQUIRED_PATTERNS
+        .iter()
+        .any(|pattern| lower.contains(pattern))
+}
+
+/// ACP 错误是否表示需要登录
+pub(crate) fn is_auth_error(error: &Value) -> bool {
+    if error.get("code").and_then(Value::as_i64) == Some(ACP_AUTH_REQUIRED_CODE) {
+        return true;
+    }
+    let message = error
+        .get("message")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| error.to_string());
+    is_auth_required_text(&message)
+}
+
+/// initialize 响应中声明的登录方式
+pub(crate) fn auth_methods_from_initialize(result: Option<&Value>) -> Vec<AuthMethod> {
+    result
+        .and_then(|result| result.get("authMethods"))
+        .and_then(Value::as_array)
+        .map(|methods| {
+            methods
+                .iter()
+                .filter_map(|method| {
+                    let id = method.get("id").and_then(Value::as_str)?.trim();
+                    if id.is_empty() {
+                        return None;
+                    }
+                    Some(AuthMethod {
+                        id: id.to_string(),
+                        name: method
+                            .get("name")
+                            .and_then(Value::as_str)
+                            .unwrap_or(id)
+                            .to_string(),
+                        description: method
+                            .get("description")
+                            .and_then(Value::as_str)
+                            .map(str::to_string),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn first_url(text: &str) -> Option<&str> {
+    let start = text.find("https://").or_else(|| text.find("http://"))?;
+    let url = text[start..]
+        .split(|ch: char| ch.is_whitespace() || ch == '"' || ch == '\'' || ch == '>')
+        .next()?;
+    Some(url.trim_end_matches(['.', ',', ')']))
+}
+
+/// 发出 auth-required；同一 Agent 在完成登录前只提示一次
+pub(crate) fn emit_auth_required(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    source: &str,
+    detail: &str,
+    methods: &[AuthMethod],
+) {
+    if !lock_awaiting().insert(agent_id.to_string()) {
+        return;
+    }
+    println!(
+        "[auth] Agent {} requires login ({}): {}",
+        agent_id, source, detail
+    );
+    let _ = app_handle.emit_unless_locked(
+        "auth-required",
+        json!({
+            "agentId": agent_id,
+            "source": source,
+            "detail": detail,
+            "authMethods": methods,
+        }),
+    );
+}
+
+pub(crate) fn clear_auth_required(agent_id: &str) {
+    lock_awaiting().remove(agent_id);
+}
+
+/// 读取 iFlow 进程的 stderr：识别登录提示，登录期间把输出（设备码、登录链接等）转发给前端
+pub(crate) fn watch_iflow_stderr(
+    app_handle: tauri::AppHandle,
+    agent_id: String,
+    stderr: ChildStderr,
+) {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            println!("[iflow:{}] {}", agent_id, line);
+            if is_auth_required_text(line) {
+                emit_auth_required(&app_handle, &agent_id, "stderr", line, &[]);
+            }
+            if lock_awaiting().contains(&agent_id) {
+                let _ = app_handle.emit_unless_locked(
+                    "auth-progress",
+                    json!({
+                        "agentId": &agent_id,
+                        "message": line,
+                        "url": first_url(line),
+                    }),
+                );
+            }
+        }
+    });
+}
+
+/// 通过 ACP `authenticate` 以指定方式登录；成功后监听任务会重新创建会话
+#[tauri::command]
+pub async fn authenticate_iflow(
+    state: State<'_, AppState>,
+    agent_id: String,
+    method_id: String,
+) -> Result<(), String> {
+    let method_id = method_id.trim().to_string();
+    if method_id.is_empty() {
+        return Err("Auth method is required".to_string());
+    }
+    let (agent_exists, sender) = state.agent_manager.sender_of(&agent_id).await;
+    if !agent_exists {
+        return Err(format!("Agent {} not found", agent_id));
+    }
+    let Some(sender) = sender else {
+        return Err("Message sender not available".to_string());
+    };
+
+    let (tx, rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
+    sender
+        .send(ListenerCommand::Authenticate {
+            method_id,
+            response: tx,
+        })
+        .map_err(|e| format!("Failed to queue authenticate: {}", e))?;
+
+    match timeout(AUTH_TIMEOUT, rx).await {
+        Ok(Ok(Ok(()))) => {}
+        Ok(Ok(Err(err))) => return Err(err),
+        Ok(Err(_)) => return Err("Authenticate response channel closed".to_string()),
+        Err(_) => return Err("Authenticate timeout after 5 minutes".to_string()),
+    }
+    clear_auth_required(&agent_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_errors_are_detected_from_code_and_text() {
+        assert!(is_auth_error(&json!({ "code": -32000, "message": "x" })));
+        assert!(is_auth_error(
+            &json!({ "code": -32603, "message": "Invalid API key provided" })
+        ));
+        assert!(!is_auth_error(
+            &json!({ "code": -32603, "message": "rate limited" })
+        ));
+        assert!(is_auth_required_text("Error: 未登录，请执行 iflow 登录"));
+        assert_eq!(
+            first_url("Open https://iflow.cn/device?code=ABCD. then continue"),
+            Some("https://iflow.cn/device?code=ABCD")
+        );
+    }
+
+    #[test]
+    fn auth_methods_are_read_from_initialize_result() {
+        let result = json!({
+            "protocolVersion": 1,
+            "authMethods": [
+                { "id": "oauth-iflow", "name": "iFlow 账号", "description": "浏览器登录" },
+                { "id": "openai-compatible" },
+                { "id": " " },
+            ],
+        });
+        let methods = auth_methods_from_initialize(Some(&result));
+        assert_eq!(methods.len(), 2);
+        assert_eq!(methods[0].name, "iFlow 账号");
+        assert_eq!(methods[1].name, "openai-compatible");
+        assert!(auth_methods_from_initialize(None).is_empty());
+    }
+}
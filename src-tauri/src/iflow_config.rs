@@ -0,0 +1,318 @@
+//! iFlow CLI 配置（`~/.iflow/settings.json`）的读取与修改：按已知字段校验类型，
+//! 未知字段原样保留；每次写入前备份原文件，密钥类字段在返回给前端时隐藏。
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tokio::fs;
+use tokio::sync::Mutex;
+
+use crate::runtime_env::home_dir;
+
+const CONFIG_FILE: &str = "settings.json";
+const BACKUP_DIR: &str = "settings-backups";
+const MAX_BACKUPS: usize = 10;
+/// 读取时隐藏、修改时未提供则保留原值的字段
+const SECRET_KEYS: &[&str] = &["apiKey", "searchApiKey"];
+const AUTH_TYPES: &[&str] = &["iflow", "oauth-iflow", "openai-compatible", "api-key"];
+
+static CONFIG_WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IflowConfig {
+    pub path: String,
+    pub exists: bool,
+    /// 配置内容，密钥类字段已移除
+    pub config: Map<String, Value>,
+    /// 已设置的密钥类字段
+    pub secrets_set: Vec<String>,
+    /// 本次修改前的备份文件
+    pub backup_path: Option<String>,
+}
+
+pub(crate) fn iflow_config_dir() -> Result<PathBuf, String> {
+    let home_dir = home_dir().ok_or_else(|| "Cannot resolve home directory".to_string())?;
+    Ok(home_dir.join(".iflow"))
+}
+
+async fn read_config_from_path(path: &Path) -> Result<Option<Map<String, Value>>, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) if content.trim().is_empty() => Ok(Some(Map::new())),
+        Ok(content) => match serde_json::from_str::<Value>(&content) {
+            Ok(Value::Object(map)) => Ok(Some(map)),
+            Ok(_) => Err("Failed to parse iFlow config: not a JSON object".to_string()),
+            Err(e) => Err(format!("Failed to parse iFlow config: {}", e)),
+        },
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read iFlow config: {}", e)),
+    }
+}
+
+fn expect_string(key: &str, value: &Value) -> Result<(), String> {
+    match value {
+        Value::String(_) => Ok(()),
+        _ => Err(format!("Invalid iFlow config: {} must be a string", key)),
+    }
+}
+
+fn validate_mcp_servers(value: &Value) -> Result<(), String> {
+    let servers = value
+        .as_object()
+        .ok_or_else(|| "Invalid iFlow config: mcpServers must be an object".to_string())?;
+    for (name, server) in servers {
+        let server = server.as_object().ok_or_else(|| {
+            format!(
+                "Invalid iFlow config: mcpServers.{} must be an object",
+                name
+            )
+        })?;
+        let has_endpoint = ["command", "url", "httpUrl"]
+            .iter()
+            .any(|key| server.get(*key).and_then(Value::as_str).is_some());
+        if !has_endpoint {
+            return Err(format!(
+                "Invalid iFlow config: mcpServers.{} needs command, url or httpUrl",
+                name
+            ));
+        }
+        if let Some(args) = server.get("args") {
+            let valid = args
+                .as_array()
+                .map(|args| args.iter().all(Value::is_string))
+                .unwrap_or(false);
+            if !valid {
+                return Err(format!(
+                    "Invalid iFlow config: mcpServers.{}.args must be a string array",
+                    name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 校验已知字段的类型与取值；未知字段不做限制
+fn validate_iflow_config(config: &Map<String, Value>) -> Result<(), String> {
+    for (key, value) in config {
+        match key.as_str() {
+            "apiKey" | "searchApiKey" | "modelName" | "theme" => expect_string(key, value)?,
+            "baseUrl" => {
+                expect_string(key, value)?;
+                let raw = value.as_str().unwrap_or_default().trim();
+                if !raw.is_empty() {
+                    let url = url::Url::parse(raw)
+                        .map_err(|e| format!("Invalid iFlow config: baseUrl: {}", e))?;
+                    if !matches!(url.scheme(), "http" | "https") {
+                        return Err("Invalid iFlow config: baseUrl must be http(s)".to_string());
+                    }
+                }
+            }
+            "selectedAuthType" => {
+                expect_string(key, value)?;
+                let auth_type = value.as_str().unwrap_or_default();
+                if !AUTH_TYPES.contains(&auth_type) {
+                    return Err(format!(
+                        "Invalid iFlow config: unknown selectedAuthType {}",
+                        auth_type
+                    ));
+                }
+            }
+            "mcpServers" => validate_mcp_servers(value)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// 合并修改：值为 null 的字段删除；密钥字段为空字符串时保留原值
+fn merge_config(current: &Map<String, Value>, patch: Map<String, Value>) -> Map<String, Value> {
+    let mut merged = current.clone();
+    for (key, value) in patch {
+        let keep_secret =
+            SECRET_KEYS.contains(&key.as_str()) && value.as_str().is_some_and(str::is_empty);
+        if keep_secret {
+            continue;
+        }
+        if value.is_null() {
+            merged.remove(&key);
+        } else {
+            merged.insert(key, value);
+        }
+    }
+    merged
+}
+
+fn to_view(
+    path: &Path,
+    config: Option<Map<String, Value>>,
+    backup: Option<PathBuf>,
+) -> IflowConfig {
+    let exists = config.is_some();
+    let mut config = config.unwrap_or_default();
+    let mut secrets_set = Vec::new();
+    for key in SECRET_KEYS {
+        if let Some(value) = config.remove(*key) {
+            if value.as_str().is_some_and(|secret| !secret.is_empty()) {
+                secrets_set.push(key.to_string());
+            }
+        }
+    }
+    IflowConfig {
+        path: path.to_string_lossy().to_string(),
+        exists,
+        config,
+        secrets_set,
+        backup_path: backup.map(|backup| backup.to_string_lossy().to_string()),
+    }
+}
+
+/// 备份当前配置并只保留最近若干份
+async fn backup_config(dir: &Path, path: &Path) -> Result<PathBuf, String> {
+    let backup_dir = dir.join(BACKUP_DIR);
+    fs::create_dir_all(&backup_dir)
+        .await
+        .map_err(|e| format!("Failed to create iFlow config backup dir: {}", e))?;
+    let backup = backup_dir.join(format!(
+        "settings-{}.json",
+        chrono::Local::now().format("%Y%m%d-%H%M%S%3f")
+    ));
+    fs::copy(path, &backup)
+        .await
+        .map_err(|e| format!("Failed to back up iFlow config: {}", e))?;
+
+    let mut backups = Vec::new();
+    let mut reader = fs::read_dir(&backup_dir)
+        .await
+        .map_err(|e| format!("Failed to read iFlow config backups: {}", e))?;
+    while let Some(entry) = reader
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read iFlow config backups: {}", e))?
+    {
+        backups.push(entry.path());
+    }
+    backups.sort();
+    let excess = backups.len().saturating_sub(MAX_BACKUPS);
+    for old in &backups[..excess] {
+        let _ = fs::remove_file(old).await;
+    }
+    Ok(backup)
+}
+
+async fn update_config_in_dir(
+    dir: &Path,
+    patch: Map<String, Value>,
+) -> Result<IflowConfig, String> {
+    let _guard = CONFIG_WRITE_LOCK.lock().await;
+    let path = dir.join(CONFIG_FILE);
+    let current = read_config_from_path(&path).await?;
+    let merged = merge_config(current.as_ref().unwrap_or(&Map::new()), patch);
+    validate_iflow_config(&merged)?;
+
+    let backup = match current {
+        Some(_) => Some(backup_config(dir, &path).await?),
+        None => None,
+    };
+    fs::create_dir_all(dir)
+        .await
+        .map_err(|e| format!("Failed to create iFlow config dir: {}", e))?;
+    let payload = serde_json::to_vec_pretty(&merged)
+        .map_err(|e| format!("Failed to encode iFlow config: {}", e))?;
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, payload)
+        .await
+        .map_err(|e| format!("Failed to write iFlow config: {}", e))?;
+    fs::rename(&temp, &path)
+        .await
+        .map_err(|e| format!("Failed to write iFlow config: {}", e))?;
+    Ok(to_view(&path, Some(merged), backup))
+}
+
+/// 读取完整配置（含密钥），供后端其他模块使用；文件不存在时为空
+pub(crate) async fn load_iflow_config() -> Result<Option<Map<String, Value>>, String> {
+    read_config_from_path(&iflow_config_dir()?.join(CONFIG_FILE)).await
+}
+
+#[tauri::command]
+pub async fn read_iflow_config() -> Result<IflowConfig, String> {
+    let path = iflow_config_dir()?.join(CONFIG_FILE);
+    let config = read_config_from_path(&path).await?;
+    Ok(to_view(&path, config, None))
+}
+
+/// 修改 iFlow 配置：`patch` 中的字段覆盖原值，null 表示删除；已运行的 iFlow 进程需重新连接后生效
+#[tauri::command]
+pub async fn update_iflow_config(patch: Map<String, Value>) -> Result<IflowConfig, String> {
+    update_config_in_dir(&iflow_config_dir()?, patch).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn object(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().expect("object")
+    }
+
+    #[test]
+    fn config_validation_checks_known_fields_only() {
+        let valid = object(json!({
+            "selectedAuthType": "openai-compatible",
+            "baseUrl": "https://apis.iflow.cn/v1",
+            "mcpServers": { "fs": { "command": "npx", "args": ["server-fs"] } },
+            "customFlag": 42,
+        }));
+        assert!(validate_iflow_config(&valid).is_ok());
+
+        for invalid in [
+            json!({ "baseUrl": "ftp://example.com" }),
+            json!({ "selectedAuthType": "password" }),
+            json!({ "apiKey": 123 }),
+            json!({ "mcpServers": { "fs": { "args": ["x"] } } }),
+            json!({ "mcpServers": { "fs": { "command": "npx", "args": "x" } } }),
+        ] {
+            assert!(validate_iflow_config(&object(invalid)).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn update_merges_backs_up_and_hides_secrets() {
+        let dir = std::env::temp_dir().join(format!("iflow-config-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).await.unwrap();
+        fs::write(
+            dir.join(CONFIG_FILE),
+            r#"{"apiKey":"sk-old","modelName":"qwen","theme":"dark","extra":true}"#,
+        )
+        .await
+        .unwrap();
+
+        let updated = update_config_in_dir(
+            &dir,
+            object(json!({ "apiKey": "", "modelName": "glm-4.6", "theme": null })),
+        )
+        .await
+        .expect("update config");
+        assert_eq!(updated.secrets_set, vec!["apiKey".to_string()]);
+        assert!(!updated.config.contains_key("apiKey"));
+        assert_eq!(updated.config["modelName"], "glm-4.6");
+        assert!(!updated.config.contains_key("theme"));
+        assert_eq!(updated.config["extra"], true);
+
+        let saved = read_config_from_path(&dir.join(CONFIG_FILE))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(saved["apiKey"], "sk-old");
+        let backup = updated.backup_path.expect("backup path");
+        let backup = std::fs::read_to_string(backup).unwrap();
+        assert!(backup.contains("\"theme\":\"dark\""));
+
+        let rejected = update_config_in_dir(&dir, object(json!({ "baseUrl": "not a url" }))).await;
+        assert!(rejected.is_err());
+        let _ = fs::remove_dir_all(&dir).await;
+    }
+}
@@ -0,0 +1,426 @@
+//! 后台任务：耗时命令（清空历史、导出、索引、安装等）立即返回任务句柄，
+//! 执行过程通过 job-progress 事件汇报，可用 cancel_job 协作式取消；
+//! 运行中的任务只在内存中登记，结束后的记录（含结果）持久化到应用数据目录，仅保留最近的若干条。
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{Manager, State};
+use tokio::fs;
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag};
+
+/// 任务因取消而中止时返回的错误
+pub(crate) const JOB_CANCELLED: &str = "Job cancelled";
+
+const MAX_JOB_RECORDS: usize = 200;
+const DEFAULT_JOB_LIST_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobHandle {
+    pub job_id: String,
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRecord {
+    pub job_id: String,
+    pub kind: String,
+    pub status: JobStatus,
+    pub done: usize,
+    pub total: usize,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub result: Option<Value>,
+    #[serde(default)]
+    pub error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    #[serde(default)]
+    pub finished_at: Option<String>,
+}
+
+impl JobRecord {
+    fn new(kind: &str) -> Self {
+        let now = chrono::Utc::now().to_rfc3339();
+        Self {
+            job_id: uuid::Uuid::new_v4().to_string(),
+            kind: kind.to_string(),
+            status: JobStatus::Running,
+            done: 0,
+            total: 0,
+            message: "queued".to_string(),
+            result: None,
+            error: None,
+            created_at: now.clone(),
+            updated_at: now,
+            finished_at: None,
+        }
+    }
+}
+
+struct RunningJob {
+    record: JobRecord,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// 运行中任务的进度与取消标记
+#[derive(Default)]
+pub(crate) struct JobRegistry {
+    jobs: Mutex<HashMap<String, RunningJob>>,
+}
+
+impl JobRegistry {
+    fn register(&self, kind: &str) -> (JobRecord, Arc<AtomicBool>) {
+        let record = JobRecord::new(kind);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.insert(
+                record.job_id.clone(),
+                RunningJob {
+                    record: record.clone(),
+                    cancelled: cancelled.clone(),
+                },
+            );
+        }
+        (record, cancelled)
+    }
+
+    fn update_progress(
+        &self,
+        job_id: &str,
+        done: usize,
+        total: usize,
+        message: &str,
+    ) -> Option<JobRecord> {
+        let mut jobs = self.jobs.lock().ok()?;
+        let record = &mut jobs.get_mut(job_id)?.record;
+        record.done = done;
+        record.total = total;
+        record.message = message.to_string();
+        record.updated_at = chrono::Utc::now().to_rfc3339();
+        Some(record.clone())
+    }
+
+    /// 结束任务并从登记表移除，返回最终记录
+    fn finish(&self, job_id: &str, outcome: Result<Value, String>) -> Option<JobRecord> {
+        let mut record = self.jobs.lock().ok()?.remove(job_id)?.record;
+        let now = chrono::Utc::now().to_rfc3339();
+        match outcome {
+            Ok(result) => {
+                record.status = JobStatus::Completed;
+                record.done = record.total.max(record.done);
+                record.result = Some(result);
+            }
+            Err(error) if error == JOB_CANCELLED => record.status = JobStatus::Cancelled,
+            Err(error) => {
+                record.status = JobStatus::Failed;
+                record.error = Some(error);
+            }
+        }
+        record.updated_at = now.clone();
+        record.finished_at = Some(now);
+        Some(record)
+    }
+
+    fn running(&self) -> Vec<JobRecord> {
+        self.jobs
+            .lock()
+            .map(|jobs| jobs.values().map(|job| job.record.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    fn get(&self, job_id: &str) -> Option<JobRecord> {
+        self.jobs
+            .lock()
+            .ok()?
+            .get(job_id)
+            .map(|job| job.record.clone())
+    }
+
+    pub(crate) fn cancel(&self, job_id: &str) -> bool {
+        let Ok(jobs) = self.jobs.lock() else {
+            return false;
+        };
+        match jobs.get(job_id) {
+            Some(job) => {
+                job.cancelled.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn jobs_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-jobs-{}.json", storage_env_tag())))
+}
+
+async fn read_jobs_from_path(path: &Path) -> Result<Vec<JobRecord>, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(Vec::new());
+            }
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse job records: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(format!("Failed to read job records: {}", err)),
+    }
+}
+
+async fn write_jobs_to_path(path: &Path, records: &[JobRecord]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create job records dir: {}", e))?;
+    }
+    let payload =
+        serde_json::to_vec(records).map_err(|e| format!("Failed to encode job records: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write job records: {}", e))
+}
+
+fn push_capped(records: &mut Vec<JobRecord>, record: JobRecord) {
+    records.push(record);
+    if records.len() > MAX_JOB_RECORDS {
+        let overflow = records.len() - MAX_JOB_RECORDS;
+        records.drain(..overflow);
+    }
+}
+
+async fn persist_job_record(
+    app_handle: &tauri::AppHandle,
+    record: JobRecord,
+) -> Result<(), String> {
+    let state = app_handle.state::<AppState>();
+    let _guard = state.jobs_lock.lock().await;
+    let path = jobs_path(app_handle)?;
+    let mut records = read_jobs_from_path(&path).await?;
+    push_capped(&mut records, record);
+    write_jobs_to_path(&path, &records).await
+}
+
+/// 传给任务体的上下文，可在阻塞线程中使用
+#[derive(Clone)]
+pub(crate) struct JobContext {
+    app_handle: tauri::AppHandle,
+    job_id: String,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl JobContext {
+    pub(crate) fn app_handle(&self) -> &tauri::AppHandle {
+        &self.app_handle
+    }
+
+    /// 已请求取消时返回 Err(JOB_CANCELLED)
+    pub(crate) fn check_cancelled(&self) -> Result<(), String> {
+        if self.cancelled.load(Ordering::SeqCst) {
+            Err(JOB_CANCELLED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn report(&self, done: usize, total: usize, message: &str) {
+        let record = self.app_handle.state::<AppState>().jobs.update_progress(
+            &self.job_id,
+            done,
+            total,
+            message,
+        );
+        if let Some(record) = record {
+            emit_job_progress(&self.app_handle, &record);
+        }
+    }
+}
+
+fn emit_job_progress(app_handle: &tauri::AppHandle, record: &JobRecord) {
+    let _ = app_handle.emit_unless_locked("job-progress", record);
+}
+
+/// 注册并在后台执行任务，结束时推送 completed / failed / cancelled 并持久化记录
+pub(crate) fn spawn_job<F, Fut, T>(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+    kind: &str,
+    run: F,
+) -> JobHandle
+where
+    F: FnOnce(JobContext) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<T, String>> + Send + 'static,
+    T: Serialize,
+{
+    let (record, cancelled) = state.jobs.register(kind);
+    emit_job_progress(app_handle, &record);
+
+    let ctx = JobContext {
+        app_handle: app_handle.clone(),
+        job_id: record.job_id.clone(),
+        cancelled,
+    };
+    tokio::spawn(async move {
+        let outcome = run(ctx.clone())
+            .await
+            .map(|value| serde_json::to_value(value).unwrap_or(Value::Null));
+        let app_handle = ctx.app_handle;
+        let Some(record) = app_handle
+            .state::<AppState>()
+            .jobs
+            .finish(&ctx.job_id, outcome)
+        else {
+            return;
+        };
+        if let Some(error) = record.error.as_deref() {
+            println!(
+                "[jobs] {} job {} failed: {}",
+                record.kind, record.job_id, error
+            );
+        }
+        emit_job_progress(&app_handle, &record);
+        if let Err(e) = persist_job_record(&app_handle, record).await {
+            println!("[jobs] Failed to persist job record: {}", e);
+        }
+    });
+
+    JobHandle {
+        job_id: record.job_id,
+        kind: record.kind,
+    }
+}
+
+/// 列出任务（运行中在前，其余按创建时间倒序），可按类型过滤
+#[tauri::command]
+pub async fn list_jobs(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    kind: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<JobRecord>, String> {
+    let mut running = state.jobs.running();
+    running.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    let finished = {
+        let _guard = state.jobs_lock.lock().await;
+        read_jobs_from_path(&jobs_path(&app_handle)?).await?
+    };
+    let limit = limit.unwrap_or(DEFAULT_JOB_LIST_LIMIT);
+    Ok(running
+        .into_iter()
+        .chain(finished.into_iter().rev())
+        .filter(|record| {
+            kind.as_deref()
+                .map(|kind| record.kind == kind)
+                .unwrap_or(true)
+        })
+        .take(limit)
+        .collect())
+}
+
+#[tauri::command]
+pub async fn get_job(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    job_id: String,
+) -> Result<JobRecord, String> {
+    if let Some(record) = state.jobs.get(&job_id) {
+        return Ok(record);
+    }
+    let _guard = state.jobs_lock.lock().await;
+    read_jobs_from_path(&jobs_path(&app_handle)?)
+        .await?
+        .into_iter()
+        .rev()
+        .find(|record| record.job_id == job_id)
+        .ok_or_else(|| format!("Job {} not found", job_id))
+}
+
+#[tauri::command]
+pub async fn cancel_job(state: State<'_, AppState>, job_id: String) -> Result<bool, String> {
+    Ok(state.jobs.cancel(&job_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_tracks_progress_and_cancellation() {
+        let registry = JobRegistry::default();
+        let (record, flag) = registry.register("export_pdf");
+        assert!(!registry.cancel("missing"));
+        assert!(registry.cancel(&record.job_id));
+        assert!(flag.load(Ordering::SeqCst));
+
+        let updated = registry
+            .update_progress(&record.job_id, 2, 5, "rendering")
+            .expect("running job");
+        assert_eq!((updated.done, updated.total), (2, 5));
+        assert_eq!(registry.running().len(), 1);
+
+        let finished = registry
+            .finish(&record.job_id, Err(JOB_CANCELLED.to_string()))
+            .expect("finish");
+        assert_eq!(finished.status, JobStatus::Cancelled);
+        assert!(finished.finished_at.is_some());
+        assert!(registry.get(&record.job_id).is_none());
+        assert!(!registry.cancel(&record.job_id));
+    }
+
+    #[test]
+    fn finished_jobs_capture_result_or_error() {
+        let registry = JobRegistry::default();
+        let (ok_job, _) = registry.register("clear_history");
+        registry.update_progress(&ok_job.job_id, 1, 3, "moving");
+        let done = registry
+            .finish(&ok_job.job_id, Ok(serde_json::json!({ "files": 3 })))
+            .expect("finish");
+        assert_eq!(done.status, JobStatus::Completed);
+        assert_eq!(done.done, 3);
+        assert_eq!(done.result, Some(serde_json::json!({ "files": 3 })));
+
+        let (failed_job, _) = registry.register("clear_history");
+        let failed = registry
+            .finish(&failed_job.job_id, Err("disk full".to_string()))
+            .expect("finish");
+        assert_eq!(failed.status, JobStatus::Failed);
+        assert_eq!(failed.error.as_deref(), Some("disk full"));
+    }
+
+    #[tokio::test]
+    async fn job_records_roundtrip_and_cap() {
+        let path = std::env::temp_dir().join(format!("iflow-jobs-{}.json", uuid::Uuid::new_v4()));
+        let mut records = Vec::new();
+        for _ in 0..(MAX_JOB_RECORDS + 3) {
+            push_capped(&mut records, JobRecord::new("export_pdf"));
+        }
+        assert_eq!(records.len(), MAX_JOB_RECORDS);
+
+        write_jobs_to_path(&path, &records).await.expect("write");
+        let loaded = read_jobs_from_path(&path).await.expect("read");
+        assert_eq!(loaded, records);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
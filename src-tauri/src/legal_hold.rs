@@ -0,0 +1,366 @@
+//! 合规保全（legal hold）：开启后拒绝删除类命令（iFlow 历史、会话存储、附件回收、档案），
+//! 并把每一次删除尝试写入审计日志（JSON Lines，只追加）。Agent 经 ACP 的文件读写也按会话记入同一日志。
+//! 与能力分组一样，前端只能开启保全，关闭需直接修改设置文件。
+//! 会话存储、设置与审计日志都按档案隔离，保全期间同时禁止创建与切换档案，避免换到未保全的档案绕过。
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, State};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag, StorageSnapshot};
+
+const LEGAL_HOLD_ACTIVE: &str = "Deletion is disabled by legal hold";
+const LEGAL_HOLD_PROFILES: &str = "Profile changes are disabled by legal hold";
+const DEFAULT_AUDIT_LIMIT: usize = 200;
+
+/// 保全期间拒绝的命令
+const DESTRUCTIVE_COMMANDS: &[&str] = &[
+    "delete_iflow_history_session",
+    "clear_iflow_history_sessions",
+    "delete_message",
+    "run_attachment_retention",
+    "delete_profile",
+];
+
+/// 保全期间拒绝的档案命令
+const PROFILE_COMMANDS: &[&str] = &["create_profile", "switch_profile"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub timestamp: String,
+    /// 命令名或后台操作名
+    pub action: String,
+    pub blocked: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Agent 操作所属的 ACP 会话
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+/// 当前是否处于保全；IPC 分发在同步上下文中进行，因此用原子量缓存一份
+#[derive(Default)]
+pub(crate) struct LegalHold {
+    active: AtomicBool,
+}
+
+impl LegalHold {
+    pub(crate) fn apply(&self, active: bool) {
+        self.active.store(active, Ordering::SeqCst);
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.active.load(Ordering::SeqCst)
+    }
+}
+
+fn audit_log_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-audit-{}.jsonl", storage_env_tag())))
+}
+
+async fn append_audit_entry_to_path(path: &Path, entry: &AuditEntry) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create audit log dir: {}", e))?;
+    }
+    let mut line =
+        serde_json::to_string(entry).map_err(|e| format!("Failed to encode audit entry: {}", e))?;
+    line.push('\n');
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .map_err(|e| format!("Failed to open audit log: {}", e))?;
+    file.write_all(line.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write audit log: {}", e))
+}
+
+/// 读取最近的 `limit` 条记录（按时间顺序），跳过无法解析的行
+async fn read_audit_entries_from_path(
+    path: &Path,
+    limit: usize,
+) -> Result<Vec<AuditEntry>, String> {
+    let content = match fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(format!("Failed to read audit log: {}", err)),
+    };
+    let entries: Vec<AuditEntry> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.into_iter().skip(skip).collect())
+}
+
+/// 写入一条审计记录；审计失败只打印日志，不影响调用方
+pub(crate) async fn record_audit(
+    app_handle: &tauri::AppHandle,
+    action: &str,
+    blocked: bool,
+    detail: Option<String>,
+) {
+    append_audit(app_handle, action, blocked, detail, None).await;
+}
+
+/// 记录 Agent 在某个 ACP 会话中的操作（如 fs 读写）
+pub(crate) async fn record_session_audit(
+    app_handle: &tauri::AppHandle,
+    session_id: &str,
+    action: &str,
+    blocked: bool,
+    detail: Option<String>,
+) {
+    let session_id = Some(session_id.to_string()).filter(|id| !id.is_empty());
+    append_audit(app_handle, action, blocked, detail, session_id).await;
+}
+
+async fn append_audit(
+    app_handle: &tauri::AppHandle,
+    action: &str,
+    blocked: bool,
+    detail: Option<String>,
+    session_id: Option<String>,
+) {
+    let entry = AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        action: action.to_string(),
+        blocked,
+        detail,
+        session_id,
+    };
+    let state = app_handle.state::<AppState>();
+    let _guard = state.audit_lock.lock().await;
+    let result = match audit_log_path(app_handle) {
+        Ok(path) => append_audit_entry_to_path(&path, &entry).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        println!("[legal-hold] Failed to record {}: {}", action, e);
+    }
+}
+
+/// 命令分发前调用：保全期间拒绝删除类命令并记录尝试
+pub(crate) fn check_destructive_command(
+    app_handle: &tauri::AppHandle,
+    command: &str,
+) -> Result<(), String> {
+    let Some(error) = blocked_by_legal_hold(command) else {
+        return Ok(());
+    };
+    if !app_handle.state::<AppState>().legal_hold.is_active() {
+        return Ok(());
+    }
+    let app_handle = app_handle.clone();
+    let action = command.to_string();
+    tauri::async_runtime::spawn(async move {
+        record_audit(&app_handle, &action, true, None).await;
+    });
+    Err(error.to_string())
+}
+
+/// 保全期间该命令被拒绝时的错误信息
+fn blocked_by_legal_hold(command: &str) -> Option<&'static str> {
+    if DESTRUCTIVE_COMMANDS.contains(&command) {
+        Some(LEGAL_HOLD_ACTIVE)
+    } else if PROFILE_COMMANDS.contains(&command) {
+        Some(LEGAL_HOLD_PROFILES)
+    } else {
+        None
+    }
+}
+
+/// 新快照相对旧快照丢失的会话数与消息数
+fn removed_records(current: &StorageSnapshot, next: &StorageSnapshot) -> (usize, usize) {
+    let next_sessions: HashSet<&str> = next
+        .sessions_by_agent
+        .values()
+        .flatten()
+        .map(|session| session.id.as_str())
+        .collect();
+    let removed_sessions = current
+        .sessions_by_agent
+        .values()
+        .flatten()
+        .filter(|session| !next_sessions.contains(session.id.as_str()))
+        .count();
+
+    let next_messages: HashSet<&str> = next
+        .messages_by_session
+        .values()
+        .flatten()
+        .map(|message| message.id.as_str())
+        .collect();
+    let removed_messages = current
+        .messages_by_session
+        .values()
+        .flatten()
+        .filter(|message| !next_messages.contains(message.id.as_str()))
+        .count();
+    (removed_sessions, removed_messages)
+}
+
+/// 保存会话存储前调用：保全期间拒绝会删除会话或消息的快照
+pub(crate) async fn check_snapshot_retained(
+    app_handle: &tauri::AppHandle,
+    current: &StorageSnapshot,
+    next: &StorageSnapshot,
+) -> Result<(), String> {
+    if !app_handle.state::<AppState>().legal_hold.is_active() {
+        return Ok(());
+    }
+    let (sessions, messages) = removed_records(current, next);
+    if sessions == 0 && messages == 0 {
+        return Ok(());
+    }
+    record_audit(
+        app_handle,
+        "save_storage_snapshot",
+        true,
+        Some(format!(
+            "would remove {} sessions and {} messages",
+            sessions, messages
+        )),
+    )
+    .await;
+    Err(LEGAL_HOLD_ACTIVE.to_string())
+}
+
+/// 某个 ACP 会话的全部审计记录（按时间顺序）
+pub(crate) async fn session_audit_entries(
+    app_handle: &tauri::AppHandle,
+    session_id: &str,
+) -> Result<Vec<AuditEntry>, String> {
+    let state = app_handle.state::<AppState>();
+    let _guard = state.audit_lock.lock().await;
+    let entries = read_audit_entries_from_path(&audit_log_path(app_handle)?, usize::MAX).await?;
+    Ok(entries
+        .into_iter()
+        .filter(|entry| entry.session_id.as_deref() == Some(session_id))
+        .collect())
+}
+
+#[tauri::command]
+pub async fn list_audit_entries(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<AuditEntry>, String> {
+    let _guard = state.audit_lock.lock().await;
+    read_audit_entries_from_path(
+        &audit_log_path(&app_handle)?,
+        limit.unwrap_or(DEFAULT_AUDIT_LIMIT),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{StoredMessage, StoredSession};
+
+    #[test]
+    fn removed_records_counts_dropped_sessions_and_messages() {
+        let mut current = StorageSnapshot::default();
+        current.sessions_by_agent.insert(
+            "agent-a".to_string(),
+            vec![
+                StoredSession {
+                    id: "s1".to_string(),
+                    ..Default::default()
+                },
+                StoredSession {
+                    id: "s2".to_string(),
+                    ..Default::default()
+                },
+            ],
+        );
+        current.messages_by_session.insert(
+            "s1".to_string(),
+            vec![
+                StoredMessage {
+                    id: "m1".to_string(),
+                    ..Default::default()
+                },
+                StoredMessage {
+                    id: "m2".to_string(),
+                    ..Default::default()
+                },
+            ],
+        );
+
+        let mut next = current.clone();
+        assert_eq!(removed_records(&current, &next), (0, 0));
+        next.messages_by_session
+            .get_mut("s1")
+            .unwrap()
+            .push(StoredMessage {
+                id: "m3".to_string(),
+                ..Default::default()
+            });
+        assert_eq!(removed_records(&current, &next), (0, 0));
+
+        next.sessions_by_agent.get_mut("agent-a").unwrap().pop();
+        next.messages_by_session.get_mut("s1").unwrap().remove(0);
+        assert_eq!(removed_records(&current, &next), (1, 1));
+    }
+
+    #[test]
+    fn profile_changes_are_blocked_with_deletions() {
+        assert_eq!(
+            blocked_by_legal_hold("delete_profile"),
+            Some(LEGAL_HOLD_ACTIVE)
+        );
+        assert_eq!(
+            blocked_by_legal_hold("switch_profile"),
+            Some(LEGAL_HOLD_PROFILES)
+        );
+        assert_eq!(
+            blocked_by_legal_hold("create_profile"),
+            Some(LEGAL_HOLD_PROFILES)
+        );
+        assert_eq!(blocked_by_legal_hold("list_profiles"), None);
+    }
+
+    #[tokio::test]
+    async fn audit_log_appends_and_reads_latest() {
+        let path = std::env::temp_dir()
+            .join(format!("iflow-audit-{}", uuid::Uuid::new_v4()))
+            .join("audit.jsonl");
+        assert!(read_audit_entries_from_path(&path, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        for action in [
+            "delete_message",
+            "delete_profile",
+            "clear_iflow_history_sessions",
+        ] {
+            let entry = AuditEntry {
+                timestamp: "2026-01-01T00:00:00Z".to_string(),
+                action: action.to_string(),
+                blocked: true,
+                detail: None,
+                session_id: None,
+            };
+            append_audit_entry_to_path(&path, &entry).await.unwrap();
+        }
+
+        let latest = read_audit_entries_from_path(&path, 2).await.unwrap();
+        let actions: Vec<&str> = latest.iter().map(|entry| entry.action.as_str()).collect();
+        assert_eq!(
+            actions,
+            vec!["delete_profile", "clear_iflow_history_sessions"]
+        );
+    }
+}
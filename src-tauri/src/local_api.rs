@@ -0,0 +1,375 @@
+//! 本地回调接口：仅监听 127.0.0.1 的极简 HTTP 服务，供 Git 钩子、编辑器插件等外部程序回调 FlowHub。
+//! 每次启动使用随机端口与随机令牌，写入应用数据目录的 `local-api.json`（仅当前用户可读），
+//! 请求须带 `Authorization: Bearer <token>`。路由不经过 IPC 分发，
+//! 按对应的命令名同样检查应用锁与能力策略。
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::Manager;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::app_lock::{constant_time_eq, APP_LOCKED};
+use crate::editor_bridge::handle_editor_request;
+use crate::git_hooks::handle_git_review_request;
+use crate::state::AppState;
+use crate::storage::base_app_data_dir;
+
+const LOCAL_API_FILE: &str = "local-api.json";
+const EDITOR_PATH_PREFIX: &str = "/v1/editor/";
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LocalApiInfo {
+    pub port: u16,
+    pub token: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct LocalApiRequest {
+    pub method: String,
+    pub path: String,
+    pub token: Option<String>,
+    pub body: Vec<u8>,
+}
+
+pub(crate) struct LocalApiResponse {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl LocalApiResponse {
+    pub(crate) fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    pub(crate) fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+}
+
+pub(crate) fn local_api_info_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(base_app_data_dir(app_handle)?.join(LOCAL_API_FILE))
+}
+
+fn find_header_end(buffer: &[u8]) -> Option<usize> {
+    buffer.windows(4).position(|window| window == b"\r\n\r\n")
+}
+
+/// 解析请求行与头部；返回请求与声明的正文长度
+fn parse_request_head(head: &str) -> Result<(LocalApiRequest, usize), String> {
+    let mut lines = head.split("\r\n");
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err("Malformed request line".to_string());
+    };
+    let mut token = None;
+    let mut content_length = 0;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "authorization" => {
+                token = value
+                    .strip_prefix("Bearer ")
+                    .map(|token| token.trim().to_string());
+            }
+            "content-length" => {
+                content_length = value
+                    .parse::<usize>()
+                    .map_err(|_| "Invalid Content-Length".to_string())?;
+            }
+            _ => {}
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return Err("Request body is too large".to_string());
+    }
+    Ok((
+        LocalApiRequest {
+            method: method.to_string(),
+            path: path.split('?').next().unwrap_or(path).to_string(),
+            token,
+            body: Vec::new(),
+        },
+        content_length,
+    ))
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<LocalApiRequest, String> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        if let Some(end) = find_header_end(&buffer) {
+            break end;
+        }
+        if buffer.len() > MAX_HEADER_BYTES {
+            return Err("Request headers are too large".to_string());
+        }
+        let read = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|e| format!("Failed to read request: {}", e))?;
+        if read == 0 {
+            return Err("Connection closed".to_string());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let (mut request, content_length) = parse_request_head(&head)?;
+    let mut body = buffer.split_off(header_end + 4);
+    while body.len() < content_length {
+        let read = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|e| format!("Failed to read request: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(content_length);
+    request.body = body;
+    Ok(request)
+}
+
+async fn write_response(stream: &mut TcpStream, response: LocalApiResponse) {
+    let body = response.body.to_string();
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        423 => "Locked",
+        500 => "Internal Server Error",
+        _ => "Error",
+    };
+    let payload = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(payload.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// 路由对应的命令名，用于能力策略检查（见 `command_capability`）
+fn route_command(method: &str, path: &str) -> &'static str {
+    match (method, path) {
+        ("POST", "/v1/git-review") => "local_api_git_review",
+        ("POST", path) if path.starts_with(EDITOR_PATH_PREFIX) => "local_api_editor",
+        _ => "local_api",
+    }
+}
+
+/// 上锁时拒绝全部路由（不刷新空闲计时），能力分组被禁用时拒绝对应路由
+fn check_route_guards(
+    app_handle: &tauri::AppHandle,
+    command: &str,
+) -> Result<(), LocalApiResponse> {
+    let state = app_handle.state::<AppState>();
+    if state.app_lock.is_locked(Instant::now()) {
+        return Err(LocalApiResponse::error(423, APP_LOCKED));
+    }
+    state
+        .capability_policy
+        .check_command(command)
+        .map_err(|e| LocalApiResponse::error(403, &e))
+}
+
+async fn route(app_handle: &tauri::AppHandle, request: LocalApiRequest) -> LocalApiResponse {
+    let command = route_command(&request.method, &request.path);
+    if let Err(response) = check_route_guards(app_handle, command) {
+        return response;
+    }
+    let body = if request.body.is_empty() {
+        Value::Null
+    } else {
+        match serde_json::from_slice::<Value>(&request.body) {
+            Ok(body) => body,
+            Err(e) => return LocalApiResponse::error(400, &format!("Invalid JSON body: {}", e)),
+        }
+    };
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/v1/ping") => LocalApiResponse::ok(json!({
+            "name": "flowhub",
+            "version": env!("CARGO_PKG_VERSION"),
+        })),
+        ("POST", "/v1/git-review") => handle_git_review_request(app_handle, body).await,
+        ("POST", path) if path.starts_with(EDITOR_PATH_PREFIX) => {
+            handle_editor_request(app_handle, &path[EDITOR_PATH_PREFIX.len()..], body).await
+        }
+        _ => LocalApiResponse::error(404, "Not found"),
+    }
+}
+
+/// 令牌按常量时间比较，避免按响应耗时逐字节猜出令牌
+fn token_matches(provided: Option<&str>, token: &str) -> bool {
+    provided.is_some_and(|provided| constant_time_eq(provided.as_bytes(), token.as_bytes()))
+}
+
+async fn handle_connection(app_handle: tauri::AppHandle, token: String, mut stream: TcpStream) {
+    let response = match read_request(&mut stream).await {
+        Ok(request) if !token_matches(request.token.as_deref(), &token) => {
+            LocalApiResponse::error(401, "Unauthorized")
+        }
+        Ok(request) => route(&app_handle, request).await,
+        Err(e) => LocalApiResponse::error(400, &e),
+    };
+    write_response(&mut stream, response).await;
+}
+
+async fn write_info_file(path: &Path, info: &LocalApiInfo) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create local API dir: {}", e))?;
+    }
+    let payload =
+        serde_json::to_vec(info).map_err(|e| format!("Failed to encode local API info: {}", e))?;
+    // 权限只在创建时生效：先删掉上次的文件，再以 0600 新建，令牌不会有可被他人读取的窗口
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to replace local API info: {}", e)),
+    }
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options
+        .open(path)
+        .await
+        .map_err(|e| format!("Failed to create local API info: {}", e))?;
+    file.write_all(&payload)
+        .await
+        .map_err(|e| format!("Failed to write local API info: {}", e))?;
+    Ok(())
+}
+
+/// 启动本地回调接口；失败时只记录日志，不影响应用其他功能
+pub(crate) fn start_local_api(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let listener = match TcpListener::bind("127.0.0.1:0").await {
+            Ok(listener) => listener,
+            Err(e) => {
+                println!("[local-api] Failed to bind: {}", e);
+                return;
+            }
+        };
+        let port = match listener.local_addr() {
+            Ok(addr) => addr.port(),
+            Err(e) => {
+                println!("[local-api] Failed to read local address: {}", e);
+                return;
+            }
+        };
+        let info = LocalApiInfo {
+            port,
+            token: uuid::Uuid::new_v4().simple().to_string(),
+        };
+        let written = match local_api_info_path(&app_handle) {
+            Ok(path) => write_info_file(&path, &info).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            println!("[local-api] {}", e);
+            return;
+        }
+        println!("[local-api] Listening on 127.0.0.1:{}", port);
+
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_connection(
+                        app_handle.clone(),
+                        info.token.clone(),
+                        stream,
+                    ));
+                }
+                Err(e) => println!("[local-api] Failed to accept connection: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capabilities::{CapabilityPolicy, CommandCapability};
+
+    #[test]
+    fn request_head_is_parsed_with_token_and_length() {
+        let head = "POST /v1/git-review?x=1 HTTP/1.1\r\nHost: 127.0.0.1\r\nAuthorization: Bearer abc123\r\ncontent-length: 42";
+        let (request, length) = parse_request_head(head).expect("parse head");
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/v1/git-review");
+        assert_eq!(request.token.as_deref(), Some("abc123"));
+        assert!(token_matches(request.token.as_deref(), "abc123"));
+        assert!(!token_matches(request.token.as_deref(), "abc124"));
+        assert!(!token_matches(None, "abc123"));
+        assert_eq!(length, 42);
+
+        assert!(parse_request_head("GARBAGE").is_err());
+        let oversized = format!("POST / HTTP/1.1\r\nContent-Length: {}", MAX_BODY_BYTES + 1);
+        assert!(parse_request_head(&oversized).is_err());
+        assert_eq!(find_header_end(b"GET / HTTP/1.1\r\n\r\nbody"), Some(14));
+    }
+
+    #[test]
+    fn prompting_routes_follow_process_capability() {
+        let policy = CapabilityPolicy::default();
+        policy.apply(&[CommandCapability::Process]);
+        for (method, path) in [
+            ("POST", "/v1/git-review"),
+            ("POST", "/v1/editor/send-selection"),
+        ] {
+            assert!(policy.check_command(route_command(method, path)).is_err());
+        }
+        assert!(policy
+            .check_command(route_command("GET", "/v1/ping"))
+            .is_ok());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn info_file_is_created_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("local-api-{}", uuid::Uuid::new_v4()));
+        let path = dir.join(LOCAL_API_FILE);
+        let info = LocalApiInfo {
+            port: 1,
+            token: "old".to_string(),
+        };
+        write_info_file(&path, &info).await.expect("write info");
+        let info = LocalApiInfo {
+            port: 2,
+            token: "new".to_string(),
+        };
+        write_info_file(&path, &info).await.expect("rewrite info");
+
+        let mode = std::fs::metadata(&path)
+            .expect("metadata")
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let stored: LocalApiInfo =
+            serde_json::from_slice(&std::fs::read(&path).expect("read info")).expect("decode");
+        assert_eq!(stored, info);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -3,62 +3,344 @@
 
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 
 use tauri::Manager;
 
+mod acp_trace;
+mod activity;
 mod agents;
+mod annotations;
+mod app_lock;
 mod artifact;
+mod artifact_csp;
+mod artifact_sync;
+mod attachments;
+mod capabilities;
 mod commands;
+mod credentials_check;
+mod database;
+mod diagnostics;
+mod diagram;
 mod dialog;
+mod editor_bridge;
+mod email_report;
+mod export;
+mod generation;
 mod git;
+mod git_hooks;
+mod gitignore;
+mod handoff;
+mod highlight;
 mod history;
+mod history_trash;
+mod history_watch;
+mod html_sanitize;
+mod iflow_auth;
+mod iflow_config;
+mod jobs;
+mod legal_hold;
+mod local_api;
 mod manager;
+mod mcp_bridge;
+mod memory_guard;
+mod message_delete;
 mod model_resolver;
+mod model_switch;
 mod models;
+mod notify_channels;
+mod path_display;
+mod permissions;
+mod plugins;
+mod postprocess;
+mod profiles;
+mod progress;
+mod prompt_batch;
+mod prompt_rules;
+mod prompt_split;
+mod quiet_hours;
+mod read_receipts;
+mod remote_storage;
+mod reply_language;
+mod result_output;
+mod retry;
 mod router;
+mod routines;
 mod runtime_env;
+mod scoreboard;
+mod scratch;
+mod secrets;
+mod session_fork;
+mod session_freeze;
+mod session_graph;
+mod session_import;
+mod session_share;
+mod session_title;
+mod settings;
 mod state;
 mod storage;
+mod stream_wal;
+mod suggestions;
+mod support_bundle;
+mod table_preview;
+mod tasks;
+mod team_sync;
+mod telemetry;
+mod tokens;
+mod updater;
+mod verification;
+mod wsl_path;
 
-use artifact::{read_html_artifact, resolve_html_artifact_path};
+use activity::get_activity_heatmap;
+use agents::autostart::get_auto_start_status;
+use agents::registry::restore_agents;
+use annotations::get_file_annotations;
+use app_lock::{get_app_lock_status, lock_app, set_app_lock_password, unlock_app};
+use artifact::{read_html_artifact, read_notebook_artifact, resolve_html_artifact_path};
+use artifact_sync::publish_artifacts;
+use attachments::{import_attachment, paste_clipboard_image, run_attachment_retention};
 use commands::{
-    connect_iflow, discover_skills, disconnect_agent, send_message, shutdown_all_agents, stop_message,
-    switch_agent_model, toggle_agent_think,
+    connect_claude, connect_gemini, connect_iflow, connect_remote_agent, disconnect_agent,
+    discover_skills, list_agent_sessions, pause_agent, resume_agent, send_message,
+    set_permission_mode, shutdown_all_agents, stop_message, switch_agent_model,
+    switch_agent_session, toggle_agent_think,
 };
+use credentials_check::check_provider_credentials;
+use database::query_database;
+use diagnostics::run_diagnostics;
+use diagram::render_diagram;
 use dialog::pick_folder;
+use editor_bridge::push_editor_patch;
+use email_report::send_report_email;
+use export::export_conversation_pdf;
 use git::{list_git_changes, load_git_file_diff};
+use git_hooks::{install_git_hook, resolve_git_review, uninstall_git_hook};
+use gitignore::ensure_gitignore_entries;
+use handoff::handoff_session;
+use highlight::{highlight_code, list_highlight_themes};
 use history::{
     clear_iflow_history_sessions, delete_iflow_history_session, list_iflow_history_sessions,
-    load_iflow_history_messages,
+    load_iflow_history_entries, load_iflow_history_messages, search_iflow_history,
 };
+use history_trash::undo_last_clear;
+use history_watch::{unwatch_iflow_history, watch_iflow_history};
+use iflow_auth::authenticate_iflow;
+use iflow_config::{read_iflow_config, update_iflow_config};
+use jobs::{cancel_job, get_job, list_jobs};
+use legal_hold::{check_destructive_command, list_audit_entries};
+use message_delete::delete_message;
 use model_resolver::list_available_models;
+use notify_channels::{
+    configure_notification_channel, list_notification_channels, notify_budget_alert,
+    remove_notification_channel,
+};
+use permissions::{
+    add_permission_rule, list_permission_rules, respond_file_write, respond_permission,
+    revoke_permission_rule,
+};
+use plugins::{install_plugin, invoke_plugin_action, list_plugins, remove_plugin};
+use profiles::{create_profile, delete_profile, list_profiles, switch_profile};
+use prompt_batch::submit_prompt_batch;
+use prompt_rules::{
+    delete_prompt_rule, list_prompt_rules, preview_prompt_preprocessing, save_prompt_rule,
+};
+use quiet_hours::get_quiet_hours_status;
+use read_receipts::{get_unread_counts, mark_session_read};
+use routines::{delete_routine, list_routines, run_routine, save_routine};
+use scoreboard::{get_model_scoreboard, rate_result};
+use scratch::get_scratch_dir;
+use secrets::{delete_secret, list_secret_names, set_secret};
+use session_fork::fork_session;
+use session_freeze::{freeze_session, verify_session_snapshot};
+use session_graph::get_session_graph;
+use session_import::import_session_jsonl;
+use session_share::{open_shared_session, share_session_encrypted};
+use settings::{get_app_settings, list_settings_history, revert_settings, update_app_settings};
 use state::AppState;
 use storage::{load_storage_snapshot, save_storage_snapshot};
+use stream_wal::replay_interrupted_messages;
+use suggestions::suggest_prompts;
+use support_bundle::create_support_bundle;
+use table_preview::preview_table_file;
+use tasks::list_task_records;
+use team_sync::sync_now;
+use telemetry::{get_telemetry_preview, upload_telemetry};
+use tokens::{estimate_prompt, trim_to_token_budget};
+use updater::{check_for_updates, download_update, fetch_release_notes};
+
+/// 命令分发前拦截：能力分组策略禁用的命令、应用锁定期间受保护的命令、合规保全期间的删除命令
+fn with_command_guards(
+    handler: impl Fn(tauri::ipc::Invoke) -> bool + Send + Sync + 'static,
+) -> impl Fn(tauri::ipc::Invoke) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let command = invoke.message.command();
+        let allowed = {
+            let webview = invoke.message.webview();
+            let state = webview.state::<AppState>();
+            state
+                .capability_policy
+                .check_command(command)
+                .and_then(|_| state.app_lock.check_command(command, Instant::now()))
+                .and_then(|_| check_destructive_command(webview.app_handle(), command))
+        };
+        if let Err(error) = allowed {
+            invoke.resolver.reject(error);
+            return true;
+        }
+        telemetry::record_feature_use(command);
+        handler(invoke)
+    }
+}
 
 fn main() {
+    if let Some(exit_code) = mcp_bridge::run_from_args() {
+        std::process::exit(exit_code);
+    }
+
     let app = tauri::Builder::default()
         .manage(AppState::default())
-        .invoke_handler(tauri::generate_handler![
+        .setup(|app| {
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::block_on(profiles::init_profiles(&app_handle));
+            tauri::async_runtime::block_on(settings::init_settings(&app_handle));
+            tauri::async_runtime::block_on(telemetry::init_telemetry(&app_handle));
+            tauri::async_runtime::block_on(plugins::init_plugins(&app_handle));
+            tauri::async_runtime::block_on(app_lock::init_app_lock(&app_handle));
+            local_api::start_local_api(app_handle.clone());
+            attachments::spawn_attachment_retention(app_handle.clone());
+            scratch::spawn_scratch_retention(app_handle.clone());
+            history_trash::spawn_history_trash_purge(app_handle.clone());
+            agents::autostart::spawn_auto_start(app_handle.clone());
+            Ok(())
+        })
+        .invoke_handler(with_command_guards(tauri::generate_handler![
             connect_iflow,
+            connect_claude,
+            connect_gemini,
+            connect_remote_agent,
+            restore_agents,
+            get_auto_start_status,
             send_message,
             stop_message,
             switch_agent_model,
             toggle_agent_think,
+            set_permission_mode,
+            list_agent_sessions,
+            switch_agent_session,
+            pause_agent,
+            resume_agent,
             list_available_models,
             list_iflow_history_sessions,
             load_iflow_history_messages,
+            load_iflow_history_entries,
+            watch_iflow_history,
+            unwatch_iflow_history,
+            search_iflow_history,
             delete_iflow_history_session,
             clear_iflow_history_sessions,
             list_git_changes,
             load_git_file_diff,
+            suggest_prompts,
             resolve_html_artifact_path,
             read_html_artifact,
+            read_notebook_artifact,
+            preview_table_file,
             disconnect_agent,
             load_storage_snapshot,
             save_storage_snapshot,
+            delete_message,
+            list_audit_entries,
+            get_telemetry_preview,
+            upload_telemetry,
             pick_folder,
             discover_skills,
-        ])
+            set_secret,
+            delete_secret,
+            list_secret_names,
+            query_database,
+            render_diagram,
+            export_conversation_pdf,
+            paste_clipboard_image,
+            import_attachment,
+            run_attachment_retention,
+            get_app_settings,
+            update_app_settings,
+            list_settings_history,
+            revert_settings,
+            list_permission_rules,
+            add_permission_rule,
+            revoke_permission_rule,
+            respond_permission,
+            respond_file_write,
+            handoff_session,
+            fork_session,
+            import_session_jsonl,
+            get_scratch_dir,
+            freeze_session,
+            verify_session_snapshot,
+            list_task_records,
+            list_prompt_rules,
+            save_prompt_rule,
+            delete_prompt_rule,
+            preview_prompt_preprocessing,
+            list_plugins,
+            install_plugin,
+            remove_plugin,
+            invoke_plugin_action,
+            highlight_code,
+            list_highlight_themes,
+            trim_to_token_budget,
+            estimate_prompt,
+            undo_last_clear,
+            cancel_job,
+            list_jobs,
+            get_job,
+            get_app_lock_status,
+            set_app_lock_password,
+            unlock_app,
+            lock_app,
+            list_profiles,
+            create_profile,
+            switch_profile,
+            delete_profile,
+            sync_now,
+            share_session_encrypted,
+            open_shared_session,
+            publish_artifacts,
+            send_report_email,
+            list_notification_channels,
+            configure_notification_channel,
+            remove_notification_channel,
+            notify_budget_alert,
+            get_quiet_hours_status,
+            get_activity_heatmap,
+            replay_interrupted_messages,
+            run_diagnostics,
+            create_support_bundle,
+            check_for_updates,
+            fetch_release_notes,
+            download_update,
+            read_iflow_config,
+            update_iflow_config,
+            authenticate_iflow,
+            check_provider_credentials,
+            rate_result,
+            get_model_scoreboard,
+            mark_session_read,
+            get_unread_counts,
+            list_routines,
+            save_routine,
+            delete_routine,
+            run_routine,
+            submit_prompt_batch,
+            install_git_hook,
+            uninstall_git_hook,
+            ensure_gitignore_entries,
+            resolve_git_review,
+            push_editor_patch,
+            get_file_annotations,
+            get_session_graph,
+        ]))
         .build(tauri::generate_context!())
         .expect("error while building tauri application");
 
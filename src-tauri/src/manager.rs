@@ -1,9 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use tokio::sync::RwLock;
 
-use crate::models::MessageSender;
+use crate::history::normalize_workspace_path;
+use crate::models::{AgentStatus, MessageSender, PermissionMode};
 use crate::state::AgentInstance;
 
 #[derive(Clone)]
@@ -55,10 +56,111 @@ impl AgentManager {
         agents.get(agent_id).map(|instance| instance.port)
     }
 
+    pub async fn set_acp_session(&self, agent_id: &str, session_id: Option<String>) {
+        let mut agents = self.agents.write().await;
+        if let Some(instance) = agents.get_mut(agent_id) {
+            instance.acp_session_id = session_id;
+        }
+    }
+
+    /// 所有在线 Agent 当前绑定的 ACP 会话
+    pub async fn active_acp_sessions(&self) -> HashSet<String> {
+        let agents = self.agents.read().await;
+        agents
+            .values()
+            .filter_map(|instance| instance.acp_session_id.clone())
+            .collect()
+    }
+
+    /// 启动 Agent 时指定的模型；未指定时使用 iFlow 默认模型
+    pub async fn model_of(&self, agent_id: &str) -> Option<String> {
+        let agents = self.agents.read().await;
+        agents
+            .get(agent_id)
+            .and_then(|instance| instance.model.clone())
+    }
+
+    /// 更新 Agent 状态，返回变化前的状态；Agent 不存在或状态未变时返回 None
+    pub async fn set_status(&self, agent_id: &str, status: AgentStatus) -> Option<AgentStatus> {
+        let mut agents = self.agents.write().await;
+        let instance = agents.get_mut(agent_id)?;
+        let previous = instance.info.status;
+        if previous == status {
+            return None;
+        }
+        instance.info.status = status;
+        Some(previous)
+    }
+
+    /// 运行中切换模型成功后更新记录，供下次切换时作为切换前的模型
+    pub async fn set_model(&self, agent_id: &str, model: Option<String>) {
+        let mut agents = self.agents.write().await;
+        if let Some(instance) = agents.get_mut(agent_id) {
+            instance.model = model;
+        }
+    }
+
+    pub async fn permission_mode_of(&self, agent_id: &str) -> Option<PermissionMode> {
+        let agents = self.agents.read().await;
+        agents
+            .get(agent_id)
+            .map(|instance| instance.permission_mode)
+    }
+
+    pub async fn set_permission_mode(&self, agent_id: &str, mode: PermissionMode) {
+        let mut agents = self.agents.write().await;
+        if let Some(instance) = agents.get_mut(agent_id) {
+            instance.permission_mode = mode;
+        }
+    }
+
+    pub async fn acp_session_of(&self, agent_id: &str) -> Option<String> {
+        let agents = self.agents.read().await;
+        agents
+            .get(agent_id)
+            .and_then(|instance| instance.acp_session_id.clone())
+    }
+
+    pub async fn agent_type_of(&self, agent_id: &str) -> Option<String> {
+        let agents = self.agents.read().await;
+        agents
+            .get(agent_id)
+            .map(|instance| instance.info.agent_type.clone())
+    }
+
     pub async fn workspace_path_of(&self, agent_id: &str) -> Option<String> {
         let agents = self.agents.read().await;
         agents
             .get(agent_id)
             .map(|instance| instance.info.workspace_path.clone())
     }
+
+    /// 工作区包含该文件的在线 Agent（多个匹配时取最内层工作区），返回 Agent ID 与工作区路径
+    pub async fn agent_for_path(&self, file_path: &str) -> Option<(String, String)> {
+        let normalized = normalize_workspace_path(file_path);
+        let agents = self.agents.read().await;
+        agents
+            .iter()
+            .filter(|(_, instance)| instance.message_sender.is_some())
+            .filter_map(|(agent_id, instance)| {
+                let workspace = normalize_workspace_path(&instance.info.workspace_path);
+                let inside = normalized == workspace
+                    || normalized.starts_with(&format!("{}/", workspace.trim_end_matches('/')));
+                inside.then(|| (agent_id.clone(), instance.info.workspace_path.clone()))
+            })
+            .max_by_key(|(_, workspace)| workspace.len())
+    }
+
+    /// 工作在指定目录的任一在线 Agent
+    pub async fn agent_for_workspace(&self, workspace_path: &str) -> Option<String> {
+        let normalized = normalize_workspace_path(workspace_path);
+        let agents = self.agents.read().await;
+        agents
+            .iter()
+            .find(|(_, instance)| {
+                instance.message_sender.is_some()
+                    && normalize_workspace_path(&instance.info.workspace_path) == normalized
+            })
+            .map(|(agent_id, _)| agent_id.clone())
+    }
 }
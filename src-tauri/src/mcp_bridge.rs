@@ -0,0 +1,198 @@
+//! 内置 MCP stdio 服务端：iFlow 通过 session/new 的 mcpServers 以子进程方式拉起本程序，
+//! 从而把 FlowHub 后端能力（只读数据库查询）暴露为 Agent 可调用的工具。
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use crate::database::{execute_read_only_query, DATABASE_URL_ENV};
+
+pub(crate) const MCP_QUERY_DATABASE_FLAG: &str = "--mcp-query-database";
+const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+
+fn query_database_tool_schema() -> Value {
+    json!({
+        "name": "query_database",
+        "description": "Run a single read-only SQL statement (SELECT/WITH/EXPLAIN) against the workspace database and return tabular results.",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "sql": {
+                    "type": "string",
+                    "description": "Read-only SQL statement",
+                },
+                "maxRows": {
+                    "type": "integer",
+                    "description": "Maximum rows to return (default 200)",
+                },
+            },
+            "required": ["sql"],
+        },
+    })
+}
+
+fn tool_text_result(text: String, is_error: bool) -> Value {
+    json!({
+        "content": [{
+            "type": "text",
+            "text": text,
+        }],
+        "isError": is_error,
+    })
+}
+
+async fn call_tool(database_url: &str, params: &Value) -> Value {
+    let name = params
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if name != "query_database" {
+        return tool_text_result(format!("Unknown tool: {}", name), true);
+    }
+
+    let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
+    let Some(sql) = arguments.get("sql").and_then(Value::as_str) else {
+        return tool_text_result("Missing sql argument".to_string(), true);
+    };
+    let max_rows = arguments
+        .get("maxRows")
+        .and_then(Value::as_u64)
+        .map(|value| value as usize);
+
+    match execute_read_only_query(database_url, sql, max_rows).await {
+        Ok(result) => match serde_json::to_string(&result) {
+            Ok(text) => tool_text_result(text, false),
+            Err(e) => tool_text_result(format!("Failed to encode result: {}", e), true),
+        },
+        Err(e) => tool_text_result(e, true),
+    }
+}
+
+async fn handle_mcp_request(
+    database_url: &str,
+    method: &str,
+    params: &Value,
+) -> Result<Value, (i64, String)> {
+    match method {
+        "initialize" => Ok(json!({
+            "protocolVersion": MCP_PROTOCOL_VERSION,
+            "capabilities": { "tools": {} },
+            "serverInfo": {
+                "name": "flowhub-database",
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": [query_database_tool_schema()] })),
+        "tools/call" => Ok(call_tool(database_url, params).await),
+        _ => Err((-32601, format!("Method not found: {}", method))),
+    }
+}
+
+async fn run_query_database_server() -> Result<(), String> {
+    let database_url =
+        std::env::var(DATABASE_URL_ENV).map_err(|_| format!("{} is not set", DATABASE_URL_ENV))?;
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|e| format!("Failed to read stdin: {}", e))?
+    {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let Ok(message) = serde_json::from_str::<Value>(trimmed) else {
+            continue;
+        };
+        // 无 id 的通知（如 notifications/initialized）无需响应
+        let Some(id) = message.get("id").cloned() else {
+            continue;
+        };
+        let method = message
+            .get("method")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let response = match handle_mcp_request(&database_url, method, &params).await {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, error)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": error },
+            }),
+        };
+
+        let mut payload = response.to_string();
+        payload.push('\n');
+        stdout
+            .write_all(payload.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write stdout: {}", e))?;
+        stdout
+            .flush()
+            .await
+            .map_err(|e| format!("Failed to flush stdout: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// 命令行带 MCP 模式参数时以 stdio 服务端运行并返回退出码，否则返回 None 继续启动 GUI
+pub fn run_from_args() -> Option<i32> {
+    let mode = std::env::args().nth(1)?;
+    if mode != MCP_QUERY_DATABASE_FLAG {
+        return None;
+    }
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("[mcp] Failed to start runtime: {}", e);
+            return Some(1);
+        }
+    };
+
+    match runtime.block_on(run_query_database_server()) {
+        Ok(_) => Some(0),
+        Err(e) => {
+            eprintln!("[mcp] {}", e);
+            Some(1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tools_list_exposes_query_database() {
+        let result = handle_mcp_request("sqlite::memory:", "tools/list", &Value::Null)
+            .await
+            .expect("tools/list");
+        let tools = result
+            .get("tools")
+            .and_then(Value::as_array)
+            .expect("tools");
+        assert_eq!(tools.len(), 1);
+        assert_eq!(
+            tools[0].get("name").and_then(Value::as_str),
+            Some("query_database")
+        );
+    }
+
+    #[tokio::test]
+    async fn tools_call_rejects_write_statement() {
+        let params = json!({
+            "name": "query_database",
+            "arguments": { "sql": "DROP TABLE users" },
+        });
+        let result = handle_mcp_request("sqlite::memory:", "tools/call", &params)
+            .await
+            .expect("tools/call");
+        assert_eq!(result.get("isError").and_then(Value::as_bool), Some(true));
+    }
+}
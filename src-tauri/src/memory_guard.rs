@@ -0,0 +1,338 @@
+//! 缓冲内容的内存守护：按 Agent 统计监听任务中缓冲的内容（排队 prompt、进行中/待重试 prompt、流式回复），
+//! 超过软上限时发出 `memory-pressure` 警告，排队 prompt 超过硬上限时拒收；
+//! 超过阈值的工具输出写入临时文件，事件中只保留预览与文件路径；
+//! 超长的用户 prompt（如整段粘贴的日志）同样写入临时文件，以 resource_link 附件随 prompt 发送。
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::Manager;
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::models::PromptResource;
+use crate::state::AppState;
+
+const DEFAULT_WARN_BYTES: u64 = 64 * 1024 * 1024;
+const DEFAULT_MAX_QUEUED_BYTES: u64 = 16 * 1024 * 1024;
+const DEFAULT_SPILL_BYTES: u64 = 256 * 1024;
+const DEFAULT_PROMPT_SPILL_BYTES: u64 = 64 * 1024;
+/// 溢出工具输出保留在事件中的预览长度
+const SPILL_PREVIEW_CHARS: usize = 4000;
+/// 溢出 prompt 保留在正文中的开头长度
+const PROMPT_PREVIEW_CHARS: usize = 2000;
+/// 回落到软上限的该比例以下后重新允许警告
+const REARM_PERCENT: u64 = 80;
+
+fn default_warn_bytes() -> u64 {
+    DEFAULT_WARN_BYTES
+}
+
+fn default_max_queued_bytes() -> u64 {
+    DEFAULT_MAX_QUEUED_BYTES
+}
+
+fn default_spill_bytes() -> u64 {
+    DEFAULT_SPILL_BYTES
+}
+
+fn default_prompt_spill_bytes() -> u64 {
+    DEFAULT_PROMPT_SPILL_BYTES
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryLimits {
+    /// 单个 Agent 缓冲内容的软上限，超过时发出 memory-pressure 警告
+    #[serde(default = "default_warn_bytes")]
+    pub warn_bytes: u64,
+    /// 排队等待发送的 prompt 总量上限，超过时拒收新 prompt
+    #[serde(default = "default_max_queued_bytes")]
+    pub max_queued_bytes: u64,
+    /// 单次工具输出超过该大小时写入临时文件
+    #[serde(default = "default_spill_bytes")]
+    pub spill_bytes: u64,
+    /// 单条 prompt 超过该大小时正文只保留开头，完整内容作为附件发送
+    #[serde(default = "default_prompt_spill_bytes")]
+    pub prompt_spill_bytes: u64,
+}
+
+impl Default for MemoryLimits {
+    fn default() -> Self {
+        Self {
+            warn_bytes: DEFAULT_WARN_BYTES,
+            max_queued_bytes: DEFAULT_MAX_QUEUED_BYTES,
+            spill_bytes: DEFAULT_SPILL_BYTES,
+            prompt_spill_bytes: DEFAULT_PROMPT_SPILL_BYTES,
+        }
+    }
+}
+
+pub(crate) fn validate_memory_limits(limits: &MemoryLimits) -> Result<(), String> {
+    if limits.warn_bytes == 0
+        || limits.max_queued_bytes == 0
+        || limits.spill_bytes == 0
+        || limits.prompt_spill_bytes == 0
+    {
+        return Err("Memory limits must be greater than zero".to_string());
+    }
+    Ok(())
+}
+
+pub(crate) async fn memory_limits(app_handle: &tauri::AppHandle) -> MemoryLimits {
+    app_handle
+        .state::<AppState>()
+        .settings
+        .read()
+        .await
+        .memory_limits
+}
+
+/// 监听任务内各类缓冲内容的字节数
+#[derive(Debug, Default, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MemoryUsage {
+    pub queued_prompts: u64,
+    /// 已发送等待响应及等待重试的 prompt
+    pub pending_prompts: u64,
+    pub streamed_reply: u64,
+}
+
+impl MemoryUsage {
+    pub(crate) fn total(&self) -> u64 {
+        self.queued_prompts
+            .saturating_add(self.pending_prompts)
+            .saturating_add(self.streamed_reply)
+    }
+}
+
+/// 单个监听任务的内存守护；越过软上限时只警告一次，回落后重新生效
+#[derive(Debug)]
+pub(crate) struct MemoryGuard {
+    agent_id: String,
+    warned: bool,
+}
+
+impl MemoryGuard {
+    pub(crate) fn new(agent_id: &str) -> Self {
+        Self {
+            agent_id: agent_id.to_string(),
+            warned: false,
+        }
+    }
+
+    /// 是否需要发出警告
+    fn observe(&mut self, usage: &MemoryUsage, warn_bytes: u64) -> bool {
+        let total = usage.total();
+        if total > warn_bytes {
+            let first = !self.warned;
+            self.warned = true;
+            return first;
+        }
+        if total.saturating_mul(100) < warn_bytes.saturating_mul(REARM_PERCENT) {
+            self.warned = false;
+        }
+        false
+    }
+
+    pub(crate) async fn check(&mut self, app_handle: &tauri::AppHandle, usage: MemoryUsage) {
+        let limits = memory_limits(app_handle).await;
+        if !self.observe(&usage, limits.warn_bytes) {
+            return;
+        }
+        println!(
+            "[memory] Agent {} buffers {} bytes (limit {})",
+            self.agent_id,
+            usage.total(),
+            limits.warn_bytes
+        );
+        let _ = app_handle.emit_unless_locked(
+            "memory-pressure",
+            json!({
+                "agentId": &self.agent_id,
+                "usedBytes": usage.total(),
+                "limitBytes": limits.warn_bytes,
+                "usage": usage,
+            }),
+        );
+    }
+}
+
+/// 排队 prompt 是否还能再接收 `incoming` 字节
+pub(crate) fn queue_has_room(queued_bytes: u64, incoming: usize, limits: &MemoryLimits) -> bool {
+    queued_bytes.saturating_add(incoming as u64) <= limits.max_queued_bytes
+}
+
+fn spill_dir(agent_id: &str) -> PathBuf {
+    let agent: String = agent_id
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '-' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    std::env::temp_dir().join("flowhub-spill").join(agent)
+}
+
+fn spill_preview(output: &str, path: &str) -> String {
+    let preview: String = output.chars().take(SPILL_PREVIEW_CHARS).collect();
+    format!(
+        "{}\n…（输出共 {} 字节，完整内容已写入 {}）",
+        preview,
+        output.len(),
+        path
+    )
+}
+
+/// 工具输出超过阈值时写入临时文件，返回（事件中使用的输出, 临时文件路径）；写入失败时保留原文
+pub(crate) async fn spill_tool_output(
+    agent_id: &str,
+    output: String,
+    limits: &MemoryLimits,
+) -> (String, Option<String>) {
+    if (output.len() as u64) <= limits.spill_bytes {
+        return (output, None);
+    }
+    let dir = spill_dir(agent_id);
+    let path = dir.join(format!("{}.txt", uuid::Uuid::new_v4()));
+    let written = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(&path, output.as_bytes()).await
+    }
+    .await;
+    if let Err(e) = written {
+        println!("[memory] Failed to spill tool output: {}", e);
+        return (output, None);
+    }
+    let path = path.to_string_lossy().to_string();
+    (spill_preview(&output, &path), Some(path))
+}
+
+/// prompt 超过阈值时写入临时文件，返回（插入说明后的正文, 附件）；写入失败时保留原文
+pub(crate) async fn spill_large_prompt(
+    agent_id: &str,
+    prompt: String,
+    limits: &MemoryLimits,
+) -> (String, Vec<PromptResource>) {
+    if (prompt.len() as u64) <= limits.prompt_spill_bytes {
+        return (prompt, Vec::new());
+    }
+    let dir = spill_dir(agent_id);
+    let file_name = format!("paste-{}.txt", uuid::Uuid::new_v4());
+    let path = dir.join(&file_name);
+    let written = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(&path, prompt.as_bytes()).await
+    }
+    .await;
+    if let Err(e) = written {
+        println!("[memory] Failed to spill prompt: {}", e);
+        return (prompt, Vec::new());
+    }
+
+    let preview: String = prompt.chars().take(PROMPT_PREVIEW_CHARS).collect();
+    let notice = format!(
+        "{}\n\n…（以上为节选。消息共 {} 字节，完整内容已作为附件 {} 随本条消息发送，请先读取附件再回答）",
+        preview,
+        prompt.len(),
+        file_name
+    );
+    let uri = url::Url::from_file_path(&path)
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| path.to_string_lossy().to_string());
+    let resource = PromptResource {
+        uri,
+        name: file_name,
+        mime_type: Some("text/plain".to_string()),
+        size: Some(prompt.len() as u64),
+    };
+    (notice, vec![resource])
+}
+
+/// Agent 监听结束时清理溢出文件
+pub(crate) async fn clear_spilled_outputs(agent_id: &str) {
+    let dir = spill_dir(agent_id);
+    if let Err(e) = tokio::fs::remove_dir_all(&dir).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            println!("[memory] Failed to clear spilled outputs: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pressure_is_reported_once_per_crossing() {
+        let mut guard = MemoryGuard::new("agent-a");
+        let usage = |streamed_reply: u64| MemoryUsage {
+            queued_prompts: 10,
+            pending_prompts: 0,
+            streamed_reply,
+        };
+        assert!(!guard.observe(&usage(50), 100));
+        assert!(guard.observe(&usage(95), 100));
+        assert!(!guard.observe(&usage(200), 100));
+        // 仍在回落阈值以上，不重新警告
+        assert!(!guard.observe(&usage(75), 100));
+        assert!(!guard.observe(&usage(95), 100));
+        assert!(!guard.observe(&usage(60), 100));
+        assert!(guard.observe(&usage(95), 100));
+    }
+
+    #[tokio::test]
+    async fn large_tool_output_is_spilled_to_temp_file() {
+        let limits = MemoryLimits {
+            spill_bytes: 16,
+            ..Default::default()
+        };
+        let agent_id = format!("spill-{}", uuid::Uuid::new_v4());
+        let (output, path) = spill_tool_output(&agent_id, "short".to_string(), &limits).await;
+        assert_eq!(output, "short");
+        assert!(path.is_none());
+
+        let large = "x".repeat(SPILL_PREVIEW_CHARS * 2);
+        let (output, path) = spill_tool_output(&agent_id, large.clone(), &limits).await;
+        let path = path.expect("spilled path");
+        assert!(output.contains(&path));
+        assert!(output.len() < large.len());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), large);
+
+        let limits = MemoryLimits {
+            prompt_spill_bytes: 16,
+            ..Default::default()
+        };
+        let large = "日志".repeat(PROMPT_PREVIEW_CHARS);
+        let (prompt, resources) = spill_large_prompt(&agent_id, large.clone(), &limits).await;
+        assert_eq!(resources.len(), 1);
+        assert!(prompt.contains(&resources[0].name));
+        assert_eq!(resources[0].size, Some(large.len() as u64));
+        let spilled = url::Url::parse(&resources[0].uri)
+            .unwrap()
+            .to_file_path()
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&spilled).unwrap(), large);
+
+        clear_spilled_outputs(&agent_id).await;
+        assert!(!std::path::Path::new(&path).exists());
+        assert!(queue_has_room(
+            10,
+            6,
+            &MemoryLimits {
+                max_queued_bytes: 16,
+                ..limits
+            }
+        ));
+        assert!(!queue_has_room(
+            10,
+            7,
+            &MemoryLimits {
+                max_queued_bytes: 16,
+                ..limits
+            }
+        ));
+    }
+}
@@ -0,0 +1,177 @@
+//! 删除单条消息：按问答对删除（用户消息及其后直到下一条用户消息之前的回复、思考与系统消息），
+//! 模型切换标记不属于问答对，保留在原位。删除后同步修正会话元数据与已读回执，
+//! 被删除消息引用的附件不再计入引用，由附件保留策略回收。
+//! 写入存储后通过 `messages-deleted` 事件同步给前端。
+use serde::Serialize;
+use tauri::State;
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::read_receipts::shift_read_receipt;
+use crate::state::AppState;
+use crate::storage::{
+    read_snapshot_from_path, storage_path, write_snapshot_to_path, StorageSnapshot,
+};
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedMessages {
+    pub session_id: String,
+    pub message_ids: Vec<String>,
+    /// 被删除消息引用的附件哈希
+    pub attachments: Vec<String>,
+}
+
+/// 定位问答对并从快照中移除，返回被删除消息在会话中的位置（从 0 开始）
+fn remove_message_pair(
+    snapshot: &mut StorageSnapshot,
+    session_id: &str,
+    message_id: &str,
+) -> Result<(Vec<usize>, DeletedMessages), String> {
+    let messages = snapshot
+        .messages_by_session
+        .get_mut(session_id)
+        .ok_or_else(|| format!("Session {} not found", session_id))?;
+    let index = messages
+        .iter()
+        .position(|message| message.id == message_id)
+        .ok_or_else(|| format!("Message {} not found", message_id))?;
+
+    // 回复类消息向前找所属的用户消息；会话开头没有用户消息时从自身开始
+    let start = if messages[index].role == "user" {
+        index
+    } else {
+        messages[..index]
+            .iter()
+            .rposition(|message| message.role == "user")
+            .unwrap_or(index)
+    };
+    let end = messages[start + 1..]
+        .iter()
+        .position(|message| message.role == "user")
+        .map_or(messages.len(), |offset| start + 1 + offset);
+
+    let positions: Vec<usize> = (start..end)
+        .filter(|&position| messages[position].model_switch.is_none())
+        .collect();
+    let mut deleted = DeletedMessages {
+        session_id: session_id.to_string(),
+        ..Default::default()
+    };
+    for &position in positions.iter().rev() {
+        let message = messages.remove(position);
+        deleted.message_ids.insert(0, message.id);
+        deleted.attachments.extend(message.attachments);
+    }
+    deleted.attachments.sort();
+    deleted.attachments.dedup();
+    let remaining = messages.len();
+
+    if let Some(session) = snapshot
+        .sessions_by_agent
+        .values_mut()
+        .flatten()
+        .find(|session| session.id == session_id)
+    {
+        session.updated_at = chrono::Utc::now().to_rfc3339();
+        if session.message_count_hint.is_some() {
+            session.message_count_hint = Some(remaining);
+        }
+    }
+    Ok((positions, deleted))
+}
+
+#[tauri::command]
+pub async fn delete_message(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    message_id: String,
+) -> Result<DeletedMessages, String> {
+    let _guard = state.storage_lock.lock().await;
+    let path = storage_path(&app_handle)?;
+    let mut snapshot = read_snapshot_from_path(&path).await?;
+    let (positions, deleted) = remove_message_pair(&mut snapshot, &session_id, &message_id)?;
+    write_snapshot_to_path(&path, &snapshot).await?;
+    shift_read_receipt(&app_handle, &session_id, &positions).await?;
+
+    let _ = app_handle.emit_unless_locked("messages-deleted", deleted.clone());
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{ModelSwitch, StoredMessage, StoredSession};
+
+    fn message(id: &str, role: &str) -> StoredMessage {
+        StoredMessage {
+            id: id.to_string(),
+            role: role.to_string(),
+            content: id.to_string(),
+            timestamp: "2024-01-01T00:00:00.000Z".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn snapshot() -> StorageSnapshot {
+        let mut snapshot = StorageSnapshot::default();
+        snapshot.sessions_by_agent.insert(
+            "agent-a".to_string(),
+            vec![StoredSession {
+                id: "session-1".to_string(),
+                agent_id: "agent-a".to_string(),
+                message_count_hint: Some(6),
+                ..Default::default()
+            }],
+        );
+        let mut answer = message("a1", "assistant");
+        answer.attachments = vec!["hash-1".to_string()];
+        let mut marker = message("switch", "system");
+        marker.model_switch = Some(ModelSwitch {
+            from: None,
+            to: "glm-4.6".to_string(),
+            restarted: false,
+        });
+        snapshot.messages_by_session.insert(
+            "session-1".to_string(),
+            vec![
+                message("u1", "user"),
+                message("t1", "thought"),
+                marker,
+                answer,
+                message("u2", "user"),
+                message("a2", "assistant"),
+            ],
+        );
+        snapshot
+    }
+
+    #[test]
+    fn deleting_reply_removes_its_question_and_keeps_markers() {
+        let mut snapshot = snapshot();
+        let (positions, deleted) = remove_message_pair(&mut snapshot, "session-1", "a1").unwrap();
+        assert_eq!(positions, vec![0, 1, 3]);
+        assert_eq!(deleted.message_ids, vec!["u1", "t1", "a1"]);
+        assert_eq!(deleted.attachments, vec!["hash-1"]);
+
+        let remaining: Vec<&str> = snapshot.messages_by_session["session-1"]
+            .iter()
+            .map(|message| message.id.as_str())
+            .collect();
+        assert_eq!(remaining, vec!["switch", "u2", "a2"]);
+        assert_eq!(
+            snapshot.sessions_by_agent["agent-a"][0].message_count_hint,
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn deleting_last_question_removes_trailing_reply() {
+        let mut snapshot = snapshot();
+        let (positions, deleted) = remove_message_pair(&mut snapshot, "session-1", "u2").unwrap();
+        assert_eq!(positions, vec![4, 5]);
+        assert_eq!(deleted.message_ids, vec!["u2", "a2"]);
+        assert!(remove_message_pair(&mut snapshot, "session-1", "u2").is_err());
+        assert!(remove_message_pair(&mut snapshot, "session-2", "u1").is_err());
+    }
+}
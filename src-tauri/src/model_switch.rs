@@ -0,0 +1,186 @@
+//! 模型切换标记：对话中途切换模型（session/set_model 或重启 Agent）后，在当前会话末尾写入一条
+//! `system` 标记消息，记录切换前后的模型，导出的记录据此区分各条回答出自哪个模型。
+//! 写入存储后通过 `model-switched` 事件同步给前端。
+use serde::Serialize;
+use serde_json::json;
+use tauri::Manager;
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::settings::{message_style, MessageStyle, SystemMarker};
+use crate::state::AppState;
+use crate::storage::{
+    read_snapshot_from_path, storage_path, write_snapshot_to_path, ModelSwitch, StorageSnapshot,
+    StoredMessage,
+};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelSwitchedPayload {
+    agent_id: String,
+    session_id: String,
+    message: StoredMessage,
+}
+
+fn model_label(model: Option<&str>) -> &str {
+    model.unwrap_or("默认模型")
+}
+
+fn model_switch_message(
+    style: MessageStyle,
+    agent_id: &str,
+    switch: ModelSwitch,
+    timestamp: String,
+) -> StoredMessage {
+    let text = format!(
+        "模型已切换：{} → {}{}",
+        model_label(switch.from.as_deref()),
+        switch.to,
+        if switch.restarted {
+            "（已重启 Agent）"
+        } else {
+            ""
+        }
+    );
+    StoredMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        role: "system".to_string(),
+        content: style.decorate(SystemMarker::ModelSwitch, &text),
+        timestamp,
+        agent_id: Some(agent_id.to_string()),
+        model_switch: Some(switch),
+        ..Default::default()
+    }
+}
+
+/// 追加到绑定该 ACP 会话的本地会话；找不到时取该 Agent 最近更新的会话。返回本地会话 ID
+fn append_switch_marker(
+    snapshot: &mut StorageSnapshot,
+    agent_id: &str,
+    acp_session_id: Option<&str>,
+    message: &StoredMessage,
+) -> Option<String> {
+    let sessions = snapshot.sessions_by_agent.get_mut(agent_id)?;
+    let bound = acp_session_id.and_then(|acp_id| {
+        sessions
+            .iter()
+            .position(|session| session.acp_session_id.as_deref() == Some(acp_id))
+    });
+    let index = bound.or_else(|| {
+        sessions
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.updated_at.cmp(&b.1.updated_at))
+            .map(|(index, _)| index)
+    })?;
+    let session = &mut sessions[index];
+    session.updated_at = message.timestamp.clone();
+    let session_id = session.id.clone();
+    snapshot
+        .messages_by_session
+        .entry(session_id.clone())
+        .or_default()
+        .push(message.clone());
+    Some(session_id)
+}
+
+async fn persist_switch_marker(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    acp_session_id: Option<&str>,
+    message: &StoredMessage,
+) -> Result<Option<String>, String> {
+    let state = app_handle.state::<AppState>();
+    let _guard = state.storage_lock.lock().await;
+    let path = storage_path(app_handle)?;
+    let mut snapshot = read_snapshot_from_path(&path).await?;
+    let session_id = append_switch_marker(&mut snapshot, agent_id, acp_session_id, message);
+    if session_id.is_some() {
+        write_snapshot_to_path(&path, &snapshot).await?;
+    }
+    Ok(session_id)
+}
+
+/// 记录一次模型切换；模型未变化或 Agent 没有会话时不写入
+pub(crate) async fn record_model_switch(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    acp_session_id: Option<&str>,
+    switch: ModelSwitch,
+) {
+    if switch.from.as_deref() == Some(switch.to.as_str()) {
+        return;
+    }
+    let style = message_style(app_handle).await;
+    let message = model_switch_message(style, agent_id, switch, chrono::Utc::now().to_rfc3339());
+    match persist_switch_marker(app_handle, agent_id, acp_session_id, &message).await {
+        Ok(Some(session_id)) => {
+            let _ = app_handle.emit_unless_locked(
+                "model-switched",
+                json!(ModelSwitchedPayload {
+                    agent_id: agent_id.to_string(),
+                    session_id,
+                    message,
+                }),
+            );
+        }
+        Ok(None) => {}
+        Err(e) => println!("[model-switch] Failed to record model switch: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StoredSession;
+
+    fn session(id: &str, acp_session_id: &str, updated_at: &str) -> StoredSession {
+        StoredSession {
+            id: id.to_string(),
+            agent_id: "agent-a".to_string(),
+            updated_at: updated_at.to_string(),
+            acp_session_id: Some(acp_session_id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn switch_marker_is_appended_to_bound_session() {
+        let mut snapshot = StorageSnapshot::default();
+        snapshot.sessions_by_agent.insert(
+            "agent-a".to_string(),
+            vec![
+                session("local-1", "acp-1", "2026-03-02T08:00:00Z"),
+                session("local-2", "acp-2", "2026-03-02T09:00:00Z"),
+            ],
+        );
+        let message = model_switch_message(
+            MessageStyle::Plain,
+            "agent-a",
+            ModelSwitch {
+                from: None,
+                to: "glm-4.7".to_string(),
+                restarted: true,
+            },
+            "2026-03-02T10:00:00Z".to_string(),
+        );
+        assert_eq!(message.role, "system");
+        assert_eq!(
+            message.content,
+            "[模型] 模型已切换：默认模型 → glm-4.7（已重启 Agent）"
+        );
+
+        assert_eq!(
+            append_switch_marker(&mut snapshot, "agent-a", Some("acp-1"), &message).as_deref(),
+            Some("local-1")
+        );
+        assert_eq!(
+            append_switch_marker(&mut snapshot, "agent-a", Some("acp-gone"), &message).as_deref(),
+            Some("local-1")
+        );
+        assert_eq!(snapshot.messages_by_session["local-1"].len(), 2);
+        assert_eq!(
+            append_switch_marker(&mut snapshot, "agent-b", None, &message),
+            None
+        );
+    }
+}
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::oneshot;
 
+use crate::generation::GenerationOptions;
+
 // Agent 状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentInfo {
@@ -13,15 +15,56 @@ pub struct AgentInfo {
     pub port: Option<u16>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum AgentStatus {
     Disconnected,
     Connecting,
+    /// 会话就绪、空闲
     Connected,
+    /// 有进行中或等待重发的 prompt
+    Busy,
+    /// 断线后重连中
+    Reconnecting,
+    /// 正在取消任务或断开连接
+    Stopping,
     Error,
 }
 
+/// Agent 会话的权限模式（ACP `session/set_mode` 的 modeId）
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PermissionMode {
+    /// 只规划不执行
+    Plan,
+    /// 自动接受文件编辑，其余工具调用需确认
+    AcceptEdits,
+    /// 全部自动放行
+    #[default]
+    Yolo,
+}
+
+/// 暂停期间收到的流式更新如何处理
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PausedStreamMode {
+    /// 缓存起来，恢复后依次推送
+    #[default]
+    Buffer,
+    /// 直接丢弃（Agent 仍照常记录预写日志与任务进度）
+    Discard,
+}
+
+impl PermissionMode {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Plan => "plan",
+            Self::AcceptEdits => "acceptEdits",
+            Self::Yolo => "yolo",
+        }
+    }
+}
+
 // 消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
@@ -40,6 +83,13 @@ pub struct ToolCall {
     pub status: String,
     pub arguments: Option<serde_json::Value>,
     pub output: Option<String>,
+    /// 输出过大时完整内容所在的临时文件
+    #[serde(
+        default,
+        rename = "spilledPath",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub spilled_path: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -48,11 +98,23 @@ pub(crate) struct PlanEntry {
     pub(crate) status: String,
 }
 
+/// prompt 中以 ACP `resource_link` 引用的本地文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PromptResource {
+    pub uri: String,
+    pub name: String,
+    pub mime_type: Option<String>,
+    pub size: Option<u64>,
+}
+
 #[derive(Debug)]
 pub(crate) enum ListenerCommand {
     UserPrompt {
         content: String,
         session_id: Option<String>,
+        generation: Option<GenerationOptions>,
+        /// 随 prompt 以 resource_link 发送的文件
+        resources: Vec<PromptResource>,
     },
     CancelPrompt,
     SetModel {
@@ -64,6 +126,48 @@ pub(crate) enum ListenerCommand {
         config: String,
         response: oneshot::Sender<Result<bool, String>>,
     },
+    Authenticate {
+        method_id: String,
+        response: oneshot::Sender<Result<(), String>>,
+    },
+    SetPermissionMode {
+        mode: PermissionMode,
+        response: oneshot::Sender<Result<(), String>>,
+    },
+    /// 应答转交前端的 session/request_permission；`option_id` 为空表示取消
+    RespondPermission {
+        request_id: i64,
+        option_id: Option<String>,
+        response: oneshot::Sender<Result<(), String>>,
+    },
+    /// 应答计划模式下等待确认的 fs/write_text_file
+    RespondFileWrite {
+        request_id: i64,
+        accept: bool,
+        response: oneshot::Sender<Result<(), String>>,
+    },
+    ListSessions {
+        response: oneshot::Sender<AgentSessions>,
+    },
+    /// 暂停/恢复：暂停期间不发送新 prompt，也不向前端推送流式更新
+    SetPaused {
+        paused: bool,
+        response: oneshot::Sender<Result<(), String>>,
+    },
+    /// 通过 session/load 切换到该 Agent 的另一个已有会话
+    SwitchSession {
+        session_id: String,
+        response: oneshot::Sender<Result<(), String>>,
+    },
+}
+
+/// 监听任务在本次进程生命周期内创建或载入过的 ACP 会话
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSessions {
+    pub current: Option<String>,
+    /// 按最近使用排序，最近的在最后
+    pub sessions: Vec<String>,
 }
 
 pub(crate) type MessageSender = UnboundedSender<ListenerCommand>;
@@ -0,0 +1,450 @@
+//! 外发通知渠道：按工作区配置 Slack / Discord Webhook，在任务结束或预算告警时按模板推送消息；
+//! 每个渠道有最小发送间隔，间隔内的后续通知直接丢弃，避免刷屏。
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, State};
+use tokio::fs;
+use url::Url;
+
+use crate::capabilities::{capability_enabled, CommandCapability};
+use crate::history::normalize_workspace_path;
+use crate::quiet_hours::is_quiet_now;
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag};
+
+const DEFAULT_MIN_INTERVAL_SECS: u64 = 60;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(15);
+const MAX_SUMMARY_CHARS: usize = 600;
+/// Discord 单条消息上限为 2000 字符，Slack 更宽松，统一按较小值截断
+const MAX_MESSAGE_CHARS: usize = 2000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelKind {
+    Slack,
+    Discord,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum NotificationEvent {
+    TaskFinish,
+    BudgetAlert,
+}
+
+impl NotificationEvent {
+    fn default_template(self) -> &'static str {
+        match self {
+            Self::TaskFinish => "✅ [{{workspace}}] 任务结束（{{stopReason}}）\n{{summary}}",
+            Self::BudgetAlert => "⚠️ [{{workspace}}] 预算告警：{{message}}",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationChannel {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub kind: ChannelKind,
+    pub webhook_url: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub events: Vec<NotificationEvent>,
+    /// 按事件覆盖的消息模板，支持 `{{workspace}}`、`{{agentId}}`、`{{stopReason}}`、
+    /// `{{summary}}`、`{{message}}` 占位符
+    #[serde(default)]
+    pub templates: HashMap<NotificationEvent, String>,
+    /// 同一渠道两次发送的最小间隔；为空时为 60 秒
+    #[serde(default)]
+    pub min_interval_secs: Option<u64>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct NotificationChannelStore {
+    #[serde(default)]
+    by_workspace: HashMap<String, Vec<NotificationChannel>>,
+}
+
+/// 各渠道最近一次发送时间，仅在内存中保存
+#[derive(Default)]
+pub(crate) struct NotificationRateLimiter {
+    last_sent: Mutex<HashMap<String, Instant>>,
+}
+
+impl NotificationRateLimiter {
+    /// 允许发送时记录本次时间并返回 true
+    fn try_acquire(&self, channel_id: &str, min_interval: Duration, now: Instant) -> bool {
+        let Ok(mut last_sent) = self.last_sent.lock() else {
+            return false;
+        };
+        match last_sent.get(channel_id) {
+            Some(previous) if now.duration_since(*previous) < min_interval => false,
+            _ => {
+                last_sent.insert(channel_id.to_string(), now);
+                true
+            }
+        }
+    }
+}
+
+fn channels_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!(
+        "iflow-notification-channels-{}.json",
+        storage_env_tag()
+    )))
+}
+
+async fn read_channels_from_path(path: &Path) -> Result<NotificationChannelStore, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(NotificationChannelStore::default());
+            }
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse notification channels: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(NotificationChannelStore::default()),
+        Err(err) => Err(format!("Failed to read notification channels: {}", err)),
+    }
+}
+
+async fn write_channels_to_path(
+    path: &Path,
+    store: &NotificationChannelStore,
+) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create notification channels dir: {}", e))?;
+    }
+    let payload = serde_json::to_vec(store)
+        .map_err(|e| format!("Failed to encode notification channels: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write notification channels: {}", e))?;
+
+    // Webhook 地址等同于凭据，限制为仅当前用户可读
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).await;
+    }
+    Ok(())
+}
+
+fn validate_channel(channel: &NotificationChannel) -> Result<(), String> {
+    if channel.name.trim().is_empty() {
+        return Err("Channel name cannot be empty".to_string());
+    }
+    let url = Url::parse(channel.webhook_url.trim())
+        .map_err(|e| format!("Invalid webhook URL: {}", e))?;
+    if url.scheme() != "https" {
+        return Err("Webhook URL must use https".to_string());
+    }
+    if channel.events.is_empty() {
+        return Err("Select at least one notification event".to_string());
+    }
+    Ok(())
+}
+
+fn render_template(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut rendered = template.to_string();
+    for (name, value) in vars {
+        rendered = rendered.replace(&format!("{{{{{}}}}}", name), value);
+    }
+    if rendered.chars().count() > MAX_MESSAGE_CHARS {
+        rendered = format!(
+            "{}…",
+            rendered
+                .chars()
+                .take(MAX_MESSAGE_CHARS - 1)
+                .collect::<String>()
+        );
+    }
+    rendered
+}
+
+fn webhook_payload(kind: ChannelKind, text: &str) -> serde_json::Value {
+    match kind {
+        ChannelKind::Slack => serde_json::json!({ "text": text }),
+        ChannelKind::Discord => serde_json::json!({ "content": text }),
+    }
+}
+
+async fn post_webhook(channel: &NotificationChannel, text: &str) -> Result<(), String> {
+    let client = reqwest::Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let response = client
+        .post(channel.webhook_url.trim())
+        .json(&webhook_payload(channel.kind, text))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to post notification: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to post notification: HTTP {}",
+            response.status()
+        ));
+    }
+    Ok(())
+}
+
+/// 向工作区中订阅了该事件的渠道发送通知（后台执行，失败只记录日志）
+pub(crate) fn spawn_channel_notification(
+    app_handle: &tauri::AppHandle,
+    workspace_path: &str,
+    event: NotificationEvent,
+    vars: Vec<(&'static str, String)>,
+) {
+    if !capability_enabled(app_handle, CommandCapability::Network) {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    let workspace_path = workspace_path.to_string();
+    tokio::spawn(async move {
+        // 免打扰时段只静默任务结束通知，预算告警照常发送
+        if event == NotificationEvent::TaskFinish && is_quiet_now(&app_handle).await {
+            return;
+        }
+        let channels = match channels_path(&app_handle) {
+            Ok(path) => read_channels_from_path(&path).await,
+            Err(e) => Err(e),
+        };
+        let channels = match channels {
+            Ok(store) => store
+                .by_workspace
+                .get(&normalize_workspace_path(&workspace_path))
+                .cloned()
+                .unwrap_or_default(),
+            Err(e) => {
+                println!("[notify] {}", e);
+                return;
+            }
+        };
+
+        let workspace_name = normalize_workspace_path(&workspace_path)
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let mut all_vars: Vec<(&str, &str)> = vec![("workspace", workspace_name.as_str())];
+        all_vars.extend(vars.iter().map(|(name, value)| (*name, value.as_str())));
+
+        let state = app_handle.state::<AppState>();
+        for channel in channels
+            .iter()
+            .filter(|channel| channel.enabled && channel.events.contains(&event))
+        {
+            let min_interval = Duration::from_secs(
+                channel
+                    .min_interval_secs
+                    .unwrap_or(DEFAULT_MIN_INTERVAL_SECS),
+            );
+            if !state
+                .notification_limiter
+                .try_acquire(&channel.id, min_interval, Instant::now())
+            {
+                continue;
+            }
+            let template = channel
+                .templates
+                .get(&event)
+                .map(String::as_str)
+                .unwrap_or_else(|| event.default_template());
+            let text = render_template(template, &all_vars);
+            if let Err(e) = post_webhook(channel, &text).await {
+                println!("[notify] {} ({}): {}", channel.name, channel.id, e);
+            }
+        }
+    });
+}
+
+/// 任务结束摘要：取助手回复开头，避免整段输出推送到群聊
+pub(crate) fn notify_task_finish(
+    app_handle: &tauri::AppHandle,
+    workspace_path: &str,
+    agent_id: &str,
+    stop_reason: &str,
+    message: &str,
+) {
+    let summary: String = message.trim().chars().take(MAX_SUMMARY_CHARS).collect();
+    spawn_channel_notification(
+        app_handle,
+        workspace_path,
+        NotificationEvent::TaskFinish,
+        vec![
+            ("agentId", agent_id.to_string()),
+            ("stopReason", stop_reason.to_string()),
+            ("summary", summary),
+        ],
+    );
+}
+
+#[tauri::command]
+pub async fn list_notification_channels(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: String,
+) -> Result<Vec<NotificationChannel>, String> {
+    let _guard = state.notifications_lock.lock().await;
+    let store = read_channels_from_path(&channels_path(&app_handle)?).await?;
+    Ok(store
+        .by_workspace
+        .get(&normalize_workspace_path(&workspace_path))
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// 新增或按 ID 更新工作区的通知渠道
+#[tauri::command]
+pub async fn configure_notification_channel(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: String,
+    channel: NotificationChannel,
+) -> Result<NotificationChannel, String> {
+    validate_channel(&channel)?;
+
+    let _guard = state.notifications_lock.lock().await;
+    let path = channels_path(&app_handle)?;
+    let mut store = read_channels_from_path(&path).await?;
+    let channels = store
+        .by_workspace
+        .entry(normalize_workspace_path(&workspace_path))
+        .or_default();
+
+    let mut channel = channel;
+    channel.name = channel.name.trim().to_string();
+    channel.webhook_url = channel.webhook_url.trim().to_string();
+    match channels
+        .iter_mut()
+        .find(|existing| !channel.id.is_empty() && existing.id == channel.id)
+    {
+        Some(existing) => *existing = channel.clone(),
+        None => {
+            channel.id = uuid::Uuid::new_v4().to_string();
+            channels.push(channel.clone());
+        }
+    }
+    write_channels_to_path(&path, &store).await?;
+    Ok(channel)
+}
+
+#[tauri::command]
+pub async fn remove_notification_channel(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: String,
+    channel_id: String,
+) -> Result<bool, String> {
+    let _guard = state.notifications_lock.lock().await;
+    let path = channels_path(&app_handle)?;
+    let mut store = read_channels_from_path(&path).await?;
+    let Some(channels) = store
+        .by_workspace
+        .get_mut(&normalize_workspace_path(&workspace_path))
+    else {
+        return Ok(false);
+    };
+    let before = channels.len();
+    channels.retain(|channel| channel.id != channel_id);
+    let removed = channels.len() != before;
+    if removed {
+        write_channels_to_path(&path, &store).await?;
+    }
+    Ok(removed)
+}
+
+/// 发送预算告警；由跟踪用量的一方（如前端用量面板）在超出阈值时调用
+#[tauri::command]
+pub async fn notify_budget_alert(
+    app_handle: tauri::AppHandle,
+    workspace_path: String,
+    message: String,
+) -> Result<(), String> {
+    let message = message.trim().to_string();
+    if message.is_empty() {
+        return Err("Alert message cannot be empty".to_string());
+    }
+    spawn_channel_notification(
+        &app_handle,
+        &workspace_path,
+        NotificationEvent::BudgetAlert,
+        vec![("message", message)],
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_render_and_truncate() {
+        let rendered = render_template(
+            NotificationEvent::TaskFinish.default_template(),
+            &[
+                ("workspace", "flowhub"),
+                ("stopReason", "end_turn"),
+                ("summary", "done"),
+            ],
+        );
+        assert_eq!(rendered, "✅ [flowhub] 任务结束（end_turn）\ndone");
+
+        let long = "x".repeat(MAX_MESSAGE_CHARS + 10);
+        let truncated = render_template("{{summary}}", &[("summary", long.as_str())]);
+        assert_eq!(truncated.chars().count(), MAX_MESSAGE_CHARS);
+
+        assert_eq!(
+            webhook_payload(ChannelKind::Discord, "hi"),
+            serde_json::json!({ "content": "hi" })
+        );
+    }
+
+    #[test]
+    fn rate_limiter_enforces_min_interval_per_channel() {
+        let limiter = NotificationRateLimiter::default();
+        let interval = Duration::from_secs(60);
+        let start = Instant::now();
+        assert!(limiter.try_acquire("a", interval, start));
+        assert!(!limiter.try_acquire("a", interval, start + Duration::from_secs(30)));
+        assert!(limiter.try_acquire("b", interval, start + Duration::from_secs(30)));
+        assert!(limiter.try_acquire("a", interval, start + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn channels_require_https_webhook_and_events() {
+        let mut channel = NotificationChannel {
+            id: String::new(),
+            name: "team".to_string(),
+            kind: ChannelKind::Slack,
+            webhook_url: "https://hooks.slack.com/services/T000/B000/XXX".to_string(),
+            enabled: true,
+            events: vec![NotificationEvent::TaskFinish],
+            templates: HashMap::new(),
+            min_interval_secs: None,
+        };
+        assert!(validate_channel(&channel).is_ok());
+
+        channel.webhook_url = "http://hooks.slack.com/services/T000".to_string();
+        assert!(validate_channel(&channel).is_err());
+
+        channel.webhook_url = "https://discord.com/api/webhooks/1/abc".to_string();
+        channel.events.clear();
+        assert!(validate_channel(&channel).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! 对外展示路径归一化：把事件与导出中的工作区绝对路径改写为相对路径，
+//! 避免用户目录结构泄露到可分享内容；后端内部记录仍保留绝对路径。
+use serde_json::Value;
+
+fn is_path_char(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.' | '/' | '\\' | '~')
+}
+
+fn workspace_prefixes(workspace_path: &str) -> Vec<String> {
+    let trimmed = workspace_path.trim().trim_end_matches(['/', '\\']);
+    // 根目录等不含分隔符的路径改写会误伤普通文本
+    if trimmed.is_empty() || !trimmed.contains(['/', '\\']) {
+        return Vec::new();
+    }
+
+    let mut prefixes = vec![trimmed.to_string()];
+    if trimmed.contains('\\') {
+        prefixes.push(trimmed.replace('\\', "/"));
+    }
+    prefixes
+}
+
+fn relativize_with_prefix(text: &str, prefix: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(index) = rest.find(prefix) {
+        let before = &rest[..index];
+        let after = &rest[index + prefix.len()..];
+        output.push_str(before);
+        rest = after;
+
+        // 前面紧跟路径字符说明是其他路径的一部分（如 file:// URI），不改写
+        if before
+            .chars()
+            .next_back()
+            .map(is_path_char)
+            .unwrap_or(false)
+        {
+            output.push_str(prefix);
+            continue;
+        }
+
+        let mut after_chars = after.chars();
+        match after_chars.next() {
+            Some('/') | Some('\\') => {
+                let remainder = after_chars.as_str();
+                if !remainder.chars().next().map(is_path_char).unwrap_or(false) {
+                    output.push('.');
+                }
+                rest = remainder;
+            }
+            // 同名前缀的兄弟目录（如 /ws 与 /ws-old），不改写
+            Some(ch) if is_path_char(ch) => output.push_str(prefix),
+            _ => output.push('.'),
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// 将文本中的工作区绝对路径改写为相对路径（工作区根目录本身显示为 `.`）
+pub(crate) fn relativize_workspace_paths(text: &str, workspace_path: &str) -> String {
+    let mut output = text.to_string();
+    for prefix in workspace_prefixes(workspace_path) {
+        if output.contains(&prefix) {
+            output = relativize_with_prefix(&output, &prefix);
+        }
+    }
+    output
+}
+
+/// 递归改写 JSON 中所有字符串值里的工作区绝对路径
+pub(crate) fn relativize_workspace_paths_in_value(value: &Value, workspace_path: &str) -> Value {
+    match value {
+        Value::String(text) => Value::String(relativize_workspace_paths(text, workspace_path)),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| relativize_workspace_paths_in_value(item, workspace_path))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, item)| {
+                    (
+                        key.clone(),
+                        relativize_workspace_paths_in_value(item, workspace_path),
+                    )
+                })
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn relativize_rewrites_paths_under_workspace() {
+        let workspace = "/Users/alice/project/";
+        assert_eq!(
+            relativize_workspace_paths("edit /Users/alice/project/src/main.rs done", workspace),
+            "edit src/main.rs done"
+        );
+        assert_eq!(
+            relativize_workspace_paths("cd /Users/alice/project && ls", workspace),
+            "cd . && ls"
+        );
+        assert_eq!(
+            relativize_workspace_paths("/Users/alice/project-old/a.rs", workspace),
+            "/Users/alice/project-old/a.rs"
+        );
+        assert_eq!(
+            relativize_workspace_paths("file:///Users/alice/project/a.rs", workspace),
+            "file:///Users/alice/project/a.rs"
+        );
+    }
+
+    #[test]
+    fn relativize_handles_windows_paths_and_json_values() {
+        let workspace = r"C:\work\repo";
+        assert_eq!(
+            relativize_workspace_paths(r"open C:\work\repo\src\lib.rs", workspace),
+            r"open src\lib.rs"
+        );
+
+        let value = json!({
+            "title": "Read /srv/app/README.md",
+            "args": { "paths": ["/srv/app/a.txt", "/tmp/b.txt"] },
+            "count": 2,
+        });
+        let rewritten = relativize_workspace_paths_in_value(&value, "/srv/app");
+        assert_eq!(rewritten["title"], "Read README.md");
+        assert_eq!(rewritten["args"]["paths"], json!(["a.txt", "/tmp/b.txt"]));
+        assert_eq!(rewritten["count"], 2);
+    }
+}
@@ -0,0 +1,734 @@
+//! 工具权限规则持久化：按工作区记住“始终允许 / 始终拒绝”决策，
+//! 并自动应用到后续的 session/request_permission 请求。
+//! 未命中规则的请求转交前端由用户选择，超时未应答时按设置的默认决策应答。
+//! 规则可附带正则，按工具调用的命令、标题与路径匹配（如拒绝匹配 `rm -rf` 的 shell 命令）；
+//! 未经权限请求直接执行的 tool_call（yolo / acceptEdits 模式）命中拒绝规则时，由监听任务取消本轮；
+//! 此时工具可能已开始执行，拒绝只能尽力而为，不能保证工具没有产生副作用。
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::State;
+use tokio::fs;
+use tokio::time::{timeout, Duration};
+
+use crate::history::{normalize_workspace_path, workspace_path_variants};
+use crate::models::ListenerCommand;
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag};
+
+/// 匹配任意工具的通配符
+const ANY_TOOL: &str = "*";
+pub(crate) const DEFAULT_PERMISSION_TIMEOUT_SECS: u64 = 120;
+const RESPOND_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionDecision {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionRule {
+    pub id: String,
+    /// 工具种类（read / edit / execute ...）或工具标题，`*` 表示任意工具
+    pub tool: String,
+    /// 工作区相对路径前缀；为空表示不限路径
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+    /// 匹配工具调用命令、标题与路径的正则；为空表示不限内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    pub decision: PermissionDecision,
+    pub created_at: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionStore {
+    #[serde(default)]
+    pub by_workspace: HashMap<String, Vec<PermissionRule>>,
+}
+
+impl PermissionStore {
+    fn rules_mut(&mut self, workspace_path: &str) -> &mut Vec<PermissionRule> {
+        self.by_workspace
+            .entry(normalize_workspace_path(workspace_path))
+            .or_default()
+    }
+
+    fn rules(&self, workspace_path: &str) -> Vec<PermissionRule> {
+        self.by_workspace
+            .get(&normalize_workspace_path(workspace_path))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// 权限请求中用于规则匹配的信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PermissionSubject {
+    kind: Option<String>,
+    title: Option<String>,
+    /// 工具名（toolName / name）
+    name: Option<String>,
+    /// 工作区相对路径；位于工作区外的路径保留绝对路径
+    paths: Vec<String>,
+    /// 供正则匹配的原文：标题、命令与路径，逐行拼接
+    text: String,
+}
+
+fn permissions_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-permissions-{}.json", storage_env_tag())))
+}
+
+async fn read_permissions_from_path(path: &Path) -> Result<PermissionStore, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(PermissionStore::default());
+            }
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse permission rules: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(PermissionStore::default()),
+        Err(err) => Err(format!("Failed to read permission rules: {}", err)),
+    }
+}
+
+async fn write_permissions_to_path(path: &Path, store: &PermissionStore) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create permission rules dir: {}", e))?;
+    }
+    let payload = serde_json::to_vec(store)
+        .map_err(|e| format!("Failed to encode permission rules: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write permission rules: {}", e))
+}
+
+fn normalize_rule_tool(tool: &str) -> Result<String, String> {
+    let normalized = tool.trim().to_lowercase();
+    if normalized.is_empty() {
+        return Err("Permission rule tool cannot be empty".to_string());
+    }
+    Ok(normalized)
+}
+
+/// 词法归一化：去掉 `.`、消解 `..`（绝对路径在根处截止）；相对路径越出起点时保留开头的 `..`
+fn normalize_lexical(path: &str) -> String {
+    let normalized = normalize_workspace_path(path);
+    let bytes = normalized.as_bytes();
+    let root = if normalized.starts_with('/') {
+        "/"
+    } else if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && &bytes[1..3] == b":/" {
+        &normalized[..3]
+    } else {
+        ""
+    };
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in normalized[root.len()..].split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => match segments.last().copied() {
+                Some(last) if last != ".." => {
+                    segments.pop();
+                }
+                _ if !root.is_empty() => {}
+                _ => segments.push(".."),
+            },
+            _ => segments.push(segment),
+        }
+    }
+    format!("{}{}", root, segments.join("/"))
+}
+
+fn is_absolute_like(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with('/')
+        || (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+}
+
+fn normalize_rule_path(path: Option<String>) -> Option<String> {
+    let path = path?;
+    let normalized = normalize_lexical(&path);
+    let normalized = normalized.trim_end_matches('/');
+    if normalized.is_empty() || normalized == "." {
+        None
+    } else {
+        Some(normalized.to_string())
+    }
+}
+
+fn normalize_rule_pattern(pattern: Option<String>) -> Result<Option<String>, String> {
+    let Some(pattern) = pattern.map(|item| item.trim().to_string()) else {
+        return Ok(None);
+    };
+    if pattern.is_empty() {
+        return Ok(None);
+    }
+    Regex::new(&pattern).map_err(|e| format!("Invalid permission rule pattern: {}", e))?;
+    Ok(Some(pattern))
+}
+
+/// `rawInput.command` 可能是字符串或参数数组
+fn tool_command(tool_call: &Value) -> Option<String> {
+    let command = tool_call.get("rawInput")?.get("command")?;
+    match command {
+        Value::String(command) => Some(command.clone()),
+        Value::Array(parts) => Some(
+            parts
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        _ => None,
+    }
+}
+
+/// 相对路径按工作区解析并消解 `..`，越出工作区的路径保留为绝对路径
+fn relative_to_workspace(path: &str, workspace_roots: &[String]) -> String {
+    let normalized = normalize_workspace_path(path);
+    let normalized = match workspace_roots.first() {
+        Some(root) if !is_absolute_like(&normalized) => {
+            normalize_lexical(&format!("{}/{}", root, normalized))
+        }
+        _ => normalize_lexical(&normalized),
+    };
+    for root in workspace_roots {
+        if normalized == *root {
+            return String::new();
+        }
+        if let Some(rest) = normalized.strip_prefix(&format!("{}/", root)) {
+            return rest.to_string();
+        }
+    }
+    normalized.trim_start_matches("./").to_string()
+}
+
+fn parse_permission_subject(params: &Value, workspace_roots: &[String]) -> PermissionSubject {
+    parse_tool_subject(
+        params.get("toolCall").unwrap_or(&Value::Null),
+        workspace_roots,
+    )
+}
+
+/// 从 request_permission 的 toolCall 或 session/update 的 tool_call 中提取匹配信息；
+/// `workspace_roots` 为调用方预先算好的工作区路径形式（见 `workspace_path_variants`）
+fn parse_tool_subject(tool_call: &Value, workspace_roots: &[String]) -> PermissionSubject {
+    let text_field = |key: &str| {
+        tool_call
+            .get(key)
+            .and_then(Value::as_str)
+            .map(|item| item.trim().to_lowercase())
+            .filter(|item| !item.is_empty())
+    };
+
+    let paths = tool_call
+        .get("locations")
+        .and_then(Value::as_array)
+        .map(|locations| {
+            locations
+                .iter()
+                .filter_map(|location| location.get("path").and_then(Value::as_str))
+                .map(|path| relative_to_workspace(path, workspace_roots))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let text = [
+        tool_call
+            .get("title")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        tool_command(tool_call),
+    ]
+    .into_iter()
+    .flatten()
+    .chain(paths.iter().cloned())
+    .collect::<Vec<_>>()
+    .join("\n");
+
+    PermissionSubject {
+        kind: text_field("kind"),
+        title: text_field("title"),
+        name: text_field("toolName").or_else(|| text_field("name")),
+        paths,
+        text,
+    }
+}
+
+fn path_under_prefix(path: &str, prefix: &str) -> bool {
+    !is_absolute_like(path)
+        && !path.split('/').any(|segment| segment == "..")
+        && (path == prefix || path.starts_with(&format!("{}/", prefix)))
+}
+
+/// 加载时预编译正则的规则，匹配时不再重复编译
+struct CompiledRule {
+    rule: PermissionRule,
+    pattern: Option<Regex>,
+}
+
+/// 存盘前已校验正则；仍无法编译的规则不会命中，直接丢弃
+fn compile_rules(rules: Vec<PermissionRule>) -> Vec<CompiledRule> {
+    rules
+        .into_iter()
+        .filter_map(|rule| {
+            let pattern = match rule.pattern.as_deref().map(Regex::new).transpose() {
+                Ok(pattern) => pattern,
+                Err(e) => {
+                    println!(
+                        "[permissions] Skipping rule {} with invalid pattern: {}",
+                        rule.id, e
+                    );
+                    return None;
+                }
+            };
+            Some(CompiledRule { rule, pattern })
+        })
+        .collect()
+}
+
+fn rule_matches(compiled: &CompiledRule, subject: &PermissionSubject) -> bool {
+    let rule = &compiled.rule;
+    let tool_matches = rule.tool == ANY_TOOL
+        || [&subject.kind, &subject.title, &subject.name]
+            .into_iter()
+            .any(|field| field.as_deref() == Some(rule.tool.as_str()));
+    if !tool_matches {
+        return false;
+    }
+    if let Some(pattern) = &compiled.pattern {
+        if !pattern.is_match(&subject.text) {
+            return false;
+        }
+    }
+
+    match rule.path_prefix.as_deref() {
+        None => true,
+        // 允许规则要求请求的所有路径都落在前缀内；拒绝规则只要有一个路径落在前缀内即命中，
+        // 避免夹带前缀外的路径绕过拒绝
+        Some(prefix) => {
+            let mut paths = subject.paths.iter();
+            match rule.decision {
+                PermissionDecision::Allow => {
+                    !subject.paths.is_empty() && paths.all(|path| path_under_prefix(path, prefix))
+                }
+                PermissionDecision::Deny => paths.any(|path| path_under_prefix(path, prefix)),
+            }
+        }
+    }
+}
+
+/// 拒绝规则优先于允许规则
+fn match_rule<'a>(
+    rules: &'a [CompiledRule],
+    subject: &PermissionSubject,
+) -> Option<&'a PermissionRule> {
+    let candidates: Vec<&PermissionRule> = rules
+        .iter()
+        .filter(|rule| rule_matches(rule, subject))
+        .map(|compiled| &compiled.rule)
+        .collect();
+    candidates
+        .iter()
+        .copied()
+        .find(|rule| rule.decision == PermissionDecision::Deny)
+        .or_else(|| candidates.first().copied())
+}
+
+fn select_option_id(params: &Value, preferred_kinds: &[&str]) -> Option<String> {
+    let options = params.get("options").and_then(Value::as_array)?;
+    preferred_kinds.iter().find_map(|kind| {
+        options
+            .iter()
+            .find(|option| option.get("kind").and_then(Value::as_str) == Some(*kind))
+            .and_then(|option| option.get("optionId").and_then(Value::as_str))
+            .map(|item| item.to_string())
+    })
+}
+
+pub(crate) fn outcome_for_decision(params: &Value, decision: PermissionDecision) -> Value {
+    match decision {
+        PermissionDecision::Allow => {
+            let option_id = select_option_id(params, &["allow_once", "allow_always"])
+                .unwrap_or_else(|| "allow_once".to_string());
+            json!({ "outcome": "selected", "optionId": option_id })
+        }
+        PermissionDecision::Deny => {
+            match select_option_id(params, &["reject_once", "reject_always"]) {
+                Some(option_id) => json!({ "outcome": "selected", "optionId": option_id }),
+                None => json!({ "outcome": "cancelled" }),
+            }
+        }
+    }
+}
+
+/// session/update 中的 tool_call 命中拒绝规则时返回该规则。
+/// 这类调用未经权限请求，调用方只能事后取消本轮，工具可能已经开始执行
+pub(crate) async fn deny_rule_for_tool_call(
+    app_handle: &tauri::AppHandle,
+    workspace_path: &str,
+    tool_call: &Value,
+) -> Option<PermissionRule> {
+    let rules = load_rules(app_handle, workspace_path).await;
+    let workspace_roots = workspace_path_variants(workspace_path).await;
+    let subject = parse_tool_subject(tool_call, &workspace_roots);
+    match_rule(&rules, &subject)
+        .filter(|rule| rule.decision == PermissionDecision::Deny)
+        .cloned()
+}
+
+/// 用户选择的选项对应的应答；未选择（关闭对话框）视为取消
+pub(crate) fn outcome_for_option(params: &Value, option_id: Option<&str>) -> Result<Value, String> {
+    let Some(option_id) = option_id else {
+        return Ok(json!({ "outcome": "cancelled" }));
+    };
+    let known = params
+        .get("options")
+        .and_then(Value::as_array)
+        .is_some_and(|options| {
+            options
+                .iter()
+                .any(|option| option.get("optionId").and_then(Value::as_str) == Some(option_id))
+        });
+    if !known {
+        return Err(format!("Unknown permission option: {}", option_id));
+    }
+    Ok(json!({ "outcome": "selected", "optionId": option_id }))
+}
+
+async fn load_rules(app_handle: &tauri::AppHandle, workspace_path: &str) -> Vec<CompiledRule> {
+    let store = match permissions_path(app_handle) {
+        Ok(path) => read_permissions_from_path(&path).await,
+        Err(e) => Err(e),
+    };
+    match store {
+        Ok(store) => compile_rules(store.rules(workspace_path)),
+        Err(e) => {
+            println!("[permissions] Failed to load rules: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// 按工作区规则计算 session/request_permission 的应答；无匹配规则时返回 None
+pub(crate) async fn apply_permission_rules(
+    app_handle: &tauri::AppHandle,
+    workspace_path: &str,
+    params: &Value,
+) -> Option<(Value, PermissionRule)> {
+    let rules = load_rules(app_handle, workspace_path).await;
+    let workspace_roots = workspace_path_variants(workspace_path).await;
+    let subject = parse_permission_subject(params, &workspace_roots);
+    let rule = match_rule(&rules, &subject)?.clone();
+    Some((outcome_for_decision(params, rule.decision), rule))
+}
+
+#[tauri::command]
+pub async fn list_permission_rules(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: String,
+) -> Result<Vec<PermissionRule>, String> {
+    let _guard = state.permissions_lock.lock().await;
+    let store = read_permissions_from_path(&permissions_path(&app_handle)?).await?;
+    Ok(store.rules(&workspace_path))
+}
+
+#[tauri::command]
+pub async fn add_permission_rule(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: String,
+    tool: String,
+    path_prefix: Option<String>,
+    pattern: Option<String>,
+    decision: PermissionDecision,
+) -> Result<PermissionRule, String> {
+    let tool = normalize_rule_tool(&tool)?;
+    let path_prefix = normalize_rule_path(path_prefix);
+    let pattern = normalize_rule_pattern(pattern)?;
+
+    let _guard = state.permissions_lock.lock().await;
+    let path = permissions_path(&app_handle)?;
+    let mut store = read_permissions_from_path(&path).await?;
+    let rules = store.rules_mut(&workspace_path);
+    // 同一工具、路径与内容正则只保留最新决策
+    rules.retain(|rule| {
+        !(rule.tool == tool && rule.path_prefix == path_prefix && rule.pattern == pattern)
+    });
+
+    let rule = PermissionRule {
+        id: uuid::Uuid::new_v4().to_string(),
+        tool,
+        path_prefix,
+        pattern,
+        decision,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    rules.push(rule.clone());
+    write_permissions_to_path(&path, &store).await?;
+    Ok(rule)
+}
+
+#[tauri::command]
+pub async fn revoke_permission_rule(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: String,
+    rule_id: String,
+) -> Result<bool, String> {
+    let _guard = state.permissions_lock.lock().await;
+    let path = permissions_path(&app_handle)?;
+    let mut store = read_permissions_from_path(&path).await?;
+    let rules = store.rules_mut(&workspace_path);
+    let before = rules.len();
+    rules.retain(|rule| rule.id != rule_id);
+    let removed = rules.len() != before;
+    if removed {
+        write_permissions_to_path(&path, &store).await?;
+    }
+    Ok(removed)
+}
+
+/// 应答前端弹出的权限请求；`option_id` 为空表示取消
+#[tauri::command]
+pub async fn respond_permission(
+    state: State<'_, AppState>,
+    agent_id: String,
+    request_id: i64,
+    option_id: Option<String>,
+) -> Result<(), String> {
+    let (agent_exists, sender) = state.agent_manager.sender_of(&agent_id).await;
+    if !agent_exists {
+        return Err(format!("Agent {} not found", agent_id));
+    }
+    let Some(sender) = sender else {
+        return Err("Message sender not available".to_string());
+    };
+
+    let (tx, rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
+    sender
+        .send(ListenerCommand::RespondPermission {
+            request_id,
+            option_id,
+            response: tx,
+        })
+        .map_err(|e| format!("Failed to queue permission response: {}", e))?;
+
+    match timeout(RESPOND_TIMEOUT, rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err("Permission response channel closed".to_string()),
+        Err(_) => Err("Permission response timeout".to_string()),
+    }
+}
+
+/// 应答计划模式下等待确认的文件写入
+#[tauri::command]
+pub async fn respond_file_write(
+    state: State<'_, AppState>,
+    agent_id: String,
+    request_id: i64,
+    accept: bool,
+) -> Result<(), String> {
+    let (agent_exists, sender) = state.agent_manager.sender_of(&agent_id).await;
+    if !agent_exists {
+        return Err(format!("Agent {} not found", agent_id));
+    }
+    let Some(sender) = sender else {
+        return Err("Message sender not available".to_string());
+    };
+
+    let (tx, rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
+    sender
+        .send(ListenerCommand::RespondFileWrite {
+            request_id,
+            accept,
+            response: tx,
+        })
+        .map_err(|e| format!("Failed to queue file write response: {}", e))?;
+
+    match timeout(RESPOND_TIMEOUT, rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err("File write response channel closed".to_string()),
+        Err(_) => Err("File write response timeout".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(tool: &str, path_prefix: Option<&str>, decision: PermissionDecision) -> PermissionRule {
+        PermissionRule {
+            id: format!("{}-{:?}", tool, decision),
+            tool: tool.to_string(),
+            path_prefix: path_prefix.map(|item| item.to_string()),
+            pattern: None,
+            decision,
+            created_at: String::new(),
+        }
+    }
+
+    fn ws() -> Vec<String> {
+        vec!["/ws".to_string()]
+    }
+
+    fn request(kind: &str, path: &str) -> Value {
+        json!({
+            "toolCall": {
+                "kind": kind,
+                "title": "Tool",
+                "locations": [{ "path": path }],
+            },
+            "options": [
+                { "optionId": "proceed_once", "kind": "allow_once" },
+                { "optionId": "cancel", "kind": "reject_once" },
+            ],
+        })
+    }
+
+    #[test]
+    fn path_scoped_allow_and_global_deny() {
+        let rules = compile_rules(vec![
+            rule("read", Some("src"), PermissionDecision::Allow),
+            rule("execute", None, PermissionDecision::Deny),
+        ]);
+
+        let subject = parse_permission_subject(&request("read", "/ws/src/main.rs"), &ws());
+        let matched = match_rule(&rules, &subject).expect("read rule");
+        assert_eq!(matched.decision, PermissionDecision::Allow);
+
+        let outside = parse_permission_subject(&request("read", "/ws/docs/a.md"), &ws());
+        assert!(match_rule(&rules, &outside).is_none());
+
+        let prefix_sibling = parse_permission_subject(&request("read", "/ws/src-old/a.rs"), &ws());
+        assert!(match_rule(&rules, &prefix_sibling).is_none());
+
+        let shell = request("execute", "/ws");
+        let subject = parse_permission_subject(&shell, &ws());
+        let matched = match_rule(&rules, &subject).expect("execute rule");
+        assert_eq!(
+            outcome_for_decision(&shell, matched.decision),
+            json!({ "outcome": "selected", "optionId": "cancel" })
+        );
+    }
+
+    #[test]
+    fn command_pattern_denies_matching_shell_calls() {
+        let mut rm_rule = rule("run_shell_command", None, PermissionDecision::Deny);
+        rm_rule.pattern = normalize_rule_pattern(Some(r"\brm\s+-rf\b".to_string())).unwrap();
+        let rules = compile_rules(vec![
+            rule("read_file", None, PermissionDecision::Allow),
+            rm_rule,
+        ]);
+
+        let tool_call = |command: &str| {
+            json!({
+                "sessionUpdate": "tool_call",
+                "toolName": "run_shell_command",
+                "kind": "execute",
+                "title": "Shell",
+                "rawInput": { "command": command },
+            })
+        };
+        let subject = parse_tool_subject(&tool_call("rm -rf build/"), &ws());
+        assert_eq!(
+            match_rule(&rules, &subject).map(|rule| rule.decision),
+            Some(PermissionDecision::Deny)
+        );
+        let subject = parse_tool_subject(&tool_call("ls -la"), &ws());
+        assert!(match_rule(&rules, &subject).is_none());
+
+        let read = parse_tool_subject(&json!({ "toolName": "read_file", "kind": "read" }), &ws());
+        assert_eq!(
+            match_rule(&rules, &read).map(|rule| rule.decision),
+            Some(PermissionDecision::Allow)
+        );
+        assert!(normalize_rule_pattern(Some("(".to_string())).is_err());
+    }
+
+    #[test]
+    fn user_choice_must_be_an_offered_option() {
+        let params = request("edit", "/ws/src/main.rs");
+        assert_eq!(
+            outcome_for_option(&params, Some("proceed_once")).unwrap(),
+            json!({ "outcome": "selected", "optionId": "proceed_once" })
+        );
+        assert_eq!(
+            outcome_for_option(&params, None).unwrap(),
+            json!({ "outcome": "cancelled" })
+        );
+        assert!(outcome_for_option(&params, Some("allow_always")).is_err());
+    }
+
+    #[test]
+    fn parent_segments_and_mixed_paths_cannot_escape_rules() {
+        let rules = compile_rules(vec![
+            rule("read", Some("src"), PermissionDecision::Allow),
+            rule("edit", Some("secrets"), PermissionDecision::Deny),
+        ]);
+        let escaping = parse_permission_subject(&request("read", "src/../../etc/passwd"), &ws());
+        assert_eq!(escaping.paths, vec!["/etc/passwd".to_string()]);
+        assert!(match_rule(&rules, &escaping).is_none());
+        let absolute =
+            parse_permission_subject(&request("read", "/ws/src/../../etc/passwd"), &ws());
+        assert!(match_rule(&rules, &absolute).is_none());
+        let inside = parse_permission_subject(&request("read", "/ws/docs/../src/./lib.rs"), &ws());
+        assert_eq!(inside.paths, vec!["src/lib.rs".to_string()]);
+        assert!(match_rule(&rules, &inside).is_some());
+
+        let mixed = |kind: &str, first: &str, second: &str| {
+            let mut params = request(kind, first);
+            params["toolCall"]["locations"]
+                .as_array_mut()
+                .unwrap()
+                .push(json!({ "path": second }));
+            parse_permission_subject(&params, &ws())
+        };
+        let deny_with_extra = mixed("edit", "/ws/secrets/key.pem", "/ws/README.md");
+        assert_eq!(
+            match_rule(&rules, &deny_with_extra).map(|rule| rule.decision),
+            Some(PermissionDecision::Deny)
+        );
+        let sneaky = mixed("edit", "/ws/README.md", "src/../secrets/key.pem");
+        assert_eq!(
+            match_rule(&rules, &sneaky).map(|rule| rule.decision),
+            Some(PermissionDecision::Deny)
+        );
+        let allow_with_extra = mixed("read", "/ws/src/main.rs", "/ws/docs/a.md");
+        assert!(match_rule(&rules, &allow_with_extra).is_none());
+
+        assert_eq!(
+            normalize_rule_path(Some("./src/../lib/".to_string())),
+            Some("lib".to_string())
+        );
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let rules = compile_rules(vec![
+            rule("*", None, PermissionDecision::Allow),
+            rule("edit", Some("secrets"), PermissionDecision::Deny),
+        ]);
+        let subject = parse_permission_subject(&request("edit", "/ws/secrets/key.pem"), &ws());
+        assert_eq!(
+            match_rule(&rules, &subject).map(|rule| rule.decision),
+            Some(PermissionDecision::Deny)
+        );
+        assert_eq!(
+            outcome_for_decision(&json!({}), PermissionDecision::Deny),
+            json!({ "outcome": "cancelled" })
+        );
+    }
+}
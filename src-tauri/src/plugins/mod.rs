@@ -0,0 +1,419 @@
+//! WASM 插件：插件目录包含 `plugin.json` 清单与 `plugin.wasm` 模块，
+//! 可订阅事件钩子（prompt / task_finish / tool_call）并声明可被前端调用的动作；
+//! 能力需在安装时显式授予，运行时仅链接已授予能力对应的宿主函数。
+mod runtime;
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{Manager, State};
+use tokio::fs;
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag};
+
+const MANIFEST_FILE: &str = "plugin.json";
+const MODULE_FILE: &str = "plugin.wasm";
+const MAX_PLUGIN_MODULE_BYTES: u64 = 32 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginHook {
+    Prompt,
+    TaskFinish,
+    ToolCall,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginCapability {
+    /// 通过 plugin-event 向前端推送事件
+    EmitEvents,
+    /// 只读访问当前 Agent 工作区内的文件
+    ReadWorkspace,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginAction {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginManifest {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub hooks: Vec<PluginHook>,
+    #[serde(default)]
+    pub actions: Vec<PluginAction>,
+    /// 插件申请的能力，实际生效的是安装时授予的子集
+    #[serde(default)]
+    pub capabilities: Vec<PluginCapability>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledPlugin {
+    pub manifest: PluginManifest,
+    pub granted: Vec<PluginCapability>,
+    pub installed_at: String,
+}
+
+/// 已编译、可直接调用的插件
+pub(crate) struct LoadedPlugin {
+    record: InstalledPlugin,
+    module: wasmtime::Module,
+}
+
+fn plugins_root(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join("plugins"))
+}
+
+fn registry_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-plugins-{}.json", storage_env_tag())))
+}
+
+async fn read_registry_from_path(path: &Path) -> Result<Vec<InstalledPlugin>, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(Vec::new());
+            }
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse plugin registry: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(format!("Failed to read plugin registry: {}", err)),
+    }
+}
+
+async fn write_registry_to_path(path: &Path, plugins: &[InstalledPlugin]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create plugin registry dir: {}", e))?;
+    }
+    let payload = serde_json::to_vec(plugins)
+        .map_err(|e| format!("Failed to encode plugin registry: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write plugin registry: {}", e))
+}
+
+fn validate_manifest(manifest: &PluginManifest) -> Result<(), String> {
+    let valid_id = !manifest.id.is_empty()
+        && manifest
+            .id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.'))
+        && !manifest.id.starts_with('.');
+    if !valid_id {
+        return Err(format!(
+            "Invalid plugin id {:?}: use lowercase letters, digits, '-', '_' or '.'",
+            manifest.id
+        ));
+    }
+    if manifest.name.trim().is_empty() {
+        return Err("Plugin name cannot be empty".to_string());
+    }
+    Ok(())
+}
+
+/// 启动时编译已安装插件；单个插件失败时跳过
+pub(crate) async fn init_plugins(app_handle: &tauri::AppHandle) {
+    let (registry, root) = match (registry_path(app_handle), plugins_root(app_handle)) {
+        (Ok(registry), Ok(root)) => (registry, root),
+        (Err(e), _) | (_, Err(e)) => {
+            println!("[plugins] Failed to resolve plugin dir: {}", e);
+            return;
+        }
+    };
+    let installed = match read_registry_from_path(&registry).await {
+        Ok(installed) => installed,
+        Err(e) => {
+            println!("[plugins] Failed to load registry: {}", e);
+            return;
+        }
+    };
+
+    let mut loaded = Vec::new();
+    for record in installed {
+        let module_path = root.join(&record.manifest.id).join(MODULE_FILE);
+        let compiled = tokio::task::spawn_blocking(move || runtime::load_module(&module_path))
+            .await
+            .map_err(|e| format!("Plugin compile task failed: {}", e))
+            .and_then(|result| result);
+        match compiled {
+            Ok(module) => loaded.push(LoadedPlugin { record, module }),
+            Err(e) => println!("[plugins] Skipping {}: {}", record.manifest.id, e),
+        }
+    }
+    *app_handle.state::<AppState>().plugins.write().await = loaded;
+}
+
+fn emit_plugin_events(app_handle: &tauri::AppHandle, plugin_id: &str, events: Vec<Value>) {
+    for payload in events {
+        let _ = app_handle.emit_unless_locked(
+            "plugin-event",
+            json!({
+                "pluginId": plugin_id,
+                "payload": payload,
+            }),
+        );
+    }
+}
+
+/// 把事件分发给订阅了该钩子的插件（后台执行，不阻塞调用方）
+pub(crate) fn dispatch_plugin_hook(
+    app_handle: &tauri::AppHandle,
+    hook: PluginHook,
+    workspace_path: Option<&str>,
+    payload: Value,
+) {
+    let app_handle = app_handle.clone();
+    let workspace_path = workspace_path.map(|item| item.to_string());
+    tokio::spawn(async move {
+        let subscribers: Vec<(String, Vec<PluginCapability>, wasmtime::Module)> = app_handle
+            .state::<AppState>()
+            .plugins
+            .read()
+            .await
+            .iter()
+            .filter(|plugin| plugin.record.manifest.hooks.contains(&hook))
+            .map(|plugin| {
+                (
+                    plugin.record.manifest.id.clone(),
+                    plugin.record.granted.clone(),
+                    plugin.module.clone(),
+                )
+            })
+            .collect();
+        if subscribers.is_empty() {
+            return;
+        }
+
+        let input = json!({ "hook": hook, "payload": payload });
+        for (plugin_id, granted, module) in subscribers {
+            let input = input.clone();
+            let workspace_path = workspace_path.clone();
+            let call_id = plugin_id.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                runtime::call_plugin(
+                    &call_id,
+                    &module,
+                    &granted,
+                    workspace_path.as_deref(),
+                    "on_event",
+                    &input,
+                )
+            })
+            .await
+            .map_err(|e| format!("Plugin task failed: {}", e))
+            .and_then(|result| result);
+            match result {
+                Ok(output) => emit_plugin_events(&app_handle, &plugin_id, output.emitted),
+                Err(e) => println!("[plugins] Hook {:?} failed in {}: {}", hook, plugin_id, e),
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn list_plugins(state: State<'_, AppState>) -> Result<Vec<InstalledPlugin>, String> {
+    Ok(state
+        .plugins
+        .read()
+        .await
+        .iter()
+        .map(|plugin| plugin.record.clone())
+        .collect())
+}
+
+/// 从插件目录安装（或覆盖升级）插件；granted 为用户确认授予的能力
+#[tauri::command]
+pub async fn install_plugin(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    source_dir: String,
+    granted: Vec<PluginCapability>,
+) -> Result<InstalledPlugin, String> {
+    let source_dir = PathBuf::from(source_dir.trim());
+    let manifest_content = fs::read_to_string(source_dir.join(MANIFEST_FILE))
+        .await
+        .map_err(|e| format!("Failed to read plugin manifest: {}", e))?;
+    let manifest: PluginManifest = serde_json::from_str(&manifest_content)
+        .map_err(|e| format!("Failed to parse plugin manifest: {}", e))?;
+    validate_manifest(&manifest)?;
+
+    if let Some(extra) = granted
+        .iter()
+        .find(|capability| !manifest.capabilities.contains(capability))
+    {
+        return Err(format!("Plugin did not request capability {:?}", extra));
+    }
+
+    let module_source = source_dir.join(MODULE_FILE);
+    let module_size = fs::metadata(&module_source)
+        .await
+        .map_err(|e| format!("Failed to stat plugin module: {}", e))?
+        .len();
+    if module_size > MAX_PLUGIN_MODULE_BYTES {
+        return Err(format!(
+            "Plugin module is too large (>{} bytes)",
+            MAX_PLUGIN_MODULE_BYTES
+        ));
+    }
+    let module_bytes = fs::read(&module_source)
+        .await
+        .map_err(|e| format!("Failed to read plugin module: {}", e))?;
+    let granted_for_check = granted.clone();
+    let (module, module_bytes) = tokio::task::spawn_blocking(move || {
+        let module = runtime::compile_module(&module_bytes)?;
+        runtime::check_imports(&module, &granted_for_check)?;
+        Ok::<_, String>((module, module_bytes))
+    })
+    .await
+    .map_err(|e| format!("Plugin compile task failed: {}", e))??;
+
+    let mut plugins = state.plugins.write().await;
+    let target_dir = plugins_root(&app_handle)?.join(&manifest.id);
+    fs::create_dir_all(&target_dir)
+        .await
+        .map_err(|e| format!("Failed to create plugin dir: {}", e))?;
+    fs::write(target_dir.join(MODULE_FILE), &module_bytes)
+        .await
+        .map_err(|e| format!("Failed to install plugin module: {}", e))?;
+    fs::write(target_dir.join(MANIFEST_FILE), manifest_content)
+        .await
+        .map_err(|e| format!("Failed to install plugin manifest: {}", e))?;
+
+    let record = InstalledPlugin {
+        manifest,
+        granted,
+        installed_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let path = registry_path(&app_handle)?;
+    let mut registry = read_registry_from_path(&path).await?;
+    registry.retain(|item| item.manifest.id != record.manifest.id);
+    registry.push(record.clone());
+    write_registry_to_path(&path, &registry).await?;
+
+    plugins.retain(|plugin| plugin.record.manifest.id != record.manifest.id);
+    plugins.push(LoadedPlugin {
+        record: record.clone(),
+        module,
+    });
+    Ok(record)
+}
+
+#[tauri::command]
+pub async fn remove_plugin(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    plugin_id: String,
+) -> Result<bool, String> {
+    let mut plugins = state.plugins.write().await;
+    let path = registry_path(&app_handle)?;
+    let mut registry = read_registry_from_path(&path).await?;
+    let before = registry.len();
+    registry.retain(|item| item.manifest.id != plugin_id);
+    let removed = registry.len() != before;
+    if removed {
+        write_registry_to_path(&path, &registry).await?;
+        let plugin_dir = plugins_root(&app_handle)?.join(&plugin_id);
+        if let Err(e) = fs::remove_dir_all(&plugin_dir).await {
+            if e.kind() != ErrorKind::NotFound {
+                println!("[plugins] Failed to remove {}: {}", plugin_dir.display(), e);
+            }
+        }
+    }
+    plugins.retain(|plugin| plugin.record.manifest.id != plugin_id);
+    Ok(removed)
+}
+
+/// 调用插件声明的动作，返回插件的 JSON 结果
+#[tauri::command]
+pub async fn invoke_plugin_action(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    plugin_id: String,
+    action: String,
+    args: Option<Value>,
+    agent_id: Option<String>,
+) -> Result<Value, String> {
+    let (granted, module) = {
+        let plugins = state.plugins.read().await;
+        let plugin = plugins
+            .iter()
+            .find(|plugin| plugin.record.manifest.id == plugin_id)
+            .ok_or_else(|| format!("Plugin {} not found", plugin_id))?;
+        if !plugin
+            .record
+            .manifest
+            .actions
+            .iter()
+            .any(|item| item.name == action)
+        {
+            return Err(format!("Plugin {} has no action {}", plugin_id, action));
+        }
+        (plugin.record.granted.clone(), plugin.module.clone())
+    };
+    let workspace_path = match agent_id {
+        Some(agent_id) => state.agent_manager.workspace_path_of(&agent_id).await,
+        None => None,
+    };
+
+    let input = json!({ "action": action, "args": args.unwrap_or(Value::Null) });
+    let call_id = plugin_id.clone();
+    let output = tokio::task::spawn_blocking(move || {
+        runtime::call_plugin(
+            &call_id,
+            &module,
+            &granted,
+            workspace_path.as_deref(),
+            "invoke_action",
+            &input,
+        )
+    })
+    .await
+    .map_err(|e| format!("Plugin task failed: {}", e))??;
+
+    emit_plugin_events(&app_handle, &plugin_id, output.emitted);
+    Ok(output.response.unwrap_or(Value::Null))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_defaults_and_id_validation() {
+        let manifest: PluginManifest = serde_json::from_value(json!({
+            "id": "unit-converter",
+            "name": "Unit converter",
+            "version": "0.1.0",
+            "hooks": ["task_finish"],
+            "capabilities": ["emit_events"],
+        }))
+        .expect("parse manifest");
+        assert!(manifest.actions.is_empty());
+        assert_eq!(manifest.hooks, vec![PluginHook::TaskFinish]);
+        assert!(validate_manifest(&manifest).is_ok());
+
+        let invalid = PluginManifest {
+            id: "../escape".to_string(),
+            ..manifest
+        };
+        assert!(validate_manifest(&invalid).is_err());
+    }
+}
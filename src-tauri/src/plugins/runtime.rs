@@ -0,0 +1,316 @@
+//! wasmtime 宿主：每次调用新建独立 Store（燃料与内存上限），仅链接插件被授予能力对应的宿主函数。
+//!
+//! 插件 ABI（JSON 经线性内存传递）：
+//! - 导出 `memory`、`alloc(len: i32) -> i32`
+//! - 导出 `on_event(ptr: i32, len: i32) -> i64` / `invoke_action(ptr: i32, len: i32) -> i64`，
+//!   返回 `(ptr << 32) | len` 指向的 JSON 结果，0 表示无结果
+//! - 可导入 `flowhub.log(ptr, len)`；`flowhub.emit(ptr, len)` 需 emit_events 能力；
+//!   `flowhub.read_file(ptr, len) -> i64` 需 read_workspace 能力，失败返回 -1
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use wasmtime::{
+    Caller, Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+use super::PluginCapability;
+
+const HOST_MODULE: &str = "flowhub";
+const CALL_FUEL: u64 = 500_000_000;
+const MAX_PLUGIN_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+const MAX_PLUGIN_IO_BYTES: usize = 1024 * 1024;
+
+static ENGINE: Lazy<Engine> = Lazy::new(|| {
+    let mut config = Config::new();
+    config.consume_fuel(true);
+    Engine::new(&config).expect("wasmtime engine")
+});
+
+struct HostState {
+    plugin_id: String,
+    workspace_path: Option<String>,
+    limits: StoreLimits,
+    /// 插件通过 emit 发出的事件，调用结束后由调用方统一推送
+    emitted: Vec<Value>,
+}
+
+/// 一次插件调用的结果
+pub(crate) struct PluginCallOutput {
+    pub(crate) response: Option<Value>,
+    pub(crate) emitted: Vec<Value>,
+}
+
+fn required_capability(import_name: &str) -> Result<Option<PluginCapability>, String> {
+    match import_name {
+        "log" => Ok(None),
+        "emit" => Ok(Some(PluginCapability::EmitEvents)),
+        "read_file" => Ok(Some(PluginCapability::ReadWorkspace)),
+        other => Err(format!("Unknown host function {}.{}", HOST_MODULE, other)),
+    }
+}
+
+pub(crate) fn compile_module(bytes: &[u8]) -> Result<Module, String> {
+    Module::new(&ENGINE, bytes).map_err(|e| format!("Failed to compile plugin: {}", e))
+}
+
+pub(crate) fn load_module(path: &Path) -> Result<Module, String> {
+    Module::from_file(&ENGINE, path)
+        .map_err(|e| format!("Failed to load plugin {}: {}", path.display(), e))
+}
+
+/// 检查模块导入是否都在已授予的能力范围内
+pub(crate) fn check_imports(module: &Module, granted: &[PluginCapability]) -> Result<(), String> {
+    for import in module.imports() {
+        if import.module() != HOST_MODULE {
+            return Err(format!(
+                "Plugin imports unsupported module {}.{}",
+                import.module(),
+                import.name()
+            ));
+        }
+        if let Some(capability) = required_capability(import.name())? {
+            if !granted.contains(&capability) {
+                return Err(format!(
+                    "Plugin requires capability {:?} which was not granted",
+                    capability
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn caller_memory(caller: &mut Caller<'_, HostState>) -> Option<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+}
+
+fn read_guest_string(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Option<String> {
+    if ptr < 0 || len < 0 || len as usize > MAX_PLUGIN_IO_BYTES {
+        return None;
+    }
+    let memory = caller_memory(caller)?;
+    let mut buffer = vec![0_u8; len as usize];
+    memory.read(&*caller, ptr as usize, &mut buffer).ok()?;
+    String::from_utf8(buffer).ok()
+}
+
+/// 把宿主数据写入插件内存（通过插件导出的 alloc 分配），返回打包后的指针与长度
+fn write_guest_bytes(caller: &mut Caller<'_, HostState>, bytes: &[u8]) -> Option<i64> {
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(|export| export.into_func())?
+        .typed::<i32, i32>(&*caller)
+        .ok()?;
+    let len = i32::try_from(bytes.len()).ok()?;
+    let ptr = alloc.call(&mut *caller, len).ok()?;
+    let memory = caller_memory(caller)?;
+    memory.write(&mut *caller, ptr as usize, bytes).ok()?;
+    Some(((ptr as u32 as i64) << 32) | len as u32 as i64)
+}
+
+fn resolve_workspace_file(workspace_path: &str, requested: &str) -> Option<PathBuf> {
+    let root = std::fs::canonicalize(workspace_path).ok()?;
+    let target = std::fs::canonicalize(root.join(requested)).ok()?;
+    target.starts_with(&root).then_some(target)
+}
+
+fn build_linker(granted: &[PluginCapability]) -> Result<Linker<HostState>, String> {
+    let mut linker = Linker::new(&ENGINE);
+    linker
+        .func_wrap(
+            HOST_MODULE,
+            "log",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                if let Some(message) = read_guest_string(&mut caller, ptr, len) {
+                    println!("[plugin:{}] {}", caller.data().plugin_id, message);
+                }
+            },
+        )
+        .map_err(|e| format!("Failed to define host function: {}", e))?;
+
+    if granted.contains(&PluginCapability::EmitEvents) {
+        linker
+            .func_wrap(
+                HOST_MODULE,
+                "emit",
+                |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                    let payload = read_guest_string(&mut caller, ptr, len)
+                        .and_then(|text| serde_json::from_str::<Value>(&text).ok());
+                    if let Some(payload) = payload {
+                        caller.data_mut().emitted.push(payload);
+                    }
+                },
+            )
+            .map_err(|e| format!("Failed to define host function: {}", e))?;
+    }
+
+    if granted.contains(&PluginCapability::ReadWorkspace) {
+        linker
+            .func_wrap(
+                HOST_MODULE,
+                "read_file",
+                |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> i64 {
+                    let Some(requested) = read_guest_string(&mut caller, ptr, len) else {
+                        return -1;
+                    };
+                    let Some(workspace_path) = caller.data().workspace_path.clone() else {
+                        return -1;
+                    };
+                    let Some(path) = resolve_workspace_file(&workspace_path, &requested) else {
+                        return -1;
+                    };
+                    let content = match std::fs::read(&path) {
+                        Ok(content) if content.len() <= MAX_PLUGIN_IO_BYTES => content,
+                        _ => return -1,
+                    };
+                    write_guest_bytes(&mut caller, &content).unwrap_or(-1)
+                },
+            )
+            .map_err(|e| format!("Failed to define host function: {}", e))?;
+    }
+
+    Ok(linker)
+}
+
+/// 调用插件导出函数（on_event / invoke_action），输入输出均为 JSON
+pub(crate) fn call_plugin(
+    plugin_id: &str,
+    module: &Module,
+    granted: &[PluginCapability],
+    workspace_path: Option<&str>,
+    export: &str,
+    input: &Value,
+) -> Result<PluginCallOutput, String> {
+    check_imports(module, granted)?;
+
+    let mut store = Store::new(
+        &ENGINE,
+        HostState {
+            plugin_id: plugin_id.to_string(),
+            workspace_path: workspace_path.map(|item| item.to_string()),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(MAX_PLUGIN_MEMORY_BYTES)
+                .instances(1)
+                .build(),
+            emitted: Vec::new(),
+        },
+    );
+    store.limiter(|state| &mut state.limits);
+    store
+        .set_fuel(CALL_FUEL)
+        .map_err(|e| format!("Failed to set plugin fuel: {}", e))?;
+
+    let linker = build_linker(granted)?;
+    let instance = linker
+        .instantiate(&mut store, module)
+        .map_err(|e| format!("Failed to instantiate plugin: {}", e))?;
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .ok_or_else(|| "Plugin does not export memory".to_string())?;
+    let alloc = instance
+        .get_typed_func::<i32, i32>(&mut store, "alloc")
+        .map_err(|e| format!("Plugin does not export alloc: {}", e))?;
+    let entry = instance
+        .get_typed_func::<(i32, i32), i64>(&mut store, export)
+        .map_err(|e| format!("Plugin does not export {}: {}", export, e))?;
+
+    let payload =
+        serde_json::to_vec(input).map_err(|e| format!("Failed to encode plugin input: {}", e))?;
+    let len = i32::try_from(payload.len()).map_err(|_| "Plugin input is too large".to_string())?;
+    let ptr = alloc
+        .call(&mut store, len)
+        .map_err(|e| format!("Plugin alloc failed: {}", e))?;
+    memory
+        .write(&mut store, ptr as usize, &payload)
+        .map_err(|e| format!("Failed to write plugin input: {}", e))?;
+
+    let packed = entry
+        .call(&mut store, (ptr, len))
+        .map_err(|e| format!("Plugin {} failed: {}", export, e))?;
+    let response = if packed == 0 {
+        None
+    } else {
+        let out_ptr = (packed as u64 >> 32) as usize;
+        let out_len = (packed as u64 & 0xFFFF_FFFF) as usize;
+        if out_len > MAX_PLUGIN_IO_BYTES {
+            return Err("Plugin response is too large".to_string());
+        }
+        let mut buffer = vec![0_u8; out_len];
+        memory
+            .read(&store, out_ptr, &mut buffer)
+            .map_err(|e| format!("Failed to read plugin response: {}", e))?;
+        Some(
+            serde_json::from_slice(&buffer)
+                .map_err(|e| format!("Plugin returned invalid JSON: {}", e))?,
+        )
+    };
+
+    Ok(PluginCallOutput {
+        response,
+        emitted: std::mem::take(&mut store.data_mut().emitted),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const ECHO_PLUGIN: &str = r#"
+        (module
+          (import "flowhub" "emit" (func $emit (param i32 i32)))
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            global.get $next
+            local.set $ptr
+            global.get $next
+            local.get $len
+            i32.add
+            global.set $next
+            local.get $ptr)
+          (func (export "on_event") (param $ptr i32) (param $len i32) (result i64)
+            local.get $ptr
+            local.get $len
+            call $emit
+            local.get $ptr
+            i64.extend_i32_u
+            i64.const 32
+            i64.shl
+            local.get $len
+            i64.extend_i32_u
+            i64.or))
+    "#;
+
+    #[test]
+    fn plugin_echoes_and_emits_with_granted_capability() {
+        let module = compile_module(ECHO_PLUGIN.as_bytes()).expect("compile");
+        let input = json!({ "hook": "task_finish", "payload": { "reason": "end_turn" } });
+
+        let output = call_plugin(
+            "echo",
+            &module,
+            &[PluginCapability::EmitEvents],
+            None,
+            "on_event",
+            &input,
+        )
+        .expect("call");
+        assert_eq!(output.response, Some(input.clone()));
+        assert_eq!(output.emitted, vec![input]);
+    }
+
+    #[test]
+    fn ungranted_capability_is_rejected() {
+        let module = compile_module(ECHO_PLUGIN.as_bytes()).expect("compile");
+        let Err(error) = call_plugin("echo", &module, &[], None, "on_event", &json!({})) else {
+            panic!("ungranted capability should be rejected");
+        };
+        assert!(error.contains("EmitEvents"));
+    }
+}
@@ -0,0 +1,314 @@
+//! 回复后处理钩子：任务结束后把完整助手回复依次通过用户配置的外部命令（stdin 输入、stdout 输出），
+//! 结果以 message-postprocessed 事件推送，前端据此替换该任务的最终回复并重新保存。
+//! 单个钩子超时或失败时跳过该钩子，不影响原回复。
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::Manager;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::capabilities::{capability_enabled, CommandCapability};
+use crate::runtime_env::{resolve_executable_path, runtime_path_env};
+use crate::state::AppState;
+use crate::tasks::update_task_record;
+
+const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 10;
+const MAX_HOOK_TIMEOUT_SECS: u64 = 120;
+const MAX_HOOK_OUTPUT_BYTES: usize = 1024 * 1024;
+const MAX_HOOK_STDERR_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PostprocessHook {
+    pub name: String,
+    /// 可执行文件名（按运行时 PATH 查找）、绝对路径或工作区相对路径
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HookFailure {
+    pub name: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PostprocessOutcome {
+    pub content: String,
+    pub applied: Vec<String>,
+    pub failures: Vec<HookFailure>,
+}
+
+pub(crate) fn resolve_hook_command(command: &str, workspace_path: &str) -> Result<PathBuf, String> {
+    let path = Path::new(command.trim());
+    if !path.is_absolute() && path.components().count() > 1 {
+        return resolve_executable_path(&Path::new(workspace_path).join(path).to_string_lossy());
+    }
+    resolve_executable_path(command)
+}
+
+async fn run_hook(
+    hook: &PostprocessHook,
+    agent_id: &str,
+    workspace_path: &str,
+    input: &str,
+) -> Result<String, String> {
+    let executable = resolve_hook_command(&hook.command, workspace_path)?;
+    let timeout_secs = hook
+        .timeout_secs
+        .unwrap_or(DEFAULT_HOOK_TIMEOUT_SECS)
+        .clamp(1, MAX_HOOK_TIMEOUT_SECS);
+
+    let mut cmd = Command::new(executable);
+    cmd.args(&hook.args)
+        .current_dir(workspace_path)
+        .env("PATH", runtime_path_env()?)
+        .env("FLOWHUB_AGENT_ID", agent_id)
+        .env("FLOWHUB_WORKSPACE", workspace_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to start hook: {}", e))?;
+    let stdin = child.stdin.take();
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    let run = async move {
+        // stderr 只保留开头部分用于报错，在后台读取以免钩子写满管道后阻塞
+        let stderr_reader = tokio::spawn(async move {
+            let mut buffer = Vec::new();
+            if let Some(stderr) = stderr {
+                let _ = stderr
+                    .take(MAX_HOOK_STDERR_BYTES)
+                    .read_to_end(&mut buffer)
+                    .await;
+            }
+            buffer
+        });
+        // stdin 同样在后台写入：边读边写的过滤型钩子在回复超过管道缓冲区时，
+        // 若先写完 stdin 再读 stdout，双方会互相等待直到超时
+        let input = input.to_string();
+        let stdin_writer = tokio::spawn(async move {
+            if let Some(mut stdin) = stdin {
+                stdin
+                    .write_all(input.as_bytes())
+                    .await
+                    .map_err(|e| format!("Failed to write hook input: {}", e))?;
+                // 关闭 stdin，让按行读取到 EOF 的钩子能够结束
+                drop(stdin);
+            }
+            Ok::<(), String>(())
+        });
+        // 最多读取上限 + 1 字节，超出即终止钩子，不在内存中缓存完整输出
+        let mut output = Vec::new();
+        if let Some(stdout) = stdout {
+            stdout
+                .take(MAX_HOOK_OUTPUT_BYTES as u64 + 1)
+                .read_to_end(&mut output)
+                .await
+                .map_err(|e| format!("Failed to read hook output: {}", e))?;
+        }
+        if output.len() > MAX_HOOK_OUTPUT_BYTES {
+            let _ = child.kill().await;
+            stdin_writer.abort();
+            stderr_reader.abort();
+            return Err(format!(
+                "Hook output is too large (>{} bytes)",
+                MAX_HOOK_OUTPUT_BYTES
+            ));
+        }
+        stdin_writer
+            .await
+            .map_err(|e| format!("Hook input task failed: {}", e))??;
+        let status = child
+            .wait()
+            .await
+            .map_err(|e| format!("Hook failed: {}", e))?;
+        let stderr = stderr_reader.await.unwrap_or_default();
+        Ok((status, output, stderr))
+    };
+    let (status, stdout, stderr) = timeout(Duration::from_secs(timeout_secs), run)
+        .await
+        .map_err(|_| format!("Hook timeout after {} seconds", timeout_secs))??;
+
+    if !status.success() {
+        let error = String::from_utf8_lossy(&stderr).trim().to_string();
+        return Err(if error.is_empty() {
+            format!("Hook exited with {}", status)
+        } else {
+            format!("Hook exited with {}: {}", status, error)
+        });
+    }
+    String::from_utf8(stdout).map_err(|e| format!("Hook output is not UTF-8: {}", e))
+}
+
+/// 依次执行启用的钩子；空输出视为不修改
+pub(crate) async fn run_postprocess_hooks(
+    hooks: &[PostprocessHook],
+    agent_id: &str,
+    workspace_path: &str,
+    content: &str,
+) -> PostprocessOutcome {
+    let mut outcome = PostprocessOutcome {
+        content: content.to_string(),
+        applied: Vec::new(),
+        failures: Vec::new(),
+    };
+    for hook in hooks.iter().filter(|hook| hook.enabled) {
+        match run_hook(hook, agent_id, workspace_path, &outcome.content).await {
+            Ok(output) if output.trim().is_empty() || output == outcome.content => {}
+            Ok(output) => {
+                outcome.content = output;
+                outcome.applied.push(hook.name.clone());
+            }
+            Err(error) => {
+                println!("[postprocess] Hook {} failed: {}", hook.name, error);
+                outcome.failures.push(HookFailure {
+                    name: hook.name.clone(),
+                    error,
+                });
+            }
+        }
+    }
+    outcome
+}
+
+/// 任务结束后在后台执行后处理钩子；未配置钩子时不做任何事
+pub(crate) fn spawn_postprocess(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    workspace_path: &str,
+    task_id: &str,
+    message: &str,
+) {
+    if !capability_enabled(app_handle, CommandCapability::Process) {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    let agent_id = agent_id.to_string();
+    let workspace_path = workspace_path.to_string();
+    let task_id = task_id.to_string();
+    let message = message.to_string();
+    tokio::spawn(async move {
+        let hooks = app_handle
+            .state::<AppState>()
+            .settings
+            .read()
+            .await
+            .postprocess_hooks
+            .clone();
+        if !hooks.iter().any(|hook| hook.enabled) {
+            return;
+        }
+
+        let outcome = run_postprocess_hooks(&hooks, &agent_id, &workspace_path, &message).await;
+        if outcome.applied.is_empty() && outcome.failures.is_empty() {
+            return;
+        }
+
+        let summary = json!({
+            "applied": &outcome.applied,
+            "failures": &outcome.failures,
+        });
+        if let Err(e) = update_task_record(&app_handle, &task_id, |record| {
+            record.metadata.insert("postprocess".to_string(), summary);
+        })
+        .await
+        {
+            println!(
+                "[postprocess] Failed to store hook summary for {}: {}",
+                task_id, e
+            );
+        }
+
+        let _ = app_handle.emit_unless_locked(
+            "message-postprocessed",
+            json!({
+                "agentId": agent_id,
+                "taskId": task_id,
+                "content": outcome.content,
+                "changed": !outcome.applied.is_empty(),
+                "applied": outcome.applied,
+                "failures": outcome.failures,
+            }),
+        );
+    });
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn hooks_transform_and_isolate_failures() {
+        let hook = |name: &str, script: &str, timeout_secs: Option<u64>| PostprocessHook {
+            name: name.to_string(),
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            timeout_secs,
+            enabled: true,
+        };
+        let hooks = vec![
+            hook("upper", "tr a-z A-Z", None),
+            hook("broken", "echo boom >&2; exit 3", None),
+            hook("slow", "sleep 5", Some(1)),
+            hook(
+                "annotate",
+                "cat; printf '\\n-- %s' \"$FLOWHUB_AGENT_ID\"",
+                None,
+            ),
+            hook("flood", "yes", None),
+        ];
+        let workspace = std::env::temp_dir().to_string_lossy().to_string();
+
+        let outcome = run_postprocess_hooks(&hooks, "agent-1", &workspace, "hello").await;
+        assert_eq!(outcome.content, "HELLO\n-- agent-1");
+        assert_eq!(outcome.applied, vec!["upper", "annotate"]);
+        let failed: Vec<&str> = outcome
+            .failures
+            .iter()
+            .map(|failure| failure.name.as_str())
+            .collect();
+        assert_eq!(failed, vec!["broken", "slow", "flood"]);
+        assert!(outcome.failures[0].error.contains("boom"));
+        assert!(outcome.failures[2].error.contains("too large"));
+    }
+
+    #[tokio::test]
+    async fn streaming_hooks_handle_replies_larger_than_the_pipe_buffer() {
+        let hooks = vec![PostprocessHook {
+            name: "passthrough".to_string(),
+            command: "cat".to_string(),
+            args: Vec::new(),
+            timeout_secs: Some(5),
+            enabled: true,
+        }];
+        let workspace = std::env::temp_dir().to_string_lossy().to_string();
+        let reply = "line of agent output\n".repeat(8 * 1024);
+        assert!(reply.len() > 64 * 1024);
+
+        let outcome = run_postprocess_hooks(&hooks, "agent-1", &workspace, &reply).await;
+        assert!(outcome.failures.is_empty(), "{:?}", outcome.failures);
+        assert_eq!(outcome.content, reply);
+    }
+}
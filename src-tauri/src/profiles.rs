@@ -0,0 +1,315 @@
+//! 本机多用户档案：每个档案在应用数据目录下拥有独立的会话存储、设置、密钥与插件命名空间。
+//! 默认档案沿用应用数据目录根（兼容旧数据），其余档案位于 `profiles/<id>/`；
+//! 启动时选用上次的档案（可用 FLOWHUB_PROFILE 覆盖），运行中通过 switch_profile 切换。
+//! 合规保全期间禁止创建与切换档案（见 legal_hold）。
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, State};
+use tokio::fs;
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::state::AppState;
+use crate::storage::{base_app_data_dir, storage_env_tag};
+
+pub(crate) const DEFAULT_PROFILE_ID: &str = "default";
+const PROFILE_ENV_VAR: &str = "FLOWHUB_PROFILE";
+const MAX_PROFILE_NAME_CHARS: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileRegistry {
+    #[serde(default = "default_profile_id")]
+    pub active: String,
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+}
+
+fn default_profile_id() -> String {
+    DEFAULT_PROFILE_ID.to_string()
+}
+
+impl Default for ProfileRegistry {
+    fn default() -> Self {
+        Self {
+            active: default_profile_id(),
+            profiles: Vec::new(),
+        }
+    }
+}
+
+impl ProfileRegistry {
+    /// 默认档案始终存在，不需要写入注册表
+    fn with_default(mut self) -> Self {
+        if !self
+            .profiles
+            .iter()
+            .any(|profile| profile.id == DEFAULT_PROFILE_ID)
+        {
+            self.profiles.insert(
+                0,
+                Profile {
+                    id: default_profile_id(),
+                    name: "Default".to_string(),
+                    created_at: String::new(),
+                },
+            );
+        }
+        if !self.contains(&self.active) {
+            self.active = default_profile_id();
+        }
+        self
+    }
+
+    fn contains(&self, profile_id: &str) -> bool {
+        profile_id == DEFAULT_PROFILE_ID
+            || self.profiles.iter().any(|profile| profile.id == profile_id)
+    }
+}
+
+/// 档案数据目录；默认档案使用应用数据目录根
+pub(crate) fn profile_data_dir(base: &Path, profile_id: &str) -> PathBuf {
+    if profile_id == DEFAULT_PROFILE_ID {
+        base.to_path_buf()
+    } else {
+        base.join("profiles").join(profile_id)
+    }
+}
+
+fn registry_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(base_app_data_dir(app_handle)?.join(format!("iflow-profiles-{}.json", storage_env_tag())))
+}
+
+async fn read_registry_from_path(path: &Path) -> Result<ProfileRegistry, String> {
+    let registry = match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                ProfileRegistry::default()
+            } else {
+                serde_json::from_str(&content)
+                    .map_err(|e| format!("Failed to parse profiles: {}", e))?
+            }
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => ProfileRegistry::default(),
+        Err(err) => return Err(format!("Failed to read profiles: {}", err)),
+    };
+    Ok(registry.with_default())
+}
+
+async fn write_registry_to_path(path: &Path, registry: &ProfileRegistry) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create profiles dir: {}", e))?;
+    }
+    let payload = serde_json::to_vec_pretty(registry)
+        .map_err(|e| format!("Failed to encode profiles: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write profiles: {}", e))
+}
+
+/// 由名称生成目录安全的档案 ID
+fn profile_id_from_name(name: &str) -> String {
+    let slug: String = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '-' })
+        .collect();
+    let slug = slug.trim_matches('-');
+    let suffix = &uuid::Uuid::new_v4().simple().to_string()[..8];
+    if slug.is_empty() {
+        format!("profile-{}", suffix)
+    } else {
+        format!("{}-{}", slug.chars().take(24).collect::<String>(), suffix)
+    }
+}
+
+/// 当前档案 ID
+pub(crate) fn active_profile_id(state: &AppState) -> String {
+    state
+        .active_profile
+        .read()
+        .map(|active| active.clone())
+        .unwrap_or_else(|_| default_profile_id())
+}
+
+fn set_active_profile(state: &AppState, profile_id: &str) {
+    if let Ok(mut active) = state.active_profile.write() {
+        *active = profile_id.to_string();
+    }
+}
+
+/// 启动时确定当前档案，需在载入设置等其他数据之前调用
+pub(crate) async fn init_profiles(app_handle: &tauri::AppHandle) {
+    let registry = match registry_path(app_handle) {
+        Ok(path) => read_registry_from_path(&path).await,
+        Err(e) => Err(e),
+    };
+    let registry = match registry {
+        Ok(registry) => registry,
+        Err(e) => {
+            println!("[profiles] Failed to load profiles: {}", e);
+            return;
+        }
+    };
+    let requested = std::env::var(PROFILE_ENV_VAR)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    let active = match requested {
+        Some(profile_id) if registry.contains(&profile_id) => profile_id,
+        Some(profile_id) => {
+            println!(
+                "[profiles] Unknown profile {} from {}, using {}",
+                profile_id, PROFILE_ENV_VAR, registry.active
+            );
+            registry.active
+        }
+        None => registry.active,
+    };
+    set_active_profile(&app_handle.state::<AppState>(), &active);
+}
+
+#[tauri::command]
+pub async fn list_profiles(app_handle: tauri::AppHandle) -> Result<ProfileRegistry, String> {
+    read_registry_from_path(&registry_path(&app_handle)?).await
+}
+
+#[tauri::command]
+pub async fn create_profile(app_handle: tauri::AppHandle, name: String) -> Result<Profile, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+    if name.chars().count() > MAX_PROFILE_NAME_CHARS {
+        return Err(format!(
+            "Profile name is too long (>{} chars)",
+            MAX_PROFILE_NAME_CHARS
+        ));
+    }
+
+    let path = registry_path(&app_handle)?;
+    let mut registry = read_registry_from_path(&path).await?;
+    if registry.profiles.iter().any(|profile| profile.name == name) {
+        return Err(format!("Profile {} already exists", name));
+    }
+    let profile = Profile {
+        id: profile_id_from_name(&name),
+        name,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    registry.profiles.push(profile.clone());
+    write_registry_to_path(&path, &registry).await?;
+    Ok(profile)
+}
+
+/// 切换档案：要求没有已连接的 Agent，切换后重新载入该档案的设置（沿用已生效的能力限制与法律保留）、
+/// 插件与应用锁；前端收到 profile-switched 后整体重新载入
+#[tauri::command]
+pub async fn switch_profile(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    profile_id: String,
+) -> Result<ProfileRegistry, String> {
+    let (connected, _) = state.agent_manager.stats().await;
+    if connected > 0 {
+        return Err("Disconnect all agents before switching profiles".to_string());
+    }
+
+    let path = registry_path(&app_handle)?;
+    let mut registry = read_registry_from_path(&path).await?;
+    if !registry.contains(&profile_id) {
+        return Err(format!("Profile {} not found", profile_id));
+    }
+    registry.active = profile_id.clone();
+    write_registry_to_path(&path, &registry).await?;
+
+    set_active_profile(&state, &profile_id);
+    crate::settings::init_settings(&app_handle).await;
+    *state.plugins.write().await = Vec::new();
+    crate::plugins::init_plugins(&app_handle).await;
+    crate::app_lock::reload_app_lock(&app_handle).await;
+
+    let _ = app_handle.emit_unless_locked("profile-switched", &registry);
+    Ok(registry)
+}
+
+/// 删除档案及其全部数据；不能删除默认档案或当前档案
+#[tauri::command]
+pub async fn delete_profile(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    profile_id: String,
+) -> Result<bool, String> {
+    if profile_id == DEFAULT_PROFILE_ID {
+        return Err("The default profile cannot be deleted".to_string());
+    }
+    let active = state
+        .active_profile
+        .read()
+        .map(|active| active.clone())
+        .map_err(|_| "Failed to read active profile".to_string())?;
+    if active == profile_id {
+        return Err("Switch to another profile before deleting this one".to_string());
+    }
+
+    let path = registry_path(&app_handle)?;
+    let mut registry = read_registry_from_path(&path).await?;
+    let before = registry.profiles.len();
+    registry.profiles.retain(|profile| profile.id != profile_id);
+    if registry.profiles.len() == before {
+        return Ok(false);
+    }
+    write_registry_to_path(&path, &registry).await?;
+
+    let data_dir = profile_data_dir(&base_app_data_dir(&app_handle)?, &profile_id);
+    match fs::remove_dir_all(&data_dir).await {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => return Err(format!("Failed to delete profile data: {}", err)),
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_dirs_are_namespaced() {
+        let base = Path::new("/data/app");
+        assert_eq!(profile_data_dir(base, DEFAULT_PROFILE_ID), base);
+        assert_eq!(
+            profile_data_dir(base, "alice-1234abcd"),
+            base.join("profiles").join("alice-1234abcd")
+        );
+
+        let id = profile_id_from_name("Alice / QA");
+        assert!(id.starts_with("alice---qa-"));
+        assert!(id.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-'));
+        assert!(profile_id_from_name("张三").starts_with("profile-"));
+    }
+
+    #[test]
+    fn registry_always_contains_default_and_valid_active() {
+        let registry = ProfileRegistry {
+            active: "missing".to_string(),
+            profiles: Vec::new(),
+        }
+        .with_default();
+        assert_eq!(registry.active, DEFAULT_PROFILE_ID);
+        assert_eq!(registry.profiles[0].id, DEFAULT_PROFILE_ID);
+        assert!(!registry.contains("missing"));
+    }
+}
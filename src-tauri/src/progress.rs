@@ -0,0 +1,178 @@
+//! 长任务进度心跳：prompt 运行超过阈值后周期性推送 task-progress 事件
+//! （已用时、消息块数、最近一次工具调用、计划完成度与粗略剩余时间），替代静态转圈提示。
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::app_lock::EmitUnlessLocked;
+
+/// 运行超过该时长才开始推送心跳
+pub(crate) const PROGRESS_THRESHOLD: Duration = Duration::from_secs(10);
+/// 心跳间隔
+pub(crate) const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCallProgress {
+    pub id: String,
+    pub name: String,
+    pub status: String,
+}
+
+/// 当前轮次的进度计数
+#[derive(Debug, Default, Clone)]
+pub(crate) struct TaskProgress {
+    chunk_count: u64,
+    last_tool_call: Option<ToolCallProgress>,
+    plan_total: usize,
+    plan_completed: usize,
+}
+
+impl TaskProgress {
+    pub(crate) fn record_update(&mut self, update: &Value) {
+        match update
+            .get("sessionUpdate")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+        {
+            "agent_message_chunk" | "agent_thought_chunk" => self.chunk_count += 1,
+            "tool_call" | "tool_call_update" => {
+                let id = update
+                    .get("toolCallId")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let name = update
+                    .get("toolName")
+                    .and_then(Value::as_str)
+                    .or_else(|| update.get("title").and_then(Value::as_str))
+                    .map(|name| name.to_string());
+                let status = update.get("status").and_then(Value::as_str);
+
+                // tool_call_update 常只携带状态，沿用同一调用已知的名称
+                let previous = self.last_tool_call.take().filter(|call| call.id == id);
+                self.last_tool_call = Some(ToolCallProgress {
+                    name: name
+                        .or_else(|| previous.as_ref().map(|call| call.name.clone()))
+                        .unwrap_or_default(),
+                    status: status
+                        .map(|status| status.to_string())
+                        .or_else(|| previous.map(|call| call.status))
+                        .unwrap_or_else(|| "pending".to_string()),
+                    id,
+                });
+            }
+            "plan" => {
+                let entries = update
+                    .get("entries")
+                    .and_then(Value::as_array)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                self.plan_total = entries.len();
+                self.plan_completed = entries
+                    .iter()
+                    .filter(|entry| {
+                        entry.get("status").and_then(Value::as_str) == Some("completed")
+                    })
+                    .count();
+            }
+            _ => {}
+        }
+    }
+
+    fn plan_ratio(&self) -> Option<f64> {
+        (self.plan_total > 0).then(|| self.plan_completed as f64 / self.plan_total as f64)
+    }
+
+    /// 按计划完成度线性估算剩余秒数；尚无已完成条目时不给出估计
+    fn eta_secs(&self, elapsed: Duration) -> Option<u64> {
+        let ratio = self.plan_ratio().filter(|ratio| *ratio > 0.0)?;
+        Some((elapsed.as_secs_f64() * (1.0 - ratio) / ratio).round() as u64)
+    }
+
+    fn payload(&self, agent_id: &str, task_id: &str, elapsed: Duration) -> Value {
+        json!({
+            "agentId": agent_id,
+            "taskId": task_id,
+            "elapsedMs": elapsed.as_millis() as u64,
+            "chunkCount": self.chunk_count,
+            "lastToolCall": self.last_tool_call,
+            "planCompleted": self.plan_completed,
+            "planTotal": self.plan_total,
+            "planRatio": self.plan_ratio(),
+            "etaSecs": self.eta_secs(elapsed),
+        })
+    }
+}
+
+/// 任务运行超过阈值时推送一次 task-progress
+pub(crate) fn emit_task_progress(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    task_id: &str,
+    elapsed: Duration,
+    progress: &TaskProgress,
+) {
+    if elapsed < PROGRESS_THRESHOLD {
+        return;
+    }
+    let _ = app_handle.emit_unless_locked(
+        "task-progress",
+        progress.payload(agent_id, task_id, elapsed),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_tracks_chunks_tools_and_plan() {
+        let mut progress = TaskProgress::default();
+        progress.record_update(
+            &json!({ "sessionUpdate": "agent_message_chunk", "content": { "text": "a" } }),
+        );
+        progress.record_update(
+            &json!({ "sessionUpdate": "agent_thought_chunk", "content": { "text": "b" } }),
+        );
+        progress.record_update(&json!({
+            "sessionUpdate": "tool_call",
+            "toolCallId": "call-1",
+            "title": "Read src/main.rs",
+            "status": "in_progress",
+        }));
+        progress.record_update(&json!({
+            "sessionUpdate": "tool_call_update",
+            "toolCallId": "call-1",
+            "status": "completed",
+        }));
+        progress.record_update(&json!({
+            "sessionUpdate": "plan",
+            "entries": [
+                { "content": "a", "status": "completed" },
+                { "content": "b", "status": "in_progress" },
+                { "content": "c", "status": "pending" },
+                { "content": "d", "status": "pending" },
+            ],
+        }));
+
+        let payload = progress.payload("agent-1", "task-1", Duration::from_secs(30));
+        assert_eq!(payload["chunkCount"], 2);
+        assert_eq!(payload["lastToolCall"]["name"], "Read src/main.rs");
+        assert_eq!(payload["lastToolCall"]["status"], "completed");
+        assert_eq!(payload["planCompleted"], 1);
+        assert_eq!(payload["planTotal"], 4);
+        assert_eq!(payload["planRatio"], 0.25);
+        assert_eq!(payload["etaSecs"], 90);
+    }
+
+    #[test]
+    fn eta_requires_completed_plan_entries() {
+        let progress = TaskProgress::default();
+        let payload = progress.payload("agent-1", "task-1", Duration::from_secs(12));
+        assert_eq!(payload["elapsedMs"], 12_000);
+        assert!(payload["planRatio"].is_null());
+        assert!(payload["etaSecs"].is_null());
+    }
+}
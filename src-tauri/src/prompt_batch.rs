@@ -0,0 +1,405 @@
+//! 批量提交 prompt：从 Markdown（以单独一行 `---` 分隔，代码块内的 `---` 不算）或 JSONL
+//! （每行一个字符串或带 `prompt` 字段的对象）文件读取 prompt，作为后台任务依次发送。
+//! 传入多个 Agent 时每个 Agent 是一条通道，各通道并行地从同一队列取 prompt。
+//! 每条 prompt 都由监听任务登记为独立的任务记录（附带 `batch` 元数据），结束后汇总为批次报告。
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::State;
+use tokio::time::{Duration, Instant};
+
+use crate::jobs::{spawn_job, JobContext, JobHandle, JOB_CANCELLED};
+use crate::result_output::{resolve_result_output, write_result_rows, ResultFormat, ResultRow};
+use crate::routines::run_prompt_turn;
+use crate::state::AppState;
+use crate::tasks::update_task_record;
+
+const MAX_BATCH_PROMPTS: usize = 500;
+const DEFAULT_BATCH_PROMPT_TIMEOUT_SECS: u64 = 30 * 60;
+const MAX_BATCH_PROMPT_TIMEOUT_SECS: u64 = 2 * 60 * 60;
+const PROMPT_PREVIEW_CHARS: usize = 80;
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchItemResult {
+    pub index: usize,
+    pub agent_id: String,
+    /// prompt 开头的一段，完整内容见任务记录
+    pub prompt: String,
+    pub task_id: Option<String>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// 最终回答只写入结果文件，不进入报告
+    #[serde(skip)]
+    pub answer: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptBatchReport {
+    pub batch_id: String,
+    pub source: String,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub started_at: String,
+    pub finished_at: String,
+    /// 写入的结果汇总文件
+    pub output_path: Option<String>,
+    /// 按 prompt 在文件中的顺序排列
+    pub items: Vec<BatchItemResult>,
+}
+
+fn parse_markdown_prompts(raw: &str) -> Vec<String> {
+    let mut prompts = Vec::new();
+    let mut current = Vec::new();
+    let mut in_fence = false;
+    for line in raw.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if !in_fence && trimmed == "---" {
+            prompts.push(current.join("\n"));
+            current.clear();
+            continue;
+        }
+        current.push(line);
+    }
+    prompts.push(current.join("\n"));
+    prompts
+        .into_iter()
+        .map(|prompt| prompt.trim().to_string())
+        .filter(|prompt| !prompt.is_empty())
+        .collect()
+}
+
+fn parse_jsonl_prompts(raw: &str) -> Result<Vec<String>, String> {
+    let mut prompts = Vec::new();
+    for (index, line) in raw.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let record: Value = serde_json::from_str(trimmed)
+            .map_err(|e| format!("Line {}: failed to parse JSON: {}", index + 1, e))?;
+        let prompt = match &record {
+            Value::String(prompt) => Some(prompt.as_str()),
+            Value::Object(map) => map.get("prompt").and_then(Value::as_str),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            format!(
+                "Line {}: expected a string or an object with a `prompt` field",
+                index + 1
+            )
+        })?;
+        if !prompt.trim().is_empty() {
+            prompts.push(prompt.trim().to_string());
+        }
+    }
+    Ok(prompts)
+}
+
+/// 按扩展名解析 prompt 文件：`.jsonl` / `.ndjson` 按行解析，其余按 Markdown 处理
+fn parse_prompt_file(path: &Path, raw: &str) -> Result<Vec<String>, String> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let prompts = match extension.as_str() {
+        "jsonl" | "ndjson" => parse_jsonl_prompts(raw)?,
+        _ => parse_markdown_prompts(raw),
+    };
+    if prompts.is_empty() {
+        return Err(format!("No prompts found in {}", path.display()));
+    }
+    if prompts.len() > MAX_BATCH_PROMPTS {
+        return Err(format!(
+            "Batch has {} prompts, at most {} are allowed",
+            prompts.len(),
+            MAX_BATCH_PROMPTS
+        ));
+    }
+    Ok(prompts)
+}
+
+fn prompt_preview(prompt: &str) -> String {
+    if prompt.chars().count() <= PROMPT_PREVIEW_CHARS {
+        return prompt.to_string();
+    }
+    format!(
+        "{}…",
+        prompt
+            .chars()
+            .take(PROMPT_PREVIEW_CHARS)
+            .collect::<String>()
+    )
+}
+
+/// 各通道共享的批次状态
+struct BatchRun {
+    batch_id: String,
+    total: usize,
+    limit: Duration,
+    prompts: Vec<String>,
+    queue: Mutex<VecDeque<usize>>,
+    results: Mutex<Vec<BatchItemResult>>,
+    done: AtomicUsize,
+}
+
+/// 一条通道：不断从队列取 prompt 发给自己的 Agent，直到队列为空或任务被取消
+async fn run_lane(
+    job: &JobContext,
+    run: &BatchRun,
+    agent_id: &str,
+    workspace_path: &str,
+) -> Result<(), String> {
+    loop {
+        job.check_cancelled()?;
+        let Some(index) = run
+            .queue
+            .lock()
+            .ok()
+            .and_then(|mut queue| queue.pop_front())
+        else {
+            return Ok(());
+        };
+        let prompt = &run.prompts[index];
+        let started = Instant::now();
+        let turn = run_prompt_turn(job, agent_id, workspace_path, prompt, run.limit).await;
+        if let Some(task_id) = turn.task_id.as_deref() {
+            let batch = json!({ "batchId": &run.batch_id, "index": index });
+            if let Err(e) = update_task_record(job.app_handle(), task_id, |record| {
+                record.metadata.insert("batch".to_string(), batch);
+            })
+            .await
+            {
+                println!("[batch] Failed to tag task {}: {}", task_id, e);
+            }
+        }
+        let error = match turn.result {
+            Ok(_) => None,
+            Err(error) if error == JOB_CANCELLED => return Err(error),
+            Err(error) => Some(error),
+        };
+        if let Ok(mut results) = run.results.lock() {
+            results.push(BatchItemResult {
+                index,
+                agent_id: agent_id.to_string(),
+                prompt: prompt_preview(prompt),
+                task_id: turn.task_id,
+                error,
+                duration_ms: started.elapsed().as_millis() as u64,
+                answer: turn.answer,
+            });
+        }
+        let finished = run.done.fetch_add(1, Ordering::SeqCst) + 1;
+        job.report(
+            finished,
+            run.total,
+            &format!("{}/{} {}", finished, run.total, prompt_preview(prompt)),
+        );
+    }
+}
+
+fn build_report(
+    batch_id: String,
+    source: String,
+    total: usize,
+    started_at: String,
+    output_path: Option<String>,
+    mut items: Vec<BatchItemResult>,
+) -> PromptBatchReport {
+    items.sort_by_key(|item| item.index);
+    let failed = items.iter().filter(|item| item.error.is_some()).count();
+    PromptBatchReport {
+        batch_id,
+        source,
+        total,
+        succeeded: items.len() - failed,
+        failed,
+        started_at,
+        finished_at: chrono::Utc::now().to_rfc3339(),
+        output_path,
+        items,
+    }
+}
+
+async fn run_batch_job(
+    job: JobContext,
+    source: String,
+    lanes: Vec<(String, String)>,
+    prompts: Vec<String>,
+    limit: Duration,
+    output: Option<(PathBuf, ResultFormat)>,
+) -> Result<PromptBatchReport, String> {
+    let started_at = chrono::Utc::now().to_rfc3339();
+    let total = prompts.len();
+    let run = BatchRun {
+        batch_id: uuid::Uuid::new_v4().to_string(),
+        total,
+        limit,
+        queue: Mutex::new((0..total).collect()),
+        prompts,
+        results: Mutex::new(Vec::with_capacity(total)),
+        done: AtomicUsize::new(0),
+    };
+    job.report(0, total, &format!("0/{}", total));
+
+    let outcomes = futures::future::join_all(
+        lanes
+            .iter()
+            .map(|(agent_id, workspace_path)| run_lane(&job, &run, agent_id, workspace_path)),
+    )
+    .await;
+    let items = run.results.into_inner().unwrap_or_default();
+    let output_path = match output {
+        Some((path, format)) => {
+            // 中途取消时也写出已完成的部分
+            let mut rows: Vec<ResultRow> = items
+                .iter()
+                .map(|item| {
+                    ResultRow::new(
+                        item.index,
+                        &item.agent_id,
+                        &run.prompts[item.index],
+                        item.task_id.clone(),
+                        item.answer.clone(),
+                        item.error.clone(),
+                    )
+                })
+                .collect();
+            write_result_rows(&path, format, &mut rows).await?;
+            Some(path.to_string_lossy().to_string())
+        }
+        None => None,
+    };
+    if let Some(error) = outcomes.into_iter().find_map(Result::err) {
+        return Err(error);
+    }
+
+    Ok(build_report(
+        run.batch_id,
+        source,
+        total,
+        started_at,
+        output_path,
+        items,
+    ))
+}
+
+/// 从文件批量提交 prompt，立即返回任务句柄；批次报告是任务结果，用 cancel_job 中止。
+/// `output_path`（第一个 Agent 工作区内的 .jsonl / .csv）用于汇总每条 prompt 的回答
+#[tauri::command]
+pub async fn submit_prompt_batch(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    agent_ids: Vec<String>,
+    timeout_secs: Option<u64>,
+    output_path: Option<String>,
+) -> Result<JobHandle, String> {
+    if agent_ids.is_empty() {
+        return Err("At least one agent is required".to_string());
+    }
+    let mut lanes = Vec::with_capacity(agent_ids.len());
+    for agent_id in agent_ids {
+        if lanes.iter().any(|(existing, _)| existing == &agent_id) {
+            continue;
+        }
+        let workspace_path = state
+            .agent_manager
+            .workspace_path_of(&agent_id)
+            .await
+            .ok_or_else(|| format!("Agent {} not found", agent_id))?;
+        lanes.push((agent_id, workspace_path));
+    }
+
+    let file_path = Path::new(&path);
+    let raw = tokio::fs::read_to_string(file_path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", file_path.display(), e))?;
+    let prompts = parse_prompt_file(file_path, &raw)?;
+    let output = match output_path.as_deref() {
+        Some(output_path) => Some(resolve_result_output(&lanes[0].1, output_path).await?),
+        None => None,
+    };
+    let limit = Duration::from_secs(
+        timeout_secs
+            .unwrap_or(DEFAULT_BATCH_PROMPT_TIMEOUT_SECS)
+            .clamp(1, MAX_BATCH_PROMPT_TIMEOUT_SECS),
+    );
+
+    Ok(spawn_job(
+        &app_handle,
+        &state,
+        "prompt-batch",
+        move |job| async move { run_batch_job(job, path, lanes, prompts, limit, output).await },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompt_files_parse_markdown_and_jsonl() {
+        let markdown = "Translate a.md\n---\n\n---\nRefactor:\n```yaml\n---\nkey: 1\n```\n";
+        assert_eq!(
+            parse_prompt_file(Path::new("batch.md"), markdown).unwrap(),
+            vec![
+                "Translate a.md".to_string(),
+                "Refactor:\n```yaml\n---\nkey: 1\n```".to_string(),
+            ]
+        );
+
+        let jsonl = "\"first\"\n\n{\"prompt\": \"second\", \"file\": \"b.rs\"}\n";
+        assert_eq!(
+            parse_prompt_file(Path::new("batch.JSONL"), jsonl).unwrap(),
+            vec!["first".to_string(), "second".to_string()]
+        );
+        let error =
+            parse_prompt_file(Path::new("batch.jsonl"), "\"ok\"\n{\"text\": 1}").unwrap_err();
+        assert!(error.starts_with("Line 2:"));
+        assert!(parse_prompt_file(Path::new("empty.md"), "---\n").is_err());
+    }
+
+    #[test]
+    fn report_orders_items_and_counts_failures() {
+        let item = |index: usize, error: Option<&str>| BatchItemResult {
+            index,
+            agent_id: format!("agent-{}", index % 2),
+            prompt: format!("prompt {}", index),
+            task_id: None,
+            error: error.map(str::to_string),
+            duration_ms: 1,
+            answer: None,
+        };
+        let report = build_report(
+            "batch".to_string(),
+            "prompts.md".to_string(),
+            3,
+            "2026-01-01T00:00:00Z".to_string(),
+            None,
+            vec![
+                item(2, None),
+                item(0, Some("Prompt ended with refusal")),
+                item(1, None),
+            ],
+        );
+        assert_eq!(
+            report
+                .items
+                .iter()
+                .map(|item| item.index)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!((report.succeeded, report.failed), (2, 1));
+    }
+}
@@ -0,0 +1,216 @@
+//! 长 prompt 拆分：开启后，超过阈值的 prompt 拆成一段指令加若干上下文块，作为独立的 content block 发送，
+//! 让 Agent 先看到要做什么再读材料。拆分方式写入任务记录的 `promptSplit` 元数据。
+//! 请求参数由同步的构建函数生成，因此与 ACP 跟踪开关一样用全局状态缓存当前设置。
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+const DEFAULT_THRESHOLD_CHARS: usize = 24_000;
+const DEFAULT_MAX_PART_CHARS: usize = 8_000;
+
+static PROMPT_SPLIT: Lazy<RwLock<PromptSplitSettings>> =
+    Lazy::new(|| RwLock::new(PromptSplitSettings::default()));
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PromptSplitStrategy {
+    /// 整段作为一个文本块发送
+    #[default]
+    Off,
+    /// 指令与上下文拆成多个文本块，在同一次 session/prompt 中发送
+    ContentBlocks,
+}
+
+impl PromptSplitStrategy {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::ContentBlocks => "contentBlocks",
+        }
+    }
+}
+
+fn default_threshold_chars() -> usize {
+    DEFAULT_THRESHOLD_CHARS
+}
+
+fn default_max_part_chars() -> usize {
+    DEFAULT_MAX_PART_CHARS
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptSplitSettings {
+    #[serde(default)]
+    pub strategy: PromptSplitStrategy,
+    /// 超过该字符数才拆分
+    #[serde(default = "default_threshold_chars")]
+    pub threshold_chars: usize,
+    /// 每个上下文块的最大字符数
+    #[serde(default = "default_max_part_chars")]
+    pub max_part_chars: usize,
+}
+
+impl Default for PromptSplitSettings {
+    fn default() -> Self {
+        Self {
+            strategy: PromptSplitStrategy::Off,
+            threshold_chars: DEFAULT_THRESHOLD_CHARS,
+            max_part_chars: DEFAULT_MAX_PART_CHARS,
+        }
+    }
+}
+
+pub(crate) fn validate_prompt_split(settings: &PromptSplitSettings) -> Result<(), String> {
+    if settings.threshold_chars == 0 || settings.max_part_chars == 0 {
+        return Err("Prompt split sizes must be greater than zero".to_string());
+    }
+    Ok(())
+}
+
+pub(crate) fn set_prompt_split(settings: PromptSplitSettings) {
+    if let Ok(mut current) = PROMPT_SPLIT.write() {
+        *current = settings;
+    }
+}
+
+fn current_settings() -> PromptSplitSettings {
+    PROMPT_SPLIT
+        .read()
+        .map(|settings| *settings)
+        .unwrap_or_default()
+}
+
+/// 按字符数切开超长段落，尽量在换行处断开
+fn chunk_paragraph(paragraph: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_chars = 0;
+    for line in paragraph.split_inclusive('\n') {
+        let line_chars = line.chars().count();
+        if current_chars + line_chars > max_chars && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+            current_chars = 0;
+        }
+        if line_chars > max_chars {
+            let chars: Vec<char> = line.chars().collect();
+            for piece in chars.chunks(max_chars) {
+                chunks.push(piece.iter().collect());
+            }
+            continue;
+        }
+        current.push_str(line);
+        current_chars += line_chars;
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// 把上下文段落合并为不超过 `max_chars` 的块
+fn pack_context(paragraphs: &[&str], max_chars: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    for paragraph in paragraphs {
+        for chunk in chunk_paragraph(paragraph, max_chars) {
+            let joined_chars = current.chars().count() + chunk.chars().count() + 2;
+            if !current.is_empty() && joined_chars > max_chars {
+                parts.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push_str("\n\n");
+            }
+            current.push_str(&chunk);
+        }
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+/// 指令取首段与末段中较短的一段（指令通常写在粘贴材料之前或之后），其余段落作为上下文；
+/// 不需要拆分时返回 None
+pub(crate) fn split_prompt(prompt: &str, settings: &PromptSplitSettings) -> Option<Vec<String>> {
+    if settings.strategy == PromptSplitStrategy::Off
+        || prompt.chars().count() <= settings.threshold_chars
+    {
+        return None;
+    }
+    let paragraphs: Vec<&str> = prompt
+        .split("\n\n")
+        .filter(|paragraph| !paragraph.trim().is_empty())
+        .collect();
+    if paragraphs.len() < 2 {
+        return None;
+    }
+    let first = paragraphs[0];
+    let last = paragraphs[paragraphs.len() - 1];
+    let (instruction, context) = if last.chars().count() < first.chars().count() {
+        (last, &paragraphs[..paragraphs.len() - 1])
+    } else {
+        (first, &paragraphs[1..])
+    };
+    if instruction.chars().count() > settings.max_part_chars {
+        return None;
+    }
+
+    let context = pack_context(context, settings.max_part_chars);
+    let total = context.len();
+    let mut parts = vec![format!(
+        "{}\n\n（另附 {} 段上下文材料，见后续内容块）",
+        instruction.trim(),
+        total
+    )];
+    parts.extend(
+        context
+            .into_iter()
+            .enumerate()
+            .map(|(index, text)| format!("【上下文 {}/{}】\n{}", index + 1, total, text)),
+    );
+    Some(parts)
+}
+
+/// 按当前设置得到 prompt 的文本块
+pub(crate) fn prompt_text_parts(prompt: &str) -> Vec<String> {
+    split_prompt(prompt, &current_settings()).unwrap_or_else(|| vec![prompt.to_string()])
+}
+
+/// 写入任务记录的拆分信息；未拆分时为空
+pub(crate) fn prompt_split_metadata(prompt: &str) -> Option<Value> {
+    let settings = current_settings();
+    let parts = split_prompt(prompt, &settings)?;
+    Some(json!({
+        "strategy": settings.strategy.as_str(),
+        "parts": parts.len(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_prompt_is_split_into_instruction_and_context_blocks() {
+        let settings = PromptSplitSettings {
+            strategy: PromptSplitStrategy::ContentBlocks,
+            threshold_chars: 50,
+            max_part_chars: 40,
+        };
+        let context = ["a".repeat(30), "b".repeat(30), "c".repeat(90)].join("\n\n");
+        let prompt = format!("{}\n\n总结这段日志", context);
+
+        let parts = split_prompt(&prompt, &settings).expect("split");
+        assert!(parts[0].starts_with("总结这段日志"));
+        assert_eq!(parts.len(), 6);
+        assert!(parts[1].starts_with("【上下文 1/5】\n") && parts[1].ends_with(&"a".repeat(30)));
+        assert!(parts[3].ends_with(&"c".repeat(40)));
+
+        assert_eq!(split_prompt("short", &settings), None);
+        let off = PromptSplitSettings::default();
+        assert_eq!(split_prompt(&prompt, &off), None);
+    }
+}
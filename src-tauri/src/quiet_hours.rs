@@ -0,0 +1,199 @@
+//! 免打扰时段：按星期配置（可跨午夜），期间不推送任务结束通知（提示音、Webhook），
+//! 并提供免打扰结束时间，供定时执行的一方把任务推迟到时段之后。
+use chrono::{Datelike, Duration, Local, NaiveDateTime, NaiveTime, TimeZone, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, State};
+
+use crate::state::AppState;
+
+/// 向后查找免打扰结束时间的上限（一周）
+const MAX_LOOKAHEAD_MINUTES: i64 = 7 * 24 * 60;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuietDay {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl QuietDay {
+    fn weekday(self) -> Weekday {
+        match self {
+            Self::Mon => Weekday::Mon,
+            Self::Tue => Weekday::Tue,
+            Self::Wed => Weekday::Wed,
+            Self::Thu => Weekday::Thu,
+            Self::Fri => Weekday::Fri,
+            Self::Sat => Weekday::Sat,
+            Self::Sun => Weekday::Sun,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietWindow {
+    /// 时段开始所在的星期；跨午夜的时段延续到次日
+    pub days: Vec<QuietDay>,
+    /// 本地时间 `HH:MM`；start 晚于 end 表示跨午夜，相等表示全天
+    pub start: String,
+    pub end: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub windows: Vec<QuietWindow>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHoursStatus {
+    pub quiet: bool,
+    /// 免打扰结束时间（RFC 3339，本地时区）
+    pub until: Option<String>,
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .map_err(|_| format!("Invalid quiet hours time {} (expected HH:MM)", value))
+}
+
+pub(crate) fn validate_quiet_hours(quiet_hours: &QuietHours) -> Result<(), String> {
+    for window in &quiet_hours.windows {
+        parse_time(&window.start)?;
+        parse_time(&window.end)?;
+        if window.days.is_empty() {
+            return Err("Quiet hours window must select at least one day".to_string());
+        }
+    }
+    Ok(())
+}
+
+fn window_contains(window: &QuietWindow, at: NaiveDateTime) -> bool {
+    let (Ok(start), Ok(end)) = (parse_time(&window.start), parse_time(&window.end)) else {
+        return false;
+    };
+    let time = at.time();
+    let today = at.weekday();
+    let yesterday = today.pred();
+    window.days.iter().any(|day| {
+        let day = day.weekday();
+        if start == end {
+            day == today
+        } else if start < end {
+            day == today && time >= start && time < end
+        } else {
+            (day == today && time >= start) || (day == yesterday && time < end)
+        }
+    })
+}
+
+pub(crate) fn is_quiet_at(quiet_hours: &QuietHours, at: NaiveDateTime) -> bool {
+    quiet_hours.enabled
+        && quiet_hours
+            .windows
+            .iter()
+            .any(|window| window_contains(window, at))
+}
+
+/// 免打扰结束的时刻（按分钟查找）；当前不在免打扰时段或全周免打扰时返回 None
+pub(crate) fn quiet_until(quiet_hours: &QuietHours, at: NaiveDateTime) -> Option<NaiveDateTime> {
+    if !is_quiet_at(quiet_hours, at) {
+        return None;
+    }
+    let start = at.with_second(0)?.with_nanosecond(0)?;
+    (1..=MAX_LOOKAHEAD_MINUTES)
+        .map(|minutes| start + Duration::minutes(minutes))
+        .find(|candidate| !is_quiet_at(quiet_hours, *candidate))
+}
+
+/// 当前是否处于免打扰时段（供后台通知在发送前查询）
+pub(crate) async fn is_quiet_now(app_handle: &tauri::AppHandle) -> bool {
+    let state = app_handle.state::<AppState>();
+    let settings = state.settings.read().await;
+    is_quiet_at(&settings.quiet_hours, Local::now().naive_local())
+}
+
+#[tauri::command]
+pub async fn get_quiet_hours_status(
+    state: State<'_, AppState>,
+) -> Result<QuietHoursStatus, String> {
+    let quiet_hours = state.settings.read().await.quiet_hours.clone();
+    let now = Local::now().naive_local();
+    let until = quiet_until(&quiet_hours, now)
+        .and_then(|until| Local.from_local_datetime(&until).earliest())
+        .map(|until| until.to_rfc3339());
+    Ok(QuietHoursStatus {
+        quiet: is_quiet_at(&quiet_hours, now),
+        until,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn weeknights() -> QuietHours {
+        QuietHours {
+            enabled: true,
+            windows: vec![QuietWindow {
+                days: vec![QuietDay::Mon, QuietDay::Tue, QuietDay::Wed, QuietDay::Thu],
+                start: "22:00".to_string(),
+                end: "07:30".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn overnight_windows_extend_into_next_day() {
+        let quiet_hours = weeknights();
+        // 2026-03-02 是周一
+        assert!(!is_quiet_at(&quiet_hours, at("2026-03-02 21:59")));
+        assert!(is_quiet_at(&quiet_hours, at("2026-03-02 22:00")));
+        assert!(is_quiet_at(&quiet_hours, at("2026-03-03 03:00")));
+        assert!(!is_quiet_at(&quiet_hours, at("2026-03-03 07:30")));
+        // 周四夜间延续到周五早上，周五夜间不免打扰
+        assert!(is_quiet_at(&quiet_hours, at("2026-03-06 06:00")));
+        assert!(!is_quiet_at(&quiet_hours, at("2026-03-06 23:00")));
+
+        let disabled = QuietHours {
+            enabled: false,
+            ..weeknights()
+        };
+        assert!(!is_quiet_at(&disabled, at("2026-03-03 03:00")));
+    }
+
+    #[test]
+    fn quiet_until_finds_window_end() {
+        let quiet_hours = weeknights();
+        assert_eq!(
+            quiet_until(&quiet_hours, at("2026-03-03 03:17")),
+            Some(at("2026-03-03 07:30"))
+        );
+        assert_eq!(quiet_until(&quiet_hours, at("2026-03-03 12:00")), None);
+        assert!(validate_quiet_hours(&quiet_hours).is_ok());
+
+        let invalid = QuietHours {
+            enabled: true,
+            windows: vec![QuietWindow {
+                days: vec![QuietDay::Sat],
+                start: "25:00".to_string(),
+                end: "07:00".to_string(),
+            }],
+        };
+        assert!(validate_quiet_hours(&invalid).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+//! 会话已读回执：按会话记录最后已读的消息序号（消息在会话中的位置，从 1 开始），
+//! 未读数以会话存储为准统一在后端计算，供多 Agent 面板与托盘角标使用。
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tokio::fs;
+
+use crate::state::AppState;
+use crate::storage::{
+    app_data_dir, read_snapshot_from_path, storage_env_tag, storage_path, StorageSnapshot,
+};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadReceipts {
+    /// 会话 ID -> 最后已读的消息序号
+    #[serde(default)]
+    pub last_read_by_session: HashMap<String, usize>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UnreadCounts {
+    pub by_session: HashMap<String, usize>,
+    pub by_agent: HashMap<String, usize>,
+    pub total: usize,
+}
+
+fn read_receipts_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-read-receipts-{}.json", storage_env_tag())))
+}
+
+async fn read_receipts_from_path(path: &Path) -> Result<ReadReceipts, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(ReadReceipts::default());
+            }
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse read receipts: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(ReadReceipts::default()),
+        Err(err) => Err(format!("Failed to read read receipts: {}", err)),
+    }
+}
+
+async fn write_receipts_to_path(path: &Path, receipts: &ReadReceipts) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create read receipts dir: {}", e))?;
+    }
+    let payload = serde_json::to_vec(receipts)
+        .map_err(|e| format!("Failed to encode read receipts: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write read receipts: {}", e))
+}
+
+/// 已读序号之后的非用户消息计为未读；已删除会话的回执不计入
+fn compute_unread_counts(snapshot: &StorageSnapshot, receipts: &ReadReceipts) -> UnreadCounts {
+    let mut counts = UnreadCounts::default();
+    for (agent_id, sessions) in &snapshot.sessions_by_agent {
+        for session in sessions {
+            let last_read = receipts
+                .last_read_by_session
+                .get(&session.id)
+                .copied()
+                .unwrap_or(0);
+            let unread = snapshot
+                .messages_by_session
+                .get(&session.id)
+                .map(|messages| {
+                    messages
+                        .iter()
+                        .skip(last_read)
+                        .filter(|message| message.role != "user")
+                        .count()
+                })
+                .unwrap_or(0);
+            if unread == 0 {
+                continue;
+            }
+            counts.by_session.insert(session.id.clone(), unread);
+            *counts.by_agent.entry(agent_id.clone()).or_default() += unread;
+            counts.total += unread;
+        }
+    }
+    counts
+}
+
+/// 标记会话已读；`sequence` 为空时标记到最新一条消息，已读序号不会回退
+#[tauri::command]
+pub async fn mark_session_read(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    sequence: Option<usize>,
+) -> Result<UnreadCounts, String> {
+    let _guard = state.storage_lock.lock().await;
+    let snapshot = read_snapshot_from_path(&storage_path(&app_handle)?).await?;
+    let message_count = snapshot
+        .messages_by_session
+        .get(&session_id)
+        .map(Vec::len)
+        .ok_or_else(|| format!("Session {} not found", session_id))?;
+    let sequence = sequence.unwrap_or(message_count).min(message_count);
+
+    let path = read_receipts_path(&app_handle)?;
+    let mut receipts = read_receipts_from_path(&path).await?;
+    let last_read = receipts.last_read_by_session.entry(session_id).or_default();
+    if sequence > *last_read {
+        *last_read = sequence;
+        write_receipts_to_path(&path, &receipts).await?;
+    }
+    Ok(compute_unread_counts(&snapshot, &receipts))
+}
+
+/// 会话中删除了消息后前移已读序号；`removed` 为被删除消息的位置（从 0 开始），调用方需持有存储锁
+pub(crate) async fn shift_read_receipt(
+    app_handle: &tauri::AppHandle,
+    session_id: &str,
+    removed: &[usize],
+) -> Result<(), String> {
+    let path = read_receipts_path(app_handle)?;
+    let mut receipts = read_receipts_from_path(&path).await?;
+    let Some(last_read) = receipts.last_read_by_session.get_mut(session_id) else {
+        return Ok(());
+    };
+    let shift = removed
+        .iter()
+        .filter(|&&position| position < *last_read)
+        .count();
+    if shift == 0 {
+        return Ok(());
+    }
+    *last_read -= shift;
+    write_receipts_to_path(&path, &receipts).await
+}
+
+#[tauri::command]
+pub async fn get_unread_counts(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<UnreadCounts, String> {
+    let _guard = state.storage_lock.lock().await;
+    let snapshot = read_snapshot_from_path(&storage_path(&app_handle)?).await?;
+    let receipts = read_receipts_from_path(&read_receipts_path(&app_handle)?).await?;
+    Ok(compute_unread_counts(&snapshot, &receipts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{StoredMessage, StoredSession};
+
+    fn message(role: &str) -> StoredMessage {
+        StoredMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            role: role.to_string(),
+            content: "x".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unread_counts_skip_read_and_user_messages() {
+        let mut snapshot = StorageSnapshot::default();
+        snapshot.sessions_by_agent.insert(
+            "agent-1".to_string(),
+            vec![
+                StoredSession {
+                    id: "s1".to_string(),
+                    ..Default::default()
+                },
+                StoredSession {
+                    id: "s2".to_string(),
+                    ..Default::default()
+                },
+            ],
+        );
+        snapshot.messages_by_session.insert(
+            "s1".to_string(),
+            vec![
+                message("user"),
+                message("assistant"),
+                message("user"),
+                message("assistant"),
+            ],
+        );
+        snapshot.messages_by_session.insert(
+            "s2".to_string(),
+            vec![message("user"), message("assistant")],
+        );
+
+        let mut receipts = ReadReceipts::default();
+        receipts.last_read_by_session.insert("s1".to_string(), 2);
+        receipts.last_read_by_session.insert("s2".to_string(), 2);
+        receipts
+            .last_read_by_session
+            .insert("deleted".to_string(), 0);
+
+        let counts = compute_unread_counts(&snapshot, &receipts);
+        assert_eq!(counts.by_session.get("s1"), Some(&1));
+        assert!(!counts.by_session.contains_key("s2"));
+        assert_eq!(counts.by_agent.get("agent-1"), Some(&1));
+        assert_eq!(counts.total, 1);
+    }
+
+    #[tokio::test]
+    async fn receipts_roundtrip() {
+        let path =
+            std::env::temp_dir().join(format!("iflow-read-receipts-{}.json", uuid::Uuid::new_v4()));
+        assert_eq!(
+            read_receipts_from_path(&path).await.unwrap(),
+            ReadReceipts::default()
+        );
+        let mut receipts = ReadReceipts::default();
+        receipts.last_read_by_session.insert("s1".to_string(), 3);
+        write_receipts_to_path(&path, &receipts).await.unwrap();
+        assert_eq!(read_receipts_from_path(&path).await.unwrap(), receipts);
+        let _ = fs::remove_file(&path).await;
+    }
+}
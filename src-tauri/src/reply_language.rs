@@ -0,0 +1,164 @@
+//! 工作区回复语言：发送前在 prompt 末尾附加固定的语言要求，任务结束后用 lingua 检测回复语言，
+//! 与设置不一致时发出 `language-mismatch` 提示事件（只提示，不改写回复）。
+use std::collections::HashMap;
+
+use lingua::{Language, LanguageDetector, LanguageDetectorBuilder};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::Manager;
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::history::normalize_workspace_path;
+use crate::state::AppState;
+
+/// 去掉代码后少于该字符数的回复不做检测
+const MIN_DETECT_CHARS: usize = 20;
+
+static DETECTOR: Lazy<LanguageDetector> = Lazy::new(|| {
+    LanguageDetectorBuilder::from_languages(&[Language::Chinese, Language::English])
+        .with_minimum_relative_distance(0.2)
+        .build()
+});
+
+/// 代码块、行内代码与链接不参与语言检测
+static CODE_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)```.*?```|`[^`\n]*`|https?://\S+").expect("valid code pattern"));
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplyLanguage {
+    Zh,
+    En,
+}
+
+impl ReplyLanguage {
+    fn instruction(self) -> &'static str {
+        match self {
+            Self::Zh => "请使用简体中文回复（代码、命令与专有名词可保留原文）。",
+            Self::En => {
+                "Please reply in English (code, commands and proper nouns may stay as they are)."
+            }
+        }
+    }
+
+    fn from_lingua(language: Language) -> Option<Self> {
+        match language {
+            Language::Chinese => Some(Self::Zh),
+            Language::English => Some(Self::En),
+            _ => None,
+        }
+    }
+}
+
+/// 工作区配置的回复语言
+pub(crate) fn reply_language_for(
+    languages: &HashMap<String, ReplyLanguage>,
+    workspace_path: &str,
+) -> Option<ReplyLanguage> {
+    let normalized = normalize_workspace_path(workspace_path);
+    languages
+        .iter()
+        .find(|(path, _)| normalize_workspace_path(path) == normalized)
+        .map(|(_, language)| *language)
+}
+
+/// 在 prompt 末尾附加语言要求（已存在时不重复追加）
+pub(crate) fn append_language_instruction(content: &str, language: ReplyLanguage) -> String {
+    let instruction = language.instruction();
+    if content.trim_end().ends_with(instruction) {
+        return content.to_string();
+    }
+    format!("{}\n\n{}", content.trim_end(), instruction)
+}
+
+/// 检测回复的主要语言；内容过短或无法区分时返回 None
+pub(crate) fn detect_reply_language(text: &str) -> Option<ReplyLanguage> {
+    let prose = CODE_PATTERN.replace_all(text, " ");
+    let meaningful = prose.chars().filter(|ch| ch.is_alphabetic()).count();
+    if meaningful < MIN_DETECT_CHARS {
+        return None;
+    }
+    DETECTOR
+        .detect_language_of(prose.as_ref())
+        .and_then(ReplyLanguage::from_lingua)
+}
+
+/// 任务结束后检测回复语言，与工作区设置不一致时发出提示
+pub(crate) fn spawn_language_check(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    workspace_path: &str,
+    task_id: &str,
+    message: &str,
+) {
+    let app_handle = app_handle.clone();
+    let agent_id = agent_id.to_string();
+    let workspace_path = workspace_path.to_string();
+    let task_id = task_id.to_string();
+    let message = message.to_string();
+    tokio::spawn(async move {
+        let expected = {
+            let state = app_handle.state::<AppState>();
+            let settings = state.settings.read().await;
+            reply_language_for(&settings.reply_languages, &workspace_path)
+        };
+        let Some(expected) = expected else {
+            return;
+        };
+        let detected = tokio::task::spawn_blocking(move || detect_reply_language(&message))
+            .await
+            .ok()
+            .flatten();
+        let Some(detected) = detected else {
+            return;
+        };
+        if detected != expected {
+            let _ = app_handle.emit_unless_locked(
+                "language-mismatch",
+                json!({
+                    "agentId": &agent_id,
+                    "taskId": &task_id,
+                    "expected": expected,
+                    "detected": detected,
+                }),
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instruction_is_appended_once_per_workspace_setting() {
+        let mut languages = HashMap::new();
+        languages.insert("/tmp/team-repo/".to_string(), ReplyLanguage::Zh);
+        assert_eq!(
+            reply_language_for(&languages, "/tmp/team-repo"),
+            Some(ReplyLanguage::Zh)
+        );
+        assert_eq!(reply_language_for(&languages, "/tmp/other"), None);
+
+        let once = append_language_instruction("Fix the build", ReplyLanguage::Zh);
+        assert!(once.starts_with("Fix the build\n\n"));
+        assert_eq!(append_language_instruction(&once, ReplyLanguage::Zh), once);
+    }
+
+    #[test]
+    fn reply_language_ignores_code_and_short_text() {
+        assert_eq!(
+            detect_reply_language("这个函数在读取配置文件时没有处理文件不存在的情况，我已经补充了默认值并增加了测试。"),
+            Some(ReplyLanguage::Zh)
+        );
+        assert_eq!(
+            detect_reply_language(
+                "The config loader did not handle a missing file, so I added a default and a test.\n```rust\nlet x = 1;\n```"
+            ),
+            Some(ReplyLanguage::En)
+        );
+        assert_eq!(detect_reply_language("```\nfn main() {}\n``` ok"), None);
+    }
+}
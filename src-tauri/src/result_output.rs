@@ -0,0 +1,195 @@
+//! 批量提交与例程的结果汇总文件：每个 prompt 一行，记录状态、最终回答，以及从回答中解析出的
+//! 结构化结果（整段回答是 JSON，或最后一个 ```json 代码块），写入工作区内的 JSONL 或 CSV 文件，
+//! 便于下游脚本继续处理。格式按扩展名决定，文件每次运行整体覆盖。
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::agents::fs_sandbox::resolve_sandboxed_path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ResultFormat {
+    Jsonl,
+    Csv,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ResultRow {
+    pub index: usize,
+    pub agent_id: String,
+    pub prompt: String,
+    pub task_id: Option<String>,
+    /// `ok` 或 `error`
+    pub status: &'static str,
+    pub error: Option<String>,
+    pub answer: Option<String>,
+    pub result: Option<Value>,
+}
+
+impl ResultRow {
+    pub(crate) fn new(
+        index: usize,
+        agent_id: &str,
+        prompt: &str,
+        task_id: Option<String>,
+        answer: Option<String>,
+        error: Option<String>,
+    ) -> Self {
+        Self {
+            index,
+            agent_id: agent_id.to_string(),
+            prompt: prompt.to_string(),
+            task_id,
+            status: if error.is_some() { "error" } else { "ok" },
+            error,
+            result: answer.as_deref().and_then(structured_result),
+            answer,
+        }
+    }
+}
+
+/// 回答整体是 JSON 对象 / 数组时直接使用，否则取最后一个 ```json 代码块
+fn structured_result(answer: &str) -> Option<Value> {
+    let parse = |text: &str| {
+        serde_json::from_str::<Value>(text.trim())
+            .ok()
+            .filter(|value| value.is_object() || value.is_array())
+    };
+    if let Some(value) = parse(answer) {
+        return Some(value);
+    }
+    let start = answer.rfind("```json")? + "```json".len();
+    let end = answer[start..].find("```")?;
+    parse(&answer[start..start + end])
+}
+
+/// 输出文件限定在工作区内，格式由扩展名决定
+pub(crate) async fn resolve_result_output(
+    workspace_path: &str,
+    output_path: &str,
+) -> Result<(PathBuf, ResultFormat), String> {
+    let extension = Path::new(output_path.trim())
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let format = match extension.as_str() {
+        "jsonl" | "ndjson" => ResultFormat::Jsonl,
+        "csv" => ResultFormat::Csv,
+        _ => {
+            return Err(format!(
+                "Result output {} must end with .jsonl or .csv",
+                output_path
+            ))
+        }
+    };
+    let path = resolve_sandboxed_path(workspace_path, &[], output_path).await?;
+    Ok((path, format))
+}
+
+fn render_rows(rows: &[ResultRow], format: ResultFormat) -> Result<Vec<u8>, String> {
+    match format {
+        ResultFormat::Jsonl => {
+            let mut payload = Vec::new();
+            for row in rows {
+                serde_json::to_writer(&mut payload, row)
+                    .map_err(|e| format!("Failed to encode result row: {}", e))?;
+                payload.push(b'\n');
+            }
+            Ok(payload)
+        }
+        ResultFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer
+                .write_record([
+                    "index", "agentId", "prompt", "taskId", "status", "error", "answer", "result",
+                ])
+                .map_err(|e| format!("Failed to encode result row: {}", e))?;
+            for row in rows {
+                writer
+                    .write_record([
+                        row.index.to_string(),
+                        row.agent_id.clone(),
+                        row.prompt.clone(),
+                        row.task_id.clone().unwrap_or_default(),
+                        row.status.to_string(),
+                        row.error.clone().unwrap_or_default(),
+                        row.answer.clone().unwrap_or_default(),
+                        row.result
+                            .as_ref()
+                            .map(Value::to_string)
+                            .unwrap_or_default(),
+                    ])
+                    .map_err(|e| format!("Failed to encode result row: {}", e))?;
+            }
+            writer
+                .into_inner()
+                .map_err(|e| format!("Failed to encode result rows: {}", e))
+        }
+    }
+}
+
+/// 按行号排序后写出全部结果行
+pub(crate) async fn write_result_rows(
+    path: &Path,
+    format: ResultFormat,
+    rows: &mut [ResultRow],
+) -> Result<(), String> {
+    rows.sort_by_key(|row| row.index);
+    let payload = render_rows(rows, format)?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create result output dir: {}", e))?;
+    }
+    tokio::fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write result output: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn rows_extract_structured_results_and_render_both_formats() {
+        let rows = vec![
+            ResultRow::new(
+                0,
+                "agent-1",
+                "Summarize a.rs",
+                Some("task-1".to_string()),
+                Some("Done:\n```json\n{\"lines\": 12}\n```".to_string()),
+                None,
+            ),
+            ResultRow::new(
+                1,
+                "agent-1",
+                "Summarize b.rs",
+                None,
+                None,
+                Some("Prompt ended with refusal".to_string()),
+            ),
+        ];
+        assert_eq!(rows[0].result, Some(json!({ "lines": 12 })));
+        assert_eq!(rows[1].status, "error");
+        assert_eq!(structured_result("[1, 2]"), Some(json!([1, 2])));
+        assert_eq!(structured_result("plain text"), None);
+
+        let jsonl = String::from_utf8(render_rows(&rows, ResultFormat::Jsonl).unwrap()).unwrap();
+        let first: Value = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+        assert_eq!(first["status"], "ok");
+        assert_eq!(first["result"]["lines"], 12);
+
+        let csv = String::from_utf8(render_rows(&rows, ResultFormat::Csv).unwrap()).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("index,agentId,prompt,taskId,status,error,answer,result")
+        );
+        assert!(csv.contains("\"Done:\n```json\n{\"\"lines\"\": 12}\n```\""));
+        assert!(csv.contains("1,agent-1,Summarize b.rs,,error,Prompt ended with refusal,,"));
+    }
+}
@@ -0,0 +1,155 @@
+//! prompt 自动重试：识别 ACP 的瞬时错误（内部错误、限流、连接中断），
+//! 按指数退避重新发送，并推送 prompt-retry 事件供前端提示。
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::app_lock::EmitUnlessLocked;
+
+pub(crate) const DEFAULT_PROMPT_MAX_RETRIES: u32 = 2;
+
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// JSON-RPC 通用服务端错误 / 内部错误
+const TRANSIENT_ERROR_CODES: [i64; 2] = [-32000, -32603];
+
+const TRANSIENT_MESSAGE_HINTS: [&str; 12] = [
+    "rate limit",
+    "rate_limit",
+    "too many requests",
+    "429",
+    "overloaded",
+    "temporarily unavailable",
+    "service unavailable",
+    "502",
+    "503",
+    "timed out",
+    "timeout",
+    "connection reset",
+];
+
+/// 明确不应重试的错误（鉴权、参数、配额耗尽等）
+const PERMANENT_MESSAGE_HINTS: [&str; 6] = [
+    "unauthorized",
+    "invalid api key",
+    "authentication",
+    "invalid params",
+    "quota exceeded",
+    "insufficient",
+];
+
+/// 判断 session/prompt 的错误响应是否值得自动重试
+pub(crate) fn is_transient_prompt_error(error: &Value) -> bool {
+    let message = error
+        .get("message")
+        .and_then(Value::as_str)
+        .map(|message| message.to_string())
+        .unwrap_or_else(|| error.to_string());
+    let details = error
+        .get("data")
+        .map(|data| data.to_string())
+        .unwrap_or_default();
+    let text = format!("{} {}", message, details).to_lowercase();
+
+    if PERMANENT_MESSAGE_HINTS
+        .iter()
+        .any(|hint| text.contains(hint))
+    {
+        return false;
+    }
+    let code = error.get("code").and_then(Value::as_i64);
+    code.map(|code| TRANSIENT_ERROR_CODES.contains(&code))
+        .unwrap_or(false)
+        || TRANSIENT_MESSAGE_HINTS
+            .iter()
+            .any(|hint| text.contains(hint))
+}
+
+/// 第 attempt 次重试（从 1 开始）前的等待时长
+pub(crate) fn retry_backoff(attempt: u32) -> Duration {
+    let factor = 2_u32.saturating_pow(attempt.saturating_sub(1));
+    RETRY_BASE_DELAY.saturating_mul(factor).min(RETRY_MAX_DELAY)
+}
+
+/// 第 attempt 次重连（从 1 开始）前的等待时长：指数退避的一半固定、一半按 `jitter`（[0, 1)）随机，
+/// 避免多个 Agent 同时断线后同步重连
+pub(crate) fn reconnect_backoff(attempt: u32, jitter: f64) -> Duration {
+    let factor = 2_u32.saturating_pow(attempt.saturating_sub(1));
+    let delay = RECONNECT_BASE_DELAY
+        .saturating_mul(factor)
+        .min(RECONNECT_MAX_DELAY);
+    delay / 2 + delay.mul_f64(jitter.clamp(0.0, 1.0) / 2.0)
+}
+
+pub(crate) fn reconnect_jitter() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos())
+        .unwrap_or_default();
+    f64::from(nanos % 1000) / 1000.0
+}
+
+pub(crate) fn emit_prompt_retry(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    task_id: &str,
+    attempt: u32,
+    max_retries: u32,
+    delay: Duration,
+    reason: &str,
+) {
+    let _ = app_handle.emit_unless_locked(
+        "prompt-retry",
+        json!({
+            "agentId": agent_id,
+            "taskId": task_id,
+            "attempt": attempt,
+            "maxRetries": max_retries,
+            "delayMs": delay.as_millis() as u64,
+            "reason": reason,
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_transient_errors() {
+        assert!(is_transient_prompt_error(
+            &json!({ "code": -32000, "message": "Internal error" })
+        ));
+        assert!(is_transient_prompt_error(
+            &json!({ "code": -32099, "message": "Upstream returned 429 Too Many Requests" })
+        ));
+        assert!(is_transient_prompt_error(
+            &json!({ "code": -32603, "message": "failed", "data": "connection reset by peer" })
+        ));
+        assert!(!is_transient_prompt_error(
+            &json!({ "code": -32602, "message": "Invalid params" })
+        ));
+        assert!(!is_transient_prompt_error(
+            &json!({ "code": -32000, "message": "Unauthorized: invalid API key" })
+        ));
+    }
+
+    #[test]
+    fn backoff_doubles_and_caps() {
+        assert_eq!(retry_backoff(1), Duration::from_secs(2));
+        assert_eq!(retry_backoff(2), Duration::from_secs(4));
+        assert_eq!(retry_backoff(3), Duration::from_secs(8));
+        assert_eq!(retry_backoff(10), RETRY_MAX_DELAY);
+    }
+
+    #[test]
+    fn reconnect_backoff_is_jittered_within_bounds() {
+        assert_eq!(reconnect_backoff(1, 0.0), Duration::from_millis(500));
+        assert_eq!(reconnect_backoff(3, 1.0), Duration::from_secs(4));
+        assert_eq!(reconnect_backoff(3, 0.5), Duration::from_secs(3));
+        assert_eq!(reconnect_backoff(20, 0.0), RECONNECT_MAX_DELAY / 2);
+    }
+}
@@ -1,7 +1,20 @@
 use serde_json::{json, Value};
-use tauri::Emitter;
+use tauri::Manager;
 
+use crate::app_lock::EmitUnlessLocked;
+use crate::memory_guard::{memory_limits, spill_tool_output};
 use crate::models::{PlanEntry, ToolCall};
+use crate::settings::{message_style, MessageStyle, SystemMarker};
+use crate::state::AppState;
+
+/// 后端内部的任务结束通知；不受应用锁暂存影响，供等待回复的例程与批量 prompt 使用
+#[derive(Debug, Clone)]
+pub(crate) struct TaskFinished {
+    pub agent_id: String,
+    pub task_id: String,
+    pub reason: String,
+    pub message: String,
+}
 
 pub(crate) fn text_from_content(content: &Value) -> Option<String> {
     let content_type = content.get("type")?.as_str()?;
@@ -40,34 +53,60 @@ pub(crate) fn text_from_tool_contents(contents: &Value) -> Option<String> {
     }
 }
 
-fn stop_reason_to_message(reason: &str) -> &'static str {
-    match reason {
-        "end_turn" => "✅ 任务完成",
-        "max_tokens" => "⚠️ 达到最大令牌限制",
-        "cancelled" => "🚫 任务已取消",
-        "refusal" => "⛔ 模型拒绝回答",
-        _ => "✅ 任务结束",
+/// 提取助手正文分片文本（用于在监听任务中拼接完整回复）
+pub(crate) fn message_chunk_text(update: &Value) -> Option<String> {
+    if update.get("sessionUpdate").and_then(Value::as_str) != Some("agent_message_chunk") {
+        return None;
     }
+    update.get("content").and_then(text_from_content)
 }
 
-pub(crate) async fn emit_task_finish(app_handle: &tauri::AppHandle, agent_id: &str, reason: &str) {
+fn stop_reason_to_message(style: MessageStyle, reason: &str) -> String {
+    let (marker, text) = match reason {
+        "end_turn" => (SystemMarker::Success, "任务完成"),
+        "max_tokens" => (SystemMarker::Warning, "达到最大令牌限制"),
+        "cancelled" => (SystemMarker::Cancelled, "任务已取消"),
+        "refusal" => (SystemMarker::Refusal, "模型拒绝回答"),
+        _ => (SystemMarker::Success, "任务结束"),
+    };
+    style.decorate(marker, text)
+}
+
+pub(crate) async fn emit_task_finish(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    task_id: &str,
+    reason: &str,
+    message: &str,
+) {
     // end_turn 是最常见的正常结束，不再向聊天区追加冗余“任务完成”文案。
     if reason != "end_turn" {
-        let _ = app_handle.emit(
+        let _ = app_handle.emit_unless_locked(
             "stream-message",
             json!({
                 "agentId": agent_id,
-                "content": stop_reason_to_message(reason),
+                "content": stop_reason_to_message(message_style(app_handle).await, reason),
                 "type": "system",
             }),
         );
     }
 
-    let _ = app_handle.emit(
+    let _ = app_handle
+        .state::<AppState>()
+        .task_finished
+        .send(TaskFinished {
+            agent_id: agent_id.to_string(),
+            task_id: task_id.to_string(),
+            reason: reason.to_string(),
+            message: message.to_string(),
+        });
+    let _ = app_handle.emit_unless_locked(
         "task-finish",
         json!({
             "agentId": agent_id,
+            "taskId": task_id,
             "reason": reason,
+            "message": message,
         }),
     );
 }
@@ -84,7 +123,7 @@ pub(crate) async fn handle_session_update(
     match session_update {
         "agent_message_chunk" => {
             if let Some(content) = update.get("content").and_then(text_from_content) {
-                let _ = app_handle.emit(
+                let _ = app_handle.emit_unless_locked(
                     "stream-message",
                     json!({
                         "agentId": agent_id,
@@ -96,17 +135,29 @@ pub(crate) async fn handle_session_update(
         }
         "agent_thought_chunk" => {
             if let Some(content) = update.get("content").and_then(text_from_content) {
-                let _ = app_handle.emit(
+                let _ = app_handle.emit_unless_locked(
                     "stream-message",
                     json!({
                         "agentId": agent_id,
-                        "content": format!("💭 {}", content),
+                        "content": message_style(app_handle)
+                            .await
+                            .decorate(SystemMarker::Thought, &content),
                         "type": "thought",
                     }),
                 );
             }
         }
         "tool_call" | "tool_call_update" => {
+            let (output, spilled_path) =
+                match update.get("content").and_then(text_from_tool_contents) {
+                    Some(output) => {
+                        let limits = memory_limits(app_handle).await;
+                        let (output, spilled_path) =
+                            spill_tool_output(agent_id, output, &limits).await;
+                        (Some(output), spilled_path)
+                    }
+                    None => (None, None),
+                };
             let tool_call = ToolCall {
                 id: update
                     .get("toolCallId")
@@ -125,10 +176,11 @@ pub(crate) async fn handle_session_update(
                     .unwrap_or("pending")
                     .to_string(),
                 arguments: update.get("args").cloned(),
-                output: update.get("content").and_then(text_from_tool_contents),
+                output,
+                spilled_path,
             };
 
-            let _ = app_handle.emit(
+            let _ = app_handle.emit_unless_locked(
                 "tool-call",
                 json!({
                     "agentId": agent_id,
@@ -148,11 +200,14 @@ pub(crate) async fn handle_session_update(
             }
 
             if !entries.is_empty() {
-                let _ = app_handle.emit(
+                let _ = app_handle.emit_unless_locked(
                     "stream-message",
                     json!({
                         "agentId": agent_id,
-                        "content": format!("📋 执行计划:\n{}", entries.join("\n")),
+                        "content": message_style(app_handle).await.decorate(
+                            SystemMarker::Plan,
+                            &format!("执行计划:\n{}", entries.join("\n")),
+                        ),
                         "type": "plan",
                     }),
                 );
@@ -174,7 +229,8 @@ pub(crate) async fn handle_session_update(
 mod tests {
     use serde_json::json;
 
-    use super::{text_from_content, text_from_tool_contents};
+    use super::{stop_reason_to_message, text_from_content, text_from_tool_contents};
+    use crate::settings::MessageStyle;
 
     #[test]
     fn test_text_from_content_text() {
@@ -202,4 +258,16 @@ mod tests {
         assert!(text.contains("line1"));
         assert!(text.contains("src/main.ts"));
     }
+
+    #[test]
+    fn test_stop_reason_message_follows_style() {
+        assert_eq!(
+            stop_reason_to_message(MessageStyle::Emoji, "cancelled"),
+            "🚫 任务已取消"
+        );
+        assert_eq!(
+            stop_reason_to_message(MessageStyle::Plain, "max_tokens"),
+            "[警告] 达到最大令牌限制"
+        );
+    }
 }
@@ -0,0 +1,599 @@
+//! 例程：按工作区保存的多步操作（发送 prompt、执行命令、导出产物），由 run_routine 作为后台任务
+//! 依次执行；每步开始时通过 job-progress 汇报进度，可用 cancel_job 中止，
+//! 中止时会取消正在进行的 prompt 或结束正在运行的命令。
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, State};
+use tokio::fs;
+use tokio::process::Command;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{sleep, timeout, Duration, Instant};
+
+use crate::artifact::resolve_artifact_path_in_workspace;
+use crate::artifact_sync::PUBLISHABLE_EXTENSIONS;
+use crate::capabilities::{capability_enabled, CommandCapability};
+use crate::history::normalize_workspace_path;
+use crate::jobs::{spawn_job, JobContext, JobHandle, JOB_CANCELLED};
+use crate::models::ListenerCommand;
+use crate::postprocess::resolve_hook_command;
+use crate::prompt_rules::preprocess_prompt;
+use crate::result_output::{resolve_result_output, write_result_rows, ResultFormat, ResultRow};
+use crate::runtime_env::runtime_path_env;
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag};
+
+const MAX_ROUTINE_STEPS: usize = 50;
+const DEFAULT_PROMPT_TIMEOUT_SECS: u64 = 30 * 60;
+const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 120;
+const MAX_STEP_TIMEOUT_SECS: u64 = 2 * 60 * 60;
+const MAX_STEP_OUTPUT_CHARS: usize = 4000;
+/// 等待 prompt 或命令结束时检查取消标记的间隔
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RoutineStep {
+    /// 向执行例程的 Agent 发送 prompt，并等待本轮回复结束
+    #[serde(rename_all = "camelCase")]
+    SendPrompt {
+        content: String,
+        #[serde(default)]
+        timeout_secs: Option<u64>,
+    },
+    /// 在工作区目录执行命令，非零退出视为失败
+    #[serde(rename_all = "camelCase")]
+    RunCommand {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        timeout_secs: Option<u64>,
+    },
+    /// 把工作区内的产物复制到指定目录
+    #[serde(rename_all = "camelCase")]
+    ExportArtifact { path: String, destination: String },
+}
+
+impl RoutineStep {
+    fn label(&self) -> String {
+        match self {
+            Self::SendPrompt { content, .. } => {
+                format!("prompt: {}", content.chars().take(40).collect::<String>())
+            }
+            Self::RunCommand { command, .. } => format!("command: {}", command),
+            Self::ExportArtifact { path, .. } => format!("export: {}", path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Routine {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub steps: Vec<RoutineStep>,
+    #[serde(default)]
+    pub created_at: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutineStore {
+    #[serde(default)]
+    pub by_workspace: HashMap<String, Vec<Routine>>,
+}
+
+impl RoutineStore {
+    fn routines_mut(&mut self, workspace_path: &str) -> &mut Vec<Routine> {
+        self.by_workspace
+            .entry(normalize_workspace_path(workspace_path))
+            .or_default()
+    }
+
+    fn routines(&self, workspace_path: &str) -> Vec<Routine> {
+        self.by_workspace
+            .get(&normalize_workspace_path(workspace_path))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StepOutcome {
+    pub index: usize,
+    pub label: String,
+    pub output: String,
+}
+
+fn routines_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-routines-{}.json", storage_env_tag())))
+}
+
+async fn read_routines_from_path(path: &Path) -> Result<RoutineStore, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(RoutineStore::default());
+            }
+            serde_json::from_str(&content).map_err(|e| format!("Failed to parse routines: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(RoutineStore::default()),
+        Err(err) => Err(format!("Failed to read routines: {}", err)),
+    }
+}
+
+async fn write_routines_to_path(path: &Path, store: &RoutineStore) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create routines dir: {}", e))?;
+    }
+    let payload =
+        serde_json::to_vec(store).map_err(|e| format!("Failed to encode routines: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write routines: {}", e))
+}
+
+fn validate_routine(routine: &Routine) -> Result<(), String> {
+    if routine.name.trim().is_empty() {
+        return Err("Routine name cannot be empty".to_string());
+    }
+    if routine.steps.is_empty() {
+        return Err("Routine needs at least one step".to_string());
+    }
+    if routine.steps.len() > MAX_ROUTINE_STEPS {
+        return Err(format!(
+            "Routine cannot have more than {} steps",
+            MAX_ROUTINE_STEPS
+        ));
+    }
+    for (index, step) in routine.steps.iter().enumerate() {
+        let valid = match step {
+            RoutineStep::SendPrompt { content, .. } => !content.trim().is_empty(),
+            RoutineStep::RunCommand { command, .. } => !command.trim().is_empty(),
+            RoutineStep::ExportArtifact { path, destination } => {
+                !path.trim().is_empty() && Path::new(destination.trim()).is_absolute()
+            }
+        };
+        if !valid {
+            return Err(format!("Routine step {} is incomplete", index + 1));
+        }
+    }
+    Ok(())
+}
+
+fn step_timeout(timeout_secs: Option<u64>, default_secs: u64) -> Duration {
+    Duration::from_secs(
+        timeout_secs
+            .unwrap_or(default_secs)
+            .clamp(1, MAX_STEP_TIMEOUT_SECS),
+    )
+}
+
+fn truncate_output(output: &str) -> String {
+    let trimmed = output.trim();
+    if trimmed.chars().count() <= MAX_STEP_OUTPUT_CHARS {
+        return trimmed.to_string();
+    }
+    let tail: Vec<char> = trimmed.chars().rev().take(MAX_STEP_OUTPUT_CHARS).collect();
+    format!("...{}", tail.into_iter().rev().collect::<String>())
+}
+
+/// 一轮 prompt 的结果；`task_id` 与 `answer` 来自 task-finish 事件（未收到时为空）
+pub(crate) struct PromptTurn {
+    pub task_id: Option<String>,
+    pub answer: Option<String>,
+    pub result: Result<String, String>,
+}
+
+/// 发送 prompt 并等待该 Agent 的任务结束通知；取消或超时时同时取消 Agent 上的生成
+pub(crate) async fn run_prompt_turn(
+    job: &JobContext,
+    agent_id: &str,
+    workspace_path: &str,
+    content: &str,
+    limit: Duration,
+) -> PromptTurn {
+    let app_handle = job.app_handle().clone();
+    let (agent_exists, sender) = app_handle
+        .state::<AppState>()
+        .agent_manager
+        .sender_of(agent_id)
+        .await;
+    let failed = |error: String| PromptTurn {
+        task_id: None,
+        answer: None,
+        result: Err(error),
+    };
+    if !agent_exists {
+        return failed(format!("Agent {} not found", agent_id));
+    }
+    let Some(sender) = sender else {
+        return failed("Message sender not available".to_string());
+    };
+
+    // 订阅后端副本而非 task-finish 事件：应用上锁时事件会被暂存，而例程应继续执行
+    let mut finished_rx = app_handle.state::<AppState>().task_finished.subscribe();
+
+    let content = preprocess_prompt(&app_handle, workspace_path, content)
+        .await
+        .content;
+    let mut task_id = None;
+    let mut answer = None;
+    let result = async {
+        sender
+            .send(ListenerCommand::UserPrompt {
+                content,
+                session_id: None,
+                generation: None,
+                resources: Vec::new(),
+            })
+            .map_err(|e| format!("Failed to queue prompt: {}", e))?;
+        let deadline = Instant::now() + limit;
+        loop {
+            if job.check_cancelled().is_err() {
+                let _ = sender.send(ListenerCommand::CancelPrompt);
+                return Err(JOB_CANCELLED.to_string());
+            }
+            if Instant::now() >= deadline {
+                let _ = sender.send(ListenerCommand::CancelPrompt);
+                return Err(format!("Prompt timeout after {} seconds", limit.as_secs()));
+            }
+            match timeout(CANCEL_POLL_INTERVAL, finished_rx.recv()).await {
+                Ok(Ok(finished)) if finished.agent_id == agent_id => {
+                    task_id = Some(finished.task_id);
+                    answer = Some(finished.message);
+                    if finished.reason == "end_turn" {
+                        return Ok(finished.reason);
+                    }
+                    return Err(format!("Prompt ended with {}", finished.reason));
+                }
+                Ok(Ok(_)) | Ok(Err(RecvError::Lagged(_))) | Err(_) => continue,
+                Ok(Err(RecvError::Closed)) => return Err("Task finish listener closed".to_string()),
+            }
+        }
+    }
+    .await;
+    PromptTurn {
+        task_id,
+        answer,
+        result,
+    }
+}
+
+async fn run_command_step(
+    job: &JobContext,
+    agent_id: &str,
+    workspace_path: &str,
+    command: &str,
+    args: &[String],
+    limit: Duration,
+) -> Result<String, String> {
+    if !capability_enabled(job.app_handle(), CommandCapability::Process) {
+        return Err("Process capability is disabled".to_string());
+    }
+    let executable = resolve_hook_command(command, workspace_path)?;
+    let child = Command::new(executable)
+        .args(args)
+        .current_dir(workspace_path)
+        .env("PATH", runtime_path_env()?)
+        .env("FLOWHUB_AGENT_ID", agent_id)
+        .env("FLOWHUB_WORKSPACE", workspace_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to start command: {}", e))?;
+
+    // 丢弃 future 时子进程随之结束
+    let run = child.wait_with_output();
+    tokio::pin!(run);
+    let deadline = Instant::now() + limit;
+    let output = loop {
+        tokio::select! {
+            output = &mut run => break output.map_err(|e| format!("Command failed: {}", e))?,
+            _ = sleep(CANCEL_POLL_INTERVAL) => {
+                job.check_cancelled()?;
+                if Instant::now() >= deadline {
+                    return Err(format!("Command timeout after {} seconds", limit.as_secs()));
+                }
+            }
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let detail = if stderr.trim().is_empty() {
+            stdout
+        } else {
+            stderr
+        };
+        return Err(format!(
+            "Command exited with {}: {}",
+            output.status,
+            truncate_output(&detail)
+        ));
+    }
+    Ok(truncate_output(&stdout))
+}
+
+async fn run_export_step(
+    job: &JobContext,
+    workspace_path: &str,
+    path: &str,
+    destination: &str,
+) -> Result<String, String> {
+    if !capability_enabled(job.app_handle(), CommandCapability::Fs) {
+        return Err("Fs capability is disabled".to_string());
+    }
+    let source = resolve_artifact_path_in_workspace(
+        workspace_path,
+        path,
+        PUBLISHABLE_EXTENSIONS,
+        "Unsupported artifact type",
+    )
+    .await?;
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| "Artifact file name is missing".to_string())?;
+    let destination_dir = PathBuf::from(destination.trim());
+    fs::create_dir_all(&destination_dir)
+        .await
+        .map_err(|e| format!("Failed to create export dir: {}", e))?;
+    let target = destination_dir.join(file_name);
+    fs::copy(&source, &target)
+        .await
+        .map_err(|e| format!("Failed to export artifact: {}", e))?;
+    Ok(target.to_string_lossy().to_string())
+}
+
+async fn run_routine_steps(
+    job: &JobContext,
+    agent_id: &str,
+    workspace_path: &str,
+    routine: &Routine,
+    rows: &mut Vec<ResultRow>,
+) -> Result<Vec<StepOutcome>, String> {
+    let total = routine.steps.len();
+    let mut outcomes = Vec::with_capacity(total);
+    for (index, step) in routine.steps.iter().enumerate() {
+        job.check_cancelled()?;
+        let label = step.label();
+        job.report(index, total, &format!("{}/{} {}", index + 1, total, label));
+        let result = match step {
+            RoutineStep::SendPrompt {
+                content,
+                timeout_secs,
+            } => {
+                let limit = step_timeout(*timeout_secs, DEFAULT_PROMPT_TIMEOUT_SECS);
+                let turn = run_prompt_turn(job, agent_id, workspace_path, content, limit).await;
+                rows.push(ResultRow::new(
+                    index,
+                    agent_id,
+                    content,
+                    turn.task_id,
+                    turn.answer,
+                    turn.result.as_ref().err().cloned(),
+                ));
+                turn.result
+            }
+            RoutineStep::RunCommand {
+                command,
+                args,
+                timeout_secs,
+            } => {
+                let limit = step_timeout(*timeout_secs, DEFAULT_COMMAND_TIMEOUT_SECS);
+                run_command_step(job, agent_id, workspace_path, command, args, limit).await
+            }
+            RoutineStep::ExportArtifact { path, destination } => {
+                run_export_step(job, workspace_path, path, destination).await
+            }
+        };
+        let output = match result {
+            Ok(output) => output,
+            Err(error) if error == JOB_CANCELLED => return Err(error),
+            Err(error) => return Err(format!("Step {} ({}) failed: {}", index + 1, label, error)),
+        };
+        outcomes.push(StepOutcome {
+            index,
+            label,
+            output,
+        });
+    }
+    job.report(total, total, "done");
+    Ok(outcomes)
+}
+
+/// 执行例程；指定了结果文件时，无论成功与否都写出已执行的 prompt 步骤
+async fn run_routine_job(
+    job: JobContext,
+    agent_id: String,
+    workspace_path: String,
+    routine: Routine,
+    output: Option<(PathBuf, ResultFormat)>,
+) -> Result<Vec<StepOutcome>, String> {
+    let mut rows = Vec::new();
+    let outcome = run_routine_steps(&job, &agent_id, &workspace_path, &routine, &mut rows).await;
+    if let Some((path, format)) = output {
+        write_result_rows(&path, format, &mut rows).await?;
+    }
+    outcome
+}
+
+#[tauri::command]
+pub async fn list_routines(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: String,
+) -> Result<Vec<Routine>, String> {
+    let _guard = state.routines_lock.lock().await;
+    let store = read_routines_from_path(&routines_path(&app_handle)?).await?;
+    Ok(store.routines(&workspace_path))
+}
+
+/// 新增或按 ID 更新例程
+#[tauri::command]
+pub async fn save_routine(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: String,
+    routine: Routine,
+) -> Result<Routine, String> {
+    validate_routine(&routine)?;
+
+    let _guard = state.routines_lock.lock().await;
+    let path = routines_path(&app_handle)?;
+    let mut store = read_routines_from_path(&path).await?;
+    let routines = store.routines_mut(&workspace_path);
+
+    let mut routine = routine;
+    routine.name = routine.name.trim().to_string();
+    match routines
+        .iter_mut()
+        .find(|existing| !routine.id.is_empty() && existing.id == routine.id)
+    {
+        Some(existing) => {
+            routine.created_at = existing.created_at.clone();
+            *existing = routine.clone();
+        }
+        None => {
+            routine.id = uuid::Uuid::new_v4().to_string();
+            routine.created_at = chrono::Utc::now().to_rfc3339();
+            routines.push(routine.clone());
+        }
+    }
+    write_routines_to_path(&path, &store).await?;
+    Ok(routine)
+}
+
+#[tauri::command]
+pub async fn delete_routine(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: String,
+    routine_id: String,
+) -> Result<bool, String> {
+    let _guard = state.routines_lock.lock().await;
+    let path = routines_path(&app_handle)?;
+    let mut store = read_routines_from_path(&path).await?;
+    let routines = store.routines_mut(&workspace_path);
+    let before = routines.len();
+    routines.retain(|routine| routine.id != routine_id);
+    let removed = routines.len() != before;
+    if removed {
+        write_routines_to_path(&path, &store).await?;
+    }
+    Ok(removed)
+}
+
+/// 在 Agent 的工作区执行例程，立即返回任务句柄；用 cancel_job 中止。
+/// `output_path`（工作区内的 .jsonl / .csv）用于汇总各 prompt 步骤的回答
+#[tauri::command]
+pub async fn run_routine(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    agent_id: String,
+    routine_id: String,
+    output_path: Option<String>,
+) -> Result<JobHandle, String> {
+    let workspace_path = state
+        .agent_manager
+        .workspace_path_of(&agent_id)
+        .await
+        .ok_or_else(|| format!("Agent {} not found", agent_id))?;
+    let routine = {
+        let _guard = state.routines_lock.lock().await;
+        read_routines_from_path(&routines_path(&app_handle)?)
+            .await?
+            .routines(&workspace_path)
+            .into_iter()
+            .find(|routine| routine.id == routine_id)
+            .ok_or_else(|| format!("Routine {} not found", routine_id))?
+    };
+    validate_routine(&routine)?;
+    let output = match output_path.as_deref() {
+        Some(output_path) => Some(resolve_result_output(&workspace_path, output_path).await?),
+        None => None,
+    };
+
+    Ok(spawn_job(
+        &app_handle,
+        &state,
+        "routine",
+        move |job| async move { run_routine_job(job, agent_id, workspace_path, routine, output).await },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn routine_steps_deserialize_and_validate() {
+        let routine: Routine = serde_json::from_value(json!({
+            "name": "nightly report",
+            "steps": [
+                { "type": "sendPrompt", "content": "总结今天的改动" },
+                { "type": "runCommand", "command": "npm", "args": ["test"], "timeoutSecs": 600 },
+                { "type": "exportArtifact", "path": "report.html", "destination": "/tmp/reports" },
+            ],
+        }))
+        .expect("parse routine");
+        assert!(validate_routine(&routine).is_ok());
+        assert_eq!(
+            routine.steps[1],
+            RoutineStep::RunCommand {
+                command: "npm".to_string(),
+                args: vec!["test".to_string()],
+                timeout_secs: Some(600),
+            }
+        );
+
+        let mut relative = routine.clone();
+        relative.steps[2] = RoutineStep::ExportArtifact {
+            path: "report.html".to_string(),
+            destination: "reports".to_string(),
+        };
+        assert!(validate_routine(&relative).is_err());
+
+        let empty = Routine {
+            steps: Vec::new(),
+            ..routine
+        };
+        assert!(validate_routine(&empty).is_err());
+    }
+
+    #[tokio::test]
+    async fn routines_are_stored_per_workspace() {
+        let path =
+            std::env::temp_dir().join(format!("iflow-routines-{}.json", uuid::Uuid::new_v4()));
+        let mut store = read_routines_from_path(&path).await.unwrap();
+        store.routines_mut("/tmp/project/").push(Routine {
+            id: "r1".to_string(),
+            name: "build".to_string(),
+            steps: vec![RoutineStep::RunCommand {
+                command: "make".to_string(),
+                args: Vec::new(),
+                timeout_secs: None,
+            }],
+            created_at: String::new(),
+        });
+        write_routines_to_path(&path, &store).await.unwrap();
+
+        let loaded = read_routines_from_path(&path).await.unwrap();
+        assert_eq!(loaded.routines("/tmp/project").len(), 1);
+        assert!(loaded.routines("/tmp/other").is_empty());
+        assert_eq!(truncate_output("  ok \n"), "ok");
+        let _ = fs::remove_file(&path).await;
+    }
+}
@@ -12,10 +12,27 @@ fn push_unique(paths: &mut Vec<PathBuf>, seen: &mut HashSet<PathBuf>, path: Path
     }
 }
 
-fn home_dir() -> Option<PathBuf> {
-    env::var_os("HOME")
-        .or_else(|| env::var_os("USERPROFILE"))
-        .map(PathBuf::from)
+/// 从环境变量推断主目录：Windows 上 HOME 通常未设置（或由 MSYS 等设为 `/c/Users/...` 形式），
+/// 依次使用 USERPROFILE、HOMEDRIVE + HOMEPATH，最后才是 HOME
+fn home_dir_from_env(windows: bool, lookup: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    let var = |key: &str| lookup(key).filter(|value| !value.is_empty());
+    let home = if windows {
+        var("USERPROFILE")
+            .or_else(|| {
+                let mut drive = var("HOMEDRIVE")?;
+                drive.push(var("HOMEPATH")?);
+                Some(drive)
+            })
+            .or_else(|| var("HOME"))
+    } else {
+        var("HOME").or_else(|| var("USERPROFILE"))
+    };
+    home.map(PathBuf::from)
+}
+
+/// 当前用户主目录：优先使用系统接口，失败时回退到环境变量
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    dirs::home_dir().or_else(|| home_dir_from_env(cfg!(windows), |key| env::var_os(key)))
 }
 
 fn runtime_search_paths() -> Vec<PathBuf> {
@@ -112,3 +129,39 @@ pub fn resolve_executable_path(executable: &str) -> Result<PathBuf, String> {
 
     Err(format!("Executable not found in runtime PATH: {}", trimmed))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_home_does_not_depend_on_home_variable() {
+        let vars = |pairs: &'static [(&'static str, &'static str)]| {
+            move |key: &str| {
+                pairs
+                    .iter()
+                    .find(|(name, _)| *name == key)
+                    .map(|(_, value)| OsString::from(value))
+            }
+        };
+        assert_eq!(
+            home_dir_from_env(
+                true,
+                vars(&[("HOMEDRIVE", "D:"), ("HOMEPATH", "\\Users\\me")])
+            ),
+            Some(PathBuf::from("D:\\Users\\me"))
+        );
+        assert_eq!(
+            home_dir_from_env(
+                true,
+                vars(&[("HOME", "/c/Users/me"), ("USERPROFILE", "C:\\Users\\me")])
+            ),
+            Some(PathBuf::from("C:\\Users\\me"))
+        );
+        assert_eq!(home_dir_from_env(true, vars(&[("HOMEDRIVE", "C:")])), None);
+        assert_eq!(
+            home_dir_from_env(false, vars(&[("HOME", "/home/me"), ("USERPROFILE", "")])),
+            Some(PathBuf::from("/home/me"))
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! 模型评分榜：用户对任务结果打分（同一 prompt 在多个 Agent 上的对比结果用 comparison_id 归组），
+//! 评分持久化到应用数据目录，并按模型聚合平均分、对比胜出次数与失败次数，辅助后续选择模型。
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tokio::fs;
+
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag};
+use crate::tasks::load_task_records;
+
+const MIN_RATING: u8 = 1;
+const MAX_RATING: u8 = 5;
+const MAX_NOTE_CHARS: usize = 500;
+/// 任务记录未带模型信息时归入该分组
+const DEFAULT_MODEL_LABEL: &str = "default";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoreEntry {
+    pub task_id: String,
+    #[serde(default)]
+    pub comparison_id: Option<String>,
+    pub agent_id: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    pub prompt: String,
+    pub rating: u8,
+    #[serde(default)]
+    pub note: Option<String>,
+    /// 任务以错误结束
+    #[serde(default)]
+    pub failed: bool,
+    #[serde(default)]
+    pub duration_ms: Option<i64>,
+    pub rated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelScore {
+    pub model: String,
+    pub ratings: usize,
+    pub average_rating: f64,
+    /// 参与的对比组数
+    pub comparisons: usize,
+    /// 在对比组中独得最高分的次数
+    pub wins: usize,
+    pub failures: usize,
+    pub average_duration_ms: Option<i64>,
+}
+
+fn scoreboard_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-scoreboard-{}.json", storage_env_tag())))
+}
+
+async fn read_scores_from_path(path: &Path) -> Result<Vec<ScoreEntry>, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(Vec::new());
+            }
+            serde_json::from_str(&content).map_err(|e| format!("Failed to parse scoreboard: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(format!("Failed to read scoreboard: {}", err)),
+    }
+}
+
+async fn write_scores_to_path(path: &Path, entries: &[ScoreEntry]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create scoreboard dir: {}", e))?;
+    }
+    let payload =
+        serde_json::to_vec(entries).map_err(|e| format!("Failed to encode scoreboard: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write scoreboard: {}", e))
+}
+
+fn duration_ms(started_at: &str, finished_at: Option<&str>) -> Option<i64> {
+    let started = chrono::DateTime::parse_from_rfc3339(started_at).ok()?;
+    let finished = chrono::DateTime::parse_from_rfc3339(finished_at?).ok()?;
+    Some((finished - started).num_milliseconds().max(0))
+}
+
+/// 按模型聚合评分；对比组内最高分唯一时计一次胜出
+fn aggregate_scores(entries: &[ScoreEntry]) -> Vec<ModelScore> {
+    let model_of = |entry: &ScoreEntry| {
+        entry
+            .model
+            .clone()
+            .unwrap_or_else(|| DEFAULT_MODEL_LABEL.to_string())
+    };
+
+    let mut winners: HashMap<String, usize> = HashMap::new();
+    let mut groups: HashMap<&str, Vec<&ScoreEntry>> = HashMap::new();
+    for entry in entries {
+        if let Some(comparison_id) = entry.comparison_id.as_deref() {
+            groups.entry(comparison_id).or_default().push(entry);
+        }
+    }
+    let mut comparisons: HashMap<String, usize> = HashMap::new();
+    for group in groups.values() {
+        let mut models: Vec<String> = group.iter().map(|entry| model_of(entry)).collect();
+        models.sort();
+        models.dedup();
+        for model in models {
+            *comparisons.entry(model).or_default() += 1;
+        }
+        let best = group.iter().map(|entry| entry.rating).max().unwrap_or(0);
+        let top: Vec<&&ScoreEntry> = group.iter().filter(|entry| entry.rating == best).collect();
+        if group.len() > 1 && top.len() == 1 {
+            *winners.entry(model_of(top[0])).or_default() += 1;
+        }
+    }
+
+    let mut by_model: HashMap<String, Vec<&ScoreEntry>> = HashMap::new();
+    for entry in entries {
+        by_model.entry(model_of(entry)).or_default().push(entry);
+    }
+    let mut scores: Vec<ModelScore> = by_model
+        .into_iter()
+        .map(|(model, items)| {
+            let total: u32 = items.iter().map(|entry| u32::from(entry.rating)).sum();
+            let durations: Vec<i64> = items.iter().filter_map(|entry| entry.duration_ms).collect();
+            ModelScore {
+                ratings: items.len(),
+                average_rating: f64::from(total) / items.len() as f64,
+                comparisons: comparisons.get(&model).copied().unwrap_or(0),
+                wins: winners.get(&model).copied().unwrap_or(0),
+                failures: items.iter().filter(|entry| entry.failed).count(),
+                average_duration_ms: (!durations.is_empty())
+                    .then(|| durations.iter().sum::<i64>() / durations.len() as i64),
+                model,
+            }
+        })
+        .collect();
+    scores.sort_by(|a, b| {
+        b.average_rating
+            .total_cmp(&a.average_rating)
+            .then(b.ratings.cmp(&a.ratings))
+            .then(a.model.cmp(&b.model))
+    });
+    scores
+}
+
+/// 为任务结果打分（1-5）；同一任务重复打分时覆盖
+#[tauri::command]
+pub async fn rate_result(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    task_id: String,
+    rating: u8,
+    comparison_id: Option<String>,
+    note: Option<String>,
+) -> Result<ScoreEntry, String> {
+    if !(MIN_RATING..=MAX_RATING).contains(&rating) {
+        return Err(format!(
+            "Rating must be between {} and {}",
+            MIN_RATING, MAX_RATING
+        ));
+    }
+    let record = load_task_records(&app_handle)
+        .await?
+        .into_iter()
+        .rev()
+        .find(|record| record.id == task_id)
+        .ok_or_else(|| format!("Task {} not found", task_id))?;
+
+    let entry = ScoreEntry {
+        task_id: record.id.clone(),
+        comparison_id: comparison_id
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty()),
+        agent_id: record.agent_id.clone(),
+        model: record
+            .metadata
+            .get("model")
+            .and_then(|value| value.as_str())
+            .map(str::to_string),
+        prompt: record.prompt.clone(),
+        rating,
+        note: note
+            .map(|item| item.trim().chars().take(MAX_NOTE_CHARS).collect::<String>())
+            .filter(|item| !item.is_empty()),
+        failed: record.error.is_some(),
+        duration_ms: duration_ms(&record.started_at, record.finished_at.as_deref()),
+        rated_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let _guard = state.scoreboard_lock.lock().await;
+    let path = scoreboard_path(&app_handle)?;
+    let mut entries = read_scores_from_path(&path).await?;
+    entries.retain(|item| item.task_id != entry.task_id);
+    entries.push(entry.clone());
+    write_scores_to_path(&path, &entries).await?;
+    Ok(entry)
+}
+
+/// 按模型聚合的评分榜（平均分从高到低）
+#[tauri::command]
+pub async fn get_model_scoreboard(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<ModelScore>, String> {
+    let _guard = state.scoreboard_lock.lock().await;
+    let entries = read_scores_from_path(&scoreboard_path(&app_handle)?).await?;
+    Ok(aggregate_scores(&entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(
+        task_id: &str,
+        comparison_id: Option<&str>,
+        model: Option<&str>,
+        rating: u8,
+    ) -> ScoreEntry {
+        ScoreEntry {
+            task_id: task_id.to_string(),
+            comparison_id: comparison_id.map(str::to_string),
+            agent_id: format!("agent-{}", task_id),
+            model: model.map(str::to_string),
+            prompt: "compare".to_string(),
+            rating,
+            note: None,
+            failed: false,
+            duration_ms: Some(1000),
+            rated_at: "2026-03-02T08:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn scoreboard_aggregates_ratings_and_wins() {
+        let mut failed = entry("t5", None, Some("glm-4.6"), 1);
+        failed.failed = true;
+        failed.duration_ms = None;
+        let entries = vec![
+            entry("t1", Some("c1"), Some("qwen3-coder"), 5),
+            entry("t2", Some("c1"), Some("glm-4.6"), 3),
+            entry("t3", Some("c2"), Some("qwen3-coder"), 4),
+            entry("t4", Some("c2"), Some("glm-4.6"), 4),
+            failed,
+            entry("t6", None, None, 2),
+        ];
+        let scores = aggregate_scores(&entries);
+        let models: Vec<&str> = scores.iter().map(|score| score.model.as_str()).collect();
+        assert_eq!(models, vec!["qwen3-coder", "glm-4.6", "default"]);
+
+        let qwen = &scores[0];
+        assert_eq!(qwen.ratings, 2);
+        assert_eq!(qwen.average_rating, 4.5);
+        assert_eq!(qwen.comparisons, 2);
+        // c2 平分，不计胜出
+        assert_eq!(qwen.wins, 1);
+
+        let glm = &scores[1];
+        assert_eq!(glm.wins, 0);
+        assert_eq!(glm.failures, 1);
+        assert_eq!(glm.average_duration_ms, Some(1000));
+    }
+
+    #[tokio::test]
+    async fn scores_roundtrip() {
+        let path =
+            std::env::temp_dir().join(format!("iflow-scoreboard-{}.json", uuid::Uuid::new_v4()));
+        assert!(read_scores_from_path(&path).await.unwrap().is_empty());
+        let entries = vec![entry("t1", Some("c1"), Some("qwen3-coder"), 5)];
+        write_scores_to_path(&path, &entries).await.unwrap();
+        assert_eq!(read_scores_from_path(&path).await.unwrap(), entries);
+        assert_eq!(
+            duration_ms("2026-03-02T08:00:00Z", Some("2026-03-02T08:00:02.500Z")),
+            Some(2500)
+        );
+        let _ = fs::remove_file(&path).await;
+    }
+}
@@ -0,0 +1,198 @@
+//! Agent 会话的临时草稿目录：连接时在应用数据目录下为每个 Agent 创建 `scratch/<agentId>`，
+//! 通过环境变量 `FLOWHUB_SCRATCH_DIR` 告知 Agent，存放中间文件而不污染仓库。
+//! 断开连接时删除；应用异常退出遗留的目录由启动时的保留期清理回收（登记表中可接管的 Agent 除外）。
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+use tauri::{Manager, State};
+
+use crate::agents::registry::registered_agent_ids;
+use crate::state::AppState;
+use crate::storage::app_data_dir;
+
+/// 注入 Agent 进程的草稿目录环境变量
+pub(crate) const SCRATCH_DIR_ENV: &str = "FLOWHUB_SCRATCH_DIR";
+/// 无主目录超过此时长未修改才会被清理
+const STALE_SCRATCH_GRACE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScratchRetentionReport {
+    pub removed: Vec<String>,
+}
+
+fn scratch_root(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join("scratch"))
+}
+
+/// Agent ID 来自前端，只保留可安全用作目录名的字符
+fn scratch_dir_name(agent_id: &str) -> Result<String, String> {
+    let name: String = agent_id
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        return Err("Agent ID cannot be empty".to_string());
+    }
+    Ok(name)
+}
+
+async fn ensure_scratch_dir_in(root: &Path, agent_id: &str) -> Result<PathBuf, String> {
+    let dir = root.join(scratch_dir_name(agent_id)?);
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create scratch dir {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+/// 创建（或复用）Agent 的草稿目录
+pub(crate) async fn ensure_scratch_dir(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+) -> Result<PathBuf, String> {
+    ensure_scratch_dir_in(&scratch_root(app_handle)?, agent_id).await
+}
+
+/// 会话结束时删除草稿目录；目录不存在时忽略
+pub(crate) async fn remove_scratch_dir(app_handle: &tauri::AppHandle, agent_id: &str) {
+    let Ok(dir) = scratch_root(app_handle)
+        .and_then(|root| scratch_dir_name(agent_id).map(|name| root.join(name)))
+    else {
+        return;
+    };
+    match tokio::fs::remove_dir_all(&dir).await {
+        Ok(()) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => println!("[scratch] Failed to remove {}: {}", dir.display(), err),
+    }
+}
+
+/// 删除不属于 `keep` 且超过宽限期未修改的目录
+async fn collect_stale_scratch_dirs(
+    root: &Path,
+    keep: &HashSet<String>,
+    now: SystemTime,
+) -> Result<ScratchRetentionReport, String> {
+    let mut report = ScratchRetentionReport::default();
+    let mut entries = match tokio::fs::read_dir(root).await {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+        Err(err) => return Err(format!("Failed to read scratch dir: {}", err)),
+    };
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read scratch dir: {}", e))?
+    {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if keep.contains(&name) {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+        if !metadata.is_dir() {
+            continue;
+        }
+        let modified = metadata.modified().unwrap_or(now);
+        if now.duration_since(modified).unwrap_or_default() < STALE_SCRATCH_GRACE {
+            continue;
+        }
+        if tokio::fs::remove_dir_all(entry.path()).await.is_ok() {
+            report.removed.push(name);
+        }
+    }
+    Ok(report)
+}
+
+/// 保留期清理：保留已连接与登记表中可接管的 Agent 的目录
+pub(crate) async fn run_scratch_retention_pass(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+) -> Result<ScratchRetentionReport, String> {
+    let (_, live_ids) = state.agent_manager.stats().await;
+    let keep = live_ids
+        .into_iter()
+        .chain(registered_agent_ids(app_handle).await)
+        .filter_map(|agent_id| scratch_dir_name(&agent_id).ok())
+        .collect();
+    collect_stale_scratch_dirs(&scratch_root(app_handle)?, &keep, SystemTime::now()).await
+}
+
+/// 启动时在后台执行一次保留期清理
+pub(crate) fn spawn_scratch_retention(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let state = app_handle.state::<AppState>();
+        match run_scratch_retention_pass(&app_handle, &state).await {
+            Ok(report) if !report.removed.is_empty() => println!(
+                "[scratch] Removed {} stale scratch dirs",
+                report.removed.len()
+            ),
+            Ok(_) => {}
+            Err(e) => println!("[scratch] Retention pass failed: {}", e),
+        }
+    });
+}
+
+/// 返回已连接 Agent 的草稿目录（不存在时创建）
+#[tauri::command]
+pub async fn get_scratch_dir(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    agent_id: String,
+) -> Result<String, String> {
+    if state
+        .agent_manager
+        .workspace_path_of(&agent_id)
+        .await
+        .is_none()
+    {
+        return Err(format!("Agent {} not found", agent_id));
+    }
+    let dir = ensure_scratch_dir(&app_handle, &agent_id).await?;
+    Ok(dir.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stale_scratch_dirs_are_collected_except_kept_agents() {
+        let root = std::env::temp_dir().join(format!("iflow-scratch-{}", uuid::Uuid::new_v4()));
+        let live = ensure_scratch_dir_in(&root, "agent-live")
+            .await
+            .expect("live");
+        let orphan = ensure_scratch_dir_in(&root, "agent/../orphan")
+            .await
+            .expect("orphan");
+        assert_eq!(orphan, root.join("agent____orphan"));
+        std::fs::write(orphan.join("notes.txt"), "tmp").expect("write scratch file");
+
+        let keep = HashSet::from(["agent-live".to_string()]);
+        let report = collect_stale_scratch_dirs(&root, &keep, SystemTime::now())
+            .await
+            .expect("fresh pass");
+        assert!(report.removed.is_empty());
+
+        let later = SystemTime::now() + STALE_SCRATCH_GRACE + Duration::from_secs(1);
+        let report = collect_stale_scratch_dirs(&root, &keep, later)
+            .await
+            .expect("later pass");
+        assert_eq!(report.removed, vec!["agent____orphan".to_string()]);
+        assert!(live.exists());
+        assert!(!orphan.exists());
+        assert!(scratch_dir_name("  ").is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,197 @@
+//! 敏感配置（数据库连接串、令牌等）的本地存储，支持全局与工作区两级作用域
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tokio::fs;
+
+use crate::history::normalize_workspace_path;
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretStore {
+    #[serde(default)]
+    pub global: HashMap<String, String>,
+    #[serde(default)]
+    pub by_workspace: HashMap<String, HashMap<String, String>>,
+}
+
+impl SecretStore {
+    fn scope_mut(&mut self, workspace_path: Option<&str>) -> &mut HashMap<String, String> {
+        match workspace_path {
+            Some(path) => self
+                .by_workspace
+                .entry(normalize_workspace_path(path))
+                .or_default(),
+            None => &mut self.global,
+        }
+    }
+
+    /// 工作区作用域优先，未命中时回退全局作用域
+    pub fn resolve(&self, workspace_path: Option<&str>, name: &str) -> Option<String> {
+        if let Some(path) = workspace_path {
+            let scoped = self
+                .by_workspace
+                .get(&normalize_workspace_path(path))
+                .and_then(|items| items.get(name));
+            if let Some(value) = scoped {
+                return Some(value.clone());
+            }
+        }
+        self.global.get(name).cloned()
+    }
+}
+
+fn secrets_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-secrets-{}.json", storage_env_tag())))
+}
+
+fn normalize_secret_name(name: &str) -> Result<String, String> {
+    let normalized = name.trim().to_string();
+    if normalized.is_empty() {
+        return Err("Secret name cannot be empty".to_string());
+    }
+    Ok(normalized)
+}
+
+fn normalize_scope(workspace_path: Option<String>) -> Option<String> {
+    workspace_path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+}
+
+async fn read_secrets_from_path(path: &Path) -> Result<SecretStore, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(SecretStore::default());
+            }
+            serde_json::from_str(&content).map_err(|e| format!("Failed to parse secrets: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(SecretStore::default()),
+        Err(err) => Err(format!("Failed to read secrets: {}", err)),
+    }
+}
+
+async fn write_secrets_to_path(path: &Path, store: &SecretStore) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create secrets dir: {}", e))?;
+    }
+    let payload =
+        serde_json::to_vec(store).map_err(|e| format!("Failed to encode secrets: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write secrets: {}", e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).await;
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn load_secret_store(
+    app_handle: &tauri::AppHandle,
+) -> Result<SecretStore, String> {
+    read_secrets_from_path(&secrets_path(app_handle)?).await
+}
+
+/// 后端内部读取密钥（不暴露给前端）
+pub(crate) async fn resolve_secret(
+    app_handle: &tauri::AppHandle,
+    workspace_path: Option<&str>,
+    name: &str,
+) -> Result<Option<String>, String> {
+    let store = load_secret_store(app_handle).await?;
+    Ok(store.resolve(workspace_path, name))
+}
+
+#[tauri::command]
+pub async fn set_secret(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+    value: String,
+    workspace_path: Option<String>,
+) -> Result<(), String> {
+    let name = normalize_secret_name(&name)?;
+    let scope = normalize_scope(workspace_path);
+
+    let _guard = state.secrets_lock.lock().await;
+    let path = secrets_path(&app_handle)?;
+    let mut store = read_secrets_from_path(&path).await?;
+    store.scope_mut(scope.as_deref()).insert(name, value);
+    write_secrets_to_path(&path, &store).await
+}
+
+#[tauri::command]
+pub async fn delete_secret(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+    workspace_path: Option<String>,
+) -> Result<bool, String> {
+    let name = normalize_secret_name(&name)?;
+    let scope = normalize_scope(workspace_path);
+
+    let _guard = state.secrets_lock.lock().await;
+    let path = secrets_path(&app_handle)?;
+    let mut store = read_secrets_from_path(&path).await?;
+    let removed = store.scope_mut(scope.as_deref()).remove(&name).is_some();
+    if removed {
+        write_secrets_to_path(&path, &store).await?;
+    }
+    Ok(removed)
+}
+
+/// 仅返回密钥名称，值不离开后端
+#[tauri::command]
+pub async fn list_secret_names(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: Option<String>,
+) -> Result<Vec<String>, String> {
+    let scope = normalize_scope(workspace_path);
+
+    let _guard = state.secrets_lock.lock().await;
+    let mut store = read_secrets_from_path(&secrets_path(&app_handle)?).await?;
+    let mut names: Vec<String> = store.scope_mut(scope.as_deref()).keys().cloned().collect();
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_prefers_workspace_scope() {
+        let mut store = SecretStore::default();
+        store
+            .scope_mut(None)
+            .insert("database_url".to_string(), "global".to_string());
+        store
+            .scope_mut(Some("/tmp/project/"))
+            .insert("database_url".to_string(), "scoped".to_string());
+
+        assert_eq!(
+            store
+                .resolve(Some("/tmp/project"), "database_url")
+                .as_deref(),
+            Some("scoped")
+        );
+        assert_eq!(
+            store.resolve(Some("/tmp/other"), "database_url").as_deref(),
+            Some("global")
+        );
+        assert_eq!(store.resolve(None, "missing"), None);
+    }
+}
@@ -0,0 +1,49 @@
+//! 会话分叉：复制 Agent 当前 iFlow 会话的历史文件作为新会话，再通过 `session/load` 切换过去。
+//! 新会话共享分叉点之前的上下文，之后的对话互不影响；原会话仍可用 `switch_agent_session` 切回。
+use tauri::State;
+use tokio::time::{timeout, Duration};
+
+use crate::history::copy_iflow_session;
+use crate::models::ListenerCommand;
+use crate::state::AppState;
+
+/// 从 `session_id` 分叉出新会话并设为 Agent 的当前会话，返回新会话 ID
+#[tauri::command]
+pub async fn fork_session(
+    state: State<'_, AppState>,
+    agent_id: String,
+    session_id: String,
+) -> Result<String, String> {
+    let workspace_path = state
+        .agent_manager
+        .workspace_path_of(&agent_id)
+        .await
+        .ok_or_else(|| format!("Agent {} not found", agent_id))?;
+    let (_, sender) = state.agent_manager.sender_of(&agent_id).await;
+    let sender = sender.ok_or_else(|| "Message sender not available".to_string())?;
+
+    let (forked_session_id, forked_file) = {
+        let _guard = state.history_lock.lock().await;
+        copy_iflow_session(&workspace_path, &session_id).await?
+    };
+
+    let (tx, rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
+    if let Err(e) = sender.send(ListenerCommand::SwitchSession {
+        session_id: forked_session_id.clone(),
+        response: tx,
+    }) {
+        let _ = tokio::fs::remove_file(&forked_file).await;
+        return Err(format!("Failed to queue session switch: {}", e));
+    }
+
+    match timeout(Duration::from_secs(20), rx).await {
+        Ok(Ok(Ok(()))) => Ok(forked_session_id),
+        Ok(Ok(Err(err))) => {
+            // Agent 明确拒绝载入时副本不会再被使用；超时的载入仍可能完成，保留副本
+            let _ = tokio::fs::remove_file(&forked_file).await;
+            Err(err)
+        }
+        Ok(Err(_)) => Err("Session switch response channel closed".to_string()),
+        Err(_) => Err("Session switch timeout after 20 seconds".to_string()),
+    }
+}
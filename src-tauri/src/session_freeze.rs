@@ -0,0 +1,401 @@
+//! 会话只读快照（审计用）：把会话消息、iFlow 记录中的工具调用、Agent 的 fs 审计记录与运行环境
+//! 写成一份只读文件，并在索引中登记文件内容的 SHA-256 与 HMAC-SHA256 签名。签名密钥随机生成并
+//! 保存在系统钥匙串，快照与索引所在的目录中拿不到，同时改写快照和索引也无法伪造签名。
+//! `verify_session_snapshot` 重新计算签名，与索引及快照内的 ID 比对，任何改动（包括删除）都会被
+//! 报告为校验失败。
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tauri::State;
+use tokio::fs;
+
+use crate::export::load_session_transcript;
+use crate::history::load_iflow_tool_entries;
+use crate::legal_hold::{session_audit_entries, AuditEntry};
+use crate::models::PermissionMode;
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag, StoredMessage, StoredSession};
+
+const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+const KEYRING_SERVICE: &str = "iflow-workspace";
+const SIGNING_KEY_BYTES: usize = 32;
+
+/// 冻结时的运行环境；Agent 未连接时只记录应用与系统信息
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentRecord {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub agent_type: Option<String>,
+    pub workspace_path: Option<String>,
+    pub model: Option<String>,
+    pub permission_mode: Option<PermissionMode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FrozenSessionSnapshot {
+    pub version: u32,
+    pub snapshot_id: String,
+    pub frozen_at: String,
+    pub session: StoredSession,
+    pub messages: Vec<StoredMessage>,
+    /// iFlow 会话记录中的 tool_use / tool_result 条目
+    pub tool_calls: Vec<Value>,
+    /// 该 ACP 会话中 Agent 的文件读写审计记录
+    pub fs_audit: Vec<AuditEntry>,
+    pub environment: EnvironmentRecord,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FrozenSessionRecord {
+    pub snapshot_id: String,
+    pub session_id: String,
+    pub frozen_at: String,
+    pub sha256: String,
+    /// 快照文件内容的 HMAC-SHA256（hex）；旧版本索引中为空
+    #[serde(default)]
+    pub signature: String,
+    pub path: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct FrozenSessionIndex {
+    #[serde(default)]
+    records: Vec<FrozenSessionRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotVerification {
+    pub snapshot_id: String,
+    pub valid: bool,
+    pub expected_sha256: String,
+    /// 快照文件缺失时为空
+    pub actual_sha256: Option<String>,
+    pub reason: Option<String>,
+}
+
+fn frozen_sessions_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join("frozen-sessions"))
+}
+
+fn frozen_index_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-frozen-sessions-{}.json", storage_env_tag())))
+}
+
+async fn read_index_from_path(path: &Path) -> Result<FrozenSessionIndex, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(FrozenSessionIndex::default());
+            }
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse frozen session index: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(FrozenSessionIndex::default()),
+        Err(err) => Err(format!("Failed to read frozen session index: {}", err)),
+    }
+}
+
+async fn write_index_to_path(path: &Path, index: &FrozenSessionIndex) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create frozen session dir: {}", e))?;
+    }
+    let payload = serde_json::to_vec(index)
+        .map_err(|e| format!("Failed to encode frozen session index: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write frozen session index: {}", e))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn snapshot_mac(key: &[u8], bytes: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(bytes);
+    mac
+}
+
+fn sign_snapshot(key: &[u8], bytes: &[u8]) -> String {
+    snapshot_mac(key, bytes)
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+/// 钥匙串中的签名密钥，首次冻结时生成
+fn signing_key(create: bool) -> Result<Option<Vec<u8>>, String> {
+    let entry = keyring::Entry::new(
+        KEYRING_SERVICE,
+        &format!("snapshot-signing-{}", storage_env_tag()),
+    )
+    .map_err(|e| format!("Failed to open OS keychain: {}", e))?;
+    match entry.get_password() {
+        Ok(encoded) => STANDARD
+            .decode(encoded.trim())
+            .map(Some)
+            .map_err(|e| format!("Failed to decode snapshot signing key: {}", e)),
+        Err(keyring::Error::NoEntry) if create => {
+            let mut key = vec![0_u8; SIGNING_KEY_BYTES];
+            OsRng.fill_bytes(&mut key);
+            entry
+                .set_password(&STANDARD.encode(&key))
+                .map_err(|e| format!("Failed to store snapshot signing key: {}", e))?;
+            Ok(Some(key))
+        }
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read snapshot signing key: {}", e)),
+    }
+}
+
+/// 按登记的签名与摘要校验快照文件内容；`bytes` 为空表示文件缺失，`key` 为空表示签名密钥缺失
+fn verify_snapshot_bytes(
+    record: &FrozenSessionRecord,
+    bytes: Option<&[u8]>,
+    key: Option<&[u8]>,
+) -> SnapshotVerification {
+    let mut verification = SnapshotVerification {
+        snapshot_id: record.snapshot_id.clone(),
+        valid: false,
+        expected_sha256: record.sha256.clone(),
+        actual_sha256: None,
+        reason: None,
+    };
+    let Some(bytes) = bytes else {
+        verification.reason = Some("Snapshot file is missing".to_string());
+        return verification;
+    };
+    let actual = sha256_hex(bytes);
+    verification.actual_sha256 = Some(actual.clone());
+    let Some(key) = key else {
+        verification.reason = Some("Snapshot signing key is missing".to_string());
+        return verification;
+    };
+    let signed = from_hex(&record.signature)
+        .is_some_and(|signature| snapshot_mac(key, bytes).verify_slice(&signature).is_ok());
+    if !signed {
+        verification.reason = Some("Snapshot signature is invalid".to_string());
+        return verification;
+    }
+    if actual != record.sha256 {
+        verification.reason = Some("Snapshot content does not match its recorded hash".to_string());
+        return verification;
+    }
+    match serde_json::from_slice::<FrozenSessionSnapshot>(bytes) {
+        Ok(snapshot) if snapshot.snapshot_id == record.snapshot_id => verification.valid = true,
+        Ok(_) => verification.reason = Some("Snapshot id does not match the index".to_string()),
+        Err(e) => verification.reason = Some(format!("Failed to parse snapshot: {}", e)),
+    }
+    verification
+}
+
+/// 冻结会话存储中的一个会话，返回登记的快照记录
+#[tauri::command]
+pub async fn freeze_session(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<FrozenSessionRecord, String> {
+    let transcript = load_session_transcript(&app_handle, &state, &session_id).await?;
+    let agent_id = transcript.session.agent_id.clone();
+    let manager = &state.agent_manager;
+    let environment = EnvironmentRecord {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        agent_type: manager.agent_type_of(&agent_id).await,
+        workspace_path: manager.workspace_path_of(&agent_id).await,
+        model: manager.model_of(&agent_id).await,
+        permission_mode: manager.permission_mode_of(&agent_id).await,
+    };
+
+    let (tool_calls, fs_audit) = match transcript.session.acp_session_id.as_deref() {
+        Some(acp_session_id) => (
+            load_iflow_tool_entries(environment.workspace_path.as_deref(), acp_session_id).await?,
+            session_audit_entries(&app_handle, acp_session_id).await?,
+        ),
+        None => (Vec::new(), Vec::new()),
+    };
+
+    let snapshot = FrozenSessionSnapshot {
+        version: SNAPSHOT_FORMAT_VERSION,
+        snapshot_id: uuid::Uuid::new_v4().to_string(),
+        frozen_at: chrono::Utc::now().to_rfc3339(),
+        session: transcript.session,
+        messages: transcript.messages,
+        tool_calls,
+        fs_audit,
+        environment,
+    };
+    let payload = serde_json::to_vec_pretty(&snapshot)
+        .map_err(|e| format!("Failed to encode snapshot: {}", e))?;
+    let key = signing_key(true)?.ok_or_else(|| "Snapshot signing key is missing".to_string())?;
+
+    let _guard = state.frozen_sessions_lock.lock().await;
+    let dir = frozen_sessions_dir(&app_handle)?;
+    fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create frozen session dir: {}", e))?;
+    let path = dir.join(format!("{}.json", snapshot.snapshot_id));
+    fs::write(&path, &payload)
+        .await
+        .map_err(|e| format!("Failed to write snapshot: {}", e))?;
+    let mut permissions = fs::metadata(&path)
+        .await
+        .map_err(|e| format!("Failed to inspect snapshot: {}", e))?
+        .permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&path, permissions)
+        .await
+        .map_err(|e| format!("Failed to make snapshot read-only: {}", e))?;
+
+    let record = FrozenSessionRecord {
+        snapshot_id: snapshot.snapshot_id,
+        session_id,
+        frozen_at: snapshot.frozen_at,
+        sha256: sha256_hex(&payload),
+        signature: sign_snapshot(&key, &payload),
+        path: path.to_string_lossy().to_string(),
+    };
+    let index_path = frozen_index_path(&app_handle)?;
+    let mut index = read_index_from_path(&index_path).await?;
+    index.records.push(record.clone());
+    write_index_to_path(&index_path, &index).await?;
+    Ok(record)
+}
+
+#[tauri::command]
+pub async fn verify_session_snapshot(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    snapshot_id: String,
+) -> Result<SnapshotVerification, String> {
+    let _guard = state.frozen_sessions_lock.lock().await;
+    let index = read_index_from_path(&frozen_index_path(&app_handle)?).await?;
+    let record = index
+        .records
+        .iter()
+        .find(|record| record.snapshot_id == snapshot_id)
+        .ok_or_else(|| format!("Snapshot {} not found", snapshot_id))?;
+    let path = frozen_sessions_dir(&app_handle)?.join(format!("{}.json", record.snapshot_id));
+    let bytes = match fs::read(&path).await {
+        Ok(bytes) => Some(bytes),
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => return Err(format!("Failed to read snapshot: {}", err)),
+    };
+    let key = signing_key(false)?;
+    Ok(verify_snapshot_bytes(
+        record,
+        bytes.as_deref(),
+        key.as_deref(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verification_detects_tampering_and_missing_files() {
+        let snapshot = FrozenSessionSnapshot {
+            version: SNAPSHOT_FORMAT_VERSION,
+            snapshot_id: "snap-1".to_string(),
+            frozen_at: "2026-01-01T00:00:00Z".to_string(),
+            session: StoredSession {
+                id: "session-1".to_string(),
+                ..Default::default()
+            },
+            messages: vec![StoredMessage {
+                id: "m1".to_string(),
+                role: "assistant".to_string(),
+                content: "rm -rf build".to_string(),
+                ..Default::default()
+            }],
+            tool_calls: Vec::new(),
+            fs_audit: Vec::new(),
+            environment: EnvironmentRecord {
+                app_version: "0.1.0".to_string(),
+                os: "linux".to_string(),
+                arch: "x86_64".to_string(),
+                agent_type: None,
+                workspace_path: None,
+                model: None,
+                permission_mode: None,
+            },
+        };
+        let key = b"test-signing-key".as_slice();
+        let payload = serde_json::to_vec_pretty(&snapshot).unwrap();
+        let record = FrozenSessionRecord {
+            snapshot_id: "snap-1".to_string(),
+            session_id: "session-1".to_string(),
+            frozen_at: snapshot.frozen_at.clone(),
+            sha256: sha256_hex(&payload),
+            signature: sign_snapshot(key, &payload),
+            path: String::new(),
+        };
+
+        assert!(verify_snapshot_bytes(&record, Some(&payload), Some(key)).valid);
+
+        let tampered = String::from_utf8(payload.clone())
+            .unwrap()
+            .replace("rm -rf build", "ls");
+        let result = verify_snapshot_bytes(&record, Some(tampered.as_bytes()), Some(key));
+        assert!(!result.valid);
+        assert_ne!(
+            result.actual_sha256.as_deref(),
+            Some(record.sha256.as_str())
+        );
+
+        // 同时改写快照与索引中的摘要：没有钥匙串中的密钥，签名无法随之更新
+        let rewritten = FrozenSessionRecord {
+            sha256: sha256_hex(tampered.as_bytes()),
+            signature: sign_snapshot(b"attacker-key", tampered.as_bytes()),
+            ..record.clone()
+        };
+        let result = verify_snapshot_bytes(&rewritten, Some(tampered.as_bytes()), Some(key));
+        assert!(!result.valid);
+        assert_eq!(
+            result.reason.as_deref(),
+            Some("Snapshot signature is invalid")
+        );
+
+        let unsigned = FrozenSessionRecord {
+            signature: String::new(),
+            ..record.clone()
+        };
+        assert!(!verify_snapshot_bytes(&unsigned, Some(&payload), Some(key)).valid);
+        assert!(!verify_snapshot_bytes(&record, Some(&payload), None).valid);
+
+        let missing = verify_snapshot_bytes(&record, None, Some(key));
+        assert!(!missing.valid);
+        assert!(missing.actual_sha256.is_none());
+    }
+}
@@ -0,0 +1,305 @@
+//! 会话关系图：交接等操作会让会话形成有向无环图。父子关系连同分叉点（分叉时父会话已有的消息数）
+//! 单独持久化，不受前端整体保存会话存储的影响；`get_session_graph` 按工作区返回节点与边，供界面绘制分支地图。
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tokio::fs;
+
+use crate::history::normalize_workspace_path;
+use crate::state::AppState;
+use crate::storage::{
+    app_data_dir, read_snapshot_from_path, storage_env_tag, storage_path, StorageSnapshot,
+    StoredSession,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionLink {
+    pub parent_session_id: String,
+    pub child_session_id: String,
+    /// 分叉时父会话已有的消息数，子会话从这里接续
+    pub fork_point: usize,
+    pub workspace_path: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionLinkStore {
+    #[serde(default)]
+    pub links: Vec<SessionLink>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionGraphNode {
+    pub id: String,
+    pub agent_id: String,
+    pub title: String,
+    pub created_at: String,
+    pub message_count: usize,
+    /// 没有父会话的起点
+    pub root: bool,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionGraphEdge {
+    pub from: String,
+    pub to: String,
+    /// 旧数据只记录了交接关系，没有分叉点
+    pub fork_point: Option<usize>,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionGraph {
+    pub nodes: Vec<SessionGraphNode>,
+    pub edges: Vec<SessionGraphEdge>,
+}
+
+fn session_links_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-session-graph-{}.json", storage_env_tag())))
+}
+
+async fn read_links_from_path(path: &Path) -> Result<SessionLinkStore, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(SessionLinkStore::default());
+            }
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse session graph: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(SessionLinkStore::default()),
+        Err(err) => Err(format!("Failed to read session graph: {}", err)),
+    }
+}
+
+async fn write_links_to_path(path: &Path, store: &SessionLinkStore) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create session graph dir: {}", e))?;
+    }
+    let payload =
+        serde_json::to_vec(store).map_err(|e| format!("Failed to encode session graph: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write session graph: {}", e))
+}
+
+/// 记录一条父子关系；调用方须持有 `storage_lock`
+pub(crate) async fn append_session_link(
+    app_handle: &tauri::AppHandle,
+    link: SessionLink,
+) -> Result<(), String> {
+    let path = session_links_path(app_handle)?;
+    let mut store = read_links_from_path(&path).await?;
+    store.links.retain(|existing| {
+        existing.parent_session_id != link.parent_session_id
+            || existing.child_session_id != link.child_session_id
+    });
+    store.links.push(link);
+    write_links_to_path(&path, &store).await
+}
+
+/// 工作区内的节点为：在线 Agent 的会话 + 记录在该工作区的关系所涉及的会话；已删除的会话不出现
+fn build_session_graph(
+    snapshot: &StorageSnapshot,
+    links: &[SessionLink],
+    workspace_path: &str,
+    workspace_agents: &HashSet<String>,
+) -> SessionGraph {
+    let workspace = normalize_workspace_path(workspace_path);
+    let sessions: HashMap<&str, (&str, &StoredSession)> = snapshot
+        .sessions_by_agent
+        .iter()
+        .flat_map(|(agent_id, sessions)| {
+            sessions
+                .iter()
+                .map(move |session| (session.id.as_str(), (agent_id.as_str(), session)))
+        })
+        .collect();
+
+    let mut edges: Vec<SessionGraphEdge> = links
+        .iter()
+        .filter(|link| normalize_workspace_path(&link.workspace_path) == workspace)
+        .filter(|link| {
+            sessions.contains_key(link.parent_session_id.as_str())
+                && sessions.contains_key(link.child_session_id.as_str())
+        })
+        .map(|link| SessionGraphEdge {
+            from: link.parent_session_id.clone(),
+            to: link.child_session_id.clone(),
+            fork_point: Some(link.fork_point),
+            created_at: Some(link.created_at.clone()),
+        })
+        .collect();
+
+    let mut node_ids: HashSet<&str> = sessions
+        .iter()
+        .filter(|(_, (agent_id, _))| workspace_agents.contains(*agent_id))
+        .map(|(id, _)| *id)
+        .collect();
+    for edge in &edges {
+        node_ids.insert(edge.from.as_str());
+        node_ids.insert(edge.to.as_str());
+    }
+    let mut node_ids: HashSet<String> = node_ids.into_iter().map(str::to_string).collect();
+
+    // 会话存储中的旧交接字段（前端保存快照时可能丢失，仅作补充），沿已有节点逐步扩展
+    let mut legacy: Vec<(&str, &StoredSession)> = sessions
+        .iter()
+        .filter_map(|(id, (_, session))| {
+            let parent = session.handoff_from.as_deref()?;
+            (sessions.contains_key(parent)
+                && !edges
+                    .iter()
+                    .any(|edge| edge.from == parent && edge.to == *id))
+            .then_some((parent, *session))
+        })
+        .collect();
+    loop {
+        let (connected, rest): (Vec<_>, Vec<_>) =
+            legacy.into_iter().partition(|(parent, child)| {
+                node_ids.contains(*parent) || node_ids.contains(child.id.as_str())
+            });
+        legacy = rest;
+        if connected.is_empty() {
+            break;
+        }
+        for (parent, child) in connected {
+            node_ids.insert(parent.to_string());
+            node_ids.insert(child.id.clone());
+            edges.push(SessionGraphEdge {
+                from: parent.to_string(),
+                to: child.id.clone(),
+                fork_point: None,
+                created_at: Some(child.created_at.clone()),
+            });
+        }
+    }
+
+    let children: HashSet<&str> = edges.iter().map(|edge| edge.to.as_str()).collect();
+    let mut nodes: Vec<SessionGraphNode> = node_ids
+        .iter()
+        .filter_map(|id| sessions.get(id.as_str()))
+        .map(|(agent_id, session)| SessionGraphNode {
+            id: session.id.clone(),
+            agent_id: agent_id.to_string(),
+            title: session.title.clone(),
+            created_at: session.created_at.clone(),
+            message_count: snapshot
+                .messages_by_session
+                .get(&session.id)
+                .map(Vec::len)
+                .or(session.message_count_hint)
+                .unwrap_or(0),
+            root: !children.contains(session.id.as_str()),
+        })
+        .collect();
+    nodes.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+    edges.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.to.cmp(&b.to)));
+    SessionGraph { nodes, edges }
+}
+
+/// 某工作区的会话关系图
+#[tauri::command]
+pub async fn get_session_graph(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    workspace_path: String,
+) -> Result<SessionGraph, String> {
+    let workspace = normalize_workspace_path(&workspace_path);
+    let (_, agent_ids) = state.agent_manager.stats().await;
+    let mut workspace_agents = HashSet::new();
+    for agent_id in agent_ids {
+        if let Some(path) = state.agent_manager.workspace_path_of(&agent_id).await {
+            if normalize_workspace_path(&path) == workspace {
+                workspace_agents.insert(agent_id);
+            }
+        }
+    }
+
+    let _guard = state.storage_lock.lock().await;
+    let snapshot = read_snapshot_from_path(&storage_path(&app_handle)?).await?;
+    let links = read_links_from_path(&session_links_path(&app_handle)?).await?;
+    Ok(build_session_graph(
+        &snapshot,
+        &links.links,
+        &workspace,
+        &workspace_agents,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: &str, created_at: &str) -> StoredSession {
+        StoredSession {
+            id: id.to_string(),
+            title: id.to_string(),
+            created_at: created_at.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn graph_combines_recorded_links_and_legacy_handoffs() {
+        let mut legacy_child = session("c2", "2026-01-03");
+        legacy_child.handoff_from = Some("c1".to_string());
+        let mut snapshot = StorageSnapshot::default();
+        snapshot.sessions_by_agent.insert(
+            "a1".to_string(),
+            vec![
+                session("root", "2026-01-01"),
+                session("other", "2026-01-05"),
+            ],
+        );
+        snapshot.sessions_by_agent.insert(
+            "a2".to_string(),
+            vec![session("c1", "2026-01-02"), legacy_child],
+        );
+        snapshot.messages_by_session.insert(
+            "root".to_string(),
+            vec![Default::default(), Default::default()],
+        );
+        let links = vec![
+            SessionLink {
+                parent_session_id: "root".to_string(),
+                child_session_id: "c1".to_string(),
+                fork_point: 2,
+                workspace_path: "/repo/".to_string(),
+                created_at: "2026-01-02".to_string(),
+            },
+            SessionLink {
+                parent_session_id: "root".to_string(),
+                child_session_id: "deleted".to_string(),
+                fork_point: 1,
+                workspace_path: "/repo".to_string(),
+                created_at: "2026-01-04".to_string(),
+            },
+        ];
+
+        let graph = build_session_graph(&snapshot, &links, "/repo", &HashSet::new());
+        let ids: Vec<&str> = graph.nodes.iter().map(|node| node.id.as_str()).collect();
+        assert_eq!(ids, vec!["root", "c1", "c2"]);
+        assert!(graph.nodes[0].root && !graph.nodes[1].root);
+        assert_eq!(graph.nodes[0].message_count, 2);
+        assert_eq!(graph.edges.len(), 2);
+        assert_eq!(graph.edges[0].fork_point, Some(2));
+        assert_eq!(graph.edges[1].from, "c1");
+        assert_eq!(graph.edges[1].fork_point, None);
+
+        let with_agent = HashSet::from(["a1".to_string()]);
+        let graph = build_session_graph(&snapshot, &[], "/repo", &with_agent);
+        assert_eq!(graph.nodes.len(), 2);
+        assert!(graph.edges.is_empty());
+    }
+}
@@ -0,0 +1,322 @@
+//! 导入外部会话记录：解析 iFlow 的 `session-*.jsonl` 或 Claude Code 风格的 transcript
+//! （每行一条 `{"type": "user" | "assistant", "message": {...}}`，也接受 `{"role", "content"}`
+//! 的简单格式），转换为 `StoredSession` / `StoredMessage` 并合并进会话存储。
+//! 导入会话的 ID 由原会话 ID 派生，重复导入同一文件只补充缺失的消息。写入后推送
+//! `sessions-stored`，由前端合并进内存状态。
+use std::collections::HashSet;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::State;
+
+use crate::history::{compact_title, extract_history_message_content, extract_history_timestamp};
+use crate::state::AppState;
+use crate::storage::{
+    emit_sessions_stored, read_snapshot_from_path, storage_path, write_snapshot_to_path,
+    StorageSnapshot, StoredMessage, StoredSession, StoredSessionsUpdate,
+};
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedSession {
+    pub session: StoredSession,
+    /// 本次新增的消息数；重复导入时为 0
+    pub imported_messages: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ParsedTranscript {
+    session_id: String,
+    title: Option<String>,
+    messages: Vec<StoredMessage>,
+}
+
+fn record_message(record: &Value) -> Option<(&'static str, String)> {
+    let kind = record
+        .get("type")
+        .or_else(|| record.get("role"))
+        .and_then(Value::as_str)?
+        .trim();
+    let role = match kind {
+        "user" => "user",
+        "assistant" => "assistant",
+        _ => return None,
+    };
+    // Claude Code 的元信息记录（命令回显等）不属于对话
+    if record.get("isMeta").and_then(Value::as_bool) == Some(true) {
+        return None;
+    }
+    let content = if record.get("message").is_some() {
+        extract_history_message_content(record, role)
+    } else {
+        let wrapped = json!({ "message": { "content": record.get("content")? } });
+        extract_history_message_content(&wrapped, role)
+    }?;
+    Some((role, content))
+}
+
+fn parse_transcript(raw: &str, fallback_session_id: &str) -> Result<ParsedTranscript, String> {
+    let mut session_id: Option<String> = None;
+    let mut summary: Option<String> = None;
+    let mut seen = HashSet::new();
+    let mut messages = Vec::new();
+    let fallback_timestamp = chrono::Utc::now().to_rfc3339();
+
+    for (index, line) in raw.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let Ok(record) = serde_json::from_str::<Value>(trimmed) else {
+            continue;
+        };
+
+        if session_id.is_none() {
+            session_id = record
+                .get("sessionId")
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(str::to_string);
+        }
+        if record.get("type").and_then(Value::as_str) == Some("summary") {
+            summary = record
+                .get("summary")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .or(summary);
+            continue;
+        }
+
+        let Some((role, content)) = record_message(&record) else {
+            continue;
+        };
+        let id = record
+            .get("uuid")
+            .or_else(|| record.get("id"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("line-{}", index));
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+        messages.push(StoredMessage {
+            id,
+            role: role.to_string(),
+            content,
+            timestamp: extract_history_timestamp(&record)
+                .unwrap_or_else(|| fallback_timestamp.clone()),
+            ..Default::default()
+        });
+    }
+
+    if messages.is_empty() {
+        return Err("No user or assistant messages found in transcript".to_string());
+    }
+    let session_id = session_id.unwrap_or_else(|| fallback_session_id.to_string());
+    // 行号派生的 ID 只在单个文件内唯一，加上会话 ID 避免与其它会话冲突
+    for message in &mut messages {
+        if message.id.starts_with("line-") {
+            message.id = format!("{}-{}", session_id, message.id);
+        }
+    }
+    let title = summary.or_else(|| {
+        messages
+            .iter()
+            .find(|message| message.role == "user")
+            .map(|message| message.content.clone())
+    });
+    Ok(ParsedTranscript {
+        session_id,
+        title,
+        messages,
+    })
+}
+
+/// 合并进快照：已导入过的会话只追加缺失消息，否则作为新会话放到 `agent_id` 下
+fn merge_transcript(
+    snapshot: &mut StorageSnapshot,
+    agent_id: &str,
+    transcript: ParsedTranscript,
+) -> ImportedSession {
+    let stored_id = format!("import-{}", transcript.session_id);
+    // 已导入过的会话保持原归属，新消息也记在原 Agent 名下
+    let owner = snapshot
+        .sessions_by_agent
+        .values()
+        .flatten()
+        .find(|session| session.id == stored_id)
+        .map_or_else(|| agent_id.to_string(), |session| session.agent_id.clone());
+    let messages = snapshot
+        .messages_by_session
+        .entry(stored_id.clone())
+        .or_default();
+    let existing: HashSet<String> = messages.iter().map(|message| message.id.clone()).collect();
+    let mut imported_messages = 0;
+    for mut message in transcript.messages {
+        if existing.contains(&message.id) {
+            continue;
+        }
+        message.agent_id = Some(owner.clone());
+        messages.push(message);
+        imported_messages += 1;
+    }
+    messages.sort_by(|left, right| left.timestamp.cmp(&right.timestamp));
+    let created_at = messages.first().map(|message| message.timestamp.clone());
+    let updated_at = messages.last().map(|message| message.timestamp.clone());
+    let message_count = messages.len();
+
+    if let Some(session) = snapshot
+        .sessions_by_agent
+        .values_mut()
+        .flatten()
+        .find(|session| session.id == stored_id)
+    {
+        if let Some(updated_at) = updated_at {
+            session.updated_at = updated_at;
+        }
+        session.message_count_hint = Some(message_count);
+        return ImportedSession {
+            session: session.clone(),
+            imported_messages,
+        };
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let session = StoredSession {
+        id: stored_id,
+        agent_id: owner.clone(),
+        title: compact_title(
+            transcript
+                .title
+                .as_deref()
+                .unwrap_or(&transcript.session_id),
+        ),
+        created_at: created_at.unwrap_or_else(|| now.clone()),
+        updated_at: updated_at.unwrap_or(now),
+        source: Some("import".to_string()),
+        message_count_hint: Some(message_count),
+        title_generated: true,
+        ..Default::default()
+    };
+    snapshot
+        .sessions_by_agent
+        .entry(owner)
+        .or_default()
+        .insert(0, session.clone());
+    ImportedSession {
+        session,
+        imported_messages,
+    }
+}
+
+/// 从 JSONL 文件导入会话到 `agent_id` 名下
+#[tauri::command]
+pub async fn import_session_jsonl(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    agent_id: String,
+) -> Result<ImportedSession, String> {
+    let file_path = Path::new(&path);
+    let raw = tokio::fs::read_to_string(file_path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", file_path.display(), e))?;
+    let fallback_session_id = file_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let transcript = parse_transcript(&raw, &fallback_session_id)?;
+
+    let _guard = state.storage_lock.lock().await;
+    let storage = storage_path(&app_handle)?;
+    let mut snapshot = read_snapshot_from_path(&storage).await?;
+    let imported = merge_transcript(&mut snapshot, &agent_id, transcript);
+    write_snapshot_to_path(&storage, &snapshot).await?;
+    let messages = snapshot
+        .messages_by_session
+        .remove(&imported.session.id)
+        .unwrap_or_default();
+    emit_sessions_stored(
+        &app_handle,
+        &StoredSessionsUpdate {
+            sessions: vec![imported.session.clone()],
+            messages_by_session: [(imported.session.id.clone(), messages)].into(),
+        },
+    );
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLAUDE_TRANSCRIPT: &str = r#"
+{"type":"summary","summary":"Fix the build","leafUuid":"a2"}
+{"type":"user","sessionId":"abc","uuid":"u1","timestamp":"2026-01-01T00:00:00Z","message":{"role":"user","content":"Why does cargo fail?"}}
+{"type":"user","sessionId":"abc","uuid":"u0","isMeta":true,"timestamp":"2026-01-01T00:00:00Z","message":{"role":"user","content":"<command-name>/clear</command-name>"}}
+{"type":"assistant","sessionId":"abc","uuid":"a1","timestamp":"2026-01-01T00:00:01Z","message":{"role":"assistant","content":[{"type":"tool_use","id":"t1","name":"Bash","input":{}}]}}
+{"type":"assistant","sessionId":"abc","uuid":"a2","timestamp":"2026-01-01T00:00:02Z","message":{"role":"assistant","content":[{"type":"thinking","thinking":"..."},{"type":"text","text":"A missing feature flag."}]}}
+not json
+"#;
+
+    #[test]
+    fn parses_claude_style_transcripts_and_plain_role_lines() {
+        let parsed = parse_transcript(CLAUDE_TRANSCRIPT, "fallback").unwrap();
+        assert_eq!(parsed.session_id, "abc");
+        assert_eq!(parsed.title.as_deref(), Some("Fix the build"));
+        let contents: Vec<(&str, &str)> = parsed
+            .messages
+            .iter()
+            .map(|message| (message.role.as_str(), message.content.as_str()))
+            .collect();
+        assert_eq!(
+            contents,
+            vec![
+                ("user", "Why does cargo fail?"),
+                ("assistant", "A missing feature flag."),
+            ]
+        );
+
+        let plain = parse_transcript(
+            "{\"role\":\"user\",\"content\":\"hi\"}\n{\"role\":\"assistant\",\"content\":[{\"type\":\"text\",\"text\":\"hello\"}]}",
+            "session-1",
+        )
+        .unwrap();
+        assert_eq!(plain.session_id, "session-1");
+        assert_eq!(plain.messages[0].id, "session-1-line-0");
+        assert_eq!(plain.messages[1].content, "hello");
+
+        assert!(parse_transcript("{\"type\":\"summary\"}", "empty").is_err());
+    }
+
+    #[test]
+    fn reimporting_only_adds_missing_messages() {
+        let mut snapshot = StorageSnapshot::default();
+        let parsed = parse_transcript(CLAUDE_TRANSCRIPT, "fallback").unwrap();
+        let first = merge_transcript(&mut snapshot, "agent-1", parsed.clone());
+        assert_eq!(first.imported_messages, 2);
+        assert_eq!(first.session.id, "import-abc");
+        assert_eq!(first.session.source.as_deref(), Some("import"));
+
+        let mut extended = parsed;
+        extended.messages.push(StoredMessage {
+            id: "u2".to_string(),
+            role: "user".to_string(),
+            content: "Thanks".to_string(),
+            timestamp: "2026-01-01T00:00:03Z".to_string(),
+            ..Default::default()
+        });
+        let second = merge_transcript(&mut snapshot, "agent-2", extended);
+        assert_eq!(second.imported_messages, 1);
+        assert_eq!(second.session.agent_id, "agent-1");
+        assert_eq!(second.session.message_count_hint, Some(3));
+        assert_eq!(snapshot.sessions_by_agent["agent-1"].len(), 1);
+        assert!(!snapshot.sessions_by_agent.contains_key("agent-2"));
+        assert!(snapshot.messages_by_session["import-abc"]
+            .iter()
+            .all(|message| message.agent_id.as_deref() == Some("agent-1")));
+        assert_eq!(snapshot.messages_by_session["import-abc"].len(), 3);
+    }
+}
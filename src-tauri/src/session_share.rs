@@ -0,0 +1,184 @@
+//! 端到端加密的会话分享：会话打包为 JSON 后用一次性 AES-256-GCM 密钥加密，再上传到远端存储；
+//! 密钥只放在链接的 `#key=` 片段中（HTTP 请求不会携带片段），存储服务只能看到密文。
+//! 打开链接时下载并解密为只读视图，不写入本地会话存储。
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::export::load_session_transcript;
+use crate::remote_storage::{download, RemoteStorage, MAX_PRESIGN_SECS};
+use crate::state::AppState;
+use crate::storage::{StoredMessage, StoredSession};
+
+const SHARE_KEY_PREFIX: &str = "shared";
+const KEY_FRAGMENT: &str = "key=";
+const BUNDLE_VERSION: u32 = 1;
+const NONCE_BYTES: usize = 12;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct SharedSessionBundle {
+    version: u32,
+    shared_at: String,
+    session: StoredSession,
+    messages: Vec<StoredMessage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedSessionLink {
+    /// 密文地址（不含密钥）
+    pub url: String,
+    /// base64url 编码的解密密钥
+    pub key: String,
+    /// 完整分享链接：`<url>#key=<key>`
+    pub link: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedSessionView {
+    pub shared_at: String,
+    pub session: StoredSession,
+    pub messages: Vec<StoredMessage>,
+    pub read_only: bool,
+}
+
+/// 加密并返回（密钥, nonce || 密文）
+fn encrypt_bundle(plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let key = Aes256Gcm::generate_key(OsRng);
+    let nonce = Aes256Gcm::generate_nonce(OsRng);
+    let ciphertext = Aes256Gcm::new(&key)
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| "Failed to encrypt session bundle".to_string())?;
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    Ok((key.to_vec(), payload))
+}
+
+fn decrypt_bundle(key: &[u8], payload: &[u8]) -> Result<Vec<u8>, String> {
+    if payload.len() <= NONCE_BYTES {
+        return Err("Shared session payload is truncated".to_string());
+    }
+    let cipher =
+        Aes256Gcm::new_from_slice(key).map_err(|_| "Invalid shared session key".to_string())?;
+    let (nonce, ciphertext) = payload.split_at(NONCE_BYTES);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt shared session (wrong key or corrupted data)".to_string())
+}
+
+fn build_share_link(url: &str, key: &[u8]) -> (String, String) {
+    let key = URL_SAFE_NO_PAD.encode(key);
+    (format!("{}#{}{}", url, KEY_FRAGMENT, key), key)
+}
+
+/// 拆分分享链接为（密文地址, 密钥）
+fn split_share_link(link: &str) -> Result<(String, Vec<u8>), String> {
+    let (url, fragment) = link
+        .trim()
+        .split_once('#')
+        .ok_or_else(|| "Share link is missing the key fragment".to_string())?;
+    let key = fragment
+        .strip_prefix(KEY_FRAGMENT)
+        .ok_or_else(|| "Share link is missing the key fragment".to_string())?;
+    let key = URL_SAFE_NO_PAD
+        .decode(key)
+        .map_err(|e| format!("Invalid share key: {}", e))?;
+    Ok((url.to_string(), key))
+}
+
+/// 加密上传会话并返回分享链接；S3 未配置公开前缀时链接为 7 天有效的预签名地址
+#[tauri::command]
+pub async fn share_session_encrypted(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<SharedSessionLink, String> {
+    let storage = RemoteStorage::from_settings(&app_handle).await?;
+    let transcript = load_session_transcript(&app_handle, &state, &session_id).await?;
+    let bundle = SharedSessionBundle {
+        version: BUNDLE_VERSION,
+        shared_at: chrono::Utc::now().to_rfc3339(),
+        session: transcript.session,
+        messages: transcript.messages,
+    };
+    let plaintext = serde_json::to_vec(&bundle)
+        .map_err(|e| format!("Failed to encode session bundle: {}", e))?;
+    let (key, payload) = encrypt_bundle(&plaintext)?;
+
+    let object_key = format!("{}/{}.bin", SHARE_KEY_PREFIX, uuid::Uuid::new_v4());
+    // 先确认能生成分享链接，避免上传了对方打不开的对象
+    let url = storage.share_url(&object_key, MAX_PRESIGN_SECS)?;
+    storage
+        .put(&object_key, payload, "application/octet-stream")
+        .await?;
+    let (link, key) = build_share_link(&url, &key);
+    Ok(SharedSessionLink { url, key, link })
+}
+
+/// 下载并解密分享链接，返回只读会话视图
+#[tauri::command]
+pub async fn open_shared_session(
+    app_handle: tauri::AppHandle,
+    link: String,
+) -> Result<SharedSessionView, String> {
+    let (url, key) = split_share_link(&link)?;
+    // 未配置远端存储时匿名下载（S3 预签名或公开链接无需凭据）
+    let storage = RemoteStorage::from_settings(&app_handle).await.ok();
+    let payload = download(storage.as_ref(), &url).await?;
+    let plaintext = decrypt_bundle(&key, &payload)?;
+    let bundle: SharedSessionBundle = serde_json::from_slice(&plaintext)
+        .map_err(|e| format!("Failed to parse shared session: {}", e))?;
+    if bundle.version > BUNDLE_VERSION {
+        return Err(format!(
+            "Shared session format {} is newer than supported",
+            bundle.version
+        ));
+    }
+    Ok(SharedSessionView {
+        shared_at: bundle.shared_at,
+        session: bundle.session,
+        messages: bundle.messages,
+        read_only: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundle_round_trips_and_rejects_tampering() {
+        let (key, payload) = encrypt_bundle(b"{\"hello\":\"world\"}").expect("encrypt");
+        assert_eq!(key.len(), 32);
+        assert!(!payload.windows(5).any(|window| window == b"hello"));
+        assert_eq!(
+            decrypt_bundle(&key, &payload).expect("decrypt"),
+            b"{\"hello\":\"world\"}"
+        );
+
+        let mut tampered = payload.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0x01;
+        assert!(decrypt_bundle(&key, &tampered).is_err());
+
+        let (other_key, _) = encrypt_bundle(b"other").expect("encrypt");
+        assert!(decrypt_bundle(&other_key, &payload).is_err());
+    }
+
+    #[test]
+    fn share_link_carries_key_in_fragment() {
+        let key = [7_u8; 32];
+        let (link, encoded) = build_share_link("https://dav.example.com/shared/a.bin", &key);
+        assert!(link.starts_with("https://dav.example.com/shared/a.bin#key="));
+        let (url, decoded) = split_share_link(&link).expect("split");
+        assert_eq!(url, "https://dav.example.com/shared/a.bin");
+        assert_eq!(decoded, key.to_vec());
+        assert_eq!(URL_SAFE_NO_PAD.encode(&decoded), encoded);
+        assert!(split_share_link("https://dav.example.com/shared/a.bin").is_err());
+    }
+}
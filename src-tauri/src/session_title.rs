@@ -0,0 +1,237 @@
+//! 会话自动命名：新会话完成首轮问答后，在临时会话中请 Agent 概括一个简短标题，
+//! 写回会话存储并推送 `session-title-updated`，取代按首条消息截断得到的标题。
+use serde_json::json;
+use tauri::Manager;
+
+use crate::agents::oneshot::{run_oneshot_prompt, OneshotToolPolicy};
+use crate::app_lock::EmitUnlessLocked;
+use crate::state::AppState;
+use crate::storage::{
+    read_snapshot_from_path, storage_path, write_snapshot_to_path, StorageSnapshot,
+};
+
+const TITLE_TIMEOUT_SECS: u64 = 60;
+const MAX_TITLE_SOURCE_CHARS: usize = 2000;
+const MAX_TITLE_CHARS: usize = 30;
+const TRAILING_PUNCTUATION: [char; 4] = ['。', '.', '！', '!'];
+
+fn truncate_source(text: &str) -> String {
+    text.trim().chars().take(MAX_TITLE_SOURCE_CHARS).collect()
+}
+
+fn build_title_prompt(prompt: &str, answer: &str) -> String {
+    format!(
+        "请为下面这段对话起一个简短的标题，概括用户的主要诉求。\
+         不要调用工具，也不要修改任何文件；只输出标题本身，不超过 20 个字，不加引号和标点结尾。\n\n\
+         【用户】\n{}\n\n【助手】\n{}",
+        truncate_source(prompt),
+        truncate_source(answer)
+    )
+}
+
+/// 取回复中第一行有效文本，去掉“标题：”前缀、Markdown 标记与引号
+fn parse_title(reply: &str) -> Option<String> {
+    let line = reply
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("```"))?;
+    let line = line.trim_start_matches(['#', '*', '-', '>', ' ']);
+    let line = ["标题：", "标题:", "Title:", "title:"]
+        .iter()
+        .find_map(|prefix| line.strip_prefix(prefix))
+        .unwrap_or(line);
+    let title = line
+        .trim()
+        .trim_end_matches(TRAILING_PUNCTUATION)
+        .trim_matches(|c: char| "\"'`*“”‘’「」《》".contains(c))
+        .trim_end_matches(TRAILING_PUNCTUATION)
+        .trim();
+    if title.is_empty() {
+        return None;
+    }
+    let mut truncated: String = title.chars().take(MAX_TITLE_CHARS).collect();
+    if title.chars().count() > MAX_TITLE_CHARS {
+        truncated.push('…');
+    }
+    Some(truncated)
+}
+
+/// 待命名的会话：属于该 Agent、尚未生成过标题，且只有一条用户消息
+fn session_needing_title(
+    snapshot: &StorageSnapshot,
+    agent_id: &str,
+    acp_session_id: &str,
+) -> Option<String> {
+    let session = snapshot
+        .sessions_by_agent
+        .get(agent_id)?
+        .iter()
+        .find(|session| session.acp_session_id.as_deref() == Some(acp_session_id))?;
+    if session.title_generated {
+        return None;
+    }
+    let user_turns = snapshot
+        .messages_by_session
+        .get(&session.id)
+        .map(|messages| {
+            messages
+                .iter()
+                .filter(|message| message.role == "user")
+                .count()
+        })
+        .unwrap_or(0);
+    (user_turns <= 1).then(|| session.id.clone())
+}
+
+/// 首轮问答结束后在后台生成标题，不阻塞当前会话
+pub(crate) fn spawn_session_title(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    ws_url: &str,
+    workspace_path: &str,
+    acp_session_id: &str,
+    prompt: &str,
+    answer: &str,
+) {
+    let app_handle = app_handle.clone();
+    let agent_id = agent_id.to_string();
+    let ws_url = ws_url.to_string();
+    let workspace_path = workspace_path.to_string();
+    let acp_session_id = acp_session_id.to_string();
+    let title_prompt = build_title_prompt(prompt, answer);
+    tokio::spawn(async move {
+        let state = app_handle.state::<AppState>();
+        let candidate = async {
+            let _guard = state.storage_lock.lock().await;
+            let snapshot = read_snapshot_from_path(&storage_path(&app_handle)?).await?;
+            Ok::<_, String>(session_needing_title(&snapshot, &agent_id, &acp_session_id))
+        }
+        .await;
+        let Ok(Some(session_id)) = candidate else {
+            return;
+        };
+
+        let title = match run_oneshot_prompt(
+            &ws_url,
+            &workspace_path,
+            None,
+            &title_prompt,
+            OneshotToolPolicy::Reject,
+            TITLE_TIMEOUT_SECS,
+        )
+        .await
+        {
+            Ok(reply) => match parse_title(&reply) {
+                Some(title) => title,
+                None => return,
+            },
+            Err(e) => {
+                println!(
+                    "[session-title] Failed to title session {}: {}",
+                    session_id, e
+                );
+                return;
+            }
+        };
+
+        let renamed = async {
+            let _guard = state.storage_lock.lock().await;
+            let path = storage_path(&app_handle)?;
+            let mut snapshot = read_snapshot_from_path(&path).await?;
+            let Some(session) = snapshot
+                .sessions_by_agent
+                .values_mut()
+                .flatten()
+                .find(|session| session.id == session_id)
+            else {
+                return Ok(false);
+            };
+            session.title = title.clone();
+            session.title_generated = true;
+            write_snapshot_to_path(&path, &snapshot).await?;
+            Ok::<_, String>(true)
+        }
+        .await;
+        match renamed {
+            Ok(true) => {
+                let _ = app_handle.emit_unless_locked(
+                    "session-title-updated",
+                    json!({
+                        "agentId": agent_id,
+                        "sessionId": session_id,
+                        "acpSessionId": acp_session_id,
+                        "title": title,
+                    }),
+                );
+            }
+            Ok(false) => {}
+            Err(e) => println!(
+                "[session-title] Failed to store title for {}: {}",
+                session_id, e
+            ),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{StoredMessage, StoredSession};
+
+    #[test]
+    fn title_is_cleaned_and_truncated() {
+        assert_eq!(
+            parse_title("\n标题：「修复解析器空指针」。"),
+            Some("修复解析器空指针".to_string())
+        );
+        assert_eq!(
+            parse_title("**Refactor the tokenizer**"),
+            Some("Refactor the tokenizer".to_string())
+        );
+        assert_eq!(parse_title("```\n"), None);
+        let long = "很".repeat(40);
+        assert_eq!(
+            parse_title(&long).unwrap().chars().count(),
+            MAX_TITLE_CHARS + 1
+        );
+    }
+
+    #[test]
+    fn only_untitled_first_exchange_sessions_are_named() {
+        let user = StoredMessage {
+            role: "user".to_string(),
+            ..Default::default()
+        };
+        let mut snapshot = StorageSnapshot::default();
+        snapshot.sessions_by_agent.insert(
+            "a1".to_string(),
+            vec![
+                StoredSession {
+                    id: "s1".to_string(),
+                    acp_session_id: Some("acp-1".to_string()),
+                    ..Default::default()
+                },
+                StoredSession {
+                    id: "s2".to_string(),
+                    acp_session_id: Some("acp-2".to_string()),
+                    title_generated: true,
+                    ..Default::default()
+                },
+            ],
+        );
+        snapshot
+            .messages_by_session
+            .insert("s1".to_string(), vec![user.clone()]);
+        assert_eq!(
+            session_needing_title(&snapshot, "a1", "acp-1"),
+            Some("s1".to_string())
+        );
+        assert_eq!(session_needing_title(&snapshot, "a1", "acp-2"), None);
+        assert_eq!(session_needing_title(&snapshot, "a2", "acp-1"), None);
+
+        snapshot
+            .messages_by_session
+            .insert("s1".to_string(), vec![user.clone(), user]);
+        assert_eq!(session_needing_title(&snapshot, "a1", "acp-1"), None);
+    }
+}
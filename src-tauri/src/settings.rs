@@ -0,0 +1,570 @@
+//! 应用级设置（持久化到应用数据目录），启动时载入 AppState 供后端各模块读取
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, State};
+use tokio::fs;
+
+use crate::acp_trace::set_acp_trace_enabled;
+use crate::agents::autostart::{validate_auto_start_agents, AutoStartAgent};
+use crate::agents::fs_sandbox::validate_fs_allowed_dirs;
+use crate::agents::heartbeat::DEFAULT_KEEPALIVE_INTERVAL_SECS;
+use crate::agents::spawn_env::validate_agent_env;
+use crate::artifact_csp::{validate_artifact_csp, ArtifactCspSettings};
+use crate::capabilities::{merge_disabled_capabilities, CommandCapability};
+use crate::email_report::SmtpConfig;
+use crate::legal_hold::record_audit;
+use crate::memory_guard::{validate_memory_limits, MemoryLimits};
+use crate::models::PausedStreamMode;
+use crate::permissions::{PermissionDecision, DEFAULT_PERMISSION_TIMEOUT_SECS};
+use crate::postprocess::PostprocessHook;
+use crate::prompt_split::{set_prompt_split, validate_prompt_split, PromptSplitSettings};
+use crate::quiet_hours::{validate_quiet_hours, QuietHours};
+use crate::remote_storage::RemoteStorageConfig;
+use crate::reply_language::ReplyLanguage;
+use crate::retry::DEFAULT_PROMPT_MAX_RETRIES;
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag};
+use crate::team_sync::TeamSyncConfig;
+use crate::telemetry::{flush_telemetry, set_telemetry_enabled};
+use crate::updater::UpdateChannel;
+
+/// 系统消息（停止原因、计划、思考等）的前缀风格
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageStyle {
+    #[default]
+    Emoji,
+    Plain,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SystemMarker {
+    Success,
+    Warning,
+    Cancelled,
+    Refusal,
+    Thought,
+    Plan,
+    ModelSwitch,
+}
+
+impl MessageStyle {
+    fn marker(self, marker: SystemMarker) -> &'static str {
+        match (self, marker) {
+            (Self::Emoji, SystemMarker::Success) => "✅",
+            (Self::Emoji, SystemMarker::Warning) => "⚠️",
+            (Self::Emoji, SystemMarker::Cancelled) => "🚫",
+            (Self::Emoji, SystemMarker::Refusal) => "⛔",
+            (Self::Emoji, SystemMarker::Thought) => "💭",
+            (Self::Emoji, SystemMarker::Plan) => "📋",
+            (Self::Emoji, SystemMarker::ModelSwitch) => "🔀",
+            (Self::Plain, SystemMarker::Success) => "[完成]",
+            (Self::Plain, SystemMarker::Warning) => "[警告]",
+            (Self::Plain, SystemMarker::Cancelled) => "[取消]",
+            (Self::Plain, SystemMarker::Refusal) => "[拒绝]",
+            (Self::Plain, SystemMarker::Thought) => "[思考]",
+            (Self::Plain, SystemMarker::Plan) => "[计划]",
+            (Self::Plain, SystemMarker::ModelSwitch) => "[模型]",
+        }
+    }
+
+    /// 按当前风格为系统消息加前缀
+    pub(crate) fn decorate(self, marker: SystemMarker, text: &str) -> String {
+        format!("{} {}", self.marker(marker), text)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AppSettings {
+    #[serde(default)]
+    pub message_style: MessageStyle,
+    /// 任务正常结束后用另一次请求复核回答
+    #[serde(default)]
+    pub verify_answers: bool,
+    /// 复核使用的模型；为空时使用 iFlow 默认模型
+    #[serde(default)]
+    pub verification_model: Option<String>,
+    /// prompt 遇到瞬时错误时的最大自动重试次数；为空时使用默认值
+    #[serde(default)]
+    pub prompt_max_retries: Option<u32>,
+    /// 任务结束后依次处理助手回复的外部命令
+    #[serde(default)]
+    pub postprocess_hooks: Vec<PostprocessHook>,
+    /// 禁用的命令能力分组，由后端在命令分发前拦截
+    #[serde(default)]
+    pub disabled_capabilities: Vec<CommandCapability>,
+    /// 应用锁空闲自动上锁的秒数；为空时不自动上锁
+    #[serde(default)]
+    pub app_lock_timeout_secs: Option<u64>,
+    /// 团队同步仓库配置；为空时不启用
+    #[serde(default)]
+    pub team_sync: Option<TeamSyncConfig>,
+    /// 会话加密分享与产物发布使用的远端存储（S3 兼容或 WebDAV）
+    #[serde(default)]
+    pub remote_storage: Option<RemoteStorageConfig>,
+    /// 产物发布的对象键前缀，按工作区路径配置；未配置时为 `artifacts/<工作区目录名>`
+    #[serde(default)]
+    pub artifact_prefixes: HashMap<String, String>,
+    /// 邮件报告使用的 SMTP 服务器；密码保存在密钥存储
+    #[serde(default)]
+    pub smtp: Option<SmtpConfig>,
+    /// 免打扰时段：期间不推送任务结束通知
+    #[serde(default)]
+    pub quiet_hours: QuietHours,
+    /// 缓冲内容的内存上限与工具输出溢出阈值
+    #[serde(default)]
+    pub memory_limits: MemoryLimits,
+    /// 在内存中记录最近的 ACP 报文，供问题报告附带（默认关闭）
+    #[serde(default)]
+    pub acp_trace: bool,
+    /// 检查更新使用的发布通道
+    #[serde(default)]
+    pub update_channel: UpdateChannel,
+    /// 按工作区路径配置的回复语言；发送时附加语言要求，回复语言不一致时提示
+    #[serde(default)]
+    pub reply_languages: HashMap<String, ReplyLanguage>,
+    /// 超长 prompt 拆分为指令与上下文块（默认关闭）
+    #[serde(default)]
+    pub prompt_split: PromptSplitSettings,
+    /// Agent 连接的心跳间隔（秒）；为空时使用默认值，0 表示关闭心跳
+    #[serde(default)]
+    pub keepalive_interval_secs: Option<u64>,
+    /// 等待用户应答权限请求的秒数；为空时使用默认值
+    #[serde(default)]
+    pub permission_timeout_secs: Option<u64>,
+    /// 权限请求超时未应答时的默认决策；为空时拒绝
+    #[serde(default)]
+    pub permission_timeout_decision: Option<PermissionDecision>,
+    /// 固定工作区的默认 Agent，应用启动时由后端自动连接
+    #[serde(default)]
+    pub auto_start_agents: Vec<AutoStartAgent>,
+    /// 工作区之外允许 Agent 通过 ACP fs 读写的目录（绝对路径）
+    #[serde(default)]
+    pub fs_allowed_dirs: Vec<String>,
+    /// 合规保全：禁用删除类命令并记录删除尝试；前端只能开启
+    #[serde(default)]
+    pub legal_hold: bool,
+    /// 匿名使用统计总开关，关闭时不采集也不上传
+    #[serde(default)]
+    pub telemetry_enabled: bool,
+    /// 统计上报地址（HTTPS）；为空时只在本地聚合
+    #[serde(default)]
+    pub telemetry_endpoint: Option<String>,
+    /// 启动 Agent 时注入的环境变量，取值支持 `${env:VAR}` 与 `${secret:NAME}`
+    #[serde(default)]
+    pub agent_env: HashMap<String, String>,
+    /// 按工作区路径配置的 Agent 环境变量，同名时覆盖 `agent_env`
+    #[serde(default)]
+    pub workspace_agent_env: HashMap<String, HashMap<String, String>>,
+    /// Agent 暂停期间的流式更新缓存还是丢弃
+    #[serde(default)]
+    pub paused_stream_mode: PausedStreamMode,
+    /// 预览前净化 HTML Artifact（去掉脚本与外部网络请求），开启后不再提供原始文件地址
+    #[serde(default)]
+    pub sanitize_html_artifacts: bool,
+    /// HTML Artifact 预览的内容安全策略，默认拒绝外部来源
+    #[serde(default)]
+    pub artifact_csp: ArtifactCspSettings,
+    /// 按工作区路径整体替换 `artifact_csp`，供需要加载 CDN 资源的项目使用
+    #[serde(default)]
+    pub workspace_artifact_csp: HashMap<String, ArtifactCspSettings>,
+}
+
+/// 设置变更历史中保留的版本数
+const MAX_SETTINGS_HISTORY: usize = 20;
+
+/// 一次被替换前的设置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsVersion {
+    pub version: u64,
+    /// 被替换的时间
+    pub replaced_at: String,
+    pub settings: AppSettings,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct SettingsHistory {
+    #[serde(default)]
+    next_version: u64,
+    #[serde(default)]
+    versions: Vec<SettingsVersion>,
+}
+
+impl SettingsHistory {
+    /// 记录即将被替换的设置，超出上限时丢弃最旧的版本
+    fn push(&mut self, settings: AppSettings, replaced_at: String) {
+        self.next_version += 1;
+        self.versions.push(SettingsVersion {
+            version: self.next_version,
+            replaced_at,
+            settings,
+        });
+        let excess = self.versions.len().saturating_sub(MAX_SETTINGS_HISTORY);
+        self.versions.drain(..excess);
+    }
+
+    fn find(&self, version: u64) -> Option<&SettingsVersion> {
+        self.versions.iter().find(|entry| entry.version == version)
+    }
+}
+
+fn settings_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-settings-{}.json", storage_env_tag())))
+}
+
+fn settings_history_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(
+        app_data_dir(app_handle)?
+            .join(format!("iflow-settings-history-{}.json", storage_env_tag())),
+    )
+}
+
+/// 先写临时文件再重命名，写入中途崩溃不会留下半截的设置文件
+async fn write_atomically(path: &Path, payload: Vec<u8>) -> Result<(), std::io::Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, payload).await?;
+    fs::rename(&temp, path).await
+}
+
+async fn read_settings_from_path(path: &Path) -> Result<AppSettings, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(AppSettings::default());
+            }
+            serde_json::from_str(&content).map_err(|e| format!("Failed to parse settings: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(AppSettings::default()),
+        Err(err) => Err(format!("Failed to read settings: {}", err)),
+    }
+}
+
+async fn write_settings_to_path(path: &Path, settings: &AppSettings) -> Result<(), String> {
+    let payload = serde_json::to_vec_pretty(settings)
+        .map_err(|e| format!("Failed to encode settings: {}", e))?;
+    write_atomically(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write settings: {}", e))
+}
+
+async fn read_history_from_path(path: &Path) -> Result<SettingsHistory, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(SettingsHistory::default());
+            }
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse settings history: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(SettingsHistory::default()),
+        Err(err) => Err(format!("Failed to read settings history: {}", err)),
+    }
+}
+
+async fn write_history_to_path(path: &Path, history: &SettingsHistory) -> Result<(), String> {
+    let payload = serde_json::to_vec(history)
+        .map_err(|e| format!("Failed to encode settings history: {}", e))?;
+    write_atomically(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write settings history: {}", e))
+}
+
+/// 禁用分组只增不减、法律保留一经开启不可经前端关闭；保存设置与切换档案都按此合并
+fn carry_restrictions(current: &AppSettings, settings: &mut AppSettings) {
+    settings.disabled_capabilities = merge_disabled_capabilities(
+        &current.disabled_capabilities,
+        &settings.disabled_capabilities,
+    );
+    settings.legal_hold |= current.legal_hold;
+}
+
+/// 启动时从磁盘载入设置；读取失败时保留默认值。
+/// 切换档案时同样载入新档案的设置，沿用已生效的限制并写回新档案
+pub(crate) async fn init_settings(app_handle: &tauri::AppHandle) {
+    let path = match settings_path(app_handle) {
+        Ok(path) => path,
+        Err(e) => {
+            println!("[settings] Failed to load settings: {}", e);
+            return;
+        }
+    };
+    match read_settings_from_path(&path).await {
+        Ok(mut settings) => {
+            let state = app_handle.state::<AppState>();
+            let loaded = settings.clone();
+            carry_restrictions(&state.settings.read().await, &mut settings);
+            if settings != loaded {
+                if let Err(e) = write_settings_to_path(&path, &settings).await {
+                    println!("[settings] {}", e);
+                }
+            }
+            state
+                .capability_policy
+                .apply(&settings.disabled_capabilities);
+            state.legal_hold.apply(settings.legal_hold);
+            set_acp_trace_enabled(settings.acp_trace);
+            set_prompt_split(settings.prompt_split);
+            set_telemetry_enabled(settings.telemetry_enabled);
+            *state.settings.write().await = settings;
+        }
+        Err(e) => println!("[settings] Failed to load settings: {}", e),
+    }
+}
+
+pub(crate) async fn message_style(app_handle: &tauri::AppHandle) -> MessageStyle {
+    app_handle
+        .state::<AppState>()
+        .settings
+        .read()
+        .await
+        .message_style
+}
+
+pub(crate) async fn prompt_max_retries(app_handle: &tauri::AppHandle) -> u32 {
+    app_handle
+        .state::<AppState>()
+        .settings
+        .read()
+        .await
+        .prompt_max_retries
+        .unwrap_or(DEFAULT_PROMPT_MAX_RETRIES)
+}
+
+pub(crate) async fn keepalive_interval(app_handle: &tauri::AppHandle) -> Option<Duration> {
+    let secs = app_handle
+        .state::<AppState>()
+        .settings
+        .read()
+        .await
+        .keepalive_interval_secs
+        .unwrap_or(DEFAULT_KEEPALIVE_INTERVAL_SECS);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Agent 暂停期间的流式更新处理方式
+pub(crate) async fn paused_stream_mode(app_handle: &tauri::AppHandle) -> PausedStreamMode {
+    app_handle
+        .state::<AppState>()
+        .settings
+        .read()
+        .await
+        .paused_stream_mode
+}
+
+/// 权限请求的等待时长与超时后的默认决策
+pub(crate) async fn permission_timeout(
+    app_handle: &tauri::AppHandle,
+) -> (Duration, PermissionDecision) {
+    let state = app_handle.state::<AppState>();
+    let settings = state.settings.read().await;
+    (
+        Duration::from_secs(
+            settings
+                .permission_timeout_secs
+                .unwrap_or(DEFAULT_PERMISSION_TIMEOUT_SECS),
+        ),
+        settings
+            .permission_timeout_decision
+            .unwrap_or(PermissionDecision::Deny),
+    )
+}
+
+/// 工作区之外放行给 Agent 文件读写的目录
+pub(crate) async fn fs_allowed_dirs(app_handle: &tauri::AppHandle) -> Vec<String> {
+    app_handle
+        .state::<AppState>()
+        .settings
+        .read()
+        .await
+        .fs_allowed_dirs
+        .clone()
+}
+
+#[tauri::command]
+pub async fn get_app_settings(state: State<'_, AppState>) -> Result<AppSettings, String> {
+    Ok(state.settings.read().await.clone())
+}
+
+#[tauri::command]
+pub async fn update_app_settings(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    settings: AppSettings,
+) -> Result<AppSettings, String> {
+    apply_settings(&app_handle, &state, settings).await
+}
+
+/// 最近被替换的设置版本（从旧到新）
+#[tauri::command]
+pub async fn list_settings_history(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<SettingsVersion>, String> {
+    let _guard = state.settings.read().await;
+    Ok(read_history_from_path(&settings_history_path(&app_handle)?)
+        .await?
+        .versions)
+}
+
+/// 回滚到历史中的某个版本；回滚本身同样记入历史，可以再次撤销
+#[tauri::command]
+pub async fn revert_settings(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    version: u64,
+) -> Result<AppSettings, String> {
+    let history = read_history_from_path(&settings_history_path(&app_handle)?).await?;
+    let settings = history
+        .find(version)
+        .map(|entry| entry.settings.clone())
+        .ok_or_else(|| format!("Settings version {} not found", version))?;
+    apply_settings(&app_handle, &state, settings).await
+}
+
+/// 校验并保存设置，被替换的设置记入变更历史
+async fn apply_settings(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+    mut settings: AppSettings,
+) -> Result<AppSettings, String> {
+    validate_quiet_hours(&settings.quiet_hours)?;
+    validate_memory_limits(&settings.memory_limits)?;
+    validate_prompt_split(&settings.prompt_split)?;
+    validate_auto_start_agents(&settings.auto_start_agents)?;
+    validate_fs_allowed_dirs(&settings.fs_allowed_dirs)?;
+    validate_agent_env(&settings.agent_env, &settings.workspace_agent_env)?;
+    validate_artifact_csp(&settings.artifact_csp, &settings.workspace_artifact_csp)?;
+    if let Some(endpoint) = settings.telemetry_endpoint.as_deref() {
+        if !endpoint.trim().is_empty() && !endpoint.trim().starts_with("https://") {
+            return Err("Telemetry endpoint must use https".to_string());
+        }
+    }
+    let mut current = state.settings.write().await;
+    carry_restrictions(&current, &mut settings);
+    if settings == *current {
+        return Ok(current.clone());
+    }
+    write_settings_to_path(&settings_path(app_handle)?, &settings).await?;
+    let history_path = settings_history_path(app_handle)?;
+    let history_result = match read_history_from_path(&history_path).await {
+        Ok(mut history) => {
+            history.push(current.clone(), chrono::Utc::now().to_rfc3339());
+            write_history_to_path(&history_path, &history).await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = history_result {
+        println!("[settings] {}", e);
+    }
+    state
+        .capability_policy
+        .apply(&settings.disabled_capabilities);
+    if settings.legal_hold && !current.legal_hold {
+        state.legal_hold.apply(true);
+        record_audit(app_handle, "enable_legal_hold", false, None).await;
+    }
+    state.app_lock.set_timeout(settings.app_lock_timeout_secs);
+    set_acp_trace_enabled(settings.acp_trace);
+    set_prompt_split(settings.prompt_split);
+    set_telemetry_enabled(settings.telemetry_enabled);
+    if let Err(e) = flush_telemetry(app_handle).await {
+        println!("[settings] {}", e);
+    }
+    *current = settings;
+    Ok(current.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_style_replaces_emoji_prefixes() {
+        assert_eq!(
+            MessageStyle::Emoji.decorate(SystemMarker::Plan, "执行计划:"),
+            "📋 执行计划:"
+        );
+        assert_eq!(
+            MessageStyle::Plain.decorate(SystemMarker::Plan, "执行计划:"),
+            "[计划] 执行计划:"
+        );
+        assert!(!MessageStyle::Plain
+            .decorate(SystemMarker::Thought, "x")
+            .contains('💭'));
+    }
+
+    #[tokio::test]
+    async fn settings_roundtrip_and_defaults() {
+        let path =
+            std::env::temp_dir().join(format!("iflow-settings-{}.json", uuid::Uuid::new_v4()));
+        assert_eq!(
+            read_settings_from_path(&path).await.expect("read missing"),
+            AppSettings::default()
+        );
+
+        let settings = AppSettings {
+            message_style: MessageStyle::Plain,
+            verify_answers: true,
+            verification_model: Some("glm-4.7".to_string()),
+            prompt_max_retries: Some(0),
+            ..Default::default()
+        };
+        write_settings_to_path(&path, &settings)
+            .await
+            .expect("write");
+        assert_eq!(
+            read_settings_from_path(&path).await.expect("read"),
+            settings
+        );
+        assert!(!path.with_extension("json.tmp").exists());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn restrictions_survive_settings_from_another_profile() {
+        let current = AppSettings {
+            disabled_capabilities: vec![CommandCapability::Process],
+            legal_hold: true,
+            ..Default::default()
+        };
+        let mut settings = AppSettings {
+            disabled_capabilities: vec![CommandCapability::Network],
+            ..Default::default()
+        };
+        carry_restrictions(&current, &mut settings);
+        assert_eq!(
+            settings.disabled_capabilities,
+            vec![CommandCapability::Process, CommandCapability::Network]
+        );
+        assert!(settings.legal_hold);
+    }
+
+    #[test]
+    fn settings_history_keeps_latest_versions() {
+        let mut history = SettingsHistory::default();
+        for retries in 0..(MAX_SETTINGS_HISTORY as u32 + 5) {
+            let settings = AppSettings {
+                prompt_max_retries: Some(retries),
+                ..Default::default()
+            };
+            history.push(settings, "2026-01-01T00:00:00Z".to_string());
+        }
+
+        assert_eq!(history.versions.len(), MAX_SETTINGS_HISTORY);
+        assert!(history.find(5).is_none());
+        let oldest = history.find(6).expect("oldest kept version");
+        assert_eq!(oldest.settings.prompt_max_retries, Some(5));
+        assert_eq!(history.versions.last().map(|entry| entry.version), Some(25));
+    }
+}
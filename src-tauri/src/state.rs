@@ -1,24 +1,70 @@
 use tokio::process::Child;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex, RwLock};
 
+use crate::agents::autostart::AutoStartProgress;
+use crate::app_lock::AppLockState;
+use crate::capabilities::CapabilityPolicy;
+use crate::history_watch::HistoryWatchers;
+use crate::jobs::JobRegistry;
+use crate::legal_hold::LegalHold;
 use crate::manager::AgentManager;
-use crate::models::{AgentInfo, MessageSender};
+use crate::models::{AgentInfo, MessageSender, PermissionMode};
+use crate::notify_channels::NotificationRateLimiter;
+use crate::plugins::LoadedPlugin;
+use crate::profiles::DEFAULT_PROFILE_ID;
+use crate::router::TaskFinished;
+use crate::settings::AppSettings;
 
 // Agent 实例
 #[allow(dead_code)]
 pub struct AgentInstance {
     pub info: AgentInfo,
     pub process: Option<Child>,
+    /// 应用重启后接管的进程 PID（见 `restore_agents`）
+    pub adopted_pid: Option<u32>,
     pub port: u16,
     pub iflow_path: String,
     pub model: Option<String>,
+    pub permission_mode: PermissionMode,
     pub(crate) message_sender: Option<MessageSender>,
+    /// 监听任务当前绑定的 ACP 会话
+    pub(crate) acp_session_id: Option<String>,
 }
 
 // 应用状态
 pub struct AppState {
     pub agent_manager: AgentManager,
     pub storage_lock: Mutex<()>,
+    pub secrets_lock: Mutex<()>,
+    pub permissions_lock: Mutex<()>,
+    pub tasks_lock: Mutex<()>,
+    pub prompt_rules_lock: Mutex<()>,
+    pub settings: RwLock<AppSettings>,
+    pub(crate) plugins: RwLock<Vec<LoadedPlugin>>,
+    pub history_lock: Mutex<()>,
+    pub(crate) history_watchers: HistoryWatchers,
+    pub(crate) jobs: JobRegistry,
+    pub jobs_lock: Mutex<()>,
+    pub(crate) capability_policy: CapabilityPolicy,
+    pub(crate) app_lock: AppLockState,
+    pub(crate) legal_hold: LegalHold,
+    pub audit_lock: Mutex<()>,
+    /// 当前档案 ID；数据目录解析在同步上下文中进行，因此用 std 锁
+    pub(crate) active_profile: std::sync::RwLock<String>,
+    pub team_sync_lock: Mutex<()>,
+    pub notifications_lock: Mutex<()>,
+    pub scoreboard_lock: Mutex<()>,
+    pub routines_lock: Mutex<()>,
+    pub annotations_lock: Mutex<()>,
+    pub agent_registry_lock: Mutex<()>,
+    pub frozen_sessions_lock: Mutex<()>,
+    pub last_sessions_lock: Mutex<()>,
+    /// 串行化启动时的 Agent 接管与自动连接
+    pub agent_startup_lock: Mutex<()>,
+    pub(crate) auto_start_progress: RwLock<Vec<AutoStartProgress>>,
+    pub(crate) notification_limiter: NotificationRateLimiter,
+    /// task-finish 的后端副本，应用上锁时照常投递
+    pub(crate) task_finished: broadcast::Sender<TaskFinished>,
 }
 
 impl Default for AppState {
@@ -26,6 +72,33 @@ impl Default for AppState {
         Self {
             agent_manager: AgentManager::default(),
             storage_lock: Mutex::new(()),
+            secrets_lock: Mutex::new(()),
+            permissions_lock: Mutex::new(()),
+            tasks_lock: Mutex::new(()),
+            prompt_rules_lock: Mutex::new(()),
+            settings: RwLock::new(AppSettings::default()),
+            plugins: RwLock::new(Vec::new()),
+            history_lock: Mutex::new(()),
+            history_watchers: HistoryWatchers::default(),
+            jobs: JobRegistry::default(),
+            jobs_lock: Mutex::new(()),
+            capability_policy: CapabilityPolicy::default(),
+            app_lock: AppLockState::default(),
+            legal_hold: LegalHold::default(),
+            audit_lock: Mutex::new(()),
+            active_profile: std::sync::RwLock::new(DEFAULT_PROFILE_ID.to_string()),
+            team_sync_lock: Mutex::new(()),
+            notifications_lock: Mutex::new(()),
+            scoreboard_lock: Mutex::new(()),
+            routines_lock: Mutex::new(()),
+            annotations_lock: Mutex::new(()),
+            agent_registry_lock: Mutex::new(()),
+            frozen_sessions_lock: Mutex::new(()),
+            last_sessions_lock: Mutex::new(()),
+            agent_startup_lock: Mutex::new(()),
+            auto_start_progress: RwLock::new(Vec::new()),
+            notification_limiter: NotificationRateLimiter::default(),
+            task_finished: broadcast::channel(64).0,
         }
     }
 }
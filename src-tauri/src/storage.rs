@@ -6,6 +6,10 @@ use serde::{Deserialize, Serialize};
 use tauri::{Manager, State};
 use tokio::fs;
 
+use crate::app_lock::EmitUnlessLocked;
+use crate::history::normalize_workspace_path;
+use crate::legal_hold::check_snapshot_retained;
+use crate::profiles::profile_data_dir;
 use crate::state::AppState;
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -22,6 +26,15 @@ pub struct StoredSession {
     pub source: Option<String>,
     #[serde(default)]
     pub message_count_hint: Option<usize>,
+    /// 由会话交接创建时记录来源会话 ID
+    #[serde(default)]
+    pub handoff_from: Option<String>,
+    /// 从本会话交接出去的目标会话 ID
+    #[serde(default)]
+    pub handoff_to: Vec<String>,
+    /// 标题已由 Agent 生成（或明确指定），不再按消息内容自动改写
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub title_generated: bool,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -32,6 +45,26 @@ pub struct StoredMessage {
     pub content: String,
     pub timestamp: String,
     pub agent_id: Option<String>,
+    /// 应用崩溃前未完成的流式回复（由预写日志恢复）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool,
+    /// 引用的附件（内容寻址存储中的哈希）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<String>,
+    /// 模型切换标记（`system` 消息），此后的回答出自 `to` 模型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_switch: Option<ModelSwitch>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelSwitch {
+    /// 为空表示 Agent 默认模型
+    pub from: Option<String>,
+    pub to: String,
+    /// 通过重启 Agent 切换（ACP 会话随之重建）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub restarted: bool,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -43,7 +76,20 @@ pub struct StorageSnapshot {
     pub messages_by_session: HashMap<String, Vec<StoredMessage>>,
 }
 
-fn storage_env_tag() -> &'static str {
+/// 后端直接写入存储的会话与消息。前端会整体保存内存中的状态，因此写入后需推送
+/// `sessions-stored` 事件，由前端合并进内存并保存，否则下一次保存会覆盖这些记录
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredSessionsUpdate {
+    pub sessions: Vec<StoredSession>,
+    pub messages_by_session: HashMap<String, Vec<StoredMessage>>,
+}
+
+pub(crate) fn emit_sessions_stored(app_handle: &tauri::AppHandle, update: &StoredSessionsUpdate) {
+    let _ = app_handle.emit_unless_locked("sessions-stored", update);
+}
+
+pub(crate) fn storage_env_tag() -> &'static str {
     if cfg!(test) {
         "test"
     } else if cfg!(debug_assertions) {
@@ -57,12 +103,27 @@ fn storage_file_name() -> String {
     format!("iflow-session-store-{}.json", storage_env_tag())
 }
 
-fn storage_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let base_dir = app_handle
+/// 应用数据目录根，仅档案注册表等跨档案数据使用
+pub(crate) fn base_app_data_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app_handle
         .path()
         .app_data_dir()
-        .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
-    Ok(base_dir.join(storage_file_name()))
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))
+}
+
+/// 当前档案的数据目录
+pub(crate) fn app_data_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let base = base_app_data_dir(app_handle)?;
+    let state = app_handle.state::<AppState>();
+    let active = state
+        .active_profile
+        .read()
+        .map_err(|_| "Failed to read active profile".to_string())?;
+    Ok(profile_data_dir(&base, &active))
+}
+
+pub(crate) fn storage_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(storage_file_name()))
 }
 
 pub async fn read_snapshot_from_path(path: &Path) -> Result<StorageSnapshot, String> {
@@ -93,6 +154,108 @@ pub async fn write_snapshot_to_path(path: &Path, snapshot: &StorageSnapshot) ->
     Ok(())
 }
 
+/// Agent 类型 -> 工作区 -> 最近使用的 ACP 会话；应用重启后据此恢复会话
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct LastAcpSessions {
+    #[serde(default)]
+    by_agent_type: HashMap<String, HashMap<String, String>>,
+}
+
+impl LastAcpSessions {
+    fn get(&self, agent_type: &str, workspace_path: &str) -> Option<String> {
+        self.by_agent_type
+            .get(agent_type)?
+            .get(&normalize_workspace_path(workspace_path))
+            .cloned()
+    }
+
+    /// 记录会话，返回是否有变化
+    fn set(&mut self, agent_type: &str, workspace_path: &str, session_id: &str) -> bool {
+        let previous = self
+            .by_agent_type
+            .entry(agent_type.to_string())
+            .or_default()
+            .insert(
+                normalize_workspace_path(workspace_path),
+                session_id.to_string(),
+            );
+        previous.as_deref() != Some(session_id)
+    }
+}
+
+fn last_acp_sessions_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-last-sessions-{}.json", storage_env_tag())))
+}
+
+async fn read_last_sessions_from_path(path: &Path) -> Result<LastAcpSessions, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(LastAcpSessions::default());
+            }
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse last sessions: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(LastAcpSessions::default()),
+        Err(err) => Err(format!("Failed to read last sessions: {}", err)),
+    }
+}
+
+async fn write_last_sessions_to_path(
+    path: &Path,
+    sessions: &LastAcpSessions,
+) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create last sessions dir: {}", e))?;
+    }
+    let payload = serde_json::to_vec(sessions)
+        .map_err(|e| format!("Failed to encode last sessions: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write last sessions: {}", e))
+}
+
+/// 读取工作区上次使用的 ACP 会话；读取失败时视为没有记录
+pub(crate) async fn load_last_acp_session(
+    app_handle: &tauri::AppHandle,
+    agent_type: &str,
+    workspace_path: &str,
+) -> Option<String> {
+    let state = app_handle.state::<AppState>();
+    let _guard = state.last_sessions_lock.lock().await;
+    let result = match last_acp_sessions_path(app_handle) {
+        Ok(path) => read_last_sessions_from_path(&path).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(sessions) => sessions.get(agent_type, workspace_path),
+        Err(e) => {
+            println!("[storage] {}", e);
+            None
+        }
+    }
+}
+
+/// 记录工作区当前使用的 ACP 会话
+pub(crate) async fn remember_last_acp_session(
+    app_handle: &tauri::AppHandle,
+    agent_type: &str,
+    workspace_path: &str,
+    session_id: &str,
+) -> Result<(), String> {
+    let state = app_handle.state::<AppState>();
+    let _guard = state.last_sessions_lock.lock().await;
+    let path = last_acp_sessions_path(app_handle)?;
+    let mut sessions = read_last_sessions_from_path(&path).await?;
+    if sessions.set(agent_type, workspace_path, session_id) {
+        write_last_sessions_to_path(&path, &sessions).await?;
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn load_storage_snapshot(
     app_handle: tauri::AppHandle,
@@ -111,6 +274,10 @@ pub async fn save_storage_snapshot(
 ) -> Result<(), String> {
     let _guard = state.storage_lock.lock().await;
     let path = storage_path(&app_handle)?;
+    if state.legal_hold.is_active() {
+        let current = read_snapshot_from_path(&path).await?;
+        check_snapshot_retained(&app_handle, &current, &snapshot).await?;
+    }
     write_snapshot_to_path(&path, &snapshot).await
 }
 
@@ -125,6 +292,27 @@ mod tests {
             .join(file_name)
     }
 
+    #[tokio::test]
+    async fn last_sessions_are_keyed_by_agent_type_and_workspace() {
+        let path = temp_path("last-sessions.json");
+        let mut sessions = read_last_sessions_from_path(&path).await.unwrap();
+        assert!(sessions.set("iflow", "/work/app/", "session-1"));
+        assert!(!sessions.set("iflow", "/work/app", "session-1"));
+        assert!(sessions.set("remote", "/work/app", "session-2"));
+        write_last_sessions_to_path(&path, &sessions).await.unwrap();
+
+        let loaded = read_last_sessions_from_path(&path).await.unwrap();
+        assert_eq!(
+            loaded.get("iflow", "/work/app").as_deref(),
+            Some("session-1")
+        );
+        assert_eq!(
+            loaded.get("remote", "/work/app/").as_deref(),
+            Some("session-2")
+        );
+        assert_eq!(loaded.get("iflow", "/work/other"), None);
+    }
+
     #[tokio::test]
     async fn read_missing_snapshot_returns_default() {
         let path = temp_path("missing.json");
@@ -148,6 +336,7 @@ mod tests {
                 acp_session_id: Some("session-1".to_string()),
                 source: Some("local".to_string()),
                 message_count_hint: Some(1),
+                ..Default::default()
             }],
         );
         snapshot.messages_by_session.insert(
@@ -158,6 +347,9 @@ mod tests {
                 content: "Hello".to_string(),
                 timestamp: "2024-01-01T00:00:00.000Z".to_string(),
                 agent_id: Some("agent-a".to_string()),
+                interrupted: false,
+                attachments: Vec::new(),
+                model_switch: None,
             }],
         );
 
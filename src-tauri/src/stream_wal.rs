@@ -0,0 +1,369 @@
+//! 流式回复的预写日志：助手正文分片先写入内存缓冲，按固定间隔追加到每轮回复一个的 WAL 文件，
+//! 回复正常结束后删除。应用崩溃后残留的 WAL 会在下次启动时恢复为「已中断」的助手消息写入会话存储，
+//! 并通过 `message-recovered` 事件重放给前端。
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::State;
+use tokio::io::AsyncWriteExt;
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::state::AppState;
+use crate::storage::{
+    app_data_dir, read_snapshot_from_path, storage_env_tag, storage_path, write_snapshot_to_path,
+    StorageSnapshot, StoredMessage,
+};
+
+/// 缓冲分片刷入 WAL 的间隔
+const WAL_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const WAL_EXTENSION: &str = "wal";
+
+// 正在写入的 WAL 文件，恢复时跳过（Agent 可能在前端调用恢复前已重连并开始输出）
+static ACTIVE_WAL_FILES: Lazy<Mutex<HashSet<PathBuf>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct WalHeader {
+    agent_id: String,
+    /// ACP 会话 ID
+    session_id: String,
+    started_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveredMessage {
+    pub agent_id: String,
+    /// 本地会话 ID；找不到对应会话时为空，消息未写入存储
+    pub session_id: Option<String>,
+    pub acp_session_id: String,
+    pub message: StoredMessage,
+}
+
+fn wal_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("stream-wal-{}", storage_env_tag())))
+}
+
+fn lock_active_files() -> std::sync::MutexGuard<'static, HashSet<PathBuf>> {
+    ACTIVE_WAL_FILES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// 解析 WAL：首行为头部，其余每行是一个 JSON 字符串分片；崩溃时写了一半的末行直接忽略
+fn parse_wal(raw: &str) -> Option<(WalHeader, String)> {
+    let mut lines = raw.lines();
+    let header = serde_json::from_str::<WalHeader>(lines.next()?).ok()?;
+    let content = lines
+        .filter_map(|line| serde_json::from_str::<String>(line).ok())
+        .collect::<String>();
+    Some((header, content))
+}
+
+/// 单个监听任务的 WAL 写入器；每轮回复对应一个文件
+pub(crate) struct StreamWal {
+    agent_id: String,
+    path: Option<PathBuf>,
+    session_id: Option<String>,
+    buffer: String,
+    last_flush: Instant,
+}
+
+impl StreamWal {
+    pub(crate) fn new(agent_id: &str) -> Self {
+        Self {
+            agent_id: agent_id.to_string(),
+            path: None,
+            session_id: None,
+            buffer: String::new(),
+            last_flush: Instant::now(),
+        }
+    }
+
+    /// 记录一个分片，距上次刷盘超过间隔时顺带刷盘
+    pub(crate) async fn record_chunk(
+        &mut self,
+        app_handle: &tauri::AppHandle,
+        session_id: Option<&str>,
+        chunk: &str,
+    ) {
+        let Some(session_id) = session_id else {
+            return;
+        };
+        if self.session_id.as_deref() != Some(session_id) {
+            self.finish().await;
+            if let Err(e) = self.open(app_handle, session_id).await {
+                println!("[stream-wal] Failed to open WAL: {}", e);
+                return;
+            }
+        }
+        self.buffer.push_str(chunk);
+        if self.last_flush.elapsed() >= WAL_FLUSH_INTERVAL {
+            self.flush().await;
+        }
+    }
+
+    async fn open(
+        &mut self,
+        app_handle: &tauri::AppHandle,
+        session_id: &str,
+    ) -> Result<(), String> {
+        let dir = wal_dir(app_handle)?;
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(|e| format!("Failed to create WAL dir: {}", e))?;
+        let path = dir.join(format!("{}.{}", uuid::Uuid::new_v4(), WAL_EXTENSION));
+        let header = serde_json::to_string(&WalHeader {
+            agent_id: self.agent_id.clone(),
+            session_id: session_id.to_string(),
+            started_at: chrono::Utc::now().to_rfc3339(),
+        })
+        .map_err(|e| format!("Failed to encode WAL header: {}", e))?;
+        tokio::fs::write(&path, format!("{}\n", header))
+            .await
+            .map_err(|e| format!("Failed to write WAL {}: {}", path.display(), e))?;
+
+        lock_active_files().insert(path.clone());
+        self.path = Some(path);
+        self.session_id = Some(session_id.to_string());
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+
+    /// 把缓冲的分片追加到 WAL 并落盘
+    pub(crate) async fn flush(&mut self) {
+        self.last_flush = Instant::now();
+        let Some(path) = self.path.as_ref() else {
+            return;
+        };
+        if self.buffer.is_empty() {
+            return;
+        }
+        let line = match serde_json::to_string(&self.buffer) {
+            Ok(line) => line,
+            Err(e) => {
+                println!("[stream-wal] Failed to encode chunk: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = append_line(path, &line).await {
+            println!(
+                "[stream-wal] Failed to append WAL {}: {}",
+                path.display(),
+                e
+            );
+            return;
+        }
+        self.buffer.clear();
+    }
+
+    /// 回复结束：删除 WAL 文件
+    pub(crate) async fn finish(&mut self) {
+        self.buffer.clear();
+        self.session_id = None;
+        let Some(path) = self.path.take() else {
+            return;
+        };
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            if e.kind() != ErrorKind::NotFound {
+                println!(
+                    "[stream-wal] Failed to remove WAL {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        lock_active_files().remove(&path);
+    }
+}
+
+async fn append_line(path: &Path, line: &str) -> std::io::Result<()> {
+    let mut file = tokio::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(format!("{}\n", line).as_bytes()).await?;
+    file.sync_data().await
+}
+
+/// 把 WAL 中的部分回复追加到对应 ACP 会话的本地会话末尾，返回本地会话 ID
+fn append_recovered_message(
+    snapshot: &mut StorageSnapshot,
+    header: &WalHeader,
+    message: &StoredMessage,
+) -> Option<String> {
+    let session = snapshot
+        .sessions_by_agent
+        .get_mut(&header.agent_id)?
+        .iter_mut()
+        .find(|session| session.acp_session_id.as_deref() == Some(header.session_id.as_str()))?;
+    session.updated_at = message.timestamp.clone();
+    let session_id = session.id.clone();
+    snapshot
+        .messages_by_session
+        .entry(session_id.clone())
+        .or_default()
+        .push(message.clone());
+    Some(session_id)
+}
+
+/// 恢复上次崩溃残留的 WAL，写入会话存储后以 `message-recovered` 事件重放；返回恢复条数。
+/// 前端在注册事件监听后调用。
+#[tauri::command]
+pub async fn replay_interrupted_messages(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let dir = wal_dir(&app_handle)?;
+    let mut reader = match tokio::fs::read_dir(&dir).await {
+        Ok(reader) => reader,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(format!("Failed to read WAL dir: {}", e)),
+    };
+
+    let mut pending = Vec::new();
+    while let Some(entry) = reader
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read WAL entry: {}", e))?
+    {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(WAL_EXTENSION)
+            || lock_active_files().contains(&path)
+        {
+            continue;
+        }
+        let raw = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| format!("Failed to read WAL {}: {}", path.display(), e))?;
+        let modified = entry
+            .metadata()
+            .await
+            .ok()
+            .and_then(|metadata| metadata.modified().ok())
+            .map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339());
+        pending.push((path, parse_wal(&raw), modified));
+    }
+    pending.sort_by(|a, b| a.2.cmp(&b.2));
+
+    let mut recovered = Vec::new();
+    {
+        let _guard = state.storage_lock.lock().await;
+        let path = storage_path(&app_handle)?;
+        let mut snapshot = read_snapshot_from_path(&path).await?;
+        for (_, parsed, modified) in &pending {
+            let Some((header, content)) = parsed else {
+                continue;
+            };
+            if content.trim().is_empty() {
+                continue;
+            }
+            let message = StoredMessage {
+                id: uuid::Uuid::new_v4().to_string(),
+                role: "assistant".to_string(),
+                content: content.clone(),
+                timestamp: modified
+                    .clone()
+                    .unwrap_or_else(|| header.started_at.clone()),
+                agent_id: Some(header.agent_id.clone()),
+                interrupted: true,
+                attachments: Vec::new(),
+                model_switch: None,
+            };
+            let session_id = append_recovered_message(&mut snapshot, header, &message);
+            recovered.push(RecoveredMessage {
+                agent_id: header.agent_id.clone(),
+                session_id,
+                acp_session_id: header.session_id.clone(),
+                message,
+            });
+        }
+        if recovered.iter().any(|item| item.session_id.is_some()) {
+            write_snapshot_to_path(&path, &snapshot).await?;
+        }
+    }
+
+    for (path, _, _) in &pending {
+        if let Err(e) = tokio::fs::remove_file(path).await {
+            println!(
+                "[stream-wal] Failed to remove WAL {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+    for item in &recovered {
+        let _ = app_handle.emit_unless_locked("message-recovered", json!(item));
+    }
+    Ok(recovered.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StoredSession;
+
+    #[test]
+    fn wal_parse_ignores_torn_trailing_line() {
+        let raw = concat!(
+            "{\"agentId\":\"agent-a\",\"sessionId\":\"acp-1\",\"startedAt\":\"2026-03-02T08:00:00Z\"}\n",
+            "\"Hello, \"\n",
+            "\"world\\n\"\n",
+            "\"trunc",
+        );
+        let (header, content) = parse_wal(raw).expect("parse WAL");
+        assert_eq!(header.agent_id, "agent-a");
+        assert_eq!(header.session_id, "acp-1");
+        assert_eq!(content, "Hello, world\n");
+        assert!(parse_wal("not a header\n\"x\"\n").is_none());
+    }
+
+    #[test]
+    fn recovered_message_is_appended_to_bound_session() {
+        let mut snapshot = StorageSnapshot::default();
+        snapshot.sessions_by_agent.insert(
+            "agent-a".to_string(),
+            vec![StoredSession {
+                id: "local-1".to_string(),
+                agent_id: "agent-a".to_string(),
+                acp_session_id: Some("acp-1".to_string()),
+                ..Default::default()
+            }],
+        );
+        let message = StoredMessage {
+            id: "m-1".to_string(),
+            role: "assistant".to_string(),
+            content: "partial".to_string(),
+            timestamp: "2026-03-02T08:00:05Z".to_string(),
+            agent_id: Some("agent-a".to_string()),
+            interrupted: true,
+            attachments: Vec::new(),
+            model_switch: None,
+        };
+        let header = |session_id: &str| WalHeader {
+            agent_id: "agent-a".to_string(),
+            session_id: session_id.to_string(),
+            started_at: "2026-03-02T08:00:00Z".to_string(),
+        };
+
+        assert_eq!(
+            append_recovered_message(&mut snapshot, &header("acp-1"), &message).as_deref(),
+            Some("local-1")
+        );
+        assert_eq!(
+            snapshot.messages_by_session["local-1"],
+            vec![message.clone()]
+        );
+        assert_eq!(
+            snapshot.sessions_by_agent["agent-a"][0].updated_at,
+            "2026-03-02T08:00:05Z"
+        );
+        assert!(append_recovered_message(&mut snapshot, &header("acp-2"), &message).is_none());
+    }
+}
@@ -0,0 +1,674 @@
+//! 任务结束后的快捷后续操作建议：结合本轮工具调用与本轮产生的 Git 变更（与轮次开始时的快照对比），
+//! 生成可由前端直接执行的动作描述（运行测试、提交改动、打开产物、解释 diff）。
+//! 空闲时的 prompt 建议（`suggest_prompts`）只依据工作区状态：上次测试的失败用例、
+//! TODO/FIXME 分布与未提交的改动，没有任何对话时也可用。
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::time::SystemTime;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+use crate::app_lock::EmitUnlessLocked;
+use crate::git::{list_git_changes, GitFileChange};
+use crate::history::workspace_path_variants;
+
+const MAX_ARTIFACT_SUGGESTIONS: usize = 3;
+const MAX_LISTED_ITEMS: usize = 20;
+const MAX_TODO_FILES: usize = 5;
+
+/// 常见测试工具留在工作区的上次运行结果
+const PYTEST_LAST_FAILED: &str = ".pytest_cache/v/cache/lastfailed";
+const VITEST_RESULTS: &[&str] = &[
+    "node_modules/.vite/vitest/results.json",
+    "node_modules/.vitest/results.json",
+];
+const JUNIT_REPORTS: &[&str] = &[
+    "target/nextest/default/junit.xml",
+    "junit.xml",
+    "test-results/junit.xml",
+    "reports/junit.xml",
+];
+
+static JUNIT_FAILED_CASE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"<testcase\b[^>]*?\bname="([^"]+)"[^>]*?(?:/>|>\s*<(?:failure|error)\b)"#)
+        .expect("junit pattern")
+});
+static TODO_MARKER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b(TODO|FIXME)\b").expect("todo pattern"));
+
+/// 本轮对话中观察到的工具调用摘要
+#[derive(Debug, Default, Clone)]
+pub(crate) struct TurnActivity {
+    edited_paths: BTreeSet<String>,
+    touched_paths: BTreeSet<String>,
+}
+
+impl TurnActivity {
+    pub(crate) fn record_update(&mut self, update: &Value) {
+        let session_update = update
+            .get("sessionUpdate")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if session_update != "tool_call" && session_update != "tool_call_update" {
+            return;
+        }
+
+        let kind = update
+            .get("kind")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let paths: Vec<String> = update
+            .get("locations")
+            .and_then(Value::as_array)
+            .map(|locations| {
+                locations
+                    .iter()
+                    .filter_map(|location| location.get("path").and_then(Value::as_str))
+                    .map(|path| path.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if matches!(kind, "edit" | "delete" | "move") {
+            self.edited_paths.extend(paths.iter().cloned());
+        }
+        self.touched_paths.extend(paths);
+    }
+}
+
+/// 文件指纹：大小与修改时间；文件已删除时为 None
+type FileFingerprint = Option<(u64, Option<SystemTime>)>;
+
+/// 工作区未提交改动的快照，轮次开始时记录一份，结束时对比得出本轮产生的改动
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct GitSnapshot {
+    files: BTreeMap<String, FileFingerprint>,
+}
+
+impl GitSnapshot {
+    /// 相对 `self` 新出现或内容再次变化的改动路径
+    fn changed_in(&self, current: &GitSnapshot) -> Vec<String> {
+        current
+            .files
+            .iter()
+            .filter(|(path, fingerprint)| self.files.get(*path) != Some(*fingerprint))
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+}
+
+/// 轮次开始时后台记录的快照
+pub(crate) type GitBaseline = tokio::task::JoinHandle<Option<GitSnapshot>>;
+
+/// 记录当前未提交改动及其文件指纹；非 Git 工作区返回 None
+pub(crate) async fn snapshot_git_changes(workspace_path: String) -> Option<GitSnapshot> {
+    let changes = list_git_changes(workspace_path.clone()).await.ok()?;
+    let mut files = BTreeMap::new();
+    for change in changes {
+        let fingerprint = tokio::fs::metadata(Path::new(&workspace_path).join(&change.path))
+            .await
+            .ok()
+            .map(|metadata| (metadata.len(), metadata.modified().ok()));
+        files.insert(change.path, fingerprint);
+    }
+    Some(GitSnapshot { files })
+}
+
+/// 前端可直接执行的动作描述
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ActionDescriptor {
+    /// 作为下一条用户消息发送给当前 Agent
+    Prompt { content: String },
+    /// 调用后端 Tauri 命令
+    Invoke { command: String, args: Value },
+    /// 在工作区执行的 shell 命令
+    Shell { command: String, cwd: String },
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SuggestedAction {
+    pub id: String,
+    pub label: String,
+    pub action: ActionDescriptor,
+}
+
+fn detect_test_command(workspace_path: &str) -> Option<&'static str> {
+    let root = Path::new(workspace_path);
+    if root.join("Cargo.toml").is_file() {
+        return Some("cargo test");
+    }
+    if root.join("package.json").is_file() {
+        let has_test_script = std::fs::read_to_string(root.join("package.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+            .and_then(|package| package.get("scripts")?.get("test").cloned())
+            .is_some();
+        if has_test_script {
+            return Some(if root.join("pnpm-lock.yaml").is_file() {
+                "pnpm test"
+            } else if root.join("yarn.lock").is_file() {
+                "yarn test"
+            } else {
+                "npm test"
+            });
+        }
+    }
+    if root.join("go.mod").is_file() {
+        return Some("go test ./...");
+    }
+    if root.join("pyproject.toml").is_file() || root.join("pytest.ini").is_file() {
+        return Some("pytest");
+    }
+    None
+}
+
+fn relative_display_path(path: &str, workspace_roots: &[String]) -> String {
+    let normalized = path.replace('\\', "/");
+    for root in workspace_roots {
+        if let Some(rest) = normalized.strip_prefix(&format!("{}/", root)) {
+            return rest.to_string();
+        }
+    }
+    normalized
+}
+
+fn artifact_action(path: &str, agent_id: &str) -> Option<ActionDescriptor> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())?
+        .to_lowercase();
+    let command = match extension.as_str() {
+        "html" | "htm" => "resolve_html_artifact_path",
+        "ipynb" => "read_notebook_artifact",
+        "csv" | "tsv" | "parquet" => "preview_table_file",
+        _ => return None,
+    };
+    Some(ActionDescriptor::Invoke {
+        command: command.to_string(),
+        args: json!({ "agentId": agent_id, "filePath": path }),
+    })
+}
+
+/// `workspace_roots` 为工作区路径的各种形式（见 `workspace_path_variants`），由调用方预先算好；
+/// `turn_git_changes` 为本轮产生的 Git 改动路径；轮次开始前已有且未再变化的改动不计入
+pub(crate) fn build_suggestions(
+    agent_id: &str,
+    workspace_path: &str,
+    workspace_roots: &[String],
+    activity: &TurnActivity,
+    turn_git_changes: Option<&[String]>,
+) -> Vec<SuggestedAction> {
+    let mut changed_paths: BTreeSet<String> = activity
+        .edited_paths
+        .iter()
+        .map(|path| relative_display_path(path, workspace_roots))
+        .collect();
+    if let Some(changes) = turn_git_changes {
+        changed_paths.extend(changes.iter().cloned());
+    }
+
+    let mut actions = Vec::new();
+    if !changed_paths.is_empty() {
+        if let Some(command) = detect_test_command(workspace_path) {
+            actions.push(SuggestedAction {
+                id: "run-tests".to_string(),
+                label: format!("运行测试（{}）", command),
+                action: ActionDescriptor::Shell {
+                    command: command.to_string(),
+                    cwd: workspace_path.to_string(),
+                },
+            });
+        }
+
+        if turn_git_changes
+            .map(|changes| !changes.is_empty())
+            .unwrap_or(false)
+        {
+            actions.push(SuggestedAction {
+                id: "commit-changes".to_string(),
+                label: "提交改动".to_string(),
+                action: ActionDescriptor::Prompt {
+                    content: "请检查当前工作区的改动，撰写简洁的提交信息并提交。".to_string(),
+                },
+            });
+        }
+
+        let listed = changed_paths
+            .iter()
+            .take(20)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n");
+        actions.push(SuggestedAction {
+            id: "explain-diff".to_string(),
+            label: "解释本次改动".to_string(),
+            action: ActionDescriptor::Prompt {
+                content: format!("请逐个解释本次对以下文件的改动及其原因：\n{}", listed),
+            },
+        });
+    }
+
+    let artifact_candidates = activity
+        .touched_paths
+        .iter()
+        .map(|path| relative_display_path(path, workspace_roots))
+        .chain(changed_paths.iter().cloned())
+        .collect::<BTreeSet<_>>();
+    for path in artifact_candidates
+        .iter()
+        .filter(|path| artifact_action(path, agent_id).is_some())
+        .take(MAX_ARTIFACT_SUGGESTIONS)
+    {
+        if let Some(action) = artifact_action(path, agent_id) {
+            actions.push(SuggestedAction {
+                id: format!("open-artifact:{}", path),
+                label: format!("打开 {}", path),
+                action,
+            });
+        }
+    }
+
+    actions
+}
+
+/// 任务结束后在后台计算建议并推送 suggested-actions 事件
+pub(crate) fn spawn_suggested_actions(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    workspace_path: &str,
+    activity: TurnActivity,
+    git_baseline: Option<GitBaseline>,
+) {
+    let app_handle = app_handle.clone();
+    let agent_id = agent_id.to_string();
+    let workspace_path = workspace_path.to_string();
+    tokio::spawn(async move {
+        // 非 Git 工作区或没有轮次开始时的快照时，仅依据工具调用生成建议
+        let baseline = match git_baseline {
+            Some(handle) => handle.await.ok().flatten(),
+            None => None,
+        };
+        let turn_git_changes = match baseline {
+            Some(baseline) => snapshot_git_changes(workspace_path.clone())
+                .await
+                .map(|current| baseline.changed_in(&current)),
+            None => None,
+        };
+        let workspace_roots = workspace_path_variants(&workspace_path).await;
+        let actions = build_suggestions(
+            &agent_id,
+            &workspace_path,
+            &workspace_roots,
+            &activity,
+            turn_git_changes.as_deref(),
+        );
+        if actions.is_empty() {
+            return;
+        }
+        let _ = app_handle.emit_unless_locked(
+            "suggested-actions",
+            json!({
+                "agentId": agent_id,
+                "actions": actions,
+            }),
+        );
+    });
+}
+
+/// 空闲 prompt 建议依据的工作区状态
+#[derive(Debug, Default, Clone)]
+struct WorkspaceSignals {
+    failing_tests: Vec<String>,
+    todo_count: usize,
+    fixme_count: usize,
+    /// 按标记数量降序的文件
+    todo_files: Vec<(String, usize)>,
+    uncommitted: Vec<GitFileChange>,
+}
+
+fn pytest_failures(content: &str) -> Vec<String> {
+    serde_json::from_str::<BTreeMap<String, Value>>(content)
+        .map(|failed| {
+            failed
+                .into_iter()
+                .filter(|(_, value)| value.as_bool().unwrap_or(false))
+                .map(|(test, _)| test)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn vitest_failures(content: &str) -> Vec<String> {
+    let Ok(report) = serde_json::from_str::<Value>(content) else {
+        return Vec::new();
+    };
+    report
+        .get("results")
+        .and_then(Value::as_object)
+        .map(|results| {
+            results
+                .iter()
+                .filter(|(_, result)| result.get("failed").and_then(Value::as_bool) == Some(true))
+                .map(|(file, _)| file.trim_start_matches('/').to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn junit_failures(content: &str) -> Vec<String> {
+    JUNIT_FAILED_CASE
+        .captures_iter(content)
+        .filter(|captures| !captures[0].ends_with("/>"))
+        .map(|captures| captures[1].to_string())
+        .collect()
+}
+
+/// 读取上次测试运行留下的失败用例；各工具的结果文件都不存在时返回空
+async fn last_failed_tests(workspace_path: &str) -> Vec<String> {
+    let root = Path::new(workspace_path);
+    let read = |relative: &str| tokio::fs::read_to_string(root.join(relative));
+
+    let mut failures = Vec::new();
+    if let Ok(content) = read(PYTEST_LAST_FAILED).await {
+        failures.extend(pytest_failures(&content));
+    }
+    for path in VITEST_RESULTS {
+        if let Ok(content) = read(path).await {
+            failures.extend(vitest_failures(&content));
+            break;
+        }
+    }
+    for path in JUNIT_REPORTS {
+        if let Ok(content) = read(path).await {
+            failures.extend(junit_failures(&content));
+        }
+    }
+    failures.sort();
+    failures.dedup();
+    failures
+}
+
+/// 统计 `git grep` 输出（`path:line:text`）中的 TODO/FIXME
+fn count_todo_markers(output: &str) -> (usize, usize, Vec<(String, usize)>) {
+    let mut todo_count = 0;
+    let mut fixme_count = 0;
+    let mut per_file: BTreeMap<&str, usize> = BTreeMap::new();
+    for line in output.lines() {
+        let mut parts = line.splitn(3, ':');
+        let (Some(path), Some(_), Some(text)) = (parts.next(), parts.next(), parts.next()) else {
+            continue;
+        };
+        for marker in TODO_MARKER.find_iter(text) {
+            if marker.as_str() == "FIXME" {
+                fixme_count += 1;
+            } else {
+                todo_count += 1;
+            }
+            *per_file.entry(path).or_default() += 1;
+        }
+    }
+    let mut files: Vec<(String, usize)> = per_file
+        .into_iter()
+        .map(|(path, count)| (path.to_string(), count))
+        .collect();
+    files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    files.truncate(MAX_TODO_FILES);
+    (todo_count, fixme_count, files)
+}
+
+/// 只统计 Git 跟踪的文本文件，避开依赖与构建产物；非 Git 工作区不统计
+async fn todo_markers(workspace_path: &str) -> (usize, usize, Vec<(String, usize)>) {
+    let output = timeout(
+        Duration::from_secs(10),
+        Command::new("git")
+            .arg("-C")
+            .arg(workspace_path)
+            .args(["grep", "-I", "-n", "-w", "-E", "TODO|FIXME"])
+            .output(),
+    )
+    .await;
+    match output {
+        Ok(Ok(output)) if output.status.success() => {
+            count_todo_markers(&String::from_utf8_lossy(&output.stdout))
+        }
+        _ => (0, 0, Vec::new()),
+    }
+}
+
+async fn collect_workspace_signals(workspace_path: &str) -> WorkspaceSignals {
+    let (todo_count, fixme_count, todo_files) = todo_markers(workspace_path).await;
+    WorkspaceSignals {
+        failing_tests: last_failed_tests(workspace_path).await,
+        todo_count,
+        fixme_count,
+        todo_files,
+        uncommitted: list_git_changes(workspace_path.to_string())
+            .await
+            .unwrap_or_default(),
+    }
+}
+
+fn bullet_list<'a>(items: impl Iterator<Item = &'a str>) -> String {
+    items
+        .take(MAX_LISTED_ITEMS)
+        .map(|item| format!("- {}", item))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn build_prompt_suggestions(signals: &WorkspaceSignals) -> Vec<SuggestedAction> {
+    let mut actions = Vec::new();
+    if !signals.failing_tests.is_empty() {
+        actions.push(SuggestedAction {
+            id: "fix-failing-tests".to_string(),
+            label: format!("修复失败的测试（{} 个）", signals.failing_tests.len()),
+            action: ActionDescriptor::Prompt {
+                content: format!(
+                    "上次运行测试时以下用例失败，请定位原因并修复：\n{}",
+                    bullet_list(signals.failing_tests.iter().map(String::as_str))
+                ),
+            },
+        });
+    }
+
+    if !signals.uncommitted.is_empty() {
+        actions.push(SuggestedAction {
+            id: "review-uncommitted".to_string(),
+            label: format!("审查未提交的改动（{} 个文件）", signals.uncommitted.len()),
+            action: ActionDescriptor::Prompt {
+                content: format!(
+                    "请审查工作区中尚未提交的改动，指出潜在问题并给出修改建议：\n{}",
+                    bullet_list(
+                        signals
+                            .uncommitted
+                            .iter()
+                            .map(|change| change.path.as_str())
+                    )
+                ),
+            },
+        });
+    }
+
+    if signals.todo_count + signals.fixme_count > 0 {
+        let files = signals
+            .todo_files
+            .iter()
+            .map(|(path, count)| format!("{}（{} 处）", path, count))
+            .collect::<Vec<_>>();
+        actions.push(SuggestedAction {
+            id: "resolve-todos".to_string(),
+            label: format!(
+                "处理 TODO/FIXME（TODO {} · FIXME {}）",
+                signals.todo_count, signals.fixme_count
+            ),
+            action: ActionDescriptor::Prompt {
+                content: format!(
+                    "工作区中有 {} 处 TODO、{} 处 FIXME，主要集中在：\n{}\n请优先处理 FIXME，并说明每处的处理方式。",
+                    signals.todo_count,
+                    signals.fixme_count,
+                    bullet_list(files.iter().map(String::as_str))
+                ),
+            },
+        });
+    }
+
+    if actions.is_empty() {
+        actions.push(SuggestedAction {
+            id: "explain-workspace".to_string(),
+            label: "梳理项目结构".to_string(),
+            action: ActionDescriptor::Prompt {
+                content: "请梳理这个项目的目录结构、主要模块及其职责，以及构建和测试方式。"
+                    .to_string(),
+            },
+        });
+    }
+    actions
+}
+
+/// 依据工作区状态给出可直接发送的 prompt 建议，不依赖已有对话
+#[tauri::command]
+pub async fn suggest_prompts(workspace_path: String) -> Result<Vec<SuggestedAction>, String> {
+    if !Path::new(&workspace_path).is_dir() {
+        return Err(format!("Workspace not found: {}", workspace_path));
+    }
+    let signals = collect_workspace_signals(&workspace_path).await;
+    Ok(build_prompt_suggestions(&signals))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggestions_cover_tests_diff_and_artifacts() {
+        let workspace =
+            std::env::temp_dir().join(format!("iflow-suggest-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&workspace).expect("create workspace");
+        std::fs::write(workspace.join("Cargo.toml"), "[package]\nname = \"demo\"\n")
+            .expect("write");
+        let workspace_path = workspace.to_string_lossy().to_string();
+
+        let mut activity = TurnActivity::default();
+        activity.record_update(&json!({
+            "sessionUpdate": "tool_call",
+            "kind": "edit",
+            "locations": [{ "path": format!("{}/src/lib.rs", workspace_path) }],
+        }));
+        activity.record_update(&json!({
+            "sessionUpdate": "tool_call",
+            "kind": "edit",
+            "locations": [{ "path": format!("{}/report.html", workspace_path) }],
+        }));
+
+        let actions = build_suggestions(
+            "agent-1",
+            &workspace_path,
+            &[workspace_path.clone()],
+            &activity,
+            None,
+        );
+        let ids: Vec<&str> = actions.iter().map(|action| action.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["run-tests", "explain-diff", "open-artifact:report.html"]
+        );
+        assert_eq!(
+            actions[0].action,
+            ActionDescriptor::Shell {
+                command: "cargo test".to_string(),
+                cwd: workspace_path.clone(),
+            }
+        );
+
+        let _ = std::fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn only_changes_made_during_the_turn_count() {
+        let modified = Some(SystemTime::UNIX_EPOCH);
+        let baseline = GitSnapshot {
+            files: BTreeMap::from([
+                ("dirty-before.rs".to_string(), Some((10, modified))),
+                ("edited-again.rs".to_string(), Some((10, modified))),
+            ]),
+        };
+        let current = GitSnapshot {
+            files: BTreeMap::from([
+                ("dirty-before.rs".to_string(), Some((10, modified))),
+                ("edited-again.rs".to_string(), Some((12, modified))),
+                ("new-file.rs".to_string(), Some((3, modified))),
+            ]),
+        };
+        assert_eq!(
+            baseline.changed_in(&current),
+            vec!["edited-again.rs", "new-file.rs"]
+        );
+
+        let untouched = baseline.changed_in(&baseline);
+        let actions = build_suggestions(
+            "agent-1",
+            "/nonexistent",
+            &[],
+            &TurnActivity::default(),
+            Some(&untouched),
+        );
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn no_activity_yields_no_suggestions() {
+        let actions = build_suggestions(
+            "agent-1",
+            "/nonexistent",
+            &[],
+            &TurnActivity::default(),
+            Some(&[][..]),
+        );
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn idle_suggestions_follow_workspace_signals() {
+        let junit = r#"<testsuite>
+            <testcase classname="api" name="login_ok" time="0.1"/>
+            <testcase classname="api" name="login_rejects_bad_token" time="0.2">
+                <failure message="assertion failed"/>
+            </testcase>
+        </testsuite>"#;
+        assert_eq!(junit_failures(junit), vec!["login_rejects_bad_token"]);
+        assert_eq!(
+            pytest_failures(r#"{"tests/test_api.py::test_login": true}"#),
+            vec!["tests/test_api.py::test_login"]
+        );
+
+        let (todo_count, fixme_count, todo_files) = count_todo_markers(
+            "src/a.rs:3:// TODO: split\nsrc/a.rs:9:// FIXME: leak\nsrc/b.rs:1:// TODO x TODO y\n",
+        );
+        assert_eq!((todo_count, fixme_count), (3, 1));
+        assert_eq!(todo_files[0], ("src/a.rs".to_string(), 2));
+
+        let signals = WorkspaceSignals {
+            failing_tests: junit_failures(junit),
+            todo_count,
+            fixme_count,
+            todo_files,
+            uncommitted: Vec::new(),
+        };
+        let ids: Vec<String> = build_prompt_suggestions(&signals)
+            .into_iter()
+            .map(|action| action.id)
+            .collect();
+        assert_eq!(ids, vec!["fix-failing-tests", "resolve-todos"]);
+        assert_eq!(
+            build_prompt_suggestions(&WorkspaceSignals::default())[0].id,
+            "explain-workspace"
+        );
+    }
+}
@@ -0,0 +1,327 @@
+//! CSV / TSV / Parquet 数据文件预览：读取前 N 行与列类型
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::State;
+
+use crate::artifact::{local_artifact_workspace, resolve_artifact_path_in_workspace};
+use crate::state::AppState;
+
+const DEFAULT_PREVIEW_ROWS: usize = 50;
+const MAX_PREVIEW_ROWS: usize = 1000;
+const MAX_CELL_CHARS: usize = 2000;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TableColumn {
+    pub name: String,
+    pub data_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TablePreview {
+    pub path: String,
+    pub format: String,
+    pub columns: Vec<TableColumn>,
+    pub rows: Vec<Vec<Value>>,
+    pub total_rows: Option<u64>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CellType {
+    Null,
+    Boolean,
+    Integer,
+    Float,
+    String,
+}
+
+impl CellType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Boolean => "boolean",
+            Self::Integer => "integer",
+            Self::Float => "float",
+            Self::String => "string",
+        }
+    }
+
+    // 列类型取所有非空单元格的最宽类型
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Null, item) | (item, Self::Null) => item,
+            (left, right) if left == right => left,
+            (Self::Integer, Self::Float) | (Self::Float, Self::Integer) => Self::Float,
+            _ => Self::String,
+        }
+    }
+}
+
+fn truncate_cell(text: &str) -> String {
+    if text.chars().count() <= MAX_CELL_CHARS {
+        return text.to_string();
+    }
+    format!("{}…", text.chars().take(MAX_CELL_CHARS).collect::<String>())
+}
+
+/// 推断出的类型与值，以及截断后的原始文本（列回退为 string 时使用）
+struct InferredCell {
+    cell_type: CellType,
+    value: Value,
+    raw: String,
+}
+
+fn infer_cell(raw: &str) -> InferredCell {
+    let trimmed = raw.trim();
+    let (cell_type, value) = if trimmed.is_empty() {
+        (CellType::Null, Value::Null)
+    } else if let Ok(value) = trimmed.parse::<i64>() {
+        (CellType::Integer, json!(value))
+    } else if let Some(value) = trimmed
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+    {
+        (CellType::Float, json!(value))
+    } else {
+        match trimmed.to_lowercase().as_str() {
+            "true" => (CellType::Boolean, Value::Bool(true)),
+            "false" => (CellType::Boolean, Value::Bool(false)),
+            _ => (CellType::String, Value::String(truncate_cell(raw))),
+        }
+    };
+    InferredCell {
+        cell_type,
+        value,
+        raw: truncate_cell(raw),
+    }
+}
+
+fn preview_delimited(path: &Path, delimiter: u8, limit: usize) -> Result<TablePreview, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| format!("Failed to read header: {}", e))?
+        .iter()
+        .map(|item| item.trim().to_string())
+        .collect();
+
+    let mut column_types = vec![CellType::Null; headers.len()];
+    let mut raw_rows: Vec<Vec<InferredCell>> = Vec::new();
+    let mut truncated = false;
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Failed to read row: {}", e))?;
+        if raw_rows.len() >= limit {
+            truncated = true;
+            break;
+        }
+        let cells = (0..headers.len())
+            .map(|index| infer_cell(record.get(index).unwrap_or_default()))
+            .collect::<Vec<_>>();
+        for (index, cell) in cells.iter().enumerate() {
+            column_types[index] = column_types[index].merge(cell.cell_type);
+        }
+        raw_rows.push(cells);
+    }
+
+    // 列被推断为 string 时，数值与布尔单元格回退为文件中的原始文本（"007" 不会变成 7），保证同列类型一致
+    let rows = raw_rows
+        .into_iter()
+        .map(|cells| {
+            cells
+                .into_iter()
+                .enumerate()
+                .map(|(index, cell)| match (column_types[index], cell.value) {
+                    (CellType::String, Value::Number(_) | Value::Bool(_)) => {
+                        Value::String(cell.raw)
+                    }
+                    (_, value) => value,
+                })
+                .collect()
+        })
+        .collect();
+
+    let format = if delimiter == b'\t' { "tsv" } else { "csv" };
+    Ok(TablePreview {
+        path: path.to_string_lossy().to_string(),
+        format: format.to_string(),
+        columns: headers
+            .into_iter()
+            .zip(column_types)
+            .map(|(name, data_type)| TableColumn {
+                name,
+                data_type: data_type.as_str().to_string(),
+            })
+            .collect(),
+        rows,
+        total_rows: None,
+        truncated,
+    })
+}
+
+fn parquet_field_to_json(field: &parquet::record::Field) -> Value {
+    use parquet::record::Field;
+    match field {
+        Field::Null => Value::Null,
+        Field::Bool(value) => json!(value),
+        Field::Byte(value) => json!(value),
+        Field::Short(value) => json!(value),
+        Field::Int(value) => json!(value),
+        Field::Long(value) => json!(value),
+        Field::UByte(value) => json!(value),
+        Field::UShort(value) => json!(value),
+        Field::UInt(value) => json!(value),
+        Field::ULong(value) => json!(value),
+        Field::Float(value) => json!(value),
+        Field::Double(value) => json!(value),
+        Field::Str(value) => Value::String(truncate_cell(value)),
+        other => Value::String(truncate_cell(&other.to_string())),
+    }
+}
+
+fn preview_parquet(path: &Path, limit: usize) -> Result<TablePreview, String> {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let reader = SerializedFileReader::new(file)
+        .map_err(|e| format!("Failed to read parquet metadata: {}", e))?;
+
+    let file_metadata = reader.metadata().file_metadata();
+    let total_rows = u64::try_from(file_metadata.num_rows()).ok();
+    let columns = file_metadata
+        .schema_descr()
+        .root_schema()
+        .get_fields()
+        .iter()
+        .map(|field| {
+            let data_type = if field.is_primitive() {
+                let basic_info = field.get_basic_info();
+                match basic_info.logical_type() {
+                    Some(logical) => format!("{:?}", logical),
+                    None => format!("{:?}", field.get_physical_type()),
+                }
+            } else {
+                "group".to_string()
+            };
+            TableColumn {
+                name: field.name().to_string(),
+                data_type: data_type.to_lowercase(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut rows = Vec::new();
+    let row_iter = reader
+        .get_row_iter(None)
+        .map_err(|e| format!("Failed to read parquet rows: {}", e))?;
+    for row in row_iter.take(limit) {
+        let row = row.map_err(|e| format!("Failed to read parquet row: {}", e))?;
+        rows.push(
+            row.get_column_iter()
+                .map(|(_, field)| parquet_field_to_json(field))
+                .collect(),
+        );
+    }
+
+    Ok(TablePreview {
+        path: path.to_string_lossy().to_string(),
+        format: "parquet".to_string(),
+        columns,
+        truncated: total_rows
+            .map(|total| total > rows.len() as u64)
+            .unwrap_or(false),
+        rows,
+        total_rows,
+    })
+}
+
+/// 预览工作区内的 CSV / TSV / Parquet 文件（限制在当前 Agent 工作目录内）
+#[tauri::command]
+pub async fn preview_table_file(
+    state: State<'_, AppState>,
+    agent_id: String,
+    file_path: String,
+    limit: Option<usize>,
+) -> Result<TablePreview, String> {
+    let workspace_path = local_artifact_workspace(&state, &agent_id).await?;
+    let canonical_target = resolve_artifact_path_in_workspace(
+        &workspace_path,
+        &file_path,
+        &["csv", "tsv", "parquet"],
+        "Only .csv/.tsv/.parquet files are supported",
+    )
+    .await?;
+    let limit = limit
+        .unwrap_or(DEFAULT_PREVIEW_ROWS)
+        .clamp(1, MAX_PREVIEW_ROWS);
+
+    let extension = canonical_target
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    tokio::task::spawn_blocking(move || match extension.as_str() {
+        "parquet" => preview_parquet(&canonical_target, limit),
+        "tsv" => preview_delimited(&canonical_target, b'\t', limit),
+        _ => preview_delimited(&canonical_target, b',', limit),
+    })
+    .await
+    .map_err(|e| format!("Table preview task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_preview_infers_column_types() {
+        let path = std::env::temp_dir().join(format!("iflow-preview-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "id,score,name,flag\n1,2.5,alice,true\n2,3,bob,false\n3,,7,true\n",
+        )
+        .expect("write csv");
+
+        let preview = preview_delimited(&path, b',', 2).expect("preview csv");
+        let types: Vec<&str> = preview
+            .columns
+            .iter()
+            .map(|column| column.data_type.as_str())
+            .collect();
+        assert_eq!(types, vec!["integer", "float", "string", "boolean"]);
+        assert_eq!(preview.rows.len(), 2);
+        assert!(preview.truncated);
+        assert_eq!(preview.rows[1][1], json!(3));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn mixed_column_falls_back_to_string() {
+        assert_eq!(CellType::Integer.merge(CellType::Float), CellType::Float);
+        assert_eq!(CellType::Integer.merge(CellType::String), CellType::String);
+        assert_eq!(CellType::Null.merge(CellType::Boolean), CellType::Boolean);
+
+        let path = std::env::temp_dir().join(format!("iflow-preview-{}.csv", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "code,flag\n007,TRUE\n1e3,yes\nabc,\n").expect("write csv");
+        let preview = preview_delimited(&path, b',', 10).expect("preview csv");
+        assert_eq!(preview.columns[0].data_type, "string");
+        assert_eq!(preview.columns[1].data_type, "string");
+        assert_eq!(preview.rows[0][0], json!("007"));
+        assert_eq!(preview.rows[1][0], json!("1e3"));
+        assert_eq!(preview.rows[0][1], json!("TRUE"));
+        assert_eq!(preview.rows[2][1], Value::Null);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,236 @@
+//! 任务记录：每次 session/prompt 对应一条记录（起止时间、停止原因、校验结论等），
+//! 持久化到应用数据目录，仅保留最近的若干条。
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{Manager, State};
+use tokio::fs;
+
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag};
+
+const MAX_TASK_RECORDS: usize = 1000;
+const MAX_TASK_PROMPT_CHARS: usize = 2000;
+const DEFAULT_TASK_LIST_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VerificationVerdict {
+    Agree,
+    Disagree,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskVerification {
+    pub verdict: VerificationVerdict,
+    pub critique: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    pub verified_at: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskRecord {
+    pub id: String,
+    pub agent_id: String,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    pub prompt: String,
+    pub started_at: String,
+    #[serde(default)]
+    pub finished_at: Option<String>,
+    #[serde(default)]
+    pub stop_reason: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub verification: Option<TaskVerification>,
+    /// 其他模块附加的任务元数据
+    #[serde(default)]
+    pub metadata: Map<String, Value>,
+}
+
+impl TaskRecord {
+    pub(crate) fn new(agent_id: &str, session_id: Option<&str>, prompt: &str) -> Self {
+        let prompt = if prompt.chars().count() > MAX_TASK_PROMPT_CHARS {
+            format!(
+                "{}…",
+                prompt
+                    .chars()
+                    .take(MAX_TASK_PROMPT_CHARS)
+                    .collect::<String>()
+            )
+        } else {
+            prompt.to_string()
+        };
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            agent_id: agent_id.to_string(),
+            session_id: session_id.map(|item| item.to_string()),
+            prompt,
+            started_at: chrono::Utc::now().to_rfc3339(),
+            ..Default::default()
+        }
+    }
+}
+
+fn tasks_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-tasks-{}.json", storage_env_tag())))
+}
+
+async fn read_tasks_from_path(path: &Path) -> Result<Vec<TaskRecord>, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(Vec::new());
+            }
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse task records: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(format!("Failed to read task records: {}", err)),
+    }
+}
+
+async fn write_tasks_to_path(path: &Path, records: &[TaskRecord]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create task records dir: {}", e))?;
+    }
+    let payload =
+        serde_json::to_vec(records).map_err(|e| format!("Failed to encode task records: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write task records: {}", e))
+}
+
+fn push_capped(records: &mut Vec<TaskRecord>, record: TaskRecord) {
+    records.push(record);
+    if records.len() > MAX_TASK_RECORDS {
+        let overflow = records.len() - MAX_TASK_RECORDS;
+        records.drain(..overflow);
+    }
+}
+
+/// 新增任务记录
+pub(crate) async fn record_task_start(
+    app_handle: &tauri::AppHandle,
+    record: TaskRecord,
+) -> Result<(), String> {
+    let state = app_handle.state::<AppState>();
+    let _guard = state.tasks_lock.lock().await;
+    let path = tasks_path(app_handle)?;
+    let mut records = read_tasks_from_path(&path).await?;
+    push_capped(&mut records, record);
+    write_tasks_to_path(&path, &records).await
+}
+
+/// 读取全部任务记录（按开始顺序）
+pub(crate) async fn load_task_records(
+    app_handle: &tauri::AppHandle,
+) -> Result<Vec<TaskRecord>, String> {
+    let state = app_handle.state::<AppState>();
+    let _guard = state.tasks_lock.lock().await;
+    read_tasks_from_path(&tasks_path(app_handle)?).await
+}
+
+/// 按 ID 修改任务记录；记录已被淘汰时返回 false
+pub(crate) async fn update_task_record<F>(
+    app_handle: &tauri::AppHandle,
+    task_id: &str,
+    update: F,
+) -> Result<bool, String>
+where
+    F: FnOnce(&mut TaskRecord),
+{
+    let state = app_handle.state::<AppState>();
+    let _guard = state.tasks_lock.lock().await;
+    let path = tasks_path(app_handle)?;
+    let mut records = read_tasks_from_path(&path).await?;
+    let Some(record) = records.iter_mut().rev().find(|record| record.id == task_id) else {
+        return Ok(false);
+    };
+    update(record);
+    write_tasks_to_path(&path, &records).await?;
+    Ok(true)
+}
+
+pub(crate) async fn record_task_finish(
+    app_handle: &tauri::AppHandle,
+    task_id: &str,
+    stop_reason: Option<&str>,
+    error: Option<String>,
+) -> Result<bool, String> {
+    update_task_record(app_handle, task_id, |record| {
+        record.finished_at = Some(chrono::Utc::now().to_rfc3339());
+        record.stop_reason = stop_reason.map(|item| item.to_string());
+        record.error = error;
+    })
+    .await
+}
+
+/// 列出任务记录（最新在前），可按 Agent 过滤
+#[tauri::command]
+pub async fn list_task_records(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    agent_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<TaskRecord>, String> {
+    let _guard = state.tasks_lock.lock().await;
+    let records = read_tasks_from_path(&tasks_path(&app_handle)?).await?;
+    let limit = limit.unwrap_or(DEFAULT_TASK_LIST_LIMIT);
+    Ok(records
+        .into_iter()
+        .rev()
+        .filter(|record| {
+            agent_id
+                .as_deref()
+                .map(|agent_id| record.agent_id == agent_id)
+                .unwrap_or(true)
+        })
+        .take(limit)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn task_records_roundtrip_and_cap() {
+        let path = std::env::temp_dir().join(format!("iflow-tasks-{}.json", uuid::Uuid::new_v4()));
+        let mut records = Vec::new();
+        for index in 0..(MAX_TASK_RECORDS + 5) {
+            push_capped(
+                &mut records,
+                TaskRecord::new("agent-a", None, &format!("prompt {}", index)),
+            );
+        }
+        assert_eq!(records.len(), MAX_TASK_RECORDS);
+        assert_eq!(records[0].prompt, "prompt 5");
+
+        write_tasks_to_path(&path, &records).await.expect("write");
+        let loaded = read_tasks_from_path(&path).await.expect("read");
+        assert_eq!(loaded, records);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn task_prompt_is_truncated() {
+        let record = TaskRecord::new(
+            "agent-a",
+            Some("session-1"),
+            &"x".repeat(MAX_TASK_PROMPT_CHARS + 10),
+        );
+        assert!(record.prompt.ends_with('…'));
+        assert_eq!(record.session_id.as_deref(), Some("session-1"));
+    }
+}
@@ -0,0 +1,477 @@
+//! 团队同步：把各工作区的 prompt 规则（宏模板、改写规则）存放到用户指定的 Git 仓库，
+//! 仓库布局为 `workspaces/<name>/prompt-rules/<rule-id>.json`，本机通过设置中的映射
+//! 把团队工作区名对应到本地路径。sync_now 依次提交本地改动、合并远端、导入并推送。
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::OsStr;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tokio::fs;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+use crate::prompt_rules::{
+    load_workspace_rules, replace_workspace_rules, validate_rule, PromptRule,
+};
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag};
+
+const DEFAULT_BRANCH: &str = "main";
+const GIT_TIMEOUT: Duration = Duration::from_secs(60);
+const RULES_DIR: &str = "prompt-rules";
+const COMMIT_NAME: &str = "FlowHub";
+const COMMIT_EMAIL: &str = "flowhub@localhost";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamSyncWorkspace {
+    /// 仓库中的团队工作区名，只允许字母、数字、`.`、`_`、`-`
+    pub name: String,
+    pub workspace_path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamSyncConfig {
+    pub repo_url: String,
+    /// 为空时使用 main
+    #[serde(default)]
+    pub branch: Option<String>,
+    #[serde(default)]
+    pub workspaces: Vec<TeamSyncWorkspace>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    /// 冲突时保留本机版本
+    Local,
+    /// 冲突时采用远端版本
+    Remote,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub committed_local: bool,
+    pub merged_remote: bool,
+    pub pushed: bool,
+    /// 合并冲突的文件（相对仓库根）；非空时本次未导入也未推送
+    pub conflicts: Vec<String>,
+    pub exported: usize,
+    pub imported: usize,
+    pub head: Option<String>,
+}
+
+/// 每个团队工作区上次同步时的规则 ID，用于区分本地删除与远端新增
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct TeamSyncState {
+    #[serde(default)]
+    synced: HashMap<String, Vec<String>>,
+}
+
+fn sync_state_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-team-sync-{}.json", storage_env_tag())))
+}
+
+fn sync_repo_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("team-sync-repo-{}", storage_env_tag())))
+}
+
+async fn read_sync_state(path: &Path) -> Result<TeamSyncState, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(TeamSyncState::default());
+            }
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse team sync state: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(TeamSyncState::default()),
+        Err(err) => Err(format!("Failed to read team sync state: {}", err)),
+    }
+}
+
+async fn write_sync_state(path: &Path, sync_state: &TeamSyncState) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create team sync dir: {}", e))?;
+    }
+    let payload = serde_json::to_vec(sync_state)
+        .map_err(|e| format!("Failed to encode team sync state: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write team sync state: {}", e))
+}
+
+fn is_safe_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '.' | '_' | '-'))
+}
+
+fn validate_config(config: &TeamSyncConfig) -> Result<String, String> {
+    let repo_url = config.repo_url.trim();
+    if repo_url.is_empty() || repo_url.starts_with('-') {
+        return Err("Team sync repository URL is invalid".to_string());
+    }
+    let mut names = BTreeSet::new();
+    for workspace in &config.workspaces {
+        if !is_safe_name(&workspace.name) {
+            return Err(format!("Invalid team workspace name: {}", workspace.name));
+        }
+        if !names.insert(workspace.name.as_str()) {
+            return Err(format!("Duplicate team workspace name: {}", workspace.name));
+        }
+    }
+    let branch = config
+        .branch
+        .as_deref()
+        .map(str::trim)
+        .filter(|branch| !branch.is_empty())
+        .unwrap_or(DEFAULT_BRANCH);
+    if branch.starts_with('-') || branch.contains(char::is_whitespace) {
+        return Err(format!("Invalid team sync branch: {}", branch));
+    }
+    Ok(branch.to_string())
+}
+
+/// 本地导出时应删除的仓库文件：上次同步过、但本地已不存在的规则。
+/// 仓库中其余未同步过的规则视为远端新增，保留待导入。
+fn removed_since_last_sync(previous: &[String], local: &[PromptRule]) -> Vec<String> {
+    previous
+        .iter()
+        .filter(|id| !local.iter().any(|rule| &rule.id == *id))
+        .cloned()
+        .collect()
+}
+
+async fn run_git<I, S>(repo: &Path, args: I) -> Result<std::process::Output, String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    timeout(
+        GIT_TIMEOUT,
+        Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(args)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output(),
+    )
+    .await
+    .map_err(|_| "Git 命令超时，请稍后重试".to_string())?
+    .map_err(|e| format!("执行 Git 失败: {}", e))
+}
+
+async fn git_ok<I, S>(repo: &Path, args: I) -> Result<String, String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = run_git(repo, args).await?;
+    if !output.status.success() {
+        return Err(format!(
+            "Git 命令失败: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+async fn git_succeeds(repo: &Path, args: &[&str]) -> Result<bool, String> {
+    Ok(run_git(repo, args).await?.status.success())
+}
+
+/// 初始化本地克隆并拉取远端；首次同步且远端已有分支时直接检出远端分支
+async fn prepare_repo(repo: &Path, repo_url: &str, branch: &str) -> Result<bool, String> {
+    if !repo.join(".git").exists() {
+        fs::create_dir_all(repo)
+            .await
+            .map_err(|e| format!("Failed to create team sync repo dir: {}", e))?;
+        git_ok(repo, ["init"]).await?;
+        git_ok(repo, ["remote", "add", "origin", repo_url]).await?;
+    } else {
+        git_ok(repo, ["remote", "set-url", "origin", repo_url]).await?;
+    }
+    git_ok(repo, ["fetch", "--prune", "origin"]).await?;
+
+    let remote_ref = format!("refs/remotes/origin/{}", branch);
+    let remote_exists =
+        git_succeeds(repo, &["rev-parse", "--verify", "--quiet", &remote_ref]).await?;
+    let has_head = git_succeeds(repo, &["rev-parse", "--verify", "--quiet", "HEAD"]).await?;
+    if !has_head && remote_exists {
+        git_ok(
+            repo,
+            ["checkout", "-B", branch, &format!("origin/{}", branch)],
+        )
+        .await?;
+    } else if !has_head {
+        git_ok(repo, ["checkout", "-B", branch]).await?;
+    }
+    Ok(remote_exists)
+}
+
+fn rules_dir(repo: &Path, name: &str) -> PathBuf {
+    repo.join("workspaces").join(name).join(RULES_DIR)
+}
+
+/// 把本地规则写入仓库工作树，返回写入的规则数
+async fn export_rules(
+    repo: &Path,
+    name: &str,
+    local: &[PromptRule],
+    removed: &[String],
+) -> Result<usize, String> {
+    let dir = rules_dir(repo, name);
+    fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create team rules dir: {}", e))?;
+    for rule_id in removed.iter().filter(|id| is_safe_name(id)) {
+        match fs::remove_file(dir.join(format!("{}.json", rule_id))).await {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(format!("Failed to remove team rule: {}", err)),
+        }
+    }
+    let mut exported = 0;
+    for rule in local.iter().filter(|rule| is_safe_name(&rule.id)) {
+        let payload = serde_json::to_vec_pretty(rule)
+            .map_err(|e| format!("Failed to encode team rule: {}", e))?;
+        fs::write(dir.join(format!("{}.json", rule.id)), payload)
+            .await
+            .map_err(|e| format!("Failed to write team rule: {}", e))?;
+        exported += 1;
+    }
+    Ok(exported)
+}
+
+/// 读取仓库中的规则；无效文件跳过，按创建时间排序以保持应用顺序稳定
+async fn import_rules(repo: &Path, name: &str) -> Result<Vec<PromptRule>, String> {
+    let mut entries = match fs::read_dir(rules_dir(repo, name)).await {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(format!("Failed to read team rules: {}", err)),
+    };
+    let mut rules = Vec::new();
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read team rules: {}", e))?
+    {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let content = fs::read_to_string(&path)
+            .await
+            .map_err(|e| format!("Failed to read team rule: {}", e))?;
+        match serde_json::from_str::<PromptRule>(&content) {
+            Ok(rule) if is_safe_name(&rule.id) && validate_rule(&rule).is_ok() => rules.push(rule),
+            Ok(_) => println!("[team-sync] Skipped invalid rule {}", path.display()),
+            Err(e) => println!("[team-sync] Skipped {}: {}", path.display(), e),
+        }
+    }
+    rules.sort_by(|a, b| {
+        a.created_at
+            .cmp(&b.created_at)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    Ok(rules)
+}
+
+/// 仅在用户未配置 Git 身份时使用默认身份提交
+async fn commit_identity_args(repo: &Path) -> Result<Vec<String>, String> {
+    if git_succeeds(repo, &["config", "user.email"]).await? {
+        return Ok(Vec::new());
+    }
+    Ok(vec![
+        "-c".to_string(),
+        format!("user.name={}", COMMIT_NAME),
+        "-c".to_string(),
+        format!("user.email={}", COMMIT_EMAIL),
+    ])
+}
+
+async fn merge_remote(
+    repo: &Path,
+    branch: &str,
+    strategy: Option<ConflictStrategy>,
+) -> Result<Vec<String>, String> {
+    let mut args = commit_identity_args(repo).await?;
+    args.extend(["merge", "--no-edit"].map(String::from));
+    match strategy {
+        Some(ConflictStrategy::Local) => args.extend(["-X", "ours"].map(String::from)),
+        Some(ConflictStrategy::Remote) => args.extend(["-X", "theirs"].map(String::from)),
+        None => {}
+    }
+    args.push(format!("origin/{}", branch));
+    if run_git(repo, &args).await?.status.success() {
+        return Ok(Vec::new());
+    }
+
+    let conflicts = git_ok(repo, ["diff", "--name-only", "--diff-filter=U"]).await?;
+    let _ = run_git(repo, ["merge", "--abort"]).await;
+    let conflicts: Vec<String> = conflicts.lines().map(str::to_string).collect();
+    if conflicts.is_empty() {
+        return Err("Failed to merge team sync remote".to_string());
+    }
+    Ok(conflicts)
+}
+
+/// 立即同步：提交本地规则、合并远端、导入合并结果并推送。
+/// 有冲突时中止合并并返回冲突文件，可用 conflict_strategy 指定以哪一方为准重试。
+#[tauri::command]
+pub async fn sync_now(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    conflict_strategy: Option<ConflictStrategy>,
+) -> Result<SyncReport, String> {
+    let config = state
+        .settings
+        .read()
+        .await
+        .team_sync
+        .clone()
+        .ok_or_else(|| "Team sync is not configured".to_string())?;
+    let branch = validate_config(&config)?;
+
+    let _guard = state.team_sync_lock.lock().await;
+    let repo = sync_repo_dir(&app_handle)?;
+    let state_path = sync_state_path(&app_handle)?;
+    let mut sync_state = read_sync_state(&state_path).await?;
+    let mut report = SyncReport::default();
+
+    let remote_exists = prepare_repo(&repo, config.repo_url.trim(), &branch).await?;
+
+    for workspace in &config.workspaces {
+        let local = load_workspace_rules(&app_handle, &workspace.workspace_path).await?;
+        let previous = sync_state
+            .synced
+            .get(&workspace.name)
+            .cloned()
+            .unwrap_or_default();
+        let removed = removed_since_last_sync(&previous, &local);
+        report.exported += export_rules(&repo, &workspace.name, &local, &removed).await?;
+    }
+    git_ok(&repo, ["add", "-A"]).await?;
+    if !git_ok(&repo, ["status", "--porcelain"]).await?.is_empty() {
+        let mut args = commit_identity_args(&repo).await?;
+        args.extend(["commit", "-m", "Sync prompt rules from FlowHub"].map(String::from));
+        git_ok(&repo, &args).await?;
+        report.committed_local = true;
+    }
+
+    if remote_exists {
+        report.conflicts = merge_remote(&repo, &branch, conflict_strategy).await?;
+        if !report.conflicts.is_empty() {
+            return Ok(report);
+        }
+        report.merged_remote = true;
+    }
+
+    for workspace in &config.workspaces {
+        let rules = import_rules(&repo, &workspace.name).await?;
+        report.imported += rules.len();
+        sync_state.synced.insert(
+            workspace.name.clone(),
+            rules.iter().map(|rule| rule.id.clone()).collect(),
+        );
+        replace_workspace_rules(&app_handle, &workspace.workspace_path, rules).await?;
+    }
+    write_sync_state(&state_path, &sync_state).await?;
+
+    if git_succeeds(&repo, &["rev-parse", "--verify", "--quiet", "HEAD"]).await? {
+        git_ok(
+            &repo,
+            ["push", "origin", &format!("HEAD:refs/heads/{}", branch)],
+        )
+        .await?;
+        report.pushed = true;
+        report.head = git_ok(&repo, ["rev-parse", "--short", "HEAD"]).await.ok();
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt_rules::PromptRewrite;
+
+    fn rule(id: &str) -> PromptRule {
+        PromptRule {
+            id: id.to_string(),
+            name: id.to_string(),
+            enabled: true,
+            rewrite: PromptRewrite::RedactSecrets,
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn only_previously_synced_rules_are_deleted() {
+        let previous = vec!["a".to_string(), "b".to_string()];
+        let local = vec![rule("a"), rule("c")];
+        assert_eq!(
+            removed_since_last_sync(&previous, &local),
+            vec!["b".to_string()]
+        );
+        assert!(removed_since_last_sync(&[], &local).is_empty());
+    }
+
+    #[test]
+    fn config_rejects_unsafe_names_and_defaults_branch() {
+        let mut config = TeamSyncConfig {
+            repo_url: "git@example.com:team/prompts.git".to_string(),
+            branch: None,
+            workspaces: vec![TeamSyncWorkspace {
+                name: "frontend".to_string(),
+                workspace_path: "/work/frontend".to_string(),
+            }],
+        };
+        assert_eq!(validate_config(&config), Ok(DEFAULT_BRANCH.to_string()));
+
+        config.workspaces[0].name = "../escape".to_string();
+        assert!(validate_config(&config).is_err());
+
+        config.workspaces[0].name = "frontend".to_string();
+        config.branch = Some("--upload-pack=evil".to_string());
+        assert!(validate_config(&config).is_err());
+    }
+
+    #[tokio::test]
+    async fn exported_rules_round_trip_through_repo_tree() {
+        let repo = std::env::temp_dir().join(format!("team-sync-test-{}", uuid::Uuid::new_v4()));
+        let mut second = rule("b");
+        second.created_at = "2026-01-02T00:00:00Z".to_string();
+        let mut first = rule("a");
+        first.created_at = "2026-01-01T00:00:00Z".to_string();
+
+        let exported = export_rules(&repo, "team", &[second.clone(), first.clone()], &[])
+            .await
+            .expect("export");
+        assert_eq!(exported, 2);
+        assert_eq!(
+            import_rules(&repo, "team").await.expect("import"),
+            vec![first.clone(), second]
+        );
+
+        export_rules(&repo, "team", &[first.clone()], &["b".to_string()])
+            .await
+            .expect("export after delete");
+        assert_eq!(
+            import_rules(&repo, "team").await.expect("import"),
+            vec![first]
+        );
+
+        let _ = fs::remove_dir_all(&repo).await;
+    }
+}
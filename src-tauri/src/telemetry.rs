@@ -0,0 +1,267 @@
+//! 匿名使用统计（默认关闭）：只在命令分发处按命令名累加调用次数，不记录参数、路径或会话内容。
+//! 统计在本地聚合并定期落盘，用户可通过 `get_telemetry_preview` 查看将要上传的完整内容；
+//! 只有开启统计且配置了上报地址时，才会在用户调用 `upload_telemetry` 后上传。
+//! 关闭开关会同时停止采集并清空本地统计。
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::State;
+use tokio::fs;
+use tokio::time::Duration;
+
+use crate::state::AppState;
+use crate::storage::{app_data_dir, storage_env_tag};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(15);
+
+static TELEMETRY_ENABLED: AtomicBool = AtomicBool::new(false);
+static COUNTERS: Lazy<Mutex<TelemetryCounters>> =
+    Lazy::new(|| Mutex::new(TelemetryCounters::default()));
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct TelemetryCounters {
+    /// 本轮统计的起始时间（第一次计数时记录）
+    #[serde(default)]
+    since: Option<String>,
+    /// 命令名 -> 调用次数
+    #[serde(default)]
+    counters: BTreeMap<String, u64>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetryPreview {
+    pub enabled: bool,
+    pub upload_configured: bool,
+    /// 上传时发送的完整内容
+    pub payload: Value,
+}
+
+fn lock_counters() -> std::sync::MutexGuard<'static, TelemetryCounters> {
+    COUNTERS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// 设置变更时调用；关闭时清空本地统计，下次落盘时删除已保存的内容
+pub(crate) fn set_telemetry_enabled(enabled: bool) {
+    TELEMETRY_ENABLED.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        let mut counters = lock_counters();
+        if counters.since.is_some() || !counters.counters.is_empty() {
+            *counters = TelemetryCounters {
+                dirty: true,
+                ..Default::default()
+            };
+        }
+    }
+}
+
+/// 唯一的采集入口，由命令分发处调用
+pub(crate) fn record_feature_use(feature: &str) {
+    if !TELEMETRY_ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut counters = lock_counters();
+    counters
+        .since
+        .get_or_insert_with(|| chrono::Utc::now().to_rfc3339());
+    *counters.counters.entry(feature.to_string()).or_default() += 1;
+    counters.dirty = true;
+}
+
+fn telemetry_payload(counters: &TelemetryCounters) -> Value {
+    json!({
+        "appVersion": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "since": counters.since,
+        "until": chrono::Utc::now().to_rfc3339(),
+        "counters": counters.counters,
+    })
+}
+
+fn telemetry_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(format!("iflow-telemetry-{}.json", storage_env_tag())))
+}
+
+async fn read_counters_from_path(path: &Path) -> Result<TelemetryCounters, String> {
+    match fs::read_to_string(path).await {
+        Ok(content) => {
+            if content.trim().is_empty() {
+                return Ok(TelemetryCounters::default());
+            }
+            serde_json::from_str(&content).map_err(|e| format!("Failed to parse telemetry: {}", e))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(TelemetryCounters::default()),
+        Err(err) => Err(format!("Failed to read telemetry: {}", err)),
+    }
+}
+
+async fn write_counters_to_path(path: &Path, counters: &TelemetryCounters) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create telemetry dir: {}", e))?;
+    }
+    let payload =
+        serde_json::to_vec(counters).map_err(|e| format!("Failed to encode telemetry: {}", e))?;
+    fs::write(path, payload)
+        .await
+        .map_err(|e| format!("Failed to write telemetry: {}", e))
+}
+
+/// 有新计数时写入本地文件
+pub(crate) async fn flush_telemetry(app_handle: &tauri::AppHandle) -> Result<(), String> {
+    let snapshot = {
+        let mut counters = lock_counters();
+        if !counters.dirty {
+            return Ok(());
+        }
+        counters.dirty = false;
+        counters.clone()
+    };
+    write_counters_to_path(&telemetry_path(app_handle)?, &snapshot).await
+}
+
+/// 启动时恢复上次的统计（需在设置加载之后调用），并定期落盘
+pub(crate) async fn init_telemetry(app_handle: &tauri::AppHandle) {
+    if TELEMETRY_ENABLED.load(Ordering::Relaxed) {
+        let loaded = match telemetry_path(app_handle) {
+            Ok(path) => read_counters_from_path(&path).await,
+            Err(e) => Err(e),
+        };
+        match loaded {
+            Ok(stored) => {
+                let mut counters = lock_counters();
+                counters.since = counters.since.take().or(stored.since);
+                for (feature, count) in stored.counters {
+                    *counters.counters.entry(feature).or_default() += count;
+                }
+            }
+            Err(e) => println!("[telemetry] Failed to load telemetry: {}", e),
+        }
+    }
+
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(FLUSH_INTERVAL);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            if let Err(e) = flush_telemetry(&app_handle).await {
+                println!("[telemetry] {}", e);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_telemetry_preview(state: State<'_, AppState>) -> Result<TelemetryPreview, String> {
+    let upload_configured = state
+        .settings
+        .read()
+        .await
+        .telemetry_endpoint
+        .as_deref()
+        .is_some_and(|endpoint| !endpoint.trim().is_empty());
+    let payload = telemetry_payload(&lock_counters());
+    Ok(TelemetryPreview {
+        enabled: TELEMETRY_ENABLED.load(Ordering::Relaxed),
+        upload_configured,
+        payload,
+    })
+}
+
+/// 上传当前统计并清零；未开启统计或未配置上报地址时拒绝
+#[tauri::command]
+pub async fn upload_telemetry(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    if !TELEMETRY_ENABLED.load(Ordering::Relaxed) {
+        return Err("Telemetry is disabled".to_string());
+    }
+    let endpoint = state
+        .settings
+        .read()
+        .await
+        .telemetry_endpoint
+        .clone()
+        .filter(|endpoint| !endpoint.trim().is_empty())
+        .ok_or_else(|| "Telemetry endpoint is not configured".to_string())?;
+    let uploaded = lock_counters().clone();
+    if uploaded.counters.is_empty() {
+        return Ok(());
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(UPLOAD_TIMEOUT)
+        .user_agent(concat!("FlowHub/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let response = client
+        .post(endpoint.trim())
+        .json(&telemetry_payload(&uploaded))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to upload telemetry: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to upload telemetry: HTTP {}",
+            response.status()
+        ));
+    }
+
+    // 只扣除已上传的部分，上传期间新增的计数保留到下一轮
+    {
+        let mut counters = lock_counters();
+        for (feature, count) in &uploaded.counters {
+            if let Some(current) = counters.counters.get_mut(feature) {
+                *current = current.saturating_sub(*count);
+            }
+        }
+        counters.counters.retain(|_, count| *count > 0);
+        if counters.counters.is_empty() {
+            counters.since = None;
+        }
+        counters.dirty = true;
+    }
+    flush_telemetry(&app_handle).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collection_stays_behind_the_switch() {
+        set_telemetry_enabled(false);
+        record_feature_use("send_message");
+        assert!(lock_counters().counters.is_empty());
+
+        set_telemetry_enabled(true);
+        record_feature_use("send_message");
+        record_feature_use("send_message");
+        record_feature_use("connect_iflow");
+        let payload = telemetry_payload(&lock_counters());
+        assert_eq!(payload["counters"]["send_message"], 2);
+        assert_eq!(payload["counters"]["connect_iflow"], 1);
+        assert!(payload["since"].is_string());
+
+        set_telemetry_enabled(false);
+        let counters = lock_counters();
+        assert!(counters.counters.is_empty());
+        assert!(counters.since.is_none());
+    }
+}
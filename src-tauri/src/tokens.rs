@@ -0,0 +1,323 @@
+//! 按模型家族选择分词器计算 token 数并裁剪文本，附件与固定上下文统一在此适配上下文窗口。
+//! OpenAI 系列使用对应的 tiktoken 编码；其余模型暂无内置分词表，以 cl100k_base 近似并标记为非精确。
+//! 输入框的实时估算（token 数与按定价表折算的费用）也在此完成，避免在 webview 中加载分词表。
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tiktoken_rs::CoreBPE;
+
+static O200K: Lazy<Option<CoreBPE>> = Lazy::new(|| tiktoken_rs::o200k_base().ok());
+static CL100K: Lazy<Option<CoreBPE>> = Lazy::new(|| tiktoken_rs::cl100k_base().ok());
+
+/// 截断点落在多字节字符中间时最多回退的 token 数
+const MAX_DECODE_BACKOFF: usize = 4;
+/// 估算时每个文本附件最多读取的字节数，超出部分按字节比例折算
+const MAX_ATTACHMENT_SAMPLE_BYTES: usize = 256 * 1024;
+/// 无法读取尺寸的图片按此计
+const DEFAULT_IMAGE_TOKENS: usize = 1600;
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "webp", "bmp"];
+
+/// 输入价格（美元 / 百万 token），按模型名前缀匹配，更具体的前缀在前
+const INPUT_PRICING_PER_MILLION: &[(&str, f64)] = &[
+    ("gpt-4o-mini", 0.15),
+    ("gpt-4o", 2.5),
+    ("gpt-4.1-mini", 0.4),
+    ("gpt-4.1", 2.0),
+    ("gpt-5-mini", 0.25),
+    ("gpt-5", 1.25),
+    ("o4-mini", 1.1),
+    ("o3", 2.0),
+    ("claude-opus", 15.0),
+    ("claude-sonnet", 3.0),
+    ("claude-haiku", 0.8),
+    ("gemini-2.5-pro", 1.25),
+    ("gemini-2.5-flash", 0.3),
+    ("glm-4", 0.6),
+    ("kimi-k2", 0.6),
+    ("deepseek", 0.27),
+    ("qwen3-coder", 1.0),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenizerKind {
+    O200k,
+    Cl100k,
+}
+
+impl TokenizerKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::O200k => "o200k_base",
+            Self::Cl100k => "cl100k_base",
+        }
+    }
+
+    fn bpe(self) -> Result<&'static CoreBPE, String> {
+        let bpe = match self {
+            Self::O200k => O200K.as_ref(),
+            Self::Cl100k => CL100K.as_ref(),
+        };
+        bpe.ok_or_else(|| format!("Failed to load tokenizer {}", self.name()))
+    }
+}
+
+/// 返回模型对应的分词器及其计数是否精确
+fn tokenizer_for_model(model_id: &str) -> (TokenizerKind, bool) {
+    let model = model_id.trim().to_lowercase();
+    let model = model.rsplit('/').next().unwrap_or(&model);
+    let o200k_prefixes = [
+        "gpt-4o",
+        "gpt-4.1",
+        "gpt-4.5",
+        "gpt-5",
+        "o1",
+        "o3",
+        "o4",
+        "chatgpt-4o",
+    ];
+    if o200k_prefixes
+        .iter()
+        .any(|prefix| model.starts_with(prefix))
+    {
+        return (TokenizerKind::O200k, true);
+    }
+    let cl100k_prefixes = ["gpt-4", "gpt-3.5", "text-embedding-3", "text-embedding-ada"];
+    if cl100k_prefixes
+        .iter()
+        .any(|prefix| model.starts_with(prefix))
+    {
+        return (TokenizerKind::Cl100k, true);
+    }
+    (TokenizerKind::Cl100k, false)
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenTrimResult {
+    pub text: String,
+    pub original_tokens: usize,
+    pub tokens: usize,
+    pub budget: usize,
+    pub truncated: bool,
+    pub tokenizer: String,
+    /// false 表示使用近似分词器
+    pub exact: bool,
+}
+
+/// 保留开头不超过 budget 个 token 的文本
+pub(crate) fn trim_text_to_token_budget(
+    text: &str,
+    model_id: &str,
+    budget: usize,
+) -> Result<TokenTrimResult, String> {
+    let (kind, exact) = tokenizer_for_model(model_id);
+    let bpe = kind.bpe()?;
+    let tokens = bpe.encode_ordinary(text);
+    let original_tokens = tokens.len();
+
+    let (text, kept) = if original_tokens <= budget {
+        (text.to_string(), original_tokens)
+    } else {
+        let mut kept = budget;
+        loop {
+            match bpe.decode(tokens[..kept].to_vec()) {
+                Ok(decoded) => break (decoded, kept),
+                Err(_) if kept > 0 && budget - kept < MAX_DECODE_BACKOFF => kept -= 1,
+                Err(e) => return Err(format!("Failed to decode trimmed tokens: {}", e)),
+            }
+        }
+    };
+
+    Ok(TokenTrimResult {
+        text,
+        original_tokens,
+        tokens: kept,
+        budget,
+        truncated: kept < original_tokens,
+        tokenizer: kind.name().to_string(),
+        exact,
+    })
+}
+
+fn input_price_per_million(model_id: &str) -> Option<f64> {
+    let model = model_id.trim().to_lowercase();
+    let model = model.rsplit('/').next().unwrap_or(&model);
+    INPUT_PRICING_PER_MILLION
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map(|(_, price)| *price)
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptEstimate {
+    pub prompt_tokens: usize,
+    pub attachment_tokens: usize,
+    pub total_tokens: usize,
+    pub tokenizer: String,
+    /// false 表示使用近似分词器或附件按比例折算
+    pub exact: bool,
+    /// 定价表中没有该模型时为空
+    pub estimated_cost_usd: Option<f64>,
+    /// 不存在或无法读取的附件
+    pub skipped_attachments: Vec<String>,
+}
+
+/// 图片按面积折算（约 750 像素 / token，上限 1600）；文本只读取前一段再按字节比例外推
+fn estimate_attachment_tokens(bpe: &CoreBPE, path: &Path) -> Result<(usize, bool), String> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        let tokens = image::image_dimensions(path)
+            .map(|(width, height)| {
+                ((width as usize * height as usize) / 750).clamp(1, DEFAULT_IMAGE_TOKENS)
+            })
+            .unwrap_or(DEFAULT_IMAGE_TOKENS);
+        return Ok((tokens, false));
+    }
+
+    let file = File::open(path).map_err(|e| format!("Failed to read attachment: {}", e))?;
+    let size = file
+        .metadata()
+        .map_err(|e| format!("Failed to read attachment: {}", e))?
+        .len() as usize;
+    let mut sample = Vec::with_capacity(size.min(MAX_ATTACHMENT_SAMPLE_BYTES));
+    file.take(MAX_ATTACHMENT_SAMPLE_BYTES as u64)
+        .read_to_end(&mut sample)
+        .map_err(|e| format!("Failed to read attachment: {}", e))?;
+    let text = String::from_utf8_lossy(&sample);
+    let tokens = bpe.encode_ordinary(&text).len();
+    if sample.is_empty() || size <= sample.len() {
+        return Ok((tokens, true));
+    }
+    Ok((
+        (tokens as f64 * size as f64 / sample.len() as f64).ceil() as usize,
+        false,
+    ))
+}
+
+pub(crate) fn estimate_prompt_tokens(
+    text: &str,
+    attachments: &[String],
+    model_id: &str,
+) -> Result<PromptEstimate, String> {
+    let (kind, exact) = tokenizer_for_model(model_id);
+    let bpe = kind.bpe()?;
+    let prompt_tokens = bpe.encode_ordinary(text).len();
+
+    let mut attachment_tokens = 0;
+    let mut attachments_exact = true;
+    let mut skipped_attachments = Vec::new();
+    for attachment in attachments {
+        match estimate_attachment_tokens(bpe, Path::new(attachment)) {
+            Ok((tokens, exact)) => {
+                attachment_tokens += tokens;
+                attachments_exact &= exact;
+            }
+            Err(_) => skipped_attachments.push(attachment.clone()),
+        }
+    }
+
+    let total_tokens = prompt_tokens + attachment_tokens;
+    Ok(PromptEstimate {
+        prompt_tokens,
+        attachment_tokens,
+        total_tokens,
+        tokenizer: kind.name().to_string(),
+        exact: exact && attachments_exact,
+        estimated_cost_usd: input_price_per_million(model_id)
+            .map(|price| total_tokens as f64 * price / 1_000_000.0),
+        skipped_attachments,
+    })
+}
+
+/// 输入框防抖调用的估算入口
+#[tauri::command]
+pub async fn estimate_prompt(
+    text: String,
+    attachments: Vec<String>,
+    model: String,
+) -> Result<PromptEstimate, String> {
+    tokio::task::spawn_blocking(move || estimate_prompt_tokens(&text, &attachments, &model))
+        .await
+        .map_err(|e| format!("Prompt estimate task failed: {}", e))?
+}
+
+#[tauri::command]
+pub async fn trim_to_token_budget(
+    text: String,
+    model_id: String,
+    budget: usize,
+) -> Result<TokenTrimResult, String> {
+    tokio::task::spawn_blocking(move || trim_text_to_token_budget(&text, &model_id, budget))
+        .await
+        .map_err(|e| format!("Token trim task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_family_selects_tokenizer() {
+        assert_eq!(
+            tokenizer_for_model("gpt-4o-mini"),
+            (TokenizerKind::O200k, true)
+        );
+        assert_eq!(
+            tokenizer_for_model("openai/gpt-4-turbo"),
+            (TokenizerKind::Cl100k, true)
+        );
+        assert_eq!(
+            tokenizer_for_model("glm-4.7"),
+            (TokenizerKind::Cl100k, false)
+        );
+    }
+
+    #[test]
+    fn trims_to_budget_and_reports_counts() {
+        let result =
+            trim_text_to_token_budget("hello world hello world", "gpt-4", 2).expect("trim");
+        assert_eq!(result.text, "hello world");
+        assert_eq!(result.original_tokens, 4);
+        assert_eq!(result.tokens, 2);
+        assert!(result.truncated);
+
+        let untouched = trim_text_to_token_budget("短文本", "gpt-4o", 100).expect("trim");
+        assert_eq!(untouched.text, "短文本");
+        assert!(!untouched.truncated);
+        assert_eq!(untouched.tokenizer, "o200k_base");
+    }
+
+    #[test]
+    fn estimate_counts_attachments_and_prices_known_models() {
+        let dir = std::env::temp_dir().join(format!("flowhub-estimate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, "hello world").expect("write");
+        let missing = dir.join("missing.txt").to_string_lossy().to_string();
+
+        let estimate = estimate_prompt_tokens(
+            "hello world",
+            &[file.to_string_lossy().to_string(), missing.clone()],
+            "gpt-4o-mini",
+        )
+        .expect("estimate");
+        assert_eq!(estimate.prompt_tokens, 2);
+        assert_eq!(estimate.attachment_tokens, 2);
+        assert_eq!(estimate.skipped_attachments, vec![missing]);
+        assert!(estimate.exact);
+        let cost = estimate.estimated_cost_usd.expect("priced");
+        assert!((cost - 4.0 * 0.15 / 1_000_000.0).abs() < 1e-12);
+
+        assert_eq!(input_price_per_million("openai/gpt-4o"), Some(2.5));
+        assert_eq!(input_price_per_million("unknown-model"), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
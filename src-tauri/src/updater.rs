@@ -0,0 +1,504 @@
+//! 应用更新检查：从 GitHub Releases 获取发布列表，按更新通道（稳定版 / 测试版）筛选出比当前版本新的发布，
+//! 提供更新说明与当前平台安装包的下载。安装包按发布附带的 SHA256SUMS 校验，
+//! 只下载不安装：由用户打开下载好的安装包完成更新。
+use std::path::Path;
+use std::time::Duration;
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{Manager, State};
+use tokio::io::AsyncWriteExt;
+
+use crate::state::AppState;
+use crate::storage::app_data_dir;
+
+const RELEASES_URL: &str = "https://api.github.com/repos/chenweil/FlowHub/releases?per_page=30";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+/// 发布中列出各安装包 SHA-256 的资源文件名（`sha256sum` 输出格式）
+const CHECKSUMS_ASSET_NAMES: &[&str] = &["SHA256SUMS", "SHA256SUMS.txt"];
+
+/// 更新通道：稳定版只包含正式发布，测试版同时包含预发布
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String,
+    #[serde(default)]
+    size: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    published_at: Option<String>,
+    html_url: String,
+    #[serde(default)]
+    assets: Vec<GithubAsset>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub url: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseInfo {
+    pub version: String,
+    pub name: String,
+    /// 发布说明（Markdown）
+    pub notes: String,
+    pub prerelease: bool,
+    pub published_at: Option<String>,
+    pub url: String,
+    /// 当前平台的安装包；没有匹配的资源时为空
+    pub asset: Option<ReleaseAsset>,
+    #[serde(skip)]
+    checksums_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCheck {
+    pub current_version: String,
+    pub channel: UpdateChannel,
+    pub update_available: bool,
+    pub latest: Option<ReleaseInfo>,
+    pub checked_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadedUpdate {
+    pub version: String,
+    pub path: String,
+    pub size_bytes: u64,
+    /// 已与发布的 SHA256SUMS 核对一致的摘要
+    pub sha256: String,
+}
+
+fn parse_version(tag: &str) -> Option<Version> {
+    let tag = tag.trim();
+    Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()
+}
+
+/// 当前平台安装包的文件后缀，按优先级排列
+fn platform_extensions() -> &'static [&'static str] {
+    match std::env::consts::OS {
+        "macos" => &[".dmg", ".app.tar.gz"],
+        "windows" => &[".msi", "-setup.exe", ".exe"],
+        _ => &[".appimage", ".deb", ".rpm"],
+    }
+}
+
+fn arch_matches(name: &str) -> bool {
+    let arm = ["aarch64", "arm64"].iter().any(|tag| name.contains(tag));
+    let x64 = ["x86_64", "x64", "amd64"]
+        .iter()
+        .any(|tag| name.contains(tag));
+    match std::env::consts::ARCH {
+        "aarch64" => arm || !x64,
+        _ => x64 || !arm,
+    }
+}
+
+fn pick_asset(assets: &[GithubAsset], extensions: &[&str]) -> Option<ReleaseAsset> {
+    extensions.iter().find_map(|extension| {
+        assets
+            .iter()
+            .filter(|asset| asset.name.to_lowercase().ends_with(extension))
+            .find(|asset| arch_matches(&asset.name.to_lowercase()))
+            .map(|asset| ReleaseAsset {
+                name: asset.name.clone(),
+                url: asset.browser_download_url.clone(),
+                size: asset.size,
+            })
+    })
+}
+
+fn checksums_url(assets: &[GithubAsset]) -> Option<String> {
+    assets
+        .iter()
+        .find(|asset| CHECKSUMS_ASSET_NAMES.contains(&asset.name.as_str()))
+        .map(|asset| asset.browser_download_url.clone())
+}
+
+/// 从 `sha256sum` 格式（`<摘要>  <文件名>`，二进制模式为 `<摘要> *<文件名>`）中取出指定文件的摘要
+fn expected_checksum(sums: &str, file_name: &str) -> Option<String> {
+    sums.lines().find_map(|line| {
+        let (digest, name) = line.trim().split_once(char::is_whitespace)?;
+        let name = name.trim_start();
+        let name = name.strip_prefix('*').unwrap_or(name);
+        (name == file_name && digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+            .then(|| digest.to_ascii_lowercase())
+    })
+}
+
+/// 按通道筛选比当前版本新的发布，版本从新到旧排列
+fn newer_releases(
+    releases: &[GithubRelease],
+    current: &Version,
+    channel: UpdateChannel,
+    extensions: &[&str],
+) -> Vec<ReleaseInfo> {
+    let mut newer: Vec<(Version, ReleaseInfo)> = releases
+        .iter()
+        .filter(|release| !release.draft)
+        .filter(|release| channel == UpdateChannel::Beta || !release.prerelease)
+        .filter_map(|release| {
+            let version = parse_version(&release.tag_name)?;
+            if channel == UpdateChannel::Stable && !version.pre.is_empty() {
+                return None;
+            }
+            (version > *current).then(|| {
+                let info = ReleaseInfo {
+                    version: version.to_string(),
+                    name: release
+                        .name
+                        .clone()
+                        .filter(|name| !name.trim().is_empty())
+                        .unwrap_or_else(|| release.tag_name.clone()),
+                    notes: release.body.clone().unwrap_or_default(),
+                    prerelease: release.prerelease || !version.pre.is_empty(),
+                    published_at: release.published_at.clone(),
+                    url: release.html_url.clone(),
+                    asset: pick_asset(&release.assets, extensions),
+                    checksums_url: checksums_url(&release.assets),
+                };
+                (version, info)
+            })
+        })
+        .collect();
+    newer.sort_by(|a, b| b.0.cmp(&a.0));
+    newer.into_iter().map(|(_, info)| info).collect()
+}
+
+fn current_version(app_handle: &tauri::AppHandle) -> Result<Version, String> {
+    let version = app_handle.package_info().version.to_string();
+    Version::parse(&version).map_err(|e| format!("Failed to parse app version {}: {}", version, e))
+}
+
+async fn resolve_channel(state: &AppState, channel: Option<UpdateChannel>) -> UpdateChannel {
+    match channel {
+        Some(channel) => channel,
+        None => state.settings.read().await.update_channel,
+    }
+}
+
+async fn fetch_releases() -> Result<Vec<GithubRelease>, String> {
+    let response = http_client(REQUEST_TIMEOUT)?
+        .get(RELEASES_URL)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch releases: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to fetch releases: HTTP {}",
+            response.status()
+        ));
+    }
+    response
+        .json::<Vec<GithubRelease>>()
+        .await
+        .map_err(|e| format!("Failed to parse releases: {}", e))
+}
+
+async fn load_newer_releases(
+    app_handle: &tauri::AppHandle,
+    channel: UpdateChannel,
+) -> Result<Vec<ReleaseInfo>, String> {
+    let current = current_version(app_handle)?;
+    let releases = fetch_releases().await?;
+    Ok(newer_releases(
+        &releases,
+        &current,
+        channel,
+        platform_extensions(),
+    ))
+}
+
+/// 检查更新；未指定通道时使用设置中的更新通道
+#[tauri::command]
+pub async fn check_for_updates(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    channel: Option<UpdateChannel>,
+) -> Result<UpdateCheck, String> {
+    let channel = resolve_channel(&state, channel).await;
+    let latest = load_newer_releases(&app_handle, channel)
+        .await?
+        .into_iter()
+        .next();
+    Ok(UpdateCheck {
+        current_version: app_handle.package_info().version.to_string(),
+        channel,
+        update_available: latest.is_some(),
+        latest,
+        checked_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// 当前版本之后的全部更新说明，版本从新到旧
+#[tauri::command]
+pub async fn fetch_release_notes(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    channel: Option<UpdateChannel>,
+) -> Result<Vec<ReleaseInfo>, String> {
+    let channel = resolve_channel(&state, channel).await;
+    load_newer_releases(&app_handle, channel).await
+}
+
+fn http_client(timeout: Duration) -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .user_agent(concat!("FlowHub/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+async fn fetch_expected_checksum(
+    client: &reqwest::Client,
+    checksums_url: &str,
+    file_name: &str,
+) -> Result<String, String> {
+    let response = client
+        .get(checksums_url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch checksums: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to fetch checksums: HTTP {}",
+            response.status()
+        ));
+    }
+    let sums = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to fetch checksums: {}", e))?;
+    expected_checksum(&sums, file_name)
+        .ok_or_else(|| format!("No SHA-256 checksum published for {}", file_name))
+}
+
+/// 边下载边写入临时文件并计算摘要，返回 (字节数, 摘要)
+async fn stream_to_file(
+    mut response: reqwest::Response,
+    path: &Path,
+) -> Result<(u64, String), String> {
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| format!("Failed to write update: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut size = 0_u64;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to download update: {}", e))?
+    {
+        hasher.update(&chunk);
+        size += chunk.len() as u64;
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to write update: {}", e))?;
+    }
+    file.flush()
+        .await
+        .map_err(|e| format!("Failed to write update: {}", e))?;
+    Ok((size, format!("{:x}", hasher.finalize())))
+}
+
+/// 下载所选通道最新版本的当前平台安装包到应用数据目录，并按发布的 SHA256SUMS 校验。
+/// 不会自动安装：返回文件路径供用户打开安装
+#[tauri::command]
+pub async fn download_update(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    channel: Option<UpdateChannel>,
+) -> Result<DownloadedUpdate, String> {
+    let channel = resolve_channel(&state, channel).await;
+    let release = load_newer_releases(&app_handle, channel)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| "Already up to date".to_string())?;
+    let asset = release
+        .asset
+        .ok_or_else(|| format!("No installer for this platform in {}", release.version))?;
+    let checksums_url = release
+        .checksums_url
+        .ok_or_else(|| format!("No SHA256SUMS published in {}", release.version))?;
+    let file_name = Path::new(&asset.name)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| format!("Invalid asset name: {}", asset.name))?;
+
+    let client = http_client(DOWNLOAD_TIMEOUT)?;
+    let expected = fetch_expected_checksum(&client, &checksums_url, &file_name).await?;
+    let response = client
+        .get(&asset.url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download update: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to download update: HTTP {}",
+            response.status()
+        ));
+    }
+
+    let dir = app_data_dir(&app_handle)?.join("updates");
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create update dir: {}", e))?;
+    let path = dir.join(&file_name);
+    let partial_path = dir.join(format!("{}.part", file_name));
+
+    let verified = match stream_to_file(response, &partial_path).await {
+        Ok((size, _)) if asset.size > 0 && size != asset.size => Err(format!(
+            "Failed to download update: expected {} bytes, got {}",
+            asset.size, size
+        )),
+        Ok((_, digest)) if digest != expected => Err(format!(
+            "Update checksum mismatch for {}: expected {}, got {}",
+            file_name, expected, digest
+        )),
+        result => result,
+    };
+    let (size_bytes, sha256) = match verified {
+        Ok(verified) => verified,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            return Err(e);
+        }
+    };
+    tokio::fs::rename(&partial_path, &path)
+        .await
+        .map_err(|e| format!("Failed to write update: {}", e))?;
+
+    Ok(DownloadedUpdate {
+        version: release.version,
+        path: path.to_string_lossy().to_string(),
+        size_bytes,
+        sha256,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag: &str, prerelease: bool, assets: &[&str]) -> GithubRelease {
+        GithubRelease {
+            tag_name: tag.to_string(),
+            name: None,
+            body: Some(format!("notes for {}", tag)),
+            draft: false,
+            prerelease,
+            published_at: None,
+            html_url: format!("https://example.com/{}", tag),
+            assets: assets
+                .iter()
+                .map(|name| GithubAsset {
+                    name: name.to_string(),
+                    browser_download_url: format!("https://example.com/dl/{}", name),
+                    size: 1,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn channel_selects_newer_releases_newest_first() {
+        let releases = vec![
+            release("v0.3.9", false, &[]),
+            release("v0.4.1", false, &[]),
+            release("v0.5.0-beta.1", true, &[]),
+            release("v0.4.2", false, &[]),
+            release("nightly", true, &[]),
+            GithubRelease {
+                draft: true,
+                ..release("v0.6.0", false, &[])
+            },
+        ];
+        let current = Version::parse("0.4.0").unwrap();
+
+        let stable = newer_releases(&releases, &current, UpdateChannel::Stable, &[]);
+        let versions: Vec<&str> = stable.iter().map(|info| info.version.as_str()).collect();
+        assert_eq!(versions, vec!["0.4.2", "0.4.1"]);
+        assert_eq!(stable[0].name, "v0.4.2");
+        assert_eq!(stable[0].notes, "notes for v0.4.2");
+
+        let beta = newer_releases(&releases, &current, UpdateChannel::Beta, &[]);
+        assert_eq!(beta[0].version, "0.5.0-beta.1");
+        assert!(beta[0].prerelease);
+        assert_eq!(beta.len(), 3);
+    }
+
+    #[test]
+    fn installer_asset_follows_extension_priority() {
+        let assets = release(
+            "v0.4.1",
+            false,
+            &[
+                "FlowHub_0.4.1_amd64.deb",
+                "FlowHub_0.4.1_amd64.AppImage",
+                "FlowHub_0.4.1_aarch64.AppImage",
+                "latest.json",
+            ],
+        )
+        .assets;
+        let asset = pick_asset(&assets, &[".appimage", ".deb"]).expect("asset");
+        let expected = if std::env::consts::ARCH == "aarch64" {
+            "FlowHub_0.4.1_aarch64.AppImage"
+        } else {
+            "FlowHub_0.4.1_amd64.AppImage"
+        };
+        assert_eq!(asset.name, expected);
+        assert!(pick_asset(&assets, &[".msi"]).is_none());
+    }
+
+    #[test]
+    fn checksum_lookup_matches_exact_file_name() {
+        let digest = "a".repeat(64);
+        let sums = format!(
+            "{}  FlowHub_0.4.1_amd64.AppImage.sig\n{} *FlowHub_0.4.1_amd64.AppImage\nnot-a-digest  FlowHub.deb\n",
+            "b".repeat(64),
+            digest.to_uppercase()
+        );
+        assert_eq!(
+            expected_checksum(&sums, "FlowHub_0.4.1_amd64.AppImage"),
+            Some(digest)
+        );
+        assert_eq!(expected_checksum(&sums, "FlowHub.deb"), None);
+        assert_eq!(expected_checksum(&sums, "FlowHub_0.4.1.dmg"), None);
+
+        let assets = release("v0.4.1", false, &["FlowHub.dmg", "SHA256SUMS"]).assets;
+        assert_eq!(
+            checksums_url(&assets).as_deref(),
+            Some("https://example.com/dl/SHA256SUMS")
+        );
+    }
+}
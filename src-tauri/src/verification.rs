@@ -0,0 +1,172 @@
+//! 回答复核：任务正常结束后，在临时会话中（可指定另一模型）对原问题与回答做一次批判性检查，
+//! 结论推送到聊天区并写入对应的任务记录。
+use serde_json::json;
+use tauri::Manager;
+
+use crate::agents::oneshot::{run_oneshot_prompt, OneshotToolPolicy};
+use crate::app_lock::EmitUnlessLocked;
+use crate::settings::{message_style, SystemMarker};
+use crate::state::AppState;
+use crate::tasks::{update_task_record, TaskVerification, VerificationVerdict};
+
+const VERIFICATION_TIMEOUT_SECS: u64 = 180;
+const MAX_VERIFIED_ANSWER_CHARS: usize = 12_000;
+
+fn build_verification_prompt(prompt: &str, answer: &str) -> String {
+    let answer = if answer.chars().count() > MAX_VERIFIED_ANSWER_CHARS {
+        format!(
+            "{}…（已截断）",
+            answer
+                .chars()
+                .take(MAX_VERIFIED_ANSWER_CHARS)
+                .collect::<String>()
+        )
+    } else {
+        answer.to_string()
+    };
+    format!(
+        "请作为审阅者复核下面这次问答，不要修改任何文件，也不要调用工具。\n\
+         第一行必须是 `VERDICT: AGREE` 或 `VERDICT: DISAGREE`，\
+         之后简要说明理由；若不同意，请指出具体错误或遗漏。\n\n\
+         【原始问题】\n{}\n\n【待复核回答】\n{}",
+        prompt.trim(),
+        answer.trim()
+    )
+}
+
+/// 解析复核回复首个非空行中的结论
+fn parse_verdict(critique: &str) -> VerificationVerdict {
+    let Some(first_line) = critique
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+    else {
+        return VerificationVerdict::Unknown;
+    };
+    let normalized = first_line
+        .trim_matches(|c: char| c == '*' || c == '`' || c == '#')
+        .trim()
+        .to_uppercase();
+    let Some(verdict) = normalized.strip_prefix("VERDICT") else {
+        return VerificationVerdict::Unknown;
+    };
+    let verdict = verdict.trim_start_matches([':', '：', ' ']).trim();
+    if verdict.starts_with("DISAGREE") {
+        VerificationVerdict::Disagree
+    } else if verdict.starts_with("AGREE") {
+        VerificationVerdict::Agree
+    } else {
+        VerificationVerdict::Unknown
+    }
+}
+
+/// 在后台发起复核，不阻塞当前会话
+pub(crate) fn spawn_verification(
+    app_handle: &tauri::AppHandle,
+    agent_id: &str,
+    ws_url: &str,
+    workspace_path: &str,
+    task_id: &str,
+    prompt: &str,
+    answer: &str,
+) {
+    let app_handle = app_handle.clone();
+    let agent_id = agent_id.to_string();
+    let ws_url = ws_url.to_string();
+    let workspace_path = workspace_path.to_string();
+    let task_id = task_id.to_string();
+    let verification_prompt = build_verification_prompt(prompt, answer);
+    tokio::spawn(async move {
+        let model = app_handle
+            .state::<AppState>()
+            .settings
+            .read()
+            .await
+            .verification_model
+            .clone()
+            .filter(|model| !model.trim().is_empty());
+        let critique = match run_oneshot_prompt(
+            &ws_url,
+            &workspace_path,
+            model.as_deref(),
+            &verification_prompt,
+            OneshotToolPolicy::ReadOnly,
+            VERIFICATION_TIMEOUT_SECS,
+        )
+        .await
+        {
+            Ok(critique) => critique.trim().to_string(),
+            Err(e) => {
+                println!("[verification] Failed to verify task {}: {}", task_id, e);
+                return;
+            }
+        };
+
+        let verdict = parse_verdict(&critique);
+        let (marker, title) = match verdict {
+            VerificationVerdict::Agree => (SystemMarker::Success, "复核通过"),
+            VerificationVerdict::Disagree => (SystemMarker::Warning, "复核存在异议"),
+            VerificationVerdict::Unknown => (SystemMarker::Warning, "复核结论不明确"),
+        };
+        let _ = app_handle.emit_unless_locked(
+            "stream-message",
+            json!({
+                "agentId": agent_id,
+                "content": message_style(&app_handle)
+                    .await
+                    .decorate(marker, &format!("{}\n{}", title, critique)),
+                "type": "verification",
+                "verdict": verdict,
+                "taskId": task_id,
+            }),
+        );
+
+        let verification = TaskVerification {
+            verdict,
+            critique,
+            model,
+            verified_at: chrono::Utc::now().to_rfc3339(),
+        };
+        if let Err(e) = update_task_record(&app_handle, &task_id, |record| {
+            record.verification = Some(verification);
+        })
+        .await
+        {
+            println!(
+                "[verification] Failed to store verification for {}: {}",
+                task_id, e
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verdict_parsing_accepts_common_formats() {
+        assert_eq!(
+            parse_verdict("VERDICT: AGREE\n看起来正确"),
+            VerificationVerdict::Agree
+        );
+        assert_eq!(
+            parse_verdict("\n**Verdict: disagree**\n遗漏了边界"),
+            VerificationVerdict::Disagree
+        );
+        assert_eq!(parse_verdict("VERDICT：AGREE"), VerificationVerdict::Agree);
+        assert_eq!(
+            parse_verdict("I think it is fine"),
+            VerificationVerdict::Unknown
+        );
+        assert_eq!(parse_verdict(""), VerificationVerdict::Unknown);
+    }
+
+    #[test]
+    fn verification_prompt_contains_question_and_answer() {
+        let prompt = build_verification_prompt("1+1=?", "2");
+        assert!(prompt.contains("VERDICT: AGREE"));
+        assert!(prompt.contains("【原始问题】\n1+1=?"));
+        assert!(prompt.ends_with("【待复核回答】\n2"));
+    }
+}
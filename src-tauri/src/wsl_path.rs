@@ -0,0 +1,155 @@
+//! WSL 路径互转：`\\wsl$\<发行版>\...`（或 `\\wsl.localhost\...`）与发行版内的 Linux 路径，
+//! `/mnt/<盘符>/...` 与 `<盘符>:\...`。在 Windows 上驱动 WSL 内的 iFlow 时，Agent 报告的是
+//! Linux 路径，而工作区、历史记录 cwd 与文件读取可能使用另一种写法；各模块在比较或解析路径前
+//! 用这里的函数换算。非 WSL 形式的路径原样保留。
+
+fn forward_slashes(path: &str) -> String {
+    path.trim().replace('\\', "/")
+}
+
+/// `\\wsl$\Ubuntu\home\me` -> (`\\wsl$\Ubuntu`, `/home/me`)
+fn parse_wsl_unc(path: &str) -> Option<(String, String)> {
+    let normalized = forward_slashes(path);
+    let lower = normalized.to_ascii_lowercase();
+    let host = ["//wsl$/", "//wsl.localhost/"]
+        .into_iter()
+        .find(|prefix| lower.starts_with(prefix))?;
+    let rest = &normalized[host.len()..];
+    let (distro, linux) = rest.split_once('/').unwrap_or((rest, ""));
+    if distro.is_empty() {
+        return None;
+    }
+    let linux = format!("/{}", linux.trim_end_matches('/'));
+    let share = format!(r"\\{}\{}", &normalized[2..host.len() - 1], distro);
+    Some((share, linux))
+}
+
+/// `/mnt/c/Users/me` -> `C:/Users/me`
+pub(crate) fn wsl_mount_to_windows(path: &str) -> Option<String> {
+    let normalized = forward_slashes(path);
+    let rest = normalized.strip_prefix("/mnt/")?;
+    let (drive, tail) = rest.split_once('/').unwrap_or((rest, ""));
+    let mut chars = drive.chars();
+    let letter = chars.next().filter(char::is_ascii_alphabetic)?;
+    if chars.next().is_some() {
+        return None;
+    }
+    Some(format!(
+        "{}:/{}",
+        letter.to_ascii_uppercase(),
+        tail.trim_end_matches('/')
+    ))
+}
+
+/// `C:\Users\me` -> `/mnt/c/Users/me`
+fn windows_to_wsl_mount(path: &str) -> Option<String> {
+    let normalized = forward_slashes(path);
+    let bytes = normalized.as_bytes();
+    if bytes.len() < 2 || !bytes[0].is_ascii_alphabetic() || bytes[1] != b':' {
+        return None;
+    }
+    if bytes.len() > 2 && bytes[2] != b'/' {
+        return None;
+    }
+    Some(format!(
+        "/mnt/{}{}",
+        normalized[..1].to_ascii_lowercase(),
+        normalized[2..].trim_end_matches('/')
+    ))
+}
+
+/// 同一路径在 Windows 与 WSL 两侧的其它写法（不含自身），用于工作区与历史 cwd 的匹配
+pub(crate) fn wsl_path_aliases(path: &str) -> Vec<String> {
+    let mut aliases = Vec::new();
+    if let Some((_, linux)) = parse_wsl_unc(path) {
+        aliases.extend(wsl_mount_to_windows(&linux));
+        aliases.push(linux);
+    } else if let Some(windows) = wsl_mount_to_windows(path) {
+        aliases.push(windows);
+    } else if let Some(mount) = windows_to_wsl_mount(path) {
+        aliases.push(mount);
+    }
+    aliases
+}
+
+/// 把 Agent 报告的 Linux 绝对路径换算为工作区一侧可访问的路径；无需换算时返回 None。
+/// 工作区是 `\\wsl$\<发行版>\...` 时，`/mnt/<盘符>/...` 换算为盘符路径，其余挂到同一发行版下；
+/// 工作区是盘符路径时只换算 `/mnt/<盘符>/...`。
+pub(crate) fn to_workspace_host_path(workspace_path: &str, path: &str) -> Option<String> {
+    let path = path.trim();
+    if !path.starts_with('/') || path.starts_with("//") {
+        return None;
+    }
+    let to_windows = |linux: &str| wsl_mount_to_windows(linux).map(|p| p.replace('/', r"\"));
+    if let Some((share, _)) = parse_wsl_unc(workspace_path) {
+        return to_windows(path).or_else(|| {
+            Some(format!(
+                "{}{}",
+                share,
+                path.trim_end_matches('/').replace('/', r"\")
+            ))
+        });
+    }
+    windows_to_wsl_mount(workspace_path)?;
+    to_windows(path)
+}
+
+/// 启动 Agent 进程的工作目录：Windows 上把 `/mnt/<盘符>/...` 形式的工作区换算为盘符路径
+pub(crate) fn host_spawn_dir(workspace_path: &str) -> String {
+    if cfg!(windows) {
+        if let Some(windows) = wsl_mount_to_windows(workspace_path) {
+            return windows.replace('/', r"\");
+        }
+    }
+    workspace_path.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_translate_between_unc_mount_and_drive_paths() {
+        assert_eq!(
+            wsl_path_aliases(r"\\wsl$\Ubuntu\home\me\repo\"),
+            vec!["/home/me/repo".to_string()]
+        );
+        assert_eq!(
+            wsl_path_aliases(r"\\wsl.localhost\Ubuntu\mnt\d\work"),
+            vec!["D:/work".to_string(), "/mnt/d/work".to_string()]
+        );
+        assert_eq!(wsl_path_aliases("/mnt/c/Users/me"), vec!["C:/Users/me"]);
+        assert_eq!(wsl_path_aliases(r"C:\Users\me"), vec!["/mnt/c/Users/me"]);
+        assert!(wsl_path_aliases("/home/me/repo").is_empty());
+        assert!(wsl_path_aliases("/mnt/data/repo").is_empty());
+    }
+
+    #[test]
+    fn agent_paths_map_onto_the_workspace_side() {
+        assert_eq!(
+            to_workspace_host_path(r"\\wsl$\Ubuntu\home\me\repo", "/home/me/repo/out.html")
+                .as_deref(),
+            Some(r"\\wsl$\Ubuntu\home\me\repo\out.html")
+        );
+        assert_eq!(
+            to_workspace_host_path(r"\\wsl$\Ubuntu\home\me\repo", "/mnt/c/tmp/a.html").as_deref(),
+            Some(r"C:\tmp\a.html")
+        );
+        assert_eq!(
+            to_workspace_host_path(r"C:\work\repo", "/mnt/c/work/repo/a.html").as_deref(),
+            Some(r"C:\work\repo\a.html")
+        );
+        assert_eq!(
+            to_workspace_host_path(r"C:\work\repo", "/home/me/a.html"),
+            None
+        );
+        assert_eq!(
+            to_workspace_host_path("/home/me/repo", "/home/me/repo/a.html"),
+            None
+        );
+        assert_eq!(
+            to_workspace_host_path(r"\\wsl$\Ubuntu\home", "out.html"),
+            None
+        );
+    }
+}
@@ -1,6 +1,6 @@
 // src/features/agents/actions.ts — Agent CRUD operations
-import { connectIflow, disconnectAgent } from '../../services/tauri';
-import { shortAgentId, getWorkspaceName } from '../../lib/utils';
+import { connectIflow, disconnectAgent, watchIflowHistory } from '../../services/tauri';
+import { shortAgentId, getWorkspaceName, isAgentOnline, isRemoteAgent } from '../../lib/utils';
 import { escapeHtml } from '../../lib/html';
 import { showConfirmDialog } from '../../dom';
 import type { Agent } from '../../types';
@@ -23,7 +23,7 @@ import {
 } from '../sessions';
 import { normalizeConnectionErrorMessage, markLastConnectedAgent, removeLastConnectedAgentIfMatches, showLoading, hideLoading, showSuccess, showError } from './utils';
 import { updateAgentStatusUI, updateConnectionStatus, updateCurrentAgentModelUI, updateCurrentAgentThinkUI, closeCurrentAgentModelMenu } from './ui';
-import { refreshAgentGitChanges, resetGitChangesForAgent } from './git';
+import { offerGitignoreEntries, refreshAgentGitChanges, resetGitChangesForAgent } from './git';
 import { loadAgentModelOptions } from './model';
 
 // ── Add Agent ─────────────────────────────────────────────────────────────────
@@ -93,7 +93,7 @@ export function selectAgent(agentId: string) {
   currentAgentNameEl.textContent = agent.name;
   updateAgentStatusUI(agent.status);
 
-  const isConnected = agent.status === 'connected';
+  const isConnected = isAgentOnline(agent.status);
   clearChatBtnEl.textContent = '清空当前会话';
 
   ensureAgentHasSessions(agentId);
@@ -132,9 +132,17 @@ export function selectAgent(agentId: string) {
   void refreshAgentGitChanges(agent.id);
 
   if (isConnected) {
-    void import('../sessions').then(({ syncIflowHistorySessions }) => {
-      syncIflowHistorySessions(agent);
-    });
+    // 远程 Agent 的历史记录与工作区在远程主机上，本机无从同步
+    if (!isRemoteAgent(agent)) {
+      void import('../sessions').then(({ syncIflowHistorySessions }) => {
+        syncIflowHistorySessions(agent);
+      });
+      // 历史目录变化由 history-changed 事件驱动刷新；目录尚不存在时忽略
+      watchIflowHistory(agent.workspacePath).catch((error) => {
+        console.warn('[history] watch failed', error);
+      });
+      void offerGitignoreEntries(agent.workspacePath);
+    }
     void loadAgentModelOptions(agent).then(() => {
       if (state.currentAgentId === agent.id) {
         updateCurrentAgentModelUI();
@@ -162,7 +170,7 @@ export async function deleteAgent(agentId: string) {
 
   const deletingCurrentAgent = state.currentAgentId === agentId;
 
-  if (agent.status === 'connected') {
+  if (isAgentOnline(agent.status)) {
     void disconnectAgent(agentId).catch((e) => {
       console.error('断开连接失败:', e);
     });
@@ -379,7 +387,7 @@ export function renderAgentList() {
         <div class="status-indicator ${agent.status}"></div>
         <button class="btn-edit" data-action="rename" data-agent-id="${agent.id}" title="编辑名称">✎</button>
         ${
-          agent.status !== 'connected'
+          !isAgentOnline(agent.status)
             ? `<button class="btn-reconnect" data-action="reconnect" data-agent-id="${agent.id}" title="重连 Agent">↻</button>`
             : ''
         }
@@ -1,5 +1,6 @@
 // src/features/agents/commands.ts — Local command handlers
 import type { Message } from '../../types';
+import { importSessionJsonl } from '../../services/tauri';
 import { getMessagesForSession, commitSessionMessages } from '../sessions';
 import {
   AUTO_RECONNECT_MODE_DEFAULT,
@@ -68,3 +69,51 @@ export async function handleLocalAgentCommand(
   commitSessionMessages(sessionId, sessionMessages);
   return true;
 }
+
+// ── Session Import Command ────────────────────────────────────────────────────
+
+const IMPORT_COMMAND_PATTERN = /^\/import(?:\s+(.*))?$/;
+
+/** `/import <path>`：把 iFlow / Claude Code 的 JSONL 会话记录导入到当前 Agent 名下 */
+export async function handleLocalImportCommand(
+  content: string,
+  agentId: string | null,
+  sessionId: string,
+): Promise<boolean> {
+  const match = content.trim().match(IMPORT_COMMAND_PATTERN);
+  if (!match) {
+    return false;
+  }
+
+  const sessionMessages = getMessagesForSession(sessionId);
+  sessionMessages.push({
+    id: `msg-${Date.now()}-import-user`,
+    role: 'user',
+    content,
+    timestamp: new Date(),
+  });
+  commitSessionMessages(sessionId, sessionMessages);
+
+  const path = (match[1] || '').trim().replace(/^(["'])(.*)\1$/, '$2');
+  let reply: string;
+  if (!path || !agentId) {
+    reply = '⚠ 用法：/import <会话记录 .jsonl 文件路径>（需先选择 Agent）';
+  } else {
+    try {
+      // 导入的会话由 sessions-stored 事件合并进会话列表
+      const result = await importSessionJsonl(path, agentId);
+      reply = `✅ 已导入 ${result.importedMessages} 条消息到会话「${result.session.title}」`;
+    } catch (error) {
+      reply = `⚠ 导入失败：${String(error)}`;
+    }
+  }
+  const replyMessages = getMessagesForSession(sessionId);
+  replyMessages.push({
+    id: `msg-${Date.now()}-import-result`,
+    role: 'system',
+    content: reply,
+    timestamp: new Date(),
+  });
+  commitSessionMessages(sessionId, replyMessages);
+  return true;
+}
@@ -1,8 +1,36 @@
 // src/features/agents/git.ts — Git changes management for agents
-import { listGitChanges } from '../../services/tauri';
+import { ensureGitignoreEntries, listGitChanges } from '../../services/tauri';
 import type { GitFileChange } from '../../types';
 import { state } from '../../store';
-import { gitChangesPanelEl } from '../../dom';
+import { gitChangesPanelEl, showConfirmDialog } from '../../dom';
+import { isRemoteAgent } from '../../lib/utils';
+
+// 每个工作区在本次运行中只询问一次
+const gitignoreOfferedWorkspaces = new Set<string>();
+
+/** 工作区内有未被忽略的 FlowHub 目录时，询问是否追加到 .gitignore */
+export async function offerGitignoreEntries(workspacePath: string): Promise<void> {
+  if (gitignoreOfferedWorkspaces.has(workspacePath)) {
+    return;
+  }
+  gitignoreOfferedWorkspaces.add(workspacePath);
+  try {
+    const preview = await ensureGitignoreEntries(workspacePath, true);
+    if (preview.added.length === 0) {
+      return;
+    }
+    const confirmed = await showConfirmDialog(
+      '忽略 FlowHub 目录',
+      `工作区中的 ${preview.added.join('、')} 由 FlowHub 生成，是否追加到 .gitignore 以免误提交？`
+    );
+    if (confirmed) {
+      await ensureGitignoreEntries(workspacePath, false);
+    }
+  } catch (error) {
+    // 非 git 仓库时无需处理
+    console.warn('[gitignore] skipped', error);
+  }
+}
 
 export function showGitChangesForAgent(agentId: string, forceOpen = false): void {
   if (agentId !== state.currentAgentId) {
@@ -35,6 +63,11 @@ export async function refreshAgentGitChanges(agentId: string): Promise<void> {
     console.warn(`refreshAgentGitChanges: agent not found (${agentId})`);
     return;
   }
+  // 远程工作区的 git 状态无法在本机读取
+  if (isRemoteAgent(agent)) {
+    resetGitChangesForAgent(agentId);
+    return;
+  }
 
   state.gitChangesLoadingByAgent[agentId] = true;
   state.gitChangesErrorByAgent[agentId] = '';
@@ -104,7 +104,7 @@ export {
 } from './reconnect';
 
 // Commands
-export { handleLocalAgentCommand } from './commands';
+export { handleLocalAgentCommand, handleLocalImportCommand } from './commands';
 
 // Types re-export
 export type { Agent, ModelOption, ToolCall, RegistryCommand, RegistryMcpServer } from '../../types';
@@ -2,6 +2,7 @@
 import { listAvailableModels, switchAgentModel as tauriSwitchAgentModel, toggleAgentThink as tauriToggleAgentThink } from '../../services/tauri';
 import type { Agent, Message, ModelOption, ParsedModelSlashCommand } from '../../types';
 import { state } from '../../store';
+import { isAgentOnline } from '../../lib/utils';
 import { readErrorMessage, readTextFromUnknown, isThinkUnsupportedError, getThinkSupportByModel, setThinkSupportByModel, showError, showSuccess } from './utils';
 import { renderAgentList, saveAgents } from './actions';
 import { updateCurrentAgentModelUI, updateCurrentAgentThinkUI, updateAgentStatusUI, renderCurrentAgentModelMenu, closeCurrentAgentModelMenu, currentAgentModelLabel } from './ui';
@@ -220,7 +221,7 @@ export async function toggleCurrentAgentModelMenu() {
   const agent = state.currentAgentId
     ? state.agents.find((item) => item.id === state.currentAgentId)
     : null;
-  if (!agent || agent.status !== 'connected') {
+  if (!agent || !isAgentOnline(agent.status)) {
     return;
   }
 
@@ -253,7 +254,7 @@ export async function onCurrentAgentModelMenuClick(event: MouseEvent) {
   const agent = state.currentAgentId
     ? state.agents.find((item) => item.id === state.currentAgentId)
     : null;
-  if (!agent || !modelName || agent.status !== 'connected') {
+  if (!agent || !modelName || !isAgentOnline(agent.status)) {
     return;
   }
 
@@ -278,7 +279,7 @@ export async function toggleCurrentAgentThink() {
   const agent = state.currentAgentId
     ? state.agents.find((item) => item.id === state.currentAgentId)
     : null;
-  if (!agent || agent.status !== 'connected') {
+  if (!agent || !isAgentOnline(agent.status)) {
     return;
   }
   if (state.thinkSwitchingAgentId === agent.id) {
@@ -1,7 +1,14 @@
 // src/features/agents/reconnect.ts — auto-reconnect logic for saved agents
-import { connectIflow } from '../../services/tauri';
+import {
+  connectIflow,
+  disconnectAgent,
+  getAutoStartStatus,
+  restoreAgents,
+  type RestoredAgent,
+} from '../../services/tauri';
 import type { Agent } from '../../types';
 import { state } from '../../store';
+import { isAgentOnline } from '../../lib/utils';
 import { readLastConnectedAgentId, markLastConnectedAgent, normalizeConnectionErrorMessage } from './utils';
 
 export type AutoReconnectMode = 'all' | 'last' | 'off';
@@ -53,7 +60,7 @@ async function reconnectAgentWithOptions(
     return { success: false, error: 'Agent not found' };
   }
 
-  if (agent.status === 'connecting' || agent.status === 'connected') {
+  if (agent.status !== 'disconnected' && agent.status !== 'error') {
     return { success: true };
   }
 
@@ -68,7 +75,13 @@ async function reconnectAgentWithOptions(
   }
 
   try {
-    const result = await connectIflow(agent.id, agent.iflowPath || 'iflow', agent.workspacePath, agent.selectedModel || null);
+    const result = await connectIflow(
+      agent.id,
+      agent.iflowPath || 'iflow',
+      agent.workspacePath,
+      agent.selectedModel || null,
+      agent.permissionMode ?? null
+    );
 
     agent.port = result.port;
     agent.status = 'connected';
@@ -107,21 +120,84 @@ async function reconnectAgentWithOptions(
   }
 }
 
+// 接管应用重启前仍在运行的 Agent；不在列表中的 Agent 直接断开
+async function restoreRunningAgents(): Promise<void> {
+  let results: RestoredAgent[];
+  try {
+    results = await restoreAgents();
+  } catch (error) {
+    console.error('Restore running agents failed:', error);
+    return;
+  }
+
+  for (const result of results) {
+    if (!result.restored) {
+      continue;
+    }
+    const agent = state.agents.find((a) => a.id === result.agentId);
+    if (!agent) {
+      void disconnectAgent(result.agentId).catch((error) => {
+        console.error(`Disconnect unknown agent ${result.agentId} failed:`, error);
+      });
+      continue;
+    }
+    // 以后端记录的类型为准，远程 Agent 据此跳过本机的历史与 git 读取
+    if (result.agentType) {
+      agent.type = result.agentType;
+    }
+    if (agent.status === 'disconnected' || agent.status === 'error') {
+      agent.status = 'connecting';
+    }
+  }
+}
+
+// 同步后端自动启动的固定工作区 Agent，返回由后端负责连接的 Agent ID
+async function syncAutoStartedAgents(): Promise<Set<string>> {
+  const handled = new Set<string>();
+  let progress;
+  try {
+    progress = await getAutoStartStatus();
+  } catch (error) {
+    console.error('Read auto-start status failed:', error);
+    return handled;
+  }
+
+  for (const item of progress) {
+    if (item.phase === 'failed' || item.error) {
+      continue;
+    }
+    handled.add(item.agentId);
+    const agent = state.agents.find((a) => a.id === item.agentId);
+    if (!agent || (agent.status !== 'disconnected' && agent.status !== 'error')) {
+      continue;
+    }
+    agent.status = item.phase === 'started' || item.phase === 'skipped' ? 'connected' : 'connecting';
+  }
+  if (handled.size > 0) {
+    const { renderAgentList } = await import('./actions');
+    renderAgentList();
+  }
+  return handled;
+}
+
 export async function autoReconnectSavedAgents(): Promise<void> {
+  await restoreRunningAgents();
+  const autoStarted = await syncAutoStartedAgents();
   const mode = getAutoReconnectMode();
   if (mode === 'off') return;
 
   const agentsToConnect: Agent[] = [];
+  const candidates = state.agents.filter((a) => !autoStarted.has(a.id));
 
   if (mode === 'all') {
-    agentsToConnect.push(...state.agents);
+    agentsToConnect.push(...candidates);
   } else if (mode === 'last') {
     const lastAgentId = readLastConnectedAgentId();
-    const lastAgent = lastAgentId ? state.agents.find((a) => a.id === lastAgentId) : null;
+    const lastAgent = lastAgentId ? candidates.find((a) => a.id === lastAgentId) : null;
     if (lastAgent) {
       agentsToConnect.push(lastAgent);
-    } else if (state.agents.length > 0) {
-      agentsToConnect.push(state.agents[0]);
+    } else if (candidates.length > 0 && autoStarted.size === 0) {
+      agentsToConnect.push(candidates[0]);
     }
   }
 
@@ -141,7 +217,7 @@ export async function autoReconnectSavedAgents(): Promise<void> {
 
   const { updateConnectionStatus } = await import('./ui');
   const connectedCount = agentsToConnect.filter(
-    (a) => a.status === 'connected'
+    (a) => isAgentOnline(a.status)
   ).length;
   if (connectedCount > 0) {
     updateConnectionStatus(true);
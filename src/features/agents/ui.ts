@@ -10,6 +10,7 @@ import {
   connectionStatusEl,
 } from '../../dom';
 import { escapeHtml } from '../../lib/html';
+import { isAgentOnline } from '../../lib/utils';
 import { getThinkSupportByModel } from './utils';
 import { resolveModelDisplayName } from './model';
 
@@ -20,11 +21,14 @@ export function updateAgentStatusUI(status: Agent['status']) {
     disconnected: '离线',
     connecting: '连接中...',
     connected: '在线',
+    busy: '忙碌',
+    reconnecting: '重连中...',
+    stopping: '停止中...',
     error: '错误',
   }[status];
 
   currentAgentStatusEl.textContent = statusText;
-  currentAgentStatusEl.className = `badge${status === 'connected' ? ' connected' : ''}`;
+  currentAgentStatusEl.className = `badge${isAgentOnline(status) ? ' connected' : ''}`;
   updateCurrentAgentModelUI();
   updateCurrentAgentThinkUI();
 }
@@ -62,9 +66,9 @@ export function updateCurrentAgentModelUI() {
 
   currentAgentModelTextEl.textContent = `模型：${currentAgentModelLabel(agent)}`;
   currentAgentModelBtnEl.title = currentAgentModelLabel(agent);
-  currentAgentModelBtnEl.disabled = agent.status !== 'connected';
+  currentAgentModelBtnEl.disabled = !isAgentOnline(agent.status);
 
-  if (agent.status !== 'connected') {
+  if (!isAgentOnline(agent.status)) {
     closeCurrentAgentModelMenu();
   }
 }
@@ -109,7 +113,7 @@ export function updateCurrentAgentThinkUI() {
       : enabled
         ? '点击关闭思考模式'
         : '点击开启思考模式';
-  toggleThinkBtnEl.disabled = agent.status !== 'connected' || switching || unsupported;
+  toggleThinkBtnEl.disabled = !isAgentOnline(agent.status) || switching || unsupported;
 }
 
 // ── Model Menu ─────────────────────────────────────────────────────────────────
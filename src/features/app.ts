@@ -7,18 +7,47 @@ import {
   onAcpSession,
   onTaskFinish,
   onAgentError,
+  onMessageRecovered,
+  onSessionsStored,
+  onMessagePostprocessed,
+  onModelSwitched,
+  onMessagesDeleted,
+  onConnectionHealth,
+  onAgentAutoStart,
+  onPermissionRequest,
+  onPolicyViolation,
+  onPermissionResolved,
+  onFileWritePreview,
+  onFileWriteResolved,
+  onAgentStatusChanged,
+  onMemoryPressure,
+  onAuthRequired,
+  onAuthProgress,
+  onLanguageMismatch,
+  onDiagramRendererMissing,
+  onGitReviewRequested,
+  onEditorRequest,
+  onSessionTitleUpdated,
+  onHistoryChanged,
+  onProfileSwitched,
 } from '../services/events';
 import {
   getVersion,
+  authenticateIflow,
+  resolveGitReview,
   discoverSkills,
   sendMessage as tauriSendMessage,
   stopMessage,
   pickFolder,
+  getQuietHoursStatus,
+  replayInterruptedMessages,
+  respondPermission,
+  respondFileWrite,
 } from '../services/tauri';
 import { TIMEOUTS } from '../config';
-import { generateAcpSessionId, streamTypeToRole } from '../lib/utils';
+import { generateAcpSessionId, isAgentOnline, streamTypeToRole, stripThoughtMarker } from '../lib/utils';
 import { escapeHtml } from '../lib/html';
-import type { Message, SlashMenuItem, ComposerState, StreamMessageType, ThemeMode, SendKeyMode } from '../types';
+import type { AgentStatus, Message, SlashMenuItem, ComposerState, StreamMessageType, ThemeMode, SendKeyMode } from '../types';
 import {
   state,
   canUseConversationQuickAction,
@@ -108,6 +137,7 @@ import {
   saveDraft,
   getDraft,
   clearDraft,
+  parseStoredMessage,
 } from './storage';
 import {
   applyAcpSessionBinding,
@@ -117,6 +147,8 @@ import {
   startNewSession,
   clearChat,
   getMessagesForSession,
+  getSessionsForAgent,
+  mergeStoredSessions,
   findSessionById,
   touchCurrentSession,
   touchSessionById,
@@ -137,6 +169,7 @@ import {
   onCurrentAgentModelMenuClick,
   handleLocalModelCommand,
   handleLocalAgentCommand,
+  handleLocalImportCommand,
   type AutoReconnectMode,
   getAutoReconnectMode,
   normalizeAutoReconnectMode,
@@ -147,6 +180,9 @@ import {
   refreshCurrentAgentGitChanges,
   showGitChangesForAgent,
   showError,
+  selectAgent,
+  renderAgentList,
+  updateAgentStatusUI,
 } from './agents';
 import {
   renderMessages,
@@ -182,7 +218,11 @@ const DEFAULT_SLASH_COMMANDS: ReadonlyArray<{ command: string; description: stri
   { command: '/memory show', description: '查看当前记忆' },
   { command: '/agents autoreconnect', description: '查看自动重连模式（last/all/off）' },
   { command: '/agents autoreconnect <last|all|off>', description: '设置自动重连模式' },
+  { command: '/import <path>', description: '导入 iFlow / Claude Code 的 JSONL 会话记录' },
 ];
+// 文件写入确认框中最多展示的 diff 行数
+const FILE_WRITE_PREVIEW_MAX_LINES = 40;
+
 // 初始化
 // 主题管理
 const THEME_STORAGE_KEY = 'iflow-theme';
@@ -263,8 +303,11 @@ const AUTO_RECONNECT_MODE_LABELS: Record<AutoReconnectMode, string> = {
   all: '全部',
   off: '关闭',
 };
-const CAPABILITY_AGENT_STATUS_LABELS: Record<'connected' | 'disconnected' | 'connecting' | 'error', string> = {
+const CAPABILITY_AGENT_STATUS_LABELS: Record<AgentStatus, string> = {
   connected: '在线',
+  busy: '忙碌',
+  reconnecting: '重连中',
+  stopping: '停止中',
   disconnected: '离线',
   connecting: '连接中',
   error: '异常',
@@ -796,6 +839,19 @@ export async function playTaskFinishSound() {
   }
 }
 
+// 免打扰时段内不播放任务结束提示音；查询失败时按非免打扰处理
+async function playTaskFinishSoundUnlessQuiet() {
+  try {
+    const status = await getQuietHoursStatus();
+    if (status.quiet) {
+      return;
+    }
+  } catch (error) {
+    console.warn('Load quiet hours status failed:', error);
+  }
+  await playTaskFinishSound();
+}
+
 export function scheduleTaskFinishSound() {
   if (notificationSoundTimerId !== null) {
     window.clearTimeout(notificationSoundTimerId);
@@ -804,13 +860,13 @@ export function scheduleTaskFinishSound() {
 
   const delayMs = normalizeNotificationDelayMs(state.notificationDelayMs);
   if (delayMs === 0) {
-    void playTaskFinishSound();
+    void playTaskFinishSoundUnlessQuiet();
     return;
   }
 
   notificationSoundTimerId = window.setTimeout(() => {
     notificationSoundTimerId = null;
-    void playTaskFinishSound();
+    void playTaskFinishSoundUnlessQuiet();
   }, delayMs);
 }
 
@@ -860,7 +916,7 @@ export function setComposerState(state: ComposerState, hint: string) {
 export function refreshComposerState() {
   renderCapabilityCenterIfVisible();
   const currentAgent = state.currentAgentId ? state.agents.find((agent) => agent.id === state.currentAgentId) : null;
-  const isConnected = currentAgent?.status === 'connected';
+  const isConnected = Boolean(currentAgent && isAgentOnline(currentAgent.status));
   const hasSession = Boolean(state.currentSessionId);
   const isBusy = isCurrentAgentBusy();
 
@@ -971,6 +1027,7 @@ export function setupTauriEventListeners() {
     if (targetSessionId) {
       delete state.inflightSessionByAgent[payload.agentId];
     }
+    rememberFinishedReply(payload.taskId, targetSessionId || state.currentSessionId);
 
     if (payload.agentId === state.currentAgentId) {
       clearMessageWatchdog(state);
@@ -1008,9 +1065,408 @@ export function setupTauriEventListeners() {
     showError(`错误: ${payload.error || '未知错误'}`);
     refreshComposerState();
   });
+
+  onMemoryPressure((payload) => {
+    if (!payload.agentId || payload.agentId !== state.currentAgentId) {
+      return;
+    }
+    const usedMb = ((payload.usedBytes || 0) / 1024 / 1024).toFixed(1);
+    const limitMb = ((payload.limitBytes || 0) / 1024 / 1024).toFixed(1);
+    showError(`当前 Agent 缓冲内容占用 ${usedMb} MB，已超过 ${limitMb} MB 上限，请等待任务完成或减少排队消息`);
+  });
+
+  // iFlow 需要登录时由用户选择登录方式，登录成功后后端自动重新创建会话
+  onAuthRequired((payload) => {
+    const agentId = payload.agentId;
+    if (!agentId) {
+      return;
+    }
+    const method = payload.authMethods?.[0];
+    if (!method) {
+      showError(`iFlow 需要登录：${payload.detail || '请在终端运行 iflow 完成登录后重新连接'}`);
+      return;
+    }
+    if (!window.confirm(`iFlow 需要登录，是否使用「${method.name}」登录？`)) {
+      return;
+    }
+    authenticateIflow(agentId, method.id).catch((error) => {
+      showError(`iFlow 登录失败：${String(error)}`);
+    });
+  });
+
+  onAuthProgress((payload) => {
+    if (payload.agentId !== state.currentAgentId || !payload.url) {
+      return;
+    }
+    showError(`请在浏览器中完成 iFlow 登录：${payload.url}`);
+  });
+
+  // Git 钩子等待批准：有 Agent 审查时在审查回复结束后再询问
+  onGitReviewRequested((payload) => {
+    const action = payload.hook === 'pre-push' ? '推送' : '提交';
+    const ask = () => {
+      const approved = window.confirm(
+        `是否批准本次${action}？\n${payload.workspacePath || ''}（${payload.diffLines || 0} 行改动）`
+      );
+      resolveGitReview(payload.reviewId, approved).catch((error) => {
+        showError(`处理 Git 审查失败：${String(error)}`);
+      });
+    };
+    if (!payload.agentId) {
+      ask();
+      return;
+    }
+    if (payload.prompt) {
+      appendExternalPrompt(payload.agentId, payload.prompt);
+    }
+    showError(`Agent 正在审查即将${action}的改动，审查结束后请确认是否批准`);
+    const unlisten = onTaskFinish((finish) => {
+      if (finish.agentId !== payload.agentId) {
+        return;
+      }
+      void unlisten.then((stop) => stop());
+      ask();
+    });
+  });
+
+  // 编辑器插件发送选中代码或定位会话时切换到对应 Agent
+  onEditorRequest((payload) => {
+    if (!payload.agentId) {
+      return;
+    }
+    if (payload.agentId !== state.currentAgentId) {
+      selectAgent(payload.agentId);
+    }
+    if (payload.prompt) {
+      appendExternalPrompt(payload.agentId, payload.prompt);
+    }
+  });
+
+  // Agent 生成的会话标题覆盖按消息内容截取的标题
+  onSessionTitleUpdated((payload) => {
+    const session = payload.sessionId ? findSessionById(payload.sessionId) : null;
+    if (!session || !payload.title) {
+      return;
+    }
+    session.title = payload.title;
+    session.titleGenerated = true;
+    void saveSessions();
+    renderSessionList();
+  });
+
+  // iFlow 历史目录变化时刷新当前 Agent 的会话列表
+  onHistoryChanged((payload) => {
+    const agent = state.agents.find((item) => item.id === state.currentAgentId);
+    if (!agent || agent.workspacePath !== payload.workspacePath) {
+      return;
+    }
+    void import('./sessions').then(({ syncIflowHistorySessions }) => {
+      void syncIflowHistorySessions(agent);
+    });
+  });
+
+  // 会话存储、设置与插件都属于新档案：整体重新载入，内存中旧档案的会话不能再写入新档案
+  onProfileSwitched(() => {
+    window.location.reload();
+  });
+
+  // 本机未安装图表渲染器：每个渲染器只提示一次
+  const notifiedMissingRenderers = new Set<string>();
+  onDiagramRendererMissing((payload) => {
+    if (!payload.renderer || notifiedMissingRenderers.has(payload.renderer)) {
+      return;
+    }
+    notifiedMissingRenderers.add(payload.renderer);
+    showError(`未安装 ${payload.renderer}，无法渲染 ${payload.language || ''} 图表，可通过 ${payload.hint || '包管理器'} 安装`);
+  });
+
+  // 回复语言与工作区设置不一致时仅作提示
+  onLanguageMismatch((payload) => {
+    if (payload.agentId !== state.currentAgentId) {
+      return;
+    }
+    const expected = payload.expected === 'en' ? '英文' : '中文';
+    showError(`提示：本次回复未使用工作区设置的${expected}，可在追问中提醒 Agent 切换语言`);
+  });
+
+  // 后端状态机的每次状态变化同步到 Agent 列表与状态标记
+  onAgentStatusChanged((payload) => {
+    const agent = state.agents.find((item) => item.id === payload.agentId);
+    if (!agent || agent.status === payload.status) {
+      return;
+    }
+    agent.status = payload.status;
+    renderAgentList();
+    if (payload.agentId === state.currentAgentId) {
+      updateAgentStatusUI(agent.status);
+      refreshComposerState();
+    }
+  });
+
+  // 固定工作区的 Agent 由后端在启动时自动连接，连接状态另由 agent-status-changed 同步
+  onAgentAutoStart((payload) => {
+    const agent = state.agents.find((item) => item.id === payload.agentId);
+    if (!agent) {
+      return;
+    }
+    if (payload.phase === 'starting' && (agent.status === 'disconnected' || agent.status === 'error')) {
+      agent.status = 'connecting';
+      renderAgentList();
+    } else if (payload.phase === 'failed') {
+      agent.status = 'error';
+      renderAgentList();
+      showError(`自动启动 ${agent.name} 失败：${payload.error ?? '未知错误'}`);
+    }
+  });
+
+  // 未命中权限规则的工具调用交由用户确认；超时未选择时后端按设置的默认决策应答
+  const pendingPermissionRequests = new Set<string>();
+  onPermissionRequest(async (payload) => {
+    const key = `${payload.agentId}:${payload.requestId}`;
+    pendingPermissionRequests.add(key);
+    const agent = state.agents.find((item) => item.id === payload.agentId);
+    const tool = payload.toolCall?.title || payload.toolCall?.kind || '工具调用';
+    const allow =
+      payload.options.find((option) => option.kind === 'allow_once') ??
+      payload.options.find((option) => option.kind?.startsWith('allow'));
+    const reject =
+      payload.options.find((option) => option.kind === 'reject_once') ??
+      payload.options.find((option) => option.kind?.startsWith('reject'));
+    const confirmed = await showConfirmDialog(
+      '权限请求',
+      `${agent?.name ?? 'Agent'} 请求执行：${tool}\n${payload.timeoutSecs} 秒内未选择将自动${
+        payload.timeoutDecision === 'allow' ? '允许' : '拒绝'
+      }。`,
+      { okText: allow?.name || '允许', cancelText: reject?.name || '拒绝' }
+    );
+    // 等待期间已超时或被取消
+    if (!pendingPermissionRequests.delete(key)) {
+      return;
+    }
+    try {
+      await respondPermission(payload.agentId, payload.requestId, (confirmed ? allow : reject)?.optionId ?? null);
+    } catch (error) {
+      showError(`权限应答失败：${String(error)}`);
+    }
+  });
+
+  // 直接执行的工具调用命中拒绝规则，后端已取消本轮
+  onPolicyViolation((payload) => {
+    if (payload.agentId !== state.currentAgentId) {
+      return;
+    }
+    const tool = payload.toolCall?.title || payload.tool;
+    // 自动执行模式下工具未经权限请求，取消时可能已开始执行
+    showError(`工具调用 ${tool} 命中拒绝规则，本轮任务已取消；该工具可能已开始执行，请检查工作区改动`);
+  });
+
+  onPermissionResolved((payload) => {
+    const key = `${payload.agentId}:${payload.requestId}`;
+    if (!pendingPermissionRequests.delete(key) || !payload.timedOut) {
+      return;
+    }
+    if (payload.agentId === state.currentAgentId) {
+      showError('权限请求等待超时，已按默认设置应答');
+    }
+  });
+
+  // 计划模式下 Agent 的文件写入先展示 diff，由用户确认后才写盘
+  const pendingFileWrites = new Set<string>();
+  onFileWritePreview(async (payload) => {
+    if (!payload.awaitingApproval) {
+      return;
+    }
+    const key = `${payload.agentId}:${payload.requestId}`;
+    pendingFileWrites.add(key);
+    const agent = state.agents.find((item) => item.id === payload.agentId);
+    const diffLines = payload.diff.split('\n');
+    const diff =
+      diffLines.length > FILE_WRITE_PREVIEW_MAX_LINES
+        ? `${diffLines.slice(0, FILE_WRITE_PREVIEW_MAX_LINES).join('\n')}\n…（共 ${diffLines.length} 行）`
+        : payload.diff;
+    const accepted = await showConfirmDialog(
+      payload.newFile ? '创建文件' : '修改文件',
+      `${agent?.name ?? 'Agent'} 请求写入 ${payload.path}：\n${diff}\n${payload.timeoutSecs ?? 0} 秒内未选择将自动${
+        payload.timeoutDecision === 'allow' ? '写入' : '拒绝'
+      }。`,
+      { okText: '写入', cancelText: '拒绝' }
+    );
+    if (!pendingFileWrites.delete(key)) {
+      return;
+    }
+    try {
+      await respondFileWrite(payload.agentId, payload.requestId, accepted);
+    } catch (error) {
+      showError(`文件写入应答失败：${String(error)}`);
+    }
+  });
+
+  onFileWriteResolved((payload) => {
+    const key = `${payload.agentId}:${payload.requestId}`;
+    if (!pendingFileWrites.delete(key) || !payload.timedOut) {
+      return;
+    }
+    if (payload.agentId === state.currentAgentId) {
+      showError(`文件写入等待超时，已${payload.accepted ? '写入' : '拒绝'}`);
+    }
+  });
+
+  // 心跳停滞时提示，连接由后端自动重建
+  onConnectionHealth((payload) => {
+    if (payload.agentId !== state.currentAgentId || payload.status === 'healthy') {
+      return;
+    }
+    showError(
+      payload.status === 'stale'
+        ? `Agent 连接已 ${payload.silentSecs} 秒无响应`
+        : 'Agent 连接已断开，正在重新连接…'
+    );
+  });
+
+  // 模型切换标记已由后端写入存储，这里同步到内存中的会话
+  onModelSwitched((payload) => {
+    const sessionMessages = getMessagesForSession(payload.sessionId);
+    if (sessionMessages.some((m) => m.id === payload.message.id)) {
+      return;
+    }
+    sessionMessages.push(parseStoredMessage(payload.message));
+    state.messagesBySession[payload.sessionId] = sessionMessages;
+    touchSessionById(payload.sessionId, sessionMessages);
+    void saveSessionMessages();
+
+    if (payload.sessionId === state.currentSessionId) {
+      state.messages = sessionMessages;
+      renderMessages();
+    } else {
+      renderSessionList();
+    }
+  });
+
+  // 后端已按问答对删除并写入存储，这里同步内存中的会话
+  void onMessagesDeleted((payload) => {
+    const removed = new Set(payload.messageIds);
+    const sessionMessages = getMessagesForSession(payload.sessionId).filter((m) => !removed.has(m.id));
+    state.messagesBySession[payload.sessionId] = sessionMessages;
+    touchSessionById(payload.sessionId, sessionMessages);
+    void saveSessionMessages();
+
+    if (payload.sessionId === state.currentSessionId) {
+      state.messages = sessionMessages;
+      renderMessages();
+    } else {
+      renderSessionList();
+    }
+  });
+
+  // 导入、交接等由后端写入存储的会话，合并进内存并保存
+  void onSessionsStored((payload) => {
+    mergeStoredSessions(payload.sessions || [], payload.messagesBySession || {});
+  });
+
+  // 后处理钩子改写了最终回复：替换该任务的回复并重新保存
+  void onMessagePostprocessed((payload) => {
+    const reply = payload.taskId ? state.finishedReplyByTask[payload.taskId] : undefined;
+    if (!reply) {
+      return;
+    }
+    delete state.finishedReplyByTask[payload.taskId!];
+    if (payload.failures?.length && payload.agentId === state.currentAgentId) {
+      showError(`回复后处理钩子失败: ${payload.failures.map((failure) => failure.name).join(', ')}`);
+    }
+    if (!payload.changed || typeof payload.content !== 'string') {
+      return;
+    }
+
+    const sessionMessages = getMessagesForSession(reply.sessionId);
+    const message = sessionMessages.find((m) => m.id === reply.messageId);
+    if (!message) {
+      return;
+    }
+    message.content = payload.content;
+    delete message.estimatedTokens;
+    state.messagesBySession[reply.sessionId] = sessionMessages;
+    void saveSessionMessages();
+
+    if (reply.sessionId === state.currentSessionId) {
+      state.messages = sessionMessages;
+      renderMessages();
+    }
+  });
+
+  // 崩溃前未完成的回复已由后端写入存储，这里同步到内存中的会话
+  void onMessageRecovered((payload) => {
+    const recovered = payload.message;
+    if (!payload.sessionId || !recovered) {
+      return;
+    }
+
+    const sessionMessages = getMessagesForSession(payload.sessionId);
+    if (sessionMessages.some((m) => m.id === recovered.id)) {
+      return;
+    }
+    sessionMessages.push(parseStoredMessage(recovered));
+    state.messagesBySession[payload.sessionId] = sessionMessages;
+    touchSessionById(payload.sessionId, sessionMessages);
+    void saveSessionMessages();
+
+    if (payload.sessionId === state.currentSessionId) {
+      state.messages = sessionMessages;
+      renderMessages();
+    } else {
+      renderSessionList();
+    }
+  }).then(() =>
+    replayInterruptedMessages().catch((error) => {
+      console.error('Failed to replay interrupted messages:', error);
+    })
+  );
 }
 
 // 追加流式消息
+/** 后端代为发送的提问记为用户消息并保存，回复写入同一会话 */
+function appendExternalPrompt(agentId: string, content: string) {
+  const sessionId =
+    state.inflightSessionByAgent[agentId] ||
+    (agentId === state.currentAgentId ? state.currentSessionId : getSessionsForAgent(agentId)[0]?.id);
+  if (!sessionId) {
+    return;
+  }
+
+  const sessionMessages = getMessagesForSession(sessionId);
+  sessionMessages.push({
+    id: `msg-${Date.now()}`,
+    role: 'user',
+    content,
+    timestamp: new Date(),
+  });
+  state.messagesBySession[sessionId] = sessionMessages;
+  touchSessionById(sessionId, sessionMessages);
+  void saveSessionMessages();
+  if (!state.inflightSessionByAgent[agentId]) {
+    state.inflightSessionByAgent[agentId] = sessionId;
+  }
+
+  if (sessionId === state.currentSessionId) {
+    state.messages = sessionMessages;
+    renderMessages();
+    scrollToBottom();
+  } else {
+    renderSessionList();
+  }
+  refreshComposerState();
+}
+
+/** 记录任务结束时会话中的最后一条助手回复，后处理结果到达时据此替换 */
+function rememberFinishedReply(taskId: string | undefined, sessionId: string | null) {
+  if (!taskId || !sessionId) {
+    return;
+  }
+  const reply = [...getMessagesForSession(sessionId)].reverse().find((m) => m.role === 'assistant');
+  if (reply) {
+    state.finishedReplyByTask[taskId] = { sessionId, messageId: reply.id };
+  }
+}
+
 export function appendStreamMessage(
   agentId: string,
   sessionId: string,
@@ -1024,7 +1480,7 @@ export function appendStreamMessage(
   const role = streamTypeToRole(messageType);
   let normalizedContent = content;
   if (role === 'thought') {
-    normalizedContent = normalizedContent.replace(/^💭\s*/, '');
+    normalizedContent = stripThoughtMarker(normalizedContent);
   }
   if (!normalizedContent.trim()) {
     return;
@@ -1972,7 +2428,9 @@ export async function sendMessage() {
     return;
   }
 
-  const handledByLocalAgentCommand = await handleLocalAgentCommand(content, requestSessionId);
+  const handledByLocalAgentCommand =
+    (await handleLocalAgentCommand(content, requestSessionId)) ||
+    (await handleLocalImportCommand(content, requestAgentId, requestSessionId));
   if (handledByLocalAgentCommand) {
     messageInputEl.value = '';
     messageInputEl.style.height = 'auto';
@@ -2005,7 +2463,7 @@ export async function sendMessage() {
   }
 
   const requestAgent = state.agents.find((agent) => agent.id === requestAgentId);
-  if (requestAgent?.status !== 'connected') {
+  if (!requestAgent || !isAgentOnline(requestAgent.status)) {
     showError('当前 Agent 离线，仅支持本地命令。可输入 /agents autoreconnect 重连。');
     return;
   }
@@ -2118,7 +2576,7 @@ async function sendCompressToCurrentSession() {
   if (!agentId || !sessionId) return;
 
   const agent = state.agents.find((a) => a.id === agentId);
-  if (agent?.status !== 'connected') {
+  if (!agent || !isAgentOnline(agent.status)) {
     showError('当前 Agent 离线，无法执行压缩');
     return;
   }
@@ -1,4 +1,5 @@
 import type { Agent, AgentRegistry, RegistryMcpServer } from '../../types';
+import { isAgentOnline } from '../../lib/utils';
 import { isMcpSuggestionEnabled, type CapabilityEnabledMap } from './enables';
 
 export type McpCapabilityViewState = 'no-agent' | 'ready' | 'empty' | 'offline';
@@ -43,7 +44,7 @@ export function buildMcpCapabilityViewModel(
   const agentName = agent?.name || currentAgentId;
   const agentStatus = agent?.status || null;
 
-  if (agentStatus && agentStatus !== 'connected') {
+  if (agentStatus && !isAgentOnline(agentStatus)) {
     return {
       state: 'offline',
       agentName,
@@ -5,11 +5,20 @@ import {
   loadIflowHistoryMessages,
   deleteIflowHistorySession,
 } from '../../services/tauri';
+import { runJob } from '../../services/jobs';
 import { escapeHtml } from '../../lib/html';
 import { normalizeTitleSource } from '../../lib/markdown';
-import { formatSessionMeta } from '../../lib/utils';
+import { formatSessionMeta, isAgentOnline, isRemoteAgent } from '../../lib/utils';
 import { showConfirmDialog } from '../../dom';
-import type { Agent, Session, Message, IflowHistoryMessageRecord } from '../../types';
+import type {
+  Agent,
+  Session,
+  Message,
+  IflowHistoryMessageRecord,
+  HistoryClearManifest,
+  StoredMessage,
+  StoredSession,
+} from '../../types';
 import { state } from '../../store';
 import { sessionListEl } from '../../dom';
 import {
@@ -20,6 +29,8 @@ import {
   createSession,
   saveSessions,
   saveSessionMessages,
+  parseStoredSession,
+  parseStoredMessage,
 } from '../storage';
 import { showError, showSuccess } from '../agents';
 import { restoreDraftForSession } from '../app';
@@ -127,15 +138,20 @@ export async function clearCurrentAgentSessions() {
   // 添加确认弹窗，防止误操作
   const confirmed = await showConfirmDialog(
     '清空会话确认',
-    `确定要清空 Agent "${agent.name}" 的所有会话记录吗？此操作不可撤销。`
+    `确定要清空 Agent "${agent.name}" 的所有会话记录吗？磁盘历史会移入回收目录，10 分钟内可撤销。`
   );
   if (!confirmed) {
     return;
   }
 
   let deletedHistoryCount = 0;
+  let skippedHistoryCount = 0;
   try {
-    deletedHistoryCount = await clearIflowHistorySessions(agent.workspacePath);
+    const manifest = await runJob<HistoryClearManifest>(() =>
+      clearIflowHistorySessions(agent.workspacePath)
+    );
+    deletedHistoryCount = manifest.files.length;
+    skippedHistoryCount = manifest.skipped.length;
   } catch (error) {
     console.error('Clear iFlow history sessions error:', error);
     showError(`清除磁盘历史记录失败: ${String(error)}`);
@@ -169,7 +185,8 @@ export async function clearCurrentAgentSessions() {
   renderSessionList();
   renderMessages();
   refreshComposerState();
-  showSuccess(`已清除当前 Agent 会话（磁盘历史 ${deletedHistoryCount} 条）`);
+  const skippedNote = skippedHistoryCount > 0 ? `，${skippedHistoryCount} 条使用中已保留` : '';
+  showSuccess(`已清除当前 Agent 会话（磁盘历史 ${deletedHistoryCount} 条${skippedNote}）`);
 }
 
 // ── Session list rendering ────────────────────────────────────────────────────
@@ -290,6 +307,48 @@ export function commitSessionMessages(sessionId: string, sessionMessages: Messag
   }
 }
 
+/**
+ * 合并后端直接写入存储的会话与消息：会话按 ID 替换或插到列表最前，消息按 ID 补充缺失项。
+ * 合并后立即保存，否则前端下一次整体保存会覆盖后端写入的记录。
+ */
+export function mergeStoredSessions(
+  sessions: StoredSession[],
+  messagesBySession: Record<string, StoredMessage[]>,
+) {
+  for (const stored of sessions) {
+    const session = parseStoredSession(stored);
+    const sessionList = state.sessionsByAgent[session.agentId] || [];
+    const index = sessionList.findIndex((item) => item.id === session.id);
+    if (index >= 0) {
+      sessionList[index] = { ...sessionList[index], ...session };
+    } else {
+      sessionList.unshift(session);
+    }
+    state.sessionsByAgent[session.agentId] = sessionList;
+  }
+
+  for (const [sessionId, storedMessages] of Object.entries(messagesBySession)) {
+    const sessionMessages = getMessagesForSession(sessionId);
+    const known = new Set(sessionMessages.map((message) => message.id));
+    const added = storedMessages.filter((message) => !known.has(message.id)).map(parseStoredMessage);
+    if (added.length === 0) {
+      continue;
+    }
+    sessionMessages.push(...added);
+    sessionMessages.sort((left, right) => left.timestamp.getTime() - right.timestamp.getTime());
+    state.messagesBySession[sessionId] = sessionMessages;
+    if (sessionId === state.currentSessionId) {
+      state.messages = sessionMessages;
+      void import('../ui').then(({ renderMessages }) => {
+        renderMessages();
+      });
+    }
+  }
+
+  void saveSessions();
+  renderSessionList();
+}
+
 // ── Session navigation ────────────────────────────────────────────────────────
 
 export function startNewSession() {
@@ -455,6 +514,9 @@ export function touchSessionById(sessionId: string, sessionMessages?: Message[])
 // ── Session title generation ──────────────────────────────────────────────────
 
 export function maybeGenerateSessionTitle(session: Session, sessionMessages: Message[]) {
+  if (session.titleGenerated) {
+    return;
+  }
   const dialoguePair = getLatestDialoguePair(sessionMessages);
   if (!dialoguePair) {
     return;
@@ -710,7 +772,7 @@ export function expandIflowHistoryMessageRecord(item: IflowHistoryMessageRecord)
 // ── iFlow history sync ────────────────────────────────────────────────────────
 
 export async function syncIflowHistorySessions(agent: Agent): Promise<void> {
-  if (agent.status !== 'connected') {
+  if (!isAgentOnline(agent.status) || isRemoteAgent(agent)) {
     return;
   }
 
@@ -898,7 +960,7 @@ export async function loadIflowHistoryMessagesForSession(session: Session): Prom
     return;
   }
   const agent = state.agents.find((item) => item.id === session.agentId);
-  if (!agent) {
+  if (!agent || isRemoteAgent(agent)) {
     return;
   }
 
@@ -137,6 +137,9 @@ export function toStoredMessage(message: Message): StoredMessage {
     content: message.content,
     timestamp: message.timestamp.toISOString(),
     ...(message.agentId ? { agentId: message.agentId } : {}),
+    ...(message.interrupted ? { interrupted: true } : {}),
+    ...(message.attachments?.length ? { attachments: [...message.attachments] } : {}),
+    ...(message.modelSwitch ? { modelSwitch: { ...message.modelSwitch } } : {}),
   };
 }
 
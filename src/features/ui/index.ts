@@ -5,7 +5,7 @@ import {
   readHtmlArtifact,
   resolveHtmlArtifactPath,
 } from '../../services/tauri';
-import { formatTime, formatDateSeparator } from '../../lib/utils';
+import { formatTime, formatDateSeparator, isAgentOnline, isRemoteAgent } from '../../lib/utils';
 import { escapeHtml } from '../../lib/html';
 import { formatMessageContent } from '../../lib/markdown';
 import type { ToolCall, GitFileChange, GitStatus } from '../../types';
@@ -416,10 +416,14 @@ export async function openGitDiffPreview(path: string) {
 
 export async function openArtifactPreview(path: string) {
   const agent = state.currentAgentId ? state.agents.find((item) => item.id === state.currentAgentId) : null;
-  if (!agent || agent.status !== 'connected') {
+  if (!agent || !isAgentOnline(agent.status)) {
     showError('请先连接当前 Agent，再预览 HTML Artifact');
     return;
   }
+  if (isRemoteAgent(agent)) {
+    showError('远程 Agent 的 Artifact 位于远程主机上，无法在本机预览');
+    return;
+  }
 
   const normalizedPath = normalizeArtifactPathInput(path.trim());
   if (!normalizedPath) {
@@ -475,10 +479,16 @@ export async function openArtifactPreview(path: string) {
 
   let readError: unknown = null;
   try {
-    const html = await Promise.race([
+    const { content: html, sanitized, csp } = await Promise.race([
       readHtmlArtifact(agent.id, normalizedPath),
       createArtifactPreviewTimeoutPromise(),
     ]);
+    if (sanitized && Object.values(sanitized).some((count) => count > 0)) {
+      console.info('[artifact] HTML sanitized before preview', normalizedPath, sanitized);
+    }
+    if (csp && csp.blockedUrls.length > 0) {
+      console.info('[artifact] external resources blocked by CSP', normalizedPath, csp.blockedUrls);
+    }
 
     if (shouldIgnoreArtifactResponse(requestToken, normalizedPath)) {
       return;
@@ -887,7 +897,7 @@ export function renderMessages() {
         <div class="message-content">
           ${formatMessageContent(msg.content)}
           ${renderArtifactPreviewActions(msg.content)}
-          <div class="message-time">${formatTime(msg.timestamp)}</div>
+          <div class="message-time">${formatTime(msg.timestamp)}${msg.interrupted ? ' · 已中断' : ''}</div>
           ${quickReplySection}
         </div>
       </div>
@@ -1,4 +1,5 @@
 import type { Agent, Message, Session } from '../types';
+import { isAgentOnline } from './utils';
 
 export interface CompressionEligibilityInput {
   agent: Agent | null;
@@ -33,7 +34,7 @@ export function getCompressionDisabledReason(input: CompressionEligibilityInput)
   if (!input.hasSession) {
     return '未选择会话';
   }
-  if (!input.agent || input.agent.status !== 'connected') {
+  if (!input.agent || !isAgentOnline(input.agent.status)) {
     return 'Agent 离线';
   }
   if (input.sessionSource === 'iflow-log') {
@@ -0,0 +1,16 @@
+import { describe, expect, it } from 'vitest';
+import { stripThoughtMarker } from './utils';
+
+describe('stripThoughtMarker', () => {
+  it('merges a thought streamed in several chunks without repeated markers', () => {
+    for (const marker of ['💭', '[思考]']) {
+      const chunks = [`${marker} 先读取`, `${marker} 配置文件，`, `${marker} 再运行测试`];
+      const merged = chunks.map(stripThoughtMarker).join('');
+      expect(merged).toBe('先读取配置文件，再运行测试');
+    }
+  });
+
+  it('keeps markers that are not at the start of a chunk', () => {
+    expect(stripThoughtMarker('对比 [思考] 标记')).toBe('对比 [思考] 标记');
+  });
+});
@@ -1,5 +1,5 @@
 // src/lib/utils.ts
-import type { StreamMessageType, Message } from '../types';
+import type { Agent, AgentStatus, StreamMessageType, Message } from '../types';
 
 export function generateAcpSessionId(): string {
   const randomUuid =
@@ -29,6 +29,24 @@ export function streamTypeToRole(messageType?: StreamMessageType): Message['role
   return 'assistant';
 }
 
+/** 后端按消息风格给每个思考片段加 `💭` 或 `[思考]` 前缀，合并到同一条思考消息前去掉 */
+export function stripThoughtMarker(content: string): string {
+  return content.replace(/^(💭|\[思考\])\s*/u, '');
+}
+
+/** 会话就绪、可以发送消息（空闲或任务进行中） */
+export function isAgentOnline(status: AgentStatus): boolean {
+  return status === 'connected' || status === 'busy';
+}
+
+/** 与后端 `REMOTE_AGENT_TYPE` 一致 */
+export const REMOTE_AGENT_TYPE = 'iflow-remote';
+
+/** 远程 Agent 的工作区路径在远程主机上，不能按本机路径读取 git 状态、历史记录与产物 */
+export function isRemoteAgent(agent: Pick<Agent, 'type'>): boolean {
+  return agent.type === REMOTE_AGENT_TYPE;
+}
+
 export function normalizeStoredRole(role: string): Message['role'] {
   if (role === 'user' || role === 'assistant' || role === 'system' || role === 'thought') {
     return role;
@@ -1,5 +1,14 @@
 import { listen, type UnlistenFn } from '@tauri-apps/api/event';
-import type { StreamMessageType, ToolCall } from '../types';
+import type {
+  AgentStatus,
+  DeletedMessages,
+  JobRecord,
+  StoredMessage,
+  StoredSession,
+  StreamMessageType,
+  ToolCall,
+} from '../types';
+import type { ProfileRegistry } from './tauri';
 
 // Payload interfaces
 
@@ -33,14 +42,191 @@ export interface AcpSessionPayload {
 
 export interface TaskFinishPayload {
   agentId?: string;
+  taskId?: string;
   reason?: string;
+  message?: string;
 }
 
+export type JobProgressPayload = JobRecord;
+
 export interface AgentErrorPayload {
   agentId?: string;
   error?: string;
 }
 
+export interface MemoryPressurePayload {
+  agentId?: string;
+  usedBytes?: number;
+  limitBytes?: number;
+}
+
+export interface AuthMethod {
+  id: string;
+  name: string;
+  description?: string | null;
+}
+
+export interface AuthRequiredPayload {
+  agentId?: string;
+  source?: 'stderr' | 'acp';
+  detail?: string;
+  authMethods?: AuthMethod[];
+}
+
+export interface AuthProgressPayload {
+  agentId?: string;
+  message?: string;
+  url?: string | null;
+}
+
+export type ReplyLanguage = 'zh' | 'en';
+
+export interface LanguageMismatchPayload {
+  agentId?: string;
+  taskId?: string;
+  expected?: ReplyLanguage;
+  detected?: ReplyLanguage;
+}
+
+export interface DiagramRendererMissingPayload {
+  agentId?: string;
+  language?: 'mermaid' | 'plantuml';
+  renderer?: string;
+  hint?: string;
+}
+
+export interface GitReviewRequestedPayload {
+  reviewId: string;
+  workspacePath?: string;
+  hook?: 'pre-commit' | 'pre-push';
+  agentId?: string | null;
+  diffLines?: number;
+  /** 已发给 Agent 的审查提问 */
+  prompt?: string | null;
+}
+
+export interface EditorRequestPayload {
+  action?: 'sendSelection' | 'showSession';
+  agentId?: string;
+  filePath?: string;
+  /** 已代为发送的提问 */
+  prompt?: string | null;
+}
+
+export interface SessionTitleUpdatedPayload {
+  agentId?: string;
+  sessionId?: string;
+  acpSessionId?: string;
+  title?: string;
+}
+
+/** 后端直接写入存储的会话与消息（导入、交接等），需合并进内存状态后保存 */
+export interface SessionsStoredPayload {
+  sessions: StoredSession[];
+  messagesBySession: Record<string, StoredMessage[]>;
+}
+
+export interface MessagePostprocessedPayload {
+  agentId?: string;
+  taskId?: string;
+  content?: string;
+  changed?: boolean;
+  applied?: string[];
+  failures?: { name: string; error: string }[];
+}
+
+export interface MessageRecoveredPayload {
+  agentId?: string;
+  sessionId?: string | null;
+  acpSessionId?: string;
+  message?: StoredMessage;
+}
+
+export interface ModelSwitchedPayload {
+  agentId: string;
+  sessionId: string;
+  message: StoredMessage;
+}
+
+export interface AgentStatusChangedPayload {
+  agentId: string;
+  status: AgentStatus;
+  previous?: AgentStatus | null;
+}
+
+export interface ConnectionHealthPayload {
+  agentId: string;
+  status: 'healthy' | 'stale' | 'reconnecting';
+  silentSecs: number;
+}
+
+export interface AgentAutoStartPayload {
+  agentId: string;
+  workspacePath: string;
+  phase: 'pending' | 'starting' | 'started' | 'skipped' | 'failed';
+  error?: string | null;
+}
+
+export interface PolicyViolationPayload {
+  agentId: string;
+  toolCall: { title?: string; kind?: string; toolCallId?: string };
+  ruleId: string;
+  tool: string;
+  pattern?: string | null;
+}
+
+export interface PermissionOption {
+  optionId: string;
+  name?: string;
+  kind?: string;
+}
+
+export interface PermissionRequestPayload {
+  agentId: string;
+  requestId: number;
+  sessionId?: string | null;
+  toolCall: { title?: string; kind?: string; toolCallId?: string } | null;
+  options: PermissionOption[];
+  timeoutSecs: number;
+  timeoutDecision: 'allow' | 'deny';
+}
+
+export interface PermissionResolvedPayload {
+  agentId: string;
+  requestId: number;
+  outcome: { outcome: 'selected' | 'cancelled'; optionId?: string };
+  timedOut: boolean;
+}
+
+export interface FileWritePreviewPayload {
+  agentId: string;
+  requestId: number;
+  sessionId: string;
+  path: string;
+  newFile: boolean;
+  diff: string;
+  awaitingApproval: boolean;
+  timeoutSecs: number | null;
+  timeoutDecision: 'allow' | 'deny' | null;
+}
+
+export interface AgentPausedPayload {
+  agentId: string;
+  paused: boolean;
+}
+
+export interface HistoryChangedPayload {
+  workspacePath: string;
+  changes: Array<{ sessionId: string; kind: 'created' | 'updated' | 'removed' }>;
+}
+
+export interface FileWriteResolvedPayload {
+  agentId: string;
+  requestId: number;
+  accepted: boolean;
+  timedOut: boolean;
+}
+
 // Typed wrapper functions
 
 export function onStreamMessage(
@@ -77,8 +263,160 @@ export function onTaskFinish(
   return listen<TaskFinishPayload>('task-finish', (event) => callback(event.payload));
 }
 
+export function onJobProgress(
+  callback: (payload: JobProgressPayload) => void
+): Promise<UnlistenFn> {
+  return listen<JobProgressPayload>('job-progress', (event) => callback(event.payload));
+}
+
 export function onAgentError(
   callback: (payload: AgentErrorPayload) => void
 ): Promise<UnlistenFn> {
   return listen<AgentErrorPayload>('agent-error', (event) => callback(event.payload));
 }
+
+export function onMessagePostprocessed(
+  callback: (payload: MessagePostprocessedPayload) => void
+): Promise<UnlistenFn> {
+  return listen<MessagePostprocessedPayload>('message-postprocessed', (event) => callback(event.payload));
+}
+
+export function onMessageRecovered(
+  callback: (payload: MessageRecoveredPayload) => void
+): Promise<UnlistenFn> {
+  return listen<MessageRecoveredPayload>('message-recovered', (event) => callback(event.payload));
+}
+
+export function onSessionsStored(
+  callback: (payload: SessionsStoredPayload) => void
+): Promise<UnlistenFn> {
+  return listen<SessionsStoredPayload>('sessions-stored', (event) => callback(event.payload));
+}
+
+export function onModelSwitched(
+  callback: (payload: ModelSwitchedPayload) => void
+): Promise<UnlistenFn> {
+  return listen<ModelSwitchedPayload>('model-switched', (event) => callback(event.payload));
+}
+
+export function onMessagesDeleted(
+  callback: (payload: DeletedMessages) => void
+): Promise<UnlistenFn> {
+  return listen<DeletedMessages>('messages-deleted', (event) => callback(event.payload));
+}
+
+export function onAgentStatusChanged(
+  callback: (payload: AgentStatusChangedPayload) => void
+): Promise<UnlistenFn> {
+  return listen<AgentStatusChangedPayload>('agent-status-changed', (event) => callback(event.payload));
+}
+
+export function onConnectionHealth(
+  callback: (payload: ConnectionHealthPayload) => void
+): Promise<UnlistenFn> {
+  return listen<ConnectionHealthPayload>('connection-health', (event) => callback(event.payload));
+}
+
+export function onAgentAutoStart(
+  callback: (payload: AgentAutoStartPayload) => void
+): Promise<UnlistenFn> {
+  return listen<AgentAutoStartPayload>('agent-autostart', (event) => callback(event.payload));
+}
+
+export function onPolicyViolation(
+  callback: (payload: PolicyViolationPayload) => void
+): Promise<UnlistenFn> {
+  return listen<PolicyViolationPayload>('policy-violation', (event) => callback(event.payload));
+}
+
+export function onPermissionRequest(
+  callback: (payload: PermissionRequestPayload) => void
+): Promise<UnlistenFn> {
+  return listen<PermissionRequestPayload>('permission-request', (event) => callback(event.payload));
+}
+
+export function onPermissionResolved(
+  callback: (payload: PermissionResolvedPayload) => void
+): Promise<UnlistenFn> {
+  return listen<PermissionResolvedPayload>('permission-resolved', (event) => callback(event.payload));
+}
+
+export function onFileWritePreview(
+  callback: (payload: FileWritePreviewPayload) => void
+): Promise<UnlistenFn> {
+  return listen<FileWritePreviewPayload>('file-write-preview', (event) => callback(event.payload));
+}
+
+export function onFileWriteResolved(
+  callback: (payload: FileWriteResolvedPayload) => void
+): Promise<UnlistenFn> {
+  return listen<FileWriteResolvedPayload>('file-write-resolved', (event) => callback(event.payload));
+}
+
+export function onAgentPaused(
+  callback: (payload: AgentPausedPayload) => void
+): Promise<UnlistenFn> {
+  return listen<AgentPausedPayload>('agent-paused', (event) => callback(event.payload));
+}
+
+export function onHistoryChanged(
+  callback: (payload: HistoryChangedPayload) => void
+): Promise<UnlistenFn> {
+  return listen<HistoryChangedPayload>('history-changed', (event) => callback(event.payload));
+}
+
+export function onProfileSwitched(
+  callback: (payload: ProfileRegistry) => void
+): Promise<UnlistenFn> {
+  return listen<ProfileRegistry>('profile-switched', (event) => callback(event.payload));
+}
+
+export function onAuthRequired(
+  callback: (payload: AuthRequiredPayload) => void
+): Promise<UnlistenFn> {
+  return listen<AuthRequiredPayload>('auth-required', (event) => callback(event.payload));
+}
+
+export function onAuthProgress(
+  callback: (payload: AuthProgressPayload) => void
+): Promise<UnlistenFn> {
+  return listen<AuthProgressPayload>('auth-progress', (event) => callback(event.payload));
+}
+
+export function onMemoryPressure(
+  callback: (payload: MemoryPressurePayload) => void
+): Promise<UnlistenFn> {
+  return listen<MemoryPressurePayload>('memory-pressure', (event) => callback(event.payload));
+}
+
+export function onLanguageMismatch(
+  callback: (payload: LanguageMismatchPayload) => void
+): Promise<UnlistenFn> {
+  return listen<LanguageMismatchPayload>('language-mismatch', (event) => callback(event.payload));
+}
+
+export function onDiagramRendererMissing(
+  callback: (payload: DiagramRendererMissingPayload) => void
+): Promise<UnlistenFn> {
+  return listen<DiagramRendererMissingPayload>('diagram-renderer-missing', (event) =>
+    callback(event.payload)
+  );
+}
+
+export function onGitReviewRequested(
+  callback: (payload: GitReviewRequestedPayload) => void
+): Promise<UnlistenFn> {
+  return listen<GitReviewRequestedPayload>('git-review-requested', (event) => callback(event.payload));
+}
+
+export function onEditorRequest(
+  callback: (payload: EditorRequestPayload) => void
+): Promise<UnlistenFn> {
+  return listen<EditorRequestPayload>('editor-request', (event) => callback(event.payload));
+}
+
+export function onSessionTitleUpdated(
+  callback: (payload: SessionTitleUpdatedPayload) => void
+): Promise<UnlistenFn> {
+  return listen<SessionTitleUpdatedPayload>('session-title-updated', (event) => callback(event.payload));
+}
@@ -0,0 +1,52 @@
+// src/services/jobs.ts - Await background jobs reported via job-progress events
+import { onJobProgress, type JobProgressPayload } from './events';
+import type { JobHandle } from './tauri';
+
+/**
+ * Starts a backend job and resolves with its result once it completes.
+ * The listener is attached before starting so fast jobs cannot finish unseen.
+ */
+export async function runJob<T>(
+  start: () => Promise<JobHandle>,
+  onProgress?: (payload: JobProgressPayload) => void
+): Promise<T> {
+  const buffered: JobProgressPayload[] = [];
+  let jobId: string | null = null;
+  let settle: ((payload: JobProgressPayload) => void) | null = null;
+
+  const unlisten = await onJobProgress((payload) => {
+    if (jobId === null) {
+      buffered.push(payload);
+    } else if (payload.jobId === jobId) {
+      settle?.(payload);
+    }
+  });
+
+  try {
+    return await new Promise<T>((resolve, reject) => {
+      settle = (payload) => {
+        if (payload.status === 'running') {
+          onProgress?.(payload);
+        } else if (payload.status === 'completed') {
+          resolve(payload.result as T);
+        } else if (payload.status === 'cancelled') {
+          reject(new Error('任务已取消'));
+        } else {
+          reject(new Error(payload.error || '任务失败'));
+        }
+      };
+      start()
+        .then((handle) => {
+          jobId = handle.jobId;
+          for (const payload of buffered.splice(0)) {
+            if (payload.jobId === jobId) {
+              settle?.(payload);
+            }
+          }
+        })
+        .catch(reject);
+    });
+  } finally {
+    unlisten();
+  }
+}
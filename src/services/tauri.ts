@@ -2,11 +2,26 @@
 import { invoke, convertFileSrc } from '@tauri-apps/api/core';
 import { getVersion } from '@tauri-apps/api/app';
 import type {
+  AgentSessions,
+  AuditEntry,
+  DeletedMessages,
+  FrozenSessionRecord,
+  HistoryClearManifest,
+  HistorySearchHit,
+  ImportedSession,
+  JobRecord,
+  IflowHistoryFilter,
   IflowHistorySessionRecord,
+  IflowHistoryEntry,
   IflowHistoryMessageRecord,
   ModelOption,
+  PermissionMode,
   SkillRuntimeItem,
+  SnapshotVerification,
   StorageSnapshot,
+  StoredMessage,
+  StoredSession,
+  TelemetryPreview,
   GitFileChange,
 } from '../types';
 
@@ -18,16 +33,672 @@ export interface ConnectIflowResult {
   error?: string;
 }
 
-export function readHtmlArtifact(agentId: string, filePath: string): Promise<string> {
-  return invoke<string>('read_html_artifact', { agentId, filePath });
+/** 设置开启净化时 `sanitized` 统计被移除的脚本、内嵌框架与外部请求 */
+export interface HtmlSanitizeReport {
+  scripts: number;
+  eventHandlers: number;
+  embeddedFrames: number;
+  externalResources: number;
+  cssNetworkCalls: number;
+}
+
+/** 注入预览的 CSP；开启改写时 `blockedUrls` 列出被替换为占位地址的外部资源 */
+export interface ArtifactCspReport {
+  policy: string;
+  blockedUrls: string[];
+}
+
+export interface HtmlArtifact {
+  content: string;
+  sanitized: HtmlSanitizeReport | null;
+  csp: ArtifactCspReport | null;
+}
+
+export function readHtmlArtifact(agentId: string, filePath: string): Promise<HtmlArtifact> {
+  return invoke<HtmlArtifact>('read_html_artifact', { agentId, filePath });
 }
 
 export function resolveHtmlArtifactPath(agentId: string, filePath: string): Promise<string> {
   return invoke<string>('resolve_html_artifact_path', { agentId, filePath });
 }
 
-export function clearIflowHistorySessions(workspacePath: string): Promise<number> {
-  return invoke<number>('clear_iflow_history_sessions', { workspacePath });
+export interface JobHandle {
+  jobId: string;
+  kind: string;
+}
+
+export function clearIflowHistorySessions(workspacePath: string, dryRun = false): Promise<JobHandle> {
+  return invoke<JobHandle>('clear_iflow_history_sessions', { workspacePath, dryRun });
+}
+
+export function cancelJob(jobId: string): Promise<boolean> {
+  return invoke<boolean>('cancel_job', { jobId });
+}
+
+export function listJobs(kind?: string, limit?: number): Promise<JobRecord[]> {
+  return invoke<JobRecord[]>('list_jobs', { kind: kind ?? null, limit: limit ?? null });
+}
+
+export function getJob(jobId: string): Promise<JobRecord> {
+  return invoke<JobRecord>('get_job', { jobId });
+}
+
+export interface AppLockStatus {
+  enabled: boolean;
+  locked: boolean;
+  timeoutSecs: number | null;
+}
+
+export function getAppLockStatus(): Promise<AppLockStatus> {
+  return invoke<AppLockStatus>('get_app_lock_status');
+}
+
+export function setAppLockPassword(
+  currentPassword: string | null,
+  newPassword: string | null,
+): Promise<AppLockStatus> {
+  return invoke<AppLockStatus>('set_app_lock_password', { currentPassword, newPassword });
+}
+
+export function unlockApp(password: string): Promise<AppLockStatus> {
+  return invoke<AppLockStatus>('unlock_app', { password });
+}
+
+export function lockApp(): Promise<AppLockStatus> {
+  return invoke<AppLockStatus>('lock_app');
+}
+
+export interface Profile {
+  id: string;
+  name: string;
+  createdAt: string;
+}
+
+export interface ProfileRegistry {
+  active: string;
+  profiles: Profile[];
+}
+
+export function listProfiles(): Promise<ProfileRegistry> {
+  return invoke<ProfileRegistry>('list_profiles');
+}
+
+export function createProfile(name: string): Promise<Profile> {
+  return invoke<Profile>('create_profile', { name });
+}
+
+export function switchProfile(profileId: string): Promise<ProfileRegistry> {
+  return invoke<ProfileRegistry>('switch_profile', { profileId });
+}
+
+export function deleteProfile(profileId: string): Promise<boolean> {
+  return invoke<boolean>('delete_profile', { profileId });
+}
+
+export type ConflictStrategy = 'local' | 'remote';
+
+export interface SyncReport {
+  committedLocal: boolean;
+  mergedRemote: boolean;
+  pushed: boolean;
+  conflicts: string[];
+  exported: number;
+  imported: number;
+  head: string | null;
+}
+
+export function syncNow(conflictStrategy?: ConflictStrategy): Promise<SyncReport> {
+  return invoke<SyncReport>('sync_now', { conflictStrategy: conflictStrategy ?? null });
+}
+
+export interface SharedSessionLink {
+  url: string;
+  key: string;
+  link: string;
+}
+
+export interface SharedSessionView {
+  sharedAt: string;
+  session: StoredSession;
+  messages: StoredMessage[];
+  readOnly: boolean;
+}
+
+export function shareSessionEncrypted(sessionId: string): Promise<SharedSessionLink> {
+  return invoke<SharedSessionLink>('share_session_encrypted', { sessionId });
+}
+
+export function openSharedSession(link: string): Promise<SharedSessionView> {
+  return invoke<SharedSessionView>('open_shared_session', { link });
+}
+
+export interface PublishedArtifact {
+  path: string;
+  key: string;
+  url: string;
+}
+
+export function publishArtifacts(
+  agentId: string,
+  filePaths: string[],
+  expiresSecs?: number
+): Promise<PublishedArtifact[]> {
+  return invoke<PublishedArtifact[]>('publish_artifacts', {
+    agentId,
+    filePaths,
+    expiresSecs: expiresSecs ?? null,
+  });
+}
+
+export type ReportContent =
+  | { kind: 'sessionExport'; sessionId: string; fontPath?: string }
+  | { kind: 'dailyDigest' };
+
+export interface EmailReceipt {
+  recipients: number;
+  subject: string;
+  attachmentBytes: number;
+}
+
+export function sendReportEmail(
+  content: ReportContent,
+  recipients: string[],
+  subject?: string
+): Promise<EmailReceipt> {
+  return invoke<EmailReceipt>('send_report_email', {
+    content,
+    recipients,
+    subject: subject ?? null,
+  });
+}
+
+export type NotificationEvent = 'taskFinish' | 'budgetAlert';
+
+export interface NotificationChannel {
+  id: string;
+  name: string;
+  kind: 'slack' | 'discord';
+  webhookUrl: string;
+  enabled: boolean;
+  events: NotificationEvent[];
+  templates?: Partial<Record<NotificationEvent, string>>;
+  minIntervalSecs?: number | null;
+}
+
+export function listNotificationChannels(workspacePath: string): Promise<NotificationChannel[]> {
+  return invoke<NotificationChannel[]>('list_notification_channels', { workspacePath });
+}
+
+export function configureNotificationChannel(
+  workspacePath: string,
+  channel: NotificationChannel
+): Promise<NotificationChannel> {
+  return invoke<NotificationChannel>('configure_notification_channel', { workspacePath, channel });
+}
+
+export function removeNotificationChannel(workspacePath: string, channelId: string): Promise<boolean> {
+  return invoke<boolean>('remove_notification_channel', { workspacePath, channelId });
+}
+
+export function notifyBudgetAlert(workspacePath: string, message: string): Promise<void> {
+  return invoke<void>('notify_budget_alert', { workspacePath, message });
+}
+
+export interface QuietHoursStatus {
+  quiet: boolean;
+  until: string | null;
+}
+
+export function getQuietHoursStatus(): Promise<QuietHoursStatus> {
+  return invoke<QuietHoursStatus>('get_quiet_hours_status');
+}
+
+export interface ActivityCell {
+  prompts: number;
+  toolCalls: number;
+}
+
+export interface ActivityHeatmap {
+  from: string;
+  to: string;
+  /** cells[weekday][hour]，weekday 0 为周一 */
+  cells: ActivityCell[][];
+  totalPrompts: number;
+  totalToolCalls: number;
+  maxCell: number;
+}
+
+export function getActivityHeatmap(
+  workspacePath: string,
+  agentIds: string[],
+  rangeDays?: number
+): Promise<ActivityHeatmap> {
+  return invoke<ActivityHeatmap>('get_activity_heatmap', {
+    workspacePath,
+    agentIds,
+    rangeDays: rangeDays ?? null,
+  });
+}
+
+export function replayInterruptedMessages(): Promise<number> {
+  return invoke<number>('replay_interrupted_messages');
+}
+
+export type DiagnosticStatus = 'pass' | 'warn' | 'fail' | 'skipped';
+
+export interface DiagnosticCheck {
+  id: 'iflow' | 'acp' | 'history' | 'storage' | 'port' | 'clock';
+  status: DiagnosticStatus;
+  detail: string;
+  durationMs: number;
+}
+
+export interface DiagnosticsReport {
+  generatedAt: string;
+  ok: boolean;
+  checks: DiagnosticCheck[];
+}
+
+export function runDiagnostics(iflowPath?: string): Promise<DiagnosticsReport> {
+  return invoke<DiagnosticsReport>('run_diagnostics', { iflowPath: iflowPath ?? null });
+}
+
+export interface SupportBundle {
+  path: string;
+  sizeBytes: number;
+  entries: string[];
+}
+
+export function createSupportBundle(
+  includeAcpTrace: boolean,
+  iflowPath?: string
+): Promise<SupportBundle> {
+  return invoke<SupportBundle>('create_support_bundle', {
+    iflowPath: iflowPath ?? null,
+    includeAcpTrace,
+  });
+}
+
+export type UpdateChannel = 'stable' | 'beta';
+
+export interface ReleaseAsset {
+  name: string;
+  url: string;
+  size: number;
+}
+
+export interface ReleaseInfo {
+  version: string;
+  name: string;
+  notes: string;
+  prerelease: boolean;
+  publishedAt: string | null;
+  url: string;
+  asset: ReleaseAsset | null;
+}
+
+export interface UpdateCheck {
+  currentVersion: string;
+  channel: UpdateChannel;
+  updateAvailable: boolean;
+  latest: ReleaseInfo | null;
+  checkedAt: string;
+}
+
+export interface DownloadedUpdate {
+  version: string;
+  path: string;
+  sizeBytes: number;
+  /** 已与发布的 SHA256SUMS 核对一致 */
+  sha256: string;
+}
+
+export function checkForUpdates(channel?: UpdateChannel): Promise<UpdateCheck> {
+  return invoke<UpdateCheck>('check_for_updates', { channel: channel ?? null });
+}
+
+export function fetchReleaseNotes(channel?: UpdateChannel): Promise<ReleaseInfo[]> {
+  return invoke<ReleaseInfo[]>('fetch_release_notes', { channel: channel ?? null });
+}
+
+/** 下载并校验安装包，不会自动安装，需由用户打开返回的文件完成更新 */
+export function downloadUpdate(channel?: UpdateChannel): Promise<DownloadedUpdate> {
+  return invoke<DownloadedUpdate>('download_update', { channel: channel ?? null });
+}
+
+export interface IflowConfig {
+  path: string;
+  exists: boolean;
+  config: Record<string, unknown>;
+  secretsSet: string[];
+  backupPath: string | null;
+}
+
+export function readIflowConfig(): Promise<IflowConfig> {
+  return invoke<IflowConfig>('read_iflow_config');
+}
+
+export function updateIflowConfig(patch: Record<string, unknown>): Promise<IflowConfig> {
+  return invoke<IflowConfig>('update_iflow_config', { patch });
+}
+
+export function authenticateIflow(agentId: string, methodId: string): Promise<void> {
+  return invoke<void>('authenticate_iflow', { agentId, methodId });
+}
+
+export type PermissionDecision = 'allow' | 'deny';
+
+export interface PermissionRule {
+  id: string;
+  /** 工具种类、标题或工具名，`*` 表示任意工具 */
+  tool: string;
+  pathPrefix?: string | null;
+  /** 匹配命令、标题与路径的正则 */
+  pattern?: string | null;
+  decision: PermissionDecision;
+  createdAt: string;
+}
+
+export function listPermissionRules(workspacePath: string): Promise<PermissionRule[]> {
+  return invoke<PermissionRule[]>('list_permission_rules', { workspacePath });
+}
+
+export function addPermissionRule(
+  workspacePath: string,
+  tool: string,
+  decision: PermissionDecision,
+  pathPrefix: string | null = null,
+  pattern: string | null = null
+): Promise<PermissionRule> {
+  return invoke<PermissionRule>('add_permission_rule', {
+    workspacePath,
+    tool,
+    pathPrefix,
+    pattern,
+    decision,
+  });
+}
+
+export function revokePermissionRule(workspacePath: string, ruleId: string): Promise<boolean> {
+  return invoke<boolean>('revoke_permission_rule', { workspacePath, ruleId });
+}
+
+/** 应答 Agent 的权限请求；optionId 为空表示取消 */
+export function respondPermission(
+  agentId: string,
+  requestId: number,
+  optionId: string | null
+): Promise<void> {
+  return invoke<void>('respond_permission', { agentId, requestId, optionId });
+}
+
+/** 应答计划模式下等待确认的文件写入 */
+export function respondFileWrite(agentId: string, requestId: number, accept: boolean): Promise<void> {
+  return invoke<void>('respond_file_write', { agentId, requestId, accept });
+}
+
+export type CredentialStatus = 'valid' | 'invalid' | 'expired' | 'missing' | 'unknown';
+
+export interface ProviderCredentialCheck {
+  provider: string;
+  authType: string | null;
+  status: CredentialStatus;
+  detail: string;
+  missingModels: string[];
+}
+
+export function checkProviderCredentials(models?: string[]): Promise<ProviderCredentialCheck[]> {
+  return invoke<ProviderCredentialCheck[]>('check_provider_credentials', { models: models ?? null });
+}
+
+export interface ScoreEntry {
+  taskId: string;
+  comparisonId: string | null;
+  agentId: string;
+  model: string | null;
+  prompt: string;
+  rating: number;
+  note: string | null;
+  failed: boolean;
+  durationMs: number | null;
+  ratedAt: string;
+}
+
+export interface ModelScore {
+  model: string;
+  ratings: number;
+  averageRating: number;
+  comparisons: number;
+  wins: number;
+  failures: number;
+  averageDurationMs: number | null;
+}
+
+export function rateResult(
+  taskId: string,
+  rating: number,
+  comparisonId?: string,
+  note?: string
+): Promise<ScoreEntry> {
+  return invoke<ScoreEntry>('rate_result', {
+    taskId,
+    rating,
+    comparisonId: comparisonId ?? null,
+    note: note ?? null,
+  });
+}
+
+export function getModelScoreboard(): Promise<ModelScore[]> {
+  return invoke<ModelScore[]>('get_model_scoreboard');
+}
+
+export interface UnreadCounts {
+  bySession: Record<string, number>;
+  byAgent: Record<string, number>;
+  total: number;
+}
+
+export function markSessionRead(sessionId: string, sequence?: number): Promise<UnreadCounts> {
+  return invoke<UnreadCounts>('mark_session_read', { sessionId, sequence: sequence ?? null });
+}
+
+export function getUnreadCounts(): Promise<UnreadCounts> {
+  return invoke<UnreadCounts>('get_unread_counts');
+}
+
+export type RoutineStep =
+  | { type: 'sendPrompt'; content: string; timeoutSecs?: number | null }
+  | { type: 'runCommand'; command: string; args?: string[]; timeoutSecs?: number | null }
+  | { type: 'exportArtifact'; path: string; destination: string };
+
+export interface Routine {
+  id: string;
+  name: string;
+  steps: RoutineStep[];
+  createdAt: string;
+}
+
+export function listRoutines(workspacePath: string): Promise<Routine[]> {
+  return invoke<Routine[]>('list_routines', { workspacePath });
+}
+
+export function saveRoutine(workspacePath: string, routine: Routine): Promise<Routine> {
+  return invoke<Routine>('save_routine', { workspacePath, routine });
+}
+
+export function deleteRoutine(workspacePath: string, routineId: string): Promise<boolean> {
+  return invoke<boolean>('delete_routine', { workspacePath, routineId });
+}
+
+/** `outputPath` 为工作区内的 .jsonl / .csv，用于汇总各 prompt 步骤的回答 */
+export function runRoutine(agentId: string, routineId: string, outputPath?: string): Promise<JobHandle> {
+  return invoke<JobHandle>('run_routine', { agentId, routineId, outputPath: outputPath ?? null });
+}
+
+export interface BatchItemResult {
+  index: number;
+  agentId: string;
+  prompt: string;
+  taskId: string | null;
+  error: string | null;
+  durationMs: number;
+}
+
+/** prompt-batch 任务的结果 */
+export interface PromptBatchReport {
+  batchId: string;
+  source: string;
+  total: number;
+  succeeded: number;
+  failed: number;
+  startedAt: string;
+  finishedAt: string;
+  outputPath: string | null;
+  items: BatchItemResult[];
+}
+
+/** 从 Markdown（`---` 分隔）或 JSONL 文件批量提交 prompt；多个 Agent 时并行分发 */
+export function submitPromptBatch(
+  path: string,
+  agentIds: string[],
+  timeoutSecs?: number,
+  outputPath?: string
+): Promise<JobHandle> {
+  return invoke<JobHandle>('submit_prompt_batch', {
+    path,
+    agentIds,
+    timeoutSecs: timeoutSecs ?? null,
+    outputPath: outputPath ?? null,
+  });
+}
+
+export type GitHookKind = 'pre-commit' | 'pre-push';
+
+export interface GitHookInstallResult {
+  path: string;
+  backupPath: string | null;
+}
+
+export function installGitHook(
+  workspacePath: string,
+  hook: GitHookKind,
+  overwrite = false,
+): Promise<GitHookInstallResult> {
+  return invoke<GitHookInstallResult>('install_git_hook', { workspacePath, hook, overwrite });
+}
+
+export interface GitignoreUpdate {
+  path: string;
+  added: string[];
+  applied: boolean;
+}
+
+/** 把工作区内已存在的 FlowHub 目录追加到 .gitignore；dryRun 时只返回待追加的条目 */
+export function ensureGitignoreEntries(workspacePath: string, dryRun?: boolean): Promise<GitignoreUpdate> {
+  return invoke<GitignoreUpdate>('ensure_gitignore_entries', { workspacePath, dryRun: dryRun ?? null });
+}
+
+export function uninstallGitHook(workspacePath: string, hook: GitHookKind): Promise<boolean> {
+  return invoke<boolean>('uninstall_git_hook', { workspacePath, hook });
+}
+
+export function resolveGitReview(reviewId: string, approved: boolean): Promise<void> {
+  return invoke('resolve_git_review', { reviewId, approved });
+}
+
+export function pushEditorPatch(agentId: string, filePath: string, patch: string): Promise<number> {
+  return invoke<number>('push_editor_patch', { agentId, filePath, patch });
+}
+
+export type AnnotationSeverity = 'info' | 'warning' | 'error';
+
+export interface FileAnnotation {
+  id: string;
+  path: string;
+  line: number;
+  endLine: number | null;
+  comment: string;
+  severity: AnnotationSeverity;
+  agentId: string;
+  taskId: string;
+  createdAt: string;
+}
+
+export function getFileAnnotations(path: string, workspacePath?: string): Promise<FileAnnotation[]> {
+  return invoke<FileAnnotation[]>('get_file_annotations', { path, workspacePath: workspacePath ?? null });
+}
+
+export interface SessionGraphNode {
+  id: string;
+  agentId: string;
+  title: string;
+  createdAt: string;
+  messageCount: number;
+  root: boolean;
+}
+
+export interface SessionGraphEdge {
+  from: string;
+  to: string;
+  forkPoint: number | null;
+  createdAt: string | null;
+}
+
+export interface SessionGraph {
+  nodes: SessionGraphNode[];
+  edges: SessionGraphEdge[];
+}
+
+export function getSessionGraph(workspacePath: string): Promise<SessionGraph> {
+  return invoke<SessionGraph>('get_session_graph', { workspacePath });
+}
+
+export interface PromptEstimate {
+  promptTokens: number;
+  attachmentTokens: number;
+  totalTokens: number;
+  tokenizer: string;
+  exact: boolean;
+  estimatedCostUsd: number | null;
+  skippedAttachments: string[];
+}
+
+export function estimatePrompt(
+  text: string,
+  attachments: string[],
+  model: string
+): Promise<PromptEstimate> {
+  return invoke<PromptEstimate>('estimate_prompt', { text, attachments, model });
+}
+
+export interface AttachmentDescriptor {
+  id: string;
+  sessionId: string;
+  fileName: string;
+  path: string;
+  uri: string;
+  mimeType: string;
+  size: number;
+  width: number | null;
+  height: number | null;
+  deduplicated: boolean;
+}
+
+export interface AttachmentRetentionReport {
+  removed: string[];
+  freedBytes: number;
+}
+
+export function importAttachment(sessionId: string, path: string): Promise<AttachmentDescriptor> {
+  return invoke<AttachmentDescriptor>('import_attachment', { sessionId, path });
+}
+
+export function runAttachmentRetention(): Promise<AttachmentRetentionReport> {
+  return invoke<AttachmentRetentionReport>('run_attachment_retention');
+}
+
+export function undoLastClear(workspacePath: string): Promise<HistoryClearManifest> {
+  return invoke<HistoryClearManifest>('undo_last_clear', { workspacePath });
 }
 
 export function connectIflow(
@@ -35,12 +706,138 @@ export function connectIflow(
   iflowPath: string,
   workspacePath: string,
   model: string | null,
+  permissionMode: PermissionMode | null = null,
 ): Promise<ConnectIflowResult> {
-  return invoke<ConnectIflowResult>('connect_iflow', { agentId, iflowPath, workspacePath, model });
+  return invoke<ConnectIflowResult>('connect_iflow', {
+    agentId,
+    iflowPath,
+    workspacePath,
+    model,
+    permissionMode,
+  });
 }
 
-export function listIflowHistorySessions(workspacePath: string): Promise<IflowHistorySessionRecord[]> {
-  return invoke<IflowHistorySessionRecord[]>('list_iflow_history_sessions', { workspacePath });
+export function connectClaude(
+  agentId: string,
+  claudePath: string | null,
+  workspacePath: string,
+  model: string | null,
+  permissionMode: PermissionMode | null = null,
+): Promise<ConnectIflowResult> {
+  return invoke<ConnectIflowResult>('connect_claude', {
+    agentId,
+    claudePath,
+    workspacePath,
+    model,
+    permissionMode,
+  });
+}
+
+export function connectGemini(
+  agentId: string,
+  geminiPath: string | null,
+  workspacePath: string,
+  model: string | null,
+  permissionMode: PermissionMode | null = null,
+): Promise<ConnectIflowResult> {
+  return invoke<ConnectIflowResult>('connect_gemini', {
+    agentId,
+    geminiPath,
+    workspacePath,
+    model,
+    permissionMode,
+  });
+}
+
+export interface RemoteConnection {
+  /** `user@host` 或 ssh config 中的主机别名 */
+  host: string;
+  sshPort?: number | null;
+  identityFile?: string | null;
+  iflowPath?: string | null;
+  remotePort?: number | null;
+  /** 已转发到本机的端口，设置后不再建立隧道 */
+  forwardedPort?: number | null;
+  /** 完整的 ws:// 或 wss:// endpoint，优先于端口转发 */
+  endpoint?: string | null;
+  tls?: AcpTlsOptions | null;
+}
+
+export interface AcpTlsOptions {
+  caCertPath?: string | null;
+  clientCertPath?: string | null;
+  clientKeyPath?: string | null;
+  skipSystemRoots?: boolean;
+}
+
+export function connectRemoteAgent(
+  agentId: string,
+  workspacePath: string,
+  remote: RemoteConnection,
+  model: string | null,
+  permissionMode: PermissionMode | null = null,
+): Promise<ConnectIflowResult> {
+  return invoke<ConnectIflowResult>('connect_remote_agent', {
+    agentId,
+    workspacePath,
+    remote,
+    model,
+    permissionMode,
+  });
+}
+
+export interface RestoredAgent {
+  agentId: string;
+  agentType: string;
+  workspacePath: string;
+  model?: string | null;
+  restored: boolean;
+  error?: string | null;
+}
+
+export type SuggestedActionDescriptor =
+  | { type: 'prompt'; content: string }
+  | { type: 'invoke'; command: string; args: Record<string, unknown> }
+  | { type: 'shell'; command: string; cwd: string };
+
+export interface SuggestedAction {
+  id: string;
+  label: string;
+  action: SuggestedActionDescriptor;
+}
+
+/** 依据工作区状态（失败的测试、TODO/FIXME、未提交改动）给出 prompt 建议 */
+export function suggestPrompts(workspacePath: string): Promise<SuggestedAction[]> {
+  return invoke<SuggestedAction[]>('suggest_prompts', { workspacePath });
+}
+
+export type AutoStartPhase = 'pending' | 'starting' | 'started' | 'skipped' | 'failed';
+
+export interface AutoStartProgress {
+  agentId: string;
+  workspacePath: string;
+  phase: AutoStartPhase;
+  error?: string | null;
+}
+
+/** 启动时后端自动连接固定工作区 Agent 的进度 */
+export function getAutoStartStatus(): Promise<AutoStartProgress[]> {
+  return invoke<AutoStartProgress[]>('get_auto_start_status');
+}
+
+/** 接管应用重启前仍在运行的 Agent */
+export function restoreAgents(): Promise<RestoredAgent[]> {
+  return invoke<RestoredAgent[]>('restore_agents');
+}
+
+export function listIflowHistorySessions(
+  workspacePath: string,
+  filter?: IflowHistoryFilter
+): Promise<IflowHistorySessionRecord[]> {
+  return invoke<IflowHistorySessionRecord[]>('list_iflow_history_sessions', {
+    workspacePath,
+    filter: filter ?? null,
+  });
 }
 
 export function loadIflowHistoryMessages(
@@ -53,6 +850,31 @@ export function loadIflowHistoryMessages(
   });
 }
 
+/** 监听工作区的 iFlow 历史目录，会话文件变化时发出 `history-changed`；重复调用无副作用 */
+export function watchIflowHistory(workspacePath: string): Promise<void> {
+  return invoke<void>('watch_iflow_history', { workspacePath });
+}
+
+export function unwatchIflowHistory(workspacePath: string): Promise<void> {
+  return invoke<void>('unwatch_iflow_history', { workspacePath });
+}
+
+/** 读取会话的完整时间线，包含工具调用与执行计划 */
+export function loadIflowHistoryEntries(
+  workspacePath: string,
+  sessionId: string,
+): Promise<IflowHistoryEntry[]> {
+  return invoke<IflowHistoryEntry[]>('load_iflow_history_entries', {
+    workspacePath,
+    sessionId,
+  });
+}
+
+/** 在工作区的 iFlow 历史会话中全文搜索（空白分隔的关键词需全部命中，不区分大小写） */
+export function searchIflowHistory(workspacePath: string, query: string): Promise<HistorySearchHit[]> {
+  return invoke<HistorySearchHit[]>('search_iflow_history', { workspacePath, query });
+}
+
 export function disconnectAgent(agentId: string): Promise<void> {
   return invoke('disconnect_agent', { agentId });
 }
@@ -60,8 +882,9 @@ export function disconnectAgent(agentId: string): Promise<void> {
 export function deleteIflowHistorySession(
   workspacePath: string,
   sessionId: string,
+  dryRun = false,
 ): Promise<boolean> {
-  return invoke<boolean>('delete_iflow_history_session', { workspacePath, sessionId });
+  return invoke<boolean>('delete_iflow_history_session', { workspacePath, sessionId, dryRun });
 }
 
 export function listAvailableModels(iflowPath: string): Promise<ModelOption[]> {
@@ -82,6 +905,44 @@ export function switchAgentModel(
   });
 }
 
+/** 会话中途切换权限模式（session/set_mode） */
+export function setPermissionMode(agentId: string, mode: PermissionMode): Promise<void> {
+  return invoke<void>('set_permission_mode', { agentId, mode });
+}
+
+export function listAgentSessions(agentId: string): Promise<AgentSessions> {
+  return invoke<AgentSessions>('list_agent_sessions', { agentId });
+}
+
+export function switchAgentSession(agentId: string, sessionId: string): Promise<void> {
+  return invoke<void>('switch_agent_session', { agentId, sessionId });
+}
+
+/** 暂停 Agent：进程与会话保持运行，暂不发送新 prompt、不推送流式更新 */
+export function pauseAgent(agentId: string): Promise<void> {
+  return invoke<void>('pause_agent', { agentId });
+}
+
+/** 恢复 Agent：推送暂停期间缓存的更新并发送排队的 prompt */
+export function resumeAgent(agentId: string): Promise<void> {
+  return invoke<void>('resume_agent', { agentId });
+}
+
+/** 导入 iFlow / Claude Code 风格的 JSONL 会话记录，合并进会话存储 */
+export function importSessionJsonl(path: string, agentId: string): Promise<ImportedSession> {
+  return invoke<ImportedSession>('import_session_jsonl', { path, agentId });
+}
+
+/** Agent 会话的临时草稿目录（同时以 FLOWHUB_SCRATCH_DIR 注入 Agent 进程），断开连接时删除 */
+export function getScratchDir(agentId: string): Promise<string> {
+  return invoke<string>('get_scratch_dir', { agentId });
+}
+
+/** 从已有会话分叉出新会话并切换过去，返回新会话 ID */
+export function forkSession(agentId: string, sessionId: string): Promise<string> {
+  return invoke<string>('fork_session', { agentId, sessionId });
+}
+
 export function toggleAgentThink(
   agentId: string,
   enable: boolean,
@@ -90,12 +951,20 @@ export function toggleAgentThink(
   return invoke('toggle_agent_think', { agentId, enable, config });
 }
 
+export interface GenerationOptions {
+  temperature?: number;
+  topP?: number;
+  maxOutputTokens?: number;
+  stopSequences?: string[];
+}
+
 export function sendMessage(
   agentId: string,
   content: string,
   sessionId: string | null,
+  generation?: GenerationOptions,
 ): Promise<void> {
-  return invoke('send_message', { agentId, content, sessionId });
+  return invoke('send_message', { agentId, content, sessionId, generation: generation ?? null });
 }
 
 export function stopMessage(agentId: string): Promise<void> {
@@ -110,6 +979,30 @@ export function saveStorageSnapshot(snapshot: StorageSnapshot): Promise<void> {
   return invoke('save_storage_snapshot', { snapshot });
 }
 
+export function deleteMessage(sessionId: string, messageId: string): Promise<DeletedMessages> {
+  return invoke<DeletedMessages>('delete_message', { sessionId, messageId });
+}
+
+export function getTelemetryPreview(): Promise<TelemetryPreview> {
+  return invoke<TelemetryPreview>('get_telemetry_preview');
+}
+
+export function uploadTelemetry(): Promise<void> {
+  return invoke<void>('upload_telemetry');
+}
+
+export function listAuditEntries(limit?: number): Promise<AuditEntry[]> {
+  return invoke<AuditEntry[]>('list_audit_entries', { limit: limit ?? null });
+}
+
+export function freezeSession(sessionId: string): Promise<FrozenSessionRecord> {
+  return invoke<FrozenSessionRecord>('freeze_session', { sessionId });
+}
+
+export function verifySessionSnapshot(snapshotId: string): Promise<SnapshotVerification> {
+  return invoke<SnapshotVerification>('verify_session_snapshot', { snapshotId });
+}
+
 export function listGitChanges(workspacePath: string): Promise<GitFileChange[]> {
   return invoke<GitFileChange[]>('list_git_changes', { workspacePath });
 }
@@ -21,6 +21,7 @@ import {
   isSkillSuggestionEnabled as isSkillSuggestionEnabledInMap,
   setSkillSuggestionEnabled as setSkillSuggestionEnabledInMap,
 } from './features/capabilities/enables';
+import { isAgentOnline } from './lib/utils';
 
 const NOTIFICATION_DELAY_STORAGE_KEY = 'iflow-notification-delay-ms';
 const NOTIFICATION_DEFAULT_DELAY_MS = 5000;
@@ -65,6 +66,8 @@ export const state = {
   draftsBySession: {} as Record<string, string>,
   scrollPositionsBySession: {} as Record<string, number>,
   inflightSessionByAgent: {} as Record<string, string>,
+  /** 已结束任务的最终回复位置，供后处理钩子结果回填 */
+  finishedReplyByTask: {} as Record<string, { sessionId: string; messageId: string }>,
   
   // ── 输入历史 ──────────────────────────────────────────────────────────────
   inputHistory: [] as string[],
@@ -137,7 +140,7 @@ export function canUseConversationQuickAction(): boolean {
     return false;
   }
   const agent = state.agents.find((item) => item.id === state.currentAgentId);
-  return Boolean(agent && agent.status === 'connected' && !state.inflightSessionByAgent[agent.id]);
+  return Boolean(agent && isAgentOnline(agent.status) && !state.inflightSessionByAgent[agent.id]);
 }
 
 function saveCapabilityEnableSettings(): void {
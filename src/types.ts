@@ -1,17 +1,35 @@
 // src/types.ts - Shared type definitions
 
+export type AgentStatus =
+  | 'connected'
+  | 'busy'
+  | 'reconnecting'
+  | 'stopping'
+  | 'disconnected'
+  | 'connecting'
+  | 'error';
+
+export type PermissionMode = 'plan' | 'acceptEdits' | 'yolo';
+
 export interface Agent {
   id: string;
   name: string;
   type: string;
-  status: 'connected' | 'disconnected' | 'connecting' | 'error';
+  status: AgentStatus;
   workspacePath: string;
   iflowPath?: string;
   selectedModel?: string;
   thinkEnabled?: boolean;
+  permissionMode?: PermissionMode;
   port?: number;
 }
 
+/** Agent 进程内创建或载入过的 ACP 会话，最近使用的在最后 */
+export interface AgentSessions {
+  current: string | null;
+  sessions: string[];
+}
+
 export interface Session {
   id: string;
   agentId: string;
@@ -19,8 +37,9 @@ export interface Session {
   createdAt: Date;
   updatedAt: Date;
   acpSessionId?: string;
-  source?: 'local' | 'iflow-log';
+  source?: 'local' | 'iflow-log' | 'import';
   messageCountHint?: number;
+  titleGenerated?: boolean;
 }
 
 export interface Message {
@@ -31,6 +50,18 @@ export interface Message {
   agentId?: string;
   toolCalls?: ToolCall[];
   estimatedTokens?: number;
+  /** 崩溃前未完成、由预写日志恢复的回复 */
+  interrupted?: boolean;
+  /** 引用的附件内容哈希 */
+  attachments?: string[];
+  /** 模型切换标记 */
+  modelSwitch?: ModelSwitch;
+}
+
+export interface ModelSwitch {
+  from?: string | null;
+  to: string;
+  restarted?: boolean;
 }
 
 export interface ToolCall {
@@ -39,6 +70,8 @@ export interface ToolCall {
   status: 'pending' | 'running' | 'completed' | 'error';
   arguments?: Record<string, unknown>;
   output?: string;
+  /** 输出过大时完整内容所在的临时文件 */
+  spilledPath?: string;
 }
 
 export type GitStatus =
@@ -109,8 +142,16 @@ export interface StoredSession {
   createdAt: string;
   updatedAt: string;
   acpSessionId?: string;
-  source?: 'local' | 'iflow-log';
+  source?: 'local' | 'iflow-log' | 'import';
   messageCountHint?: number;
+  titleGenerated?: boolean;
+}
+
+/** 外部 JSONL 会话导入结果 */
+export interface ImportedSession {
+  session: StoredSession;
+  /** 本次新增的消息数；重复导入时为 0 */
+  importedMessages: number;
 }
 
 export interface StoredMessage {
@@ -119,6 +160,9 @@ export interface StoredMessage {
   content: string;
   timestamp: string;
   agentId?: string;
+  interrupted?: boolean;
+  attachments?: string[];
+  modelSwitch?: ModelSwitch;
 }
 
 export type StoredSessionMap = Record<string, StoredSession[]>;
@@ -132,6 +176,56 @@ export interface StorageSnapshot {
   draftsBySession?: StoredDraftMap;
 }
 
+/** 匿名使用统计预览；payload 即上传时发送的完整内容 */
+export interface TelemetryPreview {
+  enabled: boolean;
+  uploadConfigured: boolean;
+  payload: {
+    appVersion: string;
+    os: string;
+    arch: string;
+    since: string | null;
+    until: string;
+    counters: Record<string, number>;
+  };
+}
+
+/** 合规保全审计日志中的一条记录 */
+export interface AuditEntry {
+  timestamp: string;
+  action: string;
+  blocked: boolean;
+  detail?: string;
+  /** Agent 文件读写所属的 ACP 会话 */
+  sessionId?: string;
+}
+
+/** 会话只读快照的登记记录 */
+export interface FrozenSessionRecord {
+  snapshotId: string;
+  sessionId: string;
+  frozenAt: string;
+  sha256: string;
+  /** 快照内容的 HMAC-SHA256，密钥保存在系统钥匙串 */
+  signature: string;
+  path: string;
+}
+
+export interface SnapshotVerification {
+  snapshotId: string;
+  valid: boolean;
+  expectedSha256: string;
+  actualSha256: string | null;
+  reason: string | null;
+}
+
+/** 按问答对删除的消息；附件为被删除消息引用的哈希 */
+export interface DeletedMessages {
+  sessionId: string;
+  messageIds: string[];
+  attachments: string[];
+}
+
 export interface IflowHistorySessionRecord {
   sessionId: string;
   title: string;
@@ -140,6 +234,59 @@ export interface IflowHistorySessionRecord {
   messageCount: number;
 }
 
+/** listIflowHistorySessions 的可选过滤条件；时间为 ISO 8601（RFC 3339） */
+export interface IflowHistoryFilter {
+  updatedAfter?: string;
+  updatedBefore?: string;
+  hasAssistantMessages?: boolean;
+  minMessageCount?: number;
+}
+
+export interface ClearedHistoryFile {
+  sessionId: string;
+  originalPath: string;
+  trashPath: string;
+}
+
+export interface HistoryClearManifest {
+  clearId: string;
+  workspacePath: string;
+  clearedAt: string;
+  undoExpiresAt: string;
+  files: ClearedHistoryFile[];
+  skipped: string[];
+}
+
+export interface PendingHistoryFile {
+  sessionId: string;
+  path: string;
+  bytes: number;
+}
+
+export interface HistoryClearPreview {
+  dryRun: true;
+  workspacePath: string;
+  files: PendingHistoryFile[];
+  totalBytes: number;
+  skipped: string[];
+}
+
+export type JobStatus = 'running' | 'completed' | 'failed' | 'cancelled';
+
+export interface JobRecord {
+  jobId: string;
+  kind: string;
+  status: JobStatus;
+  done: number;
+  total: number;
+  message: string;
+  result?: unknown;
+  error?: string | null;
+  createdAt: string;
+  updatedAt: string;
+  finishedAt?: string | null;
+}
+
 export interface IflowHistoryMessageRecord {
   id: string;
   role: 'user' | 'assistant';
@@ -147,6 +294,42 @@ export interface IflowHistoryMessageRecord {
   timestamp: string;
 }
 
+export interface IflowHistoryPlanItem {
+  content: string;
+  status: string;
+}
+
+/** 历史会话时间线中的一项：文本、工具调用（附带结果）或执行计划 */
+export type IflowHistoryEntry =
+  | { kind: 'text'; id: string; role: 'user' | 'assistant'; content: string; timestamp: string }
+  | {
+      kind: 'toolCall';
+      id: string;
+      name: string;
+      input: unknown;
+      output: string | null;
+      isError: boolean;
+      timestamp: string;
+    }
+  | { kind: 'plan'; id: string; items: IflowHistoryPlanItem[]; timestamp: string };
+
+export interface HistorySearchSnippet {
+  messageId: string;
+  role: 'user' | 'assistant';
+  timestamp: string;
+  snippet: string;
+  /** 命中位置在消息内容中的字符偏移 */
+  offset: number;
+}
+
+export interface HistorySearchHit {
+  sessionId: string;
+  title: string;
+  updatedAt: string;
+  matchCount: number;
+  snippets: HistorySearchSnippet[];
+}
+
 export type ComposerState = 'ready' | 'busy' | 'disabled';
 export type StreamMessageType = 'content' | 'thought' | 'system' | 'plan';
 export type ThemeMode = 'system' | 'light' | 'dark';